@@ -0,0 +1,115 @@
+//! Charms SDK Compatibility Shim
+//!
+//! Every Charms type and macro the zkUSD contracts use goes through this
+//! crate, so a change of SDK version is a change here and nowhere else.
+//!
+//! ## Expectations
+//!
+//! - **SDK**: `charms-sdk`/`charms-data` as pinned in the workspace
+//!   (v0.11.1), or v0.12+ with the `v0_12` feature.
+//! - **Data**: app state, witnesses and public inputs are CBOR-encoded
+//!   `Data`; token amounts are a bare CBOR `u64`.
+//! - **Guest**: apps build for `wasm32-wasip1`, which has `std`.
+//!
+//! ## Coin Accounting
+//!
+//! Charms v0.11.1 does not populate `coin_ins`/`coin_outs`, so a
+//! transaction's native BTC flows read as zero; v0.12+ populates them (PR
+//! #151). Contracts read the flows with `extract_coin_flows` and ask
+//! `supports_coin_accounting` whether a zero can be trusted.
+//! The Vault Manager's `strict_conservation` feature turns on `v0_12`.
+
+pub use charms_data::{util, App, Charms, Data, NativeOutput, Transaction, TxId, UtxoId, B32};
+pub use charms_sdk::main;
+
+/// Native BTC flowing through a transaction (satoshis)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoinFlows {
+    /// BTC spent by the transaction's inputs
+    pub btc_in: u64,
+    /// BTC created by the transaction's outputs
+    pub btc_out: u64,
+}
+
+/// Returns true if the runtime populates `coin_ins`/`coin_outs`
+pub const fn supports_coin_accounting() -> bool {
+    cfg!(feature = "v0_12")
+}
+
+/// Total BTC in and out of `tx`, saturating; zero for flows the runtime
+/// does not populate
+pub fn extract_coin_flows(tx: &Transaction) -> CoinFlows {
+    let btc_in = tx.coin_ins.iter().flatten().fold(0u64, |total, coin| total.saturating_add(coin.amount));
+    let btc_out = tx.coin_outs.iter().flatten().fold(0u64, |total, coin| total.saturating_add(coin.amount));
+    CoinFlows { btc_in, btc_out }
+}
+
+/// Native BTC `tx` pays to the scriptPubKey `dest` (satoshis), saturating;
+/// zero if the runtime does not populate coin outputs
+pub fn coin_outputs_to(tx: &Transaction, dest: &[u8]) -> u64 {
+    tx.coin_outs
+        .iter()
+        .flatten()
+        .filter(|coin| coin.dest == dest)
+        .fold(0u64, |total, coin| total.saturating_add(coin.amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[cfg(feature = "v0_12")]
+    fn coins(amounts: &[u64]) -> Vec<NativeOutput> {
+        amounts.iter().map(|&amount| NativeOutput { amount, dest: Vec::new() }).collect()
+    }
+
+    /// Transaction as the runtime hands it to an app, with the given coin
+    /// flows
+    fn tx(coin_ins: Option<Vec<NativeOutput>>, coin_outs: Option<Vec<NativeOutput>>) -> Transaction {
+        Transaction {
+            ins: Vec::new(),
+            refs: Vec::new(),
+            outs: Vec::new(),
+            coin_ins,
+            coin_outs,
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(not(feature = "v0_12"))]
+    #[test]
+    fn test_v0_11_coin_flows_unpopulated() {
+        assert!(!supports_coin_accounting());
+        assert_eq!(extract_coin_flows(&tx(None, None)), CoinFlows::default());
+    }
+
+    #[cfg(feature = "v0_12")]
+    #[test]
+    fn test_v0_12_coin_flows_summed() {
+        assert!(supports_coin_accounting());
+        let flows = extract_coin_flows(&tx(Some(coins(&[60_000, 40_000])), Some(coins(&[99_000]))));
+        assert_eq!(flows, CoinFlows { btc_in: 100_000, btc_out: 99_000 });
+
+        // No outputs, and totals saturate rather than wrap
+        let flows = extract_coin_flows(&tx(Some(coins(&[u64::MAX, 1])), Some(Vec::new())));
+        assert_eq!(flows, CoinFlows { btc_in: u64::MAX, btc_out: 0 });
+    }
+
+    #[cfg(feature = "v0_12")]
+    #[test]
+    fn test_v0_12_coin_outputs_to_dest() {
+        // P2TR outputs: OP_1 OP_PUSHBYTES_32 <key>
+        let p2tr = |key: u8| [&[0x51, 0x20][..], &[key; 32]].concat();
+        let mut outs = coins(&[70_000, 20_000, 10_000]);
+        outs[1].dest = p2tr(0xF1);
+        outs[2].dest = p2tr(0xF1);
+        let tx = tx(None, Some(outs));
+        assert_eq!(coin_outputs_to(&tx, &p2tr(0xF1)), 30_000);
+        assert_eq!(coin_outputs_to(&tx, &p2tr(0xF2)), 0);
+
+        // Only the whole script matches, not the key it commits to
+        assert_eq!(coin_outputs_to(&tx, &[0xF1; 32]), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Differential Testing
+//!
+//! Every contract is validated twice over: by its library `validate`, which
+//! the builders and most tests drive with a ready-made context, and by its
+//! Charms entry point, which first has to recover that context from a
+//! transaction. A bug in the recovery (a charm looked up under the wrong
+//! key, a field never read) passes every library test.
+//!
+//! `ChainSpell` encodes a built spell as the transaction and witness its
+//! contract's entry point receives, and `compare` runs both validators over
+//! the same scenarios and reports every scenario whose verdicts differ.
+//!
+//! ## Exceptions
+//!
+//! Some divergences are legitimate: the entry point derives the signer from
+//! the spent charms rather than a signature, does not extract every context
+//! field yet, or checks what only a transaction has (app identities,
+//! duplicate companions). Each is listed as an `Exception` naming the
+//! scenario and the reason, so no divergence is silent. An exception whose
+//! scenario no longer diverges, or no longer exists, is reported as stale.
+
+use std::collections::BTreeMap;
+
+use zkusd_charms_compat::{App, Charms, Data, NativeOutput, Transaction, TxId, UtxoId, B32};
+use zkusd_common::errors::ZkUsdResult;
+
+use crate::{verify_locally, Built, SpellContext};
+
+/// Transaction and witness handed to a contract's Charms entry point
+#[derive(Debug, Clone)]
+pub struct EncodedSpell {
+    /// App whose entry point validates the spell
+    pub app: App,
+    /// Transaction carrying the spell
+    pub tx: Transaction,
+    /// Public inputs
+    pub x: Data,
+    /// Witness
+    pub w: Data,
+    /// Block the spell executes at
+    pub block_height: u64,
+}
+
+impl EncodedSpell {
+    /// Empty transaction validated by `app` with witness `w`
+    pub fn new(app: App, w: Data, block_height: u64) -> Self {
+        let tx = Transaction {
+            ins: Vec::new(),
+            refs: Vec::new(),
+            outs: Vec::new(),
+            coin_ins: None,
+            coin_outs: None,
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        };
+        Self { app, tx, x: Data::empty(), w, block_height }
+    }
+
+    /// Spend a UTXO carrying `charms`
+    pub fn spend(mut self, charms: Charms) -> Self {
+        let utxo = self.next_utxo();
+        self.tx.ins.push((utxo, charms));
+        self
+    }
+
+    /// Reference a UTXO carrying `charms` without spending it
+    pub fn reference(mut self, charms: Charms) -> Self {
+        let utxo = self.next_utxo();
+        self.tx.refs.push((utxo, charms));
+        self
+    }
+
+    /// Create an output carrying `charms`
+    pub fn create(mut self, charms: Charms) -> Self {
+        self.tx.outs.push(charms);
+        self
+    }
+
+    /// Run `app` in the same spell, as a caller the contract may resolve
+    pub fn with_app(mut self, app: App) -> Self {
+        self.tx.app_public_inputs.insert(app, Data::empty());
+        self
+    }
+
+    /// Native BTC spent and created by the transaction (satoshis), as a
+    /// runtime with coin accounting populates it
+    pub fn with_coins(mut self, btc_in: u64, btc_out: u64) -> Self {
+        let coins = |amount| Some(Vec::from([NativeOutput { amount, dest: Vec::new() }]));
+        self.tx.coin_ins = coins(btc_in);
+        self.tx.coin_outs = coins(btc_out);
+        self
+    }
+
+    /// Also pay `amount` of native BTC to the scriptPubKey `dest` (satoshis)
+    pub fn paying(mut self, dest: &[u8], amount: u64) -> Self {
+        self.tx.coin_outs.get_or_insert_with(Vec::new).push(NativeOutput { amount, dest: dest.to_vec() });
+        self
+    }
+
+    /// Distinct id for the next spent or referenced UTXO
+    fn next_utxo(&self) -> UtxoId {
+        let index = self.tx.ins.len() + self.tx.refs.len();
+        UtxoId(TxId([0xD1; 32]), u32::try_from(index).expect("few UTXOs per spell"))
+    }
+}
+
+/// App with `tag` and `identity`, and a verification key derived from the
+/// identity so distinct apps never share one
+pub fn app(tag: char, identity: [u8; 32]) -> App {
+    App { tag, identity: B32(identity), vk: B32(identity.map(|byte| !byte)) }
+}
+
+/// Charms of a UTXO carrying only `data` under `app`
+pub fn charm(app: &App, data: Data) -> Charms {
+    BTreeMap::from([(app.clone(), data)])
+}
+
+/// A contract's validation context that can also be validated on chain
+pub trait ChainSpell: SpellContext {
+    /// Transaction and witness carrying the built spell
+    fn encode(built: &Built<Self>) -> EncodedSpell;
+
+    /// Run the contract's Charms entry point
+    fn validate_encoded(spell: &EncodedSpell) -> bool;
+}
+
+/// Scenario whose verdicts are known to differ, and why
+#[derive(Debug, Clone, Copy)]
+pub struct Exception {
+    /// Name of the scenario
+    pub scenario: &'static str,
+    /// Why the entry point legitimately disagrees with the library
+    pub reason: &'static str,
+}
+
+/// Disagreement between the two validators, or with the exception list
+#[derive(Debug)]
+pub enum Divergence {
+    /// The verdicts differ and no exception covers the scenario
+    Unexpected {
+        scenario: String,
+        library: ZkUsdResult<()>,
+        entry_point: bool,
+    },
+    /// An exception covers a scenario whose verdicts agree, or that does
+    /// not exist
+    StaleException { scenario: &'static str },
+}
+
+/// Change to a built spell
+pub type Mutation<C> = fn(&mut Built<C>);
+
+/// Name every built spell, then add a copy per mutation of the spell it
+/// applies to, named `"<spell> (mutation <index>)"`
+pub fn scenarios<C>(
+    built: Vec<(&'static str, Built<C>)>,
+    mutations: &[(&'static str, Mutation<C>)],
+) -> Vec<(String, Built<C>)>
+where
+    C: SpellContext,
+    C::Action: Clone,
+{
+    let mutated: Vec<(String, Built<C>)> = mutations
+        .iter()
+        .enumerate()
+        .map(|(index, (name, mutate))| {
+            let (_, original) = built.iter().find(|(n, _)| n == name).expect("mutation of a built spell");
+            let mut spell = original.clone();
+            mutate(&mut spell);
+            (format!("{} (mutation {})", name, index), spell)
+        })
+        .collect();
+
+    built.into_iter().map(|(name, spell)| (name.to_string(), spell)).chain(mutated).collect()
+}
+
+/// Validate every scenario with both the library and the entry point,
+/// returning each divergence not covered by `exceptions` and each stale
+/// exception
+pub fn compare<C: ChainSpell>(scenarios: &[(String, Built<C>)], exceptions: &[Exception]) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    let excepted = |scenario: &str| exceptions.iter().find(|exception| exception.scenario == scenario);
+
+    for (scenario, built) in scenarios {
+        let library = verify_locally(built);
+        let entry_point = C::validate_encoded(&C::encode(built));
+        match (library.is_ok() == entry_point, excepted(scenario)) {
+            (false, None) => divergences.push(Divergence::Unexpected { scenario: scenario.clone(), library, entry_point }),
+            (true, Some(exception)) => divergences.push(Divergence::StaleException { scenario: exception.scenario }),
+            _ => {}
+        }
+    }
+    for exception in exceptions {
+        if !scenarios.iter().any(|(scenario, _)| scenario == exception.scenario) {
+            divergences.push(Divergence::StaleException { scenario: exception.scenario });
+        }
+    }
+    divergences
+}
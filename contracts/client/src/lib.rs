@@ -0,0 +1,155 @@
+//! zkUSD Client
+//!
+//! Typed builders for every contract action, producing the action together
+//! with the full validation context the contract expects: inputs, flows,
+//! signer and the expected output states.
+//!
+//! This crate is std-only and never compiled to WASM; it is meant for
+//! wallets, bots and indexers assembling spells.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let built = VaultOpsBuilder::open_vault(&state, owner, Sats(collateral), ZkUsd(debt))
+//!     .at_price(btc_price)
+//!     .at_block(height)
+//!     .build()?;
+//! verify_locally(&built)?;
+//! let (action, new_vault, new_state) = built.into_parts();
+//! ```
+//!
+//! ## Expected States
+//!
+//! Builders derive every field the validators check with the same pure
+//! functions the validators use (`zkusd_common::math`, interest accrual,
+//! the registry and deposit helpers). Fields a validator does not check are
+//! carried over unchanged from the input state.
+//!
+//! ## Stress Scenarios
+//!
+//! `scenario` chains the builders over a price path, running every block's
+//! actions through the validators and checking protocol invariants.
+//!
+//! ## Differential Testing
+//!
+//! With the `charms` feature, `differential` also runs built spells through
+//! the contracts' Charms entry points and reports where they disagree with
+//! the library validators.
+
+#[cfg(feature = "charms")]
+pub mod differential;
+pub mod oracle;
+pub mod scenario;
+pub mod stability_pool;
+pub mod token;
+pub mod vault;
+
+pub use oracle::OracleOpsBuilder;
+pub use scenario::StressScenario;
+pub use stability_pool::StabilityPoolOpsBuilder;
+pub use token::TokenOpsBuilder;
+pub use vault::VaultOpsBuilder;
+
+use zkusd_common::check;
+use zkusd_common::errors::{ZkUsdError, ZkUsdResult};
+use zkusd_common::types::{OracleAction, StabilityPoolAction, TokenAction, VaultAction};
+use zkusd_common::validation::require_owner;
+use zkusd_price_oracle::OracleContext;
+use zkusd_stability_pool::{get_pending_btc, StabilityPoolContext};
+use zkusd_token::TokenContext;
+use zkusd_vault_manager::VaultContext;
+
+/// Validation context of one contract, paired with its action type
+pub trait SpellContext: Clone {
+    /// Action validated against this context
+    type Action;
+
+    /// Run the contract's validator
+    fn validate(&mut self, action: &Self::Action) -> ZkUsdResult<()>;
+}
+
+impl SpellContext for VaultContext {
+    type Action = VaultAction;
+
+    fn validate(&mut self, action: &VaultAction) -> ZkUsdResult<()> {
+        zkusd_vault_manager::validate(self, action)
+    }
+}
+
+impl SpellContext for TokenContext {
+    type Action = TokenAction;
+
+    fn validate(&mut self, action: &TokenAction) -> ZkUsdResult<()> {
+        zkusd_token::validate(self, action)
+    }
+}
+
+impl SpellContext for StabilityPoolContext {
+    type Action = StabilityPoolAction;
+
+    fn validate(&mut self, action: &StabilityPoolAction) -> ZkUsdResult<()> {
+        zkusd_stability_pool::validate(self, action)
+    }
+}
+
+impl SpellContext for OracleContext {
+    type Action = OracleAction;
+
+    fn validate(&mut self, action: &OracleAction) -> ZkUsdResult<()> {
+        zkusd_price_oracle::validate(self, action)
+    }
+}
+
+/// An action with the context it must validate in
+#[derive(Clone)]
+pub struct Built<C: SpellContext> {
+    /// Action to put in the spell
+    pub action: C::Action,
+    /// Inputs, flows and expected outputs of the spell
+    pub context: C,
+}
+
+/// Run the real validator against built artifacts
+///
+/// Validates a copy, so the built context keeps an empty event log.
+pub fn verify_locally<C: SpellContext>(built: &Built<C>) -> ZkUsdResult<()> {
+    let mut context = built.context.clone();
+    context.validate(&built.action)
+}
+
+/// Run both legs of a BTC gain claimed into a vault: the Stability Pool's
+/// `ClaimBtcToVault` and the Vault Manager's `AddCollateral` of one spell
+///
+/// Each leg runs through its own validator. Across the legs, the signer
+/// must own both the deposit and the vault (an operator may not act for the
+/// vault owner here), both legs must target the same vault, and the
+/// collateral added must be exactly the depositor's share of the BTC gain
+/// the pool releases.
+pub fn validate_claim_and_collateralize(
+    claim: &Built<StabilityPoolContext>,
+    add: &Built<VaultContext>,
+) -> ZkUsdResult<()> {
+    let StabilityPoolAction::ClaimBtcToVault { vault_id } = claim.action else {
+        return Err(ZkUsdError::InvalidInput { param: "claim", reason: "not a claim into a vault" });
+    };
+    let VaultAction::AddCollateral { vault_id: target, amount } = add.action else {
+        return Err(ZkUsdError::InvalidInput { param: "add", reason: "not an add of collateral" });
+    };
+    check!(
+        vault_id == target,
+        ZkUsdError::InvalidInput { param: "vault_id", reason: "claim and collateral target different vaults" }
+    );
+
+    let pool = &claim.context;
+    let deposit = pool.deposit.as_ref().ok_or(ZkUsdError::DepositNotFound { user: pool.signer })?;
+    let vault = add.context.vault.as_ref().ok_or(ZkUsdError::VaultNotFound { vault_id })?;
+    require_owner(deposit.owner, pool.signer)?;
+    require_owner(vault.owner, add.context.signer)?;
+    require_owner(deposit.owner, add.context.signer)?;
+
+    let gain = deposit.split_btc_gain(get_pending_btc(deposit, &pool.state)?)?.depositor;
+    check!(gain == amount.0, ZkUsdError::ConservationViolated { inputs: gain, outputs: amount.0 });
+
+    verify_locally(claim)?;
+    verify_locally(add)
+}
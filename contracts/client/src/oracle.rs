@@ -0,0 +1,434 @@
+//! Price Oracle Builders
+//!
+//! `OracleOpsBuilder` derives the expected oracle state of each action,
+//! including the recent-deviation window a price update appends to.
+//! Aggregate updates publish the median of the given attestations, which
+//! the feeds must already have signed. A secondary feed's update changes
+//! only that feed's price; an update of the BTC/USD feed is a price update.
+
+use zkusd_common::{
+    constants::oracle::BTC_USD_FEED,
+    errors::{ZkUsdError, ZkUsdResult},
+    events::EventLog,
+    types::{Address, Feed, FeedId, OracleAction, PriceAttestation, PriceBounds, PriceData, PriceSource},
+};
+use zkusd_price_oracle::{calculate_price_deviation, median_attested_price, OracleContext, OracleState};
+
+use crate::Built;
+#[cfg(feature = "charms")]
+use crate::differential::{app, charm, ChainSpell, EncodedSpell};
+#[cfg(feature = "charms")]
+use zkusd_charms_compat::Data;
+#[cfg(feature = "charms")]
+use zkusd_price_oracle::charms::{validate_oracle_operation_at, OracleWitness};
+
+/// App id the oracle's spells are encoded under
+#[cfg(feature = "charms")]
+const ORACLE_APP_ID: [u8; 32] = [0xA3; 32];
+
+/// Builder for a Price Oracle spell
+#[derive(Debug, Clone)]
+pub struct OracleOpsBuilder {
+    state: OracleState,
+    action: OracleAction,
+    signer: Address,
+    block_height: u64,
+}
+
+impl OracleOpsBuilder {
+    fn new(state: &OracleState, signer: Address, action: OracleAction) -> Self {
+        Self { state: state.clone(), action, signer, block_height: state.price.timestamp_block }
+    }
+
+    /// Create the oracle (there is no input state; `at_block` sets the
+    /// initial price's block)
+    pub fn initialize(admin: Address, operator: Address, initial_price: u64) -> Self {
+        let state = OracleState::new(admin, operator, initial_price, 0);
+        Self::new(&state, admin, OracleAction::Initialize { admin, operator, initial_price })
+    }
+
+    /// Publish a new price (signed by the operator)
+    pub fn update_price(state: &OracleState, price: u64) -> Self {
+        Self::new(state, state.operator, OracleAction::UpdatePrice { price })
+    }
+
+    /// Replace the operator (signed by the admin)
+    pub fn set_operator(state: &OracleState, operator: Address) -> Self {
+        Self::new(state, state.admin, OracleAction::SetOperator { operator })
+    }
+
+    /// Tune the update rate limits (signed by the admin)
+    pub fn set_update_limits(state: &OracleState, min_update_interval_blocks: u64, max_cumulative_deviation_bps: u64) -> Self {
+        let action = OracleAction::SetUpdateLimits { min_update_interval_blocks, max_cumulative_deviation_bps };
+        Self::new(state, state.admin, action)
+    }
+
+    /// Tune how fast the deviation limit grows (signed by the admin)
+    pub fn set_deviation_scaling(state: &OracleState, deviation_scaling_bps_per_block: u64) -> Self {
+        Self::new(state, state.admin, OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block })
+    }
+
+    /// Clear a tripped circuit breaker before its cooldown lapses, or lift a
+    /// guardian freeze (signed by the admin)
+    pub fn reset_circuit_breaker(state: &OracleState) -> Self {
+        Self::new(state, state.admin, OracleAction::ResetCircuitBreaker)
+    }
+
+    /// Appoint the guardian (signed by the admin)
+    pub fn set_guardian(state: &OracleState, guardian: Address) -> Self {
+        Self::new(state, state.admin, OracleAction::SetGuardian { guardian })
+    }
+
+    /// Freeze price updates until the admin resets the circuit breaker
+    /// (signed by the guardian)
+    pub fn freeze_price(state: &OracleState) -> Self {
+        Self::new(state, state.guardian, OracleAction::FreezePrice)
+    }
+
+    /// Replace the registered attestation sources (signed by the admin)
+    pub fn set_attestation_sources(state: &OracleState, sources: Vec<[u8; 32]>) -> Self {
+        Self::new(state, state.admin, OracleAction::SetAttestationSources { sources })
+    }
+
+    /// Set the range of prices accepted for the asset (signed by the admin)
+    pub fn set_price_bounds(state: &OracleState, bounds: PriceBounds) -> Self {
+        Self::new(state, state.admin, OracleAction::SetPriceBounds { bounds })
+    }
+
+    /// Publish the median of signed attestations (anyone may submit)
+    pub fn aggregate_update(state: &OracleState, submitter: Address, attestations: Vec<PriceAttestation>) -> Self {
+        Self::new(state, submitter, OracleAction::AggregateUpdate { attestations })
+    }
+
+    /// Publish a feed's price (signed by the feed's operator, or the
+    /// oracle's operator for the BTC/USD feed)
+    pub fn update_feed(state: &OracleState, feed_id: FeedId, price: u64) -> Self {
+        let signer = state.feed(&feed_id).map_or(state.operator, |feed| feed.operator);
+        Self::new(state, signer, OracleAction::UpdateFeed { feed_id, price })
+    }
+
+    /// Add a secondary feed (signed by the admin)
+    pub fn add_feed(state: &OracleState, feed: Feed) -> Self {
+        Self::new(state, state.admin, OracleAction::AddFeed { feed })
+    }
+
+    /// Remove a secondary feed (signed by the admin)
+    pub fn remove_feed(state: &OracleState, feed_id: FeedId) -> Self {
+        Self::new(state, state.admin, OracleAction::RemoveFeed { feed_id })
+    }
+
+    /// Sign with another key
+    pub fn signed_by(mut self, signer: Address) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Block the spell executes at (defaults to the last price update)
+    pub fn at_block(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
+    }
+
+    /// Derive the expected oracle state
+    pub fn build(self) -> ZkUsdResult<Built<OracleContext>> {
+        let state = self.state;
+        let new_state = match &self.action {
+            OracleAction::Initialize { admin, operator, initial_price } => {
+                OracleState::new(*admin, *operator, *initial_price, self.block_height)
+            }
+            OracleAction::UpdatePrice { price } | OracleAction::UpdateFeed { feed_id: BTC_USD_FEED, price } => OracleState {
+                price: PriceData {
+                    price: *price,
+                    timestamp_block: self.block_height,
+                    effective_from_block: self.block_height + 1,
+                    ..state.price
+                },
+                previous_price: state.price.clone(),
+                last_valid_price: *price,
+                recent_deviations_bps: state.deviations_after(calculate_price_deviation(state.price.price, *price)),
+                circuit_breaker: state.circuit_breaker.after_update(&state.price, *price, self.block_height),
+                ..state.clone()
+            },
+            OracleAction::SetOperator { operator } => OracleState { operator: *operator, ..state.clone() },
+            OracleAction::SetUpdateLimits { min_update_interval_blocks, max_cumulative_deviation_bps } => OracleState {
+                min_update_interval_blocks: *min_update_interval_blocks,
+                max_cumulative_deviation_bps: *max_cumulative_deviation_bps,
+                ..state.clone()
+            },
+            OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block } => OracleState {
+                deviation_scaling_bps_per_block: *deviation_scaling_bps_per_block,
+                ..state.clone()
+            },
+            OracleAction::ResetCircuitBreaker => {
+                OracleState { circuit_breaker: state.circuit_breaker.reset(&state.price), ..state.clone() }
+            }
+            OracleAction::SetAttestationSources { sources } => {
+                OracleState { attestation_sources: sources.clone(), ..state.clone() }
+            }
+            OracleAction::SetPriceBounds { bounds } => OracleState { price_bounds: *bounds, ..state.clone() },
+            OracleAction::SetGuardian { guardian } => OracleState { guardian: *guardian, ..state.clone() },
+            OracleAction::FreezePrice => OracleState {
+                circuit_breaker: state.circuit_breaker.freeze(&state.price, self.block_height),
+                ..state.clone()
+            },
+            OracleAction::UpdateFeed { feed_id, price } => {
+                let mut new_state = state.clone();
+                let feed = new_state
+                    .feeds
+                    .iter_mut()
+                    .find(|feed| feed.feed_id == *feed_id)
+                    .ok_or(ZkUsdError::InvalidInput { param: "feed_id", reason: "unknown feed" })?;
+                feed.price = PriceData {
+                    price: *price,
+                    timestamp_block: self.block_height,
+                    effective_from_block: self.block_height + 1,
+                    ..feed.price.clone()
+                };
+                new_state
+            }
+            OracleAction::AddFeed { feed } => {
+                let mut new_state = state.clone();
+                new_state.feeds.push(feed.clone());
+                new_state
+            }
+            OracleAction::RemoveFeed { feed_id } => {
+                let mut new_state = state.clone();
+                new_state.feeds.retain(|feed| feed.feed_id != *feed_id);
+                new_state
+            }
+            OracleAction::AggregateUpdate { attestations } => {
+                let price = median_attested_price(attestations).ok_or(ZkUsdError::ZeroAmount)?;
+                OracleState {
+                    price: PriceData::published(price, self.block_height, PriceSource::Aggregated),
+                    previous_price: state.price.clone(),
+                    last_valid_price: price,
+                    recent_deviations_bps: state.deviations_after(calculate_price_deviation(state.price.price, price)),
+                    circuit_breaker: state.circuit_breaker.after_update(&state.price, price, self.block_height),
+                    ..state.clone()
+                }
+            }
+        };
+
+        let context = OracleContext {
+            state,
+            new_state,
+            signer: self.signer,
+            block_height: self.block_height,
+            events: EventLog::new(),
+        };
+        Ok(Built { action: self.action, context })
+    }
+}
+
+impl Built<OracleContext> {
+    /// Action and expected oracle state
+    pub fn into_parts(self) -> (OracleAction, OracleState) {
+        (self.action, self.context.new_state)
+    }
+}
+
+/// The oracle state is spent and recreated under the oracle app; an
+/// initialization only creates it
+#[cfg(feature = "charms")]
+impl ChainSpell for OracleContext {
+    fn encode(built: &Built<Self>) -> EncodedSpell {
+        let oracle = app('n', ORACLE_APP_ID);
+        let w = Data::from(&OracleWitness::for_action(&built.action));
+        let spell = EncodedSpell::new(oracle.clone(), w, built.context.block_height);
+        let spell = match built.action {
+            OracleAction::Initialize { .. } => spell,
+            _ => spell.spend(charm(&oracle, Data::from(&built.context.state))),
+        };
+        spell.create(charm(&oracle, Data::from(&built.context.new_state)))
+    }
+
+    fn validate_encoded(spell: &EncodedSpell) -> bool {
+        validate_oracle_operation_at(&spell.app, &spell.tx, &spell.x, &spell.w, spell.block_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_locally;
+    use ed25519_dalek::{Signer, SigningKey};
+    use zkusd_common::types::CircuitBreakerState;
+
+    const ADMIN: Address = [9u8; 32];
+    const OPERATOR: Address = [1u8; 32];
+    const GUARDIAN: Address = [7u8; 32];
+    const FEED_OPERATOR: Address = [5u8; 32];
+    const USDC_USD: FeedId = *b"USDCUSD\0";
+    const ONE_USD: u64 = 100_000_000;
+    const BTC_PRICE_100K: u64 = OracleState::DEFAULT_BTC_PRICE;
+
+    fn state() -> OracleState {
+        OracleState::new(ADMIN, OPERATOR, BTC_PRICE_100K, 100)
+    }
+
+    fn tripped_state() -> OracleState {
+        let state = state();
+        let circuit_breaker = CircuitBreakerState {
+            tripped: true,
+            tripped_at: 100,
+            reference_price: BTC_PRICE_100K,
+            reference_block: 95,
+            ..CircuitBreakerState::default()
+        };
+        OracleState { circuit_breaker, ..state }
+    }
+
+    fn guarded_state() -> OracleState {
+        OracleState { guardian: GUARDIAN, ..state() }
+    }
+
+    fn usdc_feed() -> Feed {
+        Feed {
+            feed_id: USDC_USD,
+            price: PriceData::published(ONE_USD, 100, PriceSource::Mock),
+            bounds: PriceBounds { min_price: ONE_USD / 2, max_price: ONE_USD * 2 },
+            max_deviation_bps: 200,
+            operator: FEED_OPERATOR,
+        }
+    }
+
+    fn feed_state() -> OracleState {
+        OracleState { feeds: vec![usdc_feed()], ..state() }
+    }
+
+    fn feed(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn attest(key: &SigningKey, price: u64, observed_at_block: u64) -> PriceAttestation {
+        let mut attestation =
+            PriceAttestation { source_pubkey: key.verifying_key().to_bytes(), price, observed_at_block, signature: [0; 64] };
+        attestation.signature = key.sign(&attestation.message()).to_bytes();
+        attestation
+    }
+
+    fn attested_state() -> OracleState {
+        let attestation_sources = (1..=3).map(|seed| feed(seed).verifying_key().to_bytes()).collect();
+        OracleState { attestation_sources, ..state() }
+    }
+
+    fn every_action() -> Vec<(&'static str, Built<OracleContext>)> {
+        let state = state();
+        let build = |builder: OracleOpsBuilder| builder.build().expect("builder should succeed");
+
+        Vec::from([
+            ("initialize", build(OracleOpsBuilder::initialize(ADMIN, OPERATOR, BTC_PRICE_100K))),
+            ("update_price", build(OracleOpsBuilder::update_price(&state, BTC_PRICE_100K + BTC_PRICE_100K / 100).at_block(110))),
+            ("set_operator", build(OracleOpsBuilder::set_operator(&state, [2u8; 32]))),
+            ("set_update_limits", build(OracleOpsBuilder::set_update_limits(&state, 2, 1_500))),
+            ("set_deviation_scaling", build(OracleOpsBuilder::set_deviation_scaling(&state, 5))),
+            ("reset_circuit_breaker", build(OracleOpsBuilder::reset_circuit_breaker(&tripped_state()))),
+            ("set_attestation_sources", build(OracleOpsBuilder::set_attestation_sources(&state, vec![[3u8; 32]]))),
+            ("set_guardian", build(OracleOpsBuilder::set_guardian(&state, GUARDIAN))),
+            ("freeze_price", build(OracleOpsBuilder::freeze_price(&guarded_state()).at_block(105))),
+            (
+                "set_price_bounds",
+                build(OracleOpsBuilder::set_price_bounds(&state, PriceBounds { min_price: 1, max_price: u64::MAX })),
+            ),
+            ("update_feed", build(OracleOpsBuilder::update_feed(&feed_state(), USDC_USD, ONE_USD - ONE_USD / 100).at_block(110))),
+            ("add_feed", build(OracleOpsBuilder::add_feed(&state, usdc_feed()))),
+            ("remove_feed", build(OracleOpsBuilder::remove_feed(&feed_state(), USDC_USD))),
+            (
+                "aggregate_update",
+                build(
+                    OracleOpsBuilder::aggregate_update(
+                        &attested_state(),
+                        [4u8; 32],
+                        vec![attest(&feed(1), BTC_PRICE_100K + 1, 104), attest(&feed(3), BTC_PRICE_100K - 1, 105)],
+                    )
+                    .at_block(105),
+                ),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_every_builder_validates() {
+        for (name, built) in every_action() {
+            assert_eq!(verify_locally(&built), Ok(()), "{} should validate", name);
+        }
+    }
+
+    #[test]
+    fn test_update_price_records_deviation() {
+        let (_, new_state) = OracleOpsBuilder::update_price(&state(), BTC_PRICE_100K + BTC_PRICE_100K / 100)
+            .at_block(110)
+            .build()
+            .unwrap()
+            .into_parts();
+
+        assert_eq!(new_state.price.timestamp_block, 110);
+        assert_eq!(new_state.recent_deviations_bps, [100]);
+    }
+
+    type Mutation = fn(&mut Built<OracleContext>);
+
+    /// Changes to a built spell, each of which must fail validation
+    fn mutations() -> [(&'static str, Mutation); 15] {
+        [
+            ("initialize", |b| b.context.new_state.operator = ADMIN),
+            ("update_price", |b| b.context.new_state.last_valid_price += 1),
+            ("update_price", |b| b.context.new_state.recent_deviations_bps.clear()),
+            ("set_operator", |b| b.context.signer = OPERATOR),
+            ("set_update_limits", |b| b.context.new_state.is_active = false),
+            ("set_deviation_scaling", |b| b.action = OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block: 6 }),
+            ("reset_circuit_breaker", |b| b.context.new_state.circuit_breaker.tripped = true),
+            ("set_attestation_sources", |b| b.context.signer = OPERATOR),
+            ("aggregate_update", |b| b.context.new_state.attestation_sources.clear()),
+            ("set_price_bounds", |b| b.context.new_state.price_bounds.max_price = 0),
+            ("set_guardian", |b| b.context.signer = OPERATOR),
+            ("freeze_price", |b| b.context.signer = ADMIN),
+            ("update_feed", |b| b.context.new_state.price.price = ONE_USD),
+            ("add_feed", |b| b.context.signer = FEED_OPERATOR),
+            ("remove_feed", |b| b.action = OracleAction::RemoveFeed { feed_id: BTC_USD_FEED }),
+        ]
+    }
+
+    #[test]
+    fn test_mutated_fields_fail_validation() {
+        let built = every_action();
+        for (name, mutate) in mutations() {
+            let (_, original) = built.iter().find(|(n, _)| *n == name).unwrap();
+            let mut mutated = original.clone();
+            mutate(&mut mutated);
+            assert!(verify_locally(&mutated).is_err(), "mutated {} should fail", name);
+        }
+    }
+
+    #[cfg(feature = "charms")]
+    mod differential {
+        use super::*;
+        use crate::differential::{compare, scenarios, Exception};
+
+        /// The entry point has no signature to read, so it takes the
+        /// operator of the spent state as the signer
+        const SIGNER_IS_OPERATOR: &str = "entry point reads the operator as the signer";
+
+        /// Admin, guardian and feed operator actions are refused on chain
+        /// until the entry point reads signatures
+        const EXCEPTIONS: &[Exception] = &[
+            Exception { scenario: "set_operator", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "set_update_limits", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "set_deviation_scaling", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "reset_circuit_breaker", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "set_attestation_sources", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "set_guardian", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "freeze_price", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "set_price_bounds", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "update_feed", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "add_feed", reason: SIGNER_IS_OPERATOR },
+            Exception { scenario: "remove_feed", reason: SIGNER_IS_OPERATOR },
+        ];
+
+        #[test]
+        fn test_entry_point_agrees_with_library() {
+            let divergences = compare(&scenarios(every_action(), &mutations()), EXCEPTIONS);
+            assert!(divergences.is_empty(), "{:#?}", divergences);
+        }
+    }
+}
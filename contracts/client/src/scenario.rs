@@ -0,0 +1,826 @@
+//! Stress Scenarios
+//!
+//! `StressScenario` replays a BTC price path and per-block protocol actions
+//! through the real validators, using the builders of this crate, and
+//! checks protocol invariants after every block.
+//!
+//! Each block the oracle operator publishes the path's price, stepping
+//! toward it as far as the oracle's deviation limits allow; a price of 0
+//! means the feed is down that block. The block's actions then run in
+//! order. An action the validators reject is skipped and recorded with its
+//! error: stale prices, a tripped circuit breaker or an empty pool are
+//! legitimate outcomes of a stress run, not failures.
+//!
+//! ## Invariants
+//!
+//! - **Solvency**: the active vaults' collateral is worth at least their
+//!   debt at the oracle price, and the books account for all of that debt.
+//!   The books' collateral total is not reconciled: only openings move it.
+//! - **No negative pools**: the stability pool holds exactly what was
+//!   deposited less what offsets consumed, and no builder or validator
+//!   ever hits an arithmetic overflow or underflow.
+//! - **TCR consistency**: every vault opened respected the minimum ratio of
+//!   the mode the books' TCR put the system in at that block.
+//! - **Liquidation threshold**: every liquidated vault had an ICR below the
+//!   liquidation threshold at its liquidation block.
+//!
+//! zkUSD balances are not tracked: depositors and redeemers are assumed to
+//! hold what they spend. A liquidation and its stability pool offset share
+//! one spell, so a liquidation the pool cannot absorb is skipped whole.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let report = StressScenario::flash_crash(7).run();
+//! assert!(report.is_ok(), "{}", report.trace());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use zkusd_common::{
+    constants::{limits, liquidation, ratios, token::ONE},
+    errors::{ZkUsdError, ZkUsdResult},
+    math::{calculate_icr, calculate_tcr, get_min_ratio, is_liquidatable, is_recovery_mode, safe_add},
+    types::{Address, OracleSnapshot, StabilityDeposit, StabilityPoolState, Vault},
+    units::{Sats, ZkUsd},
+};
+use zkusd_price_oracle::OracleState;
+use zkusd_stability_pool::StabilityPoolConfig;
+use zkusd_vault_manager::{VaultContext, VaultManagerState};
+
+use crate::{verify_locally, Built, OracleOpsBuilder, SpellContext, StabilityPoolOpsBuilder, VaultOpsBuilder};
+
+/// Block the first entry of a price path executes at
+pub const GENESIS_BLOCK: u64 = 1_000;
+
+/// BTC price the generators start from ($100k, 8 decimals)
+pub const START_PRICE: u64 = 100_000 * ONE;
+
+/// Halvings of the gap to the target price the operator tries before
+/// giving up on a block's update
+const PRICE_STEP_HALVINGS: u32 = 6;
+
+const ADMIN: Address = [9u8; 32];
+const OPERATOR: Address = [8u8; 32];
+const KEEPER: Address = [6u8; 32];
+const REDEEMER: Address = [7u8; 32];
+
+// ============ Actions ============
+
+/// One user or keeper action within a block
+///
+/// Vaults are named by owner; each owner holds at most one vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolAction {
+    /// Open a vault borrowing `debt`
+    OpenVault { owner: Address, collateral: Sats, debt: ZkUsd },
+    /// Add collateral to the owner's vault
+    AddCollateral { owner: Address, amount: Sats },
+    /// Repay part of the owner's vault debt
+    RepayDebt { owner: Address, amount: ZkUsd },
+    /// Deposit into the stability pool, topping up any existing deposit
+    Deposit { owner: Address, amount: ZkUsd },
+    /// Liquidate every active vault below CCR, lowest ICR first, offsetting
+    /// each against the stability pool
+    LiquidateUnderwater,
+    /// Redeem `amount` against the active vaults, lowest ICR first,
+    /// skipping those inside the redemption lockout
+    Redeem { amount: ZkUsd },
+}
+
+impl ProtocolAction {
+    /// Short label for traces
+    fn label(&self) -> String {
+        match self {
+            Self::OpenVault { owner, collateral, debt } => {
+                format!("open_vault({}, {} sats, {} zkUSD)", short(owner), collateral.into_inner(), debt.into_inner() / ONE)
+            }
+            Self::AddCollateral { owner, amount } => format!("add_collateral({}, {} sats)", short(owner), amount.into_inner()),
+            Self::RepayDebt { owner, amount } => format!("repay_debt({}, {} zkUSD)", short(owner), amount.into_inner() / ONE),
+            Self::Deposit { owner, amount } => format!("deposit({}, {} zkUSD)", short(owner), amount.into_inner() / ONE),
+            Self::LiquidateUnderwater => "liquidate_underwater".into(),
+            Self::Redeem { amount } => format!("redeem({} zkUSD)", amount.into_inner() / ONE),
+        }
+    }
+}
+
+/// First bytes of an address, enough to tell generated accounts apart
+fn short(address: &Address) -> String {
+    address[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Generated account `index`
+pub fn account(index: u32) -> Address {
+    let mut address = [0xA5; 32];
+    address[..4].copy_from_slice(&index.to_be_bytes());
+    address
+}
+
+// ============ Scenarios ============
+
+/// A price path with the actions submitted at each block
+///
+/// Entry `i` of both vectors belongs to block `GENESIS_BLOCK + i`. The
+/// first price initializes the oracle; a price of 0 is a block without an
+/// update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StressScenario {
+    /// Name shown in traces
+    pub name: &'static str,
+    /// Market BTC price per block (8 decimals)
+    pub price_path: Vec<u64>,
+    /// Actions submitted per block, in order
+    pub actions_per_block: Vec<Vec<ProtocolAction>>,
+}
+
+impl StressScenario {
+    /// Empty scenario
+    pub fn new(name: &'static str) -> Self {
+        Self { name, price_path: Vec::new(), actions_per_block: Vec::new() }
+    }
+
+    /// Append a block at `price` with `actions`
+    pub fn block(&mut self, price: u64, actions: Vec<ProtocolAction>) {
+        self.price_path.push(price);
+        self.actions_per_block.push(actions);
+    }
+
+    /// Append `blocks` blocks at `price`, each with a liquidation sweep
+    fn hold(&mut self, price: u64, blocks: u64) {
+        for _ in 0..blocks {
+            self.block(price, Vec::from([ProtocolAction::LiquidateUnderwater]));
+        }
+    }
+
+    /// BTC falls 50% in one block and stays there
+    pub fn flash_crash(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("flash_crash");
+        let supply = scenario.populate(&mut rng, 24, (130, 320), Vec::new());
+        scenario.fund_pool(supply * 2 / 5);
+        scenario.hold(START_PRICE, 10);
+        scenario.hold(START_PRICE / 2, 80);
+        scenario
+    }
+
+    /// BTC bleeds 30% over 1000 blocks while owners top up at random
+    pub fn slow_bleed(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("slow_bleed");
+        let owners = 24;
+        let supply = scenario.populate(&mut rng, owners, (130, 320), Vec::new());
+        scenario.fund_pool(supply / 2);
+        let bleed = START_PRICE / 10 * 3;
+        for step in 1..=1_000u64 {
+            let mut actions = Vec::from([ProtocolAction::LiquidateUnderwater]);
+            if step % 50 == 0 {
+                let owner = account(rng.below(owners));
+                actions.push(ProtocolAction::AddCollateral { owner, amount: Sats(ONE / 10) });
+            }
+            scenario.block(START_PRICE - bleed * step / 1_000, actions);
+        }
+        scenario
+    }
+
+    /// The feed goes dark for 20 blocks and resumes 10% lower; borrowers
+    /// and keepers keep submitting throughout
+    pub fn oracle_outage(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("oracle_outage");
+        let supply = scenario.populate(&mut rng, 16, (130, 320), Vec::new());
+        scenario.fund_pool(supply / 2);
+        scenario.hold(START_PRICE, 10);
+        for i in 0..20 {
+            let owner = account(1_000 + i);
+            let open = ProtocolAction::OpenVault { owner, collateral: Sats(ONE), debt: ZkUsd(20_000 * ONE) };
+            scenario.block(0, Vec::from([open, ProtocolAction::LiquidateUnderwater]));
+        }
+        scenario.hold(START_PRICE / 10 * 9, 30);
+        scenario
+    }
+
+    /// Tightly collateralized vaults meet a thin stability pool as BTC
+    /// steps down 15%
+    pub fn liquidation_cascade(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("liquidation_cascade");
+        // Whales keep the TCR above CCR while the tight vaults open
+        let whales = Vec::from([(10 * ONE, 400), (10 * ONE, 400), (10 * ONE, 400)]);
+        let supply = scenario.populate(&mut rng, 24, (112, 160), whales);
+        scenario.fund_pool(supply / 20);
+        scenario.hold(START_PRICE, 5);
+        for step in 1..=5 {
+            scenario.hold(START_PRICE - START_PRICE / 100 * 3 * step, 8);
+        }
+        scenario.hold(START_PRICE / 100 * 85, 20);
+        scenario
+    }
+
+    /// Holders redeem 40% of supply once the redemption lockout lapses
+    pub fn redemption_run(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("redemption_run");
+        let supply = scenario.populate(&mut rng, 20, (130, 320), Vec::new());
+        scenario.fund_pool(supply / 2);
+        scenario.hold(START_PRICE, limits::REDEMPTION_LOCKOUT_BLOCKS);
+        let chunk = supply / 25;
+        for _ in 0..10 {
+            scenario.block(START_PRICE, Vec::from([ProtocolAction::Redeem { amount: ZkUsd(chunk) }]));
+        }
+        scenario.hold(START_PRICE, 5);
+        scenario
+    }
+
+    /// Random walk of up to 3% a block with random actions
+    pub fn random(seed: u64, blocks: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut scenario = Self::new("random");
+        let owners = 16;
+        let supply = scenario.populate(&mut rng, owners, (115, 400), Vec::new());
+        scenario.fund_pool(supply / u64::from(1 + rng.below(10)));
+        let mut price = START_PRICE;
+        for _ in 0..blocks {
+            let change = price / 10_000 * u64::from(rng.below(300));
+            price = if rng.below(2) == 0 { price - change } else { price + change };
+            let mut actions = Vec::new();
+            for _ in 0..rng.below(4) {
+                let owner = account(rng.below(owners + 4));
+                actions.push(match rng.below(6) {
+                    0 => ProtocolAction::OpenVault { owner, collateral: Sats(ONE), debt: ZkUsd(rng.range(2_000, 60_000) * ONE) },
+                    1 => ProtocolAction::AddCollateral { owner, amount: Sats(rng.range(1, 50) * ONE / 100) },
+                    2 => ProtocolAction::RepayDebt { owner, amount: ZkUsd(rng.range(1, 5_000) * ONE) },
+                    3 => ProtocolAction::Deposit { owner, amount: ZkUsd(rng.range(1_000, 20_000) * ONE) },
+                    4 => ProtocolAction::Redeem { amount: ZkUsd(rng.range(1_000, 20_000) * ONE) },
+                    _ => ProtocolAction::LiquidateUnderwater,
+                });
+            }
+            scenario.block(price, actions);
+        }
+        scenario
+    }
+
+    /// Open `vaults` vaults of 0.5-2 BTC at ICRs drawn from `icr_range`
+    /// (percent), after any `(collateral, icr)` vaults in `first`, one per
+    /// block at the start price, highest ICR first so openings never drag
+    /// the TCR below CCR. Returns the debt borrowed.
+    fn populate(&mut self, rng: &mut Rng, vaults: u32, icr_range: (u64, u64), first: Vec<(u64, u64)>) -> u64 {
+        let mut drawn: Vec<(u64, u64)> = (0..vaults)
+            .map(|_| (rng.range(ONE / 2, 2 * ONE), rng.range(icr_range.0, icr_range.1)))
+            .collect();
+        drawn.sort_by_key(|&(_, icr)| std::cmp::Reverse(icr));
+
+        let mut supply = 0;
+        for (index, (collateral, icr)) in (0u32..).zip(first.into_iter().chain(drawn)) {
+            let value = u128::from(collateral) * u128::from(START_PRICE) / u128::from(ONE);
+            let debt = (value * 100 / u128::from(icr)) as u64 - limits::LIQUIDATION_RESERVE;
+            supply += debt;
+            let owner = account(index);
+            self.block(START_PRICE, Vec::from([ProtocolAction::OpenVault { owner, collateral: Sats(collateral), debt: ZkUsd(debt) }]));
+        }
+        supply
+    }
+
+    /// Spread `amount` over four stability pool depositors in one block
+    fn fund_pool(&mut self, amount: u64) {
+        let deposits = (0..4)
+            .map(|i| ProtocolAction::Deposit { owner: account(10_000 + i), amount: ZkUsd(amount / 4) })
+            .collect();
+        self.block(START_PRICE, deposits);
+    }
+
+    /// Replay the scenario through the validators
+    pub fn run(&self) -> StressReport {
+        let genesis_price = self.price_path.iter().copied().find(|price| *price > 0).unwrap_or(START_PRICE);
+        let mut executor = Executor::new(self.name, genesis_price);
+        for (offset, price) in (0u64..).zip(&self.price_path) {
+            executor.block = GENESIS_BLOCK + offset;
+            executor.publish_price(*price);
+            for action in self.actions_per_block.get(offset as usize).into_iter().flatten() {
+                executor.execute(action);
+            }
+            executor.end_block(*price);
+        }
+        executor.report
+    }
+}
+
+// ============ Reports ============
+
+/// Protocol property checked after every block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Invariant {
+    /// Active vaults are collateralized in aggregate and on the books
+    Solvency,
+    /// Pool totals match the ledger; no arithmetic underflow or overflow
+    NoNegativePools,
+    /// Openings respected the mode of the books' TCR
+    TcrConsistency,
+    /// Liquidated vaults were below the liquidation threshold
+    LiquidationThreshold,
+}
+
+/// An invariant that failed, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub block: u64,
+    pub invariant: Invariant,
+    pub detail: String,
+}
+
+/// An action the validators rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skip {
+    pub block: u64,
+    pub action: String,
+    pub reason: ZkUsdError,
+}
+
+/// A vault liquidated during the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Liquidation {
+    pub block: u64,
+    pub owner: Address,
+    /// Vault ICR at the liquidation price (percent)
+    pub icr: u64,
+    /// TCR of the books at the liquidation price (percent)
+    pub tcr: u64,
+}
+
+/// Protocol state at the end of one block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockTrace {
+    pub block: u64,
+    /// Path price (0 for an outage)
+    pub market_price: u64,
+    /// Price the oracle publishes
+    pub oracle_price: u64,
+    /// TCR of the books at the oracle price (percent)
+    pub tcr: u64,
+    pub active_vaults: usize,
+    pub pool_zkusd: u64,
+}
+
+/// Outcome of a scenario run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StressReport {
+    pub name: &'static str,
+    pub blocks: Vec<BlockTrace>,
+    pub skips: Vec<Skip>,
+    pub liquidations: Vec<Liquidation>,
+    pub violations: Vec<Violation>,
+}
+
+impl StressReport {
+    /// True if every invariant held
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Skips whose error has the given code
+    pub fn skips_with(&self, code: &str) -> usize {
+        self.skips.iter().filter(|skip| skip.reason.code() == code).count()
+    }
+
+    /// Block-by-block dump of the run, for failure messages
+    pub fn trace(&self) -> String {
+        let mut out = format!("scenario {}: {} blocks\n", self.name, self.blocks.len());
+        for violation in &self.violations {
+            let _ = writeln!(out, "VIOLATION block {} {:?}: {}", violation.block, violation.invariant, violation.detail);
+        }
+        for trace in &self.blocks {
+            let _ = writeln!(
+                out,
+                "block {} market {} oracle {} tcr {} vaults {} pool {}",
+                trace.block,
+                trace.market_price / ONE,
+                trace.oracle_price / ONE,
+                trace.tcr,
+                trace.active_vaults,
+                trace.pool_zkusd / ONE,
+            );
+            for liquidation in self.liquidations.iter().filter(|l| l.block == trace.block) {
+                let _ = writeln!(out, "  liquidated {} icr {} tcr {}", short(&liquidation.owner), liquidation.icr, liquidation.tcr);
+            }
+            for skip in self.skips.iter().filter(|s| s.block == trace.block) {
+                let _ = writeln!(out, "  skipped {}: {}", skip.action, skip.reason.code());
+            }
+        }
+        out
+    }
+}
+
+// ============ Executor ============
+
+/// Contract states and ledgers threaded through a run
+struct Executor {
+    block: u64,
+    vault_manager: VaultManagerState,
+    vaults: BTreeMap<Address, Vault>,
+    oracle: OracleState,
+    pool: StabilityPoolState,
+    config: StabilityPoolConfig,
+    deposits: BTreeMap<Address, StabilityDeposit>,
+    /// zkUSD deposited into and BTC received by the pool
+    deposited: u64,
+    offset_debt: u64,
+    offset_collateral: u64,
+    /// `(ICR, TCR)` of vaults opened this block
+    opened: Vec<(u64, u64)>,
+    report: StressReport,
+}
+
+impl Executor {
+    fn new(name: &'static str, genesis_price: u64) -> Self {
+        let vault_manager = VaultManagerState::new(ADMIN, [1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32], [5u8; 32])
+            .expect("fixture addresses are non-zero");
+        Self {
+            block: GENESIS_BLOCK,
+            vault_manager,
+            vaults: BTreeMap::new(),
+            oracle: OracleState::new(ADMIN, OPERATOR, genesis_price, GENESIS_BLOCK),
+            pool: StabilityPoolState::new(),
+            config: StabilityPoolConfig { zkusd_token_id: [1u8; 32], vault_manager_id: [2u8; 32], admin: ADMIN, frontends: Vec::new() },
+            deposits: BTreeMap::new(),
+            deposited: 0,
+            offset_debt: 0,
+            offset_collateral: 0,
+            opened: Vec::new(),
+            report: StressReport { name, ..StressReport::default() },
+        }
+    }
+
+    /// Record a rejected action
+    fn skip(&mut self, action: String, reason: ZkUsdError) {
+        self.report.skips.push(Skip { block: self.block, action, reason });
+    }
+
+    /// Built artifacts that pass the real validator
+    ///
+    /// Builders reject impossible requests (repaying more than the debt)
+    /// through checked arithmetic, but a validator hitting an overflow or
+    /// underflow breaks the no negative pools invariant.
+    fn validated<C: SpellContext>(&mut self, built: ZkUsdResult<Built<C>>) -> ZkUsdResult<Built<C>> {
+        let built = built?;
+        if let Err(reason) = verify_locally(&built) {
+            if matches!(reason, ZkUsdError::Overflow | ZkUsdError::Underflow | ZkUsdError::MathOverflow) {
+                self.violate(Invariant::NoNegativePools, format!("validator hit {}", reason.code()));
+            }
+            return Err(reason);
+        }
+        Ok(built)
+    }
+
+    fn violate(&mut self, invariant: Invariant, detail: String) {
+        self.report.violations.push(Violation { block: self.block, invariant, detail });
+    }
+
+    fn snapshot(&self) -> OracleSnapshot {
+        self.oracle.snapshot()
+    }
+
+    fn price(&self) -> u64 {
+        self.oracle.price.price
+    }
+
+    /// TCR of the books at the oracle price, as the validators compute it
+    fn tcr(&self) -> ZkUsdResult<u64> {
+        let protocol = &self.vault_manager.protocol;
+        calculate_tcr(Sats(protocol.total_collateral), ZkUsd(protocol.total_debt_with_interest(self.block)?), self.price())
+    }
+
+    fn icr(&self, vault: &Vault) -> ZkUsdResult<u64> {
+        calculate_icr(Sats(vault.collateral), ZkUsd(vault.debt), self.price())
+    }
+
+    /// Active vaults, lowest ICR first
+    fn by_icr(&self) -> Vec<(u64, Vault)> {
+        let mut vaults: Vec<(u64, Vault)> = self
+            .vaults
+            .values()
+            .filter(|vault| vault.is_active())
+            .map(|vault| (self.icr(vault).unwrap_or(u64::MAX), vault.clone()))
+            .collect();
+        vaults.sort_by_key(|(icr, _)| *icr);
+        vaults
+    }
+
+    /// Publish `target`, or the largest step toward it the oracle accepts
+    fn publish_price(&mut self, target: u64) {
+        let elapsed = self.block.saturating_sub(self.oracle.price.timestamp_block);
+        if target == 0 || elapsed < self.oracle.min_update_interval_blocks {
+            return;
+        }
+        let current = self.price();
+        let mut error = ZkUsdError::InvalidStateTransition;
+        for halvings in 0..=PRICE_STEP_HALVINGS {
+            let price = if target < current {
+                current - ((current - target) >> halvings)
+            } else {
+                current + ((target - current) >> halvings)
+            };
+            match self.validated(OracleOpsBuilder::update_price(&self.oracle, price).at_block(self.block).build()) {
+                Ok(built) => {
+                    self.oracle = built.into_parts().1;
+                    return;
+                }
+                Err(e) => error = e,
+            }
+        }
+        self.skip(format!("update_price({})", target / ONE), error);
+    }
+
+    fn execute(&mut self, action: &ProtocolAction) {
+        let result = match action {
+            ProtocolAction::OpenVault { owner, collateral, debt } => self.open_vault(*owner, *collateral, *debt),
+            ProtocolAction::AddCollateral { owner, amount } => self.with_vault(owner, |vault_manager, vault| {
+                VaultOpsBuilder::add_collateral(vault_manager, vault, *amount)
+            }),
+            ProtocolAction::RepayDebt { owner, amount } => {
+                self.with_vault(owner, |vault_manager, vault| VaultOpsBuilder::repay_debt(vault_manager, vault, *amount))
+            }
+            ProtocolAction::Deposit { owner, amount } => self.deposit(*owner, *amount),
+            ProtocolAction::LiquidateUnderwater => {
+                self.liquidate_underwater();
+                Ok(())
+            }
+            ProtocolAction::Redeem { amount } => {
+                self.redeem(*amount);
+                Ok(())
+            }
+        };
+        if let Err(reason) = result {
+            self.skip(action.label(), reason);
+        }
+    }
+
+    fn vault_spell(&mut self, builder: VaultOpsBuilder) -> ZkUsdResult<Built<VaultContext>> {
+        let built = builder.with_oracle(self.snapshot()).at_block(self.block).build();
+        self.validated(built)
+    }
+
+    fn apply_vault(&mut self, built: Built<VaultContext>) {
+        let (_, vault, state) = built.into_parts();
+        self.vault_manager = state;
+        if let Some(vault) = vault {
+            self.vaults.insert(vault.owner, vault);
+        }
+    }
+
+    fn open_vault(&mut self, owner: Address, collateral: Sats, debt: ZkUsd) -> ZkUsdResult<()> {
+        let total_debt = safe_add(debt.into_inner(), limits::LIQUIDATION_RESERVE)?;
+        let icr = calculate_icr(collateral, ZkUsd(total_debt), self.price())?;
+        let tcr = self.tcr()?;
+        let pool_zkusd = ZkUsd(self.pool.total_zkusd);
+        let built = self.vault_spell(
+            VaultOpsBuilder::open_vault(&self.vault_manager, owner, collateral, debt).with_stability_pool(pool_zkusd),
+        )?;
+        self.apply_vault(built);
+        self.opened.push((icr, tcr));
+        Ok(())
+    }
+
+    /// Build a spell on the owner's vault
+    fn with_vault(
+        &mut self,
+        owner: &Address,
+        builder: impl FnOnce(&VaultManagerState, &Vault) -> VaultOpsBuilder,
+    ) -> ZkUsdResult<()> {
+        let vault = self.vaults.get(owner).ok_or(ZkUsdError::StateNotFound)?;
+        let built = self.vault_spell(builder(&self.vault_manager, vault))?;
+        self.apply_vault(built);
+        Ok(())
+    }
+
+    fn deposit(&mut self, owner: Address, amount: ZkUsd) -> ZkUsdResult<()> {
+        let mut builder = StabilityPoolOpsBuilder::deposit(&self.pool, &self.config, owner, amount);
+        if let Some(existing) = self.deposits.get(&owner) {
+            builder = builder.topping_up(existing);
+        }
+        let (_, deposit, pool) = self.validated(builder.at_block(self.block).build())?.into_parts();
+        self.pool = pool;
+        if let Some(deposit) = deposit {
+            self.deposits.insert(owner, deposit);
+        }
+        self.deposited = safe_add(self.deposited, amount.into_inner())?;
+        Ok(())
+    }
+
+    fn liquidate_underwater(&mut self) {
+        for (icr, vault) in self.by_icr() {
+            if icr >= ratios::CCR {
+                break;
+            }
+            let label = format!("liquidate({})", short(&vault.owner));
+            if let Err(reason) = self.liquidate(icr, &vault) {
+                self.skip(label, reason);
+            }
+        }
+    }
+
+    /// Liquidate `vault` and offset it against the pool in one spell
+    fn liquidate(&mut self, icr: u64, vault: &Vault) -> ZkUsdResult<()> {
+        let tcr = self.tcr()?;
+        let liquidated = self.vault_spell(VaultOpsBuilder::liquidate(&self.vault_manager, KEEPER, vault))?;
+
+        // Collateral net of the keeper's gas compensation and bonus goes to
+        // the pool, as the VaultManager computes it
+        let bonus_bps = self.vault_manager.liquidator_bonus_bps(is_recovery_mode(tcr));
+        let to_pool = vault.collateral
+            - vault.collateral * liquidation::GAS_COMP_BPS / 10000
+            - vault.collateral * bonus_bps / 10000;
+        if self.pool.total_zkusd < vault.debt {
+            return Err(ZkUsdError::InsufficientPoolBalance { available: self.pool.total_zkusd, required: vault.debt });
+        }
+        let offset = StabilityPoolOpsBuilder::offset(&self.pool, &self.config, ZkUsd(vault.debt), Sats(to_pool));
+        let (_, _, pool) = self.validated(offset.at_block(self.block).build())?.into_parts();
+
+        self.pool = pool;
+        self.offset_debt = safe_add(self.offset_debt, vault.debt)?;
+        self.offset_collateral = safe_add(self.offset_collateral, to_pool)?;
+        self.apply_vault(liquidated);
+        self.report.liquidations.push(Liquidation { block: self.block, owner: vault.owner, icr, tcr });
+        Ok(())
+    }
+
+    fn redeem(&mut self, amount: ZkUsd) {
+        let mut remaining = amount.into_inner();
+        for (_, vault) in self.by_icr() {
+            if remaining == 0 {
+                break;
+            }
+            if self.vault_manager.is_redemption_locked(&vault, self.block) {
+                continue;
+            }
+            let take = remaining.min(vault.debt);
+            let builder = VaultOpsBuilder::redeem(&self.vault_manager, REDEEMER, ZkUsd(take), Sats::ZERO).against(&vault);
+            match self.vault_spell(builder) {
+                Ok(built) => {
+                    self.apply_vault(built);
+                    remaining -= take;
+                }
+                Err(reason) => self.skip(format!("redeem({} zkUSD, {})", take / ONE, short(&vault.owner)), reason),
+            }
+        }
+    }
+
+    /// Check the invariants and record the block
+    fn end_block(&mut self, market_price: u64) {
+        let active: Vec<&Vault> = self.vaults.values().filter(|vault| vault.is_active()).collect();
+        let collateral: u64 = active.iter().map(|vault| vault.collateral).sum();
+        let debt: u64 = active.iter().map(|vault| vault.debt).sum();
+        let active_vaults = active.len();
+        let books = self.vault_manager.protocol.total_debt;
+        let ratio = calculate_icr(Sats(collateral), ZkUsd(debt), self.price()).unwrap_or(0);
+        if ratio < 100 || debt > books {
+            let detail = format!("vaults hold {} sats / {} zkUSD (ratio {}), books {} zkUSD", collateral, debt, ratio, books);
+            self.violate(Invariant::Solvency, detail);
+        }
+
+        let expected_zkusd = self.deposited.checked_sub(self.offset_debt);
+        if expected_zkusd != Some(self.pool.total_zkusd) || self.pool.total_btc != self.offset_collateral {
+            let detail = format!(
+                "pool holds {} zkUSD / {} sats, ledger {:?} zkUSD / {} sats",
+                self.pool.total_zkusd, self.pool.total_btc, expected_zkusd, self.offset_collateral
+            );
+            self.violate(Invariant::NoNegativePools, detail);
+        }
+
+        for (icr, tcr) in std::mem::take(&mut self.opened) {
+            if icr < get_min_ratio(tcr) {
+                self.violate(Invariant::TcrConsistency, format!("vault opened at ICR {} with TCR {}", icr, tcr));
+            }
+        }
+
+        let block = self.block;
+        let unjustified: Vec<Liquidation> = self
+            .report
+            .liquidations
+            .iter()
+            .filter(|l| l.block == block && !is_liquidatable(l.icr, l.tcr))
+            .copied()
+            .collect();
+        for liquidation in unjustified {
+            let detail = format!("{} liquidated at ICR {} with TCR {}", short(&liquidation.owner), liquidation.icr, liquidation.tcr);
+            self.violate(Invariant::LiquidationThreshold, detail);
+        }
+
+        let trace = BlockTrace {
+            block,
+            market_price,
+            oracle_price: self.price(),
+            tcr: self.tcr().unwrap_or(0),
+            active_vaults,
+            pool_zkusd: self.pool.total_zkusd,
+        };
+        self.report.blocks.push(trace);
+    }
+}
+
+// ============ Randomness ============
+
+/// SplitMix64, so scenarios are reproducible from their seed
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`
+    fn below(&mut self, bound: u32) -> u32 {
+        (self.next() % u64::from(bound.max(1))) as u32
+    }
+
+    /// Uniform in `low..high`
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 7;
+
+    fn assert_holds(report: &StressReport) {
+        assert!(report.is_ok(), "{}", report.trace());
+    }
+
+    #[test]
+    fn test_flash_crash() {
+        let report = StressScenario::flash_crash(SEED).run();
+        assert_holds(&report);
+        assert!(!report.liquidations.is_empty(), "{}", report.trace());
+        // The oracle walks down within its deviation limits
+        let last = report.blocks.last().unwrap();
+        assert_eq!(last.oracle_price, START_PRICE / 2);
+    }
+
+    #[test]
+    fn test_slow_bleed() {
+        let report = StressScenario::slow_bleed(SEED).run();
+        assert_holds(&report);
+        assert_eq!(report.blocks.last().unwrap().oracle_price, START_PRICE / 10 * 7);
+    }
+
+    #[test]
+    fn test_oracle_outage_rejects_stale_actions() {
+        let report = StressScenario::oracle_outage(SEED).run();
+        assert_holds(&report);
+        assert!(report.skips_with("E030_ORACLE_STALE") > 0, "{}", report.trace());
+    }
+
+    #[test]
+    fn test_liquidation_cascade_drains_thin_pool() {
+        let report = StressScenario::liquidation_cascade(SEED).run();
+        assert_holds(&report);
+        assert!(!report.liquidations.is_empty(), "{}", report.trace());
+        assert!(report.skips_with("E050_POOL_INSUFFICIENT") > 0, "{}", report.trace());
+    }
+
+    #[test]
+    fn test_redemption_run() {
+        let report = StressScenario::redemption_run(SEED).run();
+        assert_holds(&report);
+        assert!(!report.skips.iter().any(|skip| skip.action.starts_with("redeem")), "{}", report.trace());
+        // The lowest-ICR vaults are redeemed out and close
+        assert!(report.blocks.last().unwrap().active_vaults < 20, "{}", report.trace());
+    }
+
+    #[test]
+    fn test_same_seed_same_report() {
+        assert_eq!(StressScenario::random(SEED, 100).run(), StressScenario::random(SEED, 100).run());
+        assert_ne!(StressScenario::random(SEED, 100), StressScenario::random(SEED + 1, 100));
+    }
+
+    #[test]
+    fn test_unjustified_liquidation_is_flagged() {
+        let mut executor = Executor::new("manual", START_PRICE);
+        executor.report.liquidations.push(Liquidation { block: executor.block, owner: account(0), icr: ratios::CCR, tcr: ratios::CCR });
+        executor.end_block(START_PRICE);
+
+        assert_eq!(executor.report.violations.len(), 1);
+        assert_eq!(executor.report.violations[0].invariant, Invariant::LiquidationThreshold);
+    }
+
+    #[test]
+    #[ignore = "randomized; run with --ignored"]
+    fn test_random_scenarios() {
+        let base = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        for seed in base..base + 20 {
+            let report = StressScenario::random(seed, 300).run();
+            assert!(report.is_ok(), "seed {}\n{}", seed, report.trace());
+        }
+    }
+}
@@ -0,0 +1,633 @@
+//! Stability Pool Builders
+//!
+//! `StabilityPoolOpsBuilder` derives the expected deposit and pool state of
+//! each action. A touched deposit is re-snapshotted at the current P, S,
+//! epoch and scale with its compounded value, and any pending BTC gain is
+//! paid out in the same spell, split with the deposit's frontend if it is
+//! tagged. A swept dust deposit is closed instead.
+
+use zkusd_common::{
+    commitment::StateRef,
+    constants::stability_pool::SCALE_FACTOR,
+    errors::{ZkUsdError, ZkUsdResult},
+    events::EventLog,
+    math::{safe_add, safe_mul_div_u128, safe_sub},
+    types::{
+        Address, EpochSnapshot, OffsetSample, StabilityDeposit, StabilityPoolAction, StabilityPoolState, VaultId,
+    },
+    units::{Sats, ZkUsd},
+};
+use zkusd_stability_pool::{
+    get_compounded_value, get_pending_btc, RegisteredFrontend, StabilityPoolConfig, StabilityPoolContext,
+};
+
+use crate::Built;
+#[cfg(feature = "charms")]
+use crate::differential::{app, charm, ChainSpell, EncodedSpell};
+#[cfg(feature = "charms")]
+use zkusd_charms_compat::Data;
+#[cfg(feature = "charms")]
+use zkusd_stability_pool::charms::{validate_stability_operation_at, StabilityWitness};
+
+/// App id the pool's spells are encoded under
+#[cfg(feature = "charms")]
+const POOL_APP_ID: [u8; 32] = [0x5B; 32];
+
+/// Stability Pool operation being built
+#[derive(Debug, Clone)]
+enum PoolOp {
+    Deposit { owner: Address, amount: ZkUsd, deposit: Option<StabilityDeposit> },
+    Withdraw { deposit: StabilityDeposit, amount: ZkUsd },
+    ClaimBtc { deposit: StabilityDeposit },
+    ClaimBtcToVault { deposit: StabilityDeposit, vault_id: VaultId },
+    SweepDustDeposit { deposit: StabilityDeposit },
+    Offset { debt: ZkUsd, collateral: Sats },
+    RegisterFrontend { frontend: Address, kickback_bps: u64, payout_script: Vec<u8> },
+}
+
+/// Builder for a Stability Pool spell
+#[derive(Debug, Clone)]
+pub struct StabilityPoolOpsBuilder {
+    state: StabilityPoolState,
+    config: StabilityPoolConfig,
+    op: PoolOp,
+    frontend: Option<Address>,
+    signer: Address,
+    block_height: u64,
+}
+
+impl StabilityPoolOpsBuilder {
+    fn new(state: &StabilityPoolState, config: &StabilityPoolConfig, signer: Address, op: PoolOp) -> Self {
+        Self { state: state.clone(), config: config.clone(), op, frontend: None, signer, block_height: 0 }
+    }
+
+    /// Open a new deposit (see `topping_up` to add to an existing one)
+    pub fn deposit(state: &StabilityPoolState, config: &StabilityPoolConfig, owner: Address, amount: ZkUsd) -> Self {
+        Self::new(state, config, owner, PoolOp::Deposit { owner, amount, deposit: None })
+    }
+
+    /// Withdraw zkUSD from a deposit, claiming its BTC gain
+    pub fn withdraw(state: &StabilityPoolState, config: &StabilityPoolConfig, deposit: &StabilityDeposit, amount: ZkUsd) -> Self {
+        Self::new(state, config, deposit.owner, PoolOp::Withdraw { deposit: deposit.clone(), amount })
+    }
+
+    /// Claim a deposit's BTC gain
+    pub fn claim_btc(state: &StabilityPoolState, config: &StabilityPoolConfig, deposit: &StabilityDeposit) -> Self {
+        Self::new(state, config, deposit.owner, PoolOp::ClaimBtc { deposit: deposit.clone() })
+    }
+
+    /// Claim a deposit's BTC gain as collateral of the depositor's vault
+    ///
+    /// Pair with `VaultOpsBuilder::add_collateral(..).funded_by_btc_claim(..)`
+    /// in the same spell; the gain goes to the VaultManager, not the user.
+    pub fn claim_btc_to_vault(
+        state: &StabilityPoolState,
+        config: &StabilityPoolConfig,
+        deposit: &StabilityDeposit,
+        vault_id: VaultId,
+    ) -> Self {
+        Self::new(state, config, deposit.owner, PoolOp::ClaimBtcToVault { deposit: deposit.clone(), vault_id })
+    }
+
+    /// Close a deposit worn down to dust, paying its BTC gain to the
+    /// depositor; any keeper may sign once no gain is left, the depositor
+    /// otherwise
+    pub fn sweep_dust_deposit(
+        state: &StabilityPoolState,
+        config: &StabilityPoolConfig,
+        keeper: Address,
+        deposit: &StabilityDeposit,
+    ) -> Self {
+        Self::new(state, config, keeper, PoolOp::SweepDustDeposit { deposit: deposit.clone() })
+    }
+
+    /// Offset liquidated debt against the pool, called by the VaultManager
+    pub fn offset(state: &StabilityPoolState, config: &StabilityPoolConfig, debt: ZkUsd, collateral: Sats) -> Self {
+        Self::new(state, config, config.admin, PoolOp::Offset { debt, collateral })
+    }
+
+    /// Register a frontend deposits may be tagged with, paid to
+    /// `payout_script`, signed by the admin
+    pub fn register_frontend(
+        state: &StabilityPoolState,
+        config: &StabilityPoolConfig,
+        frontend: Address,
+        kickback_bps: u64,
+        payout_script: Vec<u8>,
+    ) -> Self {
+        Self::new(state, config, config.admin, PoolOp::RegisterFrontend { frontend, kickback_bps, payout_script })
+    }
+
+    /// Tag a new deposit with a registered frontend, at its registered
+    /// kickback rate
+    pub fn through_frontend(mut self, frontend: Address) -> Self {
+        self.frontend = Some(frontend);
+        self
+    }
+
+    /// Existing deposit a Deposit adds to
+    pub fn topping_up(mut self, existing: &StabilityDeposit) -> Self {
+        if let PoolOp::Deposit { deposit, .. } = &mut self.op {
+            *deposit = Some(existing.clone());
+        }
+        self
+    }
+
+    /// Sign with another key
+    pub fn signed_by(mut self, signer: Address) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Block the spell executes at
+    pub fn at_block(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
+    }
+
+    /// Derive the action and the expected deposit and pool state
+    pub fn build(self) -> ZkUsdResult<Built<StabilityPoolContext>> {
+        let mut ctx = StabilityPoolContext {
+            state: self.state.clone(),
+            new_state: self.state.clone(),
+            config: self.config.clone(),
+            new_config: None,
+            deposit: None,
+            new_deposit: None,
+            zkusd_inputs: ZkUsd::ZERO,
+            zkusd_outputs: ZkUsd::ZERO,
+            btc_inputs: Sats::ZERO,
+            btc_outputs: Sats::ZERO,
+            frontend_btc_outputs: Sats::ZERO,
+            caller_app_id: None,
+            signer: self.signer,
+            block_height: self.block_height,
+            based_on: Some(StateRef::new(self.state.commitment(), self.block_height)),
+            events: EventLog::new(),
+        };
+
+        let action = match self.op {
+            PoolOp::Deposit { owner, amount, deposit } => {
+                let (compounded, gain) = match &deposit {
+                    Some(deposit) => (get_compounded_value(deposit, &self.state), get_pending_btc(deposit, &self.state)?),
+                    None => (0, 0),
+                };
+                let value = safe_add(compounded, amount.into_inner())?;
+
+                ctx.new_state.total_zkusd = safe_add(self.state.total_zkusd, amount.into_inner())?;
+                if deposit.is_none() {
+                    ctx.new_state.depositor_count = safe_add(self.state.depositor_count, 1)?;
+                }
+                let new_deposit = match &deposit {
+                    Some(deposit) => resnapshot(&self.state, deposit, value, self.block_height),
+                    None => {
+                        let fresh = snapshot(&self.state, owner, value, self.block_height);
+                        match self.frontend {
+                            Some(frontend) => tagged(fresh, &self.config, frontend)?,
+                            None => fresh,
+                        }
+                    }
+                };
+                ctx.zkusd_inputs = amount;
+                ctx.btc_outputs = Sats(gain);
+                ctx.new_deposit = Some(new_deposit);
+                ctx.deposit = deposit;
+                StabilityPoolAction::Deposit { amount }
+            }
+            PoolOp::Withdraw { deposit, amount } => {
+                let remaining = safe_sub(get_compounded_value(&deposit, &self.state), amount.into_inner())?;
+
+                ctx.new_state.total_zkusd = safe_sub(self.state.total_zkusd, amount.into_inner())?;
+                if remaining == 0 {
+                    ctx.new_state.depositor_count = self.state.depositor_count.saturating_sub(1);
+                } else {
+                    ctx.new_deposit = Some(resnapshot(&self.state, &deposit, remaining, self.block_height));
+                }
+                ctx.zkusd_outputs = amount;
+                pay_out_gain(&mut ctx, &deposit, &self.state)?;
+                ctx.deposit = Some(deposit);
+                StabilityPoolAction::Withdraw { amount }
+            }
+            PoolOp::ClaimBtc { deposit } => {
+                let value = get_compounded_value(&deposit, &self.state);
+
+                pay_out_gain(&mut ctx, &deposit, &self.state)?;
+                ctx.new_deposit = Some(resnapshot(&self.state, &deposit, value, self.block_height));
+                ctx.deposit = Some(deposit);
+                StabilityPoolAction::ClaimBtc
+            }
+            PoolOp::ClaimBtcToVault { deposit, vault_id } => {
+                let value = get_compounded_value(&deposit, &self.state);
+
+                // Only the frontend's share is paid out; the rest goes to the vault
+                let gain = get_pending_btc(&deposit, &self.state)?;
+                ctx.btc_outputs = Sats(deposit.split_btc_gain(gain)?.frontend);
+                ctx.frontend_btc_outputs = ctx.btc_outputs;
+                ctx.caller_app_id = Some(self.config.vault_manager_id);
+                ctx.new_deposit = Some(resnapshot(&self.state, &deposit, value, self.block_height));
+                ctx.deposit = Some(deposit);
+                StabilityPoolAction::ClaimBtcToVault { vault_id }
+            }
+            PoolOp::SweepDustDeposit { deposit } => {
+                // The dust moves from the pool total to the protocol
+                pay_out_gain(&mut ctx, &deposit, &self.state)?;
+                let dust = get_compounded_value(&deposit, &self.state);
+                ctx.new_state.total_zkusd = self.state.total_zkusd.saturating_sub(dust);
+                ctx.new_state.protocol_dust = safe_add(self.state.protocol_dust, dust)?;
+                ctx.new_state.depositor_count = self.state.depositor_count.saturating_sub(1);
+                let depositor = deposit.owner;
+                ctx.deposit = Some(deposit);
+                StabilityPoolAction::SweepDustDeposit { depositor }
+            }
+            PoolOp::Offset { debt, collateral } => {
+                apply_offset(&mut ctx.new_state, debt.into_inner(), collateral.into_inner())?;
+                ctx.new_state.record_offset(OffsetSample {
+                    debt: debt.into_inner(),
+                    collateral: collateral.into_inner(),
+                    block: self.block_height,
+                });
+                ctx.caller_app_id = Some(self.config.vault_manager_id);
+                ctx.btc_inputs = collateral;
+                StabilityPoolAction::Offset { debt, collateral }
+            }
+            PoolOp::RegisterFrontend { frontend, kickback_bps, payout_script } => {
+                let mut new_config = self.config.clone();
+                new_config.frontends.push(RegisteredFrontend { frontend, kickback_bps, payout_script: payout_script.clone() });
+                ctx.new_config = Some(new_config);
+                StabilityPoolAction::RegisterFrontend { frontend, kickback_bps, payout_script }
+            }
+        };
+
+        Ok(Built { action, context: ctx })
+    }
+}
+
+/// Deposit worth `value`, snapshotted at the pool's current P, S, epoch and scale
+fn snapshot(state: &StabilityPoolState, owner: Address, value: u64, block_height: u64) -> StabilityDeposit {
+    StabilityDeposit {
+        owner,
+        initial_value: value,
+        snapshot_p: state.product_p,
+        snapshot_s: state.sum_s,
+        snapshot_epoch: state.current_epoch,
+        snapshot_scale: state.current_scale,
+        last_updated: block_height,
+        frontend_tag: None,
+        kickback_bps: 0,
+    }
+}
+
+/// `deposit` worth `value`, re-snapshotted at the pool's current P, S,
+/// epoch and scale; it keeps its owner and frontend tag
+fn resnapshot(state: &StabilityPoolState, deposit: &StabilityDeposit, value: u64, block_height: u64) -> StabilityDeposit {
+    StabilityDeposit {
+        frontend_tag: deposit.frontend_tag,
+        kickback_bps: deposit.kickback_bps,
+        ..snapshot(state, deposit.owner, value, block_height)
+    }
+}
+
+/// `deposit` tagged with `frontend` at its registered kickback rate
+fn tagged(deposit: StabilityDeposit, config: &StabilityPoolConfig, frontend: Address) -> ZkUsdResult<StabilityDeposit> {
+    let kickback_bps = config.kickback_bps_of(&frontend).ok_or(ZkUsdError::FrontendNotRegistered { frontend })?;
+    Ok(StabilityDeposit { frontend_tag: Some(frontend), kickback_bps, ..deposit })
+}
+
+/// Pay out `deposit`'s pending BTC gain, the frontend's share to the
+/// frontend
+fn pay_out_gain(ctx: &mut StabilityPoolContext, deposit: &StabilityDeposit, state: &StabilityPoolState) -> ZkUsdResult<()> {
+    let gain = get_pending_btc(deposit, state)?;
+    ctx.btc_outputs = Sats(gain);
+    ctx.frontend_btc_outputs = Sats(deposit.split_btc_gain(gain)?.frontend);
+    Ok(())
+}
+
+/// Apply an offset to P, S and the pool total, rolling the epoch over when
+/// the pool is emptied
+fn apply_offset(state: &mut StabilityPoolState, debt: u64, collateral: u64) -> ZkUsdResult<()> {
+    let total = state.total_zkusd as u128;
+    let debt_ratio = safe_mul_div_u128(debt as u128, SCALE_FACTOR, total)?;
+    let product_p = safe_mul_div_u128(state.product_p, SCALE_FACTOR - debt_ratio, SCALE_FACTOR)?;
+    let sum_s = state.sum_s
+        .checked_add(safe_mul_div_u128(collateral as u128, state.product_p, total)?)
+        .ok_or(ZkUsdError::Overflow)?;
+
+    state.total_zkusd = safe_sub(state.total_zkusd, debt)?;
+    state.total_btc = safe_add(state.total_btc, collateral)?;
+
+    if state.total_zkusd == 0 {
+        state.record_epoch_snapshot(EpochSnapshot {
+            epoch: state.current_epoch,
+            final_scale: state.current_scale,
+            final_s_at_scale: sum_s,
+            final_s_at_scale_plus_one: 0,
+        });
+        state.current_epoch = safe_add(state.current_epoch, 1)?;
+        state.current_scale = 0;
+        state.product_p = SCALE_FACTOR;
+        state.sum_s = 0;
+    } else {
+        state.product_p = product_p;
+        state.sum_s = sum_s;
+    }
+
+    Ok(())
+}
+
+impl Built<StabilityPoolContext> {
+    /// Action, expected deposit output and expected pool state
+    pub fn into_parts(self) -> (StabilityPoolAction, Option<StabilityDeposit>, StabilityPoolState) {
+        (self.action, self.context.new_deposit, self.context.new_state)
+    }
+}
+
+/// The deposit is spent first (the entry point reads the signer from it),
+/// then the pool state: spent when it changes, referenced otherwise. The
+/// config is referenced under the pool app, or spent and re-created when
+/// it changes, zkUSD moves as bare amounts of the configured token, a
+/// frontend's share of BTC is paid to its payout script, and the calling app
+/// runs in the same spell.
+#[cfg(feature = "charms")]
+impl ChainSpell for StabilityPoolContext {
+    fn encode(built: &Built<Self>) -> EncodedSpell {
+        let ctx = &built.context;
+        let (pool, token) = (app('n', POOL_APP_ID), app('t', ctx.config.zkusd_token_id));
+        let witness = StabilityWitness { based_on: ctx.based_on, ..StabilityWitness::for_action(&built.action) };
+
+        let mut spell = EncodedSpell::new(pool.clone(), Data::from(&witness), ctx.block_height);
+        if let Some(deposit) = &ctx.deposit {
+            spell = spell.spend(charm(&pool, Data::from(deposit)));
+        }
+        let state_charm = charm(&pool, Data::from(&ctx.state));
+        spell = if ctx.new_state == ctx.state { spell.reference(state_charm) } else { spell.spend(state_charm) };
+        let config_charm = charm(&pool, Data::from(&ctx.config));
+        spell = match &ctx.new_config {
+            Some(new_config) => spell.spend(config_charm).create(charm(&pool, Data::from(new_config))),
+            None => spell.reference(config_charm),
+        };
+        if ctx.zkusd_inputs.into_inner() > 0 {
+            spell = spell.spend(charm(&token, Data::from(&ctx.zkusd_inputs.into_inner())));
+        }
+
+        spell = spell.create(charm(&pool, Data::from(&ctx.new_state)));
+        if let Some(deposit) = &ctx.new_deposit {
+            spell = spell.create(charm(&pool, Data::from(deposit)));
+        }
+        if ctx.zkusd_outputs.into_inner() > 0 {
+            spell = spell.create(charm(&token, Data::from(&ctx.zkusd_outputs.into_inner())));
+        }
+
+        let frontend_btc = ctx.frontend_btc_outputs.into_inner();
+        spell = spell.with_coins(ctx.btc_inputs.into_inner(), ctx.btc_outputs.into_inner().saturating_sub(frontend_btc));
+        let frontend = ctx.deposit.as_ref().and_then(|deposit| deposit.frontend_tag);
+        if let Some(script) = frontend.and_then(|frontend| ctx.config.payout_script_of(&frontend)) {
+            spell = spell.paying(script, frontend_btc);
+        }
+        match ctx.caller_app_id {
+            Some(caller) => spell.with_app(app('n', caller)),
+            None => spell,
+        }
+    }
+
+    fn validate_encoded(spell: &EncodedSpell) -> bool {
+        validate_stability_operation_at(&spell.app, &spell.tx, &spell.x, &spell.w, spell.block_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate_claim_and_collateralize, verify_locally, VaultOpsBuilder};
+    use zkusd_common::types::Vault;
+    use zkusd_vault_manager::{LinkedBtcClaim, VaultContext, VaultManagerState};
+
+    const ONE_ZKUSD: u64 = 100_000_000;
+    const ONE_BTC: u64 = 100_000_000;
+    const ALICE: Address = [3u8; 32];
+    const FRONTEND: Address = [0xF1; 32];
+
+    /// P2WPKH scriptPubKey paying a 20-byte key hash of `byte`s
+    fn p2wpkh(byte: u8) -> Vec<u8> {
+        [&[0x00, 0x14][..], &[byte; 20]].concat()
+    }
+
+    fn config() -> StabilityPoolConfig {
+        StabilityPoolConfig {
+            zkusd_token_id: [1u8; 32],
+            vault_manager_id: [2u8; 32],
+            admin: [9u8; 32],
+            frontends: Vec::from([RegisteredFrontend { frontend: FRONTEND, kickback_bps: 9_000, payout_script: p2wpkh(0xF1) }]),
+        }
+    }
+
+    /// Pool holding Alice's 10k zkUSD, before and after a 2k offset
+    fn pool() -> (StabilityPoolState, StabilityPoolState, StabilityDeposit) {
+        let deposited = StabilityPoolOpsBuilder::deposit(&StabilityPoolState::new(), &config(), ALICE, ZkUsd(10_000 * ONE_ZKUSD))
+            .build()
+            .unwrap();
+        let (_, deposit, before) = deposited.into_parts();
+        let (_, _, after) = StabilityPoolOpsBuilder::offset(&before, &config(), ZkUsd(2_000 * ONE_ZKUSD), Sats(ONE_BTC / 40))
+            .at_block(10)
+            .build()
+            .unwrap()
+            .into_parts();
+        (before, after, deposit.unwrap())
+    }
+
+    fn every_action() -> Vec<(&'static str, Built<StabilityPoolContext>)> {
+        let (before, after, deposit) = pool();
+        let config = config();
+        let build = |builder: StabilityPoolOpsBuilder| builder.at_block(20).build().expect("builder should succeed");
+        let (_, _, dusted) = StabilityPoolOpsBuilder::offset(&after, &config, ZkUsd(after.total_zkusd - ONE_ZKUSD / 10), Sats(ONE_BTC))
+            .at_block(15)
+            .build()
+            .unwrap()
+            .into_parts();
+        let tagged = StabilityDeposit { frontend_tag: Some(FRONTEND), kickback_bps: 9_000, ..deposit.clone() };
+
+        Vec::from([
+            ("deposit", build(StabilityPoolOpsBuilder::deposit(&before, &config, [4u8; 32], ZkUsd(1_000 * ONE_ZKUSD)))),
+            ("top_up", build(StabilityPoolOpsBuilder::deposit(&after, &config, ALICE, ZkUsd(ONE_ZKUSD)).topping_up(&deposit))),
+            ("withdraw", build(StabilityPoolOpsBuilder::withdraw(&after, &config, &deposit, ZkUsd(1_000 * ONE_ZKUSD)))),
+            ("claim_btc", build(StabilityPoolOpsBuilder::claim_btc(&after, &config, &deposit))),
+            ("claim_btc_to_vault", build(StabilityPoolOpsBuilder::claim_btc_to_vault(&after, &config, &deposit, [7u8; 32]))),
+            ("sweep_dust_deposit", build(StabilityPoolOpsBuilder::sweep_dust_deposit(&dusted, &config, deposit.owner, &deposit))),
+            ("offset", build(StabilityPoolOpsBuilder::offset(&after, &config, ZkUsd(1_000 * ONE_ZKUSD), Sats(ONE_BTC / 80)))),
+            ("offset_emptying", build(StabilityPoolOpsBuilder::offset(&after, &config, ZkUsd(after.total_zkusd), Sats(ONE_BTC)))),
+            (
+                "tagged_deposit",
+                build(StabilityPoolOpsBuilder::deposit(&before, &config, [4u8; 32], ZkUsd(1_000 * ONE_ZKUSD)).through_frontend(FRONTEND)),
+            ),
+            ("tagged_withdraw", build(StabilityPoolOpsBuilder::withdraw(&after, &config, &tagged, ZkUsd(1_000 * ONE_ZKUSD)))),
+            ("tagged_claim_btc", build(StabilityPoolOpsBuilder::claim_btc(&after, &config, &tagged))),
+            (
+                "register_frontend",
+                build(StabilityPoolOpsBuilder::register_frontend(&before, &config, [0xF2; 32], 8_000, p2wpkh(0xF2))),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_every_builder_validates() {
+        for (name, built) in every_action() {
+            assert_eq!(verify_locally(&built), Ok(()), "{} should validate", name);
+        }
+    }
+
+    #[test]
+    fn test_tagged_gain_paid_to_frontend() {
+        let (_, after, deposit) = pool();
+        let tagged = StabilityDeposit { frontend_tag: Some(FRONTEND), kickback_bps: 9_000, ..deposit };
+        let built = StabilityPoolOpsBuilder::claim_btc(&after, &config(), &tagged).build().unwrap();
+
+        // 10% of the gain to the frontend, and the tag carried forward
+        let gain = ONE_BTC / 40;
+        assert_eq!(built.context.btc_outputs, Sats(gain));
+        assert_eq!(built.context.frontend_btc_outputs, Sats(gain / 10));
+        assert_eq!(built.context.new_deposit.unwrap().frontend_tag, Some(FRONTEND));
+
+        // Only registered frontends can be chosen
+        let unregistered = StabilityPoolOpsBuilder::deposit(&after, &config(), ALICE, ZkUsd(ONE_ZKUSD)).through_frontend([0xF3; 32]);
+        assert_eq!(unregistered.build().err(), Some(ZkUsdError::FrontendNotRegistered { frontend: [0xF3; 32] }));
+    }
+
+    #[test]
+    fn test_offset_parts() {
+        let (_, after, deposit) = pool();
+
+        assert_eq!(after.total_zkusd, 8_000 * ONE_ZKUSD);
+        assert_eq!(after.recent_offsets.len(), 1);
+        assert_eq!(get_compounded_value(&deposit, &after), 8_000 * ONE_ZKUSD);
+        assert_eq!(get_pending_btc(&deposit, &after), Ok(ONE_BTC / 40));
+    }
+
+    type Mutation = fn(&mut Built<StabilityPoolContext>);
+
+    /// Changes to a built spell, each of which must fail validation
+    fn mutations() -> [(&'static str, Mutation); 12] {
+        [
+            ("deposit", |b| b.context.new_deposit.as_mut().unwrap().initial_value += 1),
+            ("top_up", |b| b.context.new_state.total_zkusd += 1),
+            ("withdraw", |b| b.context.btc_outputs = Sats(b.context.btc_outputs.0 + 1)),
+            ("claim_btc", |b| b.context.new_deposit.as_mut().unwrap().snapshot_s = 0),
+            ("claim_btc_to_vault", |b| b.context.caller_app_id = None),
+            ("sweep_dust_deposit", |b| b.context.new_state.depositor_count += 1),
+            ("offset", |b| b.context.new_state.product_p += 1),
+            ("offset_emptying", |b| b.context.new_state.current_epoch += 1),
+            ("tagged_deposit", |b| b.context.new_deposit.as_mut().unwrap().kickback_bps = 10_000),
+            ("tagged_withdraw", |b| b.context.new_deposit.as_mut().unwrap().frontend_tag = None),
+            ("tagged_claim_btc", |b| b.context.frontend_btc_outputs = Sats(b.context.frontend_btc_outputs.0 + 1)),
+            ("register_frontend", |b| b.context.new_config.as_mut().unwrap().frontends.clear()),
+        ]
+    }
+
+    #[test]
+    fn test_mutated_fields_fail_validation() {
+        let built = every_action();
+        for (name, mutate) in mutations() {
+            let (_, original) = built.iter().find(|(n, _)| *n == name).unwrap();
+            let mut mutated = original.clone();
+            mutate(&mut mutated);
+            assert!(verify_locally(&mutated).is_err(), "mutated {} should fail", name);
+        }
+    }
+
+    /// Alice's BTC gain claimed into her vault: the pool spell and the
+    /// vault spell of one transaction, each validated by its contract
+    fn claim_into_vault(vault_owner: Address, added: Sats) -> (Built<StabilityPoolContext>, Built<VaultContext>) {
+        let (_, after, deposit) = pool();
+        let gain = get_pending_btc(&deposit, &after).unwrap();
+        let state = VaultManagerState::new([9u8; 32], [1u8; 32], [5u8; 32], [6u8; 32], [7u8; 32], [8u8; 32])
+            .expect("fixture addresses are non-zero");
+        let vault = Vault::new([7u8; 32], vault_owner, ONE_BTC, 10_000 * ONE_ZKUSD, 0);
+
+        let claim = StabilityPoolOpsBuilder::claim_btc_to_vault(&after, &config(), &deposit, vault.id)
+            .at_block(20)
+            .build()
+            .unwrap();
+        let add = VaultOpsBuilder::add_collateral(&state, &vault, added)
+            .funded_by_btc_claim(LinkedBtcClaim { depositor: deposit.owner, amount: gain })
+            .at_price(100_000 * ONE_ZKUSD)
+            .at_block(20)
+            .build()
+            .unwrap();
+        (claim, add)
+    }
+
+    #[test]
+    fn test_claim_btc_into_vault() {
+        let (claim, add) = claim_into_vault(ALICE, Sats(ONE_BTC / 40));
+        assert_eq!(verify_locally(&claim), Ok(()));
+        assert_eq!(verify_locally(&add), Ok(()));
+
+        assert_eq!(validate_claim_and_collateralize(&claim, &add), Ok(()));
+
+        // The pool pays nothing out to the user; the vault grows by exactly
+        // the gain and the deposit's S-snapshot moves up to the pool's S
+        let gain = get_pending_btc(claim.context.deposit.as_ref().unwrap(), &claim.context.state).unwrap();
+        assert_eq!(claim.context.btc_outputs, Sats::ZERO);
+        assert_eq!(add.context.new_vault.as_ref().unwrap().collateral, ONE_BTC + gain);
+        let (old, new) = (claim.context.deposit.as_ref().unwrap(), claim.context.new_deposit.as_ref().unwrap());
+        assert!(new.snapshot_s > old.snapshot_s);
+        assert_eq!(new.snapshot_s, claim.context.state.sum_s);
+    }
+
+    #[test]
+    fn test_claim_and_collateralize_requires_owner_of_both() {
+        // The vault leg signed by someone other than the depositor, even
+        // though the vault manager would accept its operator
+        let operator = [4u8; 32];
+        let (claim, mut add) = claim_into_vault(ALICE, Sats(ONE_BTC / 40));
+        add.context.signer = operator;
+        assert_eq!(
+            validate_claim_and_collateralize(&claim, &add),
+            Err(ZkUsdError::Unauthorized { expected: ALICE, actual: operator })
+        );
+
+        // Vault owned by someone other than the depositor
+        let (claim, add) = claim_into_vault(operator, Sats(ONE_BTC / 40));
+        assert_eq!(
+            validate_claim_and_collateralize(&claim, &add),
+            Err(ZkUsdError::Unauthorized { expected: ALICE, actual: operator })
+        );
+
+        // Collateral other than the claimed gain
+        let (claim, add) = claim_into_vault(ALICE, Sats(ONE_BTC / 20));
+        assert_eq!(
+            validate_claim_and_collateralize(&claim, &add),
+            Err(ZkUsdError::ConservationViolated { inputs: ONE_BTC / 40, outputs: ONE_BTC / 20 })
+        );
+    }
+
+    #[test]
+    fn test_claim_btc_into_vault_mismatches_rejected() {
+        // Amount other than the claimed gain
+        let (_, add) = claim_into_vault(ALICE, Sats(ONE_BTC / 20));
+        assert_eq!(
+            verify_locally(&add),
+            Err(ZkUsdError::ConservationViolated { inputs: ONE_BTC / 40, outputs: ONE_BTC / 20 })
+        );
+
+        // Vault of someone other than the depositor
+        let bob = [4u8; 32];
+        let (_, add) = claim_into_vault(bob, Sats(ONE_BTC / 40));
+        assert_eq!(verify_locally(&add), Err(ZkUsdError::Unauthorized { expected: bob, actual: ALICE }));
+    }
+
+    #[cfg(feature = "charms")]
+    mod differential {
+        use super::*;
+        use crate::differential::{compare, scenarios, Exception};
+
+        const EXCEPTIONS: &[Exception] = &[Exception {
+            scenario: "register_frontend",
+            reason: "the entry point reads the signer from a spent deposit, so it never sees the admin sign",
+        }];
+
+        #[test]
+        fn test_entry_point_agrees_with_library() {
+            let divergences = compare(&scenarios(every_action(), &mutations()), EXCEPTIONS);
+            assert!(divergences.is_empty(), "{:#?}", divergences);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! zkUSD Token Builders
+//!
+//! `TokenOpsBuilder` assembles the token balances of a spell. By default a
+//! sender spends exactly the moved amount; `spending` supplies the actual
+//! input UTXOs, and any excess is returned to the sender as change.
+
+use zkusd_common::{
+    errors::{ZkUsdError, ZkUsdResult},
+    events::EventLog,
+    math::{safe_add, safe_sub},
+    types::{Address, AppId, Memo, TokenAction},
+    units::ZkUsd,
+};
+use zkusd_token::{TokenBalance, TokenContext, ZkUsdTokenState};
+
+use crate::Built;
+#[cfg(feature = "charms")]
+use crate::differential::{app, charm, ChainSpell, EncodedSpell};
+#[cfg(feature = "charms")]
+use zkusd_charms_compat::Data;
+#[cfg(feature = "charms")]
+use zkusd_token::charms::{validate_token_operation_at, TokenWitness};
+
+/// App id the token's spells are encoded under
+#[cfg(feature = "charms")]
+const TOKEN_APP_ID: AppId = [0x7A; 32];
+
+/// Token operation being built
+#[derive(Debug, Clone)]
+enum TokenOp {
+    Transfer { from: Address, to: Address, amount: ZkUsd },
+    Mint { to: Address, amount: ZkUsd },
+    Burn { from: Address, amount: ZkUsd },
+    MintMulti { recipients: Vec<(Address, ZkUsd)> },
+    Consolidate { owner: Address, inputs: Vec<TokenBalance> },
+}
+
+/// Builder for a zkUSD token spell
+#[derive(Debug, Clone)]
+pub struct TokenOpsBuilder {
+    state: ZkUsdTokenState,
+    op: TokenOp,
+    signer: Address,
+    inputs: Option<Vec<TokenBalance>>,
+    caller_app_id: AppId,
+    memo: Option<Memo>,
+    block_height: u64,
+}
+
+impl TokenOpsBuilder {
+    fn new(state: &ZkUsdTokenState, signer: Address, op: TokenOp) -> Self {
+        Self {
+            state: state.clone(),
+            op,
+            signer,
+            inputs: None,
+            caller_app_id: state.authorized_minter,
+            memo: None,
+            block_height: 0,
+        }
+    }
+
+    /// Transfer `amount` from `from` to `to`
+    pub fn transfer(state: &ZkUsdTokenState, from: Address, to: Address, amount: ZkUsd) -> Self {
+        Self::new(state, from, TokenOp::Transfer { from, to, amount })
+    }
+
+    /// Mint `amount` to `to`, called by the authorized minter
+    pub fn mint(state: &ZkUsdTokenState, to: Address, amount: ZkUsd) -> Self {
+        Self::new(state, to, TokenOp::Mint { to, amount })
+    }
+
+    /// Burn `amount` held by `from`, called by the authorized minter
+    pub fn burn(state: &ZkUsdTokenState, from: Address, amount: ZkUsd) -> Self {
+        Self::new(state, from, TokenOp::Burn { from, amount })
+    }
+
+    /// Mint to several recipients, called by the authorized minter
+    pub fn mint_multi(state: &ZkUsdTokenState, recipients: Vec<(Address, ZkUsd)>) -> Self {
+        Self::new(state, state.admin, TokenOp::MintMulti { recipients })
+    }
+
+    /// Merge `inputs`, all held by `owner`, into one balance of their total
+    pub fn consolidate(state: &ZkUsdTokenState, owner: Address, inputs: Vec<TokenBalance>) -> Self {
+        Self::new(state, owner, TokenOp::Consolidate { owner, inputs })
+    }
+
+    /// Input balances actually spent (transfer and burn)
+    pub fn spending(mut self, inputs: Vec<TokenBalance>) -> Self {
+        self.inputs = Some(inputs);
+        self
+    }
+
+    /// App calling mint or burn (defaults to the authorized minter)
+    pub fn called_by(mut self, caller_app_id: AppId) -> Self {
+        self.caller_app_id = caller_app_id;
+        self
+    }
+
+    /// Tag a transfer with a memo (see `token_ops::memo_hash`)
+    pub fn with_memo(mut self, memo: Memo) -> Self {
+        self.memo = Some(memo);
+        self
+    }
+
+    /// Sign with another key
+    pub fn signed_by(mut self, signer: Address) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Block the spell executes at
+    pub fn at_block(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
+    }
+
+    /// Derive the action, balances and expected token state
+    pub fn build(self) -> ZkUsdResult<Built<TokenContext>> {
+        if self.memo.is_some() && !matches!(self.op, TokenOp::Transfer { .. }) {
+            return Err(ZkUsdError::InvalidInput { param: "memo", reason: "Only transfers carry a memo" });
+        }
+
+        let mut ctx = TokenContext {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            token_state: self.state.clone(),
+            new_token_state: self.state.clone(),
+            caller_app_id: None,
+            minter_amount: None,
+            signer: self.signer,
+            block_height: self.block_height,
+            events: EventLog::new(),
+        };
+
+        let action = match self.op {
+            TokenOp::Transfer { from, to, amount } => {
+                let inputs = self.inputs.unwrap_or_else(|| Vec::from([TokenBalance::new(from, amount.into_inner())]));
+                ctx.outputs = Vec::from([TokenBalance::new(to, amount.into_inner())]);
+                push_change(&mut ctx.outputs, &inputs, from, amount.into_inner())?;
+                ctx.inputs = inputs;
+                TokenAction::Transfer { from, to, amount, memo: self.memo }
+            }
+            TokenOp::Mint { to, amount } => {
+                ctx.caller_app_id = Some(self.caller_app_id);
+                ctx.minter_amount = Some(amount.into_inner());
+                ctx.outputs = Vec::from([TokenBalance::new(to, amount.into_inner())]);
+                ctx.new_token_state.total_supply = safe_add(self.state.total_supply, amount.into_inner())?;
+                TokenAction::Mint { to, amount }
+            }
+            TokenOp::Burn { from, amount } => {
+                let inputs = self.inputs.unwrap_or_else(|| Vec::from([TokenBalance::new(from, amount.into_inner())]));
+                ctx.caller_app_id = Some(self.caller_app_id);
+                push_change(&mut ctx.outputs, &inputs, from, amount.into_inner())?;
+                ctx.inputs = inputs;
+                ctx.new_token_state.total_supply = safe_sub(self.state.total_supply, amount.into_inner())?;
+                TokenAction::Burn { from, amount }
+            }
+            TokenOp::MintMulti { recipients } => {
+                let total = recipients
+                    .iter()
+                    .try_fold(0u64, |total, (_, amount)| safe_add(total, amount.into_inner()))?;
+                ctx.caller_app_id = Some(self.caller_app_id);
+                ctx.minter_amount = Some(total);
+                ctx.outputs = recipients
+                    .iter()
+                    .map(|(to, amount)| TokenBalance::new(*to, amount.into_inner()))
+                    .collect();
+                ctx.new_token_state.total_supply = safe_add(self.state.total_supply, total)?;
+                TokenAction::MintMulti { recipients }
+            }
+            TokenOp::Consolidate { owner, inputs } => {
+                let total = inputs.iter().try_fold(0u64, |total, input| safe_add(total, input.amount))?;
+                ctx.outputs = Vec::from([TokenBalance::new(owner, total)]);
+                ctx.inputs = inputs;
+                TokenAction::Consolidate { owner }
+            }
+        };
+
+        Ok(Built { action, context: ctx })
+    }
+}
+
+/// Return everything the inputs carry beyond `spent` to `owner`
+fn push_change(outputs: &mut Vec<TokenBalance>, inputs: &[TokenBalance], owner: Address, spent: u64) -> ZkUsdResult<()> {
+    let total = inputs.iter().try_fold(0u64, |total, input| safe_add(total, input.amount))?;
+    let change = total.checked_sub(spent).ok_or(ZkUsdError::InsufficientBalance {
+        available: total,
+        requested: spent,
+    })?;
+    if change > 0 {
+        outputs.push(TokenBalance::new(owner, change));
+    }
+    Ok(())
+}
+
+impl Built<TokenContext> {
+    /// Action, output balances and expected token state
+    pub fn into_parts(self) -> (TokenAction, Vec<TokenBalance>, ZkUsdTokenState) {
+        (self.action, self.context.outputs, self.context.new_token_state)
+    }
+}
+
+/// Balances are spent first (the entry point reads the signer from the
+/// first spent balance), then the token state: spent when its supply
+/// changes, referenced otherwise. The calling app runs in the same spell.
+#[cfg(feature = "charms")]
+impl ChainSpell for TokenContext {
+    fn encode(built: &Built<Self>) -> EncodedSpell {
+        let ctx = &built.context;
+        let (state, balance) = (app('n', TOKEN_APP_ID), app('t', TOKEN_APP_ID));
+        let w = Data::from(&TokenWitness::for_action(&built.action));
+
+        let mut spell = EncodedSpell::new(state.clone(), w, ctx.block_height);
+        for input in &ctx.inputs {
+            spell = spell.spend(charm(&balance, Data::from(input)));
+        }
+        let state_charm = charm(&state, Data::from(&ctx.token_state));
+        spell = if ctx.new_token_state == ctx.token_state {
+            spell.reference(state_charm)
+        } else {
+            spell.spend(state_charm)
+        };
+        spell = spell.create(charm(&state, Data::from(&ctx.new_token_state)));
+        for output in &ctx.outputs {
+            spell = spell.create(charm(&balance, Data::from(output)));
+        }
+        match ctx.caller_app_id {
+            Some(caller) => spell.with_app(app('n', caller)),
+            None => spell,
+        }
+    }
+
+    fn validate_encoded(spell: &EncodedSpell) -> bool {
+        validate_token_operation_at(&spell.app, &spell.tx, &spell.x, &spell.w, spell.block_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_locally;
+
+    const ADMIN: Address = [9u8; 32];
+    const VAULT_MANAGER: AppId = [1u8; 32];
+    const ALICE: Address = [2u8; 32];
+    const BOB: Address = [3u8; 32];
+
+    fn state() -> ZkUsdTokenState {
+        ZkUsdTokenState { total_supply: 10_000, ..ZkUsdTokenState::with_minter(ADMIN, VAULT_MANAGER) }
+    }
+
+    fn every_action() -> Vec<(&'static str, Built<TokenContext>)> {
+        let state = state();
+        let build = |builder: TokenOpsBuilder| builder.at_block(100).build().expect("builder should succeed");
+
+        Vec::from([
+            ("transfer", build(TokenOpsBuilder::transfer(&state, ALICE, BOB, ZkUsd(600)))),
+            (
+                "transfer_with_change",
+                build(TokenOpsBuilder::transfer(&state, ALICE, BOB, ZkUsd(600)).spending(Vec::from([TokenBalance::new(ALICE, 1_000)]))),
+            ),
+            ("transfer_with_memo", build(TokenOpsBuilder::transfer(&state, ALICE, BOB, ZkUsd(600)).with_memo([7u8; 32]))),
+            ("mint", build(TokenOpsBuilder::mint(&state, ALICE, ZkUsd(500)))),
+            (
+                "burn_with_change",
+                build(TokenOpsBuilder::burn(&state, ALICE, ZkUsd(300)).spending(Vec::from([TokenBalance::new(ALICE, 1_000)]))),
+            ),
+            ("mint_multi", build(TokenOpsBuilder::mint_multi(&state, Vec::from([(ALICE, ZkUsd(100)), (BOB, ZkUsd(200))])))),
+            (
+                "consolidate",
+                build(TokenOpsBuilder::consolidate(&state, ALICE, Vec::from([100, 200, 300].map(|amount| TokenBalance::new(ALICE, amount))))),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_every_builder_validates() {
+        for (name, built) in every_action() {
+            assert_eq!(verify_locally(&built), Ok(()), "{} should validate", name);
+        }
+    }
+
+    #[test]
+    fn test_change_returns_to_sender() {
+        let state = state();
+        let built = TokenOpsBuilder::transfer(&state, ALICE, BOB, ZkUsd(600))
+            .spending(Vec::from([TokenBalance::new(ALICE, 1_000)]))
+            .build()
+            .unwrap();
+        let (_, outputs, new_state) = built.into_parts();
+
+        assert_eq!(outputs, [TokenBalance::new(BOB, 600), TokenBalance::new(ALICE, 400)]);
+        assert_eq!(new_state, state);
+    }
+
+    type Mutation = fn(&mut Built<TokenContext>);
+
+    /// Changes to a built spell, each of which must fail validation
+    fn mutations() -> [(&'static str, Mutation); 7] {
+        [
+            ("transfer_with_change", |b| b.context.outputs[1].amount += 1),
+            ("transfer", |b| b.context.signer = BOB),
+            ("mint", |b| b.context.new_token_state.total_supply += 1),
+            ("mint", |b| b.action = TokenAction::Mint { to: ALICE, amount: ZkUsd(501) }),
+            ("burn_with_change", |b| b.context.caller_app_id = Some([7u8; 32])),
+            ("mint_multi", |b| b.context.outputs[0].owner = ADMIN),
+            ("consolidate", |b| b.context.inputs[1].owner = BOB),
+        ]
+    }
+
+    #[test]
+    fn test_mutated_fields_fail_validation() {
+        let built = every_action();
+        for (name, mutate) in mutations() {
+            let (_, original) = built.iter().find(|(n, _)| *n == name).unwrap();
+            let mut mutated = original.clone();
+            mutate(&mut mutated);
+            assert!(verify_locally(&mutated).is_err(), "mutated {} should fail", name);
+        }
+    }
+
+    #[test]
+    fn test_memo_only_on_transfers() {
+        let result = TokenOpsBuilder::mint(&state(), ALICE, ZkUsd(500)).with_memo([7u8; 32]).build();
+        assert!(matches!(result, Err(ZkUsdError::InvalidInput { param: "memo", .. })));
+    }
+
+    #[test]
+    fn test_overspend_is_rejected() {
+        let result = TokenOpsBuilder::transfer(&state(), ALICE, BOB, ZkUsd(600))
+            .spending(Vec::from([TokenBalance::new(ALICE, 500)]))
+            .build();
+        assert!(matches!(result, Err(ZkUsdError::InsufficientBalance { available: 500, requested: 600 })));
+    }
+
+    #[cfg(feature = "charms")]
+    mod differential {
+        use super::*;
+        use crate::differential::{compare, scenarios, Exception};
+
+        const EXCEPTIONS: &[Exception] = &[Exception {
+            scenario: "transfer (mutation 1)",
+            reason: "entry point reads the owner of the first spent balance as the signer",
+        }];
+
+        #[test]
+        fn test_entry_point_agrees_with_library() {
+            let divergences = compare(&scenarios(every_action(), &mutations()), EXCEPTIONS);
+            assert!(divergences.is_empty(), "{:#?}", divergences);
+        }
+    }
+}
@@ -0,0 +1,1367 @@
+//! VaultManager Builders
+//!
+//! `VaultOpsBuilder` has one constructor per `VaultAction`. Each takes the
+//! current protocol state and the vault the action operates on; the
+//! expected vault and protocol state are derived on `build`.
+//!
+//! Actions that change a vault's principal or rate (open, close, mint,
+//! repay, liquidate or begin a liquidation, set protection) and interest
+//! pokes accrue global interest to the build block, as the validator requires. The block defaults to the state's last
+//! accrual block.
+
+use zkusd_common::{
+    charms_ops::calculate_flash_fee_bps,
+    commitment::StateRef,
+    constants::{fees, limits},
+    errors::{ZkUsdError, ZkUsdResult},
+    events::EventLog,
+    interest::{min_interest_rate, normalize_vault, reconcile_vault, VariableRateCurve},
+    math::{
+        apply_depositor_discount, apply_loyalty_discount, calculate_borrowing_fee, calculate_protection_rate_bump,
+        calculate_tcr, decay_base_rate, is_recovery_mode, safe_add, safe_div, safe_mul, safe_mul_div, safe_sub,
+        zkusd_to_btc_floor,
+    },
+    types::{
+        AdjustmentWindow, Address, FeeDistribution, InsuranceCharm, OracleSnapshot, PendingLiquidation, PriceData,
+        PriceSource, ProtocolState, RateMode, Vault, VaultAction, VaultStats, VaultStatus,
+    },
+    units::{Sats, ZkUsd},
+    validation::AppliedActions,
+    vault_registry::{apply_change, flatten, split, RegistryChange, VaultRegistry},
+};
+use zkusd_vault_manager::{
+    bootstrap_after_spell, generate_vault_id, split_seized_collateral, tranche_collateral, ExpectedOutputs, LinkedBtcClaim,
+    LinkedDeposit, LinkedOffset, SpellBounds, VaultContext, VaultManagerState,
+};
+
+use crate::Built;
+#[cfg(feature = "charms")]
+use crate::differential::{app, charm, ChainSpell, EncodedSpell};
+#[cfg(feature = "charms")]
+use zkusd_charms_compat::Data;
+#[cfg(feature = "charms")]
+use zkusd_common::types::{StabilityDeposit, StabilityPoolState};
+#[cfg(feature = "charms")]
+use zkusd_price_oracle::OracleState;
+#[cfg(feature = "charms")]
+use zkusd_vault_manager::charms::{validate_vault_operation_at, VaultWitness};
+
+/// App id the Vault Manager's spells are encoded under
+#[cfg(feature = "charms")]
+const VM_APP_ID: [u8; 32] = [0xE5; 32];
+
+/// Vault operation being built
+#[derive(Debug, Clone)]
+enum VaultOp {
+    Open { owner: Address, collateral: Sats, debt: ZkUsd },
+    Close { vault: Vault },
+    AddCollateral { vault: Vault, amount: Sats },
+    WithdrawCollateral { vault: Vault, amount: Sats },
+    MintDebt { vault: Vault, amount: ZkUsd },
+    RepayDebt { vault: Vault, amount: ZkUsd },
+    Liquidate { vault: Vault },
+    Redeem { vault: Option<Vault>, amount: ZkUsd, min_btc_out: Sats },
+    FlashMint { amount: ZkUsd, purpose: u8 },
+    AtomicRescue { vault: Vault, collateral_to_add: Sats, debt_to_repay: ZkUsd, rescuer_discount: Sats },
+    PurchaseInsurance { vault: Vault, coverage_btc: Sats, premium: ZkUsd, trigger_icr: u64 },
+    TriggerInsurance { vault: Vault, insurance_id: [u8; 32] },
+    TransferInsurance { vault: Vault, insurance_id: [u8; 32], new_owner: Address },
+    ExpireInsurance { vault: Vault, insurance_id: [u8; 32] },
+    SetFlashFee { fee_bps: u64 },
+    SetFeeDistribution { distribution: FeeDistribution },
+    SetVaultOperator { vault: Vault, operator: Option<Address> },
+    PokeBaseRate,
+    SetProtection { vault: Vault, bps: u64 },
+    SetBeneficiary { vault: Vault, beneficiary: Option<(Address, u64)> },
+    ClaimAsBeneficiary { vault: Vault },
+    BeginLiquidation { vault: Vault },
+    ContinueLiquidation { vault: Vault, debt_portion: ZkUsd },
+    GraduateBootstrap,
+    AccrueInterest { vault: Vault },
+}
+
+impl VaultOp {
+    /// Vault the operation spends, if any
+    fn vault_mut(&mut self) -> Option<&mut Vault> {
+        match self {
+            VaultOp::Close { vault }
+            | VaultOp::AddCollateral { vault, .. }
+            | VaultOp::WithdrawCollateral { vault, .. }
+            | VaultOp::MintDebt { vault, .. }
+            | VaultOp::RepayDebt { vault, .. }
+            | VaultOp::Liquidate { vault }
+            | VaultOp::AtomicRescue { vault, .. }
+            | VaultOp::PurchaseInsurance { vault, .. }
+            | VaultOp::TriggerInsurance { vault, .. }
+            | VaultOp::TransferInsurance { vault, .. }
+            | VaultOp::ExpireInsurance { vault, .. }
+            | VaultOp::SetVaultOperator { vault, .. }
+            | VaultOp::SetProtection { vault, .. }
+            | VaultOp::SetBeneficiary { vault, .. }
+            | VaultOp::ClaimAsBeneficiary { vault }
+            | VaultOp::BeginLiquidation { vault }
+            | VaultOp::ContinueLiquidation { vault, .. }
+            | VaultOp::AccrueInterest { vault } => Some(vault),
+            VaultOp::Redeem { vault, .. } => vault.as_mut(),
+            VaultOp::Open { .. }
+            | VaultOp::FlashMint { .. }
+            | VaultOp::SetFlashFee { .. }
+            | VaultOp::SetFeeDistribution { .. }
+            | VaultOp::PokeBaseRate
+            | VaultOp::GraduateBootstrap => None,
+        }
+    }
+}
+
+/// Builder for a VaultManager spell
+#[derive(Debug, Clone)]
+pub struct VaultOpsBuilder {
+    state: VaultManagerState,
+    op: VaultOp,
+    signer: Address,
+    btc_price: Option<u64>,
+    price_confidence: u8,
+    oracle: Option<OracleSnapshot>,
+    block_height: u64,
+    bounds: SpellBounds,
+    registry: Vec<VaultRegistry>,
+    insurance: Option<InsuranceCharm>,
+    nonce: u64,
+    linked_btc_claim: Option<LinkedBtcClaim>,
+    linked_deposit: Option<LinkedDeposit>,
+    pool_zkusd: Option<ZkUsd>,
+    rate_mode: RateMode,
+    whitelist_proof: Vec<[u8; 32]>,
+}
+
+impl VaultOpsBuilder {
+    fn new(state: &VaultManagerState, signer: Address, op: VaultOp) -> Self {
+        Self {
+            state: state.clone(),
+            op,
+            signer,
+            btc_price: None,
+            price_confidence: 100,
+            oracle: None,
+            block_height: state.protocol.last_interest_accrual_block,
+            bounds: SpellBounds::default(),
+            registry: Vec::new(),
+            insurance: None,
+            nonce: 0,
+            linked_btc_claim: None,
+            linked_deposit: None,
+            pool_zkusd: None,
+            rate_mode: RateMode::Fixed,
+            whitelist_proof: Vec::new(),
+        }
+    }
+
+    // ============ Vault Lifecycle ============
+
+    /// Open a vault borrowing `debt` (the liquidation reserve is added)
+    pub fn open_vault(state: &VaultManagerState, owner: Address, collateral: Sats, debt: ZkUsd) -> Self {
+        Self::new(state, owner, VaultOp::Open { owner, collateral, debt })
+    }
+
+    /// Close a vault, repaying its full debt
+    pub fn close_vault(state: &VaultManagerState, vault: &Vault) -> Self {
+        Self::new(state, vault.owner, VaultOp::Close { vault: vault.clone() })
+    }
+
+    /// Add collateral to a vault
+    pub fn add_collateral(state: &VaultManagerState, vault: &Vault, amount: Sats) -> Self {
+        Self::new(state, vault.owner, VaultOp::AddCollateral { vault: vault.clone(), amount })
+    }
+
+    /// Withdraw collateral from a vault
+    pub fn withdraw_collateral(state: &VaultManagerState, vault: &Vault, amount: Sats) -> Self {
+        Self::new(state, vault.owner, VaultOp::WithdrawCollateral { vault: vault.clone(), amount })
+    }
+
+    /// Mint more debt against a vault
+    pub fn mint_debt(state: &VaultManagerState, vault: &Vault, amount: ZkUsd) -> Self {
+        Self::new(state, vault.owner, VaultOp::MintDebt { vault: vault.clone(), amount })
+    }
+
+    /// Repay part of a vault's debt
+    pub fn repay_debt(state: &VaultManagerState, vault: &Vault, amount: ZkUsd) -> Self {
+        Self::new(state, vault.owner, VaultOp::RepayDebt { vault: vault.clone(), amount })
+    }
+
+    /// Liquidate an undercollateralized vault
+    pub fn liquidate(state: &VaultManagerState, liquidator: Address, vault: &Vault) -> Self {
+        Self::new(state, liquidator, VaultOp::Liquidate { vault: vault.clone() })
+    }
+
+    /// Begin liquidating a vault too large for `liquidate`, freezing it at
+    /// the build price
+    pub fn begin_liquidation(state: &VaultManagerState, liquidator: Address, vault: &Vault) -> Self {
+        Self::new(state, liquidator, VaultOp::BeginLiquidation { vault: vault.clone() })
+    }
+
+    /// Offset the next tranche of a liquidating vault's debt against the
+    /// stability pool
+    pub fn continue_liquidation(
+        state: &VaultManagerState,
+        liquidator: Address,
+        vault: &Vault,
+        debt_portion: ZkUsd,
+    ) -> Self {
+        Self::new(state, liquidator, VaultOp::ContinueLiquidation { vault: vault.clone(), debt_portion })
+    }
+
+    /// Redeem zkUSD for BTC, optionally against a vault (see `against`)
+    pub fn redeem(state: &VaultManagerState, redeemer: Address, amount: ZkUsd, min_btc_out: Sats) -> Self {
+        Self::new(state, redeemer, VaultOp::Redeem { vault: None, amount, min_btc_out })
+    }
+
+    // ============ Advanced Operations ============
+
+    /// Flash mint `amount`; the minter pays the fee from zkUSD it holds
+    pub fn flash_mint(state: &VaultManagerState, minter: Address, amount: ZkUsd, purpose: u8) -> Self {
+        Self::new(state, minter, VaultOp::FlashMint { amount, purpose })
+    }
+
+    /// Rescue a distressed vault as a third party
+    pub fn atomic_rescue(
+        state: &VaultManagerState,
+        rescuer: Address,
+        vault: &Vault,
+        collateral_to_add: Sats,
+        debt_to_repay: ZkUsd,
+        rescuer_discount: Sats,
+    ) -> Self {
+        let op = VaultOp::AtomicRescue { vault: vault.clone(), collateral_to_add, debt_to_repay, rescuer_discount };
+        Self::new(state, rescuer, op)
+    }
+
+    /// Buy insurance coverage for a vault
+    pub fn purchase_insurance(
+        state: &VaultManagerState,
+        vault: &Vault,
+        coverage_btc: Sats,
+        premium: ZkUsd,
+        trigger_icr: u64,
+    ) -> Self {
+        let op = VaultOp::PurchaseInsurance { vault: vault.clone(), coverage_btc, premium, trigger_icr };
+        Self::new(state, vault.owner, op)
+    }
+
+    /// Trigger a vault's insurance
+    ///
+    /// Without `with_insurance_charm` the whole insurance balance is drawn.
+    pub fn trigger_insurance(state: &VaultManagerState, caller: Address, vault: &Vault, insurance_id: [u8; 32]) -> Self {
+        Self::new(state, caller, VaultOp::TriggerInsurance { vault: vault.clone(), insurance_id })
+    }
+
+    /// Transfer an insurance charm with its vault
+    pub fn transfer_insurance(
+        state: &VaultManagerState,
+        vault: &Vault,
+        insurance_id: [u8; 32],
+        new_owner: Address,
+    ) -> Self {
+        let op = VaultOp::TransferInsurance { vault: vault.clone(), insurance_id, new_owner };
+        Self::new(state, vault.owner, op)
+    }
+
+    /// Retire an expired insurance charm, freeing its coverage
+    ///
+    /// Needs the charm, set with `with_insurance_charm`.
+    pub fn expire_insurance(state: &VaultManagerState, caller: Address, vault: &Vault, insurance_id: [u8; 32]) -> Self {
+        Self::new(state, caller, VaultOp::ExpireInsurance { vault: vault.clone(), insurance_id })
+    }
+
+    // ============ Admin and Key Management ============
+
+    /// Set the flash mint fee (signed by the admin)
+    pub fn set_flash_fee(state: &VaultManagerState, fee_bps: u64) -> Self {
+        Self::new(state, state.protocol.admin, VaultOp::SetFlashFee { fee_bps })
+    }
+
+    /// Set the borrowing fee split (signed by the admin)
+    pub fn set_fee_distribution(state: &VaultManagerState, distribution: FeeDistribution) -> Self {
+        Self::new(state, state.protocol.admin, VaultOp::SetFeeDistribution { distribution })
+    }
+
+    /// Set or clear a vault's operator key
+    pub fn set_vault_operator(state: &VaultManagerState, vault: &Vault, operator: Option<Address>) -> Self {
+        Self::new(state, vault.owner, VaultOp::SetVaultOperator { vault: vault.clone(), operator })
+    }
+
+    /// Apply the base rate decay (permissionless; `caller` earns the
+    /// incentive if the rate moves)
+    pub fn poke_base_rate(state: &VaultManagerState, caller: Address) -> Self {
+        Self::new(state, caller, VaultOp::PokeBaseRate)
+    }
+
+    /// Fold a vault's interest into its debt (permissionless; a `caller`
+    /// other than the owner earns the keeper bounty)
+    pub fn accrue_interest(state: &VaultManagerState, caller: Address, vault: &Vault) -> Self {
+        Self::new(state, caller, VaultOp::AccrueInterest { vault: vault.clone() })
+    }
+
+    /// Raise the share of a vault's collateral protected from redemption
+    pub fn set_protection(state: &VaultManagerState, vault: &Vault, bps: u64) -> Self {
+        Self::new(state, vault.owner, VaultOp::SetProtection { vault: vault.clone(), bps })
+    }
+
+    /// Designate a vault's beneficiary as `(key, inactivity_blocks)`, or
+    /// clear it with `None`
+    pub fn set_beneficiary(state: &VaultManagerState, vault: &Vault, beneficiary: Option<(Address, u64)>) -> Self {
+        Self::new(state, vault.owner, VaultOp::SetBeneficiary { vault: vault.clone(), beneficiary })
+    }
+
+    /// Take over an inactive vault (signed by its designated beneficiary)
+    pub fn claim_as_beneficiary(state: &VaultManagerState, vault: &Vault) -> Self {
+        let signer = vault.beneficiary.map_or(vault.owner, |(beneficiary, _)| beneficiary);
+        Self::new(state, signer, VaultOp::ClaimAsBeneficiary { vault: vault.clone() })
+    }
+
+    // ============ Bootstrap ============
+
+    /// Graduate the protocol from bootstrap mode (permissionless)
+    ///
+    /// Needs the stability pool the criteria read, set with
+    /// `with_stability_pool`.
+    pub fn graduate_bootstrap(state: &VaultManagerState, caller: Address) -> Self {
+        Self::new(state, caller, VaultOp::GraduateBootstrap)
+    }
+
+    // ============ Options ============
+
+    /// Vault a redemption is applied to (Redeem)
+    pub fn against(mut self, vault: &Vault) -> Self {
+        if let VaultOp::Redeem { vault: target, .. } = &mut self.op {
+            *target = Some(vault.clone());
+        }
+        self
+    }
+
+    /// BTC price the spell executes at (required)
+    pub fn at_price(mut self, btc_price: u64) -> Self {
+        self.btc_price = Some(btc_price);
+        self
+    }
+
+    /// Block the spell executes at
+    pub fn at_block(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
+    }
+
+    /// Sign with another key (e.g. a vault operator)
+    pub fn signed_by(mut self, signer: Address) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Oracle confidence at execution (defaults to 100)
+    pub fn with_confidence(mut self, price_confidence: u8) -> Self {
+        self.price_confidence = price_confidence;
+        self
+    }
+
+    /// Oracle state the spell reads, in place of a fresh price from
+    /// `at_price` and `with_confidence`
+    pub fn with_oracle(mut self, oracle: OracleSnapshot) -> Self {
+        self.oracle = Some(oracle);
+        self
+    }
+
+    /// Expiry and price bounds of the spell
+    pub fn with_bounds(mut self, bounds: SpellBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Every shard of the vault registry, when the registry is in use
+    pub fn with_registry(mut self, shards: Vec<VaultRegistry>) -> Self {
+        self.registry = shards;
+        self
+    }
+
+    /// Insurance charm to trigger (staged payout) or retire
+    pub fn with_insurance_charm(mut self, charm: InsuranceCharm) -> Self {
+        self.insurance = Some(charm);
+        self
+    }
+
+    /// Nonce for the new vault's ID (OpenVault)
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Rate mode of the new vault (OpenVault, defaults to fixed)
+    pub fn with_rate_mode(mut self, rate_mode: RateMode) -> Self {
+        self.rate_mode = rate_mode;
+        self
+    }
+
+    /// Proof of the signer's bootstrap whitelist membership (OpenVault
+    /// during bootstrap; see `zkusd_common::bootstrap::whitelist_proof`)
+    pub fn with_whitelist_proof(mut self, proof: Vec<[u8; 32]>) -> Self {
+        self.whitelist_proof = proof;
+        self
+    }
+
+    /// Stability pool gain claimed into the vault in the same spell
+    /// (AddCollateral; see `StabilityPoolOpsBuilder::claim_btc_to_vault`)
+    pub fn funded_by_btc_claim(mut self, claim: LinkedBtcClaim) -> Self {
+        self.linked_btc_claim = Some(claim);
+        self
+    }
+
+    /// Stability deposit referenced by the spell, earning the borrower the
+    /// depositor fee discount (OpenVault, MintDebt)
+    pub fn with_linked_deposit(mut self, deposit: LinkedDeposit) -> Self {
+        self.linked_deposit = Some(deposit);
+        self
+    }
+
+    /// zkUSD in the stability pool the spell reads (liquidations, and
+    /// openings, whose rate floor it sets; a tranche defaults to a pool
+    /// holding exactly its debt)
+    pub fn with_stability_pool(mut self, pool_zkusd: ZkUsd) -> Self {
+        self.pool_zkusd = Some(pool_zkusd);
+        self
+    }
+
+    /// Derive the action and the expected outputs
+    pub fn build(self) -> ZkUsdResult<Built<VaultContext>> {
+        let oracle = match (self.oracle, self.btc_price) {
+            (Some(oracle), _) => oracle,
+            (None, Some(btc_price)) => OracleSnapshot::active(PriceData {
+                confidence: self.price_confidence,
+                ..PriceData::new(btc_price, self.block_height, PriceSource::Mock)
+            }),
+            (None, None) => {
+                return Err(ZkUsdError::InvalidInput { param: "btc_price", reason: "set with at_price" })
+            }
+        };
+        let btc_price = oracle.price.price;
+        let mut ctx = VaultContext {
+            state: self.state.clone(),
+            new_state: self.state.clone(),
+            vault: None,
+            new_vault: None,
+            oracle,
+            btc_inputs: Sats::ZERO,
+            btc_outputs: Sats::ZERO,
+            zkusd_inputs: ZkUsd::ZERO,
+            zkusd_outputs: ZkUsd::ZERO,
+            vault_minted: ZkUsd::ZERO,
+            linked_btc_claim: self.linked_btc_claim,
+            linked_deposit: self.linked_deposit,
+            linked_offset: self
+                .pool_zkusd
+                .map(|pool| LinkedOffset { pool_zkusd: pool.into_inner(), debt: 0, collateral: 0 }),
+            insurance: None,
+            new_insurance: None,
+            registry: Vec::new(),
+            new_registry: Vec::new(),
+            // Bind the spell to the state it was built against, unless the
+            // caller chose a reference
+            bounds: SpellBounds {
+                based_on: self.bounds.based_on.or(Some(StateRef::new(self.state.commitment(), self.block_height))),
+                ..self.bounds
+            },
+            whitelist_proof: self.whitelist_proof,
+            signer: self.signer,
+            block_height: self.block_height,
+            applied_actions: AppliedActions::new(),
+            expected: ExpectedOutputs::default(),
+            events: EventLog::new(),
+        };
+        ctx.new_state.protocol.bootstrap = bootstrap_after_spell(&ctx)?;
+
+        // Build from the vault as the validator sees it, settled to this block
+        let mut op = self.op;
+        if let Some(vault) = op.vault_mut() {
+            *vault = normalize_vault(vault, ctx.block_height)?;
+        }
+
+        let action = match op {
+            VaultOp::Open { owner, collateral, debt } => {
+                let total_debt = safe_add(debt.into_inner(), limits::LIQUIDATION_RESERVE)?;
+                let id = generate_vault_id(&owner, ctx.block_height, self.nonce);
+                let mut vault = Vault::new(id, owner, collateral.into_inner(), total_debt, ctx.block_height);
+                vault.rate_mode = self.rate_mode;
+                if vault.rate_mode == RateMode::Fixed {
+                    // The default rate, raised to the floor the pool's coverage sets
+                    vault.interest_rate_bps = vault.interest_rate_bps.max(min_interest_rate(ctx.sp_coverage_bps()));
+                }
+
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.total_collateral = safe_add(protocol.total_collateral, collateral.into_inner())?;
+                protocol.total_debt = safe_add(protocol.total_debt, total_debt)?;
+                protocol.active_vault_count = safe_add(protocol.active_vault_count, 1)?;
+                protocol.add_vault_weight(&vault, total_debt)?;
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+                if vault.rate_mode == RateMode::Variable {
+                    vault.interest_rate_bps = protocol.variable_rate_bps();
+                }
+                let fee = charge_borrowing_fee(&mut ctx, owner, debt.into_inner(), &vault.stats)?;
+                vault.stats.total_fees_paid = fee;
+                vault.stats.total_debt_minted = debt.into_inner();
+
+                ctx.btc_inputs = collateral;
+                // The borrower receives the debt net of the fee
+                ctx.zkusd_outputs = ZkUsd(safe_sub(debt.into_inner(), fee)?);
+                ctx.new_vault = Some(vault);
+                VaultAction::OpenVault { collateral, debt }
+            }
+            VaultOp::Close { vault } => {
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.active_vault_count = safe_sub(protocol.active_vault_count, 1)?;
+                protocol.total_debt = protocol.total_debt.saturating_sub(vault.debt);
+                protocol.remove_vault_weight(&vault, vault.debt);
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+
+                ctx.zkusd_inputs = ZkUsd(vault.debt);
+                ctx.btc_outputs = Sats(vault.collateral);
+                ctx.new_vault = Some(Vault { status: VaultStatus::Closed, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::CloseVault { vault_id: vault.id }
+            }
+            VaultOp::AddCollateral { vault, amount } => {
+                let collateral = safe_add(vault.collateral, amount.into_inner())?;
+                ctx.btc_inputs = amount;
+                // Protection inflated by redemptions falls back to the maximum
+                ctx.new_vault = Some(Vault {
+                    collateral,
+                    stats: vault.stats_at(ctx.block_height),
+                    protected_collateral_bps: vault.protected_collateral_bps.min(fees::MAX_PROTECTED_COLLATERAL_BPS),
+                    adjustment_window: adjustment_window_after(&vault, ctx.block_height),
+                    ..vault.averaged_at(ctx.block_height)
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::AddCollateral { vault_id: vault.id, amount }
+            }
+            VaultOp::WithdrawCollateral { vault, amount } => {
+                let collateral = safe_sub(vault.collateral, amount.into_inner())?;
+                ctx.btc_outputs = amount;
+                ctx.new_vault = Some(Vault {
+                    collateral,
+                    stats: vault.stats_at(ctx.block_height),
+                    adjustment_window: adjustment_window_after(&vault, ctx.block_height),
+                    ..vault.averaged_at(ctx.block_height)
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::WithdrawCollateral { vault_id: vault.id, amount }
+            }
+            VaultOp::MintDebt { vault, amount } => {
+                let debt = safe_add(vault.debt, amount.into_inner())?;
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.total_debt = safe_add(protocol.total_debt, amount.into_inner())?;
+                protocol.remove_vault_weight(&vault, vault.debt);
+                protocol.add_vault_weight(&vault, debt)?;
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+                let mut stats = vault.stats_at(ctx.block_height);
+                let fee = charge_borrowing_fee(&mut ctx, vault.owner, amount.into_inner(), &stats)?;
+                stats.total_fees_paid = safe_add(stats.total_fees_paid, fee)?;
+                stats.total_debt_minted = safe_add(stats.total_debt_minted, amount.into_inner())?;
+
+                ctx.zkusd_outputs = amount;
+                let adjustment_window = adjustment_window_after(&vault, ctx.block_height);
+                ctx.new_vault = Some(Vault { debt, stats, adjustment_window, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::MintDebt { vault_id: vault.id, amount }
+            }
+            VaultOp::RepayDebt { vault, amount } => {
+                let debt = safe_sub(vault.debt, amount.into_inner())?;
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.total_debt = protocol.total_debt.saturating_sub(amount.into_inner());
+                protocol.remove_vault_weight(&vault, vault.debt);
+                protocol.add_vault_weight(&vault, debt)?;
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+
+                ctx.zkusd_inputs = amount;
+                let adjustment_window = adjustment_window_after(&vault, ctx.block_height);
+                ctx.new_vault = Some(Vault { debt, stats: vault.stats_at(ctx.block_height), adjustment_window, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::RepayDebt { vault_id: vault.id, amount }
+            }
+            VaultOp::Liquidate { vault } => {
+                // A non-empty pool absorbs the whole debt and the pool's
+                // share of the collateral
+                if let Some(offset) = ctx.linked_offset.as_mut().filter(|offset| offset.pool_zkusd > 0) {
+                    let tcr = calculate_tcr(
+                        Sats(ctx.state.protocol.total_collateral),
+                        ZkUsd(ctx.state.protocol.total_debt_with_interest(ctx.block_height)?),
+                        btc_price,
+                    )?;
+                    let bonus_bps = ctx.state.liquidator_bonus_bps(is_recovery_mode(tcr));
+                    let (_, _, to_pool) = split_seized_collateral(vault.collateral, bonus_bps)?;
+                    (offset.debt, offset.collateral) = (vault.debt, to_pool);
+                }
+                let sp_rebate =
+                    ctx.state.sp_liquidator_rebate(ctx.linked_deposit.as_ref(), &ctx.signer, ctx.linked_offset.as_ref())?;
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.active_vault_count = safe_sub(protocol.active_vault_count, 1)?;
+                protocol.remove_vault_weight(&vault, vault.debt);
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+                protocol.accumulated_fees -= sp_rebate;
+
+                ctx.zkusd_outputs = ZkUsd(sp_rebate);
+
+                ctx.new_vault = Some(Vault { status: VaultStatus::Liquidated, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::Liquidate { vault_id: vault.id }
+            }
+            VaultOp::Redeem { vault, amount, min_btc_out } => {
+                let btc_value = zkusd_to_btc_floor(amount, btc_price)?;
+                ctx.zkusd_inputs = amount;
+                ctx.btc_outputs = btc_value;
+                if let Some(vault) = vault {
+                    // Vaults inside the lockout pass through untouched
+                    let new_vault = if ctx.state.is_redemption_locked(&vault, ctx.block_height) {
+                        vault.clone()
+                    } else {
+                        let debt = safe_sub(vault.debt, amount.into_inner())?;
+                        let collateral = safe_sub(vault.collateral, btc_value.into_inner())?;
+                        Vault {
+                            debt,
+                            collateral,
+                            protected_collateral_bps: vault.protection_after_redemption(collateral),
+                            status: if debt == 0 { VaultStatus::Closed } else { vault.status },
+                            ..vault.averaged_at(ctx.block_height)
+                        }
+                    };
+                    ctx.new_vault = Some(new_vault);
+                    ctx.vault = Some(vault);
+                }
+                VaultAction::Redeem { amount, min_btc_out }
+            }
+            VaultOp::FlashMint { amount, purpose } => {
+                let fee = calculate_flash_fee_bps(amount.into_inner(), ctx.state.protocol.flash_fee_bps);
+                // The principal is minted and burned; the fee is burned into
+                // protocol custody
+                ctx.zkusd_inputs = ZkUsd(fee);
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accumulated_fees = safe_add(protocol.accumulated_fees, fee)?;
+                VaultAction::FlashMint { amount, purpose }
+            }
+            VaultOp::AtomicRescue { vault, collateral_to_add, debt_to_repay, rescuer_discount } => {
+                let collateral = safe_sub(
+                    safe_add(vault.collateral, collateral_to_add.into_inner())?,
+                    rescuer_discount.into_inner(),
+                )?;
+                let debt = safe_sub(vault.debt, debt_to_repay.into_inner())?;
+
+                ctx.btc_inputs = collateral_to_add;
+                ctx.btc_outputs = rescuer_discount;
+                ctx.zkusd_inputs = debt_to_repay;
+                ctx.new_vault = Some(Vault {
+                    collateral,
+                    debt,
+                    stats: vault.rescued_stats_at(ctx.block_height),
+                    ..vault.averaged_at(ctx.block_height)
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::AtomicRescue { vault_id: vault.id, collateral_to_add, debt_to_repay, rescuer_discount }
+            }
+            VaultOp::PurchaseInsurance { vault, coverage_btc, premium, trigger_icr } => {
+                ctx.zkusd_inputs = premium;
+                ctx.new_state.insurance_fund =
+                    ctx.state.insurance_fund.sell(vault.insurance_balance, coverage_btc.into_inner(), premium.into_inner())?;
+                ctx.new_vault = Some(Vault { insurance_balance: coverage_btc.into_inner(), ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::PurchaseInsurance { vault_id: vault.id, coverage_btc, premium, trigger_icr }
+            }
+            VaultOp::TriggerInsurance { vault, insurance_id } => {
+                let payout = match self.insurance {
+                    Some(charm) => {
+                        // Initial trigger pays part of the coverage and starts
+                        // grace; a later trigger draws the rest
+                        let (payout, triggered_at) = if !charm.is_triggered {
+                            let partial = safe_div(
+                                safe_mul(charm.coverage_btc, ctx.state.insurance_initial_payout_bps)?,
+                                fees::BPS_DENOMINATOR,
+                            )?;
+                            (partial, ctx.block_height)
+                        } else {
+                            (charm.coverage_btc, charm.triggered_at)
+                        };
+                        ctx.new_insurance = Some(InsuranceCharm {
+                            coverage_btc: safe_sub(charm.coverage_btc, payout)?,
+                            is_triggered: true,
+                            triggered_at,
+                            ..charm.clone()
+                        });
+                        ctx.insurance = Some(charm);
+                        payout
+                    }
+                    None => vault.insurance_balance,
+                };
+
+                ctx.new_state.insurance_fund = ctx.state.insurance_fund.release(payout);
+                ctx.new_vault = Some(Vault {
+                    collateral: safe_add(vault.collateral, payout)?,
+                    insurance_balance: safe_sub(vault.insurance_balance, payout)?,
+                    stats: vault.rescued_stats_at(ctx.block_height),
+                    ..vault.averaged_at(ctx.block_height)
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::TriggerInsurance { insurance_id, vault_id: vault.id }
+            }
+            VaultOp::TransferInsurance { vault, insurance_id, new_owner } => {
+                ctx.vault = Some(vault);
+                VaultAction::TransferInsurance { insurance_id, new_owner }
+            }
+            VaultOp::ExpireInsurance { vault, insurance_id } => {
+                let charm = self.insurance.ok_or(ZkUsdError::InvalidInput {
+                    param: "insurance",
+                    reason: "set with with_insurance_charm",
+                })?;
+                let released = charm.coverage_btc.min(vault.insurance_balance);
+
+                ctx.new_state.insurance_fund = ctx.state.insurance_fund.release(released);
+                ctx.new_vault = Some(Vault { insurance_balance: vault.insurance_balance - released, ..vault.clone() });
+                ctx.insurance = Some(charm);
+                ctx.vault = Some(vault.clone());
+                VaultAction::ExpireInsurance { insurance_id, vault_id: vault.id }
+            }
+            VaultOp::SetFlashFee { fee_bps } => {
+                ctx.new_state.protocol.flash_fee_bps = fee_bps;
+                VaultAction::SetFlashFee { fee_bps }
+            }
+            VaultOp::SetFeeDistribution { distribution } => {
+                ctx.new_state.fee_distribution = distribution;
+                VaultAction::SetFeeDistribution { distribution }
+            }
+            VaultOp::SetVaultOperator { vault, operator } => {
+                ctx.new_vault = Some(Vault { operator, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::SetVaultOperator { vault_id: vault.id, operator }
+            }
+            VaultOp::PokeBaseRate => {
+                let protocol = &mut ctx.new_state.protocol;
+                let blocks_elapsed = ctx.block_height.saturating_sub(protocol.last_fee_update_block);
+                let base_rate = decay_base_rate(protocol.base_rate, blocks_elapsed)?;
+                let incentive = if base_rate < protocol.base_rate {
+                    fees::BASE_RATE_POKE_INCENTIVE.min(protocol.accumulated_fees)
+                } else {
+                    0
+                };
+                protocol.base_rate = base_rate;
+                protocol.last_fee_update_block = ctx.block_height;
+                protocol.accumulated_fees -= incentive;
+
+                ctx.zkusd_outputs = ZkUsd(incentive);
+                VaultAction::PokeBaseRate {}
+            }
+            VaultOp::AccrueInterest { vault } => {
+                let mut new_vault = vault.clone();
+                let protocol = &mut ctx.new_state.protocol;
+                let interest = reconcile_vault(protocol, &mut new_vault, ctx.block_height)?;
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+                let bounty = if ctx.signer == vault.owner {
+                    0
+                } else {
+                    safe_mul_div(interest, fees::INTEREST_POKE_BOUNTY_BPS, fees::BPS_DENOMINATOR)?
+                        .min(fees::MAX_INTEREST_POKE_BOUNTY)
+                };
+
+                ctx.zkusd_outputs = ZkUsd(bounty);
+                ctx.new_vault = Some(new_vault);
+                ctx.vault = Some(vault.clone());
+                VaultAction::AccrueInterest { vault_id: vault.id }
+            }
+            VaultOp::GraduateBootstrap => {
+                if let Some(bootstrap) = ctx.new_state.protocol.bootstrap.as_mut() {
+                    bootstrap.graduated = true;
+                }
+                VaultAction::GraduateBootstrap {}
+            }
+            VaultOp::SetProtection { vault, bps } => {
+                let surcharge = calculate_protection_rate_bump(bps)
+                    .saturating_sub(calculate_protection_rate_bump(vault.protected_collateral_bps));
+                let interest_rate_bps = safe_add(vault.interest_rate_bps, surcharge)?;
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                protocol.add_rate_weight(vault.debt, interest_rate_bps)?;
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+
+                ctx.new_vault = Some(Vault {
+                    protected_collateral_bps: bps,
+                    interest_rate_bps,
+                    stats: vault.stats_at(ctx.block_height),
+                    ..vault.clone()
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::SetProtection { vault_id: vault.id, bps }
+            }
+            VaultOp::SetBeneficiary { vault, beneficiary } => {
+                ctx.new_vault = Some(Vault { beneficiary, last_updated: ctx.block_height, ..vault.clone() });
+                ctx.vault = Some(vault.clone());
+                VaultAction::SetBeneficiary {
+                    vault_id: vault.id,
+                    beneficiary: beneficiary.map(|(beneficiary, _)| beneficiary),
+                    inactivity_blocks: beneficiary.map_or(0, |(_, inactivity_blocks)| inactivity_blocks),
+                }
+            }
+            VaultOp::ClaimAsBeneficiary { vault } => {
+                ctx.new_vault = Some(Vault {
+                    owner: ctx.signer,
+                    beneficiary: None,
+                    last_updated: ctx.block_height,
+                    ..vault.clone()
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::ClaimAsBeneficiary { vault_id: vault.id }
+            }
+            VaultOp::BeginLiquidation { vault } => {
+                let tcr = calculate_tcr(
+                    Sats(ctx.state.protocol.total_collateral),
+                    ZkUsd(ctx.state.protocol.total_debt_with_interest(ctx.block_height)?),
+                    btc_price,
+                )?;
+                let pending = PendingLiquidation {
+                    trigger_price: btc_price,
+                    started_at: ctx.block_height,
+                    bonus_bps: ctx.state.liquidator_bonus_bps(is_recovery_mode(tcr)),
+                };
+
+                let protocol = &mut ctx.new_state.protocol;
+                protocol.accrue_interest(ctx.block_height)?;
+                protocol.active_vault_count = safe_sub(protocol.active_vault_count, 1)?;
+                protocol.remove_vault_weight(&vault, vault.debt);
+                refresh_variable_rate(protocol, ctx.block_height, ctx.state.global_debt_ceiling)?;
+
+                ctx.new_vault = Some(Vault {
+                    status: VaultStatus::Liquidating,
+                    pending_liquidation: Some(pending),
+                    ..vault.clone()
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::BeginLiquidation { vault_id: vault.id }
+            }
+            VaultOp::ContinueLiquidation { vault, debt_portion } => {
+                let pending = vault.pending_liquidation.ok_or(ZkUsdError::InvalidInput {
+                    param: "vault",
+                    reason: "vault is not being liquidated",
+                })?;
+                let seized = tranche_collateral(&vault, debt_portion.into_inner())?;
+                let (_, _, to_pool) = split_seized_collateral(seized, pending.bonus_bps)?;
+                let debt = safe_sub(vault.debt, debt_portion.into_inner())?;
+
+                ctx.linked_offset = Some(LinkedOffset {
+                    pool_zkusd: self.pool_zkusd.unwrap_or(debt_portion).into_inner(),
+                    debt: debt_portion.into_inner(),
+                    collateral: to_pool,
+                });
+                ctx.new_vault = Some(Vault {
+                    debt,
+                    collateral: vault.collateral - seized,
+                    status: if debt == 0 { VaultStatus::Liquidated } else { VaultStatus::Liquidating },
+                    pending_liquidation: (debt > 0).then_some(pending),
+                    ..vault.clone()
+                });
+                ctx.vault = Some(vault.clone());
+                VaultAction::ContinueLiquidation { vault_id: vault.id, debt_portion }
+            }
+        };
+
+        // The owner's spells restart a designated beneficiary's inactivity clock
+        if let (Some(vault), Some(new_vault)) = (&ctx.vault, &mut ctx.new_vault) {
+            let claim = matches!(action, VaultAction::ClaimAsBeneficiary { .. });
+            if vault.beneficiary.is_some() && ctx.signer == vault.owner && !claim {
+                new_vault.last_updated = ctx.block_height;
+            }
+        }
+
+        // With the registry in use, recreate every shard with the vault's change
+        let change = RegistryChange::between(ctx.vault.as_ref(), ctx.new_vault.as_ref());
+        let redeem = matches!(action, VaultAction::Redeem { .. });
+        if ctx.state.registry_shards > 0 && (change.is_some() || redeem) {
+            ctx.registry = self.registry;
+            ctx.new_registry = match change {
+                Some(change) => apply_change(&ctx.registry, change)?,
+                None => split(&flatten(&ctx.registry)?)?,
+            };
+            ctx.new_state.registry_shards = u16::try_from(ctx.new_registry.len()).map_err(|_| ZkUsdError::Overflow)?;
+        }
+
+        Ok(Built { action, context: ctx })
+    }
+}
+
+/// Record a mint against the owner's lifetime cap and credit its borrowing fee,
+/// returning the fee
+fn charge_borrowing_fee(ctx: &mut VaultContext, owner: Address, amount: u64, stats: &VaultStats) -> ZkUsdResult<u64> {
+    ctx.new_state.mint_tracker.record(owner, amount)?;
+    let mut fee = calculate_borrowing_fee(amount, ctx.state.protocol.base_rate)?;
+    if ctx.state.loyalty_discount_enabled {
+        fee = apply_loyalty_discount(fee, stats)?;
+    }
+    fee = apply_depositor_discount(fee, ctx.state.depositor_discount_bps(ctx.linked_deposit.as_ref(), &owner))?;
+    let split = ctx.state.route_borrowing_fee(fee, &mut ctx.new_state.protocol.bootstrap);
+    ctx.new_state.collected_fees = ctx.state.collected_fees.checked_add(&split)?;
+    Ok(fee)
+}
+
+/// Re-price the variable rate after an accrual, as the validator does
+fn refresh_variable_rate(protocol: &mut ProtocolState, block_height: u64, debt_ceiling: u64) -> ZkUsdResult<()> {
+    protocol.refresh_variable_rate(block_height, debt_ceiling, &VariableRateCurve::default())?;
+    Ok(())
+}
+
+/// Adjustment window of `vault` once it has been adjusted at `block_height`
+fn adjustment_window_after(vault: &Vault, block_height: u64) -> AdjustmentWindow {
+    vault.adjustment_window.after_adjustment(block_height, limits::ADJUSTMENT_WINDOW_BLOCKS)
+}
+
+impl Built<VaultContext> {
+    /// Action, expected vault output and expected protocol state
+    pub fn into_parts(self) -> (VaultAction, Option<Vault>, VaultManagerState) {
+        (self.action, self.context.new_vault, self.context.new_state)
+    }
+}
+
+/// The vault is spent first (the entry point reads the signer from its
+/// owner), then the protocol state: spent when it changes, referenced
+/// otherwise. The oracle is referenced as a price oracle state carrying the
+/// snapshot, zkUSD moves as bare amounts of the configured token, and the
+/// stability pool a liquidation reads (and offsets into) or a depositor
+/// discount references is encoded under the configured pool, spent when the
+/// spell offsets into it. A linked BTC
+/// claim is not encoded: no built scenario makes one.
+#[cfg(feature = "charms")]
+impl ChainSpell for VaultContext {
+    fn encode(built: &Built<Self>) -> EncodedSpell {
+        let ctx = &built.context;
+        let vm = app('n', VM_APP_ID);
+        let token = app('t', ctx.state.zkusd_token_id);
+        let pool = app('n', ctx.state.stability_pool_id);
+
+        let mut witness = VaultWitness::for_action(&built.action);
+        // Deployed spells name the vault even where the action does not
+        // (an opened vault's derived id, an insurance transfer's vault)
+        witness.vault_id = witness.vault_id.or(ctx.new_vault.as_ref().or(ctx.vault.as_ref()).map(|vault| vault.id));
+        witness.expires_at_block = ctx.bounds.expires_at_block;
+        witness.max_price = ctx.bounds.max_price;
+        witness.min_price = ctx.bounds.min_price;
+        witness.based_on = ctx.bounds.based_on;
+        witness.whitelist_proof = (!ctx.whitelist_proof.is_empty()).then(|| ctx.whitelist_proof.clone());
+
+        let mut spell = EncodedSpell::new(vm.clone(), Data::from(&witness), ctx.block_height);
+        if let Some(vault) = &ctx.vault {
+            spell = spell.spend(charm(&vm, Data::from(vault)));
+        }
+        let state_charm = charm(&vm, Data::from(&ctx.state));
+        spell = if ctx.new_state == ctx.state { spell.reference(state_charm) } else { spell.spend(state_charm) };
+        for shard in &ctx.registry {
+            spell = spell.spend(charm(&vm, Data::from(shard)));
+        }
+
+        let oracle = OracleState {
+            price: ctx.oracle.price.clone(),
+            previous_price: ctx.oracle.previous_price.clone(),
+            circuit_breaker: ctx.oracle.circuit_breaker,
+            operator: ctx.oracle.operator,
+            is_active: ctx.oracle.is_active,
+            last_valid_price: ctx.oracle.price.price,
+            ..OracleState::new([0u8; 32], ctx.oracle.operator, ctx.oracle.price.price, ctx.oracle.price.timestamp_block)
+        };
+        spell = spell.reference(charm(&app('n', ctx.state.price_oracle_id), Data::from(&oracle)));
+
+        let pool_state = StabilityPoolState {
+            total_zkusd: ctx.linked_offset.map_or(0, |offset| offset.pool_zkusd),
+            ..StabilityPoolState::new()
+        };
+        // An offsetting pool is spent, any other only referenced
+        let offset = ctx.linked_offset.filter(|offset| offset.debt > 0 || offset.collateral > 0);
+        if offset.is_some() {
+            spell = spell.spend(charm(&pool, Data::from(&pool_state)));
+        } else if ctx.linked_offset.is_some() || ctx.linked_deposit.is_some() {
+            spell = spell.reference(charm(&pool, Data::from(&pool_state)));
+        }
+        if let Some(linked) = &ctx.linked_deposit {
+            let deposit = StabilityDeposit {
+                owner: linked.depositor,
+                initial_value: linked.value,
+                snapshot_p: pool_state.product_p,
+                snapshot_s: pool_state.sum_s,
+                snapshot_epoch: pool_state.current_epoch,
+                snapshot_scale: pool_state.current_scale,
+                last_updated: ctx.block_height,
+                frontend_tag: None,
+                kickback_bps: 0,
+            };
+            spell = spell.reference(charm(&pool, Data::from(&deposit)));
+        }
+        if let Some(offset) = offset {
+            let new_pool = StabilityPoolState {
+                total_zkusd: pool_state.total_zkusd - offset.debt,
+                total_btc: pool_state.total_btc + offset.collateral,
+                ..pool_state.clone()
+            };
+            spell = spell.create(charm(&pool, Data::from(&new_pool)));
+        }
+
+        if ctx.zkusd_inputs.into_inner() > 0 {
+            spell = spell.spend(charm(&token, Data::from(&ctx.zkusd_inputs.into_inner())));
+        }
+        if ctx.zkusd_outputs.into_inner() > 0 {
+            spell = spell.create(charm(&token, Data::from(&ctx.zkusd_outputs.into_inner())));
+        }
+
+        if let Some(vault) = &ctx.new_vault {
+            spell = spell.create(charm(&vm, Data::from(vault)));
+        }
+        spell = spell.create(charm(&vm, Data::from(&ctx.new_state)));
+        for shard in &ctx.new_registry {
+            spell = spell.create(charm(&vm, Data::from(shard)));
+        }
+        spell.with_coins(ctx.btc_inputs.into_inner(), ctx.btc_outputs.into_inner())
+    }
+
+    fn validate_encoded(spell: &EncodedSpell) -> bool {
+        validate_vault_operation_at(&spell.app, &spell.tx, &spell.x, &spell.w, spell.block_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_locally;
+    use zkusd_common::bootstrap::{whitelist_root, BootstrapState};
+    use zkusd_common::constants::{pcv, time};
+    use zkusd_common::vault_registry::RegistryEntry;
+
+    const BTC_PRICE_100K: u64 = 100_000_00000000;
+    const ONE_BTC: u64 = 100_000_000;
+    const ONE_ZKUSD: u64 = 100_000_000;
+    const ADMIN: Address = [9u8; 32];
+    const OWNER: Address = [1u8; 32];
+    const KEEPER: Address = [6u8; 32];
+    const BLOCK: u64 = 1_000;
+
+    /// Protocol holding 10 BTC against 100k zkUSD, far from Recovery Mode
+    fn state() -> VaultManagerState {
+        let mut state = VaultManagerState::new(ADMIN, [1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32], [5u8; 32])
+            .expect("fixture addresses are non-zero");
+        state.protocol.total_collateral = 10 * ONE_BTC;
+        state.protocol.total_debt = 100_000 * ONE_ZKUSD;
+        state.protocol.active_vault_count = 2;
+        state.protocol.add_rate_weight(100_000 * ONE_ZKUSD, fees::DEFAULT_INTEREST_RATE_BPS).unwrap();
+        state
+    }
+
+    /// Vault with the given collateral against 100k zkUSD of debt
+    fn vault(collateral: u64) -> Vault {
+        Vault::new([7u8; 32], OWNER, collateral, 100_000 * ONE_ZKUSD, 0)
+    }
+
+    /// Vault past the two-phase threshold: 21 BTC against 2M zkUSD (ICR 105%)
+    fn whale() -> Vault {
+        Vault::new([7u8; 32], OWNER, 21 * ONE_BTC, 2_000_000 * ONE_ZKUSD, 0)
+    }
+
+    fn build(builder: VaultOpsBuilder) -> Built<VaultContext> {
+        builder.at_price(BTC_PRICE_100K).at_block(BLOCK).build().expect("builder should succeed")
+    }
+
+    /// One built spell per action
+    fn every_action() -> Vec<(&'static str, Built<VaultContext>)> {
+        let state = state();
+        let healthy = vault(3 * ONE_BTC);
+        let insured = Vault { insurance_balance: ONE_BTC / 2, ..vault(112_000_000) };
+        let charm = InsuranceCharm::new([8u8; 32], insured.id, OWNER, ONE_BTC / 2, 0, 115, 10, 0, 10_000);
+        let expired = InsuranceCharm { expires_at: BLOCK, ..charm.clone() };
+        let distribution = FeeDistribution { treasury_bps: 5_000, stability_pool_bps: 5_000, staking_bps: 0 };
+        let window = limits::MIN_BENEFICIARY_INACTIVITY_BLOCKS;
+        let inherited = Vault { beneficiary: Some((KEEPER, window)), ..healthy.clone() };
+        let liquidating = build(VaultOpsBuilder::begin_liquidation(&state, KEEPER, &whale())).context.new_vault.unwrap();
+        let tranche = ZkUsd(500_000 * ONE_ZKUSD);
+        let mut bootstrapping = state.clone();
+        bootstrapping.protocol.bootstrap =
+            Some(BootstrapState::new(whitelist_root(&[OWNER]), 1_000_000 * ONE_ZKUSD, 0, 50_000 * ONE_ZKUSD));
+
+        Vec::from([
+            ("open", build(VaultOpsBuilder::open_vault(&state, OWNER, Sats(2 * ONE_BTC), ZkUsd(50_000 * ONE_ZKUSD)))),
+            ("close", build(VaultOpsBuilder::close_vault(&state, &healthy))),
+            ("add_collateral", build(VaultOpsBuilder::add_collateral(&state, &healthy, Sats(ONE_BTC)))),
+            ("withdraw_collateral", build(VaultOpsBuilder::withdraw_collateral(&state, &healthy, Sats(ONE_BTC)))),
+            ("mint_debt", build(VaultOpsBuilder::mint_debt(&state, &healthy, ZkUsd(10_000 * ONE_ZKUSD)))),
+            ("repay_debt", build(VaultOpsBuilder::repay_debt(&state, &healthy, ZkUsd(10_000 * ONE_ZKUSD)))),
+            ("liquidate", build(VaultOpsBuilder::liquidate(&state, KEEPER, &vault(105_000_000)))),
+            ("redeem", build(VaultOpsBuilder::redeem(&state, KEEPER, ZkUsd(1_000 * ONE_ZKUSD), Sats(0)).against(&healthy))),
+            ("flash_mint", build(VaultOpsBuilder::flash_mint(&state, KEEPER, ZkUsd(1_000 * ONE_ZKUSD), 1))),
+            (
+                "atomic_rescue",
+                build(VaultOpsBuilder::atomic_rescue(
+                    &state,
+                    KEEPER,
+                    &vault(120_000_000),
+                    Sats(ONE_BTC),
+                    ZkUsd(10_000 * ONE_ZKUSD),
+                    Sats(ONE_BTC / 50),
+                )),
+            ),
+            (
+                "purchase_insurance",
+                build(VaultOpsBuilder::purchase_insurance(&state, &healthy, Sats(ONE_BTC), ZkUsd(100 * ONE_ZKUSD), 150)),
+            ),
+            ("trigger_insurance", build(VaultOpsBuilder::trigger_insurance(&state, KEEPER, &insured, [8u8; 32]))),
+            (
+                "trigger_insurance_charm",
+                build(VaultOpsBuilder::trigger_insurance(&state, KEEPER, &insured, [8u8; 32]).with_insurance_charm(charm)),
+            ),
+            ("transfer_insurance", build(VaultOpsBuilder::transfer_insurance(&state, &healthy, [8u8; 32], KEEPER))),
+            (
+                "expire_insurance",
+                build(VaultOpsBuilder::expire_insurance(&state, KEEPER, &insured, [8u8; 32]).with_insurance_charm(expired)),
+            ),
+            ("set_flash_fee", build(VaultOpsBuilder::set_flash_fee(&state, fees::MAX_FLASH_FEE_BPS))),
+            ("set_fee_distribution", build(VaultOpsBuilder::set_fee_distribution(&state, distribution))),
+            ("set_vault_operator", build(VaultOpsBuilder::set_vault_operator(&state, &healthy, Some(KEEPER)))),
+            ("poke_base_rate", build(VaultOpsBuilder::poke_base_rate(&state, KEEPER))),
+            (
+                "accrue_interest",
+                VaultOpsBuilder::accrue_interest(&state, KEEPER, &healthy)
+                    .at_price(BTC_PRICE_100K)
+                    .at_block(time::BLOCKS_PER_YEAR)
+                    .build()
+                    .expect("builder should succeed"),
+            ),
+            ("set_protection", build(VaultOpsBuilder::set_protection(&state, &healthy, fees::MAX_PROTECTED_COLLATERAL_BPS))),
+            ("set_beneficiary", build(VaultOpsBuilder::set_beneficiary(&state, &healthy, Some((KEEPER, window))))),
+            ("begin_liquidation", build(VaultOpsBuilder::begin_liquidation(&state, KEEPER, &whale()))),
+            ("continue_liquidation", build(VaultOpsBuilder::continue_liquidation(&state, KEEPER, &liquidating, tranche))),
+            (
+                "claim_as_beneficiary",
+                VaultOpsBuilder::claim_as_beneficiary(&state, &inherited)
+                    .at_price(BTC_PRICE_100K)
+                    .at_block(inherited.last_updated + window)
+                    .build()
+                    .expect("builder should succeed"),
+            ),
+            (
+                "graduate_bootstrap",
+                VaultOpsBuilder::graduate_bootstrap(&bootstrapping, KEEPER)
+                    .with_stability_pool(ZkUsd(50_000 * ONE_ZKUSD))
+                    .at_price(BTC_PRICE_100K)
+                    .at_block(pcv::GRADUATION_MIN_BLOCKS)
+                    .build()
+                    .expect("builder should succeed"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_every_builder_validates() {
+        for (name, built) in every_action() {
+            assert_eq!(verify_locally(&built), Ok(()), "{} should validate", name);
+        }
+    }
+
+    #[test]
+    fn test_open_vault_parts() {
+        let state = state();
+        let built = build(VaultOpsBuilder::open_vault(&state, OWNER, Sats(2 * ONE_BTC), ZkUsd(50_000 * ONE_ZKUSD)));
+        let (action, new_vault, new_state) = built.into_parts();
+
+        assert_eq!(action, VaultAction::OpenVault { collateral: Sats(2 * ONE_BTC), debt: ZkUsd(50_000 * ONE_ZKUSD) });
+        let new_vault = new_vault.unwrap();
+        assert_eq!(new_vault.debt, 50_000 * ONE_ZKUSD + limits::LIQUIDATION_RESERVE);
+        assert_eq!(new_state.protocol.active_vault_count, 3);
+        assert_eq!(new_state.protocol.last_interest_accrual_block, BLOCK);
+    }
+
+    #[test]
+    fn test_depositor_discount_lowers_fee() {
+        let mut state = state();
+        state.depositor_discount_bps = 2_000;
+        let open = || VaultOpsBuilder::open_vault(&state, OWNER, Sats(2 * ONE_BTC), ZkUsd(50_000 * ONE_ZKUSD));
+        let deposit = LinkedDeposit { depositor: OWNER, value: 1_000 * ONE_ZKUSD };
+
+        let full = build(open());
+        let discounted = build(open().with_linked_deposit(deposit));
+        assert_eq!(verify_locally(&discounted), Ok(()));
+        let fee = |built: Built<VaultContext>| built.into_parts().1.unwrap().stats.total_fees_paid;
+        let full_fee = fee(full);
+        assert_eq!(fee(discounted), full_fee - full_fee / 5);
+    }
+
+    #[test]
+    fn test_stats_accumulate_over_vault_lifetime() {
+        let step = |builder: VaultOpsBuilder, block: u64| {
+            let built = builder.at_price(BTC_PRICE_100K).at_block(block).build().expect("builder should succeed");
+            assert_eq!(verify_locally(&built), Ok(()));
+            let (_, new_vault, new_state) = built.into_parts();
+            (new_vault.unwrap(), new_state)
+        };
+        // Interest the vault's debt accrues between two blocks
+        let interest = |vault: &Vault, from: u64, to: u64| Vault { last_updated: from, ..vault.clone() }.calculate_interest(to);
+
+        let (opened, state) = step(VaultOpsBuilder::open_vault(&state(), OWNER, Sats(2 * ONE_BTC), ZkUsd(50_000 * ONE_ZKUSD)), BLOCK);
+        let open_fee = opened.stats.total_fees_paid;
+        assert!(open_fee > 0);
+        assert_eq!(opened.stats.total_debt_minted, 50_000 * ONE_ZKUSD);
+
+        let (added, state) = step(VaultOpsBuilder::add_collateral(&state, &opened, Sats(ONE_BTC)), BLOCK + 10_000);
+        assert_eq!(added.stats.blocks_active, 10_000);
+        assert_eq!(added.stats.total_interest_paid, interest(&opened, BLOCK, BLOCK + 10_000));
+
+        let (minted, state) = step(VaultOpsBuilder::mint_debt(&state, &added, ZkUsd(10_000 * ONE_ZKUSD)), BLOCK + 50_000);
+        assert_eq!(minted.stats.blocks_active, 50_000);
+        assert!(minted.stats.total_fees_paid > open_fee);
+        assert_eq!(minted.stats.total_debt_minted, 60_000 * ONE_ZKUSD);
+
+        let (repaid, _) = step(VaultOpsBuilder::repay_debt(&state, &minted, ZkUsd(20_000 * ONE_ZKUSD)), BLOCK + 60_000);
+        assert_eq!(repaid.stats.blocks_active, 60_000);
+        assert_eq!(repaid.stats.total_fees_paid, minted.stats.total_fees_paid);
+        assert_eq!(
+            repaid.stats.total_interest_paid,
+            added.stats.total_interest_paid
+                + interest(&added, BLOCK + 10_000, BLOCK + 50_000)
+                + interest(&minted, BLOCK + 50_000, BLOCK + 60_000)
+        );
+        assert_eq!(repaid.stats.last_updated, BLOCK + 60_000);
+    }
+
+    type Mutation = fn(&mut Built<VaultContext>);
+
+    /// Changes to a built spell, each of which must fail validation
+    fn mutations() -> [(&'static str, Mutation); 13] {
+        [
+            ("open", |b| b.context.new_vault.as_mut().unwrap().debt += 1),
+            ("open", |b| b.context.new_state.protocol.total_collateral += 1),
+            ("open", |b| b.context.new_state.collected_fees.treasury += 1),
+            ("open", |b| b.action = VaultAction::OpenVault { collateral: Sats(2 * ONE_BTC + 1), debt: ZkUsd(50_000 * ONE_ZKUSD) }),
+            ("close", |b| b.context.new_state.protocol.active_vault_count += 1),
+            ("mint_debt", |b| b.context.new_state.protocol.rate_weighted_debt += 1),
+            ("trigger_insurance_charm", |b| b.context.new_insurance.as_mut().unwrap().coverage_btc += 1),
+            ("expire_insurance", |b| b.context.new_vault.as_mut().unwrap().insurance_balance += 1),
+            ("set_vault_operator", |b| b.context.new_vault.as_mut().unwrap().collateral += 1),
+            ("set_protection", |b| b.context.new_vault.as_mut().unwrap().interest_rate_bps -= 1),
+            ("claim_as_beneficiary", |b| b.context.new_vault.as_mut().unwrap().debt -= 1),
+            ("graduate_bootstrap", |b| b.context.new_state.protocol.bootstrap.as_mut().unwrap().graduated = false),
+            ("accrue_interest", |b| b.context.zkusd_outputs = ZkUsd(fees::MAX_INTEREST_POKE_BOUNTY + 1)),
+        ]
+    }
+
+    #[test]
+    fn test_mutated_fields_fail_validation() {
+        let built = every_action();
+        for (name, mutate) in mutations() {
+            let (_, original) = built.iter().find(|(n, _)| *n == name).unwrap();
+            let mut mutated = original.clone();
+            mutate(&mut mutated);
+            assert!(verify_locally(&mutated).is_err(), "mutated {} should fail", name);
+        }
+    }
+
+    #[test]
+    fn test_owner_spell_restarts_beneficiary_clock() {
+        let window = limits::MIN_BENEFICIARY_INACTIVITY_BLOCKS;
+        let built = build(VaultOpsBuilder::set_beneficiary(&state(), &vault(3 * ONE_BTC), Some((KEEPER, window))));
+        let (_, designated, state) = built.into_parts();
+        let designated = designated.unwrap();
+
+        let repay_at = BLOCK + window / 2;
+        let built = VaultOpsBuilder::repay_debt(&state, &designated, ZkUsd(1_000 * ONE_ZKUSD))
+            .at_price(BTC_PRICE_100K)
+            .at_block(repay_at)
+            .build()
+            .unwrap();
+        assert_eq!(verify_locally(&built), Ok(()));
+        let (_, repaid, state) = built.into_parts();
+        let repaid = repaid.unwrap();
+        assert_eq!(repaid.last_updated, repay_at);
+
+        let claim_at = |block: u64| {
+            let built = VaultOpsBuilder::claim_as_beneficiary(&state, &repaid).at_price(BTC_PRICE_100K).at_block(block);
+            verify_locally(&built.build().unwrap())
+        };
+        assert_eq!(claim_at(BLOCK + window), Err(ZkUsdError::BeneficiaryClaimTooEarly { claimable_at: repay_at + window }));
+        assert_eq!(claim_at(repay_at + window), Ok(()));
+    }
+
+    #[test]
+    fn test_registry_follows_the_vault() {
+        let mut state = state();
+        state.registry_shards = 1;
+        let healthy = vault(3 * ONE_BTC);
+        let shards = split(&[RegistryEntry::from_vault(&healthy)]).unwrap();
+
+        let built = build(
+            VaultOpsBuilder::mint_debt(&state, &healthy, ZkUsd(10_000 * ONE_ZKUSD)).with_registry(shards.clone()),
+        );
+        assert_eq!(verify_locally(&built), Ok(()));
+        assert_eq!(built.context.new_registry[0].entries[0].debt, healthy.debt + 10_000 * ONE_ZKUSD);
+
+        let built = build(VaultOpsBuilder::close_vault(&state, &healthy).with_registry(shards));
+        assert_eq!(verify_locally(&built), Ok(()));
+        assert!(built.context.new_registry[0].entries.is_empty());
+    }
+
+    #[test]
+    fn test_stale_oracle_fails_liquidation() {
+        let last_update_block = BLOCK - zkusd_common::constants::oracle::MAX_PRICE_AGE_BLOCKS - 1;
+        let stale = OracleSnapshot::active(PriceData::new(BTC_PRICE_100K, last_update_block, PriceSource::Mock));
+        let built = VaultOpsBuilder::liquidate(&state(), KEEPER, &vault(105_000_000))
+            .with_oracle(stale)
+            .at_block(BLOCK)
+            .build()
+            .unwrap();
+        assert!(matches!(verify_locally(&built), Err(ZkUsdError::OracleStale { .. })));
+    }
+
+    #[test]
+    fn test_build_requires_price() {
+        let result = VaultOpsBuilder::set_flash_fee(&state(), fees::MAX_FLASH_FEE_BPS).build();
+        assert!(matches!(result, Err(ZkUsdError::InvalidInput { param: "btc_price", .. })));
+    }
+
+    #[cfg(feature = "charms")]
+    mod differential {
+        use super::*;
+        use crate::differential::{compare, scenarios, Exception};
+        use zkusd_vault_manager::charms::validate_vault_operation;
+
+        const SIGNER_IS_VAULT_OWNER: &str = "entry point reads the owner of the first spent vault as the signer";
+        const INSURANCE_NOT_EXTRACTED: &str = "entry point does not extract insurance charms yet";
+
+        const EXCEPTIONS: &[Exception] = &[
+            Exception { scenario: "expire_insurance", reason: INSURANCE_NOT_EXTRACTED },
+            Exception { scenario: "trigger_insurance_charm (mutation 6)", reason: INSURANCE_NOT_EXTRACTED },
+            Exception { scenario: "set_flash_fee", reason: SIGNER_IS_VAULT_OWNER },
+            Exception { scenario: "set_fee_distribution", reason: SIGNER_IS_VAULT_OWNER },
+            Exception { scenario: "claim_as_beneficiary", reason: SIGNER_IS_VAULT_OWNER },
+            Exception { scenario: "accrue_interest", reason: SIGNER_IS_VAULT_OWNER },
+        ];
+
+        #[test]
+        fn test_entry_point_agrees_with_library() {
+            let divergences = compare(&scenarios(every_action(), &mutations()), EXCEPTIONS);
+            assert!(divergences.is_empty(), "{:#?}", divergences);
+        }
+
+        #[test]
+        fn test_entry_point_refuses_liquidation_without_block_height() {
+            // The runtime gives no block height, so the price's age and
+            // confidence are unknown to the entry point
+            let built = build(VaultOpsBuilder::liquidate(&state(), KEEPER, &vault(105_000_000)));
+            let spell = VaultContext::encode(&built);
+            assert!(VaultContext::validate_encoded(&spell));
+            assert!(!validate_vault_operation(&spell.app, &spell.tx, &spell.x, &spell.w));
+
+            // Spells that read no confidence still validate
+            let spell = VaultContext::encode(&build(VaultOpsBuilder::set_flash_fee(&state(), 10)));
+            assert_eq!(
+                validate_vault_operation(&spell.app, &spell.tx, &spell.x, &spell.w),
+                VaultContext::validate_encoded(&spell)
+            );
+        }
+    }
+}
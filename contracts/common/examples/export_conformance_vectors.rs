@@ -0,0 +1,25 @@
+//! Export conformance test vectors as JSON
+//!
+//! Usage:
+//!   cargo run -p zkusd-common --example export_conformance_vectors --features conformance [OUT]
+//!
+//! Writes to `target/conformance/zkusd-test-vectors.json` unless OUT is given.
+
+use std::{env, fs, path::PathBuf};
+
+use zkusd_common::conformance::{test_vectors, test_vectors_json};
+
+fn main() -> std::io::Result<()> {
+    let out = env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target/conformance/zkusd-test-vectors.json"));
+
+    if let Some(dir) = out.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&out, test_vectors_json())?;
+
+    println!("Wrote {} vectors to {}", test_vectors().len(), out.display());
+    Ok(())
+}
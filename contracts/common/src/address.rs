@@ -0,0 +1,333 @@
+//! Address Derivation and Display for zkUSD Protocol
+//!
+//! One canonical encoding for [`Address`] values, so integrators stop mixing
+//! hex and base58 in config files and tools.
+//!
+//! - `derive_address()`: an address is the sha256 of the owner's 33-byte
+//!   compressed secp256k1 public key
+//! - `to_display()` / `parse_display()`: bech32m (BIP-350) strings under a
+//!   zkUSD human-readable part (`std` feature)
+//! - `is_zero()`: the unset/placeholder address check used by validators
+//!
+//! The 32 address bytes are encoded in array order, most significant bit
+//! first; no byte is ever reversed, so a string copied between tools always
+//! names the same address.
+//!
+//! ```text
+//! [0u8; 32]  ->  zkusd1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyxdsfu
+//! ```
+
+use sha2::{Digest, Sha256};
+
+use crate::types::Address;
+#[cfg(feature = "std")]
+use crate::errors::{ZkUsdError, ZkUsdResult};
+#[cfg(feature = "std")]
+use bech32m::{from_groups, hrp_expand, polymod, to_groups, BECH32M_CONST, CHARSET, CHECKSUM_LEN, DATA_LEN};
+
+// ============ Constants ============
+
+/// Human-readable part for mainnet addresses
+pub const MAINNET_HRP: &str = "zkusd";
+
+/// Human-readable part for testnet addresses
+pub const TESTNET_HRP: &str = "tzkusd";
+
+/// Human-readable part for the network this build targets
+#[cfg(feature = "mainnet")]
+pub const HRP: &str = MAINNET_HRP;
+#[cfg(not(feature = "mainnet"))]
+pub const HRP: &str = TESTNET_HRP;
+
+// ============ Core Helpers ============
+
+/// Whether `addr` is the all-zero address
+///
+/// Zero marks an unset owner, minter or app id, and placeholder witnesses.
+pub fn is_zero(addr: &Address) -> bool {
+    *addr == [0u8; 32]
+}
+
+/// Derive the address owned by a compressed secp256k1 public key
+///
+/// `address = sha256(pubkey)` over the 33 SEC1 bytes (`0x02`/`0x03` prefix
+/// followed by the big-endian x coordinate), with no domain tag.
+pub fn derive_address(pubkey: &[u8; 33]) -> Address {
+    Sha256::digest(pubkey).into()
+}
+
+// ============ Display ============
+
+/// bech32m (BIP-350) encoding primitives
+#[cfg(feature = "std")]
+mod bech32m {
+    use crate::types::Address;
+
+    /// bech32 data alphabet, indexed by 5-bit value
+    pub(super) const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    /// BCH generator coefficients of the bech32 checksum
+    pub(super) const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+
+    /// Checksum residue that marks bech32m, as opposed to bech32's 1
+    pub(super) const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+    /// Checksum length in characters
+    pub(super) const CHECKSUM_LEN: usize = 6;
+
+    /// 32 bytes regrouped into 5-bit values: 256 bits round up to 52 groups,
+    /// the last padded with four zero bits
+    pub(super) const DATA_LEN: usize = 52;
+
+    /// bech32 checksum polynomial over 5-bit values
+    pub(super) fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+        let mut chk: u32 = 1;
+        for value in values {
+            let top = chk >> 25;
+            chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(value);
+            for (i, generator) in GENERATOR.iter().enumerate() {
+                if (top >> i) & 1 == 1 {
+                    chk ^= generator;
+                }
+            }
+        }
+        chk
+    }
+
+    /// Human-readable part as checksum input: high bits, a zero, low bits
+    pub(super) fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+        hrp.bytes()
+            .map(|c| c >> 5)
+            .chain(core::iter::once(0))
+            .chain(hrp.bytes().map(|c| c & 31))
+    }
+
+    /// Regroup address bytes into 5-bit values, zero-padding the last group
+    pub(super) fn to_groups(addr: &Address) -> [u8; DATA_LEN] {
+        let mut groups = [0u8; DATA_LEN];
+        let (mut acc, mut bits, mut n) = (0u32, 0u32, 0);
+        for &byte in addr {
+            acc = ((acc << 8) | u32::from(byte)) & 0xfff;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                groups[n] = ((acc >> bits) & 31) as u8;
+                n += 1;
+            }
+        }
+        groups[n] = ((acc << (5 - bits)) & 31) as u8;
+        groups
+    }
+
+    /// Regroup 5-bit values into address bytes; `None` unless the padding bits
+    /// are zero
+    pub(super) fn from_groups(groups: &[u8; DATA_LEN]) -> Option<Address> {
+        let mut addr = [0u8; 32];
+        let (mut acc, mut bits, mut n) = (0u32, 0u32, 0);
+        for &group in groups {
+            acc = ((acc << 5) | u32::from(group)) & 0xfff;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                addr[n] = (acc >> bits) as u8;
+                n += 1;
+            }
+        }
+        (acc & ((1 << bits) - 1) == 0).then_some(addr)
+    }
+}
+
+/// Encode `addr` as a bech32m string under `hrp`
+///
+/// `hrp` is expected to be lowercase ASCII, normally [`HRP`]. The result is
+/// always lowercase.
+#[cfg(feature = "std")]
+pub fn to_display(addr: &Address, hrp: &str) -> String {
+    debug_assert!(hrp.bytes().all(|c| (33..=126).contains(&c) && !c.is_ascii_uppercase()));
+
+    let groups = to_groups(addr);
+    let residue = polymod(
+        hrp_expand(hrp).chain(groups).chain([0u8; CHECKSUM_LEN]),
+    ) ^ BECH32M_CONST;
+
+    let mut out = String::with_capacity(hrp.len() + 1 + DATA_LEN + CHECKSUM_LEN);
+    out.push_str(hrp);
+    out.push('1');
+    out.extend(groups.iter().map(|&g| CHARSET[usize::from(g)] as char));
+    out.extend((0..CHECKSUM_LEN).map(|i| {
+        CHARSET[((residue >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as usize] as char
+    }));
+    out
+}
+
+/// Parse a bech32m address string for the network this build targets
+#[cfg(feature = "std")]
+pub fn parse_display(s: &str) -> ZkUsdResult<Address> {
+    parse_display_with_hrp(s, HRP)
+}
+
+/// Parse a bech32m address string, requiring the human-readable part `hrp`
+///
+/// Strings are accepted all-lowercase or all-uppercase. Each failure names
+/// its cause in `InvalidAddress::reason`.
+#[cfg(feature = "std")]
+pub fn parse_display_with_hrp(s: &str, hrp: &str) -> ZkUsdResult<Address> {
+    let invalid = |reason| ZkUsdError::InvalidAddress { reason };
+
+    if s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()) {
+        return Err(invalid("mixed case"));
+    }
+    let s = s.to_ascii_lowercase();
+
+    let (prefix, data) = s.rsplit_once('1').ok_or(invalid("missing separator"))?;
+    if prefix != hrp {
+        return Err(invalid("wrong hrp"));
+    }
+    if data.len() != DATA_LEN + CHECKSUM_LEN {
+        return Err(invalid("wrong length"));
+    }
+
+    let values = data
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&d| d == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(invalid("invalid character"))?;
+
+    if polymod(hrp_expand(hrp).chain(values.iter().copied())) != BECH32M_CONST {
+        return Err(invalid("bad checksum"));
+    }
+
+    let mut groups = [0u8; DATA_LEN];
+    groups.copy_from_slice(&values[..DATA_LEN]);
+    from_groups(&groups).ok_or(invalid("non-zero padding"))
+}
+
+// ============ Tests ============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(bytes, mainnet string, testnet string)` pinned for wallet teams
+    fn vectors() -> Vec<(Address, &'static str, &'static str)> {
+        let mut counting = [0u8; 32];
+        for (i, byte) in counting.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        vec![
+            (
+                [0u8; 32],
+                "zkusd1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyxdsfu",
+                "tzkusd1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqeul38h",
+            ),
+            (
+                [0xff; 32],
+                "zkusd1lllllllllllllllllllllllllllllllllllllllllllllllllllsxcwpqt",
+                "tzkusd1lllllllllllllllllllllllllllllllllllllllllllllllllllsmzuqwq",
+            ),
+            (
+                counting,
+                "zkusd1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0sdjxjf9",
+                "tzkusd1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0ssg5n8w",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_display_vectors() {
+        for (addr, mainnet, testnet) in vectors() {
+            assert_eq!(to_display(&addr, MAINNET_HRP), mainnet);
+            assert_eq!(to_display(&addr, TESTNET_HRP), testnet);
+            assert_eq!(parse_display_with_hrp(mainnet, MAINNET_HRP), Ok(addr));
+            assert_eq!(parse_display_with_hrp(testnet, TESTNET_HRP), Ok(addr));
+            assert_eq!(parse_display_with_hrp(&mainnet.to_uppercase(), MAINNET_HRP), Ok(addr));
+        }
+    }
+
+    #[test]
+    fn test_derive_address_vector() {
+        let mut pubkey = [0x11u8; 33];
+        pubkey[0] = 0x02;
+        let addr = derive_address(&pubkey);
+        assert_eq!(
+            to_display(&addr, MAINNET_HRP),
+            "zkusd1u9thwq8pylcuum3q5nhu9kt2np5hn364mx44txhkk964avlnyg8qt9hgym"
+        );
+        assert_eq!(addr[..4], [0xe1, 0x57, 0x77, 0x00]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Deterministic pseudo-random addresses through both networks
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..256 {
+            let mut addr = [0u8; 32];
+            for byte in addr.iter_mut() {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                *byte = (seed >> 56) as u8;
+            }
+            for hrp in [MAINNET_HRP, TESTNET_HRP] {
+                assert_eq!(parse_display_with_hrp(&to_display(&addr, hrp), hrp), Ok(addr));
+            }
+            assert_eq!(parse_display(&to_display(&addr, HRP)), Ok(addr));
+        }
+    }
+
+    #[test]
+    fn test_rejects_corrupted_strings() {
+        let reason = |s: &str| match parse_display_with_hrp(s, MAINNET_HRP) {
+            Err(ZkUsdError::InvalidAddress { reason }) => reason,
+            other => panic!("{} should be rejected, got {:?}", s, other),
+        };
+        let (_, valid, testnet) = vectors()[2];
+
+        // Every single-character substitution breaks the checksum
+        for i in "zkusd1".len()..valid.len() {
+            let mut corrupted = valid.as_bytes().to_vec();
+            corrupted[i] = if corrupted[i] == b'q' { b'p' } else { b'q' };
+            assert_eq!(reason(core::str::from_utf8(&corrupted).unwrap()), "bad checksum");
+        }
+
+        // Swapped adjacent characters
+        let mut swapped = valid.as_bytes().to_vec();
+        swapped.swap(10, 11);
+        assert_eq!(reason(core::str::from_utf8(&swapped).unwrap()), "bad checksum");
+
+        assert_eq!(reason(testnet), "wrong hrp");
+        assert_eq!(reason(&valid[..valid.len() - 1]), "wrong length");
+        assert_eq!(reason("zkusdqqqq"), "missing separator");
+        assert_eq!(reason(&valid.replacen('q', "b", 1)), "invalid character");
+        assert_eq!(reason(&valid.replacen('q', "Q", 1)), "mixed case");
+    }
+
+    #[test]
+    fn test_rejects_bech32_checksum() {
+        // The same data under a bech32 (constant 1) checksum is not bech32m
+        let addr = [7u8; 32];
+        let groups = to_groups(&addr);
+        let residue = polymod(hrp_expand(MAINNET_HRP).chain(groups).chain([0u8; CHECKSUM_LEN])) ^ 1;
+        let mut s = String::from("zkusd1");
+        s.extend(groups.iter().map(|&g| CHARSET[usize::from(g)] as char));
+        s.extend((0..CHECKSUM_LEN).map(|i| CHARSET[((residue >> (5 * (5 - i))) & 31) as usize] as char));
+
+        assert_eq!(
+            parse_display_with_hrp(&s, MAINNET_HRP),
+            Err(ZkUsdError::InvalidAddress { reason: "bad checksum" })
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_zero_padding() {
+        let mut groups = to_groups(&[0u8; 32]);
+        groups[DATA_LEN - 1] = 1;
+        assert_eq!(from_groups(&groups), None);
+    }
+
+    #[test]
+    fn test_is_zero() {
+        assert!(is_zero(&[0u8; 32]));
+        let mut addr = [0u8; 32];
+        addr[31] = 1;
+        assert!(!is_zero(&addr));
+    }
+}
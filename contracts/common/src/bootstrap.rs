@@ -0,0 +1,250 @@
+//! Bootstrap Mode
+//!
+//! A launch mode in which protocol-controlled value (PCV) seeds the
+//! Stability Pool, only whitelisted signers may open vaults and total debt
+//! is capped. Borrowing fees repay the PCV bootstrap loan first, per
+//! `ProtocolControlledValue::fee_to_bootstrap`, and only the rest is split
+//! across the fee destinations.
+//!
+//! ## Graduation
+//!
+//! Anyone may graduate the protocol to permissionless mode once:
+//!
+//! - `GRADUATION_MIN_BLOCKS` have passed since bootstrap started,
+//! - the Stability Pool holds at least `GRADUATION_MIN_SP_COVERAGE_BPS` of
+//!   total debt, and
+//! - no spell has seen the system in Recovery Mode during bootstrap.
+//!
+//! Graduation is permanent. Fees keep repaying the bootstrap loan after it
+//! until the loan is repaid.
+//!
+//! ## Whitelist
+//!
+//! The whitelist is committed to as the root of a Merkle tree over
+//! `sha256(WHITELIST_LEAF_DOMAIN || address)` leaves. Each pair of nodes is
+//! hashed in sorted order, so a proof is just the sibling hashes from leaf
+//! to root; a node without a sibling moves up a level unchanged.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::constants::{fees, pcv};
+use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::types::{Address, ProtocolControlledValue};
+use crate::Vec;
+
+/// Domain tag hashed with an address to form its whitelist leaf
+const WHITELIST_LEAF_DOMAIN: &[u8; 16] = b"zkusd/whitelist/";
+
+/// Bootstrap mode state, held in `ProtocolState` from launch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct BootstrapState {
+    /// Merkle root of the addresses allowed to open vaults
+    pub whitelist_root: [u8; 32],
+    /// Most total debt the protocol may carry during bootstrap
+    pub debt_cap: u64,
+    /// Block bootstrap mode started at
+    pub started_at: u64,
+    /// Whether the protocol has graduated to permissionless mode (permanent)
+    pub graduated: bool,
+    /// Whether any spell saw the system in Recovery Mode during bootstrap
+    pub recovery_mode_seen: bool,
+    /// Stability Pool seed and the bootstrap loan fees repay
+    pub pcv: ProtocolControlledValue,
+}
+
+impl BootstrapState {
+    /// Start bootstrap mode at `started_at` with a bootstrap loan seeding
+    /// the Stability Pool
+    pub fn new(whitelist_root: [u8; 32], debt_cap: u64, started_at: u64, bootstrap_loan: u64) -> Self {
+        Self {
+            whitelist_root,
+            debt_cap,
+            started_at,
+            graduated: false,
+            recovery_mode_seen: false,
+            pcv: ProtocolControlledValue::new(bootstrap_loan),
+        }
+    }
+
+    /// Returns true until the protocol graduates
+    pub fn is_active(&self) -> bool {
+        !self.graduated
+    }
+
+    /// Require `signer` to prove membership of the whitelist
+    pub fn require_whitelisted(&self, signer: &Address, proof: &[[u8; 32]]) -> ZkUsdResult<()> {
+        if !verify_whitelist(&self.whitelist_root, signer, proof) {
+            return Err(ZkUsdError::NotWhitelisted { address: *signer });
+        }
+        Ok(())
+    }
+
+    /// Require the protocol's total debt to be within the bootstrap cap
+    pub fn require_within_cap(&self, total_debt: u64) -> ZkUsdResult<()> {
+        if total_debt > self.debt_cap {
+            return Err(ZkUsdError::BootstrapDebtCapExceeded { total_debt, cap: self.debt_cap });
+        }
+        Ok(())
+    }
+
+    /// Require every graduation criterion to hold at `block_height`, for a
+    /// protocol with `total_debt` and a Stability Pool holding `pool_zkusd`
+    pub fn require_graduation(&self, total_debt: u64, pool_zkusd: u64, block_height: u64) -> ZkUsdResult<()> {
+        let not_met = |reason| Err(ZkUsdError::GraduationCriteriaNotMet { reason });
+        if !self.is_active() {
+            return Err(ZkUsdError::BootstrapNotActive);
+        }
+        if block_height.saturating_sub(self.started_at) < pcv::GRADUATION_MIN_BLOCKS {
+            return not_met("bootstrap period not over");
+        }
+        if self.recovery_mode_seen {
+            return not_met("recovery mode seen during bootstrap");
+        }
+        let coverage_bps = pool_zkusd as u128 * fees::BPS_DENOMINATOR as u128;
+        if coverage_bps < total_debt as u128 * pcv::GRADUATION_MIN_SP_COVERAGE_BPS as u128 {
+            return not_met("stability pool coverage too low");
+        }
+        Ok(())
+    }
+
+    /// Take the bootstrap loan's share of a borrowing fee, returning it
+    pub fn repay_from_fee(&mut self, fee: u64) -> u64 {
+        let repaid = self.pcv.fee_to_bootstrap(fee);
+        self.pcv.bootstrap_debt -= repaid;
+        self.pcv.accumulated_fees = self.pcv.accumulated_fees.saturating_add(repaid);
+        repaid
+    }
+}
+
+/// Whitelist leaf of an address
+pub fn whitelist_leaf(address: &Address) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(WHITELIST_LEAF_DOMAIN);
+    hasher.update(address);
+    hasher.finalize().into()
+}
+
+/// Hash two nodes into their parent, in sorted order
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    let mut hasher = Sha256::new();
+    hasher.update(low);
+    hasher.update(high);
+    hasher.finalize().into()
+}
+
+/// Next level of the tree above `level`
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_pair(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// Whitelist root over `addresses` (all zeros for an empty whitelist)
+pub fn whitelist_root(addresses: &[Address]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = addresses.iter().map(whitelist_leaf).collect();
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level[0]
+}
+
+/// Proof of `address`'s membership of the whitelist over `addresses`
+pub fn whitelist_proof(addresses: &[Address], address: &Address) -> Option<Vec<[u8; 32]>> {
+    let mut index = addresses.iter().position(|a| a == address)?;
+    let mut level: Vec<[u8; 32]> = addresses.iter().map(whitelist_leaf).collect();
+    let mut proof = Vec::new();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = parent_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Check `proof` shows `address` is on the whitelist committed to by `root`
+pub fn verify_whitelist(root: &[u8; 32], address: &Address, proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(whitelist_leaf(address), |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::token::ONE;
+
+    const ADDRESSES: [Address; 5] = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32], [5u8; 32]];
+
+    fn bootstrap() -> BootstrapState {
+        BootstrapState::new(whitelist_root(&ADDRESSES), 1_000_000 * ONE, 100, 100_000 * ONE)
+    }
+
+    #[test]
+    fn test_whitelist_proofs() {
+        let root = whitelist_root(&ADDRESSES);
+        for address in ADDRESSES {
+            let proof = whitelist_proof(&ADDRESSES, &address).unwrap();
+            assert!(verify_whitelist(&root, &address, &proof));
+            // The proof is for this address only
+            assert!(!verify_whitelist(&root, &[9u8; 32], &proof));
+        }
+        assert!(whitelist_proof(&ADDRESSES, &[9u8; 32]).is_none());
+
+        let mut tampered = whitelist_proof(&ADDRESSES, &ADDRESSES[0]).unwrap();
+        tampered[0][0] ^= 1;
+        assert!(!verify_whitelist(&root, &ADDRESSES[0], &tampered));
+        assert_eq!(
+            bootstrap().require_whitelisted(&[9u8; 32], &tampered),
+            Err(ZkUsdError::NotWhitelisted { address: [9u8; 32] })
+        );
+    }
+
+    #[test]
+    fn test_graduation_criteria() {
+        let bootstrap = bootstrap();
+        let debt = 500_000 * ONE;
+        let covered = debt / 5;
+        let due = 100 + pcv::GRADUATION_MIN_BLOCKS;
+
+        assert_eq!(bootstrap.require_graduation(debt, covered, due), Ok(()));
+        assert!(matches!(
+            bootstrap.require_graduation(debt, covered, due - 1),
+            Err(ZkUsdError::GraduationCriteriaNotMet { .. })
+        ));
+        assert!(matches!(
+            bootstrap.require_graduation(debt, covered - 1, due),
+            Err(ZkUsdError::GraduationCriteriaNotMet { .. })
+        ));
+        let stressed = BootstrapState { recovery_mode_seen: true, ..bootstrap.clone() };
+        assert!(stressed.require_graduation(debt, covered, due).is_err());
+        let graduated = BootstrapState { graduated: true, ..bootstrap };
+        assert_eq!(graduated.require_graduation(debt, covered, due), Err(ZkUsdError::BootstrapNotActive));
+    }
+
+    #[test]
+    fn test_fees_repay_bootstrap_loan() {
+        let mut bootstrap = bootstrap();
+        // Half of each fee repays the loan
+        assert_eq!(bootstrap.repay_from_fee(1_000 * ONE), 500 * ONE);
+        assert_eq!(bootstrap.pcv.bootstrap_debt, 99_500 * ONE);
+
+        // Never more than the loan outstanding, then nothing
+        assert_eq!(bootstrap.repay_from_fee(1_000_000 * ONE), 99_500 * ONE);
+        assert!(bootstrap.pcv.is_bootstrap_repaid());
+        assert_eq!(bootstrap.repay_from_fee(1_000 * ONE), 0);
+        assert_eq!(bootstrap.pcv.accumulated_fees, 100_000 * ONE);
+    }
+}
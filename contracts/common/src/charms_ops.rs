@@ -44,17 +44,20 @@
 //! - RGB Protocol: https://rgb-org.github.io/
 
 use crate::{
-    constants::fees::BPS_DENOMINATOR,
+    constants::{fees::BPS_DENOMINATOR, token::ONE},
     errors::{ZkUsdError, ZkUsdResult},
-    math::calculate_icr,
+    math::{calculate_icr, safe_add, saturating_u64},
     types::*,
+    units::{Sats, ZkUsd},
     Vec,
 };
 
 // ============ Constants ============
 
-/// Flash mint fee in basis points (0.05% = 5 bps, same as our existing config)
-pub const FLASH_MINT_FEE_BPS: u64 = 5;
+/// Default flash mint fee in basis points (0.05% = 5 bps)
+///
+/// The live fee is `ProtocolState::flash_fee_bps`, set by the admin.
+pub const FLASH_MINT_FEE_BPS: u64 = crate::constants::fees::DEFAULT_FLASH_FEE_BPS;
 
 /// Maximum flash mint per spell (10M zkUSD)
 pub const MAX_FLASH_MINT_PER_SPELL: u64 = 10_000_000_00000000;
@@ -119,6 +122,121 @@ pub struct SpellRescue {
     pub expires_at: u64,
 }
 
+impl From<&Vault> for SpellVault {
+    fn from(vault: &Vault) -> Self {
+        Self {
+            id: vault.id,
+            owner: vault.owner,
+            collateral: vault.collateral,
+            debt: vault.debt,
+            interest_rate_bps: vault.interest_rate_bps,
+        }
+    }
+}
+
+impl From<&InsuranceCharm> for SpellInsurance {
+    fn from(charm: &InsuranceCharm) -> Self {
+        Self {
+            charm_id: charm.charm_id,
+            vault_id: charm.vault_id,
+            owner: charm.owner,
+            coverage_btc: charm.coverage_btc,
+            trigger_icr: charm.trigger_icr,
+            expires_at: charm.expires_at,
+            is_triggered: charm.is_triggered,
+        }
+    }
+}
+
+// ============ Charm-State Valuation ============
+
+impl ZkUsdCharmState {
+    /// Value of everything in the state, in zkUSD base units scaled by 1e8
+    ///
+    /// Sums loose BTC and zkUSD, each vault's net value (collateral worth
+    /// less debt, nothing for an underwater vault) and the coverage
+    /// escrowed by untriggered insurance charms. The scaling values BTC
+    /// exactly, so two states compare without rounding.
+    pub fn total_value(&self, btc_price: u64) -> u128 {
+        let btc_value = |sats: u64| (sats as u128).saturating_mul(btc_price as u128);
+        let zkusd_value = |amount: u64| amount as u128 * ONE as u128;
+
+        let vaults = self
+            .vaults
+            .iter()
+            .map(|v| btc_value(v.collateral).saturating_sub(zkusd_value(v.debt)));
+        let escrow = self
+            .insurance_charms
+            .iter()
+            .filter(|charm| !charm.is_triggered)
+            .map(|charm| btc_value(charm.coverage_btc));
+
+        vaults
+            .chain(escrow)
+            .fold(btc_value(self.btc_amount).saturating_add(zkusd_value(self.zkusd_amount)), u128::saturating_add)
+    }
+
+    /// Debt carried by the state's vaults
+    fn vault_debt(&self) -> u64 {
+        self.vaults.iter().fold(0, |total, v| total.saturating_add(v.debt))
+    }
+}
+
+/// Check that a spell moves no value out of its charms beyond
+/// `allowed_delta` (zkUSD the protocol keeps as fees)
+///
+/// Loose balances alone cannot show value leaving through a vault or
+/// insurance charm the spell also rewrites, so whole charm states are
+/// compared. Value may only grow, e.g. from BTC the spell brings in.
+pub fn verify_value_conserved(
+    input_state: &ZkUsdCharmState,
+    output_state: &ZkUsdCharmState,
+    btc_price: u64,
+    allowed_delta: u64,
+) -> ZkUsdResult<()> {
+    let input_value = input_state.total_value(btc_price);
+    let output_value = output_state
+        .total_value(btc_price)
+        .saturating_add(allowed_delta as u128 * ONE as u128);
+    if input_value > output_value {
+        let unscaled = |value: u128| (value / ONE as u128).min(u64::MAX as u128) as u64;
+        return Err(ZkUsdError::ConservationViolated {
+            inputs: unscaled(input_value),
+            outputs: unscaled(output_value),
+        });
+    }
+    Ok(())
+}
+
+// ============ Spell BTC Conservation ============
+
+/// Check that a spell's declared inputs and outputs conserve BTC: the
+/// satoshis spent equal the satoshis created plus `fee` (the miner fee)
+///
+/// Each app checks its own asset, so a spell could otherwise create or
+/// destroy BTC between UTXOs no single app accounts for. Any app's entry
+/// point can run this over the whole spell; both a surplus and a shortfall
+/// are rejected.
+pub fn validate_spell_btc_conservation(
+    inputs: &[SpellInput],
+    outputs: &[SpellOutput],
+    fee: u64,
+) -> ZkUsdResult<()> {
+    let spent = inputs
+        .iter()
+        .try_fold(0, |total, input| safe_add(total, input.btc_amount))?;
+    let created = outputs
+        .iter()
+        .try_fold(fee, |total, output| safe_add(total, output.btc_amount))?;
+    if spent != created {
+        return Err(ZkUsdError::ConservationViolated {
+            inputs: spent,
+            outputs: created,
+        });
+    }
+    Ok(())
+}
+
 // ============ UTXO-Native Flash Minting ============
 
 /// Flash mint request in UTXO model
@@ -130,7 +248,7 @@ pub struct SpellRescue {
 pub struct SpellFlashMint {
     /// Amount being flash minted
     pub mint_amount: u64,
-    /// Fee that must be paid (calculated automatically)
+    /// Fee that must be paid (see `calculate_flash_fee_bps`)
     pub fee: u64,
     /// Purpose of the flash mint (for logging/tracking)
     pub purpose: FlashMintPurpose,
@@ -153,16 +271,55 @@ pub enum FlashMintPurpose {
     Custom,
 }
 
+impl FlashMintPurpose {
+    /// Allowlist mask allowing every purpose
+    pub const ALL: u8 = 0b0011_1111;
+
+    /// This purpose's bit in an allowlist mask
+    pub const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Returns true if the allowlist `mask` allows this purpose
+    pub const fn is_allowed_by(self, mask: u8) -> bool {
+        mask & self.bit() != 0
+    }
+}
+
 /// Validate a flash mint spell
 ///
 /// In UTXO model, flash mint validation is simple:
-/// 1. Check the spell's total zkUSD output >= input + fee
-/// 2. Verify amount is within bounds
-/// 3. The atomicity is guaranteed by UTXO model
+/// 1. Verify amount is within bounds
+/// 2. Check the spell's zkUSD balances with the principal repaid:
+///    `inputs + mint + vault_minted == outputs + repaid + fee`, where
+///    `vault_minted` is zkUSD issued by vault actions in the same spell
+///    (a leveraged open repays the flash mint from the new vault's debt)
+/// 3. Check no value leaves the spell's charms beyond the fee and the
+///    debt its vaults took on without issuing it (borrowing fees, the
+///    liquidation reserve), which the protocol keeps
+/// 4. The atomicity is guaranteed by UTXO model
 pub fn validate_flash_mint_spell(
     input_state: &ZkUsdCharmState,
     output_state: &ZkUsdCharmState,
     flash_mint: &SpellFlashMint,
+    vault_minted: u64,
+    btc_price: u64,
+) -> ZkUsdResult<FlashMintValidation> {
+    let validation = check_flash_mint(input_state, output_state, flash_mint, vault_minted)?;
+    let retained = output_state
+        .vault_debt()
+        .saturating_sub(input_state.vault_debt())
+        .saturating_sub(vault_minted);
+    verify_value_conserved(input_state, output_state, btc_price, safe_add(validation.fee_paid, retained)?)?;
+    Ok(validation)
+}
+
+/// Flash mint checks short of value conservation
+fn check_flash_mint(
+    input_state: &ZkUsdCharmState,
+    output_state: &ZkUsdCharmState,
+    flash_mint: &SpellFlashMint,
+    vault_minted: u64,
 ) -> ZkUsdResult<FlashMintValidation> {
     // Validate amount bounds
     if flash_mint.mint_amount < MIN_FLASH_MINT {
@@ -179,36 +336,29 @@ pub fn validate_flash_mint_spell(
         });
     }
 
-    // Calculate required fee
-    let required_fee = calculate_flash_fee(flash_mint.mint_amount);
-
-    // In UTXO model: output_zkusd must be >= input_zkusd
-    // The flash minted amount is "used" within the spell
-    // Fee must be present as burnt/collected zkUSD
-    let zkusd_delta = output_state.zkusd_amount as i128 - input_state.zkusd_amount as i128;
-
-    // If flash mint is used for operations that don't return zkUSD (like self-liquidation),
-    // the output might have less zkUSD but the fee must still be paid
-    let fee_paid = if zkusd_delta >= 0 {
-        // Net positive: some fee might be included in output
-        required_fee
-    } else {
-        // Net negative: fee must come from the "saved" amount
-        // This is valid for self-liquidation where you burn zkUSD to free collateral
-        let deficit = (-zkusd_delta) as u64;
-        if deficit > flash_mint.mint_amount {
-            return Err(ZkUsdError::InsufficientBalance {
-                available: flash_mint.mint_amount,
-                requested: deficit,
-            });
-        }
-        required_fee
-    };
+    // Required fee is computed by the caller from the configured rate
+    let required_fee = flash_mint.fee;
+
+    // In UTXO model: whatever the spell does not output or pay as the fee
+    // is burned, and the burn must cover the flash minted principal.
+    // Burning more is allowed (self-liquidation repays vault debt too)
+    let available = safe_add(
+        safe_add(input_state.zkusd_amount, flash_mint.mint_amount)?,
+        vault_minted,
+    )?;
+    let kept = safe_add(output_state.zkusd_amount, required_fee)?;
+    let repaid = available.saturating_sub(kept);
+    if repaid < flash_mint.mint_amount {
+        return Err(ZkUsdError::ConservationViolated {
+            inputs: available,
+            outputs: safe_add(kept, flash_mint.mint_amount)?,
+        });
+    }
 
     Ok(FlashMintValidation {
         is_valid: true,
         mint_amount: flash_mint.mint_amount,
-        fee_paid,
+        fee_paid: required_fee,
         purpose: flash_mint.purpose,
         input_zkusd: input_state.zkusd_amount,
         output_zkusd: output_state.zkusd_amount,
@@ -226,9 +376,14 @@ pub struct FlashMintValidation {
     pub output_zkusd: u64,
 }
 
-/// Calculate flash mint fee
+/// Calculate flash mint fee at the default rate
 pub fn calculate_flash_fee(amount: u64) -> u64 {
-    (amount as u128 * FLASH_MINT_FEE_BPS as u128 / BPS_DENOMINATOR as u128) as u64
+    calculate_flash_fee_bps(amount, FLASH_MINT_FEE_BPS)
+}
+
+/// Calculate flash mint fee at a given rate (in basis points)
+pub fn calculate_flash_fee_bps(amount: u64, fee_bps: u64) -> u64 {
+    (amount as u128 * fee_bps as u128 / BPS_DENOMINATOR as u128) as u64
 }
 
 // ============ UTXO-Native Atomic Rescue ============
@@ -241,13 +396,28 @@ pub fn calculate_flash_fee(amount: u64) -> u64 {
 /// 3. Repays some/all debt
 /// 4. Creates new vault UTXO with improved health
 ///
-/// All participants must sign the spell (vault owner + rescuer)
+/// All participants must sign the spell (vault owner + rescuer), and the
+/// rescuer's collateral and repayment must come from the spell's own
+/// inputs: no value leaves the charms
 pub fn validate_rescue_spell(
     input_state: &ZkUsdCharmState,
     output_state: &ZkUsdCharmState,
     rescue: &SpellRescue,
     btc_price: u64,
     current_block: u64,
+) -> ZkUsdResult<RescueValidation> {
+    let validation = check_rescue(input_state, output_state, rescue, btc_price, current_block)?;
+    verify_value_conserved(input_state, output_state, btc_price, 0)?;
+    Ok(validation)
+}
+
+/// Rescue checks short of value conservation
+fn check_rescue(
+    input_state: &ZkUsdCharmState,
+    output_state: &ZkUsdCharmState,
+    rescue: &SpellRescue,
+    btc_price: u64,
+    current_block: u64,
 ) -> ZkUsdResult<RescueValidation> {
     // Find input vault
     let input_vault = input_state.vaults.iter()
@@ -263,7 +433,7 @@ pub fn validate_rescue_spell(
     }
 
     // Calculate ICR before rescue
-    let icr_before = calculate_icr(input_vault.collateral, input_vault.debt, btc_price)?;
+    let icr_before = calculate_icr(Sats(input_vault.collateral), ZkUsd(input_vault.debt), btc_price)?;
 
     // Find output vault
     let output_vault = output_state.vaults.iter()
@@ -289,7 +459,7 @@ pub fn validate_rescue_spell(
     }
 
     // Calculate ICR after rescue
-    let icr_after = calculate_icr(output_vault.collateral, output_vault.debt, btc_price)?;
+    let icr_after = calculate_icr(Sats(output_vault.collateral), ZkUsd(output_vault.debt), btc_price)?;
 
     // Verify minimum ICR requirement
     if icr_after < rescue.min_icr_after {
@@ -334,7 +504,7 @@ fn calculate_rescue_bonus(icr: u64, collateral: u64, _btc_price: u64) -> u64 {
         return 0;
     }
     let surplus_ratio = icr - 150;
-    (collateral as u128 * surplus_ratio as u128 * 50 / 10000 / 100) as u64
+    saturating_u64(collateral as u128 * surplus_ratio as u128 * 50 / 10000 / 100)
 }
 
 /// Create a rescue offer (to be included in spell)
@@ -440,7 +610,7 @@ impl InsuranceCharmOps {
             100
         };
 
-        (base * icr_multiplier as u128 / 100) as u64
+        saturating_u64(base * icr_multiplier as u128 / 100)
     }
 
     /// Validate insurance transfer in spell
@@ -533,7 +703,7 @@ impl InsuranceCharmOps {
             .ok_or(ZkUsdError::VaultNotFound { vault_id: input_charm.vault_id })?;
 
         // Check ICR trigger condition
-        let icr_before = calculate_icr(input_vault.collateral, input_vault.debt, btc_price)?;
+        let icr_before = calculate_icr(Sats(input_vault.collateral), ZkUsd(input_vault.debt), btc_price)?;
         if icr_before > input_charm.trigger_icr {
             return Err(ZkUsdError::InvalidInput {
                 param: "trigger_icr",
@@ -555,7 +725,7 @@ impl InsuranceCharmOps {
             });
         }
 
-        let icr_after = calculate_icr(output_vault.collateral, output_vault.debt, btc_price)?;
+        let icr_after = calculate_icr(Sats(output_vault.collateral), ZkUsd(output_vault.debt), btc_price)?;
 
         // Charm should be consumed (not in outputs) or marked as triggered
         let charm_consumed = !output_state.insurance_charms.iter()
@@ -597,7 +767,8 @@ pub struct InsuranceTriggerValidation {
 /// Validate a complete zkUSD spell
 ///
 /// This is the main entry point for validating zkUSD state transitions.
-/// Checks all operations: flash mints, rescues, insurance triggers, etc.
+/// Checks all operations: flash mints, rescues, insurance triggers, etc.,
+/// then that the spell as a whole loses no value beyond its fees.
 pub fn validate_zkusd_spell(
     input_state: &ZkUsdCharmState,
     output_state: &ZkUsdCharmState,
@@ -609,13 +780,14 @@ pub fn validate_zkusd_spell(
 
     // Validate flash mints
     for flash_mint in &operations.flash_mints {
-        let validation = validate_flash_mint_spell(input_state, output_state, flash_mint)?;
+        // Vault issuance is validated by the Vault Manager, not here
+        let validation = check_flash_mint(input_state, output_state, flash_mint, 0)?;
         validations.flash_mint_validations.push(validation);
     }
 
     // Validate rescues
     for rescue in &operations.rescues {
-        let validation = validate_rescue_spell(
+        let validation = check_rescue(
             input_state, output_state, rescue, btc_price, current_block
         )?;
         validations.rescue_validations.push(validation);
@@ -647,6 +819,7 @@ pub fn validate_zkusd_spell(
     validations.total_fees = validations.flash_mint_validations.iter()
         .map(|v| v.fee_paid)
         .sum();
+    verify_value_conserved(input_state, output_state, btc_price, validations.total_fees)?;
 
     validations.is_valid = true;
     Ok(validations)
@@ -713,10 +886,21 @@ mod tests {
         assert_eq!(fee, 5 * ONE_ZKUSD);
     }
 
+    #[test]
+    fn test_flash_mint_fee_configurable_rate() {
+        // 10,000 zkUSD at 1% = 100 zkUSD fee
+        assert_eq!(calculate_flash_fee_bps(10_000 * ONE_ZKUSD, 100), 100 * ONE_ZKUSD);
+        assert_eq!(
+            calculate_flash_fee_bps(10_000 * ONE_ZKUSD, FLASH_MINT_FEE_BPS),
+            calculate_flash_fee(10_000 * ONE_ZKUSD)
+        );
+    }
+
     #[test]
     fn test_flash_mint_validation_success() {
-        let input = create_test_state(0, ONE_BTC, vec![]);
-        let output = create_test_state(5 * ONE_ZKUSD, ONE_BTC, vec![]); // Fee collected
+        // Fee paid from zkUSD already held; the principal is burned
+        let input = create_test_state(5 * ONE_ZKUSD, ONE_BTC, vec![]);
+        let output = create_test_state(0, ONE_BTC, vec![]);
 
         let flash_mint = SpellFlashMint {
             mint_amount: 10_000 * ONE_ZKUSD,
@@ -724,7 +908,7 @@ mod tests {
             purpose: FlashMintPurpose::Arbitrage,
         };
 
-        let result = validate_flash_mint_spell(&input, &output, &flash_mint);
+        let result = validate_flash_mint_spell(&input, &output, &flash_mint, 0, BTC_PRICE);
         assert!(result.is_ok());
         let validation = result.unwrap();
         assert!(validation.is_valid);
@@ -742,10 +926,78 @@ mod tests {
             purpose: FlashMintPurpose::Custom,
         };
 
-        let result = validate_flash_mint_spell(&input, &output, &flash_mint);
+        let result = validate_flash_mint_spell(&input, &output, &flash_mint, 0, BTC_PRICE);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_flash_mint_repaid_from_vault_mint() {
+        // Flash 10,000, swap it away, open a vault minting 30,000 net and
+        // repay the flash from it: the user keeps 20,000
+        let flash_mint = SpellFlashMint {
+            mint_amount: 10_000 * ONE_ZKUSD,
+            fee: 5 * ONE_ZKUSD,
+            purpose: FlashMintPurpose::LeverageAdjustment,
+        };
+        let input = create_test_state(5 * ONE_ZKUSD, 0, vec![]);
+        let output = create_test_state(30_000 * ONE_ZKUSD, 0, vec![]);
+        assert!(validate_flash_mint_spell(&input, &output, &flash_mint, 30_000 * ONE_ZKUSD, BTC_PRICE).is_ok());
+
+        // Principal not repaid without the vault's mint
+        assert!(matches!(
+            validate_flash_mint_spell(&input, &output, &flash_mint, 0, BTC_PRICE),
+            Err(ZkUsdError::ConservationViolated { .. })
+        ));
+
+        // Under-repaid by one unit
+        let output = create_test_state(30_000 * ONE_ZKUSD + 1, 0, vec![]);
+        assert_eq!(
+            validate_flash_mint_spell(&input, &output, &flash_mint, 30_000 * ONE_ZKUSD, BTC_PRICE).unwrap_err(),
+            ZkUsdError::ConservationViolated {
+                inputs: 40_005 * ONE_ZKUSD,
+                outputs: 40_005 * ONE_ZKUSD + 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_flash_mint_rejects_vault_collateral_siphon() {
+        let flash_mint = SpellFlashMint {
+            mint_amount: 10_000 * ONE_ZKUSD,
+            fee: 5 * ONE_ZKUSD,
+            purpose: FlashMintPurpose::CollateralSwap,
+        };
+        let input = create_test_state(5 * ONE_ZKUSD, 0, vec![create_test_vault(2 * ONE_BTC, 50_000 * ONE_ZKUSD)]);
+
+        // Half a BTC leaves the vault without showing up among the outputs
+        let output = create_test_state(0, 0, vec![create_test_vault(ONE_BTC + ONE_BTC / 2, 50_000 * ONE_ZKUSD)]);
+        assert_eq!(
+            validate_flash_mint_spell(&input, &output, &flash_mint, 0, BTC_PRICE).unwrap_err(),
+            ZkUsdError::ConservationViolated {
+                inputs: 150_005 * ONE_ZKUSD,
+                outputs: 100_005 * ONE_ZKUSD,
+            }
+        );
+    }
+
+    #[test]
+    fn test_flash_mint_collateral_swap_conserves_value() {
+        let flash_mint = SpellFlashMint {
+            mint_amount: 10_000 * ONE_ZKUSD,
+            fee: 5 * ONE_ZKUSD,
+            purpose: FlashMintPurpose::CollateralSwap,
+        };
+        let input = create_test_state(5 * ONE_ZKUSD, 0, vec![create_test_vault(2 * ONE_BTC, 50_000 * ONE_ZKUSD)]);
+
+        // The collateral the vault gives up is paid out to its owner
+        let output = create_test_state(
+            0,
+            ONE_BTC / 2,
+            vec![create_test_vault(ONE_BTC + ONE_BTC / 2, 50_000 * ONE_ZKUSD)],
+        );
+        assert!(validate_flash_mint_spell(&input, &output, &flash_mint, 0, BTC_PRICE).is_ok());
+    }
+
     #[test]
     fn test_rescue_validation_success() {
         let vault = create_test_vault(ONE_BTC, 90_000 * ONE_ZKUSD); // ICR ~111%
@@ -803,13 +1055,45 @@ mod tests {
         assert!(premium > 0);
     }
 
+    fn spell_input(btc_amount: u64) -> SpellInput {
+        SpellInput { utxo_id: [3u8; 32], vout: 0, charms: Vec::new(), btc_amount }
+    }
+
+    fn spell_output(btc_amount: u64) -> SpellOutput {
+        SpellOutput { address: [4u8; 32], charms: Vec::new(), btc_amount }
+    }
+
+    #[test]
+    fn test_spell_btc_conservation() {
+        const FEE: u64 = 2_000;
+        let inputs = [spell_input(ONE_BTC), spell_input(ONE_BTC / 2)];
+
+        // Balanced: everything spent is created again or paid as the fee
+        let balanced = [spell_output(ONE_BTC), spell_output(ONE_BTC / 2 - FEE)];
+        assert_eq!(validate_spell_btc_conservation(&inputs, &balanced, FEE), Ok(()));
+
+        // Creates a satoshi out of nothing
+        let inflated = [spell_output(ONE_BTC), spell_output(ONE_BTC / 2 - FEE + 1)];
+        assert_eq!(
+            validate_spell_btc_conservation(&inputs, &inflated, FEE),
+            Err(ZkUsdError::ConservationViolated { inputs: 3 * ONE_BTC / 2, outputs: 3 * ONE_BTC / 2 + 1 })
+        );
+
+        // Destroys a satoshi, which the fee does not account for
+        let deflated = [spell_output(ONE_BTC), spell_output(ONE_BTC / 2 - FEE - 1)];
+        assert_eq!(
+            validate_spell_btc_conservation(&inputs, &deflated, FEE),
+            Err(ZkUsdError::ConservationViolated { inputs: 3 * ONE_BTC / 2, outputs: 3 * ONE_BTC / 2 - 1 })
+        );
+    }
+
     #[test]
     fn test_full_spell_validation() {
         let vault = create_test_vault(ONE_BTC, 50_000 * ONE_ZKUSD);
-        let input = create_test_state(0, 2 * ONE_BTC, vec![vault.clone()]);
+        let input = create_test_state(5 * ONE_ZKUSD, 2 * ONE_BTC, vec![vault.clone()]);
 
-        // Output with fee collected
-        let output = create_test_state(5 * ONE_ZKUSD, 2 * ONE_BTC, vec![vault]);
+        // Fee collected from held zkUSD, principal burned
+        let output = create_test_state(0, 2 * ONE_BTC, vec![vault]);
 
         let operations = SpellOperations {
             flash_mints: vec![SpellFlashMint {
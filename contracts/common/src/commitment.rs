@@ -0,0 +1,296 @@
+//! State Commitments
+//!
+//! Canonical 32-byte commitments to app states, so light clients and
+//! bridges can check "this is the current protocol state" from a hash.
+//!
+//! ## Encoding (consensus-relevant)
+//!
+//! ```text
+//! commitment = sha256(COMMITMENT_VERSION || app_tag || borsh(state))
+//! ```
+//!
+//! Borsh is used because it is canonical: one state has exactly one
+//! encoding, and serde/borsh round trips leave it unchanged. Any change to
+//! the encoding, the app tags or a committed state's fields changes every
+//! commitment and must bump `COMMITMENT_VERSION`. The golden tests below
+//! pin exact hashes so such a change cannot land unnoticed.
+//!
+//! ## Combined Commitment
+//!
+//! `CombinedCommitment` is a 4-leaf Merkle tree over the VaultManager,
+//! Stability Pool, Price Oracle and Token commitments (in that order):
+//!
+//! ```text
+//!                  root
+//!          /                \
+//!   node(vm, sp)      node(oracle, token)
+//! ```
+//!
+//! An inclusion proof for one app is its sibling leaf and the other
+//! subtree's node.
+//!
+//! ## State References
+//!
+//! A spell may name the state it was built against as a `StateRef`. The
+//! validator then requires the state the spell spends to have that
+//! commitment, so a spell built on a liquidation or price update a reorg
+//! has since undone fails with `StaleStateReference` instead of applying to
+//! a state its builder never saw. Without a reference, validation is as
+//! before.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::Vec;
+
+/// Version byte prefixed to every commitment preimage
+pub const COMMITMENT_VERSION: u8 = 33;
+
+/// App whose state is committed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum CommittedApp {
+    /// VaultManager protocol state
+    VaultManager = 0,
+    /// Stability Pool state
+    StabilityPool = 1,
+    /// Price Oracle state
+    PriceOracle = 2,
+    /// zkUSD token controller state
+    Token = 3,
+}
+
+/// Canonical commitment preimage: version || app tag || borsh(state)
+pub fn canonical_bytes<T: BorshSerialize>(app: CommittedApp, state: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.push(COMMITMENT_VERSION);
+    bytes.push(app as u8);
+    // Writing into a Vec cannot fail
+    state.serialize(&mut bytes).unwrap_or_default();
+    bytes
+}
+
+/// Commitment to an app state
+pub fn state_commitment<T: BorshSerialize>(app: CommittedApp, state: &T) -> [u8; 32] {
+    Sha256::digest(canonical_bytes(app, state)).into()
+}
+
+/// Check that canonical state bytes hash to the expected commitment
+pub fn verify_commitment(bytes: &[u8], expected: &[u8; 32]) -> bool {
+    let actual: [u8; 32] = Sha256::digest(bytes).into();
+    actual == *expected
+}
+
+/// App state a spell was built against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct StateRef {
+    /// Commitment to the state
+    pub commitment: [u8; 32],
+    /// Block the state was read at
+    pub block_height: u64,
+}
+
+impl StateRef {
+    /// Reference to the state with `commitment`, read at `block_height`
+    pub fn new(commitment: [u8; 32], block_height: u64) -> Self {
+        Self { commitment, block_height }
+    }
+
+    /// Require the spent state to be the referenced one
+    pub fn require_matches(&self, commitment: &[u8; 32]) -> ZkUsdResult<()> {
+        if self.commitment != *commitment {
+            return Err(ZkUsdError::StaleStateReference { block_height: self.block_height });
+        }
+        Ok(())
+    }
+}
+
+/// Hash two child nodes into their parent
+fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Commitments of all four app states, for cross-app consistency proofs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct CombinedCommitment {
+    /// VaultManager state commitment
+    pub vault_manager: [u8; 32],
+    /// Stability Pool state commitment
+    pub stability_pool: [u8; 32],
+    /// Price Oracle state commitment
+    pub price_oracle: [u8; 32],
+    /// Token state commitment
+    pub token: [u8; 32],
+}
+
+/// Inclusion proof of one app's commitment under a combined root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct CommitmentProof {
+    /// App the proven leaf belongs to
+    pub app: CommittedApp,
+    /// Other leaf under the same node
+    pub sibling: [u8; 32],
+    /// Node of the other subtree
+    pub uncle: [u8; 32],
+}
+
+impl CombinedCommitment {
+    /// Leaf commitment of an app
+    pub fn leaf(&self, app: CommittedApp) -> [u8; 32] {
+        match app {
+            CommittedApp::VaultManager => self.vault_manager,
+            CommittedApp::StabilityPool => self.stability_pool,
+            CommittedApp::PriceOracle => self.price_oracle,
+            CommittedApp::Token => self.token,
+        }
+    }
+
+    /// Merkle root over the four leaves
+    pub fn root(&self) -> [u8; 32] {
+        merkle_node(
+            &merkle_node(&self.vault_manager, &self.stability_pool),
+            &merkle_node(&self.price_oracle, &self.token),
+        )
+    }
+
+    /// Inclusion proof for one app's commitment
+    pub fn proof(&self, app: CommittedApp) -> CommitmentProof {
+        let core = merkle_node(&self.vault_manager, &self.stability_pool);
+        let feeds = merkle_node(&self.price_oracle, &self.token);
+        let (sibling, uncle) = match app {
+            CommittedApp::VaultManager => (self.stability_pool, feeds),
+            CommittedApp::StabilityPool => (self.vault_manager, feeds),
+            CommittedApp::PriceOracle => (self.token, core),
+            CommittedApp::Token => (self.price_oracle, core),
+        };
+        CommitmentProof { app, sibling, uncle }
+    }
+}
+
+/// Check that an app's commitment is included under a combined root
+pub fn verify_inclusion(leaf: &[u8; 32], proof: &CommitmentProof, root: &[u8; 32]) -> bool {
+    let computed = match proof.app {
+        CommittedApp::VaultManager => merkle_node(&merkle_node(leaf, &proof.sibling), &proof.uncle),
+        CommittedApp::StabilityPool => merkle_node(&merkle_node(&proof.sibling, leaf), &proof.uncle),
+        CommittedApp::PriceOracle => merkle_node(&proof.uncle, &merkle_node(leaf, &proof.sibling)),
+        CommittedApp::Token => merkle_node(&proof.uncle, &merkle_node(&proof.sibling, leaf)),
+    };
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::StabilityPoolState;
+
+    fn hex(bytes: &[u8; 32]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Pool state with every field set, including an epoch snapshot and an offset sample
+    fn fixture_pool() -> StabilityPoolState {
+        let mut pool = StabilityPoolState::new();
+        pool.total_zkusd = 50_000_00000000;
+        pool.total_btc = 25_000_000;
+        pool.sum_s = 7_000_000_000;
+        pool.current_epoch = 1;
+        pool.depositor_count = 3;
+        pool.record_epoch_snapshot(crate::types::EpochSnapshot {
+            epoch: 0,
+            final_scale: 0,
+            final_s_at_scale: 1_000_000_000_000_000_000,
+            final_s_at_scale_plus_one: 0,
+        });
+        pool.record_offset(crate::types::OffsetSample {
+            debt: 10_000_00000000,
+            collateral: 11_000_000,
+            block: 500,
+        });
+        pool
+    }
+
+    fn fixture_combined() -> CombinedCommitment {
+        CombinedCommitment {
+            vault_manager: [1u8; 32],
+            stability_pool: [2u8; 32],
+            price_oracle: [3u8; 32],
+            token: [4u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_stability_pool_commitment_golden() {
+        // Consensus-relevant: changing these requires a COMMITMENT_VERSION bump
+        assert_eq!(
+            hex(&StabilityPoolState::new().commitment()),
+            "0bacfb66141d36141cdbc7ec5a9725455f22f0f17234b86259bab931d3a7b592"
+        );
+        assert_eq!(
+            hex(&fixture_pool().commitment()),
+            "71a9573ea9bf6f222c75e0df55c26fecc5ee2fd1386624c1dd195925f4c8b2c1"
+        );
+    }
+
+    #[test]
+    fn test_commitment_stable_across_round_trips() {
+        let pool = fixture_pool();
+
+        let borsh_copy: StabilityPoolState = borsh::from_slice(&borsh::to_vec(&pool).unwrap()).unwrap();
+        let json_copy: StabilityPoolState =
+            serde_json::from_str(&serde_json::to_string(&pool).unwrap()).unwrap();
+
+        assert_eq!(borsh_copy.commitment(), pool.commitment());
+        assert_eq!(json_copy.commitment(), pool.commitment());
+    }
+
+    #[test]
+    fn test_verify_commitment() {
+        let pool = fixture_pool();
+        let bytes = canonical_bytes(CommittedApp::StabilityPool, &pool);
+
+        assert!(verify_commitment(&bytes, &pool.commitment()));
+        assert!(!verify_commitment(&bytes, &StabilityPoolState::new().commitment()));
+
+        // Same state under another app tag is a different commitment
+        let other = canonical_bytes(CommittedApp::VaultManager, &pool);
+        assert!(!verify_commitment(&other, &pool.commitment()));
+    }
+
+    #[test]
+    fn test_combined_root_golden() {
+        assert_eq!(
+            hex(&fixture_combined().root()),
+            "2c0c4083be2badf7c9f9046d8730d21e034c1ce50f519c166d7605848b17b0d5"
+        );
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let combined = fixture_combined();
+        let root = combined.root();
+        let apps = [
+            CommittedApp::VaultManager,
+            CommittedApp::StabilityPool,
+            CommittedApp::PriceOracle,
+            CommittedApp::Token,
+        ];
+
+        for app in apps {
+            let proof = combined.proof(app);
+            assert!(verify_inclusion(&combined.leaf(app), &proof, &root));
+            // A different leaf, or the right leaf under another app's position, fails
+            assert!(!verify_inclusion(&[9u8; 32], &proof, &root));
+            for other in apps.iter().filter(|a| **a != app) {
+                let moved = CommitmentProof { app: *other, ..proof };
+                assert!(!verify_inclusion(&combined.leaf(app), &moved, &root));
+            }
+        }
+    }
+}
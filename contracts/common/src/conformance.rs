@@ -0,0 +1,2188 @@
+//! Conformance Test Vectors
+//!
+//! Machine-readable valid/invalid scenarios for third parties re-implementing
+//! spell builders (JS/TS wallets, indexers) so they can check that their
+//! serialization and pre-validation logic agrees with the Rust validators.
+//!
+//! ## Vector Format
+//!
+//! Each [`TestVector`] carries:
+//! - `action_borsh_hex`: the contract action (`TokenAction`, `VaultAction`,
+//!   `StabilityPoolAction`, `OracleAction`) borsh-encoded as lowercase hex
+//! - `context_json`: a [`VectorContext`] describing the pre-state, flows
+//!   and signer as JSON
+//! - `expected`: `Pass` or `Fail(error_code)` using [`ZkUsdError::code`]
+//!
+//! ## Scope
+//!
+//! The executor mirrors the *pre-validation* rules of each contract
+//! (authorization, amounts, collateral ratios, oracle freshness, token
+//! conservation, recovery mode). Output-state verification is left to the
+//! contracts themselves since it depends on charm layout, not on the action.
+//! Attestation signatures (`OracleAction::AggregateUpdate`) are not modeled
+//! either: implementations verify ed25519 over `PriceAttestation::message`,
+//! and the vectors only exercise the checks that come before it.
+//!
+//! Each contract crate also replays its vectors through its own validator
+//! ([`decode_vector`] into the real context, with the outputs a valid spell
+//! would carry), so the recorded outcomes are checked against the
+//! contracts themselves. The executor here stays as an independent mirror
+//! of the same rules, a cross-check for implementations without them.
+//!
+//! Fixture values (addresses, BTC price, block height) are the ones used by
+//! the `create_test_context()` helpers in each contract's unit tests.
+//!
+//! ## Export
+//!
+//! ```bash
+//! cargo run -p zkusd-common --example export_conformance_vectors --features conformance
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    address::is_zero,
+    constants::{
+        fees, limits, ratios,
+        oracle::{
+            DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS, DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+            DEVIATION_WINDOW_UPDATES, MAX_ATTESTATION_SOURCES, MAX_CUMULATIVE_DEVIATION_BPS,
+            MAX_DEVIATION_SCALING_BPS_PER_BLOCK, MAX_PRICE_AGE_BLOCKS, MAX_PRICE_DEVIATION_BPS,
+            MAX_SCALED_PRICE_DEVIATION_BPS, MAX_UPDATE_INTERVAL_BLOCKS, MIN_CUMULATIVE_DEVIATION_BPS,
+            MIN_UPDATE_INTERVAL_BLOCKS, BTC_USD_FEED, MAX_FEEDS,
+        },
+        stability_pool::{DUST_DEPOSIT_THRESHOLD, MIN_DEPOSIT, SCALE_FACTOR}, token::ONE,
+    },
+    errors::{AmountErrorReason, RecoveryModeOp, ZkUsdError, ZkUsdResult},
+    math::{
+        calculate_btc_gain, calculate_compounded_deposit, calculate_icr, calculate_tcr,
+        get_min_ratio, is_liquidatable, is_recovery_mode, safe_add, safe_sub, zkusd_to_btc_floor,
+    },
+    liquidation::requires_two_phase,
+    token_ops::MintTracker,
+    types::{
+        Address, AppId, CircuitBreakerState, Feed, OracleAction, OracleSnapshot, PriceAttestation, PriceBounds, PriceData,
+        PriceSource, StabilityDeposit,
+        StabilityPoolAction, StabilityPoolState, TokenAction, Vault, VaultAction, VaultStatus,
+    },
+    validation::{
+        check, require_admin, require_circuit_breaker_clear, require_fresh_price, require_in_range, require_min_adjustment, require_min_icr, require_not_paused,
+        require_owner, require_owner_or_operator, FreshnessPolicy,
+        require_min_output, require_not_dust, require_positive, require_sufficient_balance, require_tcr_not_worsened,
+        require_valid_address,
+    },
+    units::{Sats, ZkUsd},
+    Vec,
+};
+
+// ============ Fixtures ============
+
+/// Shared fixture values (match the contract unit-test contexts)
+pub mod fixtures {
+    use crate::types::{Address, AppId};
+
+    /// Protocol / oracle admin
+    pub const ADMIN: Address = [0u8; 32];
+    /// Vault owner, depositor and oracle operator
+    pub const OWNER: Address = [1u8; 32];
+    /// Second user (transfer recipient)
+    pub const BOB: Address = [2u8; 32];
+    /// Unrelated signer used for unauthorized scenarios
+    pub const ATTACKER: Address = [99u8; 32];
+    /// VaultManager app_id as seen by the token contract (authorized minter)
+    pub const TOKEN_MINTER_ID: AppId = [1u8; 32];
+    /// VaultManager app_id as seen by the stability pool
+    pub const VAULT_MANAGER_ID: AppId = [2u8; 32];
+    /// Vault id used throughout the vault scenarios
+    pub const VAULT_ID: [u8; 32] = [7u8; 32];
+    /// BTC price ($100,000 with 8 decimals)
+    pub const BTC_PRICE_100K: u64 = 100_000 * 100_000_000;
+    /// Block height for all scenarios
+    pub const BLOCK_HEIGHT: u64 = 100;
+    /// First registered price feed
+    pub const FEED_A: [u8; 32] = [0xFA; 32];
+    /// Second registered price feed
+    pub const FEED_B: [u8; 32] = [0xFB; 32];
+    /// Oracle guardian
+    pub const GUARDIAN: Address = [3u8; 32];
+}
+
+use fixtures::*;
+
+// ============ Types ============
+
+/// Contract a vector targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceContract {
+    ZkusdToken,
+    VaultManager,
+    StabilityPool,
+    PriceOracle,
+}
+
+/// Expected outcome of a vector
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expected {
+    /// Validation succeeds
+    Pass,
+    /// Validation fails with the given error code (e.g. "E040_ZERO_AMOUNT")
+    Fail(String),
+}
+
+impl Expected {
+    /// Expected failure with the code of `err`
+    pub fn fail(err: ZkUsdError) -> Self {
+        Expected::Fail(err.code().into())
+    }
+
+    /// Whether `result` is this outcome: success, or an error with the
+    /// expected code
+    pub fn matches(&self, result: &ZkUsdResult<()>) -> bool {
+        match (result, self) {
+            (Ok(()), Expected::Pass) => true,
+            (Err(err), Expected::Fail(code)) => err.code() == code,
+            _ => false,
+        }
+    }
+}
+
+/// A single conformance scenario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Unique scenario name
+    pub name: String,
+    /// Target contract
+    pub contract: ConformanceContract,
+    /// Borsh-encoded action as lowercase hex
+    pub action_borsh_hex: String,
+    /// JSON-encoded [`VectorContext`]
+    pub context_json: String,
+    /// Expected outcome
+    pub expected: Expected,
+}
+
+/// Token balance entry in a vector context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorBalance {
+    pub owner: Address,
+    pub amount: u64,
+}
+
+/// Spell context shared by all contracts.
+///
+/// Fields not relevant to the target contract keep their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorContext {
+    /// Spell signer
+    pub signer: Address,
+    /// Current block height
+    pub block_height: u64,
+    /// Calling app_id (token mint/burn, stability pool offset)
+    pub caller_app_id: Option<AppId>,
+
+    // ---- Protocol / oracle ----
+    /// Protocol paused flag
+    pub is_paused: bool,
+    /// Current (oracle) BTC price
+    pub btc_price: u64,
+    /// Block at which the oracle price was published
+    pub price_block: u64,
+    /// Oracle admin
+    pub admin: Address,
+    /// Oracle operator
+    pub operator: Address,
+    /// Oracle guardian (all zeros when unset)
+    #[serde(default)]
+    pub guardian: Address,
+    /// Oracle minimum blocks between price updates
+    #[serde(default = "default_min_update_interval")]
+    pub min_update_interval_blocks: u64,
+    /// Oracle limit on summed deviation over the recent window (BPS)
+    #[serde(default = "default_max_cumulative_deviation")]
+    pub max_cumulative_deviation_bps: u64,
+    /// Oracle deviations of the most recent updates, oldest first (BPS)
+    #[serde(default)]
+    pub recent_deviations_bps: Vec<u64>,
+    /// Oracle deviation limit growth per block since the last update (BPS)
+    #[serde(default)]
+    pub deviation_scaling_bps_per_block: u64,
+    /// Oracle circuit breaker
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerState,
+    /// Oracle registered attestation sources
+    #[serde(default)]
+    pub attestation_sources: Vec<[u8; 32]>,
+    /// Oracle accepted price range
+    #[serde(default)]
+    pub price_bounds: PriceBounds,
+    /// Oracle secondary feeds
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+
+    // ---- Vault Manager ----
+    /// System-wide collateral
+    pub total_collateral: u64,
+    /// System-wide debt
+    pub total_debt: u64,
+    /// Number of active vaults
+    pub active_vault_count: u64,
+    /// Vault being operated on
+    pub vault: Option<Vault>,
+    /// Lifetime mints per owner, with the optional cap
+    #[serde(default)]
+    pub mint_tracker: MintTracker,
+    /// Most total debt the protocol may carry
+    #[serde(default = "default_global_debt_ceiling")]
+    pub global_debt_ceiling: u64,
+
+    // ---- Token ----
+    /// Authorized minter app_id
+    pub authorized_minter: AppId,
+    /// zkUSD inputs of the spell
+    pub token_inputs: Vec<VectorBalance>,
+    /// zkUSD outputs of the spell
+    pub token_outputs: Vec<VectorBalance>,
+
+    // ---- Stability Pool ----
+    /// Pool aggregate state
+    pub pool: StabilityPoolState,
+    /// Depositor's existing deposit
+    pub deposit: Option<StabilityDeposit>,
+    /// BTC entering the spell (offset collateral)
+    pub btc_inputs: u64,
+    /// VaultManager app_id authorized to offset
+    pub vault_manager_id: AppId,
+}
+
+fn default_min_update_interval() -> u64 {
+    DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS
+}
+
+fn default_max_cumulative_deviation() -> u64 {
+    DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS
+}
+
+fn default_global_debt_ceiling() -> u64 {
+    limits::DEBT_CEILING
+}
+
+impl Default for VectorContext {
+    fn default() -> Self {
+        Self {
+            signer: OWNER,
+            block_height: BLOCK_HEIGHT,
+            caller_app_id: None,
+            is_paused: false,
+            btc_price: BTC_PRICE_100K,
+            price_block: BLOCK_HEIGHT,
+            admin: ADMIN,
+            operator: OWNER,
+            guardian: [0u8; 32],
+            min_update_interval_blocks: DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+            max_cumulative_deviation_bps: DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS,
+            recent_deviations_bps: Vec::new(),
+            deviation_scaling_bps_per_block: 0,
+            circuit_breaker: CircuitBreakerState::default(),
+            attestation_sources: Vec::new(),
+            price_bounds: PriceBounds::default(),
+            feeds: Vec::new(),
+            total_collateral: 10 * ONE,
+            total_debt: 200_000 * ONE,
+            active_vault_count: 5,
+            vault: None,
+            mint_tracker: MintTracker::default(),
+            global_debt_ceiling: limits::DEBT_CEILING,
+            authorized_minter: TOKEN_MINTER_ID,
+            token_inputs: Vec::new(),
+            token_outputs: Vec::new(),
+            pool: StabilityPoolState::new(),
+            deposit: None,
+            btc_inputs: 0,
+            vault_manager_id: VAULT_MANAGER_ID,
+        }
+    }
+}
+
+impl VectorContext {
+    /// zkUSD entering the spell
+    pub fn zkusd_inputs(&self) -> u64 {
+        self.token_inputs.iter().map(|b| b.amount).sum()
+    }
+
+    /// zkUSD leaving the spell
+    pub fn zkusd_outputs(&self) -> u64 {
+        self.token_outputs.iter().map(|b| b.amount).sum()
+    }
+
+    fn input_of(&self, owner: &Address) -> u64 {
+        self.token_inputs.iter().filter(|b| &b.owner == owner).map(|b| b.amount).sum()
+    }
+
+    fn output_of(&self, owner: &Address) -> u64 {
+        self.token_outputs.iter().filter(|b| &b.owner == owner).map(|b| b.amount).sum()
+    }
+}
+
+// ============ Executor ============
+
+/// Run a vector and report whether it produced its expected outcome.
+pub fn run_vector(v: &TestVector) -> bool {
+    v.expected.matches(&execute_vector(v))
+}
+
+/// Decode and execute a vector, returning the validator result.
+///
+/// Malformed vectors (bad hex, borsh or JSON) yield `InvalidSpellFormat`.
+pub fn execute_vector(v: &TestVector) -> ZkUsdResult<()> {
+    match v.contract {
+        ConformanceContract::ZkusdToken => decode_vector(v).and_then(|(action, ctx)| check_token(&ctx, &action)),
+        ConformanceContract::VaultManager => decode_vector(v).and_then(|(action, ctx)| check_vault(&ctx, &action)),
+        ConformanceContract::StabilityPool => {
+            decode_vector(v).and_then(|(action, ctx)| check_stability_pool(&ctx, &action))
+        }
+        ConformanceContract::PriceOracle => decode_vector(v).and_then(|(action, ctx)| check_oracle(&ctx, &action)),
+    }
+}
+
+/// Decode a vector's action and context, for a contract to replay the
+/// vector through its own validator
+///
+/// Malformed vectors (bad hex, borsh or JSON) yield `InvalidSpellFormat`.
+pub fn decode_vector<A: BorshDeserialize>(v: &TestVector) -> ZkUsdResult<(A, VectorContext)> {
+    let action_bytes = from_hex(&v.action_borsh_hex).ok_or(ZkUsdError::InvalidSpellFormat)?;
+    let action = A::try_from_slice(&action_bytes).map_err(|_| ZkUsdError::InvalidSpellFormat)?;
+    let ctx = serde_json::from_str(&v.context_json).map_err(|_| ZkUsdError::InvalidSpellFormat)?;
+    Ok((action, ctx))
+}
+
+// ---- zkUSD Token ----
+
+fn check_token(ctx: &VectorContext, action: &TokenAction) -> ZkUsdResult<()> {
+    let total_inputs = ctx.zkusd_inputs();
+    let total_outputs = ctx.zkusd_outputs();
+
+    // Ownership is explicit in the reference state: no output may be owned
+    // by the zero address
+    check!(
+        ctx.token_outputs.iter().all(|output| !is_zero(&output.owner)),
+        ZkUsdError::InvalidAddress { reason: "zero-address recipient" }
+    );
+
+    match action {
+        TokenAction::Transfer { from, to, amount: ZkUsd(amount), .. } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            require_sufficient_balance(ctx.input_of(from), *amount)?;
+            check!(
+                total_inputs == total_outputs,
+                ZkUsdError::ConservationViolated { inputs: total_inputs, outputs: total_outputs }
+            );
+            check!(
+                ctx.output_of(to) >= *amount,
+                ZkUsdError::InvalidAmount { amount: ctx.output_of(to), reason: AmountErrorReason::TooSmall }
+            );
+            require_owner(*from, ctx.signer)
+        }
+        TokenAction::Mint { to, amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            let caller = ctx.caller_app_id.ok_or(ZkUsdError::MintUnauthorized { caller: [0u8; 32] })?;
+            check!(caller == ctx.authorized_minter, ZkUsdError::MintUnauthorized { caller });
+            check!(
+                total_outputs == safe_add(total_inputs, *amount)?,
+                ZkUsdError::ConservationViolated { inputs: total_inputs, outputs: total_outputs }
+            );
+            require_valid_address(*to, "to")?;
+            check_minted_to(ctx, to, *amount)
+        }
+        TokenAction::MintMulti { recipients } => {
+            check!(
+                !recipients.is_empty(),
+                ZkUsdError::InvalidInput { param: "recipients", reason: "No recipients" }
+            );
+            check!(
+                recipients.len() <= crate::constants::token::MAX_MINT_RECIPIENTS,
+                ZkUsdError::ExceedsMaximum {
+                    amount: recipients.len() as u64,
+                    maximum: crate::constants::token::MAX_MINT_RECIPIENTS as u64,
+                }
+            );
+            let mut total: u64 = 0;
+            for (i, (to, ZkUsd(amount))) in recipients.iter().enumerate() {
+                require_valid_address(*to, "recipients")?;
+                check!(*amount > 0, ZkUsdError::ZeroAmount);
+                check!(
+                    !recipients[..i].iter().any(|(seen, _)| seen == to),
+                    ZkUsdError::InvalidInput { param: "recipients", reason: "Duplicate recipient" }
+                );
+                total = safe_add(total, *amount)?;
+            }
+            let caller = ctx.caller_app_id.ok_or(ZkUsdError::MintUnauthorized { caller: [0u8; 32] })?;
+            check!(caller == ctx.authorized_minter, ZkUsdError::MintUnauthorized { caller });
+            check!(
+                total_outputs == safe_add(total_inputs, total)?,
+                ZkUsdError::ConservationViolated { inputs: total_inputs, outputs: total_outputs }
+            );
+            recipients.iter().try_for_each(|(to, amount)| check_minted_to(ctx, to, amount.into_inner()))
+        }
+        TokenAction::Burn { from, amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            let caller = ctx.caller_app_id.ok_or(ZkUsdError::BurnUnauthorized { caller: [0u8; 32] })?;
+            check!(caller == ctx.authorized_minter, ZkUsdError::BurnUnauthorized { caller });
+            check!(
+                total_inputs == safe_add(total_outputs, *amount)?,
+                ZkUsdError::ConservationViolated { inputs: total_inputs, outputs: total_outputs }
+            );
+            require_sufficient_balance(ctx.input_of(from), *amount)
+        }
+        TokenAction::Consolidate { owner } => {
+            require_owner(*owner, ctx.signer)?;
+            let (inputs, outputs) = (ctx.token_inputs.len(), ctx.token_outputs.len());
+            check!(
+                inputs <= crate::constants::token::MAX_CONSOLIDATION_INPUTS,
+                ZkUsdError::ExceedsMaximum {
+                    amount: inputs as u64,
+                    maximum: crate::constants::token::MAX_CONSOLIDATION_INPUTS as u64,
+                }
+            );
+            if let Some(input) = ctx.token_inputs.iter().find(|input| input.owner != *owner) {
+                return Err(ZkUsdError::Unauthorized { expected: *owner, actual: input.owner });
+            }
+            check!(
+                ctx.token_outputs.iter().all(|output| output.owner == *owner),
+                ZkUsdError::InvalidInput { param: "outputs", reason: "Consolidation output not owned by the owner" }
+            );
+            check!(outputs > 0, ZkUsdError::InvalidInput { param: "outputs", reason: "No outputs" });
+            check!(
+                outputs < inputs,
+                ZkUsdError::InvalidInput { param: "outputs", reason: "Consolidation must reduce the UTXO count" }
+            );
+            check!(
+                total_inputs == total_outputs,
+                ZkUsdError::ConservationViolated { inputs: total_inputs, outputs: total_outputs }
+            );
+            Ok(())
+        }
+    }
+}
+
+fn check_minted_to(ctx: &VectorContext, to: &Address, amount: u64) -> ZkUsdResult<()> {
+    let credited = ctx.input_of(to).saturating_add(amount);
+    check!(
+        ctx.output_of(to) >= credited,
+        ZkUsdError::InvalidAmount { amount: ctx.output_of(to), reason: AmountErrorReason::TooSmall }
+    );
+    Ok(())
+}
+
+// ---- Vault Manager ----
+
+fn check_vault(ctx: &VectorContext, action: &VaultAction) -> ZkUsdResult<()> {
+    require_not_paused(ctx.is_paused)?;
+
+    let oracle = OracleSnapshot::active(PriceData::new(ctx.btc_price, ctx.price_block, PriceSource::Mock));
+    require_fresh_price(&oracle, ctx.block_height, &FreshnessPolicy::default())?;
+
+    let tcr = calculate_tcr(Sats(ctx.total_collateral), ZkUsd(ctx.total_debt), ctx.btc_price)?;
+
+    match action {
+        VaultAction::OpenVault { collateral: Sats(collateral), debt: ZkUsd(debt) } => {
+            require_positive(*collateral, "collateral")?;
+            let total_debt = safe_add(*debt, limits::LIQUIDATION_RESERVE)?;
+            require_in_range(total_debt, limits::MIN_DEBT, limits::MAX_DEBT_PER_VAULT, "debt")?;
+            ctx.mint_tracker.clone().record(ctx.signer, *debt)?;
+            require_within_debt_ceiling(ctx, total_debt)?;
+            let icr = calculate_icr(Sats(*collateral), ZkUsd(total_debt), ctx.btc_price)?;
+            require_min_icr(icr, get_min_ratio(tcr))?;
+            if is_recovery_mode(tcr) {
+                let new_tcr = calculate_tcr(
+                    Sats(safe_add(ctx.total_collateral, *collateral)?),
+                    ZkUsd(safe_add(ctx.total_debt, total_debt)?),
+                    ctx.btc_price,
+                )?;
+                require_tcr_not_worsened(tcr, new_tcr)?;
+            }
+            Ok(())
+        }
+        VaultAction::CloseVault { vault_id } => {
+            let vault = active_vault(ctx, vault_id, true)?;
+            check!(
+                !(is_recovery_mode(tcr) && ctx.active_vault_count == 1),
+                ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::CloseLastVault }
+            );
+            require_sufficient_balance(ctx.zkusd_inputs(), vault.debt)
+        }
+        VaultAction::AddCollateral { vault_id, amount: Sats(amount) } => {
+            require_positive(*amount, "collateral_amount")?;
+            require_min_adjustment(*amount, limits::MIN_COLLATERAL_ADJUSTMENT)?;
+            let vault = ctx.vault.as_ref().ok_or(ZkUsdError::VaultNotFound { vault_id: *vault_id })?;
+            require_owner_or_operator(vault.owner, vault.operator, ctx.signer)?;
+            active_vault(ctx, vault_id, false).map(|_| ())
+        }
+        VaultAction::WithdrawCollateral { vault_id, amount: Sats(amount) } => {
+            require_positive(*amount, "withdraw_amount")?;
+            require_min_adjustment(*amount, limits::MIN_COLLATERAL_ADJUSTMENT)?;
+            let vault = active_vault(ctx, vault_id, true)?;
+            require_sufficient_balance(vault.collateral, *amount)?;
+            let new_collateral = safe_sub(vault.collateral, *amount)?;
+            check!(
+                new_collateral > 0 || vault.debt == 0,
+                ZkUsdError::InvalidInput { param: "collateral", reason: "Vault with debt must hold collateral" }
+            );
+            let new_icr = calculate_icr(Sats(new_collateral), ZkUsd(vault.debt), ctx.btc_price)?;
+            check!(
+                !is_recovery_mode(tcr),
+                ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::WithdrawCollateral }
+            );
+            require_min_icr(new_icr, get_min_ratio(tcr))
+        }
+        VaultAction::MintDebt { vault_id, amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            require_min_adjustment(*amount, limits::MIN_ADJUSTMENT)?;
+            let vault = active_vault(ctx, vault_id, true)?;
+            check!(
+                !is_recovery_mode(tcr),
+                ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::MintDebt }
+            );
+            let new_debt = safe_add(vault.debt, *amount)?;
+            check!(
+                new_debt <= limits::MAX_DEBT_PER_VAULT,
+                ZkUsdError::ExceedsMaximum { amount: new_debt, maximum: limits::MAX_DEBT_PER_VAULT }
+            );
+            ctx.mint_tracker.clone().record(vault.owner, *amount)?;
+            require_within_debt_ceiling(ctx, *amount)?;
+            let new_icr = calculate_icr(Sats(vault.collateral), ZkUsd(new_debt), ctx.btc_price)?;
+            require_min_icr(new_icr, ratios::MCR)
+        }
+        VaultAction::RepayDebt { vault_id, amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            let vault = active_vault(ctx, vault_id, false)?;
+            require_owner_or_operator(vault.owner, vault.operator, ctx.signer)?;
+            let net_debt = vault.net_debt();
+            check!(
+                *amount <= net_debt,
+                ZkUsdError::ExceedsMaximum { amount: *amount, maximum: net_debt }
+            );
+            if *amount < net_debt {
+                require_min_adjustment(*amount, limits::MIN_ADJUSTMENT)?;
+            }
+            require_sufficient_balance(ctx.zkusd_inputs(), *amount)
+        }
+        VaultAction::Liquidate { vault_id } | VaultAction::BeginLiquidation { vault_id } => {
+            require_circuit_breaker_clear(&ctx.circuit_breaker, ctx.block_height)?;
+            let vault = active_vault(ctx, vault_id, false)?;
+            let icr = calculate_icr(Sats(vault.collateral), ZkUsd(vault.debt), ctx.btc_price)?;
+            check!(
+                is_liquidatable(icr, tcr),
+                ZkUsdError::NotLiquidatable { vault_id: *vault_id, icr }
+            );
+            // Vectors carry no pool, so only the debt threshold routes
+            let two_phase_required = requires_two_phase(vault.debt, None);
+            check!(
+                two_phase_required == matches!(action, VaultAction::BeginLiquidation { .. }),
+                ZkUsdError::WrongLiquidationPath { debt: vault.debt, two_phase_required }
+            );
+            Ok(())
+        }
+        VaultAction::Redeem { amount: ZkUsd(amount), min_btc_out: Sats(min_btc_out) } => {
+            require_circuit_breaker_clear(&ctx.circuit_breaker, ctx.block_height)?;
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            require_sufficient_balance(ctx.zkusd_inputs(), *amount)?;
+            check!(ctx.btc_price > 0, ZkUsdError::DivisionByZero);
+            let btc_out = zkusd_to_btc_floor(ZkUsd(*amount), ctx.btc_price)?.into_inner();
+            require_min_output(btc_out, *min_btc_out)?;
+            require_not_dust(btc_out, limits::DUST_LIMIT)
+        }
+        VaultAction::SetVaultOperator { vault_id, operator } => {
+            let vault = active_vault(ctx, vault_id, true)?;
+            if let Some(operator) = operator {
+                require_valid_address(*operator, "operator")?;
+                check!(
+                    *operator != vault.owner,
+                    ZkUsdError::InvalidInput { param: "operator", reason: "operator must differ from the owner" }
+                );
+            }
+            Ok(())
+        }
+        VaultAction::SetProtection { vault_id, bps } => {
+            let vault = active_vault(ctx, vault_id, true)?;
+            check!(
+                *bps <= fees::MAX_PROTECTED_COLLATERAL_BPS,
+                ZkUsdError::ExceedsMaximum { amount: *bps, maximum: fees::MAX_PROTECTED_COLLATERAL_BPS }
+            );
+            check!(
+                *bps > vault.protected_collateral_bps,
+                ZkUsdError::InvalidInput { param: "bps", reason: "protection can only be raised" }
+            );
+            Ok(())
+        }
+        VaultAction::SetBeneficiary { vault_id, beneficiary, inactivity_blocks } => {
+            let vault = active_vault(ctx, vault_id, true)?;
+            if let Some(beneficiary) = beneficiary {
+                require_valid_address(*beneficiary, "beneficiary")?;
+                check!(
+                    *beneficiary != vault.owner,
+                    ZkUsdError::InvalidInput { param: "beneficiary", reason: "beneficiary must differ from the owner" }
+                );
+                check!(
+                    *inactivity_blocks >= limits::MIN_BENEFICIARY_INACTIVITY_BLOCKS,
+                    ZkUsdError::BelowMinimum {
+                        amount: *inactivity_blocks,
+                        minimum: limits::MIN_BENEFICIARY_INACTIVITY_BLOCKS,
+                    }
+                );
+            }
+            Ok(())
+        }
+        VaultAction::ClaimAsBeneficiary { vault_id } => {
+            let vault = active_vault(ctx, vault_id, false)?;
+            let (beneficiary, _) = vault.beneficiary.ok_or(ZkUsdError::InvalidInput {
+                param: "vault_id",
+                reason: "vault has no beneficiary",
+            })?;
+            require_owner(beneficiary, ctx.signer)?;
+            let claimable_at = vault.beneficiary_claimable_at().unwrap_or(u64::MAX);
+            check!(
+                ctx.block_height >= claimable_at,
+                ZkUsdError::BeneficiaryClaimTooEarly { claimable_at }
+            );
+            Ok(())
+        }
+        // Advanced operations depend on multi-charm spell layouts and are
+        // not covered by the pre-validation vectors.
+        _ => Err(ZkUsdError::InvalidOperation),
+    }
+}
+
+/// Look up the context vault, enforcing existence, ownership (optional)
+/// and active status in the same order as the vault manager.
+fn active_vault<'a>(
+    ctx: &'a VectorContext,
+    vault_id: &[u8; 32],
+    owner_only: bool,
+) -> ZkUsdResult<&'a Vault> {
+    let vault = ctx.vault.as_ref().ok_or(ZkUsdError::VaultNotFound { vault_id: *vault_id })?;
+    if owner_only {
+        require_owner(vault.owner, ctx.signer)?;
+    }
+    check!(vault.is_active(), ZkUsdError::VaultNotActive { vault_id: *vault_id });
+    Ok(vault)
+}
+
+/// Adding `added` to the system-wide debt must stay within the global
+/// ceiling (vectors carry no pending interest).
+fn require_within_debt_ceiling(ctx: &VectorContext, added: u64) -> ZkUsdResult<()> {
+    let attempted = safe_add(ctx.total_debt, added)?;
+    check!(
+        attempted <= ctx.global_debt_ceiling,
+        ZkUsdError::GlobalDebtCeilingExceeded { attempted, ceiling: ctx.global_debt_ceiling }
+    );
+    Ok(())
+}
+
+// ---- Stability Pool ----
+
+fn check_stability_pool(ctx: &VectorContext, action: &StabilityPoolAction) -> ZkUsdResult<()> {
+    match action {
+        StabilityPoolAction::Deposit { amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            let existing = ctx.deposit.as_ref().map(|d| d.initial_value).unwrap_or(0);
+            let total = safe_add(existing, *amount)?;
+            check!(
+                !(total < MIN_DEPOSIT && existing == 0),
+                ZkUsdError::BelowMinimum { amount: total, minimum: MIN_DEPOSIT }
+            );
+            require_sufficient_balance(ctx.zkusd_inputs(), *amount)
+        }
+        StabilityPoolAction::Withdraw { amount: ZkUsd(amount) } => {
+            check!(*amount > 0, ZkUsdError::ZeroAmount);
+            let deposit = owned_deposit(ctx)?;
+            let compounded = calculate_compounded_deposit(
+                deposit.initial_value,
+                deposit.snapshot_p,
+                ctx.pool.product_p,
+                deposit.snapshot_scale,
+                ctx.pool.current_scale,
+                deposit.snapshot_epoch,
+                ctx.pool.current_epoch,
+            );
+            require_sufficient_balance(compounded, *amount)
+        }
+        StabilityPoolAction::ClaimBtc => {
+            let deposit = owned_deposit(ctx)?;
+            let gain = calculate_btc_gain(deposit.initial_value, deposit.snapshot_s, ctx.pool.sum_s);
+            check!(gain > 0, ZkUsdError::NoRewardsToClaim);
+            require_not_dust(gain, limits::DUST_LIMIT)
+        }
+        StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) } => {
+            let caller = ctx.caller_app_id.ok_or(ZkUsdError::Unauthorized {
+                expected: ctx.vault_manager_id,
+                actual: [0u8; 32],
+            })?;
+            check!(
+                caller == ctx.vault_manager_id,
+                ZkUsdError::Unauthorized { expected: ctx.vault_manager_id, actual: caller }
+            );
+            check!(
+                ctx.pool.total_zkusd >= *debt,
+                ZkUsdError::InsufficientPoolBalance { available: ctx.pool.total_zkusd, required: *debt }
+            );
+            require_sufficient_balance(ctx.btc_inputs, *collateral)
+        }
+        StabilityPoolAction::ClaimBtcToVault { .. } => {
+            let deposit = owned_deposit(ctx)?;
+            let gain = calculate_btc_gain(deposit.initial_value, deposit.snapshot_s, ctx.pool.sum_s);
+            check!(gain > 0, ZkUsdError::NoRewardsToClaim);
+            check!(
+                ctx.caller_app_id == Some(ctx.vault_manager_id),
+                ZkUsdError::Unauthorized {
+                    expected: ctx.vault_manager_id,
+                    actual: ctx.caller_app_id.unwrap_or([0u8; 32]),
+                }
+            );
+            Ok(())
+        }
+        StabilityPoolAction::SweepDustDeposit { depositor } => {
+            let deposit = ctx
+                .deposit
+                .as_ref()
+                .filter(|d| d.owner == *depositor)
+                .ok_or(ZkUsdError::DepositNotFound { user: *depositor })?;
+            let compounded = calculate_compounded_deposit(
+                deposit.initial_value,
+                deposit.snapshot_p,
+                ctx.pool.product_p,
+                deposit.snapshot_scale,
+                ctx.pool.current_scale,
+                deposit.snapshot_epoch,
+                ctx.pool.current_epoch,
+            );
+            check!(
+                compounded < DUST_DEPOSIT_THRESHOLD,
+                ZkUsdError::InvalidAmount { amount: compounded, reason: AmountErrorReason::TooLarge }
+            );
+            let gain = calculate_btc_gain(deposit.initial_value, deposit.snapshot_s, ctx.pool.sum_s);
+            check!(
+                gain == 0 || ctx.signer == deposit.owner,
+                ZkUsdError::Unauthorized { expected: deposit.owner, actual: ctx.signer }
+            );
+            Ok(())
+        }
+        // The pool config (its admin and frontends) is not part of the
+        // vectors
+        StabilityPoolAction::RegisterFrontend { .. } => Err(ZkUsdError::InvalidOperation),
+    }
+}
+
+fn owned_deposit(ctx: &VectorContext) -> ZkUsdResult<&StabilityDeposit> {
+    let deposit = ctx.deposit.as_ref().ok_or(ZkUsdError::DepositNotFound { user: ctx.signer })?;
+    require_owner(deposit.owner, ctx.signer)?;
+    Ok(deposit)
+}
+
+// ---- Price Oracle ----
+
+fn check_oracle(ctx: &VectorContext, action: &OracleAction) -> ZkUsdResult<()> {
+    match action {
+        // Initialization only validates the created output state
+        OracleAction::Initialize { .. } => Ok(()),
+        OracleAction::UpdatePrice { price } | OracleAction::UpdateFeed { feed_id: BTC_USD_FEED, price } => {
+            require_owner(ctx.operator, ctx.signer)?;
+            check_price_update(ctx, *price)
+        }
+        OracleAction::UpdateFeed { feed_id, price } => {
+            let feed = ctx
+                .feeds
+                .iter()
+                .find(|feed| feed.feed_id == *feed_id)
+                .ok_or(ZkUsdError::InvalidInput { param: "feed_id", reason: "unknown feed" })?;
+            require_owner(feed.operator, ctx.signer)?;
+            check!(
+                !ctx.circuit_breaker.guardian_frozen,
+                ZkUsdError::OracleFrozen { frozen_at: ctx.circuit_breaker.tripped_at }
+            );
+            check!(*price > 0, ZkUsdError::ZeroAmount);
+            check!(
+                feed.bounds.contains(*price),
+                ZkUsdError::InvalidInput { param: "price", reason: "outside the feed's price bounds" }
+            );
+            let last_update_block = feed.price.timestamp_block;
+            check!(
+                ctx.block_height.saturating_sub(last_update_block) >= ctx.min_update_interval_blocks,
+                ZkUsdError::OracleUpdateTooSoon {
+                    last_update_block,
+                    current_block: ctx.block_height,
+                    min_interval: ctx.min_update_interval_blocks,
+                }
+            );
+            // A fixed per-feed limit, with no growth and no cumulative window
+            let old_price = feed.price.price;
+            let deviation = old_price.abs_diff(*price) as u128 * 10_000 / old_price.max(1) as u128;
+            check!(
+                deviation <= feed.max_deviation_bps as u128,
+                ZkUsdError::OraclePriceDeviation {
+                    old_price,
+                    new_price: *price,
+                    max_deviation_bps: feed.max_deviation_bps,
+                }
+            );
+            Ok(())
+        }
+        OracleAction::AddFeed { feed } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                feed.feed_id != BTC_USD_FEED && !ctx.feeds.iter().any(|f| f.feed_id == feed.feed_id),
+                ZkUsdError::InvalidInput { param: "feed_id", reason: "feed already exists" }
+            );
+            check!(
+                ctx.feeds.len() < MAX_FEEDS,
+                ZkUsdError::ExceedsMaximum { amount: ctx.feeds.len() as u64 + 1, maximum: MAX_FEEDS as u64 }
+            );
+            check!(
+                feed.bounds.is_valid() && feed.bounds.contains(feed.price.price),
+                ZkUsdError::InvalidInput { param: "feed", reason: "initial price outside valid bounds" }
+            );
+            require_in_range(feed.max_deviation_bps, 1, MAX_SCALED_PRICE_DEVIATION_BPS, "max_deviation_bps")?;
+            // The initial price is published at the spell's block
+            check!(feed.price.timestamp_block == ctx.block_height, ZkUsdError::InvalidStateTransition);
+            Ok(())
+        }
+        OracleAction::RemoveFeed { feed_id } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                ctx.feeds.iter().any(|feed| feed.feed_id == *feed_id),
+                ZkUsdError::InvalidInput { param: "feed_id", reason: "unknown feed" }
+            );
+            Ok(())
+        }
+        OracleAction::SetOperator { operator } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                *operator != ctx.operator,
+                ZkUsdError::InvalidInput { param: "operator", reason: "same as current" }
+            );
+            Ok(())
+        }
+        OracleAction::SetUpdateLimits { min_update_interval_blocks, max_cumulative_deviation_bps } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            require_in_range(
+                *min_update_interval_blocks,
+                MIN_UPDATE_INTERVAL_BLOCKS,
+                MAX_UPDATE_INTERVAL_BLOCKS,
+                "min_update_interval_blocks",
+            )?;
+            require_in_range(
+                *max_cumulative_deviation_bps,
+                MIN_CUMULATIVE_DEVIATION_BPS,
+                MAX_CUMULATIVE_DEVIATION_BPS,
+                "max_cumulative_deviation_bps",
+            )
+        }
+        OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            require_in_range(
+                *deviation_scaling_bps_per_block,
+                0,
+                MAX_DEVIATION_SCALING_BPS_PER_BLOCK,
+                "deviation_scaling_bps_per_block",
+            )
+        }
+        OracleAction::ResetCircuitBreaker => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                ctx.circuit_breaker.tripped,
+                ZkUsdError::InvalidInput { param: "circuit_breaker", reason: "not tripped" }
+            );
+            Ok(())
+        }
+        OracleAction::SetAttestationSources { sources } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                sources.len() <= MAX_ATTESTATION_SOURCES,
+                ZkUsdError::ExceedsMaximum { amount: sources.len() as u64, maximum: MAX_ATTESTATION_SOURCES as u64 }
+            );
+            check!(
+                !sources.iter().enumerate().any(|(i, key)| sources[..i].contains(key)),
+                ZkUsdError::InvalidInput { param: "sources", reason: "duplicate source" }
+            );
+            Ok(())
+        }
+        OracleAction::AggregateUpdate { attestations } => {
+            // A majority of the registered sources, each attesting once
+            let quorum = ctx.attestation_sources.len() / 2 + 1;
+            check!(
+                attestations.len() >= quorum,
+                ZkUsdError::BelowMinimum { amount: attestations.len() as u64, minimum: quorum as u64 }
+            );
+            for (i, attestation) in attestations.iter().enumerate() {
+                check!(ctx.attestation_sources.contains(&attestation.source_pubkey), ZkUsdError::InvalidOracleSource);
+                check!(
+                    !attestations[..i].iter().any(|a| a.source_pubkey == attestation.source_pubkey),
+                    ZkUsdError::InvalidInput { param: "attestations", reason: "duplicate source" }
+                );
+            }
+            // Observed after the current price and within the staleness limit
+            for attestation in attestations {
+                let observed = attestation.observed_at_block;
+                check!(
+                    observed > ctx.price_block
+                        && observed <= ctx.block_height
+                        && ctx.block_height - observed <= MAX_PRICE_AGE_BLOCKS,
+                    ZkUsdError::OracleStale {
+                        last_update_block: observed,
+                        current_block: ctx.block_height,
+                        max_age: MAX_PRICE_AGE_BLOCKS,
+                    }
+                );
+            }
+            // (signatures verified here, see module docs) then the lower
+            // median passes the operator update's checks
+            let mut prices: Vec<u64> = attestations.iter().map(|a| a.price).collect();
+            prices.sort_unstable();
+            check_price_update(ctx, prices[(prices.len() - 1) / 2])
+        }
+        OracleAction::SetPriceBounds { bounds } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                bounds.min_price > 0 && bounds.min_price < bounds.max_price,
+                ZkUsdError::InvalidInput { param: "price_bounds", reason: "min must be positive and below max" }
+            );
+            Ok(())
+        }
+        OracleAction::SetGuardian { guardian } => {
+            require_admin(ctx.admin, ctx.signer)?;
+            check!(
+                *guardian != ctx.guardian,
+                ZkUsdError::InvalidInput { param: "guardian", reason: "same as current" }
+            );
+            Ok(())
+        }
+        OracleAction::FreezePrice => {
+            // The guardian only, and only once the admin has set one
+            check!(
+                ctx.guardian != [0u8; 32],
+                ZkUsdError::Unauthorized { expected: ctx.guardian, actual: ctx.signer }
+            );
+            require_owner(ctx.guardian, ctx.signer)?;
+            check!(
+                !ctx.circuit_breaker.guardian_frozen,
+                ZkUsdError::InvalidInput { param: "circuit_breaker", reason: "already frozen" }
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Price checks shared by operator and aggregate updates
+fn check_price_update(ctx: &VectorContext, price: u64) -> ZkUsdResult<()> {
+    // No updates while the guardian has the oracle frozen
+    check!(
+        !ctx.circuit_breaker.guardian_frozen,
+        ZkUsdError::OracleFrozen { frozen_at: ctx.circuit_breaker.tripped_at }
+    );
+    check!(price > 0, ZkUsdError::ZeroAmount);
+    // Reasonable range for the asset ($1,000 - $10,000,000 for BTC)
+    check!(
+        ctx.price_bounds.contains(price),
+        ZkUsdError::InvalidInput { param: "price", reason: "outside the oracle's price bounds" }
+    );
+    check!(
+        ctx.block_height.saturating_sub(ctx.price_block) >= ctx.min_update_interval_blocks,
+        ZkUsdError::OracleUpdateTooSoon {
+            last_update_block: ctx.price_block,
+            current_block: ctx.block_height,
+            min_interval: ctx.min_update_interval_blocks,
+        }
+    );
+    let old_price = ctx.btc_price;
+    let diff = old_price.abs_diff(price) as u128;
+    let deviation = if old_price == 0 { 10_000 } else { diff * 10_000 / old_price as u128 };
+    // The limit grows with the blocks since the last update, up to a ceiling
+    let elapsed = ctx.block_height.saturating_sub(ctx.price_block);
+    let max_deviation_bps = ctx.deviation_scaling_bps_per_block
+        .saturating_mul(elapsed)
+        .saturating_add(MAX_PRICE_DEVIATION_BPS)
+        .min(MAX_SCALED_PRICE_DEVIATION_BPS);
+    check!(
+        deviation <= max_deviation_bps as u128,
+        ZkUsdError::OraclePriceDeviation {
+            old_price,
+            new_price: price,
+            max_deviation_bps,
+        }
+    );
+    // Sum of the last DEVIATION_WINDOW_UPDATES deviations, this one included
+    let window = ctx.recent_deviations_bps.iter().rev().take(DEVIATION_WINDOW_UPDATES - 1);
+    let cumulative_bps = window.sum::<u64>() + deviation as u64;
+    check!(
+        cumulative_bps <= ctx.max_cumulative_deviation_bps,
+        ZkUsdError::OracleCumulativeDeviation {
+            cumulative_bps,
+            max_bps: ctx.max_cumulative_deviation_bps,
+        }
+    );
+    Ok(())
+}
+
+// ============ Vector Generation ============
+
+fn vector<A: BorshSerialize>(
+    name: &str,
+    contract: ConformanceContract,
+    action: &A,
+    ctx: &VectorContext,
+    expected: Expected,
+) -> TestVector {
+    let action_bytes = borsh::to_vec(action).expect("action serialization is infallible");
+    TestVector {
+        name: name.into(),
+        contract,
+        action_borsh_hex: to_hex(&action_bytes),
+        context_json: serde_json::to_string(ctx).expect("context serialization is infallible"),
+        expected,
+    }
+}
+
+/// Healthy vault: 1 BTC / 40,000 zkUSD (ICR 250% at $100k)
+fn healthy_vault() -> Vault {
+    Vault::new(VAULT_ID, OWNER, ONE, 40_000 * ONE, BLOCK_HEIGHT - 10)
+}
+
+/// Risky vault: 1 BTC / 95,000 zkUSD (ICR ~105% at $100k)
+fn risky_vault() -> Vault {
+    Vault::new(VAULT_ID, OWNER, ONE, 95_000 * ONE, BLOCK_HEIGHT - 10)
+}
+
+/// Whale vault: 20 BTC / 1,900,000 zkUSD (ICR ~105% at $100k), past the
+/// two-phase liquidation threshold
+fn whale_vault() -> Vault {
+    Vault::new(VAULT_ID, OWNER, 20 * ONE, 1_900_000 * ONE, BLOCK_HEIGHT - 10)
+}
+
+/// System in Recovery Mode: 3 BTC / 250,000 zkUSD (TCR 120%)
+fn recovery_ctx() -> VectorContext {
+    VectorContext {
+        total_collateral: 3 * ONE,
+        total_debt: 250_000 * ONE,
+        ..VectorContext::default()
+    }
+}
+
+/// Breaker tripped two blocks ago, still cooling down
+fn tripped_breaker() -> CircuitBreakerState {
+    CircuitBreakerState {
+        tripped: true,
+        tripped_at: BLOCK_HEIGHT - 2,
+        reference_price: BTC_PRICE_100K / 100 * 125,
+        reference_block: BLOCK_HEIGHT - 6,
+        ..CircuitBreakerState::default()
+    }
+}
+
+/// Circuit breaker the guardian froze, long enough ago that a trip would
+/// have cooled down
+fn frozen_breaker() -> CircuitBreakerState {
+    CircuitBreakerState {
+        tripped: true,
+        tripped_at: 1,
+        reference_price: BTC_PRICE_100K,
+        reference_block: 1,
+        guardian_frozen: true,
+    }
+}
+
+fn with_vault(vault: Vault) -> VectorContext {
+    VectorContext { vault: Some(vault), ..VectorContext::default() }
+}
+
+fn balances(entries: &[(Address, u64)]) -> Vec<VectorBalance> {
+    entries.iter().map(|(owner, amount)| VectorBalance { owner: *owner, amount: *amount }).collect()
+}
+
+fn deposit_of(owner: Address, value: u64) -> StabilityDeposit {
+    StabilityDeposit {
+        owner,
+        initial_value: value,
+        snapshot_p: SCALE_FACTOR,
+        snapshot_s: 0,
+        snapshot_epoch: 0,
+        snapshot_scale: 0,
+        last_updated: BLOCK_HEIGHT - 10,
+        frontend_tag: None,
+        kickback_bps: 0,
+    }
+}
+
+fn pool_ctx() -> VectorContext {
+    let mut ctx = VectorContext::default();
+    ctx.pool.total_zkusd = 100_000 * ONE;
+    ctx
+}
+
+/// All conformance vectors.
+pub fn test_vectors() -> Vec<TestVector> {
+    let mut v = Vec::new();
+    v.extend(token_vectors());
+    v.extend(vault_vectors());
+    v.extend(stability_pool_vectors());
+    v.extend(oracle_vectors());
+    v
+}
+
+/// Conformance vectors targeting `contract`.
+pub fn vectors_for(contract: ConformanceContract) -> Vec<TestVector> {
+    test_vectors().into_iter().filter(|v| v.contract == contract).collect()
+}
+
+fn token_vectors() -> Vec<TestVector> {
+    use ConformanceContract::ZkusdToken as C;
+    let transfer = TokenAction::Transfer { from: OWNER, to: BOB, amount: ZkUsd(600), memo: None };
+    let transfer_ctx = VectorContext {
+        token_inputs: balances(&[(OWNER, 1000)]),
+        token_outputs: balances(&[(BOB, 600), (OWNER, 400)]),
+        ..VectorContext::default()
+    };
+    let mint = TokenAction::Mint { to: OWNER, amount: ZkUsd(1000) };
+    let mint_ctx = VectorContext {
+        caller_app_id: Some(TOKEN_MINTER_ID),
+        token_outputs: balances(&[(OWNER, 1000)]),
+        ..VectorContext::default()
+    };
+    let mint_multi = TokenAction::MintMulti { recipients: vec![(OWNER, ZkUsd(700)), (BOB, ZkUsd(300))] };
+    let mint_multi_ctx = VectorContext {
+        caller_app_id: Some(TOKEN_MINTER_ID),
+        token_outputs: balances(&[(OWNER, 700), (BOB, 300)]),
+        ..VectorContext::default()
+    };
+    let burn = TokenAction::Burn { from: OWNER, amount: ZkUsd(600) };
+    let consolidate = TokenAction::Consolidate { owner: OWNER };
+    let consolidate_ctx = VectorContext {
+        token_inputs: balances(&[(OWNER, 100), (OWNER, 200), (OWNER, 300)]),
+        token_outputs: balances(&[(OWNER, 600)]),
+        ..VectorContext::default()
+    };
+    let burn_ctx = VectorContext {
+        caller_app_id: Some(TOKEN_MINTER_ID),
+        token_inputs: balances(&[(OWNER, 1000)]),
+        token_outputs: balances(&[(OWNER, 400)]),
+        ..VectorContext::default()
+    };
+
+    vec![
+        vector("token_transfer_ok", C, &transfer, &transfer_ctx, Expected::Pass),
+        vector(
+            "token_transfer_zero_amount", C,
+            &TokenAction::Transfer { from: OWNER, to: BOB, amount: ZkUsd(0), memo: None },
+            &transfer_ctx,
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+        vector(
+            "token_transfer_insufficient_balance", C,
+            &TokenAction::Transfer { from: OWNER, to: BOB, amount: ZkUsd(2000), memo: None },
+            &transfer_ctx,
+            Expected::fail(ZkUsdError::InsufficientBalance { available: 0, requested: 0 }),
+        ),
+        vector(
+            "token_transfer_conservation_violated", C, &transfer,
+            &VectorContext { token_outputs: balances(&[(BOB, 600), (OWNER, 500)]), ..transfer_ctx.clone() },
+            Expected::fail(ZkUsdError::ConservationViolated { inputs: 0, outputs: 0 }),
+        ),
+        vector(
+            "token_transfer_to_zero_address", C,
+            &TokenAction::Transfer { from: OWNER, to: [0u8; 32], amount: ZkUsd(600), memo: None },
+            &VectorContext { token_outputs: balances(&[([0u8; 32], 600), (OWNER, 400)]), ..transfer_ctx.clone() },
+            Expected::fail(ZkUsdError::InvalidAddress { reason: "" }),
+        ),
+        vector(
+            "token_transfer_unauthorized", C, &transfer,
+            &VectorContext { signer: ATTACKER, ..transfer_ctx.clone() },
+            Expected::fail(ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] }),
+        ),
+        vector("token_mint_ok", C, &mint, &mint_ctx, Expected::Pass),
+        vector(
+            "token_mint_unauthorized", C, &mint,
+            &VectorContext { caller_app_id: Some(ATTACKER), ..mint_ctx.clone() },
+            Expected::fail(ZkUsdError::MintUnauthorized { caller: [0u8; 32] }),
+        ),
+        vector(
+            "token_mint_conservation_violated", C, &mint,
+            &VectorContext { token_outputs: balances(&[(OWNER, 1500)]), ..mint_ctx.clone() },
+            Expected::fail(ZkUsdError::ConservationViolated { inputs: 0, outputs: 0 }),
+        ),
+        vector(
+            "token_mint_ownerless_output", C, &mint,
+            &VectorContext { token_outputs: balances(&[([0u8; 32], 1000)]), ..mint_ctx.clone() },
+            Expected::fail(ZkUsdError::InvalidAddress { reason: "" }),
+        ),
+        vector("token_mint_multi_ok", C, &mint_multi, &mint_multi_ctx, Expected::Pass),
+        vector(
+            "token_mint_multi_recipient_shortchanged", C, &mint_multi,
+            &VectorContext { token_outputs: balances(&[(OWNER, 900), (BOB, 100)]), ..mint_multi_ctx.clone() },
+            Expected::fail(ZkUsdError::InvalidAmount { amount: 0, reason: AmountErrorReason::TooSmall }),
+        ),
+        vector(
+            "token_mint_multi_duplicate_recipient", C,
+            &TokenAction::MintMulti { recipients: vec![(OWNER, ZkUsd(700)), (OWNER, ZkUsd(300))] },
+            &mint_multi_ctx,
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector("token_consolidate_ok", C, &consolidate, &consolidate_ctx, Expected::Pass),
+        vector(
+            "token_consolidate_foreign_input", C, &consolidate,
+            &VectorContext { token_inputs: balances(&[(OWNER, 100), (BOB, 200), (OWNER, 300)]), ..consolidate_ctx.clone() },
+            Expected::fail(ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] }),
+        ),
+        vector(
+            "token_consolidate_not_fewer_outputs", C, &consolidate,
+            &VectorContext { token_outputs: balances(&[(OWNER, 200), (OWNER, 200), (OWNER, 200)]), ..consolidate_ctx.clone() },
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector(
+            "token_consolidate_conservation_violated", C, &consolidate,
+            &VectorContext { token_outputs: balances(&[(OWNER, 500)]), ..consolidate_ctx.clone() },
+            Expected::fail(ZkUsdError::ConservationViolated { inputs: 0, outputs: 0 }),
+        ),
+        vector("token_burn_ok", C, &burn, &burn_ctx, Expected::Pass),
+        vector(
+            "token_burn_unauthorized", C, &burn,
+            &VectorContext { caller_app_id: None, ..burn_ctx.clone() },
+            Expected::fail(ZkUsdError::BurnUnauthorized { caller: [0u8; 32] }),
+        ),
+        vector(
+            "token_burn_zero_amount", C,
+            &TokenAction::Burn { from: OWNER, amount: ZkUsd(0) },
+            &burn_ctx,
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+    ]
+}
+
+fn vault_vectors() -> Vec<TestVector> {
+    use ConformanceContract::VaultManager as C;
+    let undercollateralized = ZkUsdError::Undercollateralized { current_ratio: 0, required_ratio: 0 };
+    let insufficient = ZkUsdError::InsufficientBalance { available: 0, requested: 0 };
+    let unauthorized = ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] };
+    let exceeds = ZkUsdError::ExceedsMaximum { amount: 0, maximum: 0 };
+    let over_cap = ZkUsdError::LifetimeMintCapExceeded { address: [0u8; 32], minted: 0, requested: 0, cap: 0 };
+    // Owner has already minted 40k against a 45k lifetime cap
+    let near_cap = MintTracker { lifetime_cap: Some(45_000 * ONE), minted: vec![(OWNER, 40_000 * ONE)] };
+    let over_ceiling = ZkUsdError::GlobalDebtCeilingExceeded { attempted: 0, ceiling: 0 };
+
+    let open = VaultAction::OpenVault { collateral: Sats(ONE), debt: ZkUsd(40_000 * ONE) };
+    let close = VaultAction::CloseVault { vault_id: VAULT_ID };
+    let repay_inputs = balances(&[(OWNER, 40_000 * ONE)]);
+    // Healthy vault with BOB as its operator
+    let delegated = Vault { operator: Some(BOB), ..healthy_vault() };
+    let window = limits::MIN_BENEFICIARY_INACTIVITY_BLOCKS;
+    let inherited = Vault { beneficiary: Some((BOB, window)), ..healthy_vault() };
+    let claim = VaultAction::ClaimAsBeneficiary { vault_id: VAULT_ID };
+    let claimable_at = inherited.last_updated + window;
+    let claim_at = |signer, block_height| VectorContext {
+        signer,
+        block_height,
+        price_block: block_height,
+        ..with_vault(inherited.clone())
+    };
+
+    vec![
+
+        // Open
+        vector("vault_open_ok", C, &open, &VectorContext::default(), Expected::Pass),
+        vector(
+            "vault_open_undercollateralized", C,
+            &VaultAction::OpenVault { collateral: Sats(ONE), debt: ZkUsd(95_000 * ONE) },
+            &VectorContext::default(),
+            Expected::fail(undercollateralized.clone()),
+        ),
+        vector(
+            "vault_open_zero_collateral", C,
+            &VaultAction::OpenVault { collateral: Sats(0), debt: ZkUsd(40_000 * ONE) },
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector(
+            "vault_open_below_min_debt", C,
+            &VaultAction::OpenVault { collateral: Sats(ONE), debt: ZkUsd(0) },
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::BelowMinimum { amount: 0, minimum: 0 }),
+        ),
+        vector(
+            "vault_open_exceeds_max_debt", C,
+            &VaultAction::OpenVault { collateral: Sats(1_000 * ONE), debt: ZkUsd(limits::MAX_DEBT_PER_VAULT) },
+            &VectorContext::default(),
+            Expected::fail(exceeds.clone()),
+        ),
+        vector(
+            "vault_open_lifetime_cap_exceeded", C, &open,
+            &VectorContext { mint_tracker: MintTracker::with_cap(30_000 * ONE), ..VectorContext::default() },
+            Expected::fail(over_cap.clone()),
+        ),
+        vector(
+            "vault_open_global_debt_ceiling_exceeded", C, &open,
+            &VectorContext { global_debt_ceiling: 220_000 * ONE, ..VectorContext::default() },
+            Expected::fail(over_ceiling.clone()),
+        ),
+        vector(
+            "vault_open_protocol_paused", C, &open,
+            &VectorContext { is_paused: true, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::ProtocolPaused),
+        ),
+        vector(
+            "vault_open_stale_oracle", C, &open,
+            &VectorContext {
+                price_block: BLOCK_HEIGHT - crate::constants::oracle::MAX_PRICE_AGE_BLOCKS - 1,
+                ..VectorContext::default()
+            },
+            Expected::fail(ZkUsdError::OracleStale { last_update_block: 0, current_block: 0, max_age: 0 }),
+        ),
+        vector(
+            "vault_open_recovery_below_ccr", C,
+            &VaultAction::OpenVault { collateral: Sats(ONE), debt: ZkUsd(75_000 * ONE) },
+            &recovery_ctx(),
+            Expected::fail(undercollateralized.clone()),
+        ),
+        vector(
+            "vault_open_recovery_improves_tcr", C,
+            &VaultAction::OpenVault { collateral: Sats(2 * ONE), debt: ZkUsd(40_000 * ONE) },
+            &recovery_ctx(),
+            Expected::Pass,
+        ),
+
+        // Close
+        vector(
+            "vault_close_ok", C, &close,
+            &VectorContext { token_inputs: repay_inputs.clone(), ..with_vault(healthy_vault()) },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_close_unauthorized", C, &close,
+            &VectorContext { signer: ATTACKER, token_inputs: repay_inputs.clone(), ..with_vault(healthy_vault()) },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "vault_close_insufficient_zkusd", C, &close,
+            &VectorContext { token_inputs: balances(&[(OWNER, 10_000 * ONE)]), ..with_vault(healthy_vault()) },
+            Expected::fail(insufficient.clone()),
+        ),
+        vector(
+            "vault_close_last_vault_in_recovery", C, &close,
+            &VectorContext {
+                vault: Some(healthy_vault()),
+                active_vault_count: 1,
+                token_inputs: repay_inputs.clone(),
+                ..recovery_ctx()
+            },
+            Expected::fail(ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::CloseLastVault }),
+        ),
+        vector(
+            "vault_close_not_found", C, &close,
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::VaultNotFound { vault_id: VAULT_ID }),
+        ),
+        vector(
+            "vault_close_not_active", C, &close,
+            &with_vault(Vault { status: VaultStatus::Closed, ..healthy_vault() }),
+            Expected::fail(ZkUsdError::VaultNotActive { vault_id: VAULT_ID }),
+        ),
+
+        // Collateral
+        vector(
+            "vault_add_collateral_ok", C,
+            &VaultAction::AddCollateral { vault_id: VAULT_ID, amount: Sats(ONE / 2) },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_add_collateral_zero_amount", C,
+            &VaultAction::AddCollateral { vault_id: VAULT_ID, amount: Sats(0) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector(
+            "vault_add_collateral_by_operator", C,
+            &VaultAction::AddCollateral { vault_id: VAULT_ID, amount: Sats(ONE / 2) },
+            &VectorContext { signer: BOB, ..with_vault(delegated.clone()) },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_withdraw_collateral_by_operator", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(ONE / 5) },
+            &VectorContext { signer: BOB, ..with_vault(delegated.clone()) },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "vault_withdraw_collateral_ok", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(ONE / 5) },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_withdraw_collateral_undercollateralized", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(ONE * 7 / 10) },
+            &with_vault(healthy_vault()),
+            Expected::fail(undercollateralized.clone()),
+        ),
+        vector(
+            "vault_withdraw_collateral_in_recovery", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(ONE / 5) },
+            &VectorContext { vault: Some(healthy_vault()), ..recovery_ctx() },
+            Expected::fail(ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::WithdrawCollateral }),
+        ),
+        vector(
+            "vault_withdraw_collateral_exceeds_balance", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(2 * ONE) },
+            &with_vault(healthy_vault()),
+            Expected::fail(insufficient.clone()),
+        ),
+        vector(
+            "vault_withdraw_collateral_below_min_adjustment", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(limits::MIN_COLLATERAL_ADJUSTMENT - 1) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::AdjustmentTooSmall { delta: 0, minimum: 0 }),
+        ),
+        vector(
+            "vault_withdraw_collateral_all_with_debt", C,
+            &VaultAction::WithdrawCollateral { vault_id: VAULT_ID, amount: Sats(ONE) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+
+        // Debt
+        vector(
+            "vault_mint_debt_ok", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_mint_debt_zero_amount", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(0) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+        vector(
+            "vault_mint_debt_unauthorized", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { signer: ATTACKER, ..with_vault(healthy_vault()) },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "vault_mint_debt_in_recovery", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { vault: Some(healthy_vault()), ..recovery_ctx() },
+            Expected::fail(ZkUsdError::RecoveryModeRestriction { operation: RecoveryModeOp::MintDebt }),
+        ),
+        vector(
+            "vault_mint_debt_exceeds_max", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(limits::MAX_DEBT_PER_VAULT) },
+            &with_vault(healthy_vault()),
+            Expected::fail(exceeds.clone()),
+        ),
+        vector(
+            "vault_mint_debt_lifetime_cap_exceeded", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { mint_tracker: near_cap.clone(), ..with_vault(healthy_vault()) },
+            Expected::fail(over_cap),
+        ),
+        vector(
+            "vault_mint_debt_within_lifetime_cap", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(5_000 * ONE) },
+            &VectorContext { mint_tracker: near_cap, ..with_vault(healthy_vault()) },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_mint_debt_global_debt_ceiling_exceeded", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(30_000 * ONE) },
+            &VectorContext { global_debt_ceiling: 220_000 * ONE, ..with_vault(healthy_vault()) },
+            Expected::fail(over_ceiling),
+        ),
+        vector(
+            "vault_mint_debt_within_global_debt_ceiling", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { global_debt_ceiling: 220_000 * ONE, ..with_vault(healthy_vault()) },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_mint_debt_below_min_adjustment", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(limits::MIN_ADJUSTMENT - 1) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::AdjustmentTooSmall { delta: 0, minimum: 0 }),
+        ),
+        vector(
+            "vault_mint_debt_undercollateralized", C,
+            &VaultAction::MintDebt { vault_id: VAULT_ID, amount: ZkUsd(60_000 * ONE) },
+            &with_vault(healthy_vault()),
+            Expected::fail(undercollateralized),
+        ),
+        vector(
+            "vault_repay_debt_ok", C,
+            &VaultAction::RepayDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { token_inputs: balances(&[(OWNER, 10_000 * ONE)]), ..with_vault(healthy_vault()) },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_repay_debt_exceeds_net_debt", C,
+            &VaultAction::RepayDebt { vault_id: VAULT_ID, amount: ZkUsd(40_000 * ONE) },
+            &VectorContext { token_inputs: repay_inputs, ..with_vault(healthy_vault()) },
+            Expected::fail(exceeds.clone()),
+        ),
+        vector(
+            "vault_repay_debt_insufficient_zkusd", C,
+            &VaultAction::RepayDebt { vault_id: VAULT_ID, amount: ZkUsd(10_000 * ONE) },
+            &VectorContext { token_inputs: balances(&[(OWNER, 5_000 * ONE)]), ..with_vault(healthy_vault()) },
+            Expected::fail(insufficient.clone()),
+        ),
+
+        // Liquidation & redemption
+        vector(
+            "vault_liquidate_ok", C,
+            &VaultAction::Liquidate { vault_id: VAULT_ID },
+            &with_vault(risky_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_liquidate_circuit_breaker_active", C,
+            &VaultAction::Liquidate { vault_id: VAULT_ID },
+            &VectorContext { circuit_breaker: tripped_breaker(), ..with_vault(risky_vault()) },
+            Expected::fail(ZkUsdError::CircuitBreakerActive { until_block: 0 }),
+        ),
+        vector(
+            "vault_liquidate_healthy_vault", C,
+            &VaultAction::Liquidate { vault_id: VAULT_ID },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::NotLiquidatable { vault_id: VAULT_ID, icr: 0 }),
+        ),
+        vector(
+            "vault_liquidate_whale_needs_two_phase", C,
+            &VaultAction::Liquidate { vault_id: VAULT_ID },
+            &with_vault(whale_vault()),
+            Expected::fail(ZkUsdError::WrongLiquidationPath { debt: 0, two_phase_required: true }),
+        ),
+        vector(
+            "vault_begin_liquidation_ok", C,
+            &VaultAction::BeginLiquidation { vault_id: VAULT_ID },
+            &with_vault(whale_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_begin_liquidation_small_vault", C,
+            &VaultAction::BeginLiquidation { vault_id: VAULT_ID },
+            &with_vault(risky_vault()),
+            Expected::fail(ZkUsdError::WrongLiquidationPath { debt: 0, two_phase_required: false }),
+        ),
+        vector(
+            "vault_redeem_ok", C,
+            &VaultAction::Redeem { amount: ZkUsd(1_000 * ONE), min_btc_out: Sats(0) },
+            &VectorContext { token_inputs: balances(&[(OWNER, 1_000 * ONE)]), ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "vault_redeem_zero_amount", C,
+            &VaultAction::Redeem { amount: ZkUsd(0), min_btc_out: Sats(0) },
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+        vector(
+            "vault_redeem_insufficient_zkusd", C,
+            &VaultAction::Redeem { amount: ZkUsd(1_000 * ONE), min_btc_out: Sats(0) },
+            &VectorContext { token_inputs: balances(&[(OWNER, 500 * ONE)]), ..VectorContext::default() },
+            Expected::fail(insufficient),
+        ),
+        vector(
+            "vault_redeem_slippage_exceeded", C,
+            // 1,000 zkUSD at $100k pays 1,000,000 sats
+            &VaultAction::Redeem { amount: ZkUsd(1_000 * ONE), min_btc_out: Sats(1_000_001) },
+            &VectorContext { token_inputs: balances(&[(OWNER, 1_000 * ONE)]), ..VectorContext::default() },
+            Expected::fail(ZkUsdError::SlippageExceeded { expected_min: 0, actual: 0 }),
+        ),
+
+        // Operator key
+        vector(
+            "vault_set_operator_ok", C,
+            &VaultAction::SetVaultOperator { vault_id: VAULT_ID, operator: Some(BOB) },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_clear_operator_ok", C,
+            &VaultAction::SetVaultOperator { vault_id: VAULT_ID, operator: None },
+            &with_vault(delegated.clone()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_set_operator_to_owner", C,
+            &VaultAction::SetVaultOperator { vault_id: VAULT_ID, operator: Some(OWNER) },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector(
+            "vault_set_operator_by_operator", C,
+            &VaultAction::SetVaultOperator { vault_id: VAULT_ID, operator: Some(ATTACKER) },
+            &VectorContext { signer: BOB, ..with_vault(delegated) },
+            Expected::fail(unauthorized.clone()),
+        ),
+
+        // Redemption protection
+        vector(
+            "vault_set_protection_ok", C,
+            &VaultAction::SetProtection { vault_id: VAULT_ID, bps: 3_000 },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_set_protection_above_max", C,
+            &VaultAction::SetProtection { vault_id: VAULT_ID, bps: 3_001 },
+            &with_vault(healthy_vault()),
+            Expected::fail(exceeds),
+        ),
+        vector(
+            "vault_set_protection_lowered", C,
+            &VaultAction::SetProtection { vault_id: VAULT_ID, bps: 1_000 },
+            &with_vault(Vault { protected_collateral_bps: 2_000, ..healthy_vault() }),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+
+        // Vault inheritance
+        vector(
+            "vault_set_beneficiary_ok", C,
+            &VaultAction::SetBeneficiary { vault_id: VAULT_ID, beneficiary: Some(BOB), inactivity_blocks: window },
+            &with_vault(healthy_vault()),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_set_beneficiary_window_too_short", C,
+            &VaultAction::SetBeneficiary { vault_id: VAULT_ID, beneficiary: Some(BOB), inactivity_blocks: window - 1 },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::BelowMinimum { amount: 0, minimum: 0 }),
+        ),
+        vector(
+            "vault_set_beneficiary_to_owner", C,
+            &VaultAction::SetBeneficiary { vault_id: VAULT_ID, beneficiary: Some(OWNER), inactivity_blocks: window },
+            &with_vault(healthy_vault()),
+            Expected::fail(ZkUsdError::InvalidInput { param: "", reason: "" }),
+        ),
+        vector(
+            "vault_claim_as_beneficiary_ok", C,
+            &claim,
+            &claim_at(BOB, claimable_at),
+            Expected::Pass,
+        ),
+        vector(
+            "vault_claim_as_beneficiary_too_early", C,
+            &claim,
+            &claim_at(BOB, claimable_at - 1),
+            Expected::fail(ZkUsdError::BeneficiaryClaimTooEarly { claimable_at: 0 }),
+        ),
+        vector(
+            "vault_claim_as_beneficiary_by_stranger", C,
+            &claim,
+            &claim_at(ATTACKER, claimable_at),
+            Expected::fail(unauthorized),
+        ),
+    ]
+}
+
+fn stability_pool_vectors() -> Vec<TestVector> {
+    use ConformanceContract::StabilityPool as C;
+    let insufficient = ZkUsdError::InsufficientBalance { available: 0, requested: 0 };
+    let unauthorized = ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] };
+
+    let deposit = StabilityPoolAction::Deposit { amount: ZkUsd(10_000 * ONE) };
+    let deposit_ctx = VectorContext { token_inputs: balances(&[(OWNER, 10_000 * ONE)]), ..pool_ctx() };
+    let withdraw = StabilityPoolAction::Withdraw { amount: ZkUsd(5_000 * ONE) };
+    let depositor_ctx = VectorContext { deposit: Some(deposit_of(OWNER, 10_000 * ONE)), ..pool_ctx() };
+    let offset = StabilityPoolAction::Offset { debt: ZkUsd(10_000 * ONE), collateral: Sats(ONE) };
+    let offset_ctx = VectorContext { caller_app_id: Some(VAULT_MANAGER_ID), btc_inputs: ONE, ..pool_ctx() };
+
+    let mut rewarded_ctx = depositor_ctx.clone();
+    rewarded_ctx.pool.sum_s = SCALE_FACTOR / 1_000;
+    let mut depleted_ctx = depositor_ctx.clone();
+    depleted_ctx.pool.product_p = SCALE_FACTOR / 2;
+    let claim_to_vault = StabilityPoolAction::ClaimBtcToVault { vault_id: [7u8; 32] };
+    let routed_ctx = VectorContext { caller_app_id: Some(VAULT_MANAGER_ID), ..rewarded_ctx.clone() };
+    let sweep = StabilityPoolAction::SweepDustDeposit { depositor: OWNER };
+    // Swept by its depositor: the deposit is worn down to 0.1 zkUSD with a
+    // gain still to pay out
+    let mut dust_ctx = rewarded_ctx.clone();
+    dust_ctx.pool.product_p = SCALE_FACTOR / 100_000;
+
+    vec![
+        vector("sp_deposit_ok", C, &deposit, &deposit_ctx, Expected::Pass),
+        vector(
+            "sp_deposit_zero_amount", C,
+            &StabilityPoolAction::Deposit { amount: ZkUsd(0) },
+            &deposit_ctx,
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+        vector(
+            "sp_deposit_below_minimum", C,
+            &StabilityPoolAction::Deposit { amount: ZkUsd(MIN_DEPOSIT - 1) },
+            &deposit_ctx,
+            Expected::fail(ZkUsdError::BelowMinimum { amount: 0, minimum: 0 }),
+        ),
+        vector(
+            "sp_deposit_insufficient_zkusd", C, &deposit,
+            &VectorContext { token_inputs: balances(&[(OWNER, 5_000 * ONE)]), ..pool_ctx() },
+            Expected::fail(insufficient.clone()),
+        ),
+        vector("sp_withdraw_ok", C, &withdraw, &depositor_ctx, Expected::Pass),
+        vector(
+            "sp_withdraw_unauthorized", C, &withdraw,
+            &VectorContext { signer: ATTACKER, ..depositor_ctx.clone() },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "sp_withdraw_exceeds_compounded", C,
+            &StabilityPoolAction::Withdraw { amount: ZkUsd(6_000 * ONE) },
+            &depleted_ctx,
+            Expected::fail(insufficient.clone()),
+        ),
+        vector(
+            "sp_withdraw_no_deposit", C, &withdraw,
+            &pool_ctx(),
+            Expected::fail(ZkUsdError::DepositNotFound { user: OWNER }),
+        ),
+        vector("sp_claim_btc_ok", C, &StabilityPoolAction::ClaimBtc, &rewarded_ctx, Expected::Pass),
+        vector(
+            "sp_claim_btc_no_rewards", C,
+            &StabilityPoolAction::ClaimBtc,
+            &depositor_ctx,
+            Expected::fail(ZkUsdError::NoRewardsToClaim),
+        ),
+        vector("sp_claim_btc_to_vault_ok", C, &claim_to_vault, &routed_ctx, Expected::Pass),
+        vector(
+            "sp_claim_btc_to_vault_without_vault_manager", C, &claim_to_vault,
+            &rewarded_ctx,
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector("sp_sweep_dust_deposit_ok", C, &sweep, &dust_ctx, Expected::Pass),
+        vector(
+            "sp_sweep_dust_deposit_gain_by_keeper", C, &sweep,
+            &VectorContext { signer: ATTACKER, ..dust_ctx.clone() },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "sp_sweep_healthy_deposit", C, &sweep,
+            &VectorContext { signer: ATTACKER, ..rewarded_ctx.clone() },
+            Expected::fail(ZkUsdError::InvalidAmount { amount: 0, reason: AmountErrorReason::TooLarge }),
+        ),
+        vector(
+            "sp_sweep_dust_deposit_wrong_depositor", C,
+            &StabilityPoolAction::SweepDustDeposit { depositor: ATTACKER },
+            &dust_ctx,
+            Expected::fail(ZkUsdError::DepositNotFound { user: ATTACKER }),
+        ),
+        vector("sp_offset_ok", C, &offset, &offset_ctx, Expected::Pass),
+        vector(
+            "sp_offset_unauthorized", C, &offset,
+            &VectorContext { caller_app_id: Some(ATTACKER), ..offset_ctx.clone() },
+            Expected::fail(unauthorized),
+        ),
+        vector(
+            "sp_offset_insufficient_pool", C,
+            &StabilityPoolAction::Offset { debt: ZkUsd(200_000 * ONE), collateral: Sats(ONE) },
+            &offset_ctx,
+            Expected::fail(ZkUsdError::InsufficientPoolBalance { available: 0, required: 0 }),
+        ),
+        vector(
+            "sp_offset_insufficient_collateral", C, &offset,
+            &VectorContext { btc_inputs: ONE / 2, ..offset_ctx.clone() },
+            Expected::fail(insufficient),
+        ),
+    ]
+}
+
+fn oracle_vectors() -> Vec<TestVector> {
+    use ConformanceContract::PriceOracle as C;
+    let invalid_input = ZkUsdError::InvalidInput { param: "", reason: "" };
+    let unauthorized = ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] };
+    let update = OracleAction::UpdatePrice { price: 101_000 * 100_000_000 };
+    // Last update old enough for the next one
+    let updatable = VectorContext {
+        price_block: BLOCK_HEIGHT - DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+        ..VectorContext::default()
+    };
+    // Three registered feeds, so two make a quorum
+    let attestable = VectorContext {
+        attestation_sources: vec![FEED_A, FEED_B, [0xFC; 32]],
+        ..updatable.clone()
+    };
+    // A stablecoin feed at $1, last updated long enough ago
+    let usdc = Feed {
+        feed_id: *b"USDCUSD\0",
+        price: PriceData::published(ONE, BLOCK_HEIGHT - DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS, PriceSource::Mock),
+        bounds: PriceBounds { min_price: ONE / 2, max_price: ONE * 2 },
+        max_deviation_bps: 200,
+        operator: FEED_A,
+    };
+    let with_feed = VectorContext { feeds: vec![usdc.clone()], signer: FEED_A, ..updatable.clone() };
+    let update_feed = |price| OracleAction::UpdateFeed { feed_id: usdc.feed_id, price };
+    // Signatures are not modeled, so every vector fails before they are checked
+    let attested = |source_pubkey, observed_at_block| PriceAttestation {
+        source_pubkey,
+        price: BTC_PRICE_100K,
+        observed_at_block,
+        signature: [0u8; 64],
+    };
+
+    vec![
+        vector(
+            "oracle_initialize_ok", C,
+            &OracleAction::Initialize { admin: ADMIN, operator: OWNER, initial_price: BTC_PRICE_100K },
+            &VectorContext::default(),
+            Expected::Pass,
+        ),
+        vector("oracle_update_price_ok", C, &update, &updatable, Expected::Pass),
+        vector(
+            "oracle_update_price_too_soon", C, &update,
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::OracleUpdateTooSoon { last_update_block: 0, current_block: 0, min_interval: 0 }),
+        ),
+        vector(
+            "oracle_update_price_cumulative_deviation", C, &update,
+            &VectorContext { recent_deviations_bps: vec![500, 500, 500], ..updatable.clone() },
+            Expected::fail(ZkUsdError::OracleCumulativeDeviation { cumulative_bps: 0, max_bps: 0 }),
+        ),
+        vector(
+            "oracle_update_price_window_slides", C, &update,
+            &VectorContext { recent_deviations_bps: vec![500, 100, 500, 500], ..updatable.clone() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_update_price_unauthorized", C, &update,
+            &VectorContext { signer: ATTACKER, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] }),
+        ),
+        vector(
+            "oracle_update_price_zero", C,
+            &OracleAction::UpdatePrice { price: 0 },
+            &VectorContext::default(),
+            Expected::fail(ZkUsdError::ZeroAmount),
+        ),
+        vector(
+            "oracle_update_price_out_of_range", C,
+            &OracleAction::UpdatePrice { price: 500 * 100_000_000 },
+            &VectorContext::default(),
+            Expected::fail(invalid_input.clone()),
+        ),
+        vector(
+            "oracle_update_price_outside_asset_bounds", C, &update,
+            &VectorContext {
+                price_bounds: PriceBounds { min_price: 100_000_000_000, max_price: BTC_PRICE_100K },
+                ..updatable.clone()
+            },
+            Expected::fail(invalid_input.clone()),
+        ),
+        vector(
+            "oracle_update_price_excessive_deviation", C,
+            &OracleAction::UpdatePrice { price: 120_000 * 100_000_000 },
+            &updatable,
+            Expected::fail(ZkUsdError::OraclePriceDeviation { old_price: 0, new_price: 0, max_deviation_bps: 0 }),
+        ),
+        vector(
+            "oracle_update_price_scaled_deviation_after_quiet_period", C,
+            &OracleAction::UpdatePrice { price: BTC_PRICE_100K / 100 * 110 },
+            &VectorContext {
+                price_block: BLOCK_HEIGHT - 20,
+                deviation_scaling_bps_per_block: 25,
+                ..VectorContext::default()
+            },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_update_price_scaled_deviation_too_soon", C,
+            &OracleAction::UpdatePrice { price: BTC_PRICE_100K / 100 * 110 },
+            &VectorContext { deviation_scaling_bps_per_block: 25, ..updatable.clone() },
+            Expected::fail(ZkUsdError::OraclePriceDeviation { old_price: 0, new_price: 0, max_deviation_bps: 0 }),
+        ),
+        vector(
+            "oracle_set_operator_ok", C,
+            &OracleAction::SetOperator { operator: BOB },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_operator_not_admin", C,
+            &OracleAction::SetOperator { operator: BOB },
+            &VectorContext { signer: ATTACKER, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::AdminOnly),
+        ),
+        vector(
+            "oracle_set_operator_unchanged", C,
+            &OracleAction::SetOperator { operator: OWNER },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(invalid_input.clone()),
+        ),
+        vector(
+            "oracle_set_update_limits_ok", C,
+            &OracleAction::SetUpdateLimits { min_update_interval_blocks: 3, max_cumulative_deviation_bps: 2000 },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_update_limits_out_of_bounds", C,
+            &OracleAction::SetUpdateLimits { min_update_interval_blocks: 0, max_cumulative_deviation_bps: 2000 },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::BelowMinimum { amount: 0, minimum: 0 }),
+        ),
+        vector(
+            "oracle_set_deviation_scaling_ok", C,
+            &OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block: 25 },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_deviation_scaling_out_of_bounds", C,
+            &OracleAction::SetDeviationScaling {
+                deviation_scaling_bps_per_block: MAX_DEVIATION_SCALING_BPS_PER_BLOCK + 1,
+            },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::ExceedsMaximum { amount: 0, maximum: 0 }),
+        ),
+        vector(
+            "oracle_reset_circuit_breaker_ok", C,
+            &OracleAction::ResetCircuitBreaker,
+            &VectorContext { signer: ADMIN, circuit_breaker: tripped_breaker(), ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_reset_circuit_breaker_not_tripped", C,
+            &OracleAction::ResetCircuitBreaker,
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::InvalidInput { param: "circuit_breaker", reason: "" }),
+        ),
+        vector(
+            "oracle_reset_circuit_breaker_lifts_freeze", C,
+            &OracleAction::ResetCircuitBreaker,
+            &VectorContext { signer: ADMIN, circuit_breaker: frozen_breaker(), ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_update_price_frozen", C, &update,
+            &VectorContext { circuit_breaker: frozen_breaker(), ..updatable.clone() },
+            Expected::fail(ZkUsdError::OracleFrozen { frozen_at: 0 }),
+        ),
+        vector(
+            "oracle_set_guardian_ok", C,
+            &OracleAction::SetGuardian { guardian: GUARDIAN },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_guardian_not_admin", C,
+            &OracleAction::SetGuardian { guardian: GUARDIAN },
+            &VectorContext { signer: GUARDIAN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::AdminOnly),
+        ),
+        vector(
+            "oracle_freeze_price_ok", C,
+            &OracleAction::FreezePrice,
+            &VectorContext { signer: GUARDIAN, guardian: GUARDIAN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_freeze_price_not_guardian", C,
+            &OracleAction::FreezePrice,
+            &VectorContext { signer: ATTACKER, guardian: GUARDIAN, ..VectorContext::default() },
+            Expected::fail(unauthorized.clone()),
+        ),
+        vector(
+            "oracle_freeze_price_no_guardian", C,
+            &OracleAction::FreezePrice,
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(unauthorized),
+        ),
+        vector(
+            "oracle_freeze_price_already_frozen", C,
+            &OracleAction::FreezePrice,
+            &VectorContext { signer: GUARDIAN, guardian: GUARDIAN, circuit_breaker: frozen_breaker(), ..VectorContext::default() },
+            Expected::fail(ZkUsdError::InvalidInput { param: "circuit_breaker", reason: "" }),
+        ),
+        vector(
+            "oracle_set_attestation_sources_ok", C,
+            &OracleAction::SetAttestationSources { sources: vec![FEED_A, FEED_B] },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_attestation_sources_duplicate", C,
+            &OracleAction::SetAttestationSources { sources: vec![FEED_A, FEED_A] },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::InvalidInput { param: "sources", reason: "" }),
+        ),
+        vector(
+            "oracle_set_price_bounds_ok", C,
+            &OracleAction::SetPriceBounds { bounds: PriceBounds { min_price: 10_000_000, max_price: 1_000_000_000 } },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_set_price_bounds_inverted", C,
+            &OracleAction::SetPriceBounds { bounds: PriceBounds { min_price: 1_000_000_000, max_price: 10_000_000 } },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::fail(ZkUsdError::InvalidInput { param: "price_bounds", reason: "" }),
+        ),
+        vector(
+            "oracle_aggregate_update_below_quorum", C,
+            &OracleAction::AggregateUpdate { attestations: vec![attested(FEED_A, BLOCK_HEIGHT)] },
+            &attestable,
+            Expected::fail(ZkUsdError::BelowMinimum { amount: 0, minimum: 0 }),
+        ),
+        vector(
+            "oracle_aggregate_update_unregistered_source", C,
+            &OracleAction::AggregateUpdate {
+                attestations: vec![attested(FEED_A, BLOCK_HEIGHT), attested(ATTACKER, BLOCK_HEIGHT)],
+            },
+            &attestable,
+            Expected::fail(ZkUsdError::InvalidOracleSource),
+        ),
+        vector(
+            "oracle_aggregate_update_replayed_attestation", C,
+            &OracleAction::AggregateUpdate {
+                attestations: vec![attested(FEED_A, BLOCK_HEIGHT), attested(FEED_B, attestable.price_block)],
+            },
+            &attestable,
+            Expected::fail(ZkUsdError::OracleStale { last_update_block: 0, current_block: 0, max_age: 0 }),
+        ),
+        vector("oracle_update_feed_ok", C, &update_feed(ONE - ONE / 100), &with_feed, Expected::Pass),
+        vector(
+            "oracle_update_feed_btc_usd_is_update_price", C,
+            &OracleAction::UpdateFeed { feed_id: BTC_USD_FEED, price: 101_000 * 100_000_000 },
+            &updatable,
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_update_feed_not_feed_operator", C, &update_feed(ONE),
+            &VectorContext { signer: OWNER, ..with_feed.clone() },
+            Expected::fail(ZkUsdError::Unauthorized { expected: [0u8; 32], actual: [0u8; 32] }),
+        ),
+        vector(
+            "oracle_update_feed_deviation", C, &update_feed(ONE + ONE / 20),
+            &with_feed,
+            Expected::fail(ZkUsdError::OraclePriceDeviation { old_price: 0, new_price: 0, max_deviation_bps: 0 }),
+        ),
+        vector(
+            "oracle_update_feed_unknown", C, &update_feed(ONE),
+            &VectorContext { feeds: Vec::new(), ..with_feed.clone() },
+            Expected::fail(invalid_input.clone()),
+        ),
+        vector(
+            "oracle_add_feed_ok", C,
+            &OracleAction::AddFeed { feed: Feed { price: PriceData::published(ONE, BLOCK_HEIGHT, PriceSource::Mock), ..usdc.clone() } },
+            &VectorContext { signer: ADMIN, ..VectorContext::default() },
+            Expected::Pass,
+        ),
+        vector(
+            "oracle_add_feed_duplicate", C,
+            &OracleAction::AddFeed { feed: usdc.clone() },
+            &VectorContext { signer: ADMIN, ..with_feed.clone() },
+            Expected::fail(invalid_input),
+        ),
+        vector(
+            "oracle_remove_feed_not_admin", C,
+            &OracleAction::RemoveFeed { feed_id: usdc.feed_id },
+            &with_feed,
+            Expected::fail(ZkUsdError::AdminOnly),
+        ),
+    ]
+}
+
+// ============ JSON Export ============
+
+/// Serialize all vectors as a pretty-printed JSON array.
+pub fn test_vectors_json() -> String {
+    serde_json::to_string_pretty(&test_vectors()).expect("vector serialization is infallible")
+}
+
+// ============ Hex Helpers ============
+
+fn to_hex(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        out.push(HEX[(b >> 4) as usize] as char);
+        out.push(HEX[(b & 0x0f) as usize] as char);
+    }
+    out
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// ============ Tests ============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_vector_matches_expected() {
+        for v in test_vectors() {
+            assert!(
+                run_vector(&v),
+                "vector {} expected {:?}, got {:?}",
+                v.name,
+                v.expected,
+                execute_vector(&v)
+            );
+        }
+    }
+
+    #[test]
+    fn test_vector_coverage() {
+        let vectors = test_vectors();
+        assert!(vectors.len() >= 40);
+
+        for contract in [
+            ConformanceContract::ZkusdToken,
+            ConformanceContract::VaultManager,
+            ConformanceContract::StabilityPool,
+            ConformanceContract::PriceOracle,
+        ] {
+            assert!(vectors.iter().any(|v| v.contract == contract && v.expected == Expected::Pass));
+            assert!(vectors.iter().any(|v| v.contract == contract && v.expected != Expected::Pass));
+        }
+
+        // Vector names are unique
+        let mut names: Vec<_> = vectors.iter().map(|v| v.name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), vectors.len());
+    }
+
+    #[test]
+    fn test_wrong_expectation_is_detected() {
+        let mut v = test_vectors().into_iter().find(|v| v.name == "vault_open_ok").unwrap();
+        v.expected = Expected::fail(ZkUsdError::ZeroAmount);
+        assert!(!run_vector(&v));
+    }
+
+    #[test]
+    fn test_malformed_vector_rejected() {
+        let mut v = test_vectors().remove(0);
+        v.action_borsh_hex = "zz".into();
+        assert_eq!(execute_vector(&v), Err(ZkUsdError::InvalidSpellFormat));
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let json = test_vectors_json();
+        let parsed: Vec<TestVector> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, test_vectors());
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = [0x00, 0x7f, 0xff, 0x10];
+        assert_eq!(to_hex(&bytes), "007fff10");
+        assert_eq!(from_hex("007fff10").unwrap(), bytes.to_vec());
+        assert!(from_hex("abc").is_none());
+    }
+}
@@ -24,6 +24,10 @@ pub mod token {
     pub const DECIMALS: u8 = 8;
     /// One unit with decimals (1 zkUSD = 100_000_000 base units)
     pub const ONE: u64 = 100_000_000;
+    /// Maximum recipients of a single multi-recipient mint
+    pub const MAX_MINT_RECIPIENTS: usize = 16;
+    /// Maximum balances a single consolidation may spend
+    pub const MAX_CONSOLIDATION_INPUTS: usize = 64;
 }
 
 /// Collateralization Ratios (in percentage points, e.g., 110 = 110%)
@@ -39,8 +43,19 @@ pub mod ratios {
     /// Recommended minimum ratio for users (safety buffer)
     pub const RECOMMENDED_MIN: u64 = 200;
 
+    /// Default ICR below which a vault is warned it is at risk
+    /// 125% leaves a margin above MCR for the owner to react
+    pub const WARNING_ICR: u64 = 125;
+
     /// Maximum LTV (Loan-to-Value) = 100/MCR = ~90.9%
     pub const MAX_LTV: u64 = 90;
+
+    /// Default buffer above MCR a new vault must open with (BPS)
+    /// 0 keeps the opening requirement at MCR
+    pub const OPEN_BUFFER_BPS: u64 = 0;
+
+    /// Maximum configurable opening buffer (BPS, 50%)
+    pub const MAX_OPEN_BUFFER_BPS: u64 = 5_000;
 }
 
 /// Fee Configuration (in basis points, 100 = 1%)
@@ -74,6 +89,17 @@ pub mod fees {
     /// Refinancing fee (percentage of borrowing fee)
     pub const REFINANCING_FEE_PERCENT: u64 = 50; // 50% of issuance fee
 
+    // ===== Flash Mint Fee =====
+
+    /// Default flash mint fee (0.05%)
+    pub const DEFAULT_FLASH_FEE_BPS: u64 = 5;
+
+    /// Minimum configurable flash mint fee (0.01%)
+    pub const MIN_FLASH_FEE_BPS: u64 = 1;
+
+    /// Maximum configurable flash mint fee (1%)
+    pub const MAX_FLASH_FEE_BPS: u64 = 100;
+
     // ===== NEW: Insurance System =====
 
     /// Insurance premium rate (1% of coverage per year)
@@ -84,6 +110,104 @@ pub mod fees {
 
     /// Maximum insurance duration (52,560 blocks ~ 1 year)
     pub const MAX_INSURANCE_DURATION_BLOCKS: u64 = 52_560;
+
+    /// Share of coverage paid out on the first trigger (50%)
+    pub const INSURANCE_INITIAL_PAYOUT_BPS: u64 = 5_000;
+
+    // ===== Fee Distribution =====
+
+    /// Default share of protocol fees sent to the treasury (50%)
+    pub const DEFAULT_FEE_TREASURY_BPS: u64 = 5_000;
+
+    /// Default share of protocol fees injected into the Stability Pool (30%)
+    pub const DEFAULT_FEE_STABILITY_POOL_BPS: u64 = 3_000;
+
+    /// Default share of protocol fees paid to zkUSD stakers (20%)
+    pub const DEFAULT_FEE_STAKING_BPS: u64 = 2_000;
+
+    // ===== Loyalty Discount =====
+
+    /// Blocks of continuous debt after which a vault's borrowing fees are
+    /// discounted (~2 years)
+    pub const LOYALTY_THRESHOLD_BLOCKS: u64 = 100_000;
+
+    /// Borrowing fee discount for vaults past the threshold (10%)
+    pub const LOYALTY_DISCOUNT_BPS: u64 = 1_000;
+
+    // ===== Depositor Discount =====
+
+    /// Largest borrowing fee discount a stability deposit may earn (50%)
+    pub const MAX_DEPOSITOR_DISCOUNT_BPS: u64 = 5_000;
+
+    // ===== Stability Pool Liquidator Rebate =====
+
+    /// Largest rebate a liquidator earns on the debt its own stability
+    /// deposit absorbs (2%)
+    pub const MAX_SP_LIQUIDATOR_REBATE_BPS: u64 = 200;
+
+    // ===== Base Rate Decay =====
+
+    /// Blocks over which the base rate decays by half (~12 hours)
+    pub const BASE_RATE_HALF_LIFE_BLOCKS: u64 = 72;
+
+    /// Per-block base rate decay factor, 0.5^(1/72) at 1e18 precision
+    pub const BASE_RATE_DECAY_FACTOR: u128 = 990_419_147_466_826_256;
+
+    /// Incentive paid from collected fees to whoever pokes a decay of at
+    /// least 1 bps (1 zkUSD)
+    pub const BASE_RATE_POKE_INCENTIVE: u64 = 100_000_000;
+
+    // ===== Interest Poke =====
+
+    /// Interest a third party must fold into a vault's debt for its poke
+    /// to be accepted (10 zkUSD)
+    pub const MIN_INTEREST_POKE: u64 = 1_000_000_000;
+
+    /// Share of the folded interest paid to a third party poking a vault
+    /// (5%)
+    pub const INTEREST_POKE_BOUNTY_BPS: u64 = 500;
+
+    /// Largest bounty a single poke pays (5 zkUSD)
+    pub const MAX_INTEREST_POKE_BOUNTY: u64 = 500_000_000;
+
+    // ===== Redemption Protection =====
+
+    /// Largest share of collateral an owner may protect from redemptions (30%)
+    pub const MAX_PROTECTED_COLLATERAL_BPS: u64 = 3_000;
+
+    /// Interest rate surcharge per 100 bps of protected collateral (0.1% APR)
+    pub const PROTECTION_RATE_BPS_PER_100: u64 = 10;
+
+    // ===== Variable Rate =====
+
+    /// Variable rate up to the kink (1% APR, the default fixed rate)
+    pub const VARIABLE_RATE_BASE_BPS: u64 = DEFAULT_INTEREST_RATE_BPS;
+
+    /// Utilization above which the variable rate starts rising (80%)
+    pub const VARIABLE_RATE_KINK_BPS: u64 = 8_000;
+
+    /// Variable rate at full utilization (20% APR)
+    pub const VARIABLE_RATE_MAX_BPS: u64 = 2_000;
+
+    /// Utilization is read in steps of this size, so the rate only changes
+    /// when a step boundary is crossed (5%)
+    pub const UTILIZATION_STEP_BPS: u64 = 500;
+
+    /// Variable rate changes kept in the protocol's rate history
+    pub const RATE_HISTORY_LEN: usize = 32;
+
+    // ===== Interest Rate Floor =====
+
+    /// Stability pool coverage at or below which the rate floor is at its
+    /// highest (10% of total debt)
+    pub const RATE_FLOOR_STRESSED_COVERAGE_BPS: u64 = 1_000;
+
+    /// Stability pool coverage at or above which the rate floor is the
+    /// minimum interest rate (50% of total debt)
+    pub const RATE_FLOOR_HEALTHY_COVERAGE_BPS: u64 = 5_000;
+
+    /// Rate floor at stressed coverage (3% APR)
+    pub const RATE_FLOOR_STRESSED_BPS: u64 = 300;
 }
 
 /// Debt Limits
@@ -111,6 +235,34 @@ pub mod limits {
     /// Maximum debt per vault (prevents concentration risk)
     pub const MAX_DEBT_PER_VAULT: u64 = 10_000_000 * ONE; // 10M zkUSD
 
+    /// Protocol-wide debt the variable rate measures utilization against
+    pub const DEBT_CEILING: u64 = 100_000_000 * ONE; // 100M zkUSD
+
+    /// Blocks after vault creation during which it cannot be redeemed against
+    pub const REDEMPTION_LOCKOUT_BLOCKS: u64 = 144; // ~1 day
+
+    /// Shortest inactivity window a vault beneficiary may be given
+    pub const MIN_BENEFICIARY_INACTIVITY_BLOCKS: u64 = 26_280; // ~6 months
+
+    /// Smallest nonzero debt change an adjustment may make (zkUSD base units)
+    pub const MIN_ADJUSTMENT: u64 = ONE; // 1 zkUSD
+
+    /// Smallest nonzero collateral change an adjustment may make (sats)
+    pub const MIN_COLLATERAL_ADJUSTMENT: u64 = 10_000;
+
+    /// Smallest BTC output Bitcoin relays (sats); a spell paying out less
+    /// is rejected at broadcast
+    pub const DUST_LIMIT: u64 = 546;
+
+    /// Most adjustments a vault may make per adjustment window
+    pub const MAX_ADJUSTMENTS_PER_WINDOW: u64 = 20;
+
+    /// Length of the per-vault adjustment window (blocks)
+    pub const ADJUSTMENT_WINDOW_BLOCKS: u64 = 144; // ~1 day
+
+    /// Maximum entries per vault registry shard
+    pub const MAX_REGISTRY_ENTRIES: usize = 1024;
+
     /// Helper to check if running in mainnet mode
     #[cfg(feature = "mainnet")]
     pub const IS_MAINNET: bool = true;
@@ -128,6 +280,78 @@ pub mod oracle {
 
     /// Price precision (8 decimals like BTC)
     pub const PRICE_DECIMALS: u8 = 8;
+
+    /// Default minimum age-decayed price confidence (0-100) required to
+    /// liquidate (governance-tunable in the Vault Manager state)
+    pub const MIN_LIQUIDATION_CONFIDENCE: u8 = 30;
+
+    /// Blocks after a price takes effect during which the oracle operator
+    /// may not liquidate or redeem at it
+    pub const OPERATOR_PRICE_LOCKOUT_BLOCKS: u64 = 2;
+
+    /// Default minimum blocks between price updates
+    pub const DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS: u64 = 2;
+
+    /// Lowest configurable update interval (one update per block)
+    pub const MIN_UPDATE_INTERVAL_BLOCKS: u64 = 1;
+
+    /// Highest configurable update interval (must stay below the staleness
+    /// limit so the operator can keep the price fresh)
+    pub const MAX_UPDATE_INTERVAL_BLOCKS: u64 = MAX_PRICE_AGE_BLOCKS - 1;
+
+    /// Recent updates whose deviations count toward the cumulative limit
+    /// (including the update being validated)
+    pub const DEVIATION_WINDOW_UPDATES: usize = 4;
+
+    /// Default limit on the summed deviation over the window (15%)
+    pub const DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS: u64 = 1500;
+
+    /// Lowest configurable cumulative limit (what a single update may move)
+    pub const MIN_CUMULATIVE_DEVIATION_BPS: u64 = MAX_PRICE_DEVIATION_BPS;
+
+    /// Highest configurable cumulative limit (50%)
+    pub const MAX_CUMULATIVE_DEVIATION_BPS: u64 = 5000;
+
+    /// Default growth of the single-update deviation limit per block since
+    /// the last update (BPS); zero keeps it fixed at `MAX_PRICE_DEVIATION_BPS`
+    pub const DEFAULT_DEVIATION_SCALING_BPS_PER_BLOCK: u64 = 0;
+
+    /// Highest configurable growth of the deviation limit per block (1%)
+    pub const MAX_DEVIATION_SCALING_BPS_PER_BLOCK: u64 = 100;
+
+    /// Ceiling of the time-scaled single-update deviation limit (20%)
+    pub const MAX_SCALED_PRICE_DEVIATION_BPS: u64 = 2000;
+
+    /// Lowest price accepted by default ($1,000), suited to BTC
+    pub const DEFAULT_MIN_REASONABLE_PRICE: u64 = 100_000_000_000;
+
+    /// Highest price accepted by default ($10,000,000), suited to BTC
+    pub const DEFAULT_MAX_REASONABLE_PRICE: u64 = 1_000_000_000_000_000;
+
+    /// Move from the circuit breaker's reference price that trips it (20%)
+    pub const CIRCUIT_BREAKER_TRIP_BPS: u64 = 2000;
+
+    /// Blocks a reference price stays the circuit breaker's baseline
+    pub const CIRCUIT_BREAKER_WINDOW_BLOCKS: u64 = 6;
+
+    /// Blocks a tripped circuit breaker blocks liquidations and redemptions
+    /// before resetting on its own
+    pub const CIRCUIT_BREAKER_COOLDOWN_BLOCKS: u64 = 12;
+
+    /// Domain tag prefixed to every signed price attestation
+    pub const ATTESTATION_DOMAIN: &[u8; 24] = b"zkusd/price-attestation/";
+
+    /// Most feeds that may be registered as attestation sources
+    pub const MAX_ATTESTATION_SOURCES: usize = 16;
+
+    // ===== Price Feeds =====
+
+    /// Id of the BTC/USD feed: the oracle's own price, which no secondary
+    /// feed may take
+    pub const BTC_USD_FEED: [u8; 8] = *b"BTCUSD\0\0";
+
+    /// Most secondary feeds the oracle carries besides BTC/USD
+    pub const MAX_FEEDS: usize = 8;
 }
 
 /// Stability Pool Configuration
@@ -135,6 +359,12 @@ pub mod stability_pool {
     /// Scale factor for precision in reward calculations
     pub const SCALE_FACTOR: u128 = 1_000_000_000_000_000_000; // 1e18
 
+    /// Closed epochs whose final S values are kept for late claimers
+    pub const MAX_EPOCH_SNAPSHOTS: usize = 16;
+
+    /// Recent offsets kept for yield estimation
+    pub const MAX_OFFSET_SAMPLES: usize = 32;
+
     /// Minimum deposit to earn rewards
     /// - Mainnet: 100 zkUSD (meaningful participation)
     /// - Testnet: 1 zkUSD (allows testing with small amounts)
@@ -142,6 +372,17 @@ pub mod stability_pool {
     pub const MIN_DEPOSIT: u64 = 100 * super::token::ONE;
     #[cfg(not(feature = "mainnet"))]
     pub const MIN_DEPOSIT: u64 = 1 * super::token::ONE;
+
+    /// Compounded value below which a deposit may be swept by anyone
+    /// (1 zkUSD), as withdrawing it costs more in fees than it returns
+    pub const DUST_DEPOSIT_THRESHOLD: u64 = super::token::ONE;
+
+    /// Frontends a pool may register to tag deposits
+    pub const MAX_FRONTENDS: usize = 64;
+
+    /// Longest scriptPubKey a frontend's share may be paid to (34 bytes,
+    /// a P2WSH or P2TR output)
+    pub const MAX_PAYOUT_SCRIPT_LEN: usize = 34;
 }
 
 /// Liquidation Configuration
@@ -149,11 +390,22 @@ pub mod liquidation {
     /// Collateral bonus for liquidators (0.5% of liquidated collateral)
     pub const LIQUIDATOR_BONUS_BPS: u64 = 50;
 
+    /// Default liquidator bonus in Recovery Mode (0.25%), reduced so
+    /// cascades do not overpay liquidators with collateral backing the system
+    pub const RECOVERY_LIQUIDATOR_BONUS_BPS: u64 = 25;
+
     /// Gas compensation percentage from collateral (0.5%)
     pub const GAS_COMP_BPS: u64 = 50;
 
     /// Maximum batch liquidation size
     pub const MAX_BATCH_SIZE: usize = 10;
+
+    /// Debt above which a vault is liquidated in two phases (1M zkUSD)
+    pub const TWO_PHASE_DEBT_THRESHOLD: u64 = 1_000_000 * super::token::ONE;
+
+    /// Share of the stability pool above which a vault's debt is
+    /// liquidated in two phases (50%)
+    pub const TWO_PHASE_POOL_SHARE_BPS: u64 = 5_000;
 }
 
 /// Time-related constants
@@ -164,6 +416,9 @@ pub mod time {
     /// Blocks per hour
     pub const BLOCKS_PER_HOUR: u64 = 6;
 
+    /// Blocks per year (used for simple interest)
+    pub const BLOCKS_PER_YEAR: u64 = 52_560;
+
     /// Base rate decay half-life in blocks (~12 hours)
     pub const BASE_RATE_DECAY_HALFLIFE: u64 = 72;
 }
@@ -191,6 +446,13 @@ pub mod pcv {
 
     /// Target stability pool coverage ratio (60%)
     pub const TARGET_SP_COVERAGE_BPS: u64 = 6_000;
+
+    /// Stability pool coverage of total debt required to graduate from
+    /// bootstrap mode (20%)
+    pub const GRADUATION_MIN_SP_COVERAGE_BPS: u64 = 2_000;
+
+    /// Blocks bootstrap mode must run before graduating (~30 days)
+    pub const GRADUATION_MIN_BLOCKS: u64 = 30 * super::time::BLOCKS_PER_DAY;
 }
 
 /// Gas Pool Configuration
@@ -224,6 +486,10 @@ pub mod redistribution {
 
     /// Minimum collateral share to receive redistribution
     pub const MIN_REDISTRIBUTION_SHARE_BPS: u64 = 1; // 0.01%
+
+    /// Window over which a vault's collateral is time-averaged for
+    /// redistribution shares (~1 day)
+    pub const TWA_WINDOW_BLOCKS: u64 = 144;
 }
 
 /// Surplus Collateral Configuration
@@ -234,3 +500,19 @@ pub mod surplus {
     /// Minimum surplus amount worth claiming
     pub const MIN_SURPLUS_AMOUNT: u64 = 10_000; // 0.0001 BTC
 }
+
+/// Reorg Guidance
+///
+/// Confirmations a client should wait for on the spells an action builds
+/// on before submitting it. Advisory only: validation cannot see the chain.
+pub mod reorg {
+    /// Actions that depend only on the signer's own outputs
+    pub const DEFAULT_CONFIRMATIONS: u8 = 1;
+
+    /// Actions settled at an oracle price a reorg could replace
+    pub const PRICE_DEPENDENT_CONFIRMATIONS: u8 = 2;
+
+    /// Actions claiming what a liquidation produced (Stability Pool gains,
+    /// later liquidation tranches)
+    pub const LIQUIDATION_DEPENDENT_CONFIRMATIONS: u8 = 3;
+}
@@ -0,0 +1,173 @@
+//! State-Diff Diagnostics
+//!
+//! Validators reject a mismatch between the state a spell produces and the
+//! state they expect with `InvalidStateTransition`, or at best a
+//! `StateFieldMismatch` naming the first offending field, which keeps the
+//! on-chain path cheap but says little about *what* was wrong. The
+//! functions here compare an expected state with the actual one field by
+//! field, so off-chain tooling can name every offending field and value.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! for d in diff_vault(&expected, &actual) {
+//!     println!("{}: expected {}, got {}", d.field, d.expected, d.actual);
+//! }
+//! ```
+//!
+//! Available with the `std` feature only.
+
+use crate::types::{ProtocolState, StabilityPoolState, Vault};
+use crate::Vec;
+
+/// A single field that differs between an expected and an actual state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// Field name
+    pub field: &'static str,
+    /// Expected value (debug formatted)
+    pub expected: String,
+    /// Actual value (debug formatted)
+    pub actual: String,
+}
+
+/// Diff every listed field of two values of a struct type
+///
+/// The field list must name every field of the struct: a field added to the
+/// struct but not to the list is a compile error, not a silent blind spot.
+#[macro_export]
+macro_rules! diff_fields {
+    ($ty:ident, $expected:expr, $actual:expr, [$($field:ident),* $(,)?]) => {{
+        let (expected, actual): (&$ty, &$ty) = ($expected, $actual);
+        let $ty { $($field: _),* } = expected;
+        let mut diffs = $crate::Vec::new();
+        $(
+            if expected.$field != actual.$field {
+                diffs.push($crate::diagnostics::FieldDiff {
+                    field: stringify!($field),
+                    expected: format!("{:?}", expected.$field),
+                    actual: format!("{:?}", actual.$field),
+                });
+            }
+        )*
+        diffs
+    }};
+}
+
+pub use diff_fields;
+
+/// Fields of a vault that differ from the expected vault
+pub fn diff_vault(expected: &Vault, actual: &Vault) -> Vec<FieldDiff> {
+    diff_fields!(Vault, expected, actual, [
+        version,
+        id,
+        owner,
+        collateral,
+        debt,
+        created_at,
+        last_updated,
+        status,
+        interest_rate_bps,
+        accrued_interest,
+        redistributed_debt,
+        redistributed_collateral,
+        insurance_balance,
+        operator,
+        twa_collateral,
+        twa_updated_at,
+        stats,
+        protected_collateral_bps,
+        beneficiary,
+        adjustment_window,
+        rate_mode,
+        pending_liquidation,
+    ])
+}
+
+/// Fields of a protocol state that differ from the expected state
+pub fn diff_protocol(expected: &ProtocolState, actual: &ProtocolState) -> Vec<FieldDiff> {
+    diff_fields!(ProtocolState, expected, actual, [
+        total_collateral,
+        total_debt,
+        active_vault_count,
+        base_rate,
+        last_fee_update_block,
+        admin,
+        is_paused,
+        flash_fee_bps,
+        accumulated_fees,
+        fee_recipient,
+        interest_index,
+        last_interest_accrual_block,
+        rate_weighted_debt,
+        pending_interest,
+        variable_debt,
+        rate_history,
+        bootstrap,
+    ])
+}
+
+/// Fields of a Stability Pool state that differ from the expected state
+pub fn diff_pool_state(expected: &StabilityPoolState, actual: &StabilityPoolState) -> Vec<FieldDiff> {
+    diff_fields!(StabilityPoolState, expected, actual, [
+        version,
+        total_zkusd,
+        total_btc,
+        product_p,
+        sum_s,
+        current_epoch,
+        current_scale,
+        depositor_count,
+        epoch_snapshots,
+        recent_offsets,
+        protocol_dust,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_states_have_no_diffs() {
+        let vault = Vault::new([1u8; 32], [2u8; 32], 100_000_000, 10_000_00000000, 100);
+        assert!(diff_vault(&vault, &vault).is_empty());
+
+        let protocol = ProtocolState::new([3u8; 32]);
+        assert!(diff_protocol(&protocol, &protocol).is_empty());
+
+        let pool = StabilityPoolState::new();
+        assert!(diff_pool_state(&pool, &pool).is_empty());
+    }
+
+    #[test]
+    fn test_diff_names_each_changed_field() {
+        let expected = Vault::new([1u8; 32], [2u8; 32], 100_000_000, 10_000_00000000, 100);
+        let mut actual = expected.clone();
+        actual.collateral = 90_000_000;
+        actual.accrued_interest = 7;
+
+        assert_eq!(
+            diff_vault(&expected, &actual),
+            [
+                FieldDiff {
+                    field: "collateral",
+                    expected: "100000000".into(),
+                    actual: "90000000".into(),
+                },
+                FieldDiff { field: "accrued_interest", expected: "0".into(), actual: "7".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pool_diff() {
+        let expected = StabilityPoolState::new();
+        let mut actual = expected.clone();
+        actual.depositor_count = 1;
+
+        let diffs = diff_pool_state(&expected, &actual);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, "depositor_count");
+    }
+}
@@ -3,6 +3,9 @@
 //! Inspired by Soroban's error handling patterns, these typed errors
 //! provide clear feedback for debugging and better UX.
 
+use crate::governance::ProtocolParam;
+use crate::types::VaultStatus;
+
 /// Result type alias for zkUSD operations
 pub type ZkUsdResult<T> = Result<T, ZkUsdError>;
 
@@ -28,6 +31,30 @@ pub enum ZkUsdError {
     /// Cannot close vault with remaining debt
     VaultHasDebt { remaining_debt: u64 },
 
+    /// Redemption skipped a lower-rate vault that is not exempt
+    RedemptionOrderViolated { vault_id: [u8; 32], skipped: [u8; 32] },
+
+    /// New vault opens below MCR plus the opening buffer
+    InsufficientOpeningRatio { icr_bps: u64, required_bps: u64 },
+
+    /// Vault modified again before its operation cooldown elapsed
+    OperationCooldown { retry_at: u64 },
+
+    /// Beneficiary claimed the vault before the owner's inactivity window ran out
+    BeneficiaryClaimTooEarly { claimable_at: u64 },
+
+    /// Vault used up its adjustments for the current window
+    AdjustmentRateLimited { retry_at: u64 },
+
+    /// Fixed interest rate below the floor set by stability pool coverage
+    InterestRateBelowFloor { rate_bps: u64, floor_bps: u64 },
+
+    /// Borrowing would push the protocol's total debt past the global ceiling
+    GlobalDebtCeilingExceeded { attempted: u64, ceiling: u64 },
+
+    /// Liquidation skipped an equal-ICR vault ahead of it in tie-break order
+    LiquidationOrderViolated { vault_id: [u8; 32], skipped: [u8; 32] },
+
     // ============ Amount Errors ============
     /// Invalid amount provided
     InvalidAmount { amount: u64, reason: AmountErrorReason },
@@ -44,6 +71,12 @@ pub enum ZkUsdError {
     /// Zero amount not allowed
     ZeroAmount,
 
+    /// Output fell below the user's minimum
+    SlippageExceeded { expected_min: u64, actual: u64 },
+
+    /// Nonzero adjustment smaller than the protocol minimum
+    AdjustmentTooSmall { delta: u64, minimum: u64 },
+
     // ============ Authorization Errors ============
     /// Caller is not authorized for this operation
     Unauthorized { expected: [u8; 32], actual: [u8; 32] },
@@ -78,6 +111,39 @@ pub enum ZkUsdError {
     /// Invalid oracle source
     InvalidOracleSource,
 
+    /// Oracle attestation signature failed verification
+    InvalidAttestationSignature { source_id: [u8; 32] },
+
+    /// Oracle price outside the user's acceptable bound
+    PriceOutOfBounds { price: u64, bound: u64 },
+
+    /// Age-decayed oracle confidence below the required minimum
+    OracleLowConfidence { confidence: u8, required: u8 },
+
+    /// Price update arrived before the minimum update interval elapsed
+    OracleUpdateTooSoon { last_update_block: u64, current_block: u64, min_interval: u64 },
+
+    /// Price updates over the recent window moved the price too far in total
+    OracleCumulativeDeviation { cumulative_bps: u64, max_bps: u64 },
+
+    /// Oracle circuit breaker is tripped until `until_block`
+    CircuitBreakerActive { until_block: u64 },
+
+    /// Composite oracle health score below the required minimum
+    OracleUnhealthy { score: u8, required: u8 },
+
+    /// Prices of two correlated assets diverge beyond their configured ratio
+    CorrelatedPriceDivergence { ratio_bps: u64, min_ratio_bps: u64, max_ratio_bps: u64 },
+
+    /// Latest price not yet in effect, with no previous price to use
+    PriceNotYetEffective { effective_from_block: u64 },
+
+    /// Oracle operator liquidating or redeeming at a price it just published
+    OperatorPriceLockout { retry_at: u64 },
+
+    /// Oracle frozen by the guardian; only an admin reset lifts it
+    OracleFrozen { frozen_at: u64 },
+
     // ============ Recovery Mode Errors ============
     /// Operation not allowed in Recovery Mode
     RecoveryModeRestriction { operation: RecoveryModeOp },
@@ -95,6 +161,13 @@ pub enum ZkUsdError {
     /// No rewards to claim
     NoRewardsToClaim,
 
+    /// Final S values of a closed epoch are no longer retained
+    EpochSnapshotEvicted { epoch: u64 },
+
+    /// Deposit tagged with a frontend the pool has not registered, or at
+    /// a kickback rate other than the registered one
+    FrontendNotRegistered { frontend: [u8; 32] },
+
     // ============ Liquidation Errors ============
     /// Vault is not liquidatable
     NotLiquidatable { vault_id: [u8; 32], icr: u64 },
@@ -117,6 +190,10 @@ pub enum ZkUsdError {
     /// Insurance coverage insufficient
     InsufficientInsurance { available: u64, needed: u64 },
 
+    /// Vault debt calls for the other liquidation path: two-phase above
+    /// the threshold, single-shot below it
+    WrongLiquidationPath { debt: u64, two_phase_required: bool },
+
     // ============ Token Errors ============
     /// Token transfer failed
     TransferFailed { from: [u8; 32], to: [u8; 32], amount: u64 },
@@ -130,6 +207,12 @@ pub enum ZkUsdError {
     /// Token conservation violated (inputs != outputs)
     ConservationViolated { inputs: u64, outputs: u64 },
 
+    /// Mint would take an address past its lifetime mint cap
+    LifetimeMintCapExceeded { address: [u8; 32], minted: u64, requested: u64, cap: u64 },
+
+    /// Controller supply change differs from the spell's net token flow
+    SupplyFlowMismatch { old_supply: u64, new_supply: u64, inputs: u64, outputs: u64 },
+
     // ============ Math Errors ============
     /// Arithmetic overflow occurred
     Overflow,
@@ -150,6 +233,15 @@ pub enum ZkUsdError {
     /// Invalid spell format
     InvalidSpellFormat,
 
+    /// Companion charm missing or carrying an app id other than the recorded one
+    WrongCompanionApp { expected: [u8; 32], found: [u8; 32], role: CompanionRole },
+
+    /// More than one charm claims the same companion role
+    DuplicateCompanionApp { role: CompanionRole },
+
+    /// BTC output below the dust limit, which Bitcoin would not relay
+    OutputBelowDust { amount: u64, dust_limit: u64 },
+
     // ============ State Errors ============
     /// Protocol is paused
     ProtocolPaused,
@@ -157,6 +249,32 @@ pub enum ZkUsdError {
     /// Invalid state transition
     InvalidStateTransition,
 
+    /// Vault status change not allowed for the action
+    InvalidStatusTransition { from: VaultStatus, to: VaultStatus },
+
+    /// Spell submitted after its expiry block
+    SpellExpired { expires_at: u64, current: u64 },
+
+    /// Admin action changed a parameter it was not meant to change
+    UnexpectedParamChange { param: ProtocolParam },
+
+    /// Same action applied twice to the same input within one context
+    DuplicateAction { key: [u8; 32] },
+
+    /// Fee distribution shares do not sum to 100%
+    InvalidFeeDistribution { total_bps: u64 },
+
+    /// Output state differs from the expected state in the named field
+    StateFieldMismatch { field: &'static str },
+
+    /// Persisted state written at a version this build cannot read or
+    /// no longer writes
+    UnsupportedStateVersion { found: u8, current: u8 },
+
+    /// Spell built against a state other than the one it spends, as after
+    /// a reorg replaced it
+    StaleStateReference { block_height: u64 },
+
     /// State not found
     StateNotFound,
 
@@ -201,6 +319,9 @@ pub enum ZkUsdError {
     /// Arbitrage not profitable
     ArbitrageNotProfitable { expected_profit: i64 },
 
+    /// Flash mint purpose is not on the deployment's allowlist
+    FlashMintPurposeNotAllowed { purpose: u8 },
+
     // ============ Advanced Operation Errors ============
 
     /// Vault not eligible for rescue (ICR too high)
@@ -219,11 +340,31 @@ pub enum ZkUsdError {
     /// Invalid insurance parameters
     InvalidInsuranceParams,
 
+    /// Insurance sale would take outstanding coverage past the fund's capacity
+    InsuranceCapacityExceeded { coverage_value: u64, capacity: u64 },
+
     /// Invalid address (e.g., zero address)
     InvalidAddress {
         /// Description of why the address is invalid
         reason: &'static str,
     },
+
+    // ============ Bootstrap Errors ============
+
+    /// Signer could not prove membership of the bootstrap whitelist
+    NotWhitelisted { address: [u8; 32] },
+
+    /// Total debt would exceed the bootstrap debt cap
+    BootstrapDebtCapExceeded { total_debt: u64, cap: u64 },
+
+    /// Bootstrap graduation criteria not met yet
+    GraduationCriteriaNotMet {
+        /// Criterion that failed
+        reason: &'static str,
+    },
+
+    /// Protocol is not in bootstrap mode
+    BootstrapNotActive,
 }
 
 /// Reasons for amount-related errors
@@ -254,6 +395,19 @@ pub enum RecoveryModeOp {
     CloseLastVault,
 }
 
+/// Companion apps resolved from a spell by their recorded app id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompanionRole {
+    /// zkUSD token app
+    Token,
+    /// VaultManager app (mint/burn and offset caller)
+    VaultManager,
+    /// Stability Pool app
+    StabilityPool,
+    /// Price Oracle app
+    PriceOracle,
+}
+
 impl ZkUsdError {
     /// Returns a human-readable error code for logging/debugging
     pub fn code(&self) -> &'static str {
@@ -263,11 +417,21 @@ impl ZkUsdError {
             Self::VaultAlreadyExists { .. } => "E003_VAULT_EXISTS",
             Self::VaultNotActive { .. } => "E004_VAULT_INACTIVE",
             Self::VaultHasDebt { .. } => "E005_VAULT_HAS_DEBT",
+            Self::RedemptionOrderViolated { .. } => "E006_REDEMPTION_ORDER",
+            Self::InsufficientOpeningRatio { .. } => "E007_INSUFFICIENT_OPENING_RATIO",
+            Self::OperationCooldown { .. } => "E008_OPERATION_COOLDOWN",
+            Self::BeneficiaryClaimTooEarly { .. } => "E009_BENEFICIARY_CLAIM_TOO_EARLY",
+            Self::AdjustmentRateLimited { .. } => "E00A_ADJUSTMENT_RATE_LIMITED",
+            Self::InterestRateBelowFloor { .. } => "E00B_INTEREST_RATE_BELOW_FLOOR",
+            Self::GlobalDebtCeilingExceeded { .. } => "E00C_GLOBAL_DEBT_CEILING_EXCEEDED",
+            Self::LiquidationOrderViolated { .. } => "E00D_LIQUIDATION_ORDER",
             Self::InvalidAmount { .. } => "E010_INVALID_AMOUNT",
             Self::InsufficientBalance { .. } => "E011_INSUFFICIENT_BALANCE",
             Self::BelowMinimum { .. } => "E012_BELOW_MINIMUM",
             Self::ExceedsMaximum { .. } => "E013_EXCEEDS_MAXIMUM",
             Self::ZeroAmount => "E014_ZERO_AMOUNT",
+            Self::SlippageExceeded { .. } => "E015_SLIPPAGE_EXCEEDED",
+            Self::AdjustmentTooSmall { .. } => "E016_ADJUSTMENT_TOO_SMALL",
             Self::Unauthorized { .. } => "E020_UNAUTHORIZED",
             Self::MissingSignature => "E021_MISSING_SIGNATURE",
             Self::InvalidSignature => "E022_INVALID_SIGNATURE",
@@ -276,11 +440,24 @@ impl ZkUsdError {
             Self::OraclePriceDeviation { .. } => "E031_ORACLE_DEVIATION",
             Self::OracleNotInitialized => "E032_ORACLE_NOT_INIT",
             Self::InvalidOracleSource => "E033_INVALID_ORACLE",
+            Self::InvalidAttestationSignature { .. } => "E034_ATTESTATION_SIG",
+            Self::PriceOutOfBounds { .. } => "E035_PRICE_OUT_OF_BOUNDS",
+            Self::OracleLowConfidence { .. } => "E036_ORACLE_LOW_CONFIDENCE",
+            Self::OracleUpdateTooSoon { .. } => "E037_ORACLE_UPDATE_TOO_SOON",
+            Self::OracleCumulativeDeviation { .. } => "E038_ORACLE_CUMULATIVE_DEVIATION",
+            Self::CircuitBreakerActive { .. } => "E039_CIRCUIT_BREAKER_ACTIVE",
+            Self::OracleUnhealthy { .. } => "E03A_ORACLE_UNHEALTHY",
+            Self::CorrelatedPriceDivergence { .. } => "E03B_CORRELATED_PRICE_DIVERGENCE",
+            Self::PriceNotYetEffective { .. } => "E03C_PRICE_NOT_YET_EFFECTIVE",
+            Self::OperatorPriceLockout { .. } => "E03D_OPERATOR_PRICE_LOCKOUT",
+            Self::OracleFrozen { .. } => "E03E_ORACLE_FROZEN",
             Self::RecoveryModeRestriction { .. } => "E040_RECOVERY_MODE",
             Self::WouldWorsenTCR { .. } => "E041_WORSEN_TCR",
             Self::InsufficientPoolBalance { .. } => "E050_POOL_INSUFFICIENT",
             Self::DepositNotFound { .. } => "E051_DEPOSIT_NOT_FOUND",
             Self::NoRewardsToClaim => "E052_NO_REWARDS",
+            Self::EpochSnapshotEvicted { .. } => "E053_EPOCH_EVICTED",
+            Self::FrontendNotRegistered { .. } => "E054_FRONTEND_NOT_REGISTERED",
             Self::NotLiquidatable { .. } => "E060_NOT_LIQUIDATABLE",
             Self::NothingToLiquidate => "E061_NOTHING_TO_LIQ",
             Self::LiquidationDust { .. } => "E062_LIQ_DUST",
@@ -288,19 +465,33 @@ impl ZkUsdError {
             Self::SurplusNotFound { .. } => "E064_SURPLUS_NOT_FOUND",
             Self::InsuranceNotFound { .. } => "E065_INS_NOT_FOUND",
             Self::InsufficientInsurance { .. } => "E066_INS_INSUFFICIENT",
+            Self::WrongLiquidationPath { .. } => "E067_WRONG_LIQ_PATH",
             Self::TransferFailed { .. } => "E070_TRANSFER_FAILED",
             Self::MintUnauthorized { .. } => "E071_MINT_UNAUTH",
             Self::BurnUnauthorized { .. } => "E072_BURN_UNAUTH",
             Self::ConservationViolated { .. } => "E073_CONSERVATION",
+            Self::LifetimeMintCapExceeded { .. } => "E074_LIFETIME_MINT_CAP",
+            Self::SupplyFlowMismatch { .. } => "E075_SUPPLY_FLOW_MISMATCH",
             Self::Overflow => "E080_OVERFLOW",
             Self::Underflow => "E081_UNDERFLOW",
             Self::DivisionByZero => "E082_DIV_ZERO",
             Self::InvalidInput { .. } => "E090_INVALID_INPUT",
             Self::InvalidUtxo => "E091_INVALID_UTXO",
             Self::InvalidSpellFormat => "E092_INVALID_SPELL",
+            Self::WrongCompanionApp { .. } => "E093_WRONG_COMPANION_APP",
+            Self::DuplicateCompanionApp { .. } => "E094_DUPLICATE_COMPANION",
+            Self::OutputBelowDust { .. } => "E095_OUTPUT_BELOW_DUST",
             Self::ProtocolPaused => "E100_PAUSED",
             Self::InvalidStateTransition => "E101_INVALID_STATE",
             Self::StateNotFound => "E102_STATE_NOT_FOUND",
+            Self::InvalidStatusTransition { .. } => "E103_INVALID_STATUS_TRANSITION",
+            Self::SpellExpired { .. } => "E104_SPELL_EXPIRED",
+            Self::UnexpectedParamChange { .. } => "E105_UNEXPECTED_PARAM_CHANGE",
+            Self::DuplicateAction { .. } => "E106_DUPLICATE_ACTION",
+            Self::InvalidFeeDistribution { .. } => "E107_INVALID_FEE_DISTRIBUTION",
+            Self::StateFieldMismatch { .. } => "E108_STATE_FIELD_MISMATCH",
+            Self::UnsupportedStateVersion { .. } => "E109_UNSUPPORTED_STATE_VERSION",
+            Self::StaleStateReference { .. } => "E10A_STALE_STATE_REFERENCE",
             Self::ExcessiveLeverage => "E110_EXCESSIVE_LEVERAGE",
             Self::ConditionNotMet => "E111_CONDITION_NOT_MET",
             Self::InsufficientCollateralRatio => "E112_INSUFFICIENT_CR",
@@ -314,11 +505,17 @@ impl ZkUsdError {
             Self::FlashMintBelowMin { .. } => "E123_FLASH_BELOW_MIN",
             Self::FlashCallbackFailed => "E124_FLASH_CALLBACK_FAIL",
             Self::ArbitrageNotProfitable { .. } => "E125_ARB_NOT_PROFITABLE",
+            Self::FlashMintPurposeNotAllowed { .. } => "E126_FLASH_PURPOSE_NOT_ALLOWED",
             Self::VaultNotEligibleForRescue { .. } => "E130_NOT_RESCUE_ELIGIBLE",
             Self::NoInsurance { .. } => "E131_NO_INSURANCE",
             Self::InsuranceNotTriggerable { .. } => "E132_INS_NOT_TRIGGERABLE",
             Self::InvalidInsuranceParams => "E133_INVALID_INS_PARAMS",
             Self::InvalidAddress { .. } => "E134_INVALID_ADDRESS",
+            Self::InsuranceCapacityExceeded { .. } => "E135_INS_CAPACITY",
+            Self::NotWhitelisted { .. } => "E140_NOT_WHITELISTED",
+            Self::BootstrapDebtCapExceeded { .. } => "E141_BOOTSTRAP_DEBT_CAP",
+            Self::GraduationCriteriaNotMet { .. } => "E142_GRADUATION_NOT_MET",
+            Self::BootstrapNotActive => "E143_BOOTSTRAP_INACTIVE",
         }
     }
 
@@ -326,9 +523,22 @@ impl ZkUsdError {
     pub fn is_recoverable(&self) -> bool {
         match self {
             Self::Undercollateralized { .. } => true, // Add more collateral
+            Self::InsufficientOpeningRatio { .. } => true, // Open with more collateral
             Self::InsufficientBalance { .. } => true, // Get more funds
             Self::BelowMinimum { .. } => true,        // Increase amount
             Self::OracleStale { .. } => true,         // Wait for update
+            Self::OracleLowConfidence { .. } => true, // Wait for update
+            Self::OracleUnhealthy { .. } => true,     // Wait for update
+            Self::PriceNotYetEffective { .. } => true, // Wait a block
+            Self::OperatorPriceLockout { .. } => true, // Wait out the lockout
+            Self::SlippageExceeded { .. } => true,    // Resubmit at the new price
+            Self::OperationCooldown { .. } => true,   // Wait for the cooldown
+            Self::BeneficiaryClaimTooEarly { .. } => true, // Wait out the inactivity window
+            Self::AdjustmentRateLimited { .. } => true, // Wait for the next window
+            Self::AdjustmentTooSmall { .. } => true, // Adjust by more
+            Self::InterestRateBelowFloor { .. } => true, // Choose a higher rate
+            Self::OutputBelowDust { .. } => true,    // Increase amount
+            Self::StaleStateReference { .. } => true, // Rebuild against the current state
             _ => false,
         }
     }
@@ -350,6 +560,35 @@ mod tests {
             },
             ZkUsdError::ZeroAmount,
             ZkUsdError::Overflow,
+            ZkUsdError::InvalidStateTransition,
+            ZkUsdError::StateFieldMismatch { field: "vault.debt" },
+            ZkUsdError::UnsupportedStateVersion { found: 2, current: 1 },
+            ZkUsdError::StaleStateReference { block_height: 1 },
+            ZkUsdError::WrongLiquidationPath { debt: 1, two_phase_required: true },
+            ZkUsdError::InsuranceCapacityExceeded { coverage_value: 2, capacity: 1 },
+            ZkUsdError::FlashMintPurposeNotAllowed { purpose: 1 },
+            ZkUsdError::PriceNotYetEffective { effective_from_block: 1 },
+            ZkUsdError::OperatorPriceLockout { retry_at: 1 },
+            ZkUsdError::InterestRateBelowFloor { rate_bps: 50, floor_bps: 300 },
+            ZkUsdError::GlobalDebtCeilingExceeded { attempted: 2, ceiling: 1 },
+            ZkUsdError::LiquidationOrderViolated { vault_id: [0u8; 32], skipped: [1u8; 32] },
+            ZkUsdError::OracleFrozen { frozen_at: 1 },
+            ZkUsdError::FrontendNotRegistered { frontend: [0u8; 32] },
+            ZkUsdError::NotWhitelisted { address: [0u8; 32] },
+            ZkUsdError::BootstrapDebtCapExceeded { total_debt: 2, cap: 1 },
+            ZkUsdError::GraduationCriteriaNotMet { reason: "test" },
+            ZkUsdError::BootstrapNotActive,
+            ZkUsdError::InvalidStatusTransition {
+                from: VaultStatus::Closed,
+                to: VaultStatus::Active,
+            },
+            ZkUsdError::WrongCompanionApp {
+                expected: [1u8; 32],
+                found: [2u8; 32],
+                role: CompanionRole::PriceOracle,
+            },
+            ZkUsdError::DuplicateCompanionApp { role: CompanionRole::PriceOracle },
+            ZkUsdError::OutputBelowDust { amount: 100, dust_limit: 546 },
         ];
 
         let codes: Vec<_> = errors.iter().map(|e| e.code()).collect();
@@ -3,11 +3,26 @@
 //! Events are emitted during contract execution and can be indexed
 //! off-chain for building UIs, analytics, and notifications.
 //! Inspired by Soroban's event system.
+//!
+//! ## Topics
+//!
+//! Each event carries up to four 32-byte topics, so a wallet can find the
+//! events involving its address without decoding every event. Topic 0 is
+//! always the hash of the event kind ([`EventType::topic`]); the rest are
+//! the vault ids and addresses the event involves, listed per variant in
+//! [`ZkUsdEvent::topics`].
+
+use core::ops::Deref;
 
 use crate::Vec;
 use borsh::{BorshDeserialize, BorshSerialize};
-use serde::{Deserialize, Serialize};
-use crate::types::{Address, VaultId};
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use crate::commitment::CommittedApp;
+use crate::governance::ParamChange;
+use crate::math::{calculate_tcr, is_recovery_mode};
+use crate::types::{Address, FeedId, Memo, ProtocolState, StabilityPoolState, VaultId};
+use crate::units::{Sats, ZkUsd};
 
 /// Event types for indexing and filtering
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
@@ -22,21 +37,45 @@ pub enum EventType {
     DebtMinted = 0x05,
     DebtRepaid = 0x06,
     VaultLiquidated = 0x07,
+    VaultOperatorChanged = 0x08,
+    VaultProtectionChanged = 0x09,
+    VaultBeneficiaryChanged = 0x0A,
+    VaultClaimedByBeneficiary = 0x0B,
+    VaultAtRisk = 0x0C,
+    LiquidationStarted = 0x0D,
+    LiquidationTranche = 0x0E,
+    InterestAccrued = 0x0F,
 
     // Stability Pool Events (0x20 - 0x3F)
     StabilityDeposit = 0x20,
     StabilityWithdrawal = 0x21,
     BtcRewardClaimed = 0x22,
     LiquidationOffset = 0x23,
+    BtcClaimedToVault = 0x24,
+    DustDepositSwept = 0x25,
+    FrontendRegistered = 0x26,
+    FrontendKickbackPaid = 0x27,
 
     // Token Events (0x40 - 0x5F)
     TokenTransfer = 0x40,
     TokenMint = 0x41,
     TokenBurn = 0x42,
+    UtxosConsolidated = 0x43,
 
     // Oracle Events (0x60 - 0x7F)
     PriceUpdated = 0x60,
     OracleOperatorChanged = 0x61,
+    OracleUpdateLimitsChanged = 0x62,
+    OracleDeviationScalingChanged = 0x63,
+    CircuitBreakerTripped = 0x64,
+    CircuitBreakerReset = 0x65,
+    OracleAttestationSourcesChanged = 0x66,
+    OraclePriceBoundsChanged = 0x67,
+    OracleGuardianChanged = 0x68,
+    OracleFrozen = 0x69,
+    FeedPriceUpdated = 0x6A,
+    OracleFeedAdded = 0x6B,
+    OracleFeedRemoved = 0x6C,
 
     // Protocol Events (0x80 - 0x9F)
     ProtocolPaused = 0x80,
@@ -45,12 +84,142 @@ pub enum EventType {
     RecoveryModeEntered = 0x83,
     RecoveryModeExited = 0x84,
     Redemption = 0x85,
+    ParamsChanged = 0x86,
+    StateCommitted = 0x87,
+    BaseRatePoked = 0x88,
+    BootstrapGraduated = 0x89,
+    HealthTick = 0x8A,
 
     // Advanced Operation Events (0xA0 - 0xBF)
     FlashMint = 0xA0,
     VaultRescued = 0xA1,
     InsurancePurchased = 0xA2,
     InsuranceTriggered = 0xA3,
+    InsuranceExpired = 0xA4,
+}
+
+/// Domain tag hashed with the event type code to form topic 0
+const EVENT_TOPIC_DOMAIN: &[u8; 12] = b"zkusd/event/";
+
+impl EventType {
+    /// Topic 0 of every event of this kind: `sha256(domain || code)`
+    pub fn topic(self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(EVENT_TOPIC_DOMAIN);
+        hasher.update([self as u8]);
+        hasher.finalize().into()
+    }
+}
+
+/// Most topics an event carries
+pub const MAX_TOPICS: usize = 4;
+
+/// An event's topics (see [`ZkUsdEvent::topics`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Topics {
+    items: [[u8; 32]; MAX_TOPICS],
+    len: usize,
+}
+
+impl Topics {
+    fn new(kind: EventType) -> Self {
+        let mut items = [[0u8; 32]; MAX_TOPICS];
+        items[0] = kind.topic();
+        Self { items, len: 1 }
+    }
+
+    /// Append the given ids and addresses, skipping absent ones
+    fn with(mut self, topics: &[Option<[u8; 32]>]) -> Self {
+        for topic in topics.iter().flatten() {
+            debug_assert!(self.len < MAX_TOPICS, "too many topics");
+            self.items[self.len] = *topic;
+            self.len += 1;
+        }
+        self
+    }
+}
+
+impl Deref for Topics {
+    type Target = [[u8; 32]];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items[..self.len]
+    }
+}
+
+impl Serialize for Topics {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Layout version of [`HealthTick`], bumped whenever its fields change
+pub const HEALTH_TICK_VERSION: u8 = 1;
+
+/// Compact summary of system health, the final event of every successful
+/// vault manager and stability pool spell
+///
+/// Being the most frequent event, it holds only fixed-size fields, led by
+/// `version` so decoders can tell layouts apart. A tick carries what the
+/// emitting contract's state holds: the vault manager leaves the Stability
+/// Pool fields zero and the stability pool leaves the protocol and price
+/// fields zero, so a spell running both emits a tick from each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct HealthTick {
+    /// Layout version (`HEALTH_TICK_VERSION`)
+    pub version: u8,
+    /// Total collateral ratio (%)
+    pub tcr: u64,
+    /// Total collateral in the system (satoshis)
+    pub total_collateral: u64,
+    /// Total debt in the system (zkUSD)
+    pub total_debt: u64,
+    /// Number of active vaults
+    pub active_vault_count: u64,
+    /// zkUSD in the Stability Pool
+    pub sp_total_zkusd: u64,
+    /// BTC gains held by the Stability Pool (satoshis)
+    pub sp_total_btc: u64,
+    /// BTC price the spell ran at (8 decimals)
+    pub btc_price: u64,
+    /// Redemption base rate
+    pub base_rate: u64,
+    /// Whether the system is in Recovery Mode
+    pub recovery_mode: bool,
+    /// Block of the spell
+    pub block_height: u64,
+}
+
+impl HealthTick {
+    /// Tick for the states a spell produced, at the spell's `btc_price`
+    ///
+    /// Fields of an absent state are zero, as is the TCR without a price.
+    pub fn from_states(
+        protocol: Option<&ProtocolState>,
+        pool: Option<&StabilityPoolState>,
+        btc_price: u64,
+        block_height: u64,
+    ) -> Self {
+        let tcr = match protocol {
+            Some(protocol) if btc_price > 0 => {
+                calculate_tcr(Sats(protocol.total_collateral), ZkUsd(protocol.total_debt), btc_price).unwrap_or(0)
+            }
+            _ => 0,
+        };
+        Self {
+            version: HEALTH_TICK_VERSION,
+            tcr,
+            total_collateral: protocol.map_or(0, |p| p.total_collateral),
+            total_debt: protocol.map_or(0, |p| p.total_debt),
+            active_vault_count: protocol.map_or(0, |p| p.active_vault_count),
+            sp_total_zkusd: pool.map_or(0, |pool| pool.total_zkusd),
+            sp_total_btc: pool.map_or(0, |pool| pool.total_btc),
+            btc_price,
+            base_rate: protocol.map_or(0, |p| p.base_rate),
+            recovery_mode: protocol.is_some() && btc_price > 0 && is_recovery_mode(tcr),
+            block_height,
+        }
+    }
 }
 
 /// Main event enum containing all possible protocol events
@@ -100,6 +269,8 @@ pub enum ZkUsdEvent {
         vault_id: VaultId,
         amount: u64,
         fee: u64,
+        #[serde(default)]
+        fee_discount: u64,
         new_debt: u64,
         new_icr: u64,
         block_height: u64,
@@ -123,6 +294,16 @@ pub enum ZkUsdEvent {
         collateral_seized: u64,
         collateral_to_sp: u64,
         collateral_to_liquidator: u64,
+        sp_rebate: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a vault's operator key is set or cleared
+    VaultOperatorChanged {
+        vault_id: VaultId,
+        owner: Address,
+        old_operator: Option<Address>,
+        new_operator: Option<Address>,
         block_height: u64,
     },
 
@@ -167,6 +348,8 @@ pub enum ZkUsdEvent {
         from: Address,
         to: Address,
         amount: u64,
+        #[serde(default)]
+        memo: Option<Memo>,
         block_height: u64,
     },
 
@@ -186,6 +369,14 @@ pub enum ZkUsdEvent {
         block_height: u64,
     },
 
+    /// Emitted when an owner merges balances into fewer UTXOs
+    UtxosConsolidated {
+        owner: Address,
+        inputs: u16,
+        outputs: u16,
+        block_height: u64,
+    },
+
     // ============ Oracle Events ============
 
     /// Emitted when BTC price is updated
@@ -203,6 +394,34 @@ pub enum ZkUsdEvent {
         block_height: u64,
     },
 
+    /// Emitted when the price update rate limits change
+    OracleUpdateLimitsChanged {
+        min_update_interval_blocks: u64,
+        max_cumulative_deviation_bps: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the deviation limit's growth per block changes
+    OracleDeviationScalingChanged {
+        old_bps_per_block: u64,
+        new_bps_per_block: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a price update trips the circuit breaker
+    CircuitBreakerTripped {
+        reference_price: u64,
+        new_price: u64,
+        until_block: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin clears a tripped circuit breaker
+    CircuitBreakerReset {
+        by: Address,
+        block_height: u64,
+    },
+
     // ============ Protocol Events ============
 
     /// Emitted when protocol is paused
@@ -246,6 +465,37 @@ pub enum ZkUsdEvent {
         block_height: u64,
     },
 
+    /// Emitted when governance parameters are changed
+    ParamsChanged {
+        by: Address,
+        changes: Vec<ParamChange>,
+        block_height: u64,
+    },
+
+    /// Emitted with the commitment of each new app state
+    StateCommitted {
+        app: CommittedApp,
+        commitment: [u8; 32],
+        block_height: u64,
+    },
+
+    /// Emitted when the base rate decay is applied without a user operation
+    BaseRatePoked {
+        caller: Address,
+        old_rate: u64,
+        new_rate: u64,
+        incentive: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the protocol graduates from bootstrap mode
+    BootstrapGraduated {
+        caller: Address,
+        total_debt: u64,
+        pool_zkusd: u64,
+        block_height: u64,
+    },
+
     // ============ Advanced Operation Events ============
 
     /// Emitted on flash mint
@@ -253,6 +503,7 @@ pub enum ZkUsdEvent {
         minter: Address,
         amount: u64,
         fee: u64,
+        fee_bps: u64,
         block_height: u64,
     },
 
@@ -287,6 +538,175 @@ pub enum ZkUsdEvent {
         new_icr: u64,
         block_height: u64,
     },
+
+    /// Emitted when an expired insurance charm is retired
+    InsuranceExpired {
+        insurance_id: [u8; 32],
+        vault_id: VaultId,
+        owner: Address,
+        coverage_released: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when BTC rewards are claimed into the depositor's vault
+    BtcClaimedToVault {
+        depositor: Address,
+        vault_id: VaultId,
+        btc_amount: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a vault's redemption protection changes
+    VaultProtectionChanged {
+        vault_id: VaultId,
+        owner: Address,
+        old_bps: u64,
+        new_bps: u64,
+        new_interest_rate_bps: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a vault's beneficiary is designated or cleared
+    VaultBeneficiaryChanged {
+        vault_id: VaultId,
+        owner: Address,
+        beneficiary: Option<Address>,
+        inactivity_blocks: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a beneficiary takes over an inactive vault
+    VaultClaimedByBeneficiary {
+        vault_id: VaultId,
+        old_owner: Address,
+        new_owner: Address,
+        block_height: u64,
+    },
+
+    /// Emitted when an operation takes a vault's ICR below the warning
+    /// threshold; the operation itself still succeeds
+    VaultAtRisk {
+        vault_id: VaultId,
+        icr: u64,
+        warning_threshold: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a two-phase liquidation begins, freezing the vault
+    LiquidationStarted {
+        vault_id: VaultId,
+        owner: Address,
+        liquidator: Address,
+        debt: u64,
+        collateral: u64,
+        trigger_price: u64,
+        block_height: u64,
+    },
+
+    /// Emitted for each tranche of a two-phase liquidation offset against
+    /// the stability pool
+    LiquidationTranche {
+        vault_id: VaultId,
+        owner: Address,
+        liquidator: Address,
+        debt_offset: u64,
+        collateral_to_sp: u64,
+        collateral_to_liquidator: u64,
+        remaining_debt: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a vault's interest is folded into its debt outside a
+    /// user operation
+    InterestAccrued {
+        vault_id: VaultId,
+        owner: Address,
+        caller: Address,
+        interest: u64,
+        bounty: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a dust deposit is swept, its remainder left to the pool
+    DustDepositSwept {
+        depositor: Address,
+        swept_by: Address,
+        zkusd_dust: u64,
+        btc_amount: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin registers a frontend deposits may be tagged
+    /// with
+    FrontendRegistered {
+        frontend: Address,
+        kickback_bps: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a tagged deposit's BTC gain is split with its frontend
+    FrontendKickbackPaid {
+        depositor: Address,
+        frontend: Address,
+        depositor_btc: u64,
+        frontend_btc: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin replaces the registered attestation sources
+    OracleAttestationSourcesChanged {
+        old_count: u64,
+        new_count: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin changes the accepted price range
+    OraclePriceBoundsChanged {
+        old_min_price: u64,
+        old_max_price: u64,
+        new_min_price: u64,
+        new_max_price: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin sets the oracle guardian
+    OracleGuardianChanged {
+        old_guardian: Address,
+        new_guardian: Address,
+        block_height: u64,
+    },
+
+    /// Emitted when the guardian freezes the oracle
+    OracleFrozen {
+        guardian: Address,
+        reference_price: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when a secondary feed's operator publishes a price
+    FeedPriceUpdated {
+        feed_id: FeedId,
+        old_price: u64,
+        new_price: u64,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin adds a secondary feed
+    OracleFeedAdded {
+        feed_id: FeedId,
+        operator: Address,
+        block_height: u64,
+    },
+
+    /// Emitted when the admin removes a secondary feed
+    OracleFeedRemoved {
+        feed_id: FeedId,
+        block_height: u64,
+    },
+
+    /// Emitted last by every successful vault manager and stability pool
+    /// spell
+    HealthTick(HealthTick),
 }
 
 impl ZkUsdEvent {
@@ -300,25 +720,55 @@ impl ZkUsdEvent {
             Self::DebtMinted { .. } => EventType::DebtMinted,
             Self::DebtRepaid { .. } => EventType::DebtRepaid,
             Self::VaultLiquidated { .. } => EventType::VaultLiquidated,
+            Self::VaultOperatorChanged { .. } => EventType::VaultOperatorChanged,
             Self::StabilityDeposit { .. } => EventType::StabilityDeposit,
             Self::StabilityWithdrawal { .. } => EventType::StabilityWithdrawal,
             Self::BtcRewardClaimed { .. } => EventType::BtcRewardClaimed,
             Self::LiquidationOffset { .. } => EventType::LiquidationOffset,
+            Self::BtcClaimedToVault { .. } => EventType::BtcClaimedToVault,
+            Self::VaultProtectionChanged { .. } => EventType::VaultProtectionChanged,
+            Self::VaultBeneficiaryChanged { .. } => EventType::VaultBeneficiaryChanged,
+            Self::VaultClaimedByBeneficiary { .. } => EventType::VaultClaimedByBeneficiary,
+            Self::VaultAtRisk { .. } => EventType::VaultAtRisk,
+            Self::LiquidationStarted { .. } => EventType::LiquidationStarted,
+            Self::LiquidationTranche { .. } => EventType::LiquidationTranche,
+            Self::InterestAccrued { .. } => EventType::InterestAccrued,
+            Self::DustDepositSwept { .. } => EventType::DustDepositSwept,
+            Self::FrontendRegistered { .. } => EventType::FrontendRegistered,
+            Self::FrontendKickbackPaid { .. } => EventType::FrontendKickbackPaid,
+            Self::OracleAttestationSourcesChanged { .. } => EventType::OracleAttestationSourcesChanged,
+            Self::OraclePriceBoundsChanged { .. } => EventType::OraclePriceBoundsChanged,
+            Self::OracleGuardianChanged { .. } => EventType::OracleGuardianChanged,
+            Self::OracleFrozen { .. } => EventType::OracleFrozen,
+            Self::FeedPriceUpdated { .. } => EventType::FeedPriceUpdated,
+            Self::OracleFeedAdded { .. } => EventType::OracleFeedAdded,
+            Self::OracleFeedRemoved { .. } => EventType::OracleFeedRemoved,
             Self::TokenTransfer { .. } => EventType::TokenTransfer,
             Self::TokenMint { .. } => EventType::TokenMint,
             Self::TokenBurn { .. } => EventType::TokenBurn,
+            Self::UtxosConsolidated { .. } => EventType::UtxosConsolidated,
             Self::PriceUpdated { .. } => EventType::PriceUpdated,
             Self::OracleOperatorChanged { .. } => EventType::OracleOperatorChanged,
+            Self::OracleUpdateLimitsChanged { .. } => EventType::OracleUpdateLimitsChanged,
+            Self::OracleDeviationScalingChanged { .. } => EventType::OracleDeviationScalingChanged,
+            Self::CircuitBreakerTripped { .. } => EventType::CircuitBreakerTripped,
+            Self::CircuitBreakerReset { .. } => EventType::CircuitBreakerReset,
             Self::ProtocolPaused { .. } => EventType::ProtocolPaused,
             Self::ProtocolUnpaused { .. } => EventType::ProtocolUnpaused,
             Self::AdminChanged { .. } => EventType::AdminChanged,
             Self::RecoveryModeEntered { .. } => EventType::RecoveryModeEntered,
             Self::RecoveryModeExited { .. } => EventType::RecoveryModeExited,
             Self::Redemption { .. } => EventType::Redemption,
+            Self::ParamsChanged { .. } => EventType::ParamsChanged,
+            Self::StateCommitted { .. } => EventType::StateCommitted,
+            Self::BaseRatePoked { .. } => EventType::BaseRatePoked,
+            Self::BootstrapGraduated { .. } => EventType::BootstrapGraduated,
             Self::FlashMint { .. } => EventType::FlashMint,
             Self::VaultRescued { .. } => EventType::VaultRescued,
             Self::InsurancePurchased { .. } => EventType::InsurancePurchased,
             Self::InsuranceTriggered { .. } => EventType::InsuranceTriggered,
+            Self::InsuranceExpired { .. } => EventType::InsuranceExpired,
+            Self::HealthTick(_) => EventType::HealthTick,
         }
     }
 
@@ -332,25 +782,139 @@ impl ZkUsdEvent {
             Self::DebtMinted { block_height, .. } => *block_height,
             Self::DebtRepaid { block_height, .. } => *block_height,
             Self::VaultLiquidated { block_height, .. } => *block_height,
+            Self::VaultOperatorChanged { block_height, .. } => *block_height,
             Self::StabilityDeposit { block_height, .. } => *block_height,
             Self::StabilityWithdrawal { block_height, .. } => *block_height,
             Self::BtcRewardClaimed { block_height, .. } => *block_height,
             Self::LiquidationOffset { block_height, .. } => *block_height,
+            Self::BtcClaimedToVault { block_height, .. } => *block_height,
+            Self::VaultProtectionChanged { block_height, .. } => *block_height,
+            Self::VaultBeneficiaryChanged { block_height, .. } => *block_height,
+            Self::VaultClaimedByBeneficiary { block_height, .. } => *block_height,
+            Self::VaultAtRisk { block_height, .. } => *block_height,
+            Self::LiquidationStarted { block_height, .. } => *block_height,
+            Self::LiquidationTranche { block_height, .. } => *block_height,
+            Self::InterestAccrued { block_height, .. } => *block_height,
+            Self::DustDepositSwept { block_height, .. } => *block_height,
+            Self::FrontendRegistered { block_height, .. } => *block_height,
+            Self::FrontendKickbackPaid { block_height, .. } => *block_height,
+            Self::OracleAttestationSourcesChanged { block_height, .. } => *block_height,
+            Self::OraclePriceBoundsChanged { block_height, .. } => *block_height,
+            Self::OracleGuardianChanged { block_height, .. } => *block_height,
+            Self::OracleFrozen { block_height, .. } => *block_height,
+            Self::FeedPriceUpdated { block_height, .. } => *block_height,
+            Self::OracleFeedAdded { block_height, .. } => *block_height,
+            Self::OracleFeedRemoved { block_height, .. } => *block_height,
             Self::TokenTransfer { block_height, .. } => *block_height,
             Self::TokenMint { block_height, .. } => *block_height,
             Self::TokenBurn { block_height, .. } => *block_height,
+            Self::UtxosConsolidated { block_height, .. } => *block_height,
             Self::PriceUpdated { block_height, .. } => *block_height,
             Self::OracleOperatorChanged { block_height, .. } => *block_height,
+            Self::OracleUpdateLimitsChanged { block_height, .. } => *block_height,
+            Self::OracleDeviationScalingChanged { block_height, .. } => *block_height,
+            Self::CircuitBreakerTripped { block_height, .. } => *block_height,
+            Self::CircuitBreakerReset { block_height, .. } => *block_height,
             Self::ProtocolPaused { block_height, .. } => *block_height,
             Self::ProtocolUnpaused { block_height, .. } => *block_height,
             Self::AdminChanged { block_height, .. } => *block_height,
             Self::RecoveryModeEntered { block_height, .. } => *block_height,
             Self::RecoveryModeExited { block_height, .. } => *block_height,
             Self::Redemption { block_height, .. } => *block_height,
+            Self::ParamsChanged { block_height, .. } => *block_height,
+            Self::StateCommitted { block_height, .. } => *block_height,
+            Self::BaseRatePoked { block_height, .. } => *block_height,
+            Self::BootstrapGraduated { block_height, .. } => *block_height,
             Self::FlashMint { block_height, .. } => *block_height,
             Self::VaultRescued { block_height, .. } => *block_height,
             Self::InsurancePurchased { block_height, .. } => *block_height,
             Self::InsuranceTriggered { block_height, .. } => *block_height,
+            Self::InsuranceExpired { block_height, .. } => *block_height,
+            Self::HealthTick(tick) => tick.block_height,
+        }
+    }
+
+    /// Topics for indexing: the event kind, then the vault ids and
+    /// addresses involved, in the order listed here
+    ///
+    /// Optional parties (a cleared operator or beneficiary) are skipped.
+    /// Vault operator changes list only the new operator.
+    pub fn topics(&self) -> Topics {
+        let topics = Topics::new(self.event_type());
+        match self {
+            Self::VaultOpened { vault_id, owner, .. }
+            | Self::VaultClosed { vault_id, owner, .. }
+            | Self::VaultProtectionChanged { vault_id, owner, .. }
+            | Self::InsurancePurchased { vault_id, owner, .. } => topics.with(&[Some(*vault_id), Some(*owner)]),
+            Self::CollateralAdded { vault_id, .. }
+            | Self::CollateralWithdrawn { vault_id, .. }
+            | Self::DebtMinted { vault_id, .. }
+            | Self::DebtRepaid { vault_id, .. }
+            | Self::VaultAtRisk { vault_id, .. } => topics.with(&[Some(*vault_id)]),
+            Self::VaultLiquidated { vault_id, owner, liquidator, .. }
+            | Self::LiquidationStarted { vault_id, owner, liquidator, .. }
+            | Self::LiquidationTranche { vault_id, owner, liquidator, .. }
+            | Self::InterestAccrued { vault_id, owner, caller: liquidator, .. } => {
+                topics.with(&[Some(*vault_id), Some(*owner), Some(*liquidator)])
+            }
+            Self::VaultOperatorChanged { vault_id, owner, new_operator, .. } => {
+                topics.with(&[Some(*vault_id), Some(*owner), *new_operator])
+            }
+            Self::VaultBeneficiaryChanged { vault_id, owner, beneficiary, .. } => {
+                topics.with(&[Some(*vault_id), Some(*owner), *beneficiary])
+            }
+            Self::VaultClaimedByBeneficiary { vault_id, old_owner, new_owner, .. } => {
+                topics.with(&[Some(*vault_id), Some(*old_owner), Some(*new_owner)])
+            }
+            Self::VaultRescued { vault_id, owner, rescuer, .. } => {
+                topics.with(&[Some(*vault_id), Some(*owner), Some(*rescuer)])
+            }
+            Self::InsuranceTriggered { insurance_id, vault_id, owner, .. }
+            | Self::InsuranceExpired { insurance_id, vault_id, owner, .. } => {
+                topics.with(&[Some(*vault_id), Some(*owner), Some(*insurance_id)])
+            }
+
+            Self::StabilityDeposit { depositor, .. }
+            | Self::StabilityWithdrawal { depositor, .. }
+            | Self::BtcRewardClaimed { depositor, .. } => topics.with(&[Some(*depositor)]),
+            Self::BtcClaimedToVault { depositor, vault_id, .. } => topics.with(&[Some(*depositor), Some(*vault_id)]),
+            Self::DustDepositSwept { depositor, swept_by, .. } => topics.with(&[Some(*depositor), Some(*swept_by)]),
+            Self::FrontendRegistered { frontend, .. } => topics.with(&[Some(*frontend)]),
+            Self::FrontendKickbackPaid { depositor, frontend, .. } => topics.with(&[Some(*depositor), Some(*frontend)]),
+
+            Self::TokenTransfer { from, to, .. } => topics.with(&[Some(*from), Some(*to)]),
+            Self::TokenMint { to, .. } => topics.with(&[Some(*to)]),
+            Self::TokenBurn { from, .. } | Self::UtxosConsolidated { owner: from, .. } => topics.with(&[Some(*from)]),
+
+            Self::OracleOperatorChanged { old_operator: old, new_operator: new, .. }
+            | Self::OracleGuardianChanged { old_guardian: old, new_guardian: new, .. }
+            | Self::AdminChanged { old_admin: old, new_admin: new, .. } => topics.with(&[Some(*old), Some(*new)]),
+            Self::CircuitBreakerReset { by, .. }
+            | Self::OracleFrozen { guardian: by, .. }
+            | Self::OracleFeedAdded { operator: by, .. }
+            | Self::ProtocolPaused { by, .. }
+            | Self::ProtocolUnpaused { by, .. }
+            | Self::ParamsChanged { by, .. } => topics.with(&[Some(*by)]),
+            Self::Redemption { redeemer, .. } => topics.with(&[Some(*redeemer)]),
+            Self::BaseRatePoked { caller, .. } | Self::BootstrapGraduated { caller, .. } => {
+                topics.with(&[Some(*caller)])
+            }
+            Self::FlashMint { minter, .. } => topics.with(&[Some(*minter)]),
+
+            // Protocol-wide events involve no particular party
+            Self::LiquidationOffset { .. }
+            | Self::PriceUpdated { .. }
+            | Self::OracleUpdateLimitsChanged { .. }
+            | Self::OracleDeviationScalingChanged { .. }
+            | Self::CircuitBreakerTripped { .. }
+            | Self::OracleAttestationSourcesChanged { .. }
+            | Self::OraclePriceBoundsChanged { .. }
+            | Self::FeedPriceUpdated { .. }
+            | Self::OracleFeedRemoved { .. }
+            | Self::RecoveryModeEntered { .. }
+            | Self::RecoveryModeExited { .. }
+            | Self::StateCommitted { .. }
+            | Self::HealthTick(_) => topics,
         }
     }
 
@@ -365,6 +929,15 @@ impl ZkUsdEvent {
     }
 }
 
+/// An event with its topics, as indexers export it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedEvent<'a> {
+    /// Topic 0 (event kind) and the parties involved
+    pub topics: Topics,
+    /// The event itself
+    pub event: &'a ZkUsdEvent,
+}
+
 /// Event log for collecting multiple events during execution
 #[derive(Debug, Clone, Default)]
 pub struct EventLog {
@@ -400,6 +973,16 @@ impl EventLog {
             .collect()
     }
 
+    /// Events with `topic` among their topics, e.g. a wallet's address
+    pub fn filter_by_topic(&self, topic: [u8; 32]) -> impl Iterator<Item = &ZkUsdEvent> + '_ {
+        self.events.iter().filter(move |e| e.topics().contains(&topic))
+    }
+
+    /// Events paired with their topics, for export (e.g. as JSON)
+    pub fn indexed(&self) -> impl Iterator<Item = IndexedEvent<'_>> + '_ {
+        self.events.iter().map(|event| IndexedEvent { topics: event.topics(), event })
+    }
+
     /// Check if any events were emitted
     pub fn has_events(&self) -> bool {
         !self.events.is_empty()
@@ -441,6 +1024,7 @@ mod tests {
             from: [1u8; 32],
             to: [2u8; 32],
             amount: 1000_00000000,
+            memo: Some([7u8; 32]),
             block_height: 200,
         };
 
@@ -450,6 +1034,31 @@ mod tests {
         assert_eq!(event, restored);
     }
 
+    #[test]
+    fn test_token_transfer_schema_golden() {
+        // Indexers decode these bytes: changing the layout breaks them
+        let digest = |memo: Option<Memo>| -> String {
+            let event = ZkUsdEvent::TokenTransfer {
+                from: [1u8; 32],
+                to: [2u8; 32],
+                amount: 1000_00000000,
+                memo,
+                block_height: 200,
+            };
+            let hash: [u8; 32] = Sha256::digest(event.to_bytes()).into();
+            hash.iter().map(|b| format!("{:02x}", b)).collect()
+        };
+
+        assert_eq!(
+            digest(None),
+            "f9b92b7bba03c6ec68ef1d82c87f74aa1610e469342b1b0cf8b66eea41766055"
+        );
+        assert_eq!(
+            digest(Some([7u8; 32])),
+            "663ac6c1391abe4c1d6bf694be1a2c28e0e90859cb09ba2dd5a9dcdd3b0609aa"
+        );
+    }
+
     #[test]
     fn test_event_log() {
         let mut log = EventLog::new();
@@ -476,4 +1085,185 @@ mod tests {
         let vault_events = log.filter_by_type(EventType::VaultOpened);
         assert_eq!(vault_events.len(), 1);
     }
+
+    const OWNER: Address = [0xA1; 32];
+    const OTHER: Address = [0xB2; 32];
+    const VAULT: VaultId = [0xC3; 32];
+    const UNRELATED: Address = [0xD4; 32];
+
+    /// One event of every kind, each with the party a wallet would search
+    /// for (its owner, depositor, ... or the vault id if no address is
+    /// involved), `None` for protocol-wide events
+    fn every_event() -> Vec<(Option<[u8; 32]>, ZkUsdEvent)> {
+        use ZkUsdEvent::*;
+        let h = 100;
+        Vec::from([
+            (Some(OWNER), VaultOpened { vault_id: VAULT, owner: OWNER, collateral: 1, debt: 1, fee: 0, block_height: h }),
+            (Some(OWNER), VaultClosed { vault_id: VAULT, owner: OWNER, collateral_returned: 1, debt_repaid: 1, block_height: h }),
+            (Some(VAULT), CollateralAdded { vault_id: VAULT, amount: 1, new_collateral: 2, new_icr: 150, block_height: h }),
+            (Some(VAULT), CollateralWithdrawn { vault_id: VAULT, amount: 1, new_collateral: 1, new_icr: 150, block_height: h }),
+            (Some(VAULT), DebtMinted { vault_id: VAULT, amount: 1, fee: 0, fee_discount: 0, new_debt: 2, new_icr: 150, block_height: h }),
+            (Some(VAULT), DebtRepaid { vault_id: VAULT, amount: 1, new_debt: 1, new_icr: 150, block_height: h }),
+            (Some(OWNER), VaultLiquidated {
+                vault_id: VAULT, owner: OWNER, liquidator: OTHER, debt_absorbed: 1, collateral_seized: 1,
+                collateral_to_sp: 1, collateral_to_liquidator: 0, sp_rebate: 0, block_height: h,
+            }),
+            (Some(OWNER), VaultOperatorChanged { vault_id: VAULT, owner: OWNER, old_operator: None, new_operator: Some(OTHER), block_height: h }),
+            (Some(OWNER), VaultProtectionChanged {
+                vault_id: VAULT, owner: OWNER, old_bps: 0, new_bps: 1, new_interest_rate_bps: 1, block_height: h,
+            }),
+            (Some(OWNER), VaultBeneficiaryChanged { vault_id: VAULT, owner: OWNER, beneficiary: None, inactivity_blocks: 0, block_height: h }),
+            (Some(OWNER), VaultClaimedByBeneficiary { vault_id: VAULT, old_owner: OTHER, new_owner: OWNER, block_height: h }),
+            (Some(VAULT), VaultAtRisk { vault_id: VAULT, icr: 120, warning_threshold: 125, block_height: h }),
+            (Some(OWNER), LiquidationStarted {
+                vault_id: VAULT, owner: OWNER, liquidator: OTHER, debt: 1, collateral: 1, trigger_price: 1, block_height: h,
+            }),
+            (Some(OWNER), LiquidationTranche {
+                vault_id: VAULT, owner: OWNER, liquidator: OTHER, debt_offset: 1, collateral_to_sp: 1,
+                collateral_to_liquidator: 0, remaining_debt: 0, block_height: h,
+            }),
+            (Some(OWNER), InterestAccrued { vault_id: VAULT, owner: OWNER, caller: OTHER, interest: 20, bounty: 1, block_height: h }),
+            (Some(OWNER), StabilityDeposit { depositor: OWNER, amount: 1, new_deposit: 1, pool_total: 1, block_height: h }),
+            (Some(OWNER), StabilityWithdrawal { depositor: OWNER, zkusd_withdrawn: 1, compounded_amount: 0, block_height: h }),
+            (Some(OWNER), BtcRewardClaimed { depositor: OWNER, btc_amount: 1, block_height: h }),
+            (None, LiquidationOffset { debt_offset: 1, collateral_gained: 1, new_pool_total: 0, block_height: h }),
+            (Some(OWNER), BtcClaimedToVault { depositor: OWNER, vault_id: VAULT, btc_amount: 1, block_height: h }),
+            (Some(OWNER), DustDepositSwept { depositor: OWNER, swept_by: OTHER, zkusd_dust: 1, btc_amount: 0, block_height: h }),
+            (Some(OWNER), FrontendRegistered { frontend: OWNER, kickback_bps: 9_000, block_height: h }),
+            (Some(OWNER), FrontendKickbackPaid {
+                depositor: OWNER, frontend: OTHER, depositor_btc: 9, frontend_btc: 1, block_height: h,
+            }),
+            (Some(OWNER), TokenTransfer { from: OTHER, to: OWNER, amount: 1, memo: None, block_height: h }),
+            (Some(OWNER), TokenMint { to: OWNER, amount: 1, new_total_supply: 1, block_height: h }),
+            (Some(OWNER), TokenBurn { from: OWNER, amount: 1, new_total_supply: 0, block_height: h }),
+            (Some(OWNER), UtxosConsolidated { owner: OWNER, inputs: 2, outputs: 1, block_height: h }),
+            (None, PriceUpdated { old_price: 1, new_price: 2, source: 0, block_height: h }),
+            (Some(OWNER), OracleOperatorChanged { old_operator: OTHER, new_operator: OWNER, block_height: h }),
+            (None, OracleUpdateLimitsChanged { min_update_interval_blocks: 2, max_cumulative_deviation_bps: 1500, block_height: h }),
+            (None, OracleDeviationScalingChanged { old_bps_per_block: 0, new_bps_per_block: 1, block_height: h }),
+            (None, CircuitBreakerTripped { reference_price: 1, new_price: 2, until_block: h, block_height: h }),
+            (Some(OWNER), CircuitBreakerReset { by: OWNER, block_height: h }),
+            (None, OracleAttestationSourcesChanged { old_count: 0, new_count: 1, block_height: h }),
+            (None, OraclePriceBoundsChanged { old_min_price: 1, old_max_price: 3, new_min_price: 1, new_max_price: 2, block_height: h }),
+            (Some(OWNER), OracleGuardianChanged { old_guardian: OTHER, new_guardian: OWNER, block_height: h }),
+            (Some(OWNER), OracleFrozen { guardian: OWNER, reference_price: 1, block_height: h }),
+            (None, FeedPriceUpdated { feed_id: *b"USDCUSD\0", old_price: 1, new_price: 2, block_height: h }),
+            (Some(OWNER), OracleFeedAdded { feed_id: *b"USDCUSD\0", operator: OWNER, block_height: h }),
+            (None, OracleFeedRemoved { feed_id: *b"USDCUSD\0", block_height: h }),
+            (Some(OWNER), ProtocolPaused { by: OWNER, block_height: h }),
+            (Some(OWNER), ProtocolUnpaused { by: OWNER, block_height: h }),
+            (Some(OWNER), AdminChanged { old_admin: OWNER, new_admin: OTHER, block_height: h }),
+            (None, RecoveryModeEntered { tcr: 140, block_height: h }),
+            (None, RecoveryModeExited { tcr: 160, block_height: h }),
+            (Some(OWNER), Redemption {
+                redeemer: OWNER, zkusd_redeemed: 1, btc_received: 1, fee_paid: 0, vaults_affected: 1, block_height: h,
+            }),
+            (Some(OWNER), ParamsChanged { by: OWNER, changes: Vec::new(), block_height: h }),
+            (None, StateCommitted { app: CommittedApp::VaultManager, commitment: [0u8; 32], block_height: h }),
+            (Some(OWNER), BaseRatePoked { caller: OWNER, old_rate: 1, new_rate: 0, incentive: 0, block_height: h }),
+            (Some(OWNER), BootstrapGraduated { caller: OWNER, total_debt: 5, pool_zkusd: 1, block_height: h }),
+            (Some(OWNER), FlashMint { minter: OWNER, amount: 1, fee: 0, fee_bps: 5, block_height: h }),
+            (Some(OWNER), VaultRescued {
+                vault_id: VAULT, owner: OWNER, rescuer: OTHER, collateral_added: 1, debt_repaid: 0,
+                rescuer_reward: 0, new_icr: 150, block_height: h,
+            }),
+            (Some(OWNER), InsurancePurchased {
+                vault_id: VAULT, owner: OWNER, coverage_btc: 1, premium: 1, trigger_icr: 115, block_height: h,
+            }),
+            (Some(OWNER), InsuranceTriggered {
+                insurance_id: [0xE5; 32], vault_id: VAULT, owner: OWNER, collateral_added: 1, new_icr: 150, block_height: h,
+            }),
+            (Some(OWNER), InsuranceExpired {
+                insurance_id: [0xE5; 32], vault_id: VAULT, owner: OWNER, coverage_released: 1, block_height: h,
+            }),
+            (None, HealthTick(crate::events::HealthTick::from_states(None, None, 0, h))),
+        ])
+    }
+
+    #[test]
+    fn test_every_event_kind_has_topics() {
+        let events = every_event();
+        let mut kinds: Vec<u8> = events.iter().map(|(_, e)| e.event_type() as u8).collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        assert_eq!(kinds.len(), events.len(), "one sample per kind");
+        assert_eq!(kinds.len(), 56, "a sample for every event kind");
+
+        for (party, event) in &events {
+            let topics = event.topics();
+            assert_eq!(topics[0], event.event_type().topic());
+            assert!(topics.len() <= MAX_TOPICS);
+            if party.is_none() {
+                assert_eq!(topics.len(), 1, "{:?} involves no party", event.event_type());
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_by_topic_finds_involved_party() {
+        for (party, event) in every_event() {
+            let mut log = EventLog::new();
+            log.emit(event.clone());
+
+            if let Some(party) = party {
+                assert_eq!(log.filter_by_topic(party).count(), 1, "{:?} should match its party", event.event_type());
+            }
+            assert_eq!(log.filter_by_topic(UNRELATED).count(), 0, "{:?} matched an unrelated address", event.event_type());
+        }
+
+        // Kind topics select events of that kind only
+        let mut log = EventLog::new();
+        for (_, event) in every_event() {
+            log.emit(event);
+        }
+        let opened: Vec<_> = log.filter_by_topic(EventType::VaultOpened.topic()).collect();
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0].event_type(), EventType::VaultOpened);
+
+        // A cleared operator adds no topic
+        let cleared = ZkUsdEvent::VaultOperatorChanged {
+            vault_id: VAULT, owner: OWNER, old_operator: Some(OTHER), new_operator: None, block_height: 100,
+        };
+        assert_eq!(*cleared.topics(), [EventType::VaultOperatorChanged.topic(), VAULT, OWNER]);
+    }
+
+    #[test]
+    fn test_indexed_events_serialize_topics() {
+        let mut log = EventLog::new();
+        log.emit(ZkUsdEvent::TokenMint { to: OWNER, amount: 1, new_total_supply: 1, block_height: 100 });
+
+        let json = serde_json::to_value(log.indexed().collect::<Vec<_>>()).unwrap();
+        let topics = &json[0]["topics"];
+        assert_eq!(topics.as_array().unwrap().len(), 2);
+        assert_eq!(topics[1], serde_json::to_value(OWNER).unwrap());
+        assert!(json[0]["event"]["TokenMint"].is_object());
+    }
+
+    #[test]
+    fn test_health_tick_layout() {
+        let mut protocol = ProtocolState::new(OWNER);
+        protocol.total_collateral = 140_000_000;
+        protocol.total_debt = 100_000_00000000;
+        protocol.active_vault_count = 3;
+        let tick = HealthTick::from_states(Some(&protocol), None, 100_000_00000000, 100);
+        assert_eq!(tick.tcr, 140);
+        assert!(tick.recovery_mode);
+        assert_eq!(tick.version, HEALTH_TICK_VERSION);
+
+        // Tag, version, nine u64s and the mode flag: indexers decode these
+        // bytes, so growing the tick means a new version
+        let event = ZkUsdEvent::HealthTick(tick);
+        assert_eq!(event.to_bytes().len(), 1 + 1 + 9 * 8 + 1);
+        assert_eq!(ZkUsdEvent::from_bytes(&event.to_bytes()), Some(event.clone()));
+
+        // Without a price nothing is known about the TCR
+        let unpriced = HealthTick::from_states(Some(&protocol), None, 0, 100);
+        assert_eq!((unpriced.tcr, unpriced.recovery_mode), (0, false));
+
+        let mut log = EventLog::new();
+        log.emit(event);
+        let json = serde_json::to_value(log.indexed().collect::<Vec<_>>()).unwrap();
+        assert_eq!(json[0]["topics"].as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["event"]["HealthTick"]["tcr"], 140);
+    }
 }
@@ -0,0 +1,319 @@
+//! Governance Parameter Snapshots
+//!
+//! Captures every governance-tunable protocol parameter in one struct so a
+//! parameter change can be reviewed as an explicit before/after diff.
+//!
+//! ## Usage
+//!
+//! Snapshot the parameters before and after an admin action, `diff` them,
+//! and reject the action if the diff touches anything other than the
+//! parameters it was meant to change:
+//!
+//! ```ignore
+//! let changes = diff(&before, &after);
+//! require_only_changes(&changes, &[ProtocolParam::FlashFee])?;
+//! ```
+
+use crate::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::charms_ops::FlashMintPurpose;
+use crate::constants::{fees, limits, liquidation, oracle, ratios};
+use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::types::ProtocolState;
+
+/// Identifier of a governance-tunable parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub enum ProtocolParam {
+    /// Current borrowing base rate (BPS)
+    BaseRate,
+    /// Lower bound of the base rate (BPS)
+    MinBorrowingFee,
+    /// Upper bound of the base rate (BPS)
+    MaxBorrowingFee,
+    /// Minimum Collateral Ratio (%)
+    Mcr,
+    /// Critical Collateral Ratio (%)
+    Ccr,
+    /// Flash mint fee (BPS)
+    FlashFee,
+    /// Redemption fee floor (BPS)
+    RedemptionFeeFloor,
+    /// Minimum vault debt (zkUSD base units)
+    MinDebt,
+    /// Maximum debt per vault (zkUSD base units)
+    MaxDebtPerVault,
+    /// Redemption lockout after vault creation (blocks)
+    RedemptionLockout,
+    /// Share of insurance coverage paid on the first trigger (BPS)
+    InsuranceInitialPayout,
+    /// Share of protocol fees sent to the treasury (BPS)
+    FeeTreasuryShare,
+    /// Share of protocol fees injected into the Stability Pool (BPS)
+    FeeStabilityPoolShare,
+    /// Share of protocol fees paid to stakers (BPS)
+    FeeStakingShare,
+    /// Buffer above MCR required to open a vault (BPS)
+    OpenBuffer,
+    /// Loyalty discount on borrowing fees enabled (0 or 1)
+    LoyaltyDiscount,
+    /// Cooldown between consecutive operations on a vault (blocks)
+    OperationCooldown,
+    /// Most liquidations processed per block (0 = uncapped)
+    MaxLiquidationsPerBlock,
+    /// Smallest nonzero debt adjustment (zkUSD base units)
+    MinAdjustment,
+    /// Smallest nonzero collateral adjustment (sats)
+    MinCollateralAdjustment,
+    /// Borrowing fee discount for stability depositors (BPS)
+    DepositorDiscount,
+    /// Smallest stability deposit earning the depositor discount (zkUSD base units)
+    DepositorDiscountMinDeposit,
+    /// Liquidator bonus in Recovery Mode (BPS)
+    RecoveryLiquidatorBonus,
+    /// ICR below which a vault is warned it is at risk (percentage)
+    WarningIcr,
+    /// Most outstanding insurance coverage value per unit of reserves (BPS, 0 = uncapped)
+    InsuranceMaxCoverage,
+    /// Flash mint purposes allowed (mask of `FlashMintPurpose::bit`)
+    FlashMintPurposes,
+    /// Most total debt the protocol may carry (zkUSD base units)
+    GlobalDebtCeiling,
+    /// Rebate for liquidators whose stability deposit absorbs the offset (BPS)
+    SpLiquidatorRebate,
+    /// Minimum age-decayed price confidence to liquidate (0-100)
+    MinLiquidationConfidence,
+}
+
+/// Snapshot of all governance-tunable protocol parameters
+///
+/// Parameters not yet held in state take their compiled-in values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct ProtocolParams {
+    /// Current borrowing base rate (BPS)
+    pub base_rate_bps: u64,
+    /// Lower bound of the base rate (BPS)
+    pub min_borrowing_fee_bps: u64,
+    /// Upper bound of the base rate (BPS)
+    pub max_borrowing_fee_bps: u64,
+    /// Minimum Collateral Ratio (%)
+    pub mcr: u64,
+    /// Critical Collateral Ratio (%)
+    pub ccr: u64,
+    /// Flash mint fee (BPS)
+    pub flash_fee_bps: u64,
+    /// Redemption fee floor (BPS)
+    pub redemption_fee_floor_bps: u64,
+    /// Minimum vault debt (zkUSD base units)
+    pub min_debt: u64,
+    /// Maximum debt per vault (zkUSD base units)
+    pub max_debt_per_vault: u64,
+    /// Redemption lockout after vault creation (blocks)
+    pub redemption_lockout_blocks: u64,
+    /// Share of insurance coverage paid on the first trigger (BPS)
+    pub insurance_initial_payout_bps: u64,
+    /// Share of protocol fees sent to the treasury (BPS)
+    pub fee_treasury_bps: u64,
+    /// Share of protocol fees injected into the Stability Pool (BPS)
+    pub fee_stability_pool_bps: u64,
+    /// Share of protocol fees paid to stakers (BPS)
+    pub fee_staking_bps: u64,
+    /// Buffer above MCR required to open a vault (BPS)
+    pub open_buffer_bps: u64,
+    /// Whether long-standing vaults get the loyalty borrowing fee discount
+    pub loyalty_discount_enabled: bool,
+    /// Cooldown between consecutive operations on a vault (blocks)
+    pub operation_cooldown_blocks: u64,
+    /// Most liquidations processed per block (0 = uncapped)
+    pub max_liquidations_per_block: u64,
+    /// Smallest nonzero debt adjustment (zkUSD base units)
+    pub min_adjustment: u64,
+    /// Smallest nonzero collateral adjustment (sats)
+    pub min_collateral_adjustment: u64,
+    /// Borrowing fee discount for stability depositors (BPS)
+    pub depositor_discount_bps: u64,
+    /// Smallest stability deposit earning the depositor discount (zkUSD base units)
+    pub depositor_discount_min_deposit: u64,
+    /// Liquidator bonus in Recovery Mode (BPS)
+    pub recovery_liquidator_bonus_bps: u64,
+    /// ICR below which a vault is warned it is at risk (percentage)
+    pub warning_icr: u64,
+    /// Most outstanding insurance coverage value per unit of reserves (BPS, 0 = uncapped)
+    pub insurance_max_coverage_bps: u64,
+    /// Flash mint purposes allowed (mask of `FlashMintPurpose::bit`)
+    pub allowed_flash_mint_purposes: u64,
+    /// Most total debt the protocol may carry (zkUSD base units)
+    pub global_debt_ceiling: u64,
+    /// Rebate for liquidators whose stability deposit absorbs the offset (BPS)
+    pub sp_liquidator_rebate_bps: u64,
+    /// Minimum age-decayed price confidence to liquidate (0-100)
+    pub min_liquidation_confidence: u64,
+}
+
+impl Default for ProtocolParams {
+    fn default() -> Self {
+        Self {
+            base_rate_bps: fees::MIN_BORROWING_FEE_BPS,
+            min_borrowing_fee_bps: fees::MIN_BORROWING_FEE_BPS,
+            max_borrowing_fee_bps: fees::MAX_BORROWING_FEE_BPS,
+            mcr: ratios::MCR,
+            ccr: ratios::CCR,
+            flash_fee_bps: fees::DEFAULT_FLASH_FEE_BPS,
+            redemption_fee_floor_bps: fees::REDEMPTION_FEE_FLOOR_BPS,
+            min_debt: limits::MIN_DEBT,
+            max_debt_per_vault: limits::MAX_DEBT_PER_VAULT,
+            redemption_lockout_blocks: limits::REDEMPTION_LOCKOUT_BLOCKS,
+            insurance_initial_payout_bps: fees::INSURANCE_INITIAL_PAYOUT_BPS,
+            fee_treasury_bps: fees::DEFAULT_FEE_TREASURY_BPS,
+            fee_stability_pool_bps: fees::DEFAULT_FEE_STABILITY_POOL_BPS,
+            fee_staking_bps: fees::DEFAULT_FEE_STAKING_BPS,
+            open_buffer_bps: ratios::OPEN_BUFFER_BPS,
+            loyalty_discount_enabled: false,
+            operation_cooldown_blocks: 0,
+            max_liquidations_per_block: 0,
+            min_adjustment: limits::MIN_ADJUSTMENT,
+            min_collateral_adjustment: limits::MIN_COLLATERAL_ADJUSTMENT,
+            depositor_discount_bps: 0,
+            depositor_discount_min_deposit: 0,
+            recovery_liquidator_bonus_bps: liquidation::RECOVERY_LIQUIDATOR_BONUS_BPS,
+            warning_icr: ratios::WARNING_ICR,
+            insurance_max_coverage_bps: 0,
+            allowed_flash_mint_purposes: FlashMintPurpose::ALL as u64,
+            global_debt_ceiling: limits::DEBT_CEILING,
+            sp_liquidator_rebate_bps: 0,
+            min_liquidation_confidence: u64::from(oracle::MIN_LIQUIDATION_CONFIDENCE),
+        }
+    }
+}
+
+impl ProtocolParams {
+    /// Snapshot the parameters held in protocol state
+    pub fn from_protocol(state: &ProtocolState) -> Self {
+        Self {
+            base_rate_bps: state.base_rate,
+            flash_fee_bps: state.flash_fee_bps,
+            ..Self::default()
+        }
+    }
+
+    /// Every parameter paired with its value, in declaration order
+    fn entries(&self) -> [(ProtocolParam, u64); 29] {
+        [
+            (ProtocolParam::BaseRate, self.base_rate_bps),
+            (ProtocolParam::MinBorrowingFee, self.min_borrowing_fee_bps),
+            (ProtocolParam::MaxBorrowingFee, self.max_borrowing_fee_bps),
+            (ProtocolParam::Mcr, self.mcr),
+            (ProtocolParam::Ccr, self.ccr),
+            (ProtocolParam::FlashFee, self.flash_fee_bps),
+            (ProtocolParam::RedemptionFeeFloor, self.redemption_fee_floor_bps),
+            (ProtocolParam::MinDebt, self.min_debt),
+            (ProtocolParam::MaxDebtPerVault, self.max_debt_per_vault),
+            (ProtocolParam::RedemptionLockout, self.redemption_lockout_blocks),
+            (ProtocolParam::InsuranceInitialPayout, self.insurance_initial_payout_bps),
+            (ProtocolParam::FeeTreasuryShare, self.fee_treasury_bps),
+            (ProtocolParam::FeeStabilityPoolShare, self.fee_stability_pool_bps),
+            (ProtocolParam::FeeStakingShare, self.fee_staking_bps),
+            (ProtocolParam::OpenBuffer, self.open_buffer_bps),
+            (ProtocolParam::LoyaltyDiscount, u64::from(self.loyalty_discount_enabled)),
+            (ProtocolParam::OperationCooldown, self.operation_cooldown_blocks),
+            (ProtocolParam::MaxLiquidationsPerBlock, self.max_liquidations_per_block),
+            (ProtocolParam::MinAdjustment, self.min_adjustment),
+            (ProtocolParam::MinCollateralAdjustment, self.min_collateral_adjustment),
+            (ProtocolParam::DepositorDiscount, self.depositor_discount_bps),
+            (ProtocolParam::DepositorDiscountMinDeposit, self.depositor_discount_min_deposit),
+            (ProtocolParam::RecoveryLiquidatorBonus, self.recovery_liquidator_bonus_bps),
+            (ProtocolParam::WarningIcr, self.warning_icr),
+            (ProtocolParam::InsuranceMaxCoverage, self.insurance_max_coverage_bps),
+            (ProtocolParam::FlashMintPurposes, self.allowed_flash_mint_purposes),
+            (ProtocolParam::GlobalDebtCeiling, self.global_debt_ceiling),
+            (ProtocolParam::SpLiquidatorRebate, self.sp_liquidator_rebate_bps),
+            (ProtocolParam::MinLiquidationConfidence, self.min_liquidation_confidence),
+        ]
+    }
+}
+
+/// A single parameter changed between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct ParamChange {
+    /// Parameter that changed
+    pub param: ProtocolParam,
+    /// Value before the change
+    pub old: u64,
+    /// Value after the change
+    pub new: u64,
+}
+
+/// List every parameter that differs between two snapshots
+pub fn diff(before: &ProtocolParams, after: &ProtocolParams) -> Vec<ParamChange> {
+    before
+        .entries()
+        .into_iter()
+        .zip(after.entries())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((param, old), (_, new))| ParamChange { param, old, new })
+        .collect()
+}
+
+/// Require a diff to touch only the allowed parameters
+pub fn require_only_changes(changes: &[ParamChange], allowed: &[ProtocolParam]) -> ZkUsdResult<()> {
+    match changes.iter().find(|c| !allowed.contains(&c.param)) {
+        Some(change) => Err(ZkUsdError::UnexpectedParamChange { param: change.param }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_snapshots_have_empty_diff() {
+        let params = ProtocolParams::default();
+        assert!(diff(&params, &params).is_empty());
+    }
+
+    #[test]
+    fn test_base_rate_change_single_entry() {
+        let mut state = ProtocolState::new([1u8; 32]);
+        let before = ProtocolParams::from_protocol(&state);
+        state.base_rate = 120;
+        let after = ProtocolParams::from_protocol(&state);
+
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes,
+            [ParamChange { param: ProtocolParam::BaseRate, old: fees::MIN_BORROWING_FEE_BPS, new: 120 }]
+        );
+        assert!(require_only_changes(&changes, &[ProtocolParam::BaseRate]).is_ok());
+    }
+
+    #[test]
+    fn test_unexpected_changes_fully_enumerated() {
+        let before = ProtocolParams::default();
+        let after = ProtocolParams {
+            base_rate_bps: 120,
+            mcr: 105,
+            redemption_lockout_blocks: 0,
+            ..before.clone()
+        };
+
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes,
+            [
+                ParamChange { param: ProtocolParam::BaseRate, old: before.base_rate_bps, new: 120 },
+                ParamChange { param: ProtocolParam::Mcr, old: ratios::MCR, new: 105 },
+                ParamChange {
+                    param: ProtocolParam::RedemptionLockout,
+                    old: limits::REDEMPTION_LOCKOUT_BLOCKS,
+                    new: 0,
+                },
+            ]
+        );
+        assert_eq!(
+            require_only_changes(&changes, &[ProtocolParam::BaseRate]),
+            Err(ZkUsdError::UnexpectedParamChange { param: ProtocolParam::Mcr })
+        );
+    }
+}
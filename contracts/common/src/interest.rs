@@ -0,0 +1,867 @@
+//! Global Interest Accrual
+//!
+//! Tracks interest owed across all vaults without touching each vault.
+//!
+//! ## Design
+//!
+//! Vault interest is simple interest on principal:
+//! `debt * rate_bps * blocks / (BLOCKS_PER_YEAR * 10_000)`. Because it is
+//! linear in both debt and time, the interest owed by all vaults over an
+//! interval with no debt changes is
+//! `rate_weighted_debt * blocks / (BLOCKS_PER_YEAR * 10_000)`, where
+//! `rate_weighted_debt = Σ debt_i * rate_i` over active vaults.
+//!
+//! `ProtocolState` keeps:
+//!
+//! - `interest_index`: cumulative interest per unit of debt-bps, scaled by
+//!   `INDEX_PRECISION`. It advances by `index_delta(blocks)` on each accrual.
+//! - `last_interest_accrual_block`: block of the last accrual.
+//! - `rate_weighted_debt`: maintained incrementally whenever a vault's
+//!   principal or rate changes (open, close, mint, repay, liquidate).
+//! - `pending_interest`: interest accrued globally but not yet reconciled
+//!   onto individual vaults, scaled by `INDEX_PRECISION`.
+//!
+//! Accrual is lazy. Any operation touching protocol state may call
+//! `accrue_interest`, and must do so before changing `rate_weighted_debt`
+//! so the elapsed interval is charged at the old weighting. When a vault is
+//! touched, `reconcile_vault` moves its share from `pending_interest` into
+//! `vault.accrued_interest` and `total_debt`.
+//!
+//! `total_debt_with_interest` is therefore `total_debt` plus pending and
+//! not-yet-accrued interest. It matches the sum of every vault's
+//! `entire_debt()` plus unreconciled `calculate_interest()` to within one
+//! base unit per accrual and reconciliation (integer rounding).
+//!
+//! ## Variable Rate
+//!
+//! A vault opened with `RateMode::Variable` pays the protocol's variable
+//! rate instead of its own `interest_rate_bps`. The rate follows a kinked
+//! curve of utilization, `total_debt` over the governed debt ceiling read
+//! in 5% steps: flat at the base rate up to 80%, then rising steeply to the
+//! maximum at 100%.
+//!
+//! `ProtocolState` additionally keeps:
+//!
+//! - `variable_debt`: principal of active variable vaults. Its weight in
+//!   `rate_weighted_debt` is always at the current variable rate.
+//! - `rate_history`: the last `RATE_HISTORY_LEN` rate changes and the
+//!   block each took effect.
+//!
+//! `refresh_variable_rate` re-prices the rate after `total_debt` changes.
+//! When a step boundary moves the rate, it records the change and moves the
+//! weight of `variable_debt` to the new rate. Like any weight change it must
+//! follow `accrue_interest`. A variable vault's interest over an interval is
+//! integrated piecewise over the history (`RateHistory::interest`).
+//!
+//! ## Rate Floor
+//!
+//! A fixed rate may not be chosen below a floor that moves with the
+//! stability pool's coverage of the system's debt, `pool zkUSD / total
+//! debt`. When the pool could absorb little of a liquidation wave, cheap
+//! debt is what the protocol least wants more of, so the floor rises and
+//! borrowing costs push toward deleveraging:
+//!
+//! | coverage | floor |
+//! |----------|-------|
+//! | <= 10%   | 3.00% |
+//! | 30%      | 1.75% |
+//! | >= 50%   | 0.50% |
+//!
+//! In between the floor falls linearly, from 3% APR down to the minimum
+//! rate. Coverage is capped at 100%, and reads as full with no debt. The
+//! floor applies when a fixed rate is set; a vault already open keeps its
+//! rate when coverage later drops. Variable vaults follow their own curve.
+//!
+//! ## Average Rate
+//!
+//! `average_rate_bps` reads the debt-weighted average rate of active vaults
+//! as `rate_weighted_debt / total_debt`, so redemptions can tell whether a
+//! vault pays below or above the average without scanning vaults. It is
+//! zero once the last vault closes.
+//!
+//! ## Settlement Order
+//!
+//! Before any validator reads a spent vault, `normalize_vault` settles what
+//! the vault owes up to the spell's block, always in the same order:
+//!
+//! 1. Redistribution: debt and collateral redistributed to the vault from
+//!    liquidations are folded into its own `debt` and `collateral`.
+//! 2. Interest: the vault's lifetime stats are brought up to the block,
+//!    charging interest on the debt it now carries.
+//!
+//! Redistribution comes first because redistributed debt is debt the vault
+//! owes from the moment it arrives, so interest charged only on the old
+//! principal would undercharge it. Charging on the larger balance is the
+//! conservative choice, and it makes the result independent of how many
+//! redistributions arrived in between. Both steps leave nothing to settle
+//! at the same block, so normalizing twice changes nothing, and every
+//! validator sees the same vault for the same inputs whatever the action.
+//!
+//! Normalization never moves `last_updated`, which also clocks owner
+//! activity for cooldowns and beneficiaries.
+//!
+//! ## Migration
+//!
+//! State written before these fields existed deserializes them as zero.
+//! A zero `interest_index` is read as `INDEX_PRECISION`, and
+//! `migrate_interest_state` seeds `rate_weighted_debt` from `total_debt` at
+//! the default rate. Weight removals saturate so a legacy state can never
+//! underflow.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{
+        fees::{
+            BPS_DENOMINATOR, DEFAULT_INTEREST_RATE_BPS, MIN_INTEREST_RATE_BPS, RATE_FLOOR_HEALTHY_COVERAGE_BPS,
+            RATE_FLOOR_STRESSED_BPS, RATE_FLOOR_STRESSED_COVERAGE_BPS, RATE_HISTORY_LEN, UTILIZATION_STEP_BPS,
+            VARIABLE_RATE_BASE_BPS, VARIABLE_RATE_KINK_BPS, VARIABLE_RATE_MAX_BPS,
+        },
+        time::BLOCKS_PER_YEAR,
+    },
+    errors::{ZkUsdError, ZkUsdResult},
+    math::safe_add,
+    types::{ProtocolState, RateMode, Vault},
+    Vec,
+};
+
+/// Fixed-point precision of the interest index (1e18)
+pub const INDEX_PRECISION: u128 = 1_000_000_000_000_000_000;
+
+/// Index growth over `blocks` for one unit of debt at 1 bps
+pub fn index_delta(blocks: u64) -> u128 {
+    blocks as u128 * INDEX_PRECISION / (BLOCKS_PER_YEAR as u128 * BPS_DENOMINATOR as u128)
+}
+
+/// Weight of a vault in `rate_weighted_debt`
+pub fn rate_weight(debt: u64, rate_bps: u64) -> u128 {
+    debt as u128 * rate_bps as u128
+}
+
+/// Shape of the variable rate curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableRateCurve {
+    /// Rate up to the kink (BPS)
+    pub base_rate_bps: u64,
+    /// Utilization above which the rate rises (BPS)
+    pub kink_bps: u64,
+    /// Rate at full utilization (BPS)
+    pub max_rate_bps: u64,
+    /// Granularity utilization is read at (BPS)
+    pub step_bps: u64,
+}
+
+impl Default for VariableRateCurve {
+    fn default() -> Self {
+        Self {
+            base_rate_bps: VARIABLE_RATE_BASE_BPS,
+            kink_bps: VARIABLE_RATE_KINK_BPS,
+            max_rate_bps: VARIABLE_RATE_MAX_BPS,
+            step_bps: UTILIZATION_STEP_BPS,
+        }
+    }
+}
+
+/// Variable rate (BPS) with `total_debt` outstanding against `debt_ceiling`
+///
+/// Utilization is rounded down to a whole step and capped at 100%; a zero
+/// ceiling reads as no utilization. Up to the kink the rate is the base
+/// rate, beyond it the rate rises linearly to the maximum.
+pub fn variable_rate_bps(total_debt: u64, debt_ceiling: u64, curve: &VariableRateCurve) -> u64 {
+    if debt_ceiling == 0 {
+        return curve.base_rate_bps;
+    }
+    // Capped at BPS_DENOMINATOR: fits u64
+    let utilization = (total_debt as u128 * BPS_DENOMINATOR as u128 / debt_ceiling as u128)
+        .min(BPS_DENOMINATOR as u128) as u64;
+    let utilization = utilization - utilization.checked_rem(curve.step_bps).unwrap_or(0);
+    if utilization <= curve.kink_bps {
+        return curve.base_rate_bps;
+    }
+
+    let above_kink = utilization - curve.kink_bps;
+    let steep_range = BPS_DENOMINATOR - curve.kink_bps;
+    let rise = curve.max_rate_bps.saturating_sub(curve.base_rate_bps) as u128 * above_kink as u128
+        / steep_range as u128;
+    // rise <= max_rate_bps - base_rate_bps
+    curve.base_rate_bps + rise as u64
+}
+
+/// Shape of the interest rate floor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateFloorCurve {
+    /// Coverage at or below which the floor is at its highest (BPS)
+    pub stressed_coverage_bps: u64,
+    /// Coverage at or above which the floor is at its lowest (BPS)
+    pub healthy_coverage_bps: u64,
+    /// Floor at stressed coverage (BPS)
+    pub stressed_floor_bps: u64,
+    /// Floor at healthy coverage (BPS)
+    pub healthy_floor_bps: u64,
+}
+
+impl Default for RateFloorCurve {
+    fn default() -> Self {
+        Self {
+            stressed_coverage_bps: RATE_FLOOR_STRESSED_COVERAGE_BPS,
+            healthy_coverage_bps: RATE_FLOOR_HEALTHY_COVERAGE_BPS,
+            stressed_floor_bps: RATE_FLOOR_STRESSED_BPS,
+            healthy_floor_bps: MIN_INTEREST_RATE_BPS,
+        }
+    }
+}
+
+/// Stability pool coverage (BPS) of `total_debt` by `pool_zkusd`
+///
+/// Capped at 100%; with no debt outstanding there is nothing to cover, which
+/// reads as full coverage.
+pub fn sp_coverage_bps(pool_zkusd: u64, total_debt: u64) -> u64 {
+    if total_debt == 0 {
+        return BPS_DENOMINATOR;
+    }
+    // Capped at BPS_DENOMINATOR: fits u64
+    (pool_zkusd as u128 * BPS_DENOMINATOR as u128 / total_debt as u128).min(BPS_DENOMINATOR as u128) as u64
+}
+
+/// Lowest fixed rate (BPS) allowed at `coverage_bps` on `curve`
+///
+/// The stressed floor up to stressed coverage, the healthy floor from
+/// healthy coverage, linear in between (rounded up, so the floor never
+/// undershoots the curve).
+pub fn rate_floor_bps(coverage_bps: u64, curve: &RateFloorCurve) -> u64 {
+    if coverage_bps <= curve.stressed_coverage_bps {
+        return curve.stressed_floor_bps;
+    }
+    if coverage_bps >= curve.healthy_coverage_bps {
+        return curve.healthy_floor_bps;
+    }
+
+    let remaining = (curve.healthy_coverage_bps - coverage_bps) as u128;
+    let range = (curve.healthy_coverage_bps - curve.stressed_coverage_bps) as u128;
+    let span = curve.stressed_floor_bps.saturating_sub(curve.healthy_floor_bps) as u128;
+    // <= span, since remaining < range
+    curve.healthy_floor_bps + (span * remaining).div_ceil(range) as u64
+}
+
+/// Lowest fixed rate (BPS) allowed at `coverage_bps` on the default curve
+pub fn min_interest_rate(coverage_bps: u64) -> u64 {
+    rate_floor_bps(coverage_bps, &RateFloorCurve::default())
+}
+
+/// A change of the variable rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct RateChange {
+    /// Block the rate took effect at
+    pub block: u64,
+    /// New variable rate (BPS)
+    pub rate_bps: u64,
+}
+
+/// Most recent changes of the variable rate
+///
+/// Before any change the rate is `VARIABLE_RATE_BASE_BPS`. Once the ring is
+/// full the oldest change is dropped and its rate kept as `prior_rate_bps`,
+/// so a vault untouched across more than `RATE_HISTORY_LEN` changes is
+/// charged that rate for the forgotten part of its interval.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct RateHistory {
+    /// Kept changes, oldest first
+    pub changes: Vec<RateChange>,
+    /// Rate in force before the oldest kept change, once changes were dropped
+    pub prior_rate_bps: Option<u64>,
+}
+
+impl RateHistory {
+    /// Rate in force now
+    pub fn current(&self) -> u64 {
+        match self.changes.last() {
+            Some(change) => change.rate_bps,
+            None => self.prior_rate_bps.unwrap_or(VARIABLE_RATE_BASE_BPS),
+        }
+    }
+
+    /// Rate in force at `block`
+    pub fn rate_at(&self, block: u64) -> u64 {
+        self.changes
+            .iter()
+            .rev()
+            .find(|change| change.block <= block)
+            .map(|change| change.rate_bps)
+            .unwrap_or(self.prior_rate_bps.unwrap_or(VARIABLE_RATE_BASE_BPS))
+    }
+
+    /// Record the rate taking effect at `block`, returning whether it changed
+    ///
+    /// A second change within one block replaces the first.
+    pub fn record(&mut self, block: u64, rate_bps: u64) -> bool {
+        if rate_bps == self.current() {
+            return false;
+        }
+        match self.changes.last_mut() {
+            Some(last) if last.block == block => last.rate_bps = rate_bps,
+            _ => self.changes.push(RateChange { block, rate_bps }),
+        }
+        if self.changes.len() > RATE_HISTORY_LEN {
+            let dropped = self.changes.remove(0);
+            self.prior_rate_bps = Some(dropped.rate_bps);
+        }
+        true
+    }
+
+    /// Simple interest on `debt` from block `from` to `to`, each block
+    /// charged at the rate then in force
+    pub fn interest(&self, debt: u64, from: u64, to: u64) -> u64 {
+        if to <= from {
+            return 0;
+        }
+        let mut rate_blocks = 0u128;
+        let mut start = from;
+        let mut rate = self.rate_at(from);
+        for change in self.changes.iter().filter(|c| c.block > from && c.block < to) {
+            rate_blocks += rate as u128 * (change.block - start) as u128;
+            start = change.block;
+            rate = change.rate_bps;
+        }
+        rate_blocks += rate as u128 * (to - start) as u128;
+
+        let interest = (debt as u128).saturating_mul(rate_blocks)
+            / (BLOCKS_PER_YEAR as u128 * BPS_DENOMINATOR as u128);
+        interest.min(u64::MAX as u128) as u64
+    }
+}
+
+impl ProtocolState {
+    /// Current interest index (a zero index from legacy state reads as 1.0)
+    pub fn current_interest_index(&self) -> u128 {
+        if self.interest_index == 0 {
+            INDEX_PRECISION
+        } else {
+            self.interest_index
+        }
+    }
+
+    /// Scaled interest accrued by all vaults since the last accrual
+    fn interest_since_accrual(&self, current_block: u64) -> ZkUsdResult<u128> {
+        let blocks = current_block.saturating_sub(self.last_interest_accrual_block);
+        self.rate_weighted_debt
+            .checked_mul(index_delta(blocks))
+            .ok_or(ZkUsdError::Overflow)
+    }
+
+    /// Accrue global interest up to `current_block`
+    pub fn accrue_interest(&mut self, current_block: u64) -> ZkUsdResult<()> {
+        if current_block <= self.last_interest_accrual_block {
+            return Ok(());
+        }
+        let blocks = current_block - self.last_interest_accrual_block;
+        let accrued = self.interest_since_accrual(current_block)?;
+
+        self.interest_index = self
+            .current_interest_index()
+            .checked_add(index_delta(blocks))
+            .ok_or(ZkUsdError::Overflow)?;
+        self.pending_interest = self
+            .pending_interest
+            .checked_add(accrued)
+            .ok_or(ZkUsdError::Overflow)?;
+        self.last_interest_accrual_block = current_block;
+        Ok(())
+    }
+
+    /// Total debt including interest not yet reconciled onto vaults
+    pub fn total_debt_with_interest(&self, current_block: u64) -> ZkUsdResult<u64> {
+        let pending = self
+            .pending_interest
+            .checked_add(self.interest_since_accrual(current_block)?)
+            .ok_or(ZkUsdError::Overflow)?;
+        let pending = pending / INDEX_PRECISION;
+        if pending > u64::MAX as u128 {
+            return Err(ZkUsdError::Overflow);
+        }
+        safe_add(self.total_debt, pending as u64)
+    }
+
+    /// Add a vault's debt to the rate weighting
+    pub fn add_rate_weight(&mut self, debt: u64, rate_bps: u64) -> ZkUsdResult<()> {
+        self.rate_weighted_debt = self
+            .rate_weighted_debt
+            .checked_add(rate_weight(debt, rate_bps))
+            .ok_or(ZkUsdError::Overflow)?;
+        Ok(())
+    }
+
+    /// Remove a vault's debt from the rate weighting (saturating for legacy state)
+    pub fn remove_rate_weight(&mut self, debt: u64, rate_bps: u64) {
+        self.rate_weighted_debt = self.rate_weighted_debt.saturating_sub(rate_weight(debt, rate_bps));
+    }
+
+    /// Debt-weighted average interest rate of active vaults (BPS), or zero
+    /// with no debt outstanding
+    pub fn average_rate_bps(&self) -> u64 {
+        if self.total_debt == 0 {
+            return 0;
+        }
+        (self.rate_weighted_debt / self.total_debt as u128).min(u64::MAX as u128) as u64
+    }
+
+    /// Returns true if `vault` is charged less than the average rate
+    ///
+    /// Compares the unrounded average, so a vault exactly at a fractional
+    /// average is not mistaken for one below it.
+    pub fn is_below_average_rate(&self, vault: &Vault) -> bool {
+        self.total_debt > 0 && rate_weight(self.total_debt, self.effective_rate_bps(vault)) < self.rate_weighted_debt
+    }
+
+    /// Current variable rate (BPS)
+    pub fn variable_rate_bps(&self) -> u64 {
+        self.rate_history.current()
+    }
+
+    /// Rate a vault is charged now: its own, or the variable rate
+    pub fn effective_rate_bps(&self, vault: &Vault) -> u64 {
+        match vault.rate_mode {
+            RateMode::Fixed => vault.interest_rate_bps,
+            RateMode::Variable => self.variable_rate_bps(),
+        }
+    }
+
+    /// Add `debt` of a vault to the rate weighting at its effective rate
+    pub fn add_vault_weight(&mut self, vault: &Vault, debt: u64) -> ZkUsdResult<()> {
+        self.add_rate_weight(debt, self.effective_rate_bps(vault))?;
+        if vault.rate_mode == RateMode::Variable {
+            self.variable_debt = safe_add(self.variable_debt, debt)?;
+        }
+        Ok(())
+    }
+
+    /// Remove `debt` of a vault from the rate weighting (saturating)
+    pub fn remove_vault_weight(&mut self, vault: &Vault, debt: u64) {
+        self.remove_rate_weight(debt, self.effective_rate_bps(vault));
+        if vault.rate_mode == RateMode::Variable {
+            self.variable_debt = self.variable_debt.saturating_sub(debt);
+        }
+    }
+
+    /// Re-price the variable rate against the current `total_debt`
+    ///
+    /// Must follow `accrue_interest` so the elapsed interval is charged at
+    /// the old rate. Returns whether the rate changed.
+    pub fn refresh_variable_rate(
+        &mut self,
+        current_block: u64,
+        debt_ceiling: u64,
+        curve: &VariableRateCurve,
+    ) -> ZkUsdResult<bool> {
+        let old_rate = self.variable_rate_bps();
+        let new_rate = variable_rate_bps(self.total_debt, debt_ceiling, curve);
+        if !self.rate_history.record(current_block, new_rate) {
+            return Ok(false);
+        }
+        self.remove_rate_weight(self.variable_debt, old_rate);
+        self.add_rate_weight(self.variable_debt, new_rate)?;
+        Ok(true)
+    }
+
+    /// Initialize interest fields on state written before they existed
+    pub fn migrate_interest_state(&mut self, current_block: u64) {
+        if self.interest_index == 0 {
+            self.interest_index = INDEX_PRECISION;
+            self.last_interest_accrual_block = current_block;
+        }
+        if self.rate_weighted_debt == 0 && self.total_debt > 0 {
+            self.rate_weighted_debt = rate_weight(self.total_debt, DEFAULT_INTEREST_RATE_BPS);
+        }
+    }
+}
+
+/// Interest a vault owes since `last_updated`, at its fixed rate or
+/// integrated over the variable rate history
+pub fn vault_interest(protocol: &ProtocolState, vault: &Vault, current_block: u64) -> u64 {
+    match vault.rate_mode {
+        RateMode::Fixed => vault.calculate_interest(current_block),
+        RateMode::Variable => protocol.rate_history.interest(vault.debt, vault.last_updated, current_block),
+    }
+}
+
+/// Reconcile a touched vault against the global accrual
+///
+/// Accrues protocol interest, moves the vault's simple interest since
+/// `last_updated` from `pending_interest` into the vault, and returns it.
+pub fn reconcile_vault(
+    protocol: &mut ProtocolState,
+    vault: &mut Vault,
+    current_block: u64,
+) -> ZkUsdResult<u64> {
+    protocol.accrue_interest(current_block)?;
+
+    let interest = vault_interest(protocol, vault, current_block);
+    vault.accrued_interest = safe_add(vault.accrued_interest, interest)?;
+    vault.last_updated = current_block;
+
+    protocol.pending_interest = protocol
+        .pending_interest
+        .saturating_sub(interest as u128 * INDEX_PRECISION);
+    protocol.total_debt = safe_add(protocol.total_debt, interest)?;
+
+    Ok(interest)
+}
+
+/// Settle a spent vault up to `current_block` in the canonical order:
+/// redistribution, then interest
+///
+/// Idempotent at a given block; the module docs explain the order.
+pub fn normalize_vault(vault: &Vault, current_block: u64) -> ZkUsdResult<Vault> {
+    let mut normalized = vault.clone();
+    normalized.debt = safe_add(vault.debt, vault.redistributed_debt)?;
+    normalized.collateral = safe_add(vault.collateral, vault.redistributed_collateral)?;
+    normalized.redistributed_debt = 0;
+    normalized.redistributed_collateral = 0;
+    normalized.stats = normalized.stats_at(current_block);
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec;
+
+    const ONE_ZKUSD: u64 = 100_000_000;
+    const ONE_BTC: u64 = 100_000_000;
+
+    /// Deterministic xorshift generator for property tests
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn range(&mut self, lo: u64, hi: u64) -> u64 {
+            lo + self.next() % (hi - lo)
+        }
+    }
+
+    fn open(protocol: &mut ProtocolState, vaults: &mut Vec<Vault>, rng: &mut Rng, block: u64) {
+        protocol.accrue_interest(block).unwrap();
+        let debt = rng.range(2_000, 500_000) * ONE_ZKUSD;
+        let rate = rng.range(50, 501);
+        let id = [vaults.len() as u8; 32];
+        let vault = Vault::with_interest_rate(id, [1u8; 32], ONE_BTC, debt, block, rate);
+        protocol.add_rate_weight(debt, rate).unwrap();
+        protocol.total_debt += debt;
+        vaults.push(vault);
+    }
+
+    /// Sum of per-vault entire debt including unreconciled interest
+    fn sum_vault_debt(vaults: &[Vault], block: u64) -> u64 {
+        vaults.iter().map(|v| v.entire_debt() + v.calculate_interest(block)).sum()
+    }
+
+    #[test]
+    fn test_index_delta_one_year() {
+        // One year at 1 bps on 1 unit of debt
+        assert_eq!(index_delta(BLOCKS_PER_YEAR), INDEX_PRECISION / BPS_DENOMINATOR as u128);
+    }
+
+    #[test]
+    fn test_accrue_matches_single_vault_interest() {
+        let mut protocol = ProtocolState::new([0u8; 32]);
+        let vault = Vault::with_interest_rate([0u8; 32], [1u8; 32], ONE_BTC, 50_000 * ONE_ZKUSD, 0, 100);
+        protocol.add_rate_weight(vault.debt, vault.interest_rate_bps).unwrap();
+        protocol.total_debt = vault.debt;
+
+        protocol.accrue_interest(BLOCKS_PER_YEAR).unwrap();
+
+        // 1% of 50,000 zkUSD after one year
+        assert_eq!(
+            protocol.total_debt_with_interest(BLOCKS_PER_YEAR).unwrap(),
+            50_500 * ONE_ZKUSD
+        );
+        assert!(protocol.interest_index > INDEX_PRECISION);
+    }
+
+    #[test]
+    fn test_total_with_interest_without_accrual() {
+        let mut protocol = ProtocolState::new([0u8; 32]);
+        protocol.add_rate_weight(50_000 * ONE_ZKUSD, 100).unwrap();
+        protocol.total_debt = 50_000 * ONE_ZKUSD;
+
+        // Projection does not need an accrual to have happened
+        assert_eq!(
+            protocol.total_debt_with_interest(BLOCKS_PER_YEAR).unwrap(),
+            50_500 * ONE_ZKUSD
+        );
+    }
+
+    #[test]
+    fn test_reconcile_moves_pending_into_vault() {
+        let mut protocol = ProtocolState::new([0u8; 32]);
+        let mut vault = Vault::with_interest_rate([0u8; 32], [1u8; 32], ONE_BTC, 50_000 * ONE_ZKUSD, 0, 100);
+        protocol.add_rate_weight(vault.debt, vault.interest_rate_bps).unwrap();
+        protocol.total_debt = vault.debt;
+
+        let interest = reconcile_vault(&mut protocol, &mut vault, BLOCKS_PER_YEAR).unwrap();
+
+        assert_eq!(interest, 500 * ONE_ZKUSD);
+        assert_eq!(vault.accrued_interest, 500 * ONE_ZKUSD);
+        assert_eq!(vault.last_updated, BLOCKS_PER_YEAR);
+        assert_eq!(protocol.total_debt, 50_500 * ONE_ZKUSD);
+        assert_eq!(protocol.pending_interest, 0);
+    }
+
+    #[test]
+    fn test_normalize_vault_settles_redistribution_before_interest() {
+        let mut vault = Vault::with_interest_rate([0u8; 32], [1u8; 32], ONE_BTC, 40_000 * ONE_ZKUSD, 0, 100);
+        vault.redistributed_debt = 10_000 * ONE_ZKUSD;
+        vault.redistributed_collateral = ONE_BTC / 4;
+
+        let normalized = normalize_vault(&vault, BLOCKS_PER_YEAR).unwrap();
+
+        assert_eq!(normalized.debt, 50_000 * ONE_ZKUSD);
+        assert_eq!(normalized.collateral, ONE_BTC + ONE_BTC / 4);
+        assert_eq!((normalized.redistributed_debt, normalized.redistributed_collateral), (0, 0));
+        // A year at 1% on the redistributed balance, not just the 40,000 of
+        // principal interest settled first would charge
+        assert_eq!(normalized.stats.total_interest_paid, 500 * ONE_ZKUSD);
+        assert_eq!(normalized.stats.last_updated, BLOCKS_PER_YEAR);
+        assert_eq!(normalized.last_updated, vault.last_updated);
+
+        // Nothing is left to settle at the same block
+        assert_eq!(normalize_vault(&normalized, BLOCKS_PER_YEAR).unwrap(), normalized);
+    }
+
+    #[test]
+    fn test_migrate_legacy_state() {
+        let mut protocol = ProtocolState {
+            total_debt: 10_000 * ONE_ZKUSD,
+            interest_index: 0,
+            rate_weighted_debt: 0,
+            ..ProtocolState::new([0u8; 32])
+        };
+        assert_eq!(protocol.current_interest_index(), INDEX_PRECISION);
+
+        protocol.migrate_interest_state(500);
+
+        assert_eq!(protocol.interest_index, INDEX_PRECISION);
+        assert_eq!(protocol.last_interest_accrual_block, 500);
+        assert_eq!(
+            protocol.rate_weighted_debt,
+            rate_weight(10_000 * ONE_ZKUSD, DEFAULT_INTEREST_RATE_BPS)
+        );
+
+        // Removing more weight than tracked saturates instead of failing
+        protocol.remove_rate_weight(u64::MAX, 500);
+        assert_eq!(protocol.rate_weighted_debt, 0);
+    }
+
+    #[test]
+    fn test_variable_rate_curve_breakpoints() {
+        let curve = VariableRateCurve::default();
+        let ceiling = 1_000_000 * ONE_ZKUSD;
+        let at = |pct: u64| variable_rate_bps(ceiling / 100 * pct, ceiling, &curve);
+
+        // Flat at the base rate up to the kink
+        assert_eq!(at(0), VARIABLE_RATE_BASE_BPS);
+        assert_eq!(at(50), VARIABLE_RATE_BASE_BPS);
+        assert_eq!(at(80), VARIABLE_RATE_BASE_BPS);
+        // Steep above it, up to the maximum at full utilization
+        assert_eq!(at(85), 575);
+        assert_eq!(at(90), 1_050);
+        assert_eq!(at(95), 1_525);
+        assert_eq!(at(100), VARIABLE_RATE_MAX_BPS);
+        // Capped past the ceiling, snapped down between steps
+        assert_eq!(at(150), VARIABLE_RATE_MAX_BPS);
+        assert_eq!(at(89), 575);
+        // No ceiling means no utilization
+        assert_eq!(variable_rate_bps(ceiling, 0, &curve), VARIABLE_RATE_BASE_BPS);
+    }
+
+    #[test]
+    fn test_rate_floor_follows_coverage() {
+        let debt = 1_000_000 * ONE_ZKUSD;
+        let floor_at = |pct: u64| min_interest_rate(sp_coverage_bps(debt / 100 * pct, debt));
+
+        // Highest while the pool covers little, the minimum once it covers half
+        assert_eq!(floor_at(0), RATE_FLOOR_STRESSED_BPS);
+        assert_eq!(floor_at(10), RATE_FLOOR_STRESSED_BPS);
+        assert_eq!(floor_at(30), 175);
+        assert_eq!(floor_at(50), MIN_INTEREST_RATE_BPS);
+        assert_eq!(floor_at(200), MIN_INTEREST_RATE_BPS);
+        // Falling in between, never below the curve
+        assert_eq!(floor_at(11), 294);
+        assert!(floor_at(20) > floor_at(40));
+        // Nothing to cover without debt
+        assert_eq!(sp_coverage_bps(0, 0), BPS_DENOMINATOR);
+    }
+
+    #[test]
+    fn test_rate_history_records_changes() {
+        let mut history = RateHistory::default();
+        assert_eq!(history.current(), VARIABLE_RATE_BASE_BPS);
+
+        // An unchanged rate is not recorded; a same-block change replaces
+        assert!(!history.record(10, VARIABLE_RATE_BASE_BPS));
+        assert!(history.record(10, 575));
+        assert!(history.record(10, 1_050));
+        assert_eq!(history.changes, [RateChange { block: 10, rate_bps: 1_050 }]);
+        assert_eq!(history.rate_at(9), VARIABLE_RATE_BASE_BPS);
+        assert_eq!(history.rate_at(10), 1_050);
+
+        // The ring keeps the last RATE_HISTORY_LEN changes
+        for i in 0..RATE_HISTORY_LEN as u64 {
+            assert!(history.record(20 + i, 200 + i));
+        }
+        assert_eq!(history.changes.len(), RATE_HISTORY_LEN);
+        assert_eq!(history.prior_rate_bps, Some(1_050));
+        assert_eq!(history.rate_at(0), 1_050);
+    }
+
+    #[test]
+    fn test_variable_accrual_is_piecewise() {
+        let mut protocol = ProtocolState::new([0u8; 32]);
+        let ceiling = 100_000 * ONE_ZKUSD;
+        let curve = VariableRateCurve::default();
+        let mut vault = Vault::new([0u8; 32], [1u8; 32], ONE_BTC, 50_000 * ONE_ZKUSD, 0);
+        vault.rate_mode = RateMode::Variable;
+        protocol.total_debt = vault.debt;
+        protocol.add_vault_weight(&vault, vault.debt).unwrap();
+        assert!(!protocol.refresh_variable_rate(0, ceiling, &curve).unwrap());
+
+        // Half a year at the base rate, then utilization reaches 90%
+        let half_year = BLOCKS_PER_YEAR / 2;
+        protocol.accrue_interest(half_year).unwrap();
+        protocol.total_debt = 90_000 * ONE_ZKUSD;
+        assert!(protocol.refresh_variable_rate(half_year, ceiling, &curve).unwrap());
+        assert_eq!(protocol.variable_rate_bps(), 1_050);
+        assert_eq!(protocol.rate_weighted_debt, rate_weight(vault.debt, 1_050));
+
+        // 1% for half a year plus 10.5% for half a year on 50,000 zkUSD
+        let interest = reconcile_vault(&mut protocol, &mut vault, BLOCKS_PER_YEAR).unwrap();
+        assert_eq!(interest, 250 * ONE_ZKUSD + 2_625 * ONE_ZKUSD);
+        assert_eq!(protocol.pending_interest, 0);
+        // A fixed vault at the same nominal rate is unaffected by the change
+        let fixed = Vault::new([2u8; 32], [1u8; 32], ONE_BTC, 50_000 * ONE_ZKUSD, 0);
+        assert_eq!(vault_interest(&protocol, &fixed, BLOCKS_PER_YEAR), 500 * ONE_ZKUSD);
+    }
+
+    #[test]
+    fn test_property_index_total_tracks_vault_sum() {
+        for seed in 1..=20u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut protocol = ProtocolState::new([0u8; 32]);
+            let mut vaults: Vec<Vault> = Vec::new();
+            let mut block = 0u64;
+            let mut rounding_events = 0u64;
+
+            for _ in 0..200 {
+                block += rng.range(1, 2_000);
+
+                match rng.range(0, 4) {
+                    // Open a vault
+                    0 => open(&mut protocol, &mut vaults, &mut rng, block),
+                    // Touch a vault: reconcile and change its principal
+                    1 if !vaults.is_empty() => {
+                        let i = rng.range(0, vaults.len() as u64) as usize;
+                        let vault = &mut vaults[i];
+                        reconcile_vault(&mut protocol, vault, block).unwrap();
+                        protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                        let new_debt = rng.range(2_000, 500_000) * ONE_ZKUSD;
+                        protocol.total_debt = protocol.total_debt - vault.debt + new_debt;
+                        vault.debt = new_debt;
+                        protocol.add_rate_weight(vault.debt, vault.interest_rate_bps).unwrap();
+                    }
+                    // Close a vault
+                    2 if !vaults.is_empty() => {
+                        let i = rng.range(0, vaults.len() as u64) as usize;
+                        let mut vault = vaults.swap_remove(i);
+                        reconcile_vault(&mut protocol, &mut vault, block).unwrap();
+                        protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                        protocol.total_debt -= vault.entire_debt();
+                    }
+                    // Lazy accrual only
+                    _ => protocol.accrue_interest(block).unwrap(),
+                }
+                rounding_events += 2;
+
+                let index_total = protocol.total_debt_with_interest(block).unwrap();
+                let vault_total = sum_vault_debt(&vaults, block);
+                let diff = index_total.abs_diff(vault_total);
+                assert!(
+                    diff <= rounding_events + vaults.len() as u64,
+                    "seed {} block {}: index {} vs vaults {} (diff {})",
+                    seed,
+                    block,
+                    index_total,
+                    vault_total,
+                    diff
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_average_rate_matches_recomputation() {
+        for seed in 1..=20u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut protocol = ProtocolState::new([0u8; 32]);
+            let mut vaults: Vec<Vault> = Vec::new();
+
+            for block in 1..=200u64 {
+                match rng.range(0, 4) {
+                    // Open a vault
+                    0 => open(&mut protocol, &mut vaults, &mut rng, block),
+                    // Mint or repay: change a vault's principal
+                    1 if !vaults.is_empty() => {
+                        let i = rng.range(0, vaults.len() as u64) as usize;
+                        let vault = &mut vaults[i];
+                        protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                        let new_debt = rng.range(2_000, 500_000) * ONE_ZKUSD;
+                        protocol.total_debt = protocol.total_debt - vault.debt + new_debt;
+                        vault.debt = new_debt;
+                        protocol.add_rate_weight(vault.debt, vault.interest_rate_bps).unwrap();
+                    }
+                    // Refinance: change a vault's rate
+                    2 if !vaults.is_empty() => {
+                        let i = rng.range(0, vaults.len() as u64) as usize;
+                        let vault = &mut vaults[i];
+                        protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                        vault.interest_rate_bps = rng.range(50, 501);
+                        protocol.add_rate_weight(vault.debt, vault.interest_rate_bps).unwrap();
+                    }
+                    // Close a vault
+                    3 if !vaults.is_empty() => {
+                        let vault = vaults.swap_remove(rng.range(0, vaults.len() as u64) as usize);
+                        protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                        protocol.total_debt -= vault.debt;
+                    }
+                    _ => {}
+                }
+
+                // Full recomputation over every vault
+                let weighted: u128 = vaults.iter().map(|v| rate_weight(v.debt, v.interest_rate_bps)).sum();
+                let debt: u64 = vaults.iter().map(|v| v.debt).sum();
+                let expected = if debt == 0 { 0 } else { (weighted / debt as u128) as u64 };
+                assert_eq!(protocol.average_rate_bps(), expected, "seed {} block {}", seed, block);
+                for vault in &vaults {
+                    let below = rate_weight(debt, vault.interest_rate_bps) < weighted;
+                    assert_eq!(protocol.is_below_average_rate(vault), below);
+                }
+            }
+
+            // Closing the last vault resets the average
+            for vault in vaults.drain(..) {
+                protocol.remove_rate_weight(vault.debt, vault.interest_rate_bps);
+                protocol.total_debt -= vault.debt;
+            }
+            assert_eq!(protocol.rate_weighted_debt, 0);
+            assert_eq!(protocol.average_rate_bps(), 0);
+            assert!(!protocol.is_below_average_rate(&Vault::with_interest_rate([0u8; 32], [1u8; 32], ONE_BTC, ONE_ZKUSD, 0, 0)));
+        }
+    }
+}
@@ -6,15 +6,26 @@
 //!
 //! - **constants**: Protocol parameters
 //! - **types**: Core data structures (Vault, PriceData, etc.)
+//! - **address**: Address derivation and bech32m display (`std` for strings)
 //! - **errors**: Error handling
+//! - **units**: Unit-typed amounts (`Sats`, `ZkUsd`)
 //! - **events**: Event logging
 //! - **math**: Financial calculations (ICR, TCR, fees)
+//! - **interest**: Global interest accrual index
+//! - **governance**: Parameter snapshots and diffs
+//! - **commitment**: Canonical state commitments for light clients
+//! - **versioning**: Leading version byte and migration of persisted states
+//! - **migrations**: Frozen past layouts and their migrations
+//! - **bootstrap**: Permissioned launch mode and its graduation
+//! - **diagnostics**: Field-by-field state diffs (`std` feature)
 //! - **liquidation**: Liquidation logic
 //! - **charms_ops**: UTXO-native operations
 //! - **oracle**: Price oracle utilities
 //! - **vault_manager**: Vault lifecycle
+//! - **vault_registry**: Rate-sorted on-chain registry of active vaults
 //! - **stability_pool**: Debt absorption
 //! - **token_ops**: Token minting/burning
+//! - **conformance**: Cross-language test vectors (`conformance` feature)
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -29,16 +40,29 @@ pub use std::vec::Vec;
 // Core modules
 pub mod constants;
 pub mod errors;
+pub mod units;
 pub mod types;
+pub mod address;
 pub mod math;
+pub mod interest;
+pub mod governance;
+pub mod commitment;
+pub mod versioning;
+pub mod migrations;
+pub mod bootstrap;
+#[cfg(feature = "std")]
+pub mod diagnostics;
 pub mod events;
 pub mod liquidation;
 pub mod charms_ops;
 pub mod oracle;
 pub mod vault_manager;
+pub mod vault_registry;
 pub mod stability_pool;
 pub mod token_ops;
 pub mod validation;
+#[cfg(feature = "conformance")]
+pub mod conformance;
 
 #[cfg(test)]
 mod tests;
@@ -46,13 +70,24 @@ mod tests;
 // Re-exports
 pub use constants::*;
 pub use errors::*;
+pub use units::*;
 pub use types::*;
+pub use address::*;
 pub use math::*;
+pub use interest::*;
+pub use governance::*;
+pub use commitment::*;
+pub use versioning::*;
+pub use bootstrap::*;
 pub use events::*;
 pub use liquidation::*;
 pub use charms_ops::*;
 pub use oracle::*;
 pub use vault_manager::*;
+pub use vault_registry::*;
 pub use stability_pool::*;
 pub use token_ops::*;
 pub use validation::*;
+// Both `types` and `oracle` define a `PriceAttestation`; the root name is
+// the contract's signed attestation, carried by `OracleAction`
+pub use types::PriceAttestation;
@@ -50,13 +50,18 @@
 use crate::{
     constants::{
         fees::BPS_DENOMINATOR,
-        liquidation::LIQUIDATOR_BONUS_BPS,
+        liquidation::{LIQUIDATOR_BONUS_BPS, TWO_PHASE_DEBT_THRESHOLD, TWO_PHASE_POOL_SHARE_BPS},
         ratios::{CCR, MCR},
         redistribution::{LIQUIDATION_PENALTY_BPS, LIQUIDATION_PENALTY_RM_BPS},
     },
     errors::{ZkUsdError, ZkUsdResult},
-    math::calculate_icr,
+    interest::normalize_vault,
+    math::{
+        btc_to_zkusd_floor, calculate_icr, calculate_icr_bps, min_collateral_for_debt, safe_add, safe_mul_div_ceil,
+        saturating_u64, zkusd_to_btc_ceil, zkusd_to_btc_floor,
+    },
     types::{Address, LiquidationResult, StabilityPoolState, SurplusClaim, Vault},
+    units::{Sats, ZkUsd},
 };
 
 /// Configuration for liquidation processing
@@ -68,10 +73,14 @@ pub struct LiquidationConfig {
     pub block_height: u64,
     /// Whether system is in recovery mode
     pub is_recovery_mode: bool,
+    /// Liquidator bonus in recovery mode (BPS, capped at the normal bonus)
+    pub recovery_liquidator_bonus_bps: u64,
     /// Total collateral in system (for redistribution calc)
     pub total_system_collateral: u64,
     /// Liquidator address (receives bonus)
     pub liquidator: Address,
+    /// Most liquidations processed in this block (0 = uncapped)
+    pub max_liquidations_per_block: u64,
 }
 
 /// Result of processing a liquidation
@@ -85,13 +94,37 @@ pub struct ProcessedLiquidation {
     pub used_redistribution: bool,
 }
 
+/// A vault's standing at a hypothetical BTC price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultProjection {
+    /// Debt with redistributions folded in and interest owed to the block
+    /// (zkUSD base units)
+    pub debt_with_interest: u64,
+    /// ICR at the price, on `debt_with_interest` (BPS)
+    pub icr_bps: u64,
+    /// Whether a liquidation at the price would be accepted
+    pub liquidatable: bool,
+    /// Collateral the owner would need to add for `icr_bps` to reach the
+    /// minimum (satoshis; 0 when it already does)
+    pub collateral_to_add: u64,
+}
+
+/// Result of a batch liquidation
+#[derive(Debug, Clone)]
+pub struct BatchLiquidationOutcome {
+    /// Liquidations processed, highest risk score first
+    pub liquidations: Vec<ProcessedLiquidation>,
+    /// Eligible vaults left for the next block by the per-block cap
+    pub deferred: usize,
+}
+
 /// Check if a vault can be liquidated
 pub fn can_liquidate(vault: &Vault, btc_price: u64, is_recovery_mode: bool) -> bool {
     if !vault.is_active() {
         return false;
     }
 
-    let icr = match calculate_icr(vault.entire_collateral(), vault.entire_debt(), btc_price) {
+    let icr = match calculate_icr(Sats(vault.entire_collateral()), ZkUsd(vault.entire_debt()), btc_price) {
         Ok(icr) => icr,
         Err(_) => return false, // On math error, assume not liquidatable
     };
@@ -105,6 +138,70 @@ pub fn can_liquidate(vault: &Vault, btc_price: u64, is_recovery_mode: bool) -> b
     }
 }
 
+/// Check if a vault's debt is too large to liquidate in a single spell
+///
+/// Debt above `TWO_PHASE_DEBT_THRESHOLD`, or above half of the stability
+/// pool when the pool is known, is liquidated in tranches: `BeginLiquidation`
+/// freezes the vault, then `ContinueLiquidation` offsets it piece by piece.
+/// An empty pool can absorb no tranche, so it leaves the single-shot path
+/// (and its redistribution) open.
+pub fn requires_two_phase(debt: u64, pool_zkusd: Option<u64>) -> bool {
+    let pool_share = pool_zkusd
+        .filter(|pool| *pool > 0)
+        .is_some_and(|pool| debt as u128 * BPS_DENOMINATOR as u128 > pool as u128 * TWO_PHASE_POOL_SHARE_BPS as u128);
+    debt > TWO_PHASE_DEBT_THRESHOLD || pool_share
+}
+
+/// Systemic risk of a vault below `mcr_bps`, for prioritizing liquidations
+///
+/// The score is the collateral value (zkUSD base units) the vault lacks to
+/// reach `mcr_bps`:
+///
+/// ```text
+/// score = debt × (mcr_bps − icr_bps) / 10_000
+/// ```
+///
+/// so it grows both with how far the vault is below MCR and with its debt.
+/// A vault at or above `mcr_bps`, or without debt, scores 0. Pass the CCR
+/// (in BPS) as `mcr_bps` in Recovery Mode.
+pub fn vault_risk_score(vault: &Vault, btc_price: u64, mcr_bps: u64) -> u64 {
+    let debt = vault.entire_debt();
+    let icr_bps = calculate_icr_bps(Sats(vault.entire_collateral()), ZkUsd(debt), btc_price).unwrap_or(u64::MAX);
+    let shortfall_bps = mcr_bps.saturating_sub(icr_bps);
+    saturating_u64(debt as u128 * shortfall_bps as u128 / BPS_DENOMINATOR as u128)
+}
+
+/// Project `vault` at `hypothetical_price`, without changing anything
+///
+/// Liquidation is decided as `validate_liquidate` decides it: on the vault
+/// settled to `current_block`, an active vault below `mcr_bps` (pass the
+/// CCR in BPS for Recovery Mode). That check counts interest only once it
+/// is folded into the debt, so `icr_bps`, which also counts interest owed
+/// since, can be a little lower, and `collateral_to_add` covers it.
+pub fn project_vault_at_price(
+    vault: &Vault,
+    hypothetical_price: u64,
+    mcr_bps: u64,
+    current_block: u64,
+) -> ZkUsdResult<VaultProjection> {
+    let settled = normalize_vault(vault, current_block)?;
+    let settled_icr_bps = calculate_icr_bps(Sats(settled.collateral), ZkUsd(settled.debt), hypothetical_price)?;
+
+    let interest = safe_add(settled.accrued_interest, settled.calculate_interest(current_block))?;
+    let debt_with_interest = safe_add(settled.debt, interest)?;
+    let icr_bps = calculate_icr_bps(Sats(settled.collateral), ZkUsd(debt_with_interest), hypothetical_price)?;
+
+    let required_value = safe_mul_div_ceil(debt_with_interest, mcr_bps, BPS_DENOMINATOR)?;
+    let required_collateral = zkusd_to_btc_ceil(ZkUsd(required_value), hypothetical_price)?.into_inner();
+
+    Ok(VaultProjection {
+        debt_with_interest,
+        icr_bps,
+        liquidatable: settled.is_active() && settled_icr_bps < mcr_bps,
+        collateral_to_add: required_collateral.saturating_sub(settled.collateral),
+    })
+}
+
 /// Process a single vault liquidation
 ///
 /// Returns the liquidation result with all distributions calculated.
@@ -116,7 +213,7 @@ pub fn process_liquidation(
 ) -> ZkUsdResult<ProcessedLiquidation> {
     // 1. Verify vault can be liquidated
     if !can_liquidate(vault, config.btc_price, config.is_recovery_mode) {
-        let icr = calculate_icr(vault.entire_collateral(), vault.entire_debt(), config.btc_price)
+        let icr = calculate_icr(Sats(vault.entire_collateral()), ZkUsd(vault.entire_debt()), config.btc_price)
             .unwrap_or(u64::MAX);
         return Err(ZkUsdError::NotLiquidatable {
             vault_id: vault.id,
@@ -129,27 +226,30 @@ pub fn process_liquidation(
 
     let mut result = LiquidationResult::new(vault.id);
 
-    // 2. Calculate liquidator bonus (0.5% of collateral)
+    // 2. Calculate liquidator bonus (0.5% of collateral, reduced in
+    // Recovery Mode)
+    let bonus_bps = if config.is_recovery_mode {
+        config.recovery_liquidator_bonus_bps.min(LIQUIDATOR_BONUS_BPS)
+    } else {
+        LIQUIDATOR_BONUS_BPS
+    };
     result.liquidator_bonus = entire_collateral
-        .saturating_mul(LIQUIDATOR_BONUS_BPS)
+        .saturating_mul(bonus_bps)
         / BPS_DENOMINATOR;
 
     let collateral_after_bonus = entire_collateral.saturating_sub(result.liquidator_bonus);
 
     // 3. Check for surplus collateral in Recovery Mode
-    // If ICR > 110% but < 150%, user gets excess back
-    let icr = calculate_icr(entire_collateral, entire_debt, config.btc_price)
+    // If ICR > 110% but < 150%, user gets excess back. The surplus is what
+    // remains after the bonus, so a reduced bonus is returned to the owner
+    let icr = calculate_icr(Sats(entire_collateral), ZkUsd(entire_debt), config.btc_price)
         .unwrap_or(0);
     let surplus_claim = if config.is_recovery_mode && icr > MCR {
-        // Calculate collateral needed to cover debt at 110%
-        let collateral_needed = entire_debt
-            .saturating_mul(MCR as u64)
-            / 100;
-
-        // Convert zkUSD value to BTC
-        let collateral_needed_btc = collateral_needed
-            .saturating_mul(100_000_000) // 8 decimals
-            / config.btc_price;
+        // Collateral needed to cover debt at 110%, priced at 128 bits: a
+        // saturating u64 product undervalues a large vault's requirement
+        // and overpays its surplus. Unpriceable means no surplus.
+        let collateral_needed_btc = min_collateral_for_debt(ZkUsd(entire_debt), config.btc_price)
+            .map_or(u64::MAX, Sats::into_inner);
 
         let surplus = collateral_after_bonus.saturating_sub(collateral_needed_btc);
 
@@ -216,18 +316,47 @@ pub fn process_liquidation(
 }
 
 /// Process multiple liquidations in batch (UTXO advantage: parallel processing)
+///
+/// The vaults with the highest `vault_risk_score` go first, then the lowest
+/// ICR among equal scores. Once `max_liquidations_per_block`
+/// is reached the remaining eligible vaults are deferred to the next block,
+/// so a sharp drop does not liquidate vaults that would recover on the next
+/// price update.
 pub fn process_batch_liquidation(
     vaults: &[Vault],
     stability_pool: &StabilityPoolState,
     config: &LiquidationConfig,
-) -> ZkUsdResult<Vec<ProcessedLiquidation>> {
+) -> ZkUsdResult<BatchLiquidationOutcome> {
     let mut results = Vec::with_capacity(vaults.len());
+    let mut deferred = 0;
     let mut remaining_sp = stability_pool.total_zkusd;
 
     // Create a mutable copy of SP state for tracking
     let mut current_sp = stability_pool.clone();
 
-    for vault in vaults {
+    // Highest risk first, then lowest ICR
+    let threshold_bps = if config.is_recovery_mode { CCR } else { MCR } * BPS_DENOMINATOR / 100;
+    let mut by_risk: Vec<(u64, u64, &Vault)> = vaults
+        .iter()
+        .map(|v| {
+            let icr = calculate_icr_bps(Sats(v.entire_collateral()), ZkUsd(v.entire_debt()), config.btc_price)
+                .unwrap_or(u64::MAX);
+            (vault_risk_score(v, config.btc_price, threshold_bps), icr, v)
+        })
+        .collect();
+    by_risk.sort_by_key(|&(score, icr, _)| (core::cmp::Reverse(score), icr));
+
+    for (_, _, vault) in by_risk {
+        // Past the cap, eligible vaults wait for the next block
+        let cap_reached = config.max_liquidations_per_block > 0
+            && results.len() as u64 >= config.max_liquidations_per_block;
+        if cap_reached {
+            if can_liquidate(vault, config.btc_price, config.is_recovery_mode) {
+                deferred += 1;
+            }
+            continue;
+        }
+
         // Update SP state for next liquidation
         current_sp.total_zkusd = remaining_sp;
 
@@ -249,34 +378,48 @@ pub fn process_batch_liquidation(
         return Err(ZkUsdError::NoLiquidatableVaults);
     }
 
-    Ok(results)
+    Ok(BatchLiquidationOutcome { liquidations: results, deferred })
 }
 
-/// Calculate redistribution shares for active vaults
+/// Calculate a vault's redistribution shares
 ///
-/// Each vault receives a proportional share based on their collateral
+/// Each vault receives a share proportional to its time-weighted average
+/// collateral at `block_height`, so collateral added just before a
+/// liquidation and withdrawn after it earns little extra.
+/// `total_twa_collateral` is the sum over all recipients at the same
+/// block (see `total_twa_collateral`).
 pub fn calculate_redistribution_shares(
     debt_to_redistribute: u64,
     collateral_to_redistribute: u64,
-    recipient_collateral: u64,
-    total_system_collateral: u64,
+    recipient: &Vault,
+    total_twa_collateral: u64,
+    block_height: u64,
 ) -> (u64, u64) {
-    if total_system_collateral == 0 {
+    if total_twa_collateral == 0 {
         return (0, 0);
     }
 
-    // Share = (recipient_collateral / total_collateral) * amount
+    // Share = (recipient_twa / total_twa) * amount
+    let recipient_collateral = recipient.twa_collateral_at(block_height).min(total_twa_collateral);
     let debt_share = (debt_to_redistribute as u128)
         .saturating_mul(recipient_collateral as u128)
-        / total_system_collateral as u128;
+        / total_twa_collateral as u128;
 
     let collateral_share = (collateral_to_redistribute as u128)
         .saturating_mul(recipient_collateral as u128)
-        / total_system_collateral as u128;
+        / total_twa_collateral as u128;
 
     (debt_share as u64, collateral_share as u64)
 }
 
+/// Sum of the active vaults' time-weighted average collateral at `block_height`
+pub fn total_twa_collateral(vaults: &[Vault], block_height: u64) -> u64 {
+    vaults
+        .iter()
+        .filter(|v| v.is_active())
+        .fold(0u64, |total, v| total.saturating_add(v.twa_collateral_at(block_height)))
+}
+
 /// Apply redistribution to a vault
 ///
 /// In the UTXO model, this creates a new vault charm with updated values
@@ -299,7 +442,7 @@ pub fn should_trigger_insurance(
         return false;
     }
 
-    let icr = calculate_icr(vault.entire_collateral(), vault.entire_debt(), btc_price)
+    let icr = calculate_icr(Sats(vault.entire_collateral()), ZkUsd(vault.entire_debt()), btc_price)
         .unwrap_or(u64::MAX);
     icr <= insurance_trigger_icr
 }
@@ -310,10 +453,11 @@ pub fn calculate_insurance_payout(
     btc_price: u64,
     target_icr: u64, // e.g., 150% = 150
 ) -> u64 {
-    let current_icr = calculate_icr(vault.entire_collateral(), vault.entire_debt(), btc_price)
+    let current_icr = calculate_icr(Sats(vault.entire_collateral()), ZkUsd(vault.entire_debt()), btc_price)
         .unwrap_or(u64::MAX);
 
-    if current_icr >= target_icr {
+    // No payout can be priced without a BTC price
+    if current_icr >= target_icr || btc_price == 0 {
         return 0;
     }
 
@@ -324,18 +468,15 @@ pub fn calculate_insurance_payout(
         .saturating_mul(target_icr as u128)
         / 10000;
 
-    let current_collateral_value = (vault.entire_collateral() as u128)
-        .saturating_mul(btc_price as u128)
-        / 100_000_000;
+    let current_collateral_value = btc_to_zkusd_floor(Sats(vault.entire_collateral()), btc_price)
+        .map_or(u64::MAX, ZkUsd::into_inner);
 
-    let needed_value = target_collateral_value.saturating_sub(current_collateral_value);
+    let needed_value = target_collateral_value
+        .saturating_sub(current_collateral_value as u128)
+        .min(u64::MAX as u128) as u64;
 
     // Convert to BTC
-    let needed_btc = (needed_value as u128)
-        .saturating_mul(100_000_000)
-        / btc_price as u128;
-
-    needed_btc.min(u64::MAX as u128) as u64
+    zkusd_to_btc_floor(ZkUsd(needed_value), btc_price).map_or(u64::MAX, Sats::into_inner)
 }
 
 // ============ Tests ============
@@ -343,14 +484,29 @@ pub fn calculate_insurance_payout(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::VaultStatus;
+    use crate::types::{AdjustmentWindow, RateMode, VaultId, VaultStats, VaultStatus};
+    use crate::constants::liquidation::RECOVERY_LIQUIDATOR_BONUS_BPS;
 
     const BTC_PRICE: u64 = 100_000_00000000; // $100,000
     const ONE_BTC: u64 = 100_000_000;
     const ONE_ZKUSD: u64 = 100_000_000;
 
+    #[test]
+    fn test_requires_two_phase() {
+        // Debt threshold applies with or without a known pool
+        assert!(!requires_two_phase(TWO_PHASE_DEBT_THRESHOLD, None));
+        assert!(requires_two_phase(TWO_PHASE_DEBT_THRESHOLD + 1, None));
+        assert!(requires_two_phase(TWO_PHASE_DEBT_THRESHOLD + 1, Some(u64::MAX)));
+
+        // Below it, more than half of the pool still calls for two phases
+        assert!(!requires_two_phase(50_000 * ONE_ZKUSD, Some(100_000 * ONE_ZKUSD)));
+        assert!(requires_two_phase(50_000 * ONE_ZKUSD + 1, Some(100_000 * ONE_ZKUSD)));
+        assert!(!requires_two_phase(1, Some(0)));
+    }
+
     fn create_test_vault(collateral: u64, debt: u64) -> Vault {
         Vault {
+            version: crate::migrations::V2,
             id: [1u8; 32],
             owner: [2u8; 32],
             collateral,
@@ -363,6 +519,15 @@ mod tests {
             redistributed_debt: 0,
             redistributed_collateral: 0,
             insurance_balance: 0,
+            operator: None,
+            twa_collateral: 0,
+            twa_updated_at: 0,
+            stats: VaultStats::default(),
+            protected_collateral_bps: 0,
+            beneficiary: None,
+            adjustment_window: AdjustmentWindow::default(),
+            rate_mode: RateMode::Fixed,
+            pending_liquidation: None,
         }
     }
 
@@ -371,8 +536,10 @@ mod tests {
             btc_price: BTC_PRICE,
             block_height: 1000,
             is_recovery_mode,
+            recovery_liquidator_bonus_bps: RECOVERY_LIQUIDATOR_BONUS_BPS,
             total_system_collateral: 100 * ONE_BTC,
             liquidator: [99u8; 32],
+            max_liquidations_per_block: 0,
         }
     }
 
@@ -419,6 +586,91 @@ mod tests {
         assert!(!result.used_redistribution);
     }
 
+    #[test]
+    fn test_batch_liquidation_cap_defers_least_underwater() {
+        // Ten vaults from ICR ~108.7% (92k debt) down to ~99% (101k debt)
+        let vaults: Vec<Vault> = (0..10u8)
+            .map(|i| Vault {
+                id: [i; 32],
+                ..create_test_vault(ONE_BTC, (92_000 + 1_000 * u64::from(i)) * ONE_ZKUSD)
+            })
+            .collect();
+        let sp = StabilityPoolState { total_zkusd: 1_000_000 * ONE_ZKUSD, ..Default::default() };
+        let config = LiquidationConfig { max_liquidations_per_block: 3, ..create_test_config(false) };
+
+        let batch = process_batch_liquidation(&vaults, &sp, &config).unwrap();
+
+        let liquidated: Vec<VaultId> = batch.liquidations.iter().map(|l| l.result.vault_id).collect();
+        assert_eq!(liquidated, [[9u8; 32], [8u8; 32], [7u8; 32]]);
+        assert_eq!(batch.deferred, 7);
+
+        // Uncapped, every eligible vault goes in one block
+        let batch = process_batch_liquidation(&vaults, &sp, &create_test_config(false)).unwrap();
+        assert_eq!((batch.liquidations.len(), batch.deferred), (10, 0));
+    }
+
+    #[test]
+    fn test_risk_score_prioritizes_larger_debt() {
+        // Both at 105% ICR, 5% below MCR; the large vault owes ten times more
+        let small = Vault { id: [1u8; 32], ..create_test_vault(10_500_000, 10_000 * ONE_ZKUSD) };
+        let large = Vault { id: [2u8; 32], ..create_test_vault(105_000_000, 100_000 * ONE_ZKUSD) };
+        // Far below MCR (90%) but tiny
+        let tiny = Vault { id: [3u8; 32], ..create_test_vault(900_000, 1_000 * ONE_ZKUSD) };
+        let mcr_bps = MCR * BPS_DENOMINATOR / 100;
+
+        assert_eq!(vault_risk_score(&small, BTC_PRICE, mcr_bps), 500 * ONE_ZKUSD);
+        assert_eq!(vault_risk_score(&large, BTC_PRICE, mcr_bps), 5_000 * ONE_ZKUSD);
+        assert_eq!(vault_risk_score(&tiny, BTC_PRICE, mcr_bps), 200 * ONE_ZKUSD);
+        let healthy = create_test_vault(2 * ONE_BTC, 100_000 * ONE_ZKUSD);
+        assert_eq!(vault_risk_score(&healthy, BTC_PRICE, mcr_bps), 0);
+
+        // With room for one liquidation, the large vault goes first
+        let sp = StabilityPoolState { total_zkusd: 1_000_000 * ONE_ZKUSD, ..Default::default() };
+        let config = LiquidationConfig { max_liquidations_per_block: 1, ..create_test_config(false) };
+        let batch = process_batch_liquidation(&[tiny, small, large], &sp, &config).unwrap();
+        assert_eq!(batch.liquidations[0].result.vault_id, [2u8; 32]);
+        assert_eq!(batch.deferred, 2);
+    }
+
+    #[test]
+    fn test_projection_around_liquidation_price() {
+        // 1.5 BTC against 50,000 zkUSD, a year of 1% interest owed
+        let vault = create_test_vault(150_000_000, 50_000 * ONE_ZKUSD);
+        let block = vault.last_updated + 52_560;
+        let mcr_bps = MCR * BPS_DENOMINATOR / 100;
+        let price = crate::math::liquidation_price(Sats(vault.collateral), ZkUsd(vault.debt)).unwrap();
+
+        // At the liquidation price the vault survives, though the interest
+        // owed already puts it below MCR until the owner tops up
+        let at = project_vault_at_price(&vault, price, mcr_bps, block).unwrap();
+        assert_eq!(at.debt_with_interest, 50_500 * ONE_ZKUSD);
+        assert!(!at.liquidatable);
+        assert!(at.icr_bps < mcr_bps);
+        assert!(at.collateral_to_add > 0);
+
+        let topped_up = |extra: u64| {
+            let vault = Vault { collateral: vault.collateral + extra, ..vault.clone() };
+            project_vault_at_price(&vault, price, mcr_bps, block).unwrap().icr_bps
+        };
+        assert!(topped_up(at.collateral_to_add) >= mcr_bps);
+        assert!(topped_up(at.collateral_to_add - 1) < mcr_bps);
+
+        // Just below it the vault is liquidatable
+        let below = project_vault_at_price(&vault, price - 1, mcr_bps, block).unwrap();
+        assert!(below.liquidatable);
+        assert!(below.collateral_to_add > at.collateral_to_add);
+
+        // Well above it nothing is needed
+        let above = project_vault_at_price(&vault, BTC_PRICE, mcr_bps, block).unwrap();
+        assert!(!above.liquidatable);
+        assert_eq!(above.collateral_to_add, 0);
+        assert_eq!(above.icr_bps, 150_000_000 * 100_000 * BPS_DENOMINATOR / (50_500 * ONE_ZKUSD));
+
+        // A vault no longer active is never liquidatable
+        let closed = Vault { status: VaultStatus::Closed, ..vault };
+        assert!(!project_vault_at_price(&closed, price - 1, mcr_bps, block).unwrap().liquidatable);
+    }
+
     #[test]
     fn test_partial_offset_with_redistribution() {
         // 0.98 BTC at $100k = $98k collateral, $90k debt => ICR ~109% (below MCR 110%)
@@ -457,17 +709,108 @@ mod tests {
         assert!(result.result.collateral_surplus > 0);
     }
 
+    #[test]
+    fn test_whale_surplus_at_max_price() {
+        use crate::constants::oracle::DEFAULT_MAX_REASONABLE_PRICE as MAX_PRICE;
+
+        // 2,000 BTC at $10M ($20B) backing ~$15.4B of debt: ICR 130%
+        let collateral = 2_000 * ONE_BTC;
+        let debt = 20_000_000_000 * ONE_ZKUSD / 13 * 10;
+        let vault = create_test_vault(collateral, debt);
+        let sp = StabilityPoolState { total_zkusd: debt, ..Default::default() };
+        let config = LiquidationConfig { btc_price: MAX_PRICE, ..create_test_config(true) };
+
+        let result = process_liquidation(&vault, &sp, &config).unwrap().result;
+
+        // The owner keeps what exceeds 110% of the debt, less the bonus
+        let needed = min_collateral_for_debt(ZkUsd(debt), MAX_PRICE).unwrap().into_inner();
+        assert_eq!(result.collateral_surplus, collateral - result.liquidator_bonus - needed);
+        assert!(result.collateral_to_sp >= needed);
+
+        // Saturating u64 math put the requirement at a few thousand sats
+        // and handed nearly the whole vault back as surplus
+        let saturated = debt.saturating_mul(MCR).saturating_mul(ONE_BTC) / 100 / MAX_PRICE;
+        assert!(saturated < ONE_BTC);
+        assert!(result.collateral_surplus < collateral / 5);
+    }
+
+    #[test]
+    fn test_recovery_bonus_reduction_returned_as_surplus() {
+        let vault = create_test_vault(130_000_000, 100_000 * ONE_ZKUSD);
+        let sp = StabilityPoolState {
+            total_zkusd: 200_000 * ONE_ZKUSD,
+            ..Default::default()
+        };
+        let full_bonus = LiquidationConfig {
+            recovery_liquidator_bonus_bps: LIQUIDATOR_BONUS_BPS,
+            ..create_test_config(true)
+        };
+
+        let reduced = process_liquidation(&vault, &sp, &create_test_config(true)).unwrap().result;
+        let full = process_liquidation(&vault, &sp, &full_bonus).unwrap().result;
+
+        // 0.25% instead of 0.5% of 1.3 BTC, and the owner keeps the difference
+        assert_eq!(reduced.liquidator_bonus, 325_000);
+        assert_eq!(full.liquidator_bonus, 650_000);
+        assert_eq!(reduced.collateral_surplus - full.collateral_surplus, 325_000);
+        assert_eq!(reduced.collateral_to_sp, full.collateral_to_sp);
+
+        // The recovery bonus never exceeds the normal one
+        let inflated = LiquidationConfig {
+            recovery_liquidator_bonus_bps: 10 * LIQUIDATOR_BONUS_BPS,
+            ..create_test_config(true)
+        };
+        assert_eq!(process_liquidation(&vault, &sp, &inflated).unwrap().result.liquidator_bonus, 650_000);
+    }
+
     #[test]
     fn test_redistribution_shares() {
+        let recipient = create_test_vault(10 * ONE_BTC, 0);
         let (debt_share, coll_share) = calculate_redistribution_shares(
             10_000 * ONE_ZKUSD, // debt to redistribute
             ONE_BTC,            // collateral to redistribute
-            10 * ONE_BTC,       // recipient has 10 BTC
+            &recipient,         // recipient has held 10 BTC all window
             100 * ONE_BTC,      // total system has 100 BTC
+            1000,
         );
 
         // Recipient should get 10% of redistributed amounts
         assert_eq!(debt_share, 1_000 * ONE_ZKUSD);
         assert_eq!(coll_share, ONE_BTC / 10);
     }
+
+    #[test]
+    fn test_redistribution_share_ignores_collateral_spike() {
+        // Two vaults holding 1 BTC each for a full window
+        let steady = Vault { twa_collateral: ONE_BTC, ..create_test_vault(ONE_BTC, 0) };
+
+        // One adds 9 BTC a block before the liquidation
+        let mut spiking = steady.averaged_at(999);
+        spiking.collateral += 9 * ONE_BTC;
+        let vaults = [spiking.clone(), steady.clone()];
+        let total = total_twa_collateral(&vaults, 1000);
+
+        // Its average moved by 9 BTC / 144, not to 10 BTC
+        assert_eq!(spiking.twa_collateral_at(1000), ONE_BTC + 9 * ONE_BTC / 144);
+        assert_eq!(total, 2 * ONE_BTC + 9 * ONE_BTC / 144);
+
+        let (spike_debt, _) = calculate_redistribution_shares(10_000 * ONE_ZKUSD, ONE_BTC, &spiking, total, 1000);
+        let (steady_debt, _) = calculate_redistribution_shares(10_000 * ONE_ZKUSD, ONE_BTC, &steady, total, 1000);
+
+        // ~51.5% rather than the 10/11 its spot collateral would give
+        assert!(spike_debt < 5_200 * ONE_ZKUSD);
+        assert!(steady_debt > 4_800 * ONE_ZKUSD);
+        assert!(spike_debt + steady_debt <= 10_000 * ONE_ZKUSD);
+    }
+
+    #[test]
+    fn test_twa_collateral_ramps_over_window() {
+        let vault = Vault::new([1u8; 32], [2u8; 32], 144 * ONE_BTC, 0, 100);
+
+        // A new vault starts from zero and reaches its collateral after a window
+        assert_eq!(vault.twa_collateral_at(100), 0);
+        assert_eq!(vault.twa_collateral_at(101), ONE_BTC);
+        assert_eq!(vault.twa_collateral_at(100 + 144), 144 * ONE_BTC);
+        assert_eq!(vault.twa_collateral_at(100 + 1_000), 144 * ONE_BTC);
+    }
 }
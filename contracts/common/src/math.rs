@@ -4,43 +4,85 @@
 
 use crate::errors::{ZkUsdError, ZkUsdResult};
 use crate::constants::{precision, ratios, token, fees};
+use crate::types::{EpochSnapshot, StabilityDeposit, StabilityPoolState, VaultStats};
+use crate::units::{Sats, ZkUsd};
+
+/// A USD value (8 decimals) or other intermediate held at 128 bits
+///
+/// Collateral value is `sats * price / 1e8`, which exceeds u64 for a large
+/// enough vault at a high enough price. Ratio math keeps values in this
+/// type end to end and narrows only at the comparison boundary, through
+/// `to_u64` (reject) or `saturating_u64` (clamp).
+pub type ValueU128 = u128;
+
+/// Narrow a 128-bit intermediate to u64, `Overflow` if it does not fit
+pub fn to_u64(value: ValueU128) -> ZkUsdResult<u64> {
+    u64::try_from(value).map_err(|_| ZkUsdError::Overflow)
+}
+
+/// Narrow a 128-bit intermediate to u64, clamping at `u64::MAX`
+///
+/// For ratios and thresholds where "larger than representable" compares
+/// the same as `u64::MAX`. Never a plain `as u64`, which keeps the low
+/// 64 bits and can turn a huge value into a small one.
+pub fn saturating_u64(value: ValueU128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+/// USD value of `collateral` at `btc_price`, rounded down, at 128 bits
+pub fn collateral_value(collateral: Sats, btc_price: u64) -> ZkUsdResult<ValueU128> {
+    (collateral.into_inner() as u128)
+        .checked_mul(btc_price as u128)
+        .ok_or(ZkUsdError::Overflow)?
+        .checked_div(token::ONE as u128)
+        .ok_or(ZkUsdError::DivisionByZero)
+}
 
 /// Calculate Individual Collateral Ratio (ICR)
 ///
 /// ICR = (collateral_value_usd * 100) / debt
 ///
 /// # Arguments
-/// * `collateral_sats` - Collateral in satoshis
+/// * `collateral` - Collateral in satoshis
 /// * `debt` - Debt in zkUSD base units (8 decimals)
 /// * `btc_price` - BTC price in USD with 8 decimals
 ///
 /// # Returns
 /// ICR as a percentage (e.g., 150 = 150%)
-pub fn calculate_icr(collateral_sats: u64, debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
+pub fn calculate_icr(collateral: Sats, debt: ZkUsd, btc_price: u64) -> ZkUsdResult<u64> {
+    icr_at_scale(collateral, debt, btc_price, precision::PERCENT_PRECISION)
+}
+
+/// Calculate ICR in basis points (e.g., 15_000 = 150%)
+///
+/// Finer-grained than `calculate_icr`, for thresholds below a whole percent.
+pub fn calculate_icr_bps(collateral: Sats, debt: ZkUsd, btc_price: u64) -> ZkUsdResult<u64> {
+    icr_at_scale(collateral, debt, btc_price, fees::BPS_DENOMINATOR)
+}
+
+/// ICR scaled so that 100% equals `scale`
+fn icr_at_scale(collateral: Sats, debt: ZkUsd, btc_price: u64, scale: u64) -> ZkUsdResult<u64> {
+    let debt = debt.into_inner();
     if debt == 0 {
         return Ok(u64::MAX); // Infinite ratio for zero debt
     }
 
     // collateral_value_usd = collateral_sats * btc_price / 1e8
-    let collateral_value = (collateral_sats as u128)
-        .checked_mul(btc_price as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(token::ONE as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+    let collateral_value = collateral_value(collateral, btc_price)?;
 
-    // ICR = collateral_value * 100 / debt
-    let icr = collateral_value
-        .checked_mul(precision::PERCENT_PRECISION as u128)
+    // ICR = collateral_value * scale / debt
+    let icr: ValueU128 = collateral_value
+        .checked_mul(scale as u128)
         .ok_or(ZkUsdError::Overflow)?
         .checked_div(debt as u128)
         .ok_or(ZkUsdError::DivisionByZero)?;
 
-    // Cap at u64::MAX if somehow exceeds
-    Ok(icr.min(u64::MAX as u128) as u64)
+    // A ratio past u64::MAX compares like an infinite one
+    Ok(saturating_u64(icr))
 }
 
 /// Calculate Total Collateral Ratio (TCR) for the entire system
-pub fn calculate_tcr(total_collateral: u64, total_debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
+pub fn calculate_tcr(total_collateral: Sats, total_debt: ZkUsd, btc_price: u64) -> ZkUsdResult<u64> {
     calculate_icr(total_collateral, total_debt, btc_price)
 }
 
@@ -93,13 +135,63 @@ pub fn calculate_borrowing_fee(debt: u64, base_rate: u64) -> ZkUsdResult<u64> {
         .min(fees::MAX_BORROWING_FEE_BPS);
 
     // fee = debt * fee_rate / 10000
-    let fee = (debt as u128)
-        .checked_mul(fee_rate as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(fees::BPS_DENOMINATOR as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+    safe_mul_div(debt, fee_rate, fees::BPS_DENOMINATOR)
+}
+
+/// Borrowing fee discount a vault's lifetime stats qualify for (BPS)
+///
+/// Vaults that have carried debt for `LOYALTY_THRESHOLD_BLOCKS` get
+/// `LOYALTY_DISCOUNT_BPS` off their borrowing fees. Only applied when the
+/// protocol enables loyalty discounts.
+pub fn loyalty_discount_bps(stats: &VaultStats) -> u64 {
+    if stats.blocks_active >= fees::LOYALTY_THRESHOLD_BLOCKS {
+        fees::LOYALTY_DISCOUNT_BPS
+    } else {
+        0
+    }
+}
+
+/// Borrowing fee after the loyalty discount `stats` qualify for
+pub fn apply_loyalty_discount(fee: u64, stats: &VaultStats) -> ZkUsdResult<u64> {
+    let discount = safe_mul_div(fee, loyalty_discount_bps(stats), fees::BPS_DENOMINATOR)?;
+    safe_sub(fee, discount)
+}
+
+/// Borrowing fee after a stability depositor discount of `discount_bps`,
+/// capped at `MAX_DEPOSITOR_DISCOUNT_BPS` so the fee stays positive
+pub fn apply_depositor_discount(fee: u64, discount_bps: u64) -> ZkUsdResult<u64> {
+    let discount_bps = discount_bps.min(fees::MAX_DEPOSITOR_DISCOUNT_BPS);
+    let discount = safe_mul_div(fee, discount_bps, fees::BPS_DENOMINATOR)?;
+    safe_sub(fee, discount)
+}
+
+/// Interest rate surcharge for protecting `protected_bps` of a vault's
+/// collateral from redemptions
+pub fn calculate_protection_rate_bump(protected_bps: u64) -> u64 {
+    protected_bps.saturating_mul(fees::PROTECTION_RATE_BPS_PER_100) / 100
+}
+
+/// Base rate after `blocks_elapsed` blocks of decay
+///
+/// Halves every `BASE_RATE_HALF_LIFE_BLOCKS`, compounding per block, and
+/// never decays below the minimum borrowing fee.
+pub fn decay_base_rate(base_rate: u64, blocks_elapsed: u64) -> ZkUsdResult<u64> {
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    // factor^blocks_elapsed by repeated squaring
+    let mut factor = ONE;
+    let mut square = fees::BASE_RATE_DECAY_FACTOR;
+    let mut exponent = blocks_elapsed;
+    while exponent > 0 && factor > 0 {
+        if exponent & 1 == 1 {
+            factor = safe_mul_div_u128(factor, square, ONE)?;
+        }
+        square = safe_mul_div_u128(square, square, ONE)?;
+        exponent >>= 1;
+    }
 
-    Ok(fee as u64)
+    let decayed = to_u64(safe_mul_div_u128(u128::from(base_rate), factor, ONE)?)?;
+    Ok(decayed.max(fees::MIN_BORROWING_FEE_BPS.min(base_rate)))
 }
 
 /// Calculate redemption fee (variable rate - Liquity style)
@@ -114,13 +206,7 @@ pub fn calculate_redemption_fee(redeemed_amount: u64, base_rate: u64) -> ZkUsdRe
     // fee_rate = max(FLOOR, base_rate)
     let fee_rate = base_rate.max(fees::REDEMPTION_FEE_FLOOR_BPS);
 
-    let fee = (redeemed_amount as u128)
-        .checked_mul(fee_rate as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(fees::BPS_DENOMINATOR as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
-
-    Ok(fee as u64)
+    safe_mul_div(redeemed_amount, fee_rate, fees::BPS_DENOMINATOR)
 }
 
 /// Calculate fixed redemption fee (Mezo style - simpler, more predictable)
@@ -134,59 +220,106 @@ pub fn calculate_redemption_fee(redeemed_amount: u64, base_rate: u64) -> ZkUsdRe
 /// # Returns
 /// Fee amount in zkUSD base units (0.75% of redeemed amount)
 pub fn calculate_redemption_fee_fixed(redeemed_amount: u64) -> ZkUsdResult<u64> {
-    let fee = (redeemed_amount as u128)
-        .checked_mul(fees::REDEMPTION_FEE_FIXED_BPS as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(fees::BPS_DENOMINATOR as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
-
-    Ok(fee as u64)
+    safe_mul_div(redeemed_amount, fees::REDEMPTION_FEE_FIXED_BPS, fees::BPS_DENOMINATOR)
 }
 
 /// Calculate maximum debt for given collateral
 ///
 /// max_debt = collateral_value * 100 / MCR
-pub fn max_debt_for_collateral(collateral_sats: u64, btc_price: u64) -> ZkUsdResult<u64> {
-    // collateral_value_usd = collateral_sats * btc_price / 1e8
-    let collateral_value = (collateral_sats as u128)
-        .checked_mul(btc_price as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(token::ONE as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
-
-    // max_debt = collateral_value * 100 / MCR
-    let max_debt = collateral_value
-        .checked_mul(precision::PERCENT_PRECISION as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(ratios::MCR as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
-
-    Ok(max_debt as u64)
+///
+/// Rounds down: the capacity is what the user may borrow.
+pub fn max_debt_for_collateral(collateral: Sats, btc_price: u64) -> ZkUsdResult<ZkUsd> {
+    let collateral_value = btc_to_zkusd_floor(collateral, btc_price)?;
+    safe_mul_div(collateral_value.into_inner(), precision::PERCENT_PRECISION, ratios::MCR).map(ZkUsd)
 }
 
 /// Calculate minimum collateral for given debt
 ///
 /// min_collateral = debt * MCR / 100 / btc_price * 1e8
-pub fn min_collateral_for_debt(debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
-    if btc_price == 0 {
+///
+/// Rounds up: the collateral is what the user must put up.
+pub fn min_collateral_for_debt(debt: ZkUsd, btc_price: u64) -> ZkUsdResult<Sats> {
+    let required_value = safe_mul_div_ceil(debt.into_inner(), ratios::MCR, precision::PERCENT_PRECISION)?;
+    zkusd_to_btc_ceil(ZkUsd(required_value), btc_price)
+}
+
+/// BTC price (8 decimals) at which a vault's ICR falls to MCR
+///
+/// liquidation_price = debt * MCR / 100 * 1e8 / collateral
+///
+/// Rounds up, so the vault is safe at any price at or above the result.
+/// Zero for a vault without debt.
+pub fn liquidation_price(collateral: Sats, debt: ZkUsd) -> ZkUsdResult<u64> {
+    let required_value = (debt.into_inner() as u128) * ratios::MCR as u128 * token::ONE as u128;
+    let scale = precision::PERCENT_PRECISION as u128 * collateral.into_inner() as u128;
+    if scale == 0 {
         return Err(ZkUsdError::DivisionByZero);
     }
+    to_u64(required_value.div_ceil(scale))
+}
 
-    // Required USD value = debt * MCR / 100
-    let required_usd = (debt as u128)
-        .checked_mul(ratios::MCR as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(precision::PERCENT_PRECISION as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+// ============ Quote Conversions ============
+//
+// The protocol's only BTC/zkUSD conversions. Each comes in two rounding
+// directions: `_floor` where the result is paid to the user (or values
+// what the user holds), `_ceil` where it is charged to the user (or is a
+// requirement the user must meet). All use a 128-bit intermediate.
 
-    // min_collateral = required_usd * 1e8 / btc_price
-    let min_collateral = required_usd
-        .checked_mul(token::ONE as u128)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(btc_price as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+/// Convert BTC (satoshis) to zkUSD at the given price, rounded down
+///
+/// zkusd = sats * btc_price / 1e8
+///
+/// Collateral is never valued above its exact USD worth. A zero price
+/// values anything at zero.
+pub fn btc_to_zkusd_floor(sats: Sats, btc_price: u64) -> ZkUsdResult<ZkUsd> {
+    safe_mul_div(sats.into_inner(), btc_price, token::ONE).map(ZkUsd)
+}
 
-    Ok(min_collateral as u64)
+/// Convert BTC (satoshis) to zkUSD at the given price, rounded up
+///
+/// For zkUSD charged against BTC, so dust never goes uncharged.
+pub fn btc_to_zkusd_ceil(sats: Sats, btc_price: u64) -> ZkUsdResult<ZkUsd> {
+    safe_mul_div_ceil(sats.into_inner(), btc_price, token::ONE).map(ZkUsd)
+}
+
+/// Convert zkUSD to BTC (satoshis) at the given price, rounded down
+///
+/// sats = zkusd * 1e8 / btc_price
+///
+/// A redeemer never receives more BTC than the exact value of the zkUSD
+/// burned. `DivisionByZero` at a zero price.
+pub fn zkusd_to_btc_floor(amount: ZkUsd, btc_price: u64) -> ZkUsdResult<Sats> {
+    safe_mul_div(amount.into_inner(), token::ONE, btc_price).map(Sats)
+}
+
+/// Convert zkUSD to BTC (satoshis) at the given price, rounded up
+///
+/// For BTC the user must put up, so a requirement is never undershot.
+pub fn zkusd_to_btc_ceil(amount: ZkUsd, btc_price: u64) -> ZkUsdResult<Sats> {
+    safe_mul_div_ceil(amount.into_inner(), token::ONE, btc_price).map(Sats)
+}
+
+/// 1e8 * 1e8 * 1e8: sats per BTC, price decimals and result decimals
+const PRICE_INVERSE_NUMERATOR: u128 = (token::ONE as u128) * (token::ONE as u128) * (token::ONE as u128);
+
+/// Sats per zkUSD with 8 decimals, rounded down
+///
+/// The inverse quote of `btc_price` (zkUSD per BTC with 8 decimals): at
+/// $100,000 one zkUSD is 1,000 sats, returned as `1_000_00000000`.
+/// `DivisionByZero` at a zero price, `Overflow` below about $0.00054.
+pub fn price_inverse_floor(btc_price: u64) -> ZkUsdResult<u64> {
+    if btc_price == 0 {
+        return Err(ZkUsdError::DivisionByZero);
+    }
+    u64::try_from(PRICE_INVERSE_NUMERATOR / btc_price as u128).map_err(|_| ZkUsdError::Overflow)
+}
+
+/// Sats per zkUSD with 8 decimals, rounded up
+pub fn price_inverse_ceil(btc_price: u64) -> ZkUsdResult<u64> {
+    if btc_price == 0 {
+        return Err(ZkUsdError::DivisionByZero);
+    }
+    u64::try_from(PRICE_INVERSE_NUMERATOR.div_ceil(btc_price as u128)).map_err(|_| ZkUsdError::Overflow)
 }
 
 /// Calculate compounded deposit value in Stability Pool
@@ -220,15 +353,36 @@ pub fn calculate_compounded_deposit(
         return 0;
     }
 
+    calculate_compounded_deposit_with_scale_factor(
+        crate::constants::stability_pool::SCALE_FACTOR,
+        initial_deposit,
+        snapshot_p,
+        current_p,
+        snapshot_scale,
+        current_scale,
+    )
+}
+
+/// Compounded deposit value within one epoch, for P values expressed in
+/// `scale_factor` rather than the current `SCALE_FACTOR`
+///
+/// Used to value deposits of a pool built with a different precision
+/// (see `migrate_deposit` in the stability pool).
+pub fn calculate_compounded_deposit_with_scale_factor(
+    scale_factor: u128,
+    initial_deposit: u64,
+    snapshot_p: u128,
+    current_p: u128,
+    snapshot_scale: u64,
+    current_scale: u64,
+) -> u64 {
     // Validate snapshot_p is not zero (would indicate corrupt state)
-    if snapshot_p == 0 {
+    if snapshot_p == 0 || scale_factor == 0 {
         // This should never happen in production - indicates corrupt state
         // Return 0 rather than panicking to allow graceful degradation
         return 0;
     }
 
-    let scale_factor = crate::constants::stability_pool::SCALE_FACTOR;
-
     // Calculate scale difference
     let scale_diff = current_scale.saturating_sub(snapshot_scale);
 
@@ -243,21 +397,20 @@ pub fn calculate_compounded_deposit(
         current_p
             .saturating_mul(scale_factor)
             / snapshot_p // Safe: snapshot_p validated above
-            / scale_factor // Safe: scale_factor is constant > 0
+            / scale_factor // Safe: scale_factor validated above
     } else {
         // More than 1 scale change = deposit effectively zeroed
         // This happens when pool size decreased dramatically
         0
     };
 
-    // compounded = initial * p_ratio / SCALE_FACTOR
-    // Safe: scale_factor is constant > 0
+    // compounded = initial * p_ratio / scale_factor
     let result = (initial_deposit as u128)
         .saturating_mul(p_ratio)
         / scale_factor;
 
     // Safe truncation: result will always fit in u64 due to the division
-    result.min(u64::MAX as u128) as u64
+    saturating_u64(result)
 }
 
 /// Calculate BTC gain from Stability Pool
@@ -277,19 +430,105 @@ pub fn calculate_btc_gain(
     snapshot_s: u128,
     current_s: u128,
 ) -> u64 {
-    let scale_factor = crate::constants::stability_pool::SCALE_FACTOR;
+    calculate_btc_gain_with_scale_factor(
+        crate::constants::stability_pool::SCALE_FACTOR,
+        initial_deposit,
+        snapshot_s,
+        current_s,
+    )
+}
+
+/// BTC gain for S values expressed in `scale_factor`
+pub fn calculate_btc_gain_with_scale_factor(
+    scale_factor: u128,
+    initial_deposit: u64,
+    snapshot_s: u128,
+    current_s: u128,
+) -> u64 {
+    if scale_factor == 0 {
+        return 0;
+    }
 
     // Calculate difference in S (cumulative reward per unit)
     let s_diff = current_s.saturating_sub(snapshot_s);
 
-    // gain = initial * s_diff / SCALE_FACTOR
-    // Safe: SCALE_FACTOR is a constant > 0
+    // gain = initial * s_diff / scale_factor
     let result = (initial_deposit as u128)
         .saturating_mul(s_diff)
         / scale_factor;
 
     // Safe truncation: result will always fit in u64 due to the division
-    result.min(u64::MAX as u128) as u64
+    saturating_u64(result)
+}
+
+/// Calculate BTC gain of a deposit from a closed epoch
+///
+/// Uses the epoch's final S values, since the live `sum_s` has been reset.
+/// Gains at the deposit's scale are taken in full and the next scale's are
+/// divided by SCALE_FACTOR; deposits from any earlier scale earn nothing,
+/// as with `calculate_compounded_deposit`.
+pub fn calculate_epoch_btc_gain(
+    initial_deposit: u64,
+    snapshot_s: u128,
+    snapshot_scale: u64,
+    epoch: &EpochSnapshot,
+) -> u64 {
+    calculate_epoch_btc_gain_with_scale_factor(
+        crate::constants::stability_pool::SCALE_FACTOR,
+        initial_deposit,
+        snapshot_s,
+        snapshot_scale,
+        epoch,
+    )
+}
+
+/// Closed-epoch BTC gain for S values expressed in `scale_factor`
+pub fn calculate_epoch_btc_gain_with_scale_factor(
+    scale_factor: u128,
+    initial_deposit: u64,
+    snapshot_s: u128,
+    snapshot_scale: u64,
+    epoch: &EpochSnapshot,
+) -> u64 {
+    if snapshot_scale != epoch.final_scale || scale_factor == 0 {
+        return 0;
+    }
+
+    let first_portion = epoch.final_s_at_scale.saturating_sub(snapshot_s);
+    let second_portion = epoch.final_s_at_scale_plus_one / scale_factor;
+
+    calculate_btc_gain_with_scale_factor(
+        scale_factor,
+        initial_deposit,
+        0,
+        first_portion.saturating_add(second_portion),
+    )
+}
+
+/// Pending BTC gain of a stability deposit
+///
+/// Deposits from a closed epoch are paid from that epoch's snapshot.
+///
+/// # Errors
+/// - `EpochSnapshotEvicted` if the deposit's epoch is no longer retained
+pub fn calculate_pending_btc(deposit: &StabilityDeposit, state: &StabilityPoolState) -> ZkUsdResult<u64> {
+    if deposit.snapshot_epoch >= state.current_epoch {
+        return Ok(calculate_btc_gain(
+            deposit.initial_value,
+            deposit.snapshot_s,
+            state.sum_s,
+        ));
+    }
+
+    let epoch = state.epoch_snapshot(deposit.snapshot_epoch).ok_or(
+        ZkUsdError::EpochSnapshotEvicted { epoch: deposit.snapshot_epoch },
+    )?;
+    Ok(calculate_epoch_btc_gain(
+        deposit.initial_value,
+        deposit.snapshot_s,
+        deposit.snapshot_scale,
+        epoch,
+    ))
 }
 
 /// Safe addition with overflow check
@@ -297,6 +536,11 @@ pub fn safe_add(a: u64, b: u64) -> ZkUsdResult<u64> {
     a.checked_add(b).ok_or(ZkUsdError::Overflow)
 }
 
+/// Sum with overflow check
+pub fn safe_sum(amounts: impl IntoIterator<Item = u64>) -> ZkUsdResult<u64> {
+    amounts.into_iter().try_fold(0, safe_add)
+}
+
 /// Safe subtraction with underflow check
 pub fn safe_sub(a: u64, b: u64) -> ZkUsdResult<u64> {
     a.checked_sub(b).ok_or(ZkUsdError::Underflow)
@@ -308,11 +552,94 @@ pub fn safe_mul(a: u64, b: u64) -> ZkUsdResult<u128> {
 }
 
 /// Safe division with zero check
+///
+/// `Overflow` if the quotient does not fit in a u64.
 pub fn safe_div(a: u128, b: u64) -> ZkUsdResult<u64> {
     if b == 0 {
         return Err(ZkUsdError::DivisionByZero);
     }
-    Ok((a / b as u128) as u64)
+    to_u64(a / b as u128)
+}
+
+/// `a * b / c` with a 128-bit intermediate
+///
+/// # Rounding
+/// Truncates toward zero. Use `safe_mul_div_ceil` where rounding down
+/// would favor the user over the protocol.
+///
+/// # Errors
+/// `DivisionByZero` if `c` is 0, `Overflow` if the result does not fit in
+/// a u64 (the product itself always fits in a u128).
+pub fn safe_mul_div(a: u64, b: u64, c: u64) -> ZkUsdResult<u64> {
+    if c == 0 {
+        return Err(ZkUsdError::DivisionByZero);
+    }
+    u64::try_from(a as u128 * b as u128 / c as u128).map_err(|_| ZkUsdError::Overflow)
+}
+
+/// `a * b / c` with a 128-bit intermediate, rounded up
+///
+/// Same errors as `safe_mul_div`; exact quotients are not rounded.
+pub fn safe_mul_div_ceil(a: u64, b: u64, c: u64) -> ZkUsdResult<u64> {
+    if c == 0 {
+        return Err(ZkUsdError::DivisionByZero);
+    }
+    u64::try_from((a as u128 * b as u128).div_ceil(c as u128)).map_err(|_| ZkUsdError::Overflow)
+}
+
+/// `a * b / c` over u128 operands, for Stability Pool P and S values
+///
+/// Truncates toward zero. `Overflow` if the product exceeds u128,
+/// `DivisionByZero` if `c` is 0.
+pub fn safe_mul_div_u128(a: u128, b: u128, c: u128) -> ZkUsdResult<u128> {
+    if c == 0 {
+        return Err(ZkUsdError::DivisionByZero);
+    }
+    a.checked_mul(b).map(|product| product / c).ok_or(ZkUsdError::Overflow)
+}
+
+/// Untyped `u64` signatures of the unit-typed functions above
+///
+/// Kept for one release so downstream code can migrate gradually; wrap
+/// amounts in `Sats` / `ZkUsd` and call the functions in `math` instead.
+pub mod compat {
+    use super::*;
+
+    /// `calculate_icr` on raw amounts
+    #[deprecated(note = "use math::calculate_icr with Sats / ZkUsd")]
+    pub fn calculate_icr(collateral_sats: u64, debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::calculate_icr(Sats(collateral_sats), ZkUsd(debt), btc_price)
+    }
+
+    /// `calculate_tcr` on raw amounts
+    #[deprecated(note = "use math::calculate_tcr with Sats / ZkUsd")]
+    pub fn calculate_tcr(total_collateral: u64, total_debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::calculate_tcr(Sats(total_collateral), ZkUsd(total_debt), btc_price)
+    }
+
+    /// `max_debt_for_collateral` on raw amounts
+    #[deprecated(note = "use math::max_debt_for_collateral with Sats")]
+    pub fn max_debt_for_collateral(collateral_sats: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::max_debt_for_collateral(Sats(collateral_sats), btc_price).map(ZkUsd::into_inner)
+    }
+
+    /// `min_collateral_for_debt` on raw amounts
+    #[deprecated(note = "use math::min_collateral_for_debt with ZkUsd")]
+    pub fn min_collateral_for_debt(debt: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::min_collateral_for_debt(ZkUsd(debt), btc_price).map(Sats::into_inner)
+    }
+
+    /// `btc_to_zkusd_floor` on raw amounts
+    #[deprecated(note = "use math::btc_to_zkusd_floor with Sats")]
+    pub fn btc_to_zkusd(sats: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::btc_to_zkusd_floor(Sats(sats), btc_price).map(ZkUsd::into_inner)
+    }
+
+    /// `zkusd_to_btc_floor` on raw amounts
+    #[deprecated(note = "use math::zkusd_to_btc_floor with ZkUsd")]
+    pub fn zkusd_to_btc(amount: u64, btc_price: u64) -> ZkUsdResult<u64> {
+        super::zkusd_to_btc_floor(ZkUsd(amount), btc_price).map(Sats::into_inner)
+    }
 }
 
 #[cfg(test)]
@@ -326,21 +653,65 @@ mod tests {
     #[test]
     fn test_icr_calculation() {
         // 1 BTC ($100k) backing 50k zkUSD = 200% ICR
-        let icr = calculate_icr(ONE_BTC, 50_000 * ONE_ZKUSD, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(ONE_BTC), ZkUsd(50_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, 200);
 
         // 1 BTC backing 100k zkUSD = 100% ICR
-        let icr = calculate_icr(ONE_BTC, 100_000 * ONE_ZKUSD, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(ONE_BTC), ZkUsd(100_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, 100);
 
         // 1.5 BTC backing 100k zkUSD = 150% ICR
-        let icr = calculate_icr(150_000_000, 100_000 * ONE_ZKUSD, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(150_000_000), ZkUsd(100_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, 150);
     }
 
+    #[test]
+    fn test_whale_vault_at_max_price() {
+        use crate::constants::oracle::DEFAULT_MAX_REASONABLE_PRICE as MAX_PRICE;
+
+        // 2,000 BTC at $10M: $20B of collateral against $16.67B of debt
+        let collateral = Sats(2_000 * ONE_BTC);
+        let debt = ZkUsd(20_000_000_000 * ONE_ZKUSD / 12 * 10);
+        assert_eq!(collateral_value(collateral, MAX_PRICE).unwrap(), 20_000_000_000 * ONE_ZKUSD as u128);
+        assert_eq!(calculate_icr(collateral, debt, MAX_PRICE).unwrap(), 120);
+        assert_eq!(calculate_icr_bps(collateral, debt, MAX_PRICE).unwrap(), 12_000);
+
+        // Borrowing up to MCR is accepted, one unit past it is not
+        let max_debt = max_debt_for_collateral(collateral, MAX_PRICE).unwrap();
+        assert!(calculate_icr(collateral, max_debt, MAX_PRICE).unwrap() >= ratios::MCR);
+        assert!(calculate_icr_bps(collateral, ZkUsd(max_debt.into_inner() + ONE_ZKUSD), MAX_PRICE).unwrap() < 11_000);
+    }
+
+    #[test]
+    fn test_value_past_u64_is_not_truncated() {
+        use crate::constants::oracle::DEFAULT_MAX_REASONABLE_PRICE as MAX_PRICE;
+
+        // 20,000 BTC at $10M is $200B: past u64::MAX in 8-decimal units
+        let collateral = Sats(20_000 * ONE_BTC);
+        let value = collateral_value(collateral, MAX_PRICE).unwrap();
+        assert!(value > u64::MAX as u128);
+
+        // A plain `as u64` keeps the low bits and values the vault at ~8%
+        let truncated = value as u64;
+        assert!((truncated as u128) < value / 12);
+        let debt = ZkUsd(100_000_000_000 * ONE_ZKUSD); // $100B
+        let truncated_icr = truncated as u128 * 100 / debt.into_inner() as u128;
+        assert!(truncated_icr < ratios::MCR as u128);
+
+        // The 128-bit path prices it correctly at 200%
+        assert_eq!(calculate_icr(collateral, debt, MAX_PRICE).unwrap(), 200);
+
+        // Results that must be u64 are rejected, never wrapped
+        assert_eq!(to_u64(value), Err(ZkUsdError::Overflow));
+        assert_eq!(saturating_u64(value), u64::MAX);
+        assert_eq!(btc_to_zkusd_floor(collateral, MAX_PRICE), Err(ZkUsdError::Overflow));
+        assert_eq!(max_debt_for_collateral(collateral, MAX_PRICE), Err(ZkUsdError::Overflow));
+        assert_eq!(safe_div(value, 1), Err(ZkUsdError::Overflow));
+    }
+
     #[test]
     fn test_icr_zero_debt() {
-        let icr = calculate_icr(ONE_BTC, 0, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(ONE_BTC), ZkUsd(0), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, u64::MAX);
     }
 
@@ -379,18 +750,68 @@ mod tests {
         assert_eq!(fee, 1_000 * ONE_ZKUSD);
     }
 
+    #[test]
+    fn test_base_rate_decay() {
+        let half_life = fees::BASE_RATE_HALF_LIFE_BLOCKS;
+        assert_eq!(decay_base_rate(400, 0).unwrap(), 400);
+        // One half-life halves it (within rounding of the per-block factor)
+        assert!((199..=200).contains(&decay_base_rate(400, half_life).unwrap()));
+        assert!((99..=100).contains(&decay_base_rate(400, 2 * half_life).unwrap()));
+        // Floored at the minimum borrowing fee
+        assert_eq!(decay_base_rate(400, 100 * half_life).unwrap(), fees::MIN_BORROWING_FEE_BPS);
+        assert_eq!(decay_base_rate(fees::MIN_BORROWING_FEE_BPS, u64::MAX).unwrap(), fees::MIN_BORROWING_FEE_BPS);
+    }
+
+    #[test]
+    fn test_loyalty_discount_at_threshold() {
+        let stats = |blocks_active| VaultStats { blocks_active, ..VaultStats::default() };
+        let fee = 500 * ONE_ZKUSD;
+
+        assert_eq!(loyalty_discount_bps(&stats(fees::LOYALTY_THRESHOLD_BLOCKS - 1)), 0);
+        assert_eq!(apply_loyalty_discount(fee, &stats(fees::LOYALTY_THRESHOLD_BLOCKS - 1)).unwrap(), fee);
+
+        // Exactly at the threshold: 10% off
+        assert_eq!(loyalty_discount_bps(&stats(fees::LOYALTY_THRESHOLD_BLOCKS)), 1_000);
+        assert_eq!(apply_loyalty_discount(fee, &stats(fees::LOYALTY_THRESHOLD_BLOCKS)).unwrap(), 450 * ONE_ZKUSD);
+    }
+
+    #[test]
+    fn test_depositor_discount_capped() {
+        let fee = 500 * ONE_ZKUSD;
+
+        assert_eq!(apply_depositor_discount(fee, 0).unwrap(), fee);
+        assert_eq!(apply_depositor_discount(fee, 2_000).unwrap(), 400 * ONE_ZKUSD);
+
+        // Past the cap (even past 100%) the discount stops at half the fee
+        assert_eq!(apply_depositor_discount(fee, fees::MAX_DEPOSITOR_DISCOUNT_BPS).unwrap(), 250 * ONE_ZKUSD);
+        assert_eq!(apply_depositor_discount(fee, 20_000).unwrap(), 250 * ONE_ZKUSD);
+    }
+
     #[test]
     fn test_max_debt_for_collateral() {
         // 1 BTC at $100k with 110% MCR = max ~90,909 zkUSD
-        let max_debt = max_debt_for_collateral(ONE_BTC, BTC_PRICE_100K).unwrap();
-        assert_eq!(max_debt, 90909_09090909); // ~90,909 zkUSD
+        let max_debt = max_debt_for_collateral(Sats(ONE_BTC), BTC_PRICE_100K).unwrap();
+        assert_eq!(max_debt, ZkUsd(90909_09090909)); // ~90,909 zkUSD
     }
 
     #[test]
     fn test_min_collateral_for_debt() {
         // 50,000 zkUSD needs at least 0.55 BTC at $100k (110% MCR)
-        let min_coll = min_collateral_for_debt(50_000 * ONE_ZKUSD, BTC_PRICE_100K).unwrap();
-        assert_eq!(min_coll, 55_000_000); // 0.55 BTC
+        let min_coll = min_collateral_for_debt(ZkUsd(50_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap();
+        assert_eq!(min_coll, Sats(55_000_000)); // 0.55 BTC
+    }
+
+    #[test]
+    fn test_liquidation_price() {
+        // 50,000 zkUSD against 0.55 BTC sits exactly at MCR at $100k
+        let price = liquidation_price(Sats(55_000_000), ZkUsd(50_000 * ONE_ZKUSD)).unwrap();
+        assert_eq!(price, BTC_PRICE_100K);
+        assert_eq!(calculate_icr(Sats(55_000_000), ZkUsd(50_000 * ONE_ZKUSD), price), Ok(ratios::MCR));
+
+        // Rounded up; no debt never liquidates; no collateral has no price
+        assert_eq!(liquidation_price(Sats(3), ZkUsd(1)), Ok(36_666_667));
+        assert_eq!(liquidation_price(Sats(ONE_BTC), ZkUsd(0)), Ok(0));
+        assert_eq!(liquidation_price(Sats(0), ZkUsd(1)), Err(ZkUsdError::DivisionByZero));
     }
 
     #[test]
@@ -424,4 +845,138 @@ mod tests {
         // Fixed is more predictable but slightly higher
         assert!(fixed_fee > var_fee);
     }
+
+    #[test]
+    fn test_btc_zkusd_conversion() {
+        assert_eq!(btc_to_zkusd_floor(Sats(ONE_BTC), BTC_PRICE_100K).unwrap(), ZkUsd(100_000 * ONE_ZKUSD));
+        assert_eq!(zkusd_to_btc_floor(ZkUsd(50_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap(), Sats(ONE_BTC / 2));
+        assert_eq!(btc_to_zkusd_floor(Sats(ONE_BTC), 0).unwrap(), ZkUsd::ZERO);
+        assert_eq!(zkusd_to_btc_floor(ZkUsd(ONE_ZKUSD), 0), Err(ZkUsdError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_btc_zkusd_conversion_rounds_down() {
+        // $30,000.00000001 per BTC: 1 sat is worth 0.0003 zkUSD + dust
+        let price = 30_000 * ONE_ZKUSD + 1;
+        assert_eq!(btc_to_zkusd_floor(Sats(1), price).unwrap(), ZkUsd(30_000));
+
+        // 1 base unit of zkUSD is worth 1/30000 sat: redeemer gets nothing
+        assert_eq!(zkusd_to_btc_floor(ZkUsd(1), price).unwrap(), Sats::ZERO);
+        // 1 zkUSD is worth 3333.33 sats: rounded down to 3333
+        assert_eq!(zkusd_to_btc_floor(ZkUsd(ONE_ZKUSD), price).unwrap(), Sats(3_333));
+    }
+
+    #[test]
+    fn test_btc_zkusd_conversion_overflow() {
+        assert_eq!(btc_to_zkusd_floor(Sats(u64::MAX), u64::MAX), Err(ZkUsdError::Overflow));
+        assert_eq!(zkusd_to_btc_floor(ZkUsd(u64::MAX), 1), Err(ZkUsdError::Overflow));
+    }
+
+    #[test]
+    fn test_btc_zkusd_round_trip() {
+        let prices = [ONE_ZKUSD, 12_345 * ONE_ZKUSD + 6_789, BTC_PRICE_100K, 1_000_000 * ONE_ZKUSD + 1];
+        let amounts = [0, 1, 7, 99_999, ONE_BTC - 1, ONE_BTC, 21_000 * ONE_BTC];
+
+        for &price in &prices {
+            for &sats in &amounts {
+                let zkusd = btc_to_zkusd_floor(Sats(sats), price).unwrap();
+                let back = zkusd_to_btc_floor(zkusd, price).unwrap().into_inner();
+                // Both directions round down, so the round trip never gains
+                assert!(back <= sats, "price {} sats {} back {}", price, sats, back);
+                assert!(sats - back <= 1, "price {} sats {} back {}", price, sats, back);
+            }
+        }
+    }
+
+    /// Largest prime below 2^64
+    const PRIME_NEAR_MAX: u64 = 18_446_744_073_709_551_557;
+
+    #[test]
+    fn test_btc_to_zkusd_floor_and_ceil() {
+        // (sats, price, floor, ceil)
+        let cases: [(u64, u64, ZkUsdResult<u64>, ZkUsdResult<u64>); 7] = [
+            (1, 30_000 * ONE_ZKUSD + 1, Ok(30_000), Ok(30_001)),
+            (7, 99_991, Ok(0), Ok(1)),
+            (ONE_BTC, 2_147_483_647, Ok(2_147_483_647), Ok(2_147_483_647)),
+            (99_999_989, 10_000_000_000_037, Ok(9_999_998_900_036), Ok(9_999_998_900_037)),
+            (1, PRIME_NEAR_MAX, Ok(184_467_440_737), Ok(184_467_440_738)),
+            (ONE_BTC, PRIME_NEAR_MAX, Ok(PRIME_NEAR_MAX), Ok(PRIME_NEAR_MAX)),
+            (21_000_000 * ONE_BTC, 10_000_000_000_037, Err(ZkUsdError::Overflow), Err(ZkUsdError::Overflow)),
+        ];
+
+        for (sats, price, floor, ceil) in cases {
+            assert_eq!(btc_to_zkusd_floor(Sats(sats), price).map(ZkUsd::into_inner), floor, "floor {} @ {}", sats, price);
+            assert_eq!(btc_to_zkusd_ceil(Sats(sats), price).map(ZkUsd::into_inner), ceil, "ceil {} @ {}", sats, price);
+        }
+    }
+
+    #[test]
+    fn test_zkusd_to_btc_floor_and_ceil() {
+        // (zkusd, price, floor, ceil)
+        let cases: [(u64, u64, ZkUsdResult<u64>, ZkUsdResult<u64>); 8] = [
+            (1, 30_000 * ONE_ZKUSD + 1, Ok(0), Ok(1)),
+            (ONE_ZKUSD, 30_000 * ONE_ZKUSD + 1, Ok(3_333), Ok(3_334)),
+            (7, 99_991, Ok(7_000), Ok(7_001)),
+            (ONE_ZKUSD, 2_147_483_647, Ok(4_656_612), Ok(4_656_613)),
+            (1_000 * ONE_ZKUSD, 10_000_000_000_037, Ok(999_999), Ok(1_000_000)),
+            (u64::MAX, PRIME_NEAR_MAX, Ok(ONE_BTC), Ok(ONE_BTC + 1)),
+            (u64::MAX, 99_991, Err(ZkUsdError::Overflow), Err(ZkUsdError::Overflow)),
+            (ONE_ZKUSD, 0, Err(ZkUsdError::DivisionByZero), Err(ZkUsdError::DivisionByZero)),
+        ];
+
+        for (zkusd, price, floor, ceil) in cases {
+            assert_eq!(zkusd_to_btc_floor(ZkUsd(zkusd), price).map(Sats::into_inner), floor, "floor {} @ {}", zkusd, price);
+            assert_eq!(zkusd_to_btc_ceil(ZkUsd(zkusd), price).map(Sats::into_inner), ceil, "ceil {} @ {}", zkusd, price);
+        }
+    }
+
+    #[test]
+    fn test_price_inverse_floor_and_ceil() {
+        // (price, floor, ceil)
+        let cases: [(u64, ZkUsdResult<u64>, ZkUsdResult<u64>); 8] = [
+            (BTC_PRICE_100K, Ok(1_000 * ONE_BTC), Ok(1_000 * ONE_BTC)),
+            (30_000 * ONE_ZKUSD + 1, Ok(333_333_333_333), Ok(333_333_333_334)),
+            (10_000_000_000_037, Ok(99_999_999_999), Ok(100_000_000_000)),
+            (2_147_483_647, Ok(465_661_287_524_579), Ok(465_661_287_524_580)),
+            (99_991, Ok(10_000_900_081_007_290_656), Ok(10_000_900_081_007_290_657)),
+            (PRIME_NEAR_MAX, Ok(54_210), Ok(54_211)),
+            (54_210, Err(ZkUsdError::Overflow), Err(ZkUsdError::Overflow)),
+            (0, Err(ZkUsdError::DivisionByZero), Err(ZkUsdError::DivisionByZero)),
+        ];
+
+        for (price, floor, ceil) in cases {
+            assert_eq!(price_inverse_floor(price), floor, "floor @ {}", price);
+            assert_eq!(price_inverse_ceil(price), ceil, "ceil @ {}", price);
+        }
+    }
+
+    #[test]
+    fn test_safe_mul_div_rounding() {
+        // Exact quotients are the same either way
+        assert_eq!(safe_mul_div(6, 10, 4), Ok(15));
+        assert_eq!(safe_mul_div_ceil(6, 10, 4), Ok(15));
+
+        // 7 * 10 / 4 = 17.5: truncated vs rounded up
+        assert_eq!(safe_mul_div(7, 10, 4), Ok(17));
+        assert_eq!(safe_mul_div_ceil(7, 10, 4), Ok(18));
+
+        // The intermediate product may exceed u64
+        assert_eq!(safe_mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+        assert_eq!(safe_mul_div_u128(u128::from(u64::MAX), 3, 3), Ok(u128::from(u64::MAX)));
+    }
+
+    #[test]
+    fn test_safe_mul_div_errors() {
+        assert_eq!(safe_mul_div(1, 1, 0), Err(ZkUsdError::DivisionByZero));
+        assert_eq!(safe_mul_div_ceil(1, 1, 0), Err(ZkUsdError::DivisionByZero));
+        assert_eq!(safe_mul_div_u128(1, 1, 0), Err(ZkUsdError::DivisionByZero));
+
+        assert_eq!(safe_mul_div(u64::MAX, 2, 1), Err(ZkUsdError::Overflow));
+        assert_eq!(safe_mul_div_u128(u128::MAX, 2, 4), Err(ZkUsdError::Overflow));
+
+        // (2^96 - 1) / 2^32 fits when truncated, but not once rounded up
+        let (a, b, c) = ((1u64 << 48) - 1, (1u64 << 48) + 1, 1u64 << 32);
+        assert_eq!(safe_mul_div(a, b, c), Ok(u64::MAX));
+        assert_eq!(safe_mul_div_ceil(a, b, c), Err(ZkUsdError::Overflow));
+    }
 }
@@ -0,0 +1,483 @@
+//! State Migrations
+//!
+//! Frozen layouts of past versions of the persisted types, and the
+//! functions that carry each one to the next version. See `versioning` for
+//! the migration contract and the version-bump checklist.
+//!
+//! ## Vault and Protocol State, v1 to v2
+//!
+//! Version 1 is the layout vaults and the Vault Manager state launched
+//! with. Version 2 adds operators, time-weighted collateral, lifetime
+//! stats, redemption protection, beneficiaries, adjustment windows, rate
+//! modes and two-phase liquidation to vaults, and interest accounting,
+//! flash fees and bootstrap mode to the protocol state.
+//!
+//! A v1 charm never carried any of those, so each migrates to a fixed
+//! value: zero, `None` or the type's default for vault fields, and the
+//! value `ProtocolState::new` starts a protocol with for protocol fields.
+//! Nothing about a migration is up to the spell: validators migrate the
+//! spent charm themselves and hold the outputs to the result.
+//!
+//! ## Stability Pool State, v1 to v2
+//!
+//! Version 2 adds `protocol_dust`, the zkUSD swept from dust deposits. No
+//! v1 pool has swept any, so it migrates to zero.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    Address, EpochSnapshot, OffsetSample, ProtocolState, StabilityPoolState, Vault, VaultId, VaultStatus,
+};
+use crate::versioning::INITIAL_STATE_VERSION;
+use crate::Vec;
+
+/// Version of the layout that adds the v2 vault and protocol fields
+pub const V2: u8 = INITIAL_STATE_VERSION + 1;
+
+/// Vault as laid out at version 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct VaultV1 {
+    /// Layout version, always 1
+    pub version: u8,
+    /// Unique identifier for this vault
+    pub id: VaultId,
+    /// Owner's address (pubkey hash)
+    pub owner: Address,
+    /// Collateral amount in satoshis
+    pub collateral: u64,
+    /// Debt amount in zkUSD base units
+    pub debt: u64,
+    /// Block height when vault was created
+    pub created_at: u64,
+    /// Last modification block height
+    pub last_updated: u64,
+    /// Current status
+    pub status: VaultStatus,
+    /// Fixed interest rate (basis points)
+    pub interest_rate_bps: u64,
+    /// Accrued interest in zkUSD base units
+    pub accrued_interest: u64,
+    /// Redistributed debt received from other liquidations
+    pub redistributed_debt: u64,
+    /// Redistributed collateral received from liquidations
+    pub redistributed_collateral: u64,
+    /// Insurance premium paid
+    pub insurance_balance: u64,
+}
+
+impl From<Vault> for VaultV1 {
+    /// The fields a v1 vault carries; any later field a value decoded at
+    /// version 1 holds is dropped rather than carried over
+    fn from(vault: Vault) -> Self {
+        Self {
+            version: INITIAL_STATE_VERSION,
+            id: vault.id,
+            owner: vault.owner,
+            collateral: vault.collateral,
+            debt: vault.debt,
+            created_at: vault.created_at,
+            last_updated: vault.last_updated,
+            status: vault.status,
+            interest_rate_bps: vault.interest_rate_bps,
+            accrued_interest: vault.accrued_interest,
+            redistributed_debt: vault.redistributed_debt,
+            redistributed_collateral: vault.redistributed_collateral,
+            insurance_balance: vault.insurance_balance,
+        }
+    }
+}
+
+/// Protocol state as laid out at version 1 of the Vault Manager state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct ProtocolStateV1 {
+    /// Total collateral in the system (satoshis)
+    pub total_collateral: u64,
+    /// Total debt in the system (zkUSD base units)
+    pub total_debt: u64,
+    /// Number of active vaults
+    pub active_vault_count: u64,
+    /// Current base rate for borrowing fee (in basis points)
+    pub base_rate: u64,
+    /// Last block when base rate was updated
+    pub last_fee_update_block: u64,
+    /// Protocol admin address
+    pub admin: Address,
+    /// Whether protocol is paused
+    pub is_paused: bool,
+}
+
+impl From<ProtocolState> for ProtocolStateV1 {
+    /// The fields a v1 protocol state carries
+    fn from(protocol: ProtocolState) -> Self {
+        Self {
+            total_collateral: protocol.total_collateral,
+            total_debt: protocol.total_debt,
+            active_vault_count: protocol.active_vault_count,
+            base_rate: protocol.base_rate,
+            last_fee_update_block: protocol.last_fee_update_block,
+            admin: protocol.admin,
+            is_paused: protocol.is_paused,
+        }
+    }
+}
+
+/// Stability pool state as laid out at version 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct StabilityPoolStateV1 {
+    /// Layout version, always 1
+    pub version: u8,
+    /// Total zkUSD deposited
+    pub total_zkusd: u64,
+    /// Total BTC from liquidations (pending distribution)
+    pub total_btc: u64,
+    /// Product P for loss calculation (decreases on liquidations)
+    pub product_p: u128,
+    /// Sum S for BTC reward calculation
+    pub sum_s: u128,
+    /// Current epoch (resets on P underflow)
+    pub current_epoch: u64,
+    /// Current scale (for precision)
+    pub current_scale: u64,
+    /// Number of depositors
+    pub depositor_count: u64,
+    /// Final S values of recently closed epochs (oldest first)
+    pub epoch_snapshots: Vec<EpochSnapshot>,
+    /// Most recent offsets (oldest first), for yield estimation
+    pub recent_offsets: Vec<OffsetSample>,
+}
+
+impl From<StabilityPoolState> for StabilityPoolStateV1 {
+    /// The fields a v1 pool carries; any later field a value decoded at
+    /// version 1 holds is dropped rather than carried over
+    fn from(pool: StabilityPoolState) -> Self {
+        Self {
+            version: INITIAL_STATE_VERSION,
+            total_zkusd: pool.total_zkusd,
+            total_btc: pool.total_btc,
+            product_p: pool.product_p,
+            sum_s: pool.sum_s,
+            current_epoch: pool.current_epoch,
+            current_scale: pool.current_scale,
+            depositor_count: pool.depositor_count,
+            epoch_snapshots: pool.epoch_snapshots,
+            recent_offsets: pool.recent_offsets,
+        }
+    }
+}
+
+/// Vault `old` migrated to version 2
+pub fn migrate_vault_v1_v2(old: VaultV1) -> Vault {
+    Vault {
+        version: V2,
+        id: old.id,
+        owner: old.owner,
+        collateral: old.collateral,
+        debt: old.debt,
+        created_at: old.created_at,
+        last_updated: old.last_updated,
+        status: old.status,
+        interest_rate_bps: old.interest_rate_bps,
+        accrued_interest: old.accrued_interest,
+        redistributed_debt: old.redistributed_debt,
+        redistributed_collateral: old.redistributed_collateral,
+        insurance_balance: old.insurance_balance,
+        operator: None,
+        twa_collateral: 0,
+        twa_updated_at: 0,
+        stats: Default::default(),
+        protected_collateral_bps: 0,
+        beneficiary: None,
+        adjustment_window: Default::default(),
+        rate_mode: Default::default(),
+        pending_liquidation: None,
+    }
+}
+
+/// Protocol state `old` migrated to version 2 of the Vault Manager state
+pub fn migrate_protocol_v1_v2(old: ProtocolStateV1) -> ProtocolState {
+    ProtocolState {
+        total_collateral: old.total_collateral,
+        total_debt: old.total_debt,
+        active_vault_count: old.active_vault_count,
+        base_rate: old.base_rate,
+        last_fee_update_block: old.last_fee_update_block,
+        is_paused: old.is_paused,
+        ..ProtocolState::new(old.admin)
+    }
+}
+
+/// Stability pool state `old` migrated to version 2
+pub fn migrate_stability_pool_v1_v2(old: StabilityPoolStateV1) -> StabilityPoolState {
+    StabilityPoolState {
+        version: V2,
+        total_zkusd: old.total_zkusd,
+        total_btc: old.total_btc,
+        product_p: old.product_p,
+        sum_s: old.sum_s,
+        current_epoch: old.current_epoch,
+        current_scale: old.current_scale,
+        depositor_count: old.depositor_count,
+        epoch_snapshots: old.epoch_snapshots,
+        recent_offsets: old.recent_offsets,
+        protocol_dust: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ZkUsdError;
+    use crate::versioning::VersionedState;
+
+    /// Layouts of the vault and protocol state before this change, copied
+    /// verbatim, to capture v1 fixtures independently of `VaultV1` and
+    /// `ProtocolStateV1`
+    mod pre_change {
+        use borsh::BorshSerialize;
+        use serde::Serialize;
+
+        use crate::types::{Address, VaultId, VaultStatus};
+
+        #[derive(Serialize, BorshSerialize)]
+        pub struct Vault {
+            pub id: VaultId,
+            pub owner: Address,
+            pub collateral: u64,
+            pub debt: u64,
+            pub created_at: u64,
+            pub last_updated: u64,
+            pub status: VaultStatus,
+            pub interest_rate_bps: u64,
+            pub accrued_interest: u64,
+            pub redistributed_debt: u64,
+            pub redistributed_collateral: u64,
+            pub insurance_balance: u64,
+        }
+
+        #[derive(Serialize)]
+        pub struct ProtocolState {
+            pub total_collateral: u64,
+            pub total_debt: u64,
+            pub active_vault_count: u64,
+            pub base_rate: u64,
+            pub last_fee_update_block: u64,
+            pub admin: Address,
+            pub is_paused: bool,
+        }
+    }
+
+    fn pre_change_vault() -> pre_change::Vault {
+        pre_change::Vault {
+            id: [0xAA; 32],
+            owner: [0x11; 32],
+            collateral: 150_000_000,
+            debt: 50_000_00000000,
+            created_at: 100,
+            last_updated: 120,
+            status: VaultStatus::Active,
+            interest_rate_bps: 100,
+            accrued_interest: 7,
+            redistributed_debt: 3,
+            redistributed_collateral: 2,
+            insurance_balance: 1,
+        }
+    }
+
+    /// Borsh bytes of `pre_change_vault` behind a v1 version byte
+    const VAULT_V1_FIXTURE: &str = concat!(
+        "01",
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "1111111111111111111111111111111111111111111111111111111111111111",
+        "80d1f00800000000",
+        "005039278c040000",
+        "6400000000000000",
+        "7800000000000000",
+        "00",
+        "6400000000000000",
+        "0700000000000000",
+        "0300000000000000",
+        "0200000000000000",
+        "0100000000000000",
+    );
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn migrated_vault() -> Vault {
+        Vault {
+            last_updated: 120,
+            accrued_interest: 7,
+            redistributed_debt: 3,
+            redistributed_collateral: 2,
+            insurance_balance: 1,
+            twa_updated_at: 0,
+            stats: Default::default(),
+            ..Vault::new([0xAA; 32], [0x11; 32], 150_000_000, 50_000_00000000, 100)
+        }
+    }
+
+    #[test]
+    fn test_v1_vault_fixture_migrates() {
+        let mut captured = Vec::from([INITIAL_STATE_VERSION]);
+        captured.extend(borsh::to_vec(&pre_change_vault()).unwrap());
+        assert_eq!(captured, from_hex(VAULT_V1_FIXTURE));
+
+        // v1 bytes decode through `VaultV1` and come out at v2, every new
+        // field at its fixed value
+        let vault = Vault::migrate(&captured).unwrap();
+        assert_eq!(vault, migrated_vault());
+        assert_eq!(vault.version, V2);
+
+        // ...and a v2 vault round-trips as is
+        assert_eq!(Vault::migrate(&borsh::to_vec(&vault).unwrap()), Ok(vault));
+    }
+
+    #[test]
+    fn test_v1_vault_charm_data_migrates() {
+        // Charm data from before the version field reads as v1
+        let json = serde_json::to_value(pre_change_vault()).unwrap();
+        let decoded: Vault = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.version, INITIAL_STATE_VERSION);
+        assert_eq!(decoded.upgrade(), Ok(migrated_vault()));
+
+        // A charm claiming v1 but carrying later fields loses them
+        let mut smuggled = serde_json::to_value(pre_change_vault()).unwrap();
+        smuggled["stats"] = serde_json::to_value(crate::types::VaultStats { total_fees_paid: 1, ..Default::default() }).unwrap();
+        let decoded: Vault = serde_json::from_value(smuggled).unwrap();
+        assert_eq!(decoded.upgrade(), Ok(migrated_vault()));
+    }
+
+    #[test]
+    fn test_unknown_vault_versions_rejected() {
+        let mut bytes = borsh::to_vec(&migrated_vault()).unwrap();
+        for found in [0, V2 + 1] {
+            bytes[0] = found;
+            assert_eq!(Vault::migrate(&bytes), Err(ZkUsdError::UnsupportedStateVersion { found, current: V2 }));
+            let vault = Vault { version: found, ..migrated_vault() };
+            assert_eq!(vault.upgrade(), Err(ZkUsdError::UnsupportedStateVersion { found, current: V2 }));
+        }
+    }
+
+    /// Layout of the pool before this change, copied verbatim, to capture
+    /// v1 fixtures independently of `StabilityPoolStateV1`
+    mod pre_change_pool {
+        use borsh::BorshSerialize;
+
+        use crate::types::{EpochSnapshot, OffsetSample};
+
+        #[derive(BorshSerialize)]
+        pub struct StabilityPoolState {
+            pub total_zkusd: u64,
+            pub total_btc: u64,
+            pub product_p: u128,
+            pub sum_s: u128,
+            pub current_epoch: u64,
+            pub current_scale: u64,
+            pub depositor_count: u64,
+            pub epoch_snapshots: Vec<EpochSnapshot>,
+            pub recent_offsets: Vec<OffsetSample>,
+        }
+    }
+
+    #[test]
+    fn test_v1_pool_fixture_migrates() {
+        let offset = OffsetSample { debt: 40, collateral: 5, block: 90 };
+        let pre_change = pre_change_pool::StabilityPoolState {
+            total_zkusd: 1_000,
+            total_btc: 20,
+            product_p: 7,
+            sum_s: 9,
+            current_epoch: 1,
+            current_scale: 2,
+            depositor_count: 3,
+            epoch_snapshots: Vec::new(),
+            recent_offsets: Vec::from([offset]),
+        };
+        let mut captured = Vec::from([INITIAL_STATE_VERSION]);
+        captured.extend(borsh::to_vec(&pre_change).unwrap());
+
+        // v1 bytes come out at v2 with no dust swept
+        let pool = StabilityPoolState::migrate(&captured).unwrap();
+        assert_eq!(
+            pool,
+            StabilityPoolState {
+                total_zkusd: 1_000,
+                total_btc: 20,
+                product_p: 7,
+                sum_s: 9,
+                current_epoch: 1,
+                current_scale: 2,
+                depositor_count: 3,
+                recent_offsets: Vec::from([offset]),
+                ..StabilityPoolState::default()
+            }
+        );
+        assert_eq!(pool.version, V2);
+
+        // A pool claiming v1 but carrying swept dust loses it
+        let smuggled = StabilityPoolState { version: INITIAL_STATE_VERSION, protocol_dust: 5, ..pool.clone() };
+        assert_eq!(smuggled.upgrade(), Ok(pool));
+    }
+
+    #[test]
+    fn test_protocol_migrates_with_defaults() {
+        let old = ProtocolStateV1 {
+            total_collateral: 10,
+            total_debt: 20,
+            active_vault_count: 2,
+            base_rate: 75,
+            last_fee_update_block: 9,
+            admin: [0x22; 32],
+            is_paused: true,
+        };
+        let migrated = migrate_protocol_v1_v2(old.clone());
+        assert_eq!(ProtocolStateV1::from(migrated.clone()), old);
+        assert_eq!(
+            migrated,
+            ProtocolState {
+                total_collateral: 10,
+                total_debt: 20,
+                active_vault_count: 2,
+                base_rate: 75,
+                last_fee_update_block: 9,
+                is_paused: true,
+                ..ProtocolState::new([0x22; 32])
+            }
+        );
+    }
+
+    #[test]
+    fn test_v1_protocol_charm_data_migrates() {
+        // Charm data from before the flash fee and interest fields decodes,
+        // and migrates to the values a new protocol starts with
+        let json = serde_json::to_value(pre_change::ProtocolState {
+            total_collateral: 10,
+            total_debt: 20,
+            active_vault_count: 2,
+            base_rate: 75,
+            last_fee_update_block: 9,
+            admin: [0x22; 32],
+            is_paused: false,
+        })
+        .unwrap();
+        let decoded: ProtocolState = serde_json::from_value(json).unwrap();
+        let migrated = migrate_protocol_v1_v2(ProtocolStateV1::from(decoded));
+        assert_eq!(
+            migrated,
+            ProtocolState {
+                total_collateral: 10,
+                total_debt: 20,
+                active_vault_count: 2,
+                base_rate: 75,
+                last_fee_update_block: 9,
+                ..ProtocolState::new([0x22; 32])
+            }
+        );
+        assert_eq!(
+            (migrated.flash_fee_bps, migrated.accumulated_fees, migrated.fee_recipient),
+            (crate::constants::fees::DEFAULT_FLASH_FEE_BPS, 0, [0x22; 32])
+        );
+    }
+}
@@ -9,6 +9,8 @@
 //! - **TWAP**: Time-weighted average price for manipulation resistance
 //! - **Circuit Breakers**: Automatic pausing on extreme deviations
 //! - **Staleness Detection**: Reject stale price data
+//! - **Correlation Checks**: Batches keep correlated assets (BTC and a BTC
+//!   wrapper) within their configured price ratio
 //! - **ZK-Proof Verification**: Verify oracle attestations
 //!
 //! ## Oracle Sources
@@ -24,7 +26,10 @@ use alloc::{vec, vec::Vec};
 #[allow(unused_imports)]
 use std::vec::Vec;
 
+use crate::constants::fees::BPS_DENOMINATOR;
 use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::math::saturating_u64;
+use crate::validation::require_min_health;
 
 // ============================================================================
 // Constants
@@ -54,6 +59,21 @@ pub const CIRCUIT_BREAKER_THRESHOLD_BPS: u64 = 1500; // 15%
 /// Cooldown after circuit breaker triggers (blocks)
 pub const CIRCUIT_BREAKER_COOLDOWN: u64 = 6; // ~1.5 minutes
 
+/// Weight of freshness in the health score (BPS)
+pub const HEALTH_FRESHNESS_WEIGHT_BPS: u64 = 4000; // 40%
+
+/// Weight of aggregate confidence in the health score (BPS)
+pub const HEALTH_CONFIDENCE_WEIGHT_BPS: u64 = 3000; // 30%
+
+/// Weight of source agreement in the health score (BPS)
+pub const HEALTH_AGREEMENT_WEIGHT_BPS: u64 = 3000; // 30%
+
+/// Default minimum health score for adversarial operations
+pub const DEFAULT_MIN_HEALTH_SCORE: u8 = 60;
+
+/// Maximum configured correlated asset pairs
+pub const MAX_PRICE_CORRELATIONS: usize = 16;
+
 // ============================================================================
 // Types
 // ============================================================================
@@ -283,6 +303,47 @@ pub struct AggregatedPrice {
     pub circuit_breaker_triggered: bool,
 }
 
+/// Signed price attestation from a single oracle source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceAttestation {
+    /// Source that signed the attestation
+    pub source_id: [u8; 32],
+    /// Attested price (8 decimals)
+    pub price: u64,
+    /// Signature over `attestation_message(asset_id, source_id, price)`
+    pub signature: Vec<u8>,
+}
+
+/// Verifies oracle attestation signatures
+///
+/// Implementors provide single-signature verification; `verify_batch`
+/// loops over it by default. Aggregate schemes (e.g. BLS) can override
+/// `verify_batch` to check the whole batch at once, but must still
+/// report the first failing source.
+pub trait AttestationVerifier {
+    /// Verify one signature against a source public key
+    fn verify(&self, public_key: &[u8; 33], message: &[u8], signature: &[u8]) -> bool;
+
+    /// Verify every attestation, failing on the first bad signature
+    fn verify_batch(
+        &self,
+        asset_id: &[u8; 32],
+        sources: &[OracleSource],
+        attestations: &[PriceAttestation],
+    ) -> ZkUsdResult<()> {
+        for att in attestations {
+            let public_key = attestation_public_key(sources, &att.source_id)?;
+            let message = attestation_message(asset_id, &att.source_id, att.price);
+            if !self.verify(public_key, &message, &att.signature) {
+                return Err(ZkUsdError::InvalidAttestationSignature {
+                    source_id: att.source_id,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Circuit breaker state
 #[derive(Debug, Clone, Default)]
 pub struct CircuitBreaker {
@@ -311,7 +372,7 @@ impl CircuitBreaker {
             old_price - new_price
         };
 
-        let change_bps = (change as u128 * 10000 / old_price as u128) as u64;
+        let change_bps = saturating_u64(change as u128 * 10000 / old_price as u128);
         change_bps > CIRCUIT_BREAKER_THRESHOLD_BPS
     }
 
@@ -354,6 +415,8 @@ pub struct OracleConfig {
     pub aggregation_method: AggregationMethod,
     /// Is oracle active
     pub is_active: bool,
+    /// Minimum health score (0-100) for adversarial operations
+    pub min_health_score: u8,
 }
 
 /// Method for aggregating multiple oracle sources
@@ -381,6 +444,7 @@ impl OracleConfig {
             last_update_block: 0,
             aggregation_method: AggregationMethod::Median,
             is_active: true,
+            min_health_score: DEFAULT_MIN_HEALTH_SCORE,
         }
     }
 
@@ -416,11 +480,58 @@ impl OracleConfig {
     }
 }
 
+/// Accepted price ratio between two correlated assets
+///
+/// Opt-in per pair: a batch update is checked only against the pairs
+/// configured on the [`OracleState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceCorrelation {
+    /// Asset the ratio is measured against (e.g. BTC)
+    pub base_asset: [u8; 32],
+    /// Asset expected to track the base (e.g. a BTC wrapper)
+    pub quote_asset: [u8; 32],
+    /// Lowest accepted quote/base price ratio (BPS)
+    pub min_ratio_bps: u64,
+    /// Highest accepted quote/base price ratio (BPS)
+    pub max_ratio_bps: u64,
+}
+
+impl PriceCorrelation {
+    /// Pair whose prices should match, within `max_divergence_bps`
+    pub fn pegged(base_asset: [u8; 32], quote_asset: [u8; 32], max_divergence_bps: u64) -> Self {
+        Self {
+            base_asset,
+            quote_asset,
+            min_ratio_bps: BPS_DENOMINATOR.saturating_sub(max_divergence_bps),
+            max_ratio_bps: BPS_DENOMINATOR.saturating_add(max_divergence_bps),
+        }
+    }
+
+    /// Check the pair's prices keep their ratio within bounds
+    pub fn check(&self, base_price: u64, quote_price: u64) -> ZkUsdResult<()> {
+        if base_price == 0 {
+            return Err(ZkUsdError::DivisionByZero);
+        }
+        let ratio_bps = (quote_price as u128 * BPS_DENOMINATOR as u128 / base_price as u128)
+            .min(u64::MAX as u128) as u64;
+        if ratio_bps < self.min_ratio_bps || ratio_bps > self.max_ratio_bps {
+            return Err(ZkUsdError::CorrelatedPriceDivergence {
+                ratio_bps,
+                min_ratio_bps: self.min_ratio_bps,
+                max_ratio_bps: self.max_ratio_bps,
+            });
+        }
+        Ok(())
+    }
+}
+
 /// Global oracle state
 #[derive(Debug, Clone, Default)]
 pub struct OracleState {
     /// Oracle configs by asset
     pub oracles: Vec<OracleConfig>,
+    /// Correlated asset pairs checked on batch updates
+    pub correlations: Vec<PriceCorrelation>,
     /// Admin address
     pub admin: [u8; 32],
     /// Is oracle system paused
@@ -431,10 +542,66 @@ pub struct OracleState {
     pub circuit_breaker_triggers: u64,
 }
 
+impl OracleState {
+    /// Configure a correlated asset pair
+    pub fn add_correlation(&mut self, correlation: PriceCorrelation) -> ZkUsdResult<()> {
+        if self.correlations.len() >= MAX_PRICE_CORRELATIONS {
+            return Err(ZkUsdError::ExceedsMaximum {
+                amount: self.correlations.len() as u64,
+                maximum: MAX_PRICE_CORRELATIONS as u64,
+            });
+        }
+        let valid = correlation.base_asset != correlation.quote_asset
+            && correlation.min_ratio_bps > 0
+            && correlation.min_ratio_bps <= correlation.max_ratio_bps;
+        let configured = self.correlations.iter().any(|c| {
+            (c.base_asset, c.quote_asset) == (correlation.base_asset, correlation.quote_asset)
+        });
+        if !valid || configured {
+            return Err(ZkUsdError::InvalidParameter);
+        }
+        self.correlations.push(correlation);
+        Ok(())
+    }
+
+    /// Last aggregated price of an asset, if it has one
+    fn last_price(&self, asset_id: &[u8; 32]) -> Option<u64> {
+        self.oracles
+            .iter()
+            .find(|c| c.asset_id == *asset_id)
+            .map(|c| c.last_price)
+            .filter(|price| *price > 0)
+    }
+}
+
 // ============================================================================
 // Core Operations
 // ============================================================================
 
+/// Validate a batch of `(asset_id, price)` updates against the configured
+/// correlations
+///
+/// Every pair with an asset in the batch is checked, taking the other
+/// asset's price from the batch or, failing that, its last aggregated
+/// price; pairs without both prices are skipped.
+pub fn validate_price_batch(state: &OracleState, prices: &[([u8; 32], u64)]) -> ZkUsdResult<()> {
+    let batch_price = |asset_id: &[u8; 32]| prices.iter().rev().find(|(id, _)| id == asset_id).map(|(_, p)| *p);
+
+    for correlation in &state.correlations {
+        let base = batch_price(&correlation.base_asset);
+        let quote = batch_price(&correlation.quote_asset);
+        if base.is_none() && quote.is_none() {
+            continue;
+        }
+        let base = base.or_else(|| state.last_price(&correlation.base_asset));
+        let quote = quote.or_else(|| state.last_price(&correlation.quote_asset));
+        if let (Some(base), Some(quote)) = (base, quote) {
+            correlation.check(base, quote)?;
+        }
+    }
+    Ok(())
+}
+
 /// Update price from an oracle source
 pub fn update_price(
     config: &mut OracleConfig,
@@ -528,13 +695,7 @@ pub fn aggregate_price(
     };
 
     // Calculate max deviation
-    let max_price = prices.iter().map(|p| p.0).max().unwrap_or(price);
-    let min_price = prices.iter().map(|p| p.0).min().unwrap_or(price);
-    let max_deviation_bps = if min_price > 0 {
-        ((max_price - min_price) as u128 * 10000 / min_price as u128) as u64
-    } else {
-        0
-    };
+    let max_deviation_bps = dispersion_bps(prices.iter().map(|p| p.0));
 
     // Check circuit breaker
     let circuit_breaker_triggered = config.circuit_breaker.is_triggered
@@ -565,6 +726,26 @@ pub fn aggregate_price(
     })
 }
 
+/// Verify a batch of signed attestations, then aggregate
+///
+/// All signatures are verified before any source is updated, so a single
+/// bad attestation leaves the config untouched and the error names the
+/// failing source.
+pub fn aggregate_attested_price<V: AttestationVerifier>(
+    config: &mut OracleConfig,
+    attestations: &[PriceAttestation],
+    verifier: &V,
+    current_block: u64,
+) -> ZkUsdResult<AggregatedPrice> {
+    verifier.verify_batch(&config.asset_id, &config.sources, attestations)?;
+
+    for att in attestations {
+        update_price(config, att.source_id, att.price, current_block, Some(&att.signature))?;
+    }
+
+    aggregate_price(config, current_block)
+}
+
 /// Get current price (with staleness check)
 pub fn get_price(
     config: &OracleConfig,
@@ -613,6 +794,46 @@ pub fn reset_circuit_breaker(
     Ok(())
 }
 
+/// Composite oracle health score (0-100)
+///
+/// Weighted sum of three 0-100 components:
+/// - freshness (40%): share of the `MAX_PRICE_AGE_BLOCKS` heartbeat left
+///   since the last aggregation
+/// - confidence (30%): the confidence `aggregate_price` would report now
+/// - agreement (30%): 100 at identical source prices, falling linearly to
+///   0 at a dispersion of `MAX_PRICE_DEVIATION_BPS`
+///
+/// Read-only; an inactive oracle or one without valid sources scores 0.
+pub fn oracle_health_score(config: &OracleConfig, current_block: u64) -> u8 {
+    let valid_sources = config.get_valid_sources(current_block);
+    if !config.is_active || valid_sources.len() < MIN_ORACLE_SOURCES {
+        return 0;
+    }
+
+    let age = current_block.saturating_sub(config.last_update_block);
+    let freshness = MAX_PRICE_AGE_BLOCKS.saturating_sub(age) * 100 / MAX_PRICE_AGE_BLOCKS;
+
+    let dispersion = dispersion_bps(valid_sources.iter().map(|s| s.last_price));
+    let circuit_breaker_triggered = config.circuit_breaker.is_triggered
+        && !config.circuit_breaker.can_reset(current_block);
+    let confidence = calculate_confidence(valid_sources.len(), dispersion, circuit_breaker_triggered);
+
+    let agreement = 100 - dispersion.min(MAX_PRICE_DEVIATION_BPS) * 100 / MAX_PRICE_DEVIATION_BPS;
+
+    let weighted = freshness * HEALTH_FRESHNESS_WEIGHT_BPS
+        + u64::from(confidence) * HEALTH_CONFIDENCE_WEIGHT_BPS
+        + agreement * HEALTH_AGREEMENT_WEIGHT_BPS;
+    (weighted / BPS_DENOMINATOR) as u8
+}
+
+/// Require the oracle's health score to meet its configured minimum,
+/// returning the score
+pub fn require_oracle_health(config: &OracleConfig, current_block: u64) -> ZkUsdResult<u8> {
+    let score = oracle_health_score(config, current_block);
+    require_min_health(score, config.min_health_score)?;
+    Ok(score)
+}
+
 /// Verify price is within acceptable deviation
 pub fn verify_price_deviation(
     reported_price: u64,
@@ -629,7 +850,7 @@ pub fn verify_price_deviation(
         reference_price - reported_price
     };
 
-    let deviation_bps = (deviation as u128 * 10000 / reference_price as u128) as u64;
+    let deviation_bps = saturating_u64(deviation as u128 * 10000 / reference_price as u128);
     deviation_bps <= max_deviation_bps
 }
 
@@ -637,6 +858,38 @@ pub fn verify_price_deviation(
 // Helpers
 // ============================================================================
 
+/// Message signed by an oracle source: asset_id || source_id || price (LE)
+pub fn attestation_message(asset_id: &[u8; 32], source_id: &[u8; 32], price: u64) -> [u8; 72] {
+    let mut message = [0u8; 72];
+    message[..32].copy_from_slice(asset_id);
+    message[32..64].copy_from_slice(source_id);
+    message[64..].copy_from_slice(&price.to_le_bytes());
+    message
+}
+
+/// Look up the public key of an active, signing source
+fn attestation_public_key<'a>(
+    sources: &'a [OracleSource],
+    source_id: &[u8; 32],
+) -> ZkUsdResult<&'a [u8; 33]> {
+    sources
+        .iter()
+        .find(|s| s.source_id == *source_id && s.is_active)
+        .and_then(|s| s.public_key.as_ref())
+        .ok_or(ZkUsdError::InvalidOracleSource)
+}
+
+/// Spread between the highest and lowest price, relative to the lowest (BPS)
+fn dispersion_bps(prices: impl Iterator<Item = u64> + Clone) -> u64 {
+    let max_price = prices.clone().max().unwrap_or(0);
+    let min_price = prices.min().unwrap_or(0);
+    if min_price > 0 {
+        saturating_u64((max_price - min_price) as u128 * 10000 / min_price as u128)
+    } else {
+        0
+    }
+}
+
 fn calculate_confidence(
     source_count: usize,
     deviation_bps: u64,
@@ -824,6 +1077,53 @@ mod tests {
         assert!(low < 50);
     }
 
+    /// Three fresh, agreeing sources aggregated at block 100
+    fn healthy_config() -> OracleConfig {
+        let mut config = OracleConfig::new([1u8; 32]);
+        for id in 1..=3 {
+            config.add_source(create_test_source(id, 100_00000000)).unwrap();
+        }
+        aggregate_price(&mut config, 100).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_health_score_components() {
+        let healthy = oracle_health_score(&healthy_config(), 100);
+        assert_eq!(healthy, 100);
+
+        // Stale: half the heartbeat gone
+        let stale = oracle_health_score(&healthy_config(), 100 + MAX_PRICE_AGE_BLOCKS / 2);
+        assert_eq!(stale, 80);
+
+        // Low confidence: a single source
+        let mut single = healthy_config();
+        single.sources.truncate(1);
+        let low_confidence = oracle_health_score(&single, 100);
+        assert_eq!(low_confidence, 94);
+
+        // High dispersion: sources 4% apart
+        let mut dispersed = healthy_config();
+        dispersed.sources[2].last_price = 104_00000000;
+        let disagreeing = oracle_health_score(&dispersed, 100);
+        assert!(disagreeing < healthy, "dispersion should lower the score: {}", disagreeing);
+
+        // Past the heartbeat with no valid source left
+        assert_eq!(oracle_health_score(&healthy_config(), 100 + MAX_PRICE_AGE_BLOCKS + 1), 0);
+    }
+
+    #[test]
+    fn test_require_oracle_health() {
+        let mut config = healthy_config();
+        assert_eq!(require_oracle_health(&config, 100), Ok(100));
+
+        config.min_health_score = 90;
+        assert_eq!(
+            require_oracle_health(&config, 100 + MAX_PRICE_AGE_BLOCKS / 2),
+            Err(ZkUsdError::OracleUnhealthy { score: 80, required: 90 })
+        );
+    }
+
     #[test]
     fn test_update_price_with_twap() {
         let mut config = OracleConfig::new([1u8; 32]);
@@ -862,4 +1162,130 @@ mod tests {
         config.remove_source(source_id).unwrap();
         assert_eq!(config.sources.len(), 0);
     }
+
+    /// Test verifier: a signature is sha256(public_key || message)
+    struct HashVerifier;
+
+    impl AttestationVerifier for HashVerifier {
+        fn verify(&self, public_key: &[u8; 33], message: &[u8], signature: &[u8]) -> bool {
+            hash_sign(public_key, message).as_slice() == signature
+        }
+    }
+
+    fn hash_sign(public_key: &[u8; 33], message: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(public_key);
+        hasher.update(message);
+        hasher.finalize().to_vec()
+    }
+
+    fn signed_config(asset_id: [u8; 32]) -> OracleConfig {
+        let mut config = OracleConfig::new(asset_id);
+        for id in 1..=3u8 {
+            let mut source = create_test_source(id, 0);
+            source.public_key = Some([id; 33]);
+            config.add_source(source).unwrap();
+        }
+        config
+    }
+
+    fn attest(asset_id: &[u8; 32], id: u8, price: u64) -> PriceAttestation {
+        let mut source_id = [0u8; 32];
+        source_id[0] = id;
+        let message = attestation_message(asset_id, &source_id, price);
+        PriceAttestation {
+            source_id,
+            price,
+            signature: hash_sign(&[id; 33], &message),
+        }
+    }
+
+    #[test]
+    fn test_attested_batch_aggregation() {
+        let asset_id = [1u8; 32];
+        let mut config = signed_config(asset_id);
+        let batch = vec![
+            attest(&asset_id, 1, 100_00000000),
+            attest(&asset_id, 2, 102_00000000),
+            attest(&asset_id, 3, 104_00000000),
+        ];
+
+        let result = aggregate_attested_price(&mut config, &batch, &HashVerifier, 105).unwrap();
+
+        assert_eq!(result.price, 102_00000000);
+        assert_eq!(result.sources_used, 3);
+    }
+
+    #[test]
+    fn test_attested_batch_reports_failing_source() {
+        let asset_id = [1u8; 32];
+        let mut config = signed_config(asset_id);
+        let mut bad = attest(&asset_id, 2, 102_00000000);
+        bad.price = 150_00000000; // Signature no longer matches
+        let batch = vec![
+            attest(&asset_id, 1, 100_00000000),
+            bad,
+            attest(&asset_id, 3, 104_00000000),
+        ];
+
+        let result = aggregate_attested_price(&mut config, &batch, &HashVerifier, 105);
+
+        let mut failing = [0u8; 32];
+        failing[0] = 2;
+        assert_eq!(result.unwrap_err(), ZkUsdError::InvalidAttestationSignature { source_id: failing });
+        // No source was updated
+        assert!(config.sources.iter().all(|s| s.last_price == 0));
+    }
+
+    #[test]
+    fn test_attestation_from_unknown_source() {
+        let asset_id = [1u8; 32];
+        let mut config = signed_config(asset_id);
+        let batch = vec![attest(&asset_id, 9, 100_00000000)];
+
+        let result = aggregate_attested_price(&mut config, &batch, &HashVerifier, 105);
+
+        assert_eq!(result.unwrap_err(), ZkUsdError::InvalidOracleSource);
+    }
+
+    #[test]
+    fn test_price_batch_correlation() {
+        let (btc, wbtc, eth) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let btc_price = 100_000 * PRICE_PRECISION;
+        let mut state = OracleState::default();
+        state.add_correlation(PriceCorrelation::pegged(btc, wbtc, 500)).unwrap();
+
+        // 1% drift between the peg and BTC passes
+        assert_eq!(validate_price_batch(&state, &[(btc, btc_price), (wbtc, btc_price / 100 * 99)]), Ok(()));
+
+        // 20% drift is a feed error
+        assert_eq!(
+            validate_price_batch(&state, &[(btc, btc_price), (wbtc, btc_price / 100 * 80)]),
+            Err(ZkUsdError::CorrelatedPriceDivergence { ratio_bps: 8000, min_ratio_bps: 9500, max_ratio_bps: 10_500 })
+        );
+
+        // Unconfigured pairs are not checked
+        assert_eq!(validate_price_batch(&state, &[(btc, btc_price), (eth, btc_price / 20)]), Ok(()));
+
+        // A lone update is checked against the other asset's last price
+        let mut config = OracleConfig::new(btc);
+        config.last_price = btc_price;
+        state.oracles.push(config);
+        assert!(validate_price_batch(&state, &[(wbtc, btc_price / 100 * 120)]).is_err());
+        assert_eq!(validate_price_batch(&state, &[(wbtc, btc_price / 100 * 101)]), Ok(()));
+    }
+
+    #[test]
+    fn test_add_correlation_rejects_invalid_pairs() {
+        let (btc, wbtc) = ([1u8; 32], [2u8; 32]);
+        let mut state = OracleState::default();
+
+        assert_eq!(state.add_correlation(PriceCorrelation::pegged(btc, btc, 500)), Err(ZkUsdError::InvalidParameter));
+        let inverted = PriceCorrelation { base_asset: btc, quote_asset: wbtc, min_ratio_bps: 10_500, max_ratio_bps: 9500 };
+        assert_eq!(state.add_correlation(inverted), Err(ZkUsdError::InvalidParameter));
+
+        state.add_correlation(PriceCorrelation::pegged(btc, wbtc, 500)).unwrap();
+        assert_eq!(state.add_correlation(PriceCorrelation::pegged(btc, wbtc, 100)), Err(ZkUsdError::InvalidParameter));
+    }
 }
@@ -15,6 +15,7 @@
 use crate::{Vec, ZkUsdError, ZkUsdResult};
 use crate::errors::AmountErrorReason;
 use crate::constants::token;
+use crate::math::saturating_u64;
 
 // ============================================================================
 // Constants
@@ -126,7 +127,7 @@ impl SpDeposit {
         };
 
         // BTC reward = deposit * S_delta / SCALE_FACTOR
-        let reward = (self.deposit as u128 * s_delta / SP_SCALE_FACTOR) as u64;
+        let reward = saturating_u64(self.deposit as u128 * s_delta / SP_SCALE_FACTOR);
         reward
     }
 }
@@ -186,7 +187,7 @@ impl SpPoolState {
         if total_debt == 0 {
             return 10000; // 100% coverage
         }
-        ((self.total_deposits as u128 * 10000) / total_debt as u128) as u64
+        saturating_u64((self.total_deposits as u128 * 10000) / total_debt as u128)
     }
 }
 
@@ -230,8 +230,9 @@ mod stability_pool_tests {
 
 #[cfg(test)]
 mod math_edge_case_tests {
+    use crate::units::{Sats, ZkUsd};
     use crate::math::{
-        calculate_icr, calculate_tcr, calculate_borrowing_fee, calculate_redemption_fee,
+        calculate_icr, calculate_icr_bps, calculate_tcr, calculate_borrowing_fee, calculate_redemption_fee,
         calculate_redemption_fee_fixed, max_debt_for_collateral, min_collateral_for_debt,
         is_liquidatable, is_recovery_mode, get_min_ratio,
         calculate_compounded_deposit, calculate_btc_gain,
@@ -305,14 +306,21 @@ mod math_edge_case_tests {
 
     #[test]
     fn test_icr_zero_debt_returns_max() {
-        let icr = calculate_icr(ONE_BTC, 0, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(ONE_BTC), ZkUsd(0), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, u64::MAX);
     }
 
+    #[test]
+    fn test_icr_bps_keeps_sub_percent_precision() {
+        // 1.105 BTC against $100k debt: 110% truncated, 11_050 in BPS
+        assert_eq!(calculate_icr(Sats(110_500_000), ZkUsd(100_000 * ONE_ZKUSD), BTC_PRICE_100K), Ok(110));
+        assert_eq!(calculate_icr_bps(Sats(110_500_000), ZkUsd(100_000 * ONE_ZKUSD), BTC_PRICE_100K), Ok(11_050));
+    }
+
     #[test]
     fn test_icr_very_small_collateral() {
         // 1 satoshi collateral, 1 zkUSD debt
-        let icr = calculate_icr(1, ONE_ZKUSD, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(1), ZkUsd(ONE_ZKUSD), BTC_PRICE_100K).unwrap();
         // Expected: (1 * $100k) / 1 zkUSD = 0.001% -> ICR = 0
         assert_eq!(icr, 0);
     }
@@ -321,7 +329,7 @@ mod math_edge_case_tests {
     fn test_icr_very_large_collateral() {
         // 21M BTC (max supply) backing 1 zkUSD
         let max_btc = 21_000_000 * ONE_BTC;
-        let icr = calculate_icr(max_btc, ONE_ZKUSD, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(max_btc), ZkUsd(ONE_ZKUSD), BTC_PRICE_100K).unwrap();
         // Should be extremely high but not overflow
         assert!(icr > 1_000_000_000);
     }
@@ -331,7 +339,7 @@ mod math_edge_case_tests {
         // 1.1 BTC backing $100k debt at $100k/BTC = exactly 110%
         let collateral = 110_000_000; // 1.1 BTC
         let debt = 100_000 * ONE_ZKUSD;
-        let icr = calculate_icr(collateral, debt, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(collateral), ZkUsd(debt), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, 110);
     }
 
@@ -340,7 +348,7 @@ mod math_edge_case_tests {
         // 1.5 BTC backing $100k debt at $100k/BTC = exactly 150%
         let collateral = 150_000_000; // 1.5 BTC
         let debt = 100_000 * ONE_ZKUSD;
-        let icr = calculate_icr(collateral, debt, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(collateral), ZkUsd(debt), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, 150);
     }
 
@@ -349,8 +357,8 @@ mod math_edge_case_tests {
         // TCR uses same calculation as ICR
         let collateral = 150_000_000;
         let debt = 100_000 * ONE_ZKUSD;
-        let icr = calculate_icr(collateral, debt, BTC_PRICE_100K).unwrap();
-        let tcr = calculate_tcr(collateral, debt, BTC_PRICE_100K).unwrap();
+        let icr = calculate_icr(Sats(collateral), ZkUsd(debt), BTC_PRICE_100K).unwrap();
+        let tcr = calculate_tcr(Sats(collateral), ZkUsd(debt), BTC_PRICE_100K).unwrap();
         assert_eq!(icr, tcr);
     }
 
@@ -427,32 +435,32 @@ mod math_edge_case_tests {
     #[test]
     fn test_max_debt_for_collateral() {
         // 1 BTC at $100k with 110% MCR = max ~$90,909 debt
-        let max_debt = max_debt_for_collateral(ONE_BTC, BTC_PRICE_100K).unwrap();
-        assert_eq!(max_debt, 90909_09090909);
+        let max_debt = max_debt_for_collateral(Sats(ONE_BTC), BTC_PRICE_100K).unwrap();
+        assert_eq!(max_debt, ZkUsd(90909_09090909));
     }
 
     #[test]
     fn test_max_debt_for_zero_collateral() {
-        let max_debt = max_debt_for_collateral(0, BTC_PRICE_100K).unwrap();
-        assert_eq!(max_debt, 0);
+        let max_debt = max_debt_for_collateral(Sats(0), BTC_PRICE_100K).unwrap();
+        assert_eq!(max_debt, ZkUsd::ZERO);
     }
 
     #[test]
     fn test_min_collateral_for_debt() {
         // 50k zkUSD needs 0.55 BTC at $100k (110% MCR)
-        let min_coll = min_collateral_for_debt(50_000 * ONE_ZKUSD, BTC_PRICE_100K).unwrap();
-        assert_eq!(min_coll, 55_000_000);
+        let min_coll = min_collateral_for_debt(ZkUsd(50_000 * ONE_ZKUSD), BTC_PRICE_100K).unwrap();
+        assert_eq!(min_coll, Sats(55_000_000));
     }
 
     #[test]
     fn test_min_collateral_for_zero_debt() {
-        let min_coll = min_collateral_for_debt(0, BTC_PRICE_100K).unwrap();
-        assert_eq!(min_coll, 0);
+        let min_coll = min_collateral_for_debt(ZkUsd(0), BTC_PRICE_100K).unwrap();
+        assert_eq!(min_coll, Sats::ZERO);
     }
 
     #[test]
     fn test_min_collateral_zero_price_error() {
-        let result = min_collateral_for_debt(50_000 * ONE_ZKUSD, 0);
+        let result = min_collateral_for_debt(ZkUsd(50_000 * ONE_ZKUSD), 0);
         assert!(matches!(result, Err(ZkUsdError::DivisionByZero)));
     }
 
@@ -763,4 +771,16 @@ mod price_data_tests {
         // Should not panic with block < timestamp (though shouldn't happen)
         assert!(!price.is_stale(50)); // Uses saturating_sub
     }
+
+    #[test]
+    fn test_effective_confidence_decay() {
+        let price = PriceData::new(100_000_00000000, 100, PriceSource::Mock);
+
+        assert_eq!(price.effective_confidence(100), 100);
+        assert_eq!(price.effective_confidence(50), 100); // Uses saturating_sub
+        assert!(price.effective_confidence(101) < 100);
+        assert!(price.effective_confidence(100 + MAX_PRICE_AGE_BLOCKS - 1) > 0);
+        assert_eq!(price.effective_confidence(100 + MAX_PRICE_AGE_BLOCKS), 0);
+        assert_eq!(price.effective_confidence(100 + MAX_PRICE_AGE_BLOCKS + 10), 0);
+    }
 }
@@ -10,10 +10,16 @@
 //! - **UTXO Transfers**: Atomic token transfers
 //! - **Conservation**: Total inputs = Total outputs
 //! - **Supply Tracking**: Track total supply changes
+//! - **Memos**: Fixed-size payload commitments attached to transfers
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{Vec, ZkUsdError, ZkUsdResult};
 use crate::errors::AmountErrorReason;
 use crate::constants::token as token_config;
+use crate::types::Memo;
 
 // ============================================================================
 // Constants
@@ -33,6 +39,9 @@ pub const ROLE_FLASH_MINTER: u8 = 3;
 pub const ROLE_PSM: u8 = 4;
 pub const ROLE_GOVERNANCE: u8 = 5;
 
+/// Domain tag hashed in front of memo payloads
+pub const MEMO_DOMAIN: &[u8] = b"zkUSD/memo/v1";
+
 // ============================================================================
 // Types
 // ============================================================================
@@ -143,6 +152,62 @@ impl MintAuth {
     }
 }
 
+/// Lifetime zkUSD minted against each address, under an optional cap
+///
+/// The cap is on lifetime mints, not outstanding debt: burning or repaying
+/// never restores capacity. Mints are only tracked while a cap is set, so
+/// an uncapped deployment's state does not grow.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct MintTracker {
+    /// Lifetime mint cap per address (None = unlimited)
+    pub lifetime_cap: Option<u64>,
+    /// Lifetime minted amount per address
+    pub minted: Vec<([u8; 32], u64)>,
+}
+
+impl MintTracker {
+    /// Create a tracker enforcing a per-address lifetime cap
+    pub fn with_cap(cap: u64) -> Self {
+        Self { lifetime_cap: Some(cap), minted: Vec::new() }
+    }
+
+    /// Lifetime amount minted against an address
+    pub fn minted_by(&self, address: &[u8; 32]) -> u64 {
+        self.minted
+            .iter()
+            .find(|(a, _)| a == address)
+            .map(|(_, amount)| *amount)
+            .unwrap_or(0)
+    }
+
+    /// Record a mint against an address, rejecting it if it would take the
+    /// address past the lifetime cap
+    pub fn record(&mut self, address: [u8; 32], amount: u64) -> ZkUsdResult<()> {
+        let cap = match self.lifetime_cap {
+            Some(cap) => cap,
+            None => return Ok(()),
+        };
+
+        let minted = self.minted_by(&address);
+        let total = minted.checked_add(amount).ok_or(ZkUsdError::Overflow)?;
+        if total > cap {
+            return Err(ZkUsdError::LifetimeMintCapExceeded {
+                address,
+                minted,
+                requested: amount,
+                cap,
+            });
+        }
+
+        match self.minted.iter_mut().find(|(a, _)| *a == address) {
+            Some(entry) => entry.1 = total,
+            None => self.minted.push((address, total)),
+        }
+        Ok(())
+    }
+}
+
 /// Transfer request
 #[derive(Debug, Clone)]
 pub struct TransferRequest {
@@ -152,6 +217,8 @@ pub struct TransferRequest {
     pub to: [u8; 32],
     /// Amount to transfer
     pub amount: u64,
+    /// Optional payload commitment
+    pub memo: Option<Memo>,
     /// Block height
     pub block_height: u64,
 }
@@ -189,6 +256,8 @@ pub struct TransferResult {
     pub to_balance: TokenBalance,
     /// Transfer amount (confirmed)
     pub amount: u64,
+    /// Memo carried by the transfer
+    pub memo: Option<Memo>,
 }
 
 /// Mint result
@@ -220,6 +289,8 @@ pub struct BatchTransfer {
     pub to: [u8; 32],
     /// Amount to transfer
     pub amount: u64,
+    /// Optional payload commitment for this entry
+    pub memo: Option<Memo>,
 }
 
 /// UTXO token input
@@ -296,6 +367,7 @@ pub fn execute_transfer(
         from_balance: new_from_balance,
         to_balance: new_to_balance,
         amount: request.amount,
+        memo: request.memo,
     })
 }
 
@@ -452,6 +524,7 @@ pub fn execute_batch_transfer(
             from_balance: TokenBalance::new(from, remaining, block_height),
             to_balance: TokenBalance::new(transfer.to, transfer.amount, block_height),
             amount: transfer.amount,
+            memo: transfer.memo,
         });
     }
 
@@ -518,6 +591,25 @@ pub fn get_token_info(supply: &TokenSupply) -> TokenInfo {
     }
 }
 
+// ============================================================================
+// Memos
+// ============================================================================
+
+/// Commit to an arbitrary payload (order id, invoice, ...) as a transfer memo
+///
+/// `sha256(MEMO_DOMAIN || payload)`; the payload itself stays off-chain.
+pub fn memo_hash(payload: &[u8]) -> Memo {
+    let mut hasher = Sha256::new();
+    hasher.update(MEMO_DOMAIN);
+    hasher.update(payload);
+    hasher.finalize().into()
+}
+
+/// Check that `memo` commits to `payload`
+pub fn verify_memo(payload: &[u8], memo: &Memo) -> bool {
+    memo_hash(payload) == *memo
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -540,6 +632,35 @@ mod tests {
         [3u8; 32]
     }
 
+    #[test]
+    fn test_mint_tracker_uncapped_tracks_nothing() {
+        let mut tracker = MintTracker::default();
+        tracker.record(test_owner(), u64::MAX).unwrap();
+        assert_eq!(tracker.minted_by(&test_owner()), 0);
+        assert!(tracker.minted.is_empty());
+    }
+
+    #[test]
+    fn test_mint_tracker_lifetime_cap() {
+        let mut tracker = MintTracker::with_cap(1000 * ONE_ZKUSD);
+        tracker.record(test_owner(), 600 * ONE_ZKUSD).unwrap();
+        tracker.record(test_owner(), 400 * ONE_ZKUSD).unwrap();
+        tracker.record(test_recipient(), 1000 * ONE_ZKUSD).unwrap();
+
+        assert_eq!(tracker.minted_by(&test_owner()), 1000 * ONE_ZKUSD);
+        assert_eq!(
+            tracker.record(test_owner(), 1),
+            Err(ZkUsdError::LifetimeMintCapExceeded {
+                address: test_owner(),
+                minted: 1000 * ONE_ZKUSD,
+                requested: 1,
+                cap: 1000 * ONE_ZKUSD,
+            })
+        );
+        // A rejected mint leaves the tracker unchanged
+        assert_eq!(tracker.minted_by(&test_owner()), 1000 * ONE_ZKUSD);
+    }
+
     #[test]
     fn test_transfer() {
         let from_balance = TokenBalance::new(test_owner(), 1000 * ONE_ZKUSD, 1000);
@@ -548,6 +669,7 @@ mod tests {
             from: test_owner(),
             to: test_recipient(),
             amount: 100 * ONE_ZKUSD,
+            memo: None,
             block_height: 1001,
         };
 
@@ -565,6 +687,7 @@ mod tests {
             from: test_owner(),
             to: test_recipient(),
             amount: 100 * ONE_ZKUSD,
+            memo: None,
             block_height: 1001,
         };
 
@@ -580,6 +703,7 @@ mod tests {
             from: test_owner(),
             to: test_owner(),
             amount: 100 * ONE_ZKUSD,
+            memo: None,
             block_height: 1001,
         };
 
@@ -699,10 +823,12 @@ mod tests {
             BatchTransfer {
                 to: test_recipient(),
                 amount: 100 * ONE_ZKUSD,
+                memo: None,
             },
             BatchTransfer {
                 to: [3u8; 32],
                 amount: 200 * ONE_ZKUSD,
+                memo: None,
             },
         ];
 
@@ -713,6 +839,56 @@ mod tests {
         assert_eq!(results[1].from_balance.balance, 700 * ONE_ZKUSD);
     }
 
+    #[test]
+    fn test_batch_transfer_distinct_memos() {
+        let from_balance = TokenBalance::new(test_owner(), 1000 * ONE_ZKUSD, 1000);
+        let invoice_a = memo_hash(b"invoice-a");
+        let invoice_b = memo_hash(b"invoice-b");
+
+        let transfers = vec![
+            BatchTransfer { to: test_recipient(), amount: 100 * ONE_ZKUSD, memo: Some(invoice_a) },
+            BatchTransfer { to: [3u8; 32], amount: 200 * ONE_ZKUSD, memo: Some(invoice_b) },
+            BatchTransfer { to: [4u8; 32], amount: 50 * ONE_ZKUSD, memo: None },
+        ];
+
+        let results = execute_batch_transfer(test_owner(), &transfers, &from_balance, 1001).unwrap();
+
+        let memos: Vec<_> = results.iter().map(|r| r.memo).collect();
+        assert_eq!(memos, [Some(invoice_a), Some(invoice_b), None]);
+    }
+
+    #[test]
+    fn test_transfer_carries_memo() {
+        let from_balance = TokenBalance::new(test_owner(), 1000 * ONE_ZKUSD, 1000);
+        let memo = memo_hash(b"order-42");
+
+        let request = TransferRequest {
+            from: test_owner(),
+            to: test_recipient(),
+            amount: 100 * ONE_ZKUSD,
+            memo: Some(memo),
+            block_height: 1001,
+        };
+
+        let result = execute_transfer(&request, &from_balance, None).unwrap();
+        assert_eq!(result.memo, Some(memo));
+    }
+
+    #[test]
+    fn test_memo_hash() {
+        let memo = memo_hash(b"order-42");
+
+        assert!(verify_memo(b"order-42", &memo));
+        assert!(!verify_memo(b"order-43", &memo));
+        // Domain-separated from a bare sha256 of the payload
+        let bare: Memo = Sha256::digest(b"order-42").into();
+        assert_ne!(memo, bare);
+
+        // Integrators hash payloads off-chain; this vector pins the scheme
+        let hex: String = memo.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "b4d1d19bb30940098a9a9c6aac782e7bf739c0ab76d6b09083fd0d384ae8a0df");
+    }
+
     #[test]
     fn test_conservation() {
         let inputs = vec![
@@ -4,6 +4,7 @@
 //! the zkUSD protocol contracts.
 
 use crate::Vec;
+use crate::units::{Sats, ZkUsd};
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 
@@ -19,10 +20,15 @@ pub type TxId = [u8; 32];
 /// Type alias for app identifiers
 pub type AppId = [u8; 32];
 
+/// Type alias for transfer memos (a commitment to an off-chain payload,
+/// see `token_ops::memo_hash`)
+pub type Memo = [u8; 32];
+
 // ============ Vault Types ============
 
 /// Status of a vault
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum VaultStatus {
     /// Vault is active and can be modified
     #[default]
@@ -35,9 +41,26 @@ pub enum VaultStatus {
     Liquidated,
 }
 
+/// How a vault's interest rate is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum RateMode {
+    /// `interest_rate_bps`, chosen at opening
+    #[default]
+    Fixed,
+    /// The protocol's utilization-based variable rate, whatever it is at
+    /// the time (see `interest` module)
+    Variable,
+}
+
 /// Individual vault state
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct Vault {
+    /// Layout version (see `versioning`); charm data without one predates
+    /// the field and reads as version 1
+    #[serde(default = "crate::versioning::initial_state_version")]
+    pub version: u8,
     /// Unique identifier for this vault
     pub id: VaultId,
     /// Owner's address (pubkey hash)
@@ -53,7 +76,8 @@ pub struct Vault {
     /// Current status
     pub status: VaultStatus,
     // ===== NEW: Mezo-inspired improvements =====
-    /// Fixed interest rate (basis points, e.g., 100 = 1% APR)
+    /// Fixed interest rate (basis points, e.g., 100 = 1% APR); the rate
+    /// at opening for a variable-rate vault
     pub interest_rate_bps: u64,
     /// Accrued interest in zkUSD base units
     pub accrued_interest: u64,
@@ -63,6 +87,98 @@ pub struct Vault {
     pub redistributed_collateral: u64,
     /// Insurance premium paid (for optional liquidation protection)
     pub insurance_balance: u64,
+    /// Hot key allowed to take defensive actions on the owner's behalf
+    #[serde(default)]
+    pub operator: Option<Address>,
+    /// Time-weighted average collateral as of `twa_updated_at`, used for
+    /// redistribution shares instead of the spot collateral
+    #[serde(default)]
+    pub twa_collateral: u64,
+    /// Block the average was last brought up to date
+    #[serde(default)]
+    pub twa_updated_at: u64,
+    /// Lifetime statistics (fee rebates, loyalty tiers)
+    #[serde(default)]
+    pub stats: VaultStats,
+    /// Share of collateral redemptions cannot take (basis points), paid for
+    /// with a higher interest rate; liquidations ignore it
+    #[serde(default)]
+    pub protected_collateral_bps: u64,
+    /// Key that may take over the vault, and the blocks without an owner
+    /// spell (measured from `last_updated`) before it can
+    #[serde(default)]
+    pub beneficiary: Option<(Address, u64)>,
+    /// Owner adjustments made in the current adjustment window
+    #[serde(default)]
+    pub adjustment_window: AdjustmentWindow,
+    /// Whether the vault pays its fixed rate or the variable rate
+    #[serde(default)]
+    pub rate_mode: RateMode,
+    /// Terms of a two-phase liquidation in progress, set while the vault
+    /// is `Liquidating`
+    #[serde(default)]
+    pub pending_liquidation: Option<PendingLiquidation>,
+}
+
+/// Terms a two-phase liquidation is frozen at when it begins
+///
+/// Every tranche settles at these terms, so moving the oracle after the
+/// liquidation has begun changes nothing about how it ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct PendingLiquidation {
+    /// BTC price the vault was found liquidatable at
+    pub trigger_price: u64,
+    /// Block the liquidation began at
+    pub started_at: u64,
+    /// Liquidator bonus in basis points of each tranche's collateral
+    pub bonus_bps: u64,
+}
+
+/// Count of a vault's adjustments within a fixed window of blocks, which
+/// caps how often one vault can churn the registry and event history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct AdjustmentWindow {
+    /// Block the current window opened at
+    pub started_at: u64,
+    /// Adjustments made since `started_at`
+    pub count: u64,
+}
+
+impl AdjustmentWindow {
+    /// Window after one more adjustment at `block_height`
+    ///
+    /// An adjustment at or past the end of the window opens a new one
+    /// counting just that adjustment.
+    pub fn after_adjustment(&self, block_height: u64, window_blocks: u64) -> Self {
+        if self.count == 0 || block_height >= self.started_at.saturating_add(window_blocks) {
+            Self { started_at: block_height, count: 1 }
+        } else {
+            Self { started_at: self.started_at, count: self.count.saturating_add(1) }
+        }
+    }
+}
+
+/// Lifetime statistics of a vault, rolled forward whenever it is touched
+///
+/// Vaults written before the stats existed deserialize them as zero and
+/// count from `created_at` on their first roll forward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct VaultStats {
+    /// Borrowing fees paid, after discounts
+    pub total_fees_paid: u64,
+    /// Interest accrued on the vault's debt
+    pub total_interest_paid: u64,
+    /// zkUSD minted (open and MintDebt), excluding the liquidation reserve
+    pub total_debt_minted: u64,
+    /// Blocks the vault has carried debt
+    pub blocks_active: u64,
+    /// Rescues and insurance triggers that pulled the vault back from liquidation
+    pub times_liquidated_avoided: u32,
+    /// Block the stats were last rolled forward to
+    pub last_updated: u64,
 }
 
 impl Vault {
@@ -81,6 +197,7 @@ impl Vault {
         interest_rate_bps: u64,
     ) -> Self {
         Self {
+            version: crate::migrations::V2,
             id,
             owner,
             collateral,
@@ -93,6 +210,17 @@ impl Vault {
             redistributed_debt: 0,
             redistributed_collateral: 0,
             insurance_balance: 0,
+            operator: None,
+            // A new vault's average starts at zero and ramps up as the
+            // collateral is held
+            twa_collateral: 0,
+            twa_updated_at: block_height,
+            stats: VaultStats { last_updated: block_height, ..VaultStats::default() },
+            protected_collateral_bps: 0,
+            beneficiary: None,
+            adjustment_window: AdjustmentWindow::default(),
+            rate_mode: RateMode::Fixed,
+            pending_liquidation: None,
         }
     }
 
@@ -118,10 +246,102 @@ impl Vault {
         self.collateral.saturating_add(self.redistributed_collateral)
     }
 
+    /// Collateral out of reach of redemptions (rounded up)
+    pub fn protected_collateral(&self) -> u64 {
+        let bps = self.protected_collateral_bps.min(crate::constants::fees::BPS_DENOMINATOR);
+        // collateral * bps / 10_000 <= collateral: fits u64
+        ((self.collateral as u128 * bps as u128).div_ceil(crate::constants::fees::BPS_DENOMINATOR as u128)) as u64
+    }
+
+    /// Block from which the designated beneficiary may claim the vault
+    pub fn beneficiary_claimable_at(&self) -> Option<u64> {
+        self.beneficiary.map(|(_, inactivity_blocks)| self.last_updated.saturating_add(inactivity_blocks))
+    }
+
+    /// Collateral a redemption may take
+    pub fn redeemable_collateral(&self) -> u64 {
+        self.collateral - self.protected_collateral()
+    }
+
+    /// Protection left after a redemption brings the collateral down to
+    /// `collateral`
+    ///
+    /// Redemptions only take unprotected collateral, so the protected
+    /// amount is kept and its share grows. Re-expressing it in basis
+    /// points (rounded up) is what stops repeated partial redemptions
+    /// from eroding it.
+    pub fn protection_after_redemption(&self, collateral: u64) -> u64 {
+        let protected = self.protected_collateral() as u128;
+        if protected == 0 || collateral == 0 {
+            return self.protected_collateral_bps;
+        }
+        let denominator = crate::constants::fees::BPS_DENOMINATOR as u128;
+        // protected <= collateral here, so the share is at most 10_000
+        (protected * denominator).div_ceil(collateral as u128).min(denominator) as u64
+    }
+
+    /// Time-weighted average collateral at `block_height`
+    ///
+    /// Blends the stored average with the collateral held since
+    /// `twa_updated_at`, weighting the latter by the blocks elapsed out of
+    /// `TWA_WINDOW_BLOCKS`. Collateral held for a single block moves the
+    /// average by only 1/`TWA_WINDOW_BLOCKS` of the change.
+    pub fn twa_collateral_at(&self, block_height: u64) -> u64 {
+        let window = crate::constants::redistribution::TWA_WINDOW_BLOCKS;
+        let elapsed = block_height.saturating_sub(self.twa_updated_at).min(window);
+
+        // Weighted mean of two u64 values: fits u64
+        let weighted = (self.twa_collateral as u128) * ((window - elapsed) as u128)
+            + (self.collateral as u128) * (elapsed as u128);
+        (weighted / window as u128) as u64
+    }
+
+    /// Copy of the vault with its average brought up to `block_height`
+    ///
+    /// Required before every collateral change, so the old collateral is
+    /// weighted by how long it was held.
+    pub fn averaged_at(&self, block_height: u64) -> Self {
+        Self {
+            twa_collateral: self.twa_collateral_at(block_height),
+            twa_updated_at: block_height,
+            ..self.clone()
+        }
+    }
+
+    /// Lifetime stats rolled forward to `block_height`
+    ///
+    /// Adds the blocks since the stats were last updated, and the interest
+    /// the debt accrued over them. Fee and mint totals are added by the
+    /// validator of the action that charges them.
+    pub fn stats_at(&self, block_height: u64) -> VaultStats {
+        let since = self.stats.last_updated.max(self.created_at);
+        let blocks = block_height.saturating_sub(since);
+        VaultStats {
+            total_interest_paid: self
+                .stats
+                .total_interest_paid
+                .saturating_add(self.interest_over(blocks)),
+            blocks_active: self.stats.blocks_active.saturating_add(blocks),
+            last_updated: block_height.max(since),
+            ..self.stats
+        }
+    }
+
+    /// Lifetime stats of a vault pulled back from liquidation at `block_height`
+    pub fn rescued_stats_at(&self, block_height: u64) -> VaultStats {
+        let mut stats = self.stats_at(block_height);
+        stats.times_liquidated_avoided = stats.times_liquidated_avoided.saturating_add(1);
+        stats
+    }
+
     /// Calculate accrued interest based on blocks elapsed
     /// Uses simple interest: principal * rate * time / (blocks_per_year * 10000)
     pub fn calculate_interest(&self, current_block: u64) -> u64 {
-        let blocks_elapsed = current_block.saturating_sub(self.last_updated);
+        self.interest_over(current_block.saturating_sub(self.last_updated))
+    }
+
+    /// Simple interest on the current debt over `blocks_elapsed`
+    fn interest_over(&self, blocks_elapsed: u64) -> u64 {
         if blocks_elapsed == 0 || self.interest_rate_bps == 0 {
             return 0;
         }
@@ -148,6 +368,7 @@ impl Vault {
 
 /// Global protocol state
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct ProtocolState {
     /// Total collateral in the system (satoshis)
     pub total_collateral: u64,
@@ -163,6 +384,36 @@ pub struct ProtocolState {
     pub admin: Address,
     /// Whether protocol is paused
     pub is_paused: bool,
+    /// Flash mint fee (in basis points, admin-configurable)
+    #[serde(default)]
+    pub flash_fee_bps: u64,
+    /// Protocol fees collected in zkUSD and held by the protocol state
+    #[serde(default)]
+    pub accumulated_fees: u64,
+    /// Address that `accumulated_fees` are paid out to when distributed
+    #[serde(default)]
+    pub fee_recipient: Address,
+    /// Cumulative interest index per unit of debt-bps (see `interest` module)
+    #[serde(default)]
+    pub interest_index: u128,
+    /// Block of the last global interest accrual
+    #[serde(default)]
+    pub last_interest_accrual_block: u64,
+    /// Sum of debt * interest_rate_bps over active vaults
+    #[serde(default)]
+    pub rate_weighted_debt: u128,
+    /// Interest accrued globally but not yet reconciled onto vaults (scaled)
+    #[serde(default)]
+    pub pending_interest: u128,
+    /// Principal of active variable-rate vaults
+    #[serde(default)]
+    pub variable_debt: u64,
+    /// Recent changes of the variable rate
+    #[serde(default)]
+    pub rate_history: crate::interest::RateHistory,
+    /// Bootstrap mode, when the protocol launched in it
+    #[serde(default)]
+    pub bootstrap: Option<crate::bootstrap::BootstrapState>,
 }
 
 impl ProtocolState {
@@ -176,14 +427,113 @@ impl ProtocolState {
             last_fee_update_block: 0,
             admin,
             is_paused: false,
+            flash_fee_bps: crate::constants::fees::DEFAULT_FLASH_FEE_BPS,
+            accumulated_fees: 0,
+            fee_recipient: admin,
+            interest_index: crate::interest::INDEX_PRECISION,
+            last_interest_accrual_block: 0,
+            rate_weighted_debt: 0,
+            pending_interest: 0,
+            variable_debt: 0,
+            rate_history: crate::interest::RateHistory::default(),
+            bootstrap: None,
+        }
+    }
+}
+
+/// Split of protocol fees between their destinations (BPS, sums to 100%)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct FeeDistribution {
+    /// Share sent to the protocol treasury
+    pub treasury_bps: u64,
+    /// Share injected into the Stability Pool as protocol-owned stability
+    pub stability_pool_bps: u64,
+    /// Share paid to zkUSD stakers
+    pub staking_bps: u64,
+}
+
+impl Default for FeeDistribution {
+    fn default() -> Self {
+        Self {
+            treasury_bps: crate::constants::fees::DEFAULT_FEE_TREASURY_BPS,
+            stability_pool_bps: crate::constants::fees::DEFAULT_FEE_STABILITY_POOL_BPS,
+            staking_bps: crate::constants::fees::DEFAULT_FEE_STAKING_BPS,
+        }
+    }
+}
+
+impl FeeDistribution {
+    /// Sum of all shares (BPS)
+    pub fn total_bps(&self) -> u64 {
+        self.treasury_bps
+            .saturating_add(self.stability_pool_bps)
+            .saturating_add(self.staking_bps)
+    }
+
+    /// Require the shares to sum to exactly 100%
+    pub fn validate(&self) -> crate::ZkUsdResult<()> {
+        let total_bps = self.total_bps();
+        if total_bps != crate::constants::fees::BPS_DENOMINATOR {
+            return Err(crate::ZkUsdError::InvalidFeeDistribution { total_bps });
+        }
+        Ok(())
+    }
+
+    /// Split a fee across the destinations
+    ///
+    /// The Stability Pool and staking shares round down; the treasury takes
+    /// the remainder so the parts always sum to the fee.
+    pub fn split(&self, fee: u64) -> FeeSplit {
+        let share = |bps: u64| {
+            (fee as u128 * bps as u128 / crate::constants::fees::BPS_DENOMINATOR as u128) as u64
+        };
+        let stability_pool = share(self.stability_pool_bps);
+        let staking = share(self.staking_bps);
+        FeeSplit {
+            treasury: fee.saturating_sub(stability_pool).saturating_sub(staking),
+            stability_pool,
+            staking,
         }
     }
 }
 
+/// Protocol fee amounts per destination (zkUSD base units)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct FeeSplit {
+    /// Amount for the protocol treasury
+    pub treasury: u64,
+    /// Amount for the Stability Pool
+    pub stability_pool: u64,
+    /// Amount for zkUSD stakers
+    pub staking: u64,
+}
+
+impl FeeSplit {
+    /// Sum of all destinations
+    pub fn total(&self) -> u64 {
+        self.treasury
+            .saturating_add(self.stability_pool)
+            .saturating_add(self.staking)
+    }
+
+    /// Add another split destination by destination
+    pub fn checked_add(&self, other: &FeeSplit) -> crate::ZkUsdResult<FeeSplit> {
+        use crate::math::safe_add;
+        Ok(FeeSplit {
+            treasury: safe_add(self.treasury, other.treasury)?,
+            stability_pool: safe_add(self.stability_pool, other.stability_pool)?,
+            staking: safe_add(self.staking, other.staking)?,
+        })
+    }
+}
+
 // ============ Oracle Types ============
 
 /// Price data from oracle
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct PriceData {
     /// Price in USD with 8 decimal places (e.g., 100000_00000000 = $100,000)
     pub price: u64,
@@ -193,10 +543,15 @@ pub struct PriceData {
     pub source: PriceSource,
     /// Confidence level (0-100)
     pub confidence: u8,
+    /// First block at which spells may use the price; an update takes
+    /// effect the block after it is published
+    #[serde(default)]
+    pub effective_from_block: u64,
 }
 
 /// Price source identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum PriceSource {
     /// Mock oracle for testing
     #[default]
@@ -212,26 +567,337 @@ pub enum PriceSource {
 }
 
 impl PriceData {
-    /// Creates a new price data entry
+    /// Creates a new price data entry, in effect from `block`
     pub fn new(price: u64, block: u64, source: PriceSource) -> Self {
         Self {
             price,
             timestamp_block: block,
             source,
             confidence: 100,
+            effective_from_block: block,
         }
     }
 
+    /// Price published by an oracle update at `block`, in effect from the
+    /// next block
+    pub fn published(price: u64, block: u64, source: PriceSource) -> Self {
+        Self { effective_from_block: block.saturating_add(1), ..Self::new(price, block, source) }
+    }
+
+    /// Returns true once spells at `current_block` may use the price
+    pub fn is_effective(&self, current_block: u64) -> bool {
+        current_block >= self.effective_from_block
+    }
+
     /// Checks if price is stale based on current block
     pub fn is_stale(&self, current_block: u64) -> bool {
         current_block.saturating_sub(self.timestamp_block) > crate::constants::oracle::MAX_PRICE_AGE_BLOCKS
     }
+
+    /// Confidence decayed linearly with price age
+    ///
+    /// Full `confidence` on the update block, falling to zero at
+    /// `MAX_PRICE_AGE_BLOCKS` and staying there once the price is stale.
+    pub fn effective_confidence(&self, current_block: u64) -> u8 {
+        let max_age = crate::constants::oracle::MAX_PRICE_AGE_BLOCKS;
+        let age = current_block.saturating_sub(self.timestamp_block);
+        if age >= max_age {
+            return 0;
+        }
+        // Safe: result <= confidence <= u8::MAX
+        (self.confidence as u64 * (max_age - age) / max_age) as u8
+    }
+}
+
+/// Price oracle circuit breaker
+///
+/// Trips when an update lands more than `CIRCUIT_BREAKER_TRIP_BPS` from the
+/// reference price, which moves to the previous update once it is older
+/// than `CIRCUIT_BREAKER_WINDOW_BLOCKS`. While active, liquidations and
+/// redemptions are refused; it lapses after `CIRCUIT_BREAKER_COOLDOWN_BLOCKS`
+/// or on an admin reset.
+///
+/// The oracle guardian may also freeze the oracle by hand. A frozen breaker
+/// stays active, and price updates are refused, until the admin resets it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct CircuitBreakerState {
+    /// Whether the breaker has tripped (it may since have cooled down)
+    pub tripped: bool,
+    /// Block the breaker last tripped at
+    pub tripped_at: u64,
+    /// Price moves are measured from
+    pub reference_price: u64,
+    /// Block the reference price was published at
+    pub reference_block: u64,
+    /// Whether the guardian froze the oracle (cleared only by an admin reset)
+    #[serde(default)]
+    pub guardian_frozen: bool,
+}
+
+/// Prices an oracle accepts as plausible for its asset
+///
+/// Defaults to the BTC range ($1k - $10M); an oracle pricing another
+/// collateral asset is configured with bounds that fit that asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct PriceBounds {
+    /// Lowest accepted price (8 decimals)
+    pub min_price: u64,
+    /// Highest accepted price (8 decimals)
+    pub max_price: u64,
+}
+
+impl Default for PriceBounds {
+    fn default() -> Self {
+        use crate::constants::oracle::{DEFAULT_MAX_REASONABLE_PRICE, DEFAULT_MIN_REASONABLE_PRICE};
+        Self { min_price: DEFAULT_MIN_REASONABLE_PRICE, max_price: DEFAULT_MAX_REASONABLE_PRICE }
+    }
+}
+
+/// Identifier of a price feed, e.g. `*b"USDCUSD\0"`
+pub type FeedId = [u8; 8];
+
+/// Secondary price feed carried by the oracle next to its BTC/USD price
+/// (a stablecoin's USD price, a market rate), with its own operator and
+/// limits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct Feed {
+    /// Feed identifier
+    pub feed_id: FeedId,
+    /// Latest price, published like the oracle's own
+    pub price: PriceData,
+    /// Prices accepted for the feed's asset
+    pub bounds: PriceBounds,
+    /// Largest deviation a single update may make (BPS)
+    pub max_deviation_bps: u64,
+    /// Key allowed to publish the feed's prices
+    pub operator: Address,
+}
+
+impl PriceBounds {
+    /// Whether `price` lies within the bounds (inclusive)
+    pub fn contains(&self, price: u64) -> bool {
+        (self.min_price..=self.max_price).contains(&price)
+    }
+
+    /// Whether the bounds form a non-empty range of positive prices
+    pub fn is_valid(&self) -> bool {
+        self.min_price > 0 && self.min_price < self.max_price
+    }
+}
+
+impl CircuitBreakerState {
+    /// First block after the cooldown of the last trip (never, while frozen)
+    pub fn until_block(&self) -> u64 {
+        if self.guardian_frozen {
+            return u64::MAX;
+        }
+        self.tripped_at.saturating_add(crate::constants::oracle::CIRCUIT_BREAKER_COOLDOWN_BLOCKS)
+    }
+
+    /// Whether the breaker is tripped and still cooling down
+    pub fn is_active(&self, current_block: u64) -> bool {
+        self.tripped && current_block < self.until_block()
+    }
+
+    /// Breaker after the price moves from `old` to `new_price`
+    ///
+    /// Updates keep flowing while the breaker is active and leave it as is;
+    /// otherwise a lapsed trip clears and an expired reference moves up.
+    pub fn after_update(&self, old: &PriceData, new_price: u64, block_height: u64) -> Self {
+        use crate::constants::oracle::{CIRCUIT_BREAKER_TRIP_BPS, CIRCUIT_BREAKER_WINDOW_BLOCKS};
+
+        if self.is_active(block_height) {
+            return *self;
+        }
+        let mut next = Self { tripped: false, ..*self };
+        let expired = block_height.saturating_sub(self.reference_block) > CIRCUIT_BREAKER_WINDOW_BLOCKS;
+        if self.tripped || self.reference_price == 0 || expired {
+            next.reference_price = old.price;
+            next.reference_block = old.timestamp_block;
+        }
+
+        let reference = u128::from(next.reference_price.max(1));
+        let move_bps = u128::from(next.reference_price.abs_diff(new_price)) * 10_000 / reference;
+        if move_bps > u128::from(CIRCUIT_BREAKER_TRIP_BPS) {
+            next.tripped = true;
+            next.tripped_at = block_height;
+        }
+        next
+    }
+
+    /// Breaker frozen by the guardian at `block_height`
+    ///
+    /// An active breaker keeps its reference, the last good price from
+    /// before the move that tripped it; otherwise `price` becomes it.
+    pub fn freeze(&self, price: &PriceData, block_height: u64) -> Self {
+        let mut next = Self { tripped: true, tripped_at: block_height, guardian_frozen: true, ..*self };
+        if !self.is_active(block_height) {
+            next.reference_price = price.price;
+            next.reference_block = price.timestamp_block;
+        }
+        next
+    }
+
+    /// Breaker cleared by the admin, measuring from `price` again
+    pub fn reset(&self, price: &PriceData) -> Self {
+        Self {
+            tripped: false,
+            reference_price: price.price,
+            reference_block: price.timestamp_block,
+            guardian_frozen: false,
+            ..*self
+        }
+    }
+}
+
+/// Oracle state as seen by the contracts reading its price
+///
+/// Carries the full price data and liveness rather than a bare price, so a
+/// consumer can check freshness itself (see `validation::require_fresh_price`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct OracleSnapshot {
+    /// Latest published price
+    pub price: PriceData,
+    /// Whether the oracle is active
+    pub is_active: bool,
+    /// Circuit breaker on sudden price moves
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerState,
+    /// Price before the latest update, in effect until the latest is
+    #[serde(default)]
+    pub previous_price: PriceData,
+    /// Operator publishing the price
+    #[serde(default)]
+    pub operator: Address,
+}
+
+impl OracleSnapshot {
+    /// Snapshot of an active oracle publishing `price`
+    pub fn active(price: PriceData) -> Self {
+        Self {
+            price,
+            is_active: true,
+            circuit_breaker: CircuitBreakerState::default(),
+            previous_price: PriceData::default(),
+            operator: [0u8; 32],
+        }
+    }
+
+    /// Price in effect at `current_block`: the latest price from the block
+    /// after its update, the previous one on the update block itself
+    ///
+    /// `None` if the latest price is not yet in effect and there is no
+    /// previous price to fall back to.
+    pub fn effective_price(&self, current_block: u64) -> Option<PriceData> {
+        if self.price.is_effective(current_block) {
+            return Some(self.price.clone());
+        }
+        (self.previous_price.price > 0).then(|| self.previous_price.clone())
+    }
+
+    /// Price confidence decayed by age; an inactive oracle has none
+    pub fn effective_confidence(&self, current_block: u64) -> u8 {
+        if !self.is_active {
+            return 0;
+        }
+        self.price.effective_confidence(current_block)
+    }
+
+    /// Last price before the move that tripped the circuit breaker
+    ///
+    /// `None` if the breaker never recorded a reference price.
+    pub fn last_good_price(&self) -> Option<PriceData> {
+        let breaker = &self.circuit_breaker;
+        (breaker.reference_price > 0).then(|| PriceData {
+            price: breaker.reference_price,
+            timestamp_block: breaker.reference_block,
+            ..self.price.clone()
+        })
+    }
+}
+
+/// A price observation signed by an off-chain feed
+///
+/// The feed signs `message()` with the ed25519 key `source_pubkey`; the
+/// oracle accepts aggregates of attestations from its registered sources.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct PriceAttestation {
+    /// Ed25519 public key of the signing feed
+    pub source_pubkey: [u8; 32],
+    /// Observed price (8 decimals)
+    pub price: u64,
+    /// Block height at which the feed observed the price
+    pub observed_at_block: u64,
+    /// Ed25519 signature over `message()`
+    #[serde(with = "signature_bytes")]
+    pub signature: [u8; 64],
+}
+
+impl PriceAttestation {
+    /// Bytes the feed signs: the domain tag, then price and block (LE)
+    pub fn message(&self) -> [u8; ATTESTATION_MESSAGE_LEN] {
+        let mut message = [0u8; ATTESTATION_MESSAGE_LEN];
+        let (tag, rest) = message.split_at_mut(crate::constants::oracle::ATTESTATION_DOMAIN.len());
+        tag.copy_from_slice(crate::constants::oracle::ATTESTATION_DOMAIN);
+        rest[..8].copy_from_slice(&self.price.to_le_bytes());
+        rest[8..].copy_from_slice(&self.observed_at_block.to_le_bytes());
+        message
+    }
+}
+
+/// Length of a signed attestation message
+pub const ATTESTATION_MESSAGE_LEN: usize = crate::constants::oracle::ATTESTATION_DOMAIN.len() + 16;
+
+/// Serde for 64-byte signatures, past the array sizes serde derives for
+mod signature_bytes {
+    use core::fmt;
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 64], D::Error> {
+        deserializer.deserialize_bytes(SignatureVisitor)
+    }
+
+    struct SignatureVisitor;
+
+    impl<'de> Visitor<'de> for SignatureVisitor {
+        type Value = [u8; 64];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("64 signature bytes")
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = [0u8; 64];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            if seq.next_element::<u8>()?.is_some() {
+                return Err(A::Error::invalid_length(65, &self));
+            }
+            Ok(bytes)
+        }
+    }
 }
 
 // ============ Stability Pool Types ============
 
 /// Individual deposit in stability pool
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct StabilityDeposit {
     /// Depositor's address
     pub owner: Address,
@@ -247,11 +913,53 @@ pub struct StabilityDeposit {
     pub snapshot_scale: u64,
     /// Block height of last update
     pub last_updated: u64,
+    /// Frontend the deposit was made through, fixed at the first deposit
+    #[serde(default)]
+    pub frontend_tag: Option<Address>,
+    /// Share of the BTC gains the depositor keeps when tagged (BPS), the
+    /// frontend's registered kickback rate at the first deposit
+    #[serde(default)]
+    pub kickback_bps: u64,
+}
+
+impl StabilityDeposit {
+    /// Split a BTC gain between the depositor and the deposit's frontend
+    ///
+    /// The depositor's share rounds down and the frontend takes the
+    /// remainder; an untagged deposit keeps the whole gain.
+    pub fn split_btc_gain(&self, gain: u64) -> crate::ZkUsdResult<BtcGainSplit> {
+        if self.frontend_tag.is_none() {
+            return Ok(BtcGainSplit { depositor: gain, frontend: 0 });
+        }
+        let kickback_bps = self.kickback_bps.min(crate::constants::fees::BPS_DENOMINATOR);
+        let depositor = crate::math::safe_mul_div(gain, kickback_bps, crate::constants::fees::BPS_DENOMINATOR)?;
+        Ok(BtcGainSplit { depositor, frontend: gain - depositor })
+    }
+}
+
+/// BTC gain amounts per recipient (satoshis)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct BtcGainSplit {
+    /// Amount paid to the depositor
+    pub depositor: u64,
+    /// Amount paid to the deposit's frontend
+    pub frontend: u64,
+}
+
+impl BtcGainSplit {
+    /// Sum of both shares, the whole gain
+    pub fn total(&self) -> u64 {
+        self.depositor.saturating_add(self.frontend)
+    }
 }
 
 /// Global stability pool state
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct StabilityPoolState {
+    /// Layout version (see `versioning`)
+    #[serde(default = "crate::versioning::initial_state_version")]
+    pub version: u8,
     /// Total zkUSD deposited
     pub total_zkusd: u64,
     /// Total BTC from liquidations (pending distribution)
@@ -266,12 +974,72 @@ pub struct StabilityPoolState {
     pub current_scale: u64,
     /// Number of depositors
     pub depositor_count: u64,
+    /// Final S values of recently closed epochs (oldest first)
+    #[serde(default)]
+    pub epoch_snapshots: Vec<EpochSnapshot>,
+    /// Most recent offsets (oldest first), for yield estimation
+    #[serde(default)]
+    pub recent_offsets: Vec<OffsetSample>,
+    /// zkUSD swept from dust deposits, held for the protocol rather than
+    /// any depositor
+    #[serde(default)]
+    pub protocol_dust: u64,
+}
+
+/// Final S values of an epoch, recorded when the pool is emptied
+///
+/// Lets deposits from that epoch claim the BTC they earned after
+/// `sum_s` has been reset for the next epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct EpochSnapshot {
+    /// Epoch that was closed
+    pub epoch: u64,
+    /// Scale the epoch closed at
+    pub final_scale: u64,
+    /// S at `final_scale` when the epoch closed
+    pub final_s_at_scale: u128,
+    /// S accrued at `final_scale + 1` (non-zero only if the closing
+    /// offset crossed a scale boundary)
+    pub final_s_at_scale_plus_one: u128,
+}
+
+/// A liquidation offset absorbed by the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct OffsetSample {
+    /// Debt absorbed (zkUSD)
+    pub debt: u64,
+    /// Collateral gained (sats)
+    pub collateral: u64,
+    /// Block height of the offset
+    pub block: u64,
+}
+
+/// All-zero pool at the current version (unlike `new`, with P unset)
+impl Default for StabilityPoolState {
+    fn default() -> Self {
+        Self {
+            version: crate::migrations::V2,
+            total_zkusd: 0,
+            total_btc: 0,
+            product_p: 0,
+            sum_s: 0,
+            current_epoch: 0,
+            current_scale: 0,
+            depositor_count: 0,
+            epoch_snapshots: Vec::new(),
+            recent_offsets: Vec::new(),
+            protocol_dust: 0,
+        }
+    }
 }
 
 impl StabilityPoolState {
     /// Creates initial stability pool state
     pub fn new() -> Self {
         Self {
+            version: crate::migrations::V2,
             total_zkusd: 0,
             total_btc: 0,
             product_p: crate::constants::stability_pool::SCALE_FACTOR,
@@ -279,6 +1047,39 @@ impl StabilityPoolState {
             current_epoch: 0,
             current_scale: 0,
             depositor_count: 0,
+            epoch_snapshots: Vec::new(),
+            recent_offsets: Vec::new(),
+            protocol_dust: 0,
+        }
+    }
+
+    /// Canonical commitment to this state (see `commitment` module)
+    pub fn commitment(&self) -> [u8; 32] {
+        crate::commitment::state_commitment(crate::commitment::CommittedApp::StabilityPool, self)
+    }
+
+    /// Final S values of a closed epoch, if still retained
+    pub fn epoch_snapshot(&self, epoch: u64) -> Option<&EpochSnapshot> {
+        self.epoch_snapshots.iter().find(|s| s.epoch == epoch)
+    }
+
+    /// Record a closed epoch, evicting the oldest beyond `MAX_EPOCH_SNAPSHOTS`
+    pub fn record_epoch_snapshot(&mut self, snapshot: EpochSnapshot) {
+        self.epoch_snapshots.push(snapshot);
+        let max = crate::constants::stability_pool::MAX_EPOCH_SNAPSHOTS;
+        if self.epoch_snapshots.len() > max {
+            let excess = self.epoch_snapshots.len() - max;
+            self.epoch_snapshots.drain(..excess);
+        }
+    }
+
+    /// Record an offset, evicting the oldest beyond `MAX_OFFSET_SAMPLES`
+    pub fn record_offset(&mut self, sample: OffsetSample) {
+        self.recent_offsets.push(sample);
+        let max = crate::constants::stability_pool::MAX_OFFSET_SAMPLES;
+        if self.recent_offsets.len() > max {
+            let excess = self.recent_offsets.len() - max;
+            self.recent_offsets.drain(..excess);
         }
     }
 }
@@ -313,34 +1114,51 @@ impl Default for TokenMetadata {
 
 /// Actions for zkUSD Token contract
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum TokenAction {
-    /// Transfer tokens between addresses
-    Transfer { from: Address, to: Address, amount: u64 },
+    /// Transfer tokens between addresses, optionally tagged with a memo
+    Transfer {
+        from: Address,
+        to: Address,
+        amount: ZkUsd,
+        #[serde(default)]
+        memo: Option<Memo>,
+    },
     /// Mint new tokens (only from authorized contracts)
-    Mint { to: Address, amount: u64 },
+    Mint { to: Address, amount: ZkUsd },
     /// Burn tokens (repay debt)
-    Burn { from: Address, amount: u64 },
+    Burn { from: Address, amount: ZkUsd },
+    /// Mint new tokens split across several recipients
+    MintMulti { recipients: Vec<(Address, ZkUsd)> },
+    /// Merge an owner's balances into fewer UTXOs
+    Consolidate { owner: Address },
 }
 
 /// Actions for Vault Manager contract
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum VaultAction {
     /// Open a new vault
-    OpenVault { collateral: u64, debt: u64 },
+    OpenVault { collateral: Sats, debt: ZkUsd },
     /// Close an existing vault
     CloseVault { vault_id: VaultId },
     /// Add collateral to vault
-    AddCollateral { vault_id: VaultId, amount: u64 },
+    AddCollateral { vault_id: VaultId, amount: Sats },
     /// Withdraw collateral from vault
-    WithdrawCollateral { vault_id: VaultId, amount: u64 },
+    WithdrawCollateral { vault_id: VaultId, amount: Sats },
     /// Mint additional debt
-    MintDebt { vault_id: VaultId, amount: u64 },
+    MintDebt { vault_id: VaultId, amount: ZkUsd },
     /// Repay debt
-    RepayDebt { vault_id: VaultId, amount: u64 },
+    RepayDebt { vault_id: VaultId, amount: ZkUsd },
     /// Liquidate undercollateralized vault
     Liquidate { vault_id: VaultId },
-    /// Redeem zkUSD for collateral
-    Redeem { amount: u64 },
+    /// Redeem zkUSD for collateral, paying at least `min_btc_out`
+    /// satoshis (0 disables the slippage floor)
+    Redeem {
+        amount: ZkUsd,
+        #[serde(default)]
+        min_btc_out: Sats,
+    },
 
     // ============ Advanced UTXO-Native Operations ============
 
@@ -348,7 +1166,7 @@ pub enum VaultAction {
     /// UTXO model ensures atomicity without callbacks
     FlashMint {
         /// Amount to flash mint
-        amount: u64,
+        amount: ZkUsd,
         /// Purpose of the flash mint (for tracking)
         purpose: u8,
     },
@@ -358,12 +1176,12 @@ pub enum VaultAction {
     AtomicRescue {
         /// Vault to rescue
         vault_id: VaultId,
-        /// Collateral rescuer is adding (satoshis)
-        collateral_to_add: u64,
-        /// Debt rescuer is repaying (zkUSD)
-        debt_to_repay: u64,
-        /// Discount for rescuer (in BTC satoshis)
-        rescuer_discount: u64,
+        /// Collateral rescuer is adding
+        collateral_to_add: Sats,
+        /// Debt rescuer is repaying
+        debt_to_repay: ZkUsd,
+        /// Collateral the rescuer takes as a discount (an amount, not a rate)
+        rescuer_discount: Sats,
     },
 
     /// Purchase insurance charm for a vault
@@ -371,9 +1189,9 @@ pub enum VaultAction {
         /// Vault to insure
         vault_id: VaultId,
         /// Coverage amount in BTC
-        coverage_btc: u64,
+        coverage_btc: Sats,
         /// Premium paid in zkUSD
-        premium: u64,
+        premium: ZkUsd,
         /// ICR threshold that triggers insurance
         trigger_icr: u64,
     },
@@ -393,23 +1211,168 @@ pub enum VaultAction {
         /// New owner address
         new_owner: Address,
     },
+
+    // ============ Admin Operations ============
+
+    /// Set the flash mint fee (admin only)
+    SetFlashFee {
+        /// New fee in basis points
+        fee_bps: u64,
+    },
+
+    /// Set the split of protocol fees between destinations (admin only)
+    SetFeeDistribution {
+        /// New split (must sum to 100%)
+        distribution: FeeDistribution,
+    },
+
+    // ============ Vault Key Management ============
+
+    /// Set or clear the vault's operator key (owner only)
+    ///
+    /// Appended after the admin operations to keep existing borsh
+    /// discriminants stable.
+    SetVaultOperator {
+        /// Vault to delegate
+        vault_id: VaultId,
+        /// New operator, or `None` to revoke
+        operator: Option<Address>,
+    },
+
+    // ============ Fee Maintenance ============
+
+    /// Apply the base rate decay since `last_fee_update_block`
+    /// (permissionless)
+    PokeBaseRate {},
+    /// Protect a share of the vault's collateral from redemptions, raising
+    /// its interest rate (owner only)
+    SetProtection {
+        /// Vault to protect
+        vault_id: VaultId,
+        /// Protected share in basis points (at most 30%)
+        bps: u64,
+    },
+
+    // ============ Vault Inheritance ============
+
+    /// Designate or clear the key that may take over the vault after a
+    /// period of owner inactivity (owner only)
+    SetBeneficiary {
+        /// Vault to designate a beneficiary for
+        vault_id: VaultId,
+        /// New beneficiary, or `None` to clear it
+        beneficiary: Option<Address>,
+        /// Blocks without an owner spell before the beneficiary may claim
+        inactivity_blocks: u64,
+    },
+    /// Take over ownership of an inactive vault (designated beneficiary only)
+    ClaimAsBeneficiary {
+        /// Vault to claim
+        vault_id: VaultId,
+    },
+
+    // ============ Two-Phase Liquidation ============
+
+    /// Start liquidating a vault too large for a single `Liquidate`,
+    /// freezing it at the current price (permissionless)
+    BeginLiquidation {
+        /// Vault to liquidate
+        vault_id: VaultId,
+    },
+    /// Offset the next tranche of a `Liquidating` vault's debt against the
+    /// stability pool (permissionless)
+    ContinueLiquidation {
+        /// Vault being liquidated
+        vault_id: VaultId,
+        /// Debt this tranche offsets
+        debt_portion: ZkUsd,
+    },
+
+    // ============ Insurance Expiry ============
+
+    /// Retire an expired insurance charm, freeing its remaining coverage
+    /// from the insurance fund (permissionless)
+    ExpireInsurance {
+        /// Insurance charm ID
+        insurance_id: [u8; 32],
+        /// Vault the charm protected
+        vault_id: VaultId,
+    },
+
+    // ============ Bootstrap ============
+
+    /// Graduate the protocol from bootstrap mode once its criteria are met
+    /// (permissionless)
+    GraduateBootstrap {},
+
+    // ============ Interest Maintenance ============
+
+    /// Fold a vault's interest since it was last updated into its debt
+    /// (permissionless; a third party earns a bounty)
+    AccrueInterest {
+        /// Vault to poke
+        vault_id: VaultId,
+    },
+}
+
+impl VaultAction {
+    /// Confirmations to wait for on the spells this action builds on
+    /// (see `constants::reorg`)
+    pub fn min_confirmations_hint(&self) -> u8 {
+        use crate::constants::reorg;
+        match self {
+            Self::ContinueLiquidation { .. } => reorg::LIQUIDATION_DEPENDENT_CONFIRMATIONS,
+            Self::Liquidate { .. }
+            | Self::BeginLiquidation { .. }
+            | Self::Redeem { .. }
+            | Self::AtomicRescue { .. }
+            | Self::TriggerInsurance { .. } => reorg::PRICE_DEPENDENT_CONFIRMATIONS,
+            _ => reorg::DEFAULT_CONFIRMATIONS,
+        }
+    }
 }
 
 /// Actions for Stability Pool contract
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum StabilityPoolAction {
     /// Deposit zkUSD into pool
-    Deposit { amount: u64 },
+    Deposit { amount: ZkUsd },
     /// Withdraw zkUSD from pool
-    Withdraw { amount: u64 },
+    Withdraw { amount: ZkUsd },
     /// Claim accumulated BTC rewards
     ClaimBtc,
     /// Offset debt during liquidation (internal)
-    Offset { debt: u64, collateral: u64 },
+    Offset { debt: ZkUsd, collateral: Sats },
+    /// Claim accumulated BTC rewards as collateral of the depositor's vault
+    ClaimBtcToVault { vault_id: VaultId },
+    /// Close a deposit liquidations have worn down to dust, paying out its
+    /// BTC gain (callable by anyone)
+    SweepDustDeposit { depositor: Address },
+    /// Register a frontend deposits may be tagged with, keeping
+    /// `kickback_bps` of their BTC gains for the depositor and paying the
+    /// rest to `payout_script` (admin only)
+    RegisterFrontend { frontend: Address, kickback_bps: u64, payout_script: Vec<u8> },
+}
+
+impl StabilityPoolAction {
+    /// Confirmations to wait for on the spells this action builds on
+    /// (see `constants::reorg`)
+    pub fn min_confirmations_hint(&self) -> u8 {
+        use crate::constants::reorg;
+        match self {
+            Self::Withdraw { .. } | Self::ClaimBtc | Self::ClaimBtcToVault { .. } | Self::SweepDustDeposit { .. } => {
+                reorg::LIQUIDATION_DEPENDENT_CONFIRMATIONS
+            }
+            Self::Offset { .. } => reorg::PRICE_DEPENDENT_CONFIRMATIONS,
+            Self::Deposit { .. } | Self::RegisterFrontend { .. } => reorg::DEFAULT_CONFIRMATIONS,
+        }
+    }
 }
 
 /// Actions for Price Oracle contract
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub enum OracleAction {
     /// Initialize oracle with initial state (admin and operator only)
     Initialize {
@@ -421,6 +1384,36 @@ pub enum OracleAction {
     UpdatePrice { price: u64 },
     /// Set oracle operator
     SetOperator { operator: Address },
+    /// Tune the price update rate limits (admin only)
+    SetUpdateLimits {
+        min_update_interval_blocks: u64,
+        max_cumulative_deviation_bps: u64,
+    },
+    /// Tune how fast the deviation limit grows between updates (admin only)
+    SetDeviationScaling { deviation_scaling_bps_per_block: u64 },
+    /// Clear a tripped circuit breaker before its cooldown ends, or lift a
+    /// guardian freeze (admin only)
+    ResetCircuitBreaker,
+    /// Replace the feeds whose attestations `AggregateUpdate` accepts
+    /// (admin only)
+    SetAttestationSources { sources: Vec<[u8; 32]> },
+    /// Set the range of prices accepted for the oracle's asset (admin only)
+    SetPriceBounds { bounds: PriceBounds },
+    /// Publish the median of signed attestations from a quorum of the
+    /// registered feeds (anyone may submit)
+    AggregateUpdate { attestations: Vec<PriceAttestation> },
+    /// Set the guardian allowed to freeze the oracle (admin only)
+    SetGuardian { guardian: Address },
+    /// Freeze price updates and trip the circuit breaker until an admin
+    /// reset (guardian only)
+    FreezePrice,
+    /// Publish a price on a feed (the feed's operator only); the BTC/USD
+    /// feed is the oracle's own price, updated as by `UpdatePrice`
+    UpdateFeed { feed_id: FeedId, price: u64 },
+    /// Add a secondary feed (admin only)
+    AddFeed { feed: Feed },
+    /// Remove a secondary feed (admin only)
+    RemoveFeed { feed_id: FeedId },
 }
 
 // ============ NEW: Advanced Pool Types (Mezo-inspired) ============
@@ -490,6 +1483,7 @@ impl DefaultPool {
 /// Protocol Controlled Value (PCV) - protocol's own stability deposit
 /// Acts as first line of defense in liquidations
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct ProtocolControlledValue {
     /// zkUSD deposited in stability pool by protocol
     pub stability_deposit: u64,
@@ -817,7 +1811,8 @@ impl SoftLiquidationVault {
             if band.status == LiquidationBandStatus::Healthy && band.contains_price(current_price) {
                 let to_convert = band.btc_to_convert(current_price);
                 if to_convert > 0 {
-                    let zkusd_value = (to_convert as u128 * current_price as u128 / 100_000_000) as u64;
+                    let zkusd_value = crate::math::btc_to_zkusd_floor(Sats(to_convert), current_price)
+                        .map_or(u64::MAX, ZkUsd::into_inner);
                     band.btc_amount = band.btc_amount.saturating_sub(to_convert);
                     band.zkusd_amount = band.zkusd_amount.saturating_add(zkusd_value);
                     band.status = LiquidationBandStatus::SoftLiquidation;
@@ -844,7 +1839,8 @@ impl SoftLiquidationVault {
         for band in &mut self.bands {
             if band.status == LiquidationBandStatus::SoftLiquidation && current_price > band.price_upper {
                 // Convert zkUSD back to BTC
-                let btc_recovered = (band.zkusd_amount as u128 * 100_000_000 / current_price as u128) as u64;
+                let btc_recovered = crate::math::zkusd_to_btc_floor(ZkUsd(band.zkusd_amount), current_price)
+                    .map_or(u64::MAX, Sats::into_inner);
                 band.btc_amount = band.btc_amount.saturating_add(btc_recovered);
                 band.zkusd_amount = 0;
                 band.status = LiquidationBandStatus::Healthy;
@@ -1007,13 +2003,29 @@ impl StakedZkUSD {
         }
     }
 
-    /// Calculate pending rewards based on current index
-    pub fn pending_rewards(&self, current_reward_index: u128) -> u64 {
+    /// Calculate pending rewards at `block_height`: nothing until the
+    /// position has been held for the pool's minimum holding period, then
+    /// everything accrued since it was staked
+    pub fn pending_rewards(&self, pool: &StakingPool, block_height: u64) -> u64 {
+        if !self.is_held_long_enough(pool, block_height) {
+            return 0;
+        }
+        self.accrued_rewards(pool.reward_index)
+    }
+
+    /// Returns true once the position has been staked for at least the
+    /// pool's minimum holding period
+    pub fn is_held_long_enough(&self, pool: &StakingPool, block_height: u64) -> bool {
+        block_height.saturating_sub(self.staked_at) >= pool.min_holding_blocks
+    }
+
+    /// Rewards accrued since the index snapshot, regardless of holding period
+    fn accrued_rewards(&self, current_reward_index: u128) -> u64 {
         if current_reward_index <= self.reward_index_snapshot {
             return 0;
         }
         let delta = current_reward_index - self.reward_index_snapshot;
-        ((self.staked_amount as u128 * delta) / 1_000_000_000_000_000_000) as u64
+        crate::math::saturating_u64((self.staked_amount as u128 * delta) / 1_000_000_000_000_000_000)
     }
 }
 
@@ -1030,6 +2042,10 @@ pub struct StakingPool {
     pub staker_count: u64,
     /// Accumulated fees pending distribution
     pub pending_fees: u64,
+    /// Blocks a position must be staked before it earns rewards; unstaking
+    /// sooner forfeits them to the pool (0 disables the holding period)
+    #[serde(default)]
+    pub min_holding_blocks: u64,
 }
 
 impl StakingPool {
@@ -1042,7 +2058,31 @@ impl StakingPool {
             reward_index: 0,
             staker_count: 0,
             pending_fees: 0,
+            min_holding_blocks: 0,
+        }
+    }
+
+    /// Stake `amount` for `owner` at `block_height`
+    pub fn stake(&mut self, owner: Address, amount: u64, block_height: u64) -> StakedZkUSD {
+        self.total_staked = self.total_staked.saturating_add(amount);
+        self.staker_count = self.staker_count.saturating_add(1);
+        StakedZkUSD::new(owner, amount, block_height, self.reward_index)
+    }
+
+    /// Unstake `position` at `block_height`, returning the rewards paid out
+    ///
+    /// A position unstaked before the minimum holding period forfeits its
+    /// rewards, which return to the pending fees for the remaining stakers.
+    pub fn unstake(&mut self, position: &StakedZkUSD, block_height: u64) -> u64 {
+        let rewards = position.rewards_earned.saturating_add(position.accrued_rewards(self.reward_index));
+        self.total_staked = self.total_staked.saturating_sub(position.staked_amount);
+        self.staker_count = self.staker_count.saturating_sub(1);
+        if position.is_held_long_enough(self, block_height) {
+            return rewards;
         }
+        self.total_rewards_distributed = self.total_rewards_distributed.saturating_sub(rewards);
+        self.add_fees(rewards);
+        0
     }
 
     /// Add fees to be distributed
@@ -1260,6 +2300,9 @@ pub struct RedemptionBatch {
     pub fee: u64,
     /// Redeemer address
     pub redeemer: Address,
+    /// Minimum BTC the redeemer accepts (0 disables the slippage floor)
+    #[serde(default)]
+    pub min_btc_out: u64,
 }
 
 impl RedemptionBatch {
@@ -1270,9 +2313,15 @@ impl RedemptionBatch {
             total_btc: 0,
             fee: 0,
             redeemer,
+            min_btc_out: 0,
         }
     }
 
+    /// Require the calculated BTC total to meet the redeemer's floor
+    pub fn require_min_output(&self) -> crate::ZkUsdResult<()> {
+        crate::validation::require_min_output(self.total_btc, self.min_btc_out)
+    }
+
     /// Add vault to redemption batch (maintains sorted order)
     pub fn add_vault(&mut self, order: RedemptionOrder) {
         // Insert in sorted order by interest_rate_bps
@@ -1292,7 +2341,8 @@ impl RedemptionBatch {
                 break;
             }
             let to_redeem = remaining.min(order.max_redeemable);
-            let btc_amount = (to_redeem as u128 * 100_000_000 / btc_price as u128) as u64;
+            // Rounds down in the protocol's favor; a zero price pays nothing
+            let btc_amount = crate::math::zkusd_to_btc_floor(ZkUsd(to_redeem), btc_price).map_or(0, Sats::into_inner);
             order.btc_per_zkusd = btc_amount;
             self.total_btc = self.total_btc.saturating_add(btc_amount);
             remaining = remaining.saturating_sub(to_redeem);
@@ -1304,10 +2354,66 @@ impl RedemptionBatch {
     }
 }
 
+// ============ Insurance Fund ============
+
+/// Insurance sold by the protocol, against the premiums backing it
+///
+/// Coverage is held in BTC and premiums in zkUSD, so capacity is checked at
+/// the price of the sale: outstanding coverage may be worth at most
+/// `max_coverage_bps` of the reserves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct InsuranceFund {
+    /// Premiums collected (zkUSD base units)
+    pub reserves: u64,
+    /// Coverage sold and not yet paid out or expired (sats)
+    pub outstanding_coverage: u64,
+    /// Most outstanding coverage value per unit of reserves (BPS, 0 = uncapped)
+    pub max_coverage_bps: u64,
+}
+
+impl InsuranceFund {
+    /// Fund after selling `coverage_btc` for `premium` on a vault whose
+    /// previous coverage of `replaced` sats it supersedes
+    pub fn sell(&self, replaced: u64, coverage_btc: u64, premium: u64) -> crate::ZkUsdResult<Self> {
+        use crate::math::safe_add;
+        Ok(Self {
+            reserves: safe_add(self.reserves, premium)?,
+            outstanding_coverage: safe_add(self.outstanding_coverage.saturating_sub(replaced), coverage_btc)?,
+            ..*self
+        })
+    }
+
+    /// Fund after `coverage_btc` of coverage is paid out or expires
+    ///
+    /// Saturates, since coverage sold before the fund was tracked was never
+    /// counted.
+    pub fn release(&self, coverage_btc: u64) -> Self {
+        Self { outstanding_coverage: self.outstanding_coverage.saturating_sub(coverage_btc), ..*self }
+    }
+
+    /// Require the outstanding coverage, valued at `btc_price`, to be within
+    /// the fund's capacity
+    pub fn require_capacity(&self, btc_price: u64) -> crate::ZkUsdResult<()> {
+        if self.max_coverage_bps == 0 {
+            return Ok(());
+        }
+        let coverage_value = crate::math::btc_to_zkusd_ceil(Sats(self.outstanding_coverage), btc_price)?.into_inner();
+        let capacity = crate::math::saturating_u64(
+            self.reserves as u128 * self.max_coverage_bps as u128 / crate::constants::fees::BPS_DENOMINATOR as u128,
+        );
+        if coverage_value > capacity {
+            return Err(crate::ZkUsdError::InsuranceCapacityExceeded { coverage_value, capacity });
+        }
+        Ok(())
+    }
+}
+
 // ============ NEW: Insurance Charm (Enhanced) ============
 
 /// Insurance Charm - tradeable insurance token
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct InsuranceCharm {
     /// Unique charm ID
     pub charm_id: [u8; 32],
@@ -1400,7 +2506,7 @@ impl InsuranceCharm {
             100
         };
 
-        (base * icr_multiplier as u128 / 100) as u64
+        crate::math::saturating_u64(base * icr_multiplier as u128 / 100)
     }
 }
 
@@ -1458,6 +2564,36 @@ mod tests {
         assert_eq!(vault.collateral, 100_000_000);
     }
 
+    #[test]
+    fn test_stats_roll_forward() {
+        let vault = Vault::new([1u8; 32], [2u8; 32], 100_000_000, 52_560_00000000, 100);
+
+        // One year at 1% APR on 52,560 zkUSD
+        let stats = vault.stats_at(100 + 52_560);
+        assert_eq!(stats.blocks_active, 52_560);
+        assert_eq!(stats.total_interest_paid, 525_60000000);
+        assert_eq!(stats.last_updated, 100 + 52_560);
+
+        // Legacy vaults (no stats yet) count from creation
+        let legacy = Vault { stats: VaultStats::default(), ..vault };
+        assert_eq!(legacy.stats_at(150).blocks_active, 50);
+    }
+
+    #[test]
+    fn test_price_takes_effect_the_block_after_its_update() {
+        let previous = PriceData::new(100_000_00000000, 90, PriceSource::Mock);
+        let oracle = OracleSnapshot {
+            previous_price: previous.clone(),
+            ..OracleSnapshot::active(PriceData::published(90_000_00000000, 100, PriceSource::Mock))
+        };
+        assert_eq!(oracle.effective_price(100), Some(previous));
+        assert_eq!(oracle.effective_price(101), Some(oracle.price.clone()));
+
+        // Nothing to fall back to on a first update
+        let first = OracleSnapshot { previous_price: PriceData::default(), ..oracle };
+        assert_eq!(first.effective_price(100), None);
+    }
+
     #[test]
     fn test_price_staleness() {
         let price = PriceData::new(100_000_00000000, 100, PriceSource::Mock);
@@ -1465,4 +2601,144 @@ mod tests {
         assert!(!price.is_stale(103)); // 3 blocks old, ok
         assert!(price.is_stale(110));  // 10 blocks old, stale
     }
+
+    #[test]
+    fn test_circuit_breaker_trips_and_lapses() {
+        use crate::constants::oracle::CIRCUIT_BREAKER_COOLDOWN_BLOCKS;
+
+        let mut price = PriceData::new(100_000_00000000, 100, PriceSource::Mock);
+        let mut breaker = CircuitBreakerState::default();
+
+        // Two steps of 12% within the window: the second passes 20%
+        for (block, new_price) in [(102, 88_000_00000000), (104, 77_440_00000000)] {
+            breaker = breaker.after_update(&price, new_price, block);
+            price = PriceData::new(new_price, block, PriceSource::Mock);
+        }
+        assert!(breaker.tripped);
+        assert_eq!(breaker.reference_price, 100_000_00000000);
+        assert_eq!(breaker.until_block(), 104 + CIRCUIT_BREAKER_COOLDOWN_BLOCKS);
+
+        // Active through the cooldown, even as prices keep coming
+        breaker = breaker.after_update(&price, 78_000_00000000, 106);
+        price = PriceData::new(78_000_00000000, 106, PriceSource::Mock);
+        assert!(breaker.is_active(breaker.until_block() - 1));
+        assert!(!breaker.is_active(breaker.until_block()));
+
+        // The next update after it lapses clears it and measures from there
+        let block = breaker.until_block() + 1;
+        breaker = breaker.after_update(&price, 79_000_00000000, block);
+        assert!(!breaker.tripped);
+        assert_eq!((breaker.reference_price, breaker.reference_block), (78_000_00000000, 106));
+
+        // The same total move spread wider than the window does not trip
+        let mut breaker = CircuitBreakerState::default();
+        let mut price = PriceData::new(100_000_00000000, 100, PriceSource::Mock);
+        for (block, new_price) in [(104, 88_000_00000000), (110, 77_440_00000000)] {
+            breaker = breaker.after_update(&price, new_price, block);
+            price = PriceData::new(new_price, block, PriceSource::Mock);
+        }
+        assert!(!breaker.tripped);
+    }
+
+    #[test]
+    fn test_guardian_freeze_holds_until_reset() {
+        use crate::constants::oracle::CIRCUIT_BREAKER_COOLDOWN_BLOCKS;
+
+        let price = PriceData::new(100_000_00000000, 100, PriceSource::Mock);
+        let frozen = CircuitBreakerState::default().freeze(&price, 102);
+        assert!(frozen.is_active(102 + CIRCUIT_BREAKER_COOLDOWN_BLOCKS * 10));
+        assert_eq!((frozen.reference_price, frozen.reference_block), (100_000_00000000, 100));
+
+        // Freezing an active breaker keeps the price from before the move
+        let tripped = CircuitBreakerState::default().after_update(&price, 70_000_00000000, 102);
+        let moved = PriceData::new(70_000_00000000, 102, PriceSource::Mock);
+        assert_eq!(tripped.freeze(&moved, 103).reference_price, 100_000_00000000);
+
+        let reset = frozen.reset(&price);
+        assert!(!reset.guardian_frozen);
+        assert!(!reset.is_active(103));
+    }
+
+    #[test]
+    fn test_fee_distribution_must_sum_to_100_percent() {
+        assert!(FeeDistribution::default().validate().is_ok());
+
+        let short = FeeDistribution { treasury_bps: 5_000, stability_pool_bps: 3_000, staking_bps: 1_000 };
+        assert_eq!(short.validate(), Err(crate::ZkUsdError::InvalidFeeDistribution { total_bps: 9_000 }));
+
+        let over = FeeDistribution { treasury_bps: 10_000, stability_pool_bps: 1, staking_bps: 0 };
+        assert_eq!(over.validate(), Err(crate::ZkUsdError::InvalidFeeDistribution { total_bps: 10_001 }));
+    }
+
+    #[test]
+    fn test_fee_split_remainder_goes_to_treasury() {
+        let distribution = FeeDistribution { treasury_bps: 3_334, stability_pool_bps: 3_333, staking_bps: 3_333 };
+
+        // 3,333 BPS of 100 is 33.33, rounded down for the pool and stakers
+        let split = distribution.split(100);
+        assert_eq!(split, FeeSplit { treasury: 34, stability_pool: 33, staking: 33 });
+        assert_eq!(split.total(), 100);
+    }
+
+    #[test]
+    fn test_redemption_batch_slippage_floor() {
+        let mut batch = RedemptionBatch::new([1u8; 32]);
+        batch.add_vault(RedemptionOrder {
+            vault_id: [2u8; 32],
+            interest_rate_bps: 100,
+            max_redeemable: 10_000 * crate::constants::token::ONE,
+            btc_per_zkusd: 0,
+        });
+        // 1,000 zkUSD at $100k pays 1,000,000 sats
+        batch.calculate(1_000 * crate::constants::token::ONE, 100_000 * crate::constants::token::ONE);
+        assert_eq!(batch.total_btc, 1_000_000);
+
+        assert!(batch.require_min_output().is_ok());
+        batch.min_btc_out = 1_000_001;
+        assert_eq!(
+            batch.require_min_output(),
+            Err(crate::ZkUsdError::SlippageExceeded { expected_min: 1_000_001, actual: 1_000_000 })
+        );
+    }
+    #[test]
+    fn test_staking_minimum_holding_period() {
+        let one = crate::constants::token::ONE;
+        let mut pool = StakingPool { min_holding_blocks: 1_000, ..StakingPool::new() };
+        let early = pool.stake([1u8; 32], 1_000 * one, 100);
+        let held = pool.stake([2u8; 32], 4_000 * one, 100);
+        pool.add_fees(500 * one);
+        pool.distribute_fees();
+
+        // Nothing accrues until the holding period is over
+        assert_eq!(early.pending_rewards(&pool, 500), 0);
+        assert_eq!(held.pending_rewards(&pool, 1_099), 0);
+
+        // Unstaking early earns nothing and returns the rewards to the pool
+        assert_eq!(pool.unstake(&early, 500), 0);
+        assert_eq!(pool.pending_fees, 100 * one);
+        assert_eq!(pool.total_rewards_distributed, 400 * one);
+        assert_eq!((pool.total_staked, pool.staker_count), (4_000 * one, 1));
+
+        // Held past the minimum: its full proportional share, then the
+        // forfeited rewards once redistributed
+        assert_eq!(held.pending_rewards(&pool, 1_100), 400 * one);
+        pool.distribute_fees();
+        assert_eq!(held.pending_rewards(&pool, 1_100), 500 * one);
+        assert_eq!(pool.unstake(&held, 1_100), 500 * one);
+        assert_eq!((pool.total_staked, pool.staker_count, pool.pending_fees), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_liquidation_dependent_actions_wait_longest() {
+        use crate::constants::reorg;
+        let claim = StabilityPoolAction::ClaimBtc;
+        let tranche = VaultAction::ContinueLiquidation { vault_id: [0u8; 32], debt_portion: ZkUsd(1) };
+        let liquidate = VaultAction::Liquidate { vault_id: [0u8; 32] };
+        let deposit = StabilityPoolAction::Deposit { amount: ZkUsd(1) };
+
+        assert_eq!(claim.min_confirmations_hint(), reorg::LIQUIDATION_DEPENDENT_CONFIRMATIONS);
+        assert_eq!(tranche.min_confirmations_hint(), reorg::LIQUIDATION_DEPENDENT_CONFIRMATIONS);
+        assert_eq!(liquidate.min_confirmations_hint(), reorg::PRICE_DEPENDENT_CONFIRMATIONS);
+        assert_eq!(deposit.min_confirmations_hint(), reorg::DEFAULT_CONFIRMATIONS);
+    }
 }
@@ -0,0 +1,149 @@
+//! Unit-Typed Amounts
+//!
+//! BTC amounts (satoshis) and zkUSD amounts (base units) are both `u64`
+//! with 8 decimals, so passing one where the other is expected compiles
+//! and silently produces wrong numbers. `Sats` and `ZkUsd` make that a type
+//! error:
+//!
+//! ```compile_fail
+//! use zkusd_common::math::calculate_icr;
+//! use zkusd_common::units::{Sats, ZkUsd};
+//!
+//! let collateral = Sats(100_000_000);
+//! let debt = ZkUsd(50_000 * 100_000_000);
+//! // Collateral and debt swapped: rejected by the compiler
+//! let icr = calculate_icr(debt, collateral, 100_000 * 100_000_000);
+//! ```
+//!
+//! ```
+//! use zkusd_common::math::calculate_icr;
+//! use zkusd_common::units::{Sats, ZkUsd};
+//!
+//! let icr = calculate_icr(Sats(100_000_000), ZkUsd(50_000 * 100_000_000), 100_000 * 100_000_000);
+//! assert_eq!(icr, Ok(200));
+//! ```
+//!
+//! ## Encoding
+//!
+//! Both types serialize exactly as the bare `u64` (serde and borsh), so
+//! migrating a field to them changes no witness, state or commitment.
+//!
+//! ## Migration
+//!
+//! `From<u64>` and `into_inner` convert at the edges of code that still
+//! works in raw `u64`, such as the `Vault` and pool state structs.
+
+use core::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{ZkUsdError, ZkUsdResult};
+
+macro_rules! amount_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(
+            Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+            Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+        )]
+        #[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+        #[serde(transparent)]
+        pub struct $name(pub u64);
+
+        impl $name {
+            /// Zero amount
+            pub const ZERO: Self = Self(0);
+
+            /// Raw `u64` amount
+            pub const fn into_inner(self) -> u64 {
+                self.0
+            }
+
+            /// Whether the amount is zero
+            pub const fn is_zero(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Addition with overflow check (see `math::safe_add`)
+            pub fn checked_add(self, other: Self) -> ZkUsdResult<Self> {
+                self.0.checked_add(other.0).map(Self).ok_or(ZkUsdError::Overflow)
+            }
+
+            /// Subtraction with underflow check (see `math::safe_sub`)
+            pub fn checked_sub(self, other: Self) -> ZkUsdResult<Self> {
+                self.0.checked_sub(other.0).map(Self).ok_or(ZkUsdError::Underflow)
+            }
+
+            /// Subtraction clamped at zero
+            pub fn saturating_sub(self, other: Self) -> Self {
+                Self(self.0.saturating_sub(other.0))
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(amount: u64) -> Self {
+                Self(amount)
+            }
+        }
+
+        impl From<$name> for u64 {
+            fn from(amount: $name) -> Self {
+                amount.0
+            }
+        }
+    };
+}
+
+amount_type! {
+    /// BTC amount in satoshis
+    Sats
+}
+
+amount_type! {
+    /// zkUSD amount in base units (8 decimals)
+    ZkUsd
+}
+
+impl fmt::Display for Sats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sats", self.0)
+    }
+}
+
+impl fmt::Display for ZkUsd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let one = crate::constants::token::ONE;
+        write!(f, "{}.{:08} zkUSD", self.0 / one, self.0 % one)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Sats(5).checked_add(Sats(3)), Ok(Sats(8)));
+        assert_eq!(Sats(u64::MAX).checked_add(Sats(1)), Err(ZkUsdError::Overflow));
+        assert_eq!(ZkUsd(5).checked_sub(ZkUsd(3)), Ok(ZkUsd(2)));
+        assert_eq!(ZkUsd(3).checked_sub(ZkUsd(5)), Err(ZkUsdError::Underflow));
+        assert_eq!(ZkUsd(3).saturating_sub(ZkUsd(5)), ZkUsd::ZERO);
+    }
+
+    #[test]
+    fn test_display_with_units() {
+        assert_eq!(Sats(150_000_000).to_string(), "150000000 sats");
+        assert_eq!(ZkUsd(1_234 * crate::constants::token::ONE + 50_000_000).to_string(), "1234.50000000 zkUSD");
+        assert_eq!(ZkUsd(1).to_string(), "0.00000001 zkUSD");
+    }
+
+    #[test]
+    fn test_encoding_is_bare_u64() {
+        let amount = 42_000_000u64;
+
+        assert_eq!(borsh::to_vec(&Sats(amount)).unwrap(), borsh::to_vec(&amount).unwrap());
+        assert_eq!(serde_json::to_string(&ZkUsd(amount)).unwrap(), amount.to_string());
+        assert_eq!(serde_json::from_str::<ZkUsd>("7").unwrap(), ZkUsd(7));
+    }
+}
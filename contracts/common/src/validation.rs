@@ -6,6 +6,7 @@
 //! ## Features
 //!
 //! - `check!` macro for cleaner validation code
+//! - `StateTransition` for output-state checks that name the mismatched field
 //! - `token_amounts_balanced()` for zkUSD conservation
 //! - Common validation helpers for cross-contract operations
 //!
@@ -21,9 +22,14 @@
 //! token_amounts_balanced(inputs, outputs, minted, burned)?;
 //! ```
 
+use borsh::BorshSerialize;
+use sha2::{Digest, Sha256};
+
 use crate::{
-    errors::{ZkUsdError, ZkUsdResult},
-    types::Address,
+    address::is_zero,
+    constants::oracle::MAX_PRICE_AGE_BLOCKS,
+    errors::{CompanionRole, ZkUsdError, ZkUsdResult},
+    types::{Address, CircuitBreakerState, OracleSnapshot, ProtocolState, Vault},
     Vec,
 };
 
@@ -62,6 +68,55 @@ macro_rules! check {
 
 pub use check;
 
+// ============ Check Modes ============
+
+/// Failures of a run of checks
+///
+/// `validate` runs its checks fail-fast, returning the first failure. A
+/// dry run collects every failure instead, so a client sees everything
+/// wrong with a request in one pass. A failed computation that later
+/// checks depend on (an overflow, a missing state) stops the run in
+/// either mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checks {
+    collect: bool,
+    errors: Vec<ZkUsdError>,
+}
+
+impl Checks {
+    /// Stop at the first failure
+    pub fn fail_fast() -> Self {
+        Self { collect: false, errors: Vec::new() }
+    }
+
+    /// Record every failure and carry on
+    pub fn collect_all() -> Self {
+        Self { collect: true, errors: Vec::new() }
+    }
+
+    /// Record a check's result: a failure is returned when failing fast,
+    /// and kept otherwise
+    pub fn require(&mut self, result: ZkUsdResult<()>) -> ZkUsdResult<()> {
+        match result {
+            Err(error) if self.collect => {
+                self.errors.push(error);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Failures collected so far
+    pub fn errors(&self) -> &[ZkUsdError] {
+        &self.errors
+    }
+
+    /// Take the collected failures
+    pub fn into_errors(self) -> Vec<ZkUsdError> {
+        self.errors
+    }
+}
+
 // ============ Token Conservation ============
 
 /// Validates that token amounts are balanced (conservation law).
@@ -184,6 +239,26 @@ pub fn require_in_range(
     Ok(())
 }
 
+/// Require a nonzero adjustment to be at least `minimum`.
+///
+/// A zero delta leaves that side of the vault untouched and always passes.
+pub fn require_min_adjustment(delta: u64, minimum: u64) -> ZkUsdResult<()> {
+    if delta != 0 && delta < minimum {
+        return Err(ZkUsdError::AdjustmentTooSmall { delta, minimum });
+    }
+    Ok(())
+}
+
+/// Require a nonzero BTC output to be at least `dust_limit`.
+///
+/// A zero amount creates no output and always passes.
+pub fn require_not_dust(amount: u64, dust_limit: u64) -> ZkUsdResult<()> {
+    if amount != 0 && amount < dust_limit {
+        return Err(ZkUsdError::OutputBelowDust { amount, dust_limit });
+    }
+    Ok(())
+}
+
 /// Require sufficient balance for an operation.
 pub fn require_sufficient_balance(available: u64, requested: u64) -> ZkUsdResult<()> {
     if available < requested {
@@ -206,6 +281,14 @@ pub fn require_owner(owner: Address, signer: Address) -> ZkUsdResult<()> {
     Ok(())
 }
 
+/// Require the signer to be the owner or the owner's delegated operator.
+pub fn require_owner_or_operator(owner: Address, operator: Option<Address>, signer: Address) -> ZkUsdResult<()> {
+    if operator == Some(signer) {
+        return Ok(());
+    }
+    require_owner(owner, signer)
+}
+
 /// Require the signer to be the admin.
 pub fn require_admin(admin: Address, signer: Address) -> ZkUsdResult<()> {
     if admin != signer {
@@ -216,7 +299,7 @@ pub fn require_admin(admin: Address, signer: Address) -> ZkUsdResult<()> {
 
 /// Require address to not be zero.
 pub fn require_valid_address(address: Address, param: &'static str) -> ZkUsdResult<()> {
-    if address == [0u8; 32] {
+    if is_zero(&address) {
         return Err(ZkUsdError::InvalidAddress {
             reason: param,
         });
@@ -232,6 +315,103 @@ pub fn require_not_paused(is_paused: bool) -> ZkUsdResult<()> {
     Ok(())
 }
 
+// ============ Spell Freshness Helpers ============
+
+/// Require the spell to not be past its expiry block (if any).
+pub fn require_not_expired(expires_at: Option<u64>, current_block: u64) -> ZkUsdResult<()> {
+    if let Some(expires_at) = expires_at {
+        if current_block > expires_at {
+            return Err(ZkUsdError::SpellExpired {
+                expires_at,
+                current: current_block,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Require price to not exceed the user's maximum (if any).
+pub fn require_price_at_most(price: u64, max_price: Option<u64>) -> ZkUsdResult<()> {
+    if let Some(bound) = max_price {
+        if price > bound {
+            return Err(ZkUsdError::PriceOutOfBounds { price, bound });
+        }
+    }
+    Ok(())
+}
+
+/// Require an output amount to meet the user's minimum (0 disables).
+pub fn require_min_output(actual: u64, expected_min: u64) -> ZkUsdResult<()> {
+    if actual < expected_min {
+        return Err(ZkUsdError::SlippageExceeded { expected_min, actual });
+    }
+    Ok(())
+}
+
+/// Require price to be at least the user's minimum (if any).
+pub fn require_price_at_least(price: u64, min_price: Option<u64>) -> ZkUsdResult<()> {
+    if let Some(bound) = min_price {
+        if price < bound {
+            return Err(ZkUsdError::PriceOutOfBounds { price, bound });
+        }
+    }
+    Ok(())
+}
+
+/// Require the age-decayed oracle confidence to meet a minimum.
+pub fn require_min_confidence(confidence: u8, required: u8) -> ZkUsdResult<()> {
+    if confidence < required {
+        return Err(ZkUsdError::OracleLowConfidence { confidence, required });
+    }
+    Ok(())
+}
+
+/// Require the composite oracle health score to meet a minimum.
+pub fn require_min_health(score: u8, required: u8) -> ZkUsdResult<()> {
+    if score < required {
+        return Err(ZkUsdError::OracleUnhealthy { score, required });
+    }
+    Ok(())
+}
+
+/// Age limit an oracle price must meet to be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreshnessPolicy {
+    /// Maximum blocks since the price was published
+    pub max_age_blocks: u64,
+}
+
+impl Default for FreshnessPolicy {
+    fn default() -> Self {
+        Self { max_age_blocks: MAX_PRICE_AGE_BLOCKS }
+    }
+}
+
+/// Require an active oracle with a price no older than the policy allows.
+///
+/// Returns the price, so callers never read it without the check.
+pub fn require_fresh_price(oracle: &OracleSnapshot, current_block: u64, policy: &FreshnessPolicy) -> ZkUsdResult<u64> {
+    if !oracle.is_active {
+        return Err(ZkUsdError::OracleNotInitialized);
+    }
+    if current_block.saturating_sub(oracle.price.timestamp_block) > policy.max_age_blocks {
+        return Err(ZkUsdError::OracleStale {
+            last_update_block: oracle.price.timestamp_block,
+            current_block,
+            max_age: policy.max_age_blocks,
+        });
+    }
+    Ok(oracle.price.price)
+}
+
+/// Require the oracle circuit breaker to not be tripped and cooling down.
+pub fn require_circuit_breaker_clear(breaker: &CircuitBreakerState, current_block: u64) -> ZkUsdResult<()> {
+    if breaker.is_active(current_block) {
+        return Err(ZkUsdError::CircuitBreakerActive { until_block: breaker.until_block() });
+    }
+    Ok(())
+}
+
 // ============ Collateral Ratio Helpers ============
 
 /// Require ICR to meet minimum ratio.
@@ -277,6 +457,121 @@ pub fn verify_field_eq<T: PartialEq>(actual: T, expected: T) -> ZkUsdResult<()>
     Ok(())
 }
 
+/// Expect every listed field of two values of a struct type
+///
+/// As with `diff_fields!`, the list must name every field of the struct,
+/// so a field added to the struct but not to the list is a compile error.
+#[macro_export]
+macro_rules! expect_fields {
+    ($transition:expr, $ty:ident, $actual:expr, $expected:expr, $prefix:literal, [$($field:ident),* $(,)?]) => {{
+        let (actual, expected): (&$ty, &$ty) = ($actual, $expected);
+        let $ty { $($field: _),* } = expected;
+        $transition
+            $(.expect_field(&actual.$field, &expected.$field, concat!($prefix, ".", stringify!($field))))*
+    }};
+}
+
+pub use expect_fields;
+
+/// Field-by-field check of a state transition
+///
+/// Accumulates `expect_field` comparisons and fails with
+/// `StateFieldMismatch` naming the first one that differs, where
+/// `verify_field_eq` only reports that *something* did. Comparisons after
+/// the first mismatch are skipped.
+///
+/// ```rust,ignore
+/// StateTransition::new()
+///     .expect_vault(new_vault, &expected_vault)
+///     .expect_field(&new_state.mint_tracker, &expected_tracker, "mint_tracker")
+///     .finish()?;
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[must_use = "a transition is only checked by `finish`"]
+pub struct StateTransition {
+    mismatch: Option<&'static str>,
+}
+
+impl StateTransition {
+    /// Transition with nothing expected yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect `actual` to equal `expected`, reporting a mismatch as `field`
+    pub fn expect_field<T: PartialEq>(mut self, actual: T, expected: T, field: &'static str) -> Self {
+        if self.mismatch.is_none() && actual != expected {
+            self.mismatch = Some(field);
+        }
+        self
+    }
+
+    /// Expect every field of a vault to match, named `vault.<field>`
+    pub fn expect_vault(self, actual: &Vault, expected: &Vault) -> Self {
+        expect_fields!(self, Vault, actual, expected, "vault", [
+            version,
+            id,
+            owner,
+            collateral,
+            debt,
+            created_at,
+            last_updated,
+            status,
+            interest_rate_bps,
+            accrued_interest,
+            redistributed_debt,
+            redistributed_collateral,
+            insurance_balance,
+            operator,
+            twa_collateral,
+            twa_updated_at,
+            stats,
+            protected_collateral_bps,
+            beneficiary,
+            adjustment_window,
+            rate_mode,
+            pending_liquidation,
+        ])
+    }
+
+    /// Expect every field of the protocol state to match, named
+    /// `protocol.<field>`
+    pub fn expect_protocol(self, actual: &ProtocolState, expected: &ProtocolState) -> Self {
+        expect_fields!(self, ProtocolState, actual, expected, "protocol", [
+            total_collateral,
+            total_debt,
+            active_vault_count,
+            base_rate,
+            last_fee_update_block,
+            admin,
+            is_paused,
+            flash_fee_bps,
+            accumulated_fees,
+            fee_recipient,
+            interest_index,
+            last_interest_accrual_block,
+            rate_weighted_debt,
+            pending_interest,
+            variable_debt,
+            rate_history,
+            bootstrap,
+        ])
+    }
+
+    /// First mismatched field, if any
+    pub fn mismatch(&self) -> Option<&'static str> {
+        self.mismatch
+    }
+
+    /// Fail with the first mismatched field
+    pub fn finish(self) -> ZkUsdResult<()> {
+        match self.mismatch {
+            Some(field) => Err(ZkUsdError::StateFieldMismatch { field }),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Verify state was properly updated with delta.
 pub fn verify_state_delta(
     old_value: u64,
@@ -336,6 +631,89 @@ pub fn validate_cross_contract_call(
     })
 }
 
+/// Resolve the charm filling a companion role strictly by app id.
+///
+/// `claimants` yields `(app_id, value)` for every charm in the spell whose
+/// data decodes into the role's state shape. All of them must carry the
+/// `expected` app id recorded in protocol state, and they must agree on a
+/// single value; a lookalike app is rejected even if its state decodes.
+pub fn require_companion<T: PartialEq>(
+    role: CompanionRole,
+    expected: [u8; 32],
+    claimants: impl IntoIterator<Item = ([u8; 32], T)>,
+) -> ZkUsdResult<T> {
+    let mut resolved: Option<T> = None;
+
+    for (found, value) in claimants {
+        check!(found == expected, ZkUsdError::WrongCompanionApp { expected, found, role });
+        match &resolved {
+            Some(existing) => check!(*existing == value, ZkUsdError::DuplicateCompanionApp { role }),
+            None => resolved = Some(value),
+        }
+    }
+
+    resolved.ok_or(ZkUsdError::WrongCompanionApp { expected, found: [0u8; 32], role })
+}
+
+// ============ Replay Protection ============
+
+/// Actions already applied within one validation context.
+///
+/// Each entry is keyed by `sha256(borsh(action) || input_charm_id)`, so a
+/// batch that lists the same transition against the same input twice is
+/// rejected even though each application would pass on its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedActions {
+    keys: Vec<[u8; 32]>,
+}
+
+impl AppliedActions {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key identifying `action` applied to the charm `input_id`
+    pub fn key<A: BorshSerialize>(action: &A, input_id: &[u8; 32]) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        // Writing into a Vec cannot fail
+        action.serialize(&mut bytes).unwrap_or_default();
+        bytes.extend_from_slice(input_id);
+        Sha256::digest(bytes).into()
+    }
+
+    /// Reject an action already applied to `input_id`, returning its key
+    pub fn ensure_new<A: BorshSerialize>(&self, action: &A, input_id: &[u8; 32]) -> ZkUsdResult<[u8; 32]> {
+        let key = Self::key(action, input_id);
+        check!(!self.keys.contains(&key), ZkUsdError::DuplicateAction { key });
+        Ok(key)
+    }
+
+    /// Mark the action behind `key` as applied
+    pub fn insert(&mut self, key: [u8; 32]) {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+
+    /// Record an application, rejecting one already recorded
+    pub fn record<A: BorshSerialize>(&mut self, action: &A, input_id: &[u8; 32]) -> ZkUsdResult<()> {
+        let key = self.ensure_new(action, input_id)?;
+        self.insert(key);
+        Ok(())
+    }
+
+    /// Number of recorded applications
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if nothing has been applied yet
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
 // ============ Witness Data Structures ============
 
 /// Standard witness structure for vault operations.
@@ -394,6 +772,25 @@ pub struct RedemptionWitness {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_state_transition_names_first_mismatch() {
+        let expected = Vault::new([1u8; 32], [2u8; 32], 1_000, 2_000, 100);
+        let actual = Vault { debt: 2_001, status: crate::types::VaultStatus::Closed, ..expected.clone() };
+
+        assert_eq!(StateTransition::new().expect_vault(&expected, &expected).finish(), Ok(()));
+        assert_eq!(
+            StateTransition::new().expect_vault(&actual, &expected).finish(),
+            Err(ZkUsdError::StateFieldMismatch { field: "vault.debt" })
+        );
+
+        // The first mismatch wins, across fields and whole states
+        let transition = StateTransition::new()
+            .expect_field(1, 1, "first")
+            .expect_field(2, 3, "second")
+            .expect_vault(&actual, &expected);
+        assert_eq!(transition.mismatch(), Some("second"));
+    }
+
     #[test]
     fn test_token_amounts_balanced() {
         // Mint: 0 in, 1000 out, 1000 minted
@@ -416,12 +813,56 @@ mod tests {
         assert_eq!(sum_token_amount(&[u64::MAX, 1]), u64::MAX); // Saturates
     }
 
+    #[test]
+    fn test_checks_modes() {
+        let run = |checks: &mut Checks| -> ZkUsdResult<()> {
+            checks.require(require_positive(0, "amount"))?;
+            checks.require(require_in_range(5, 10, 100, "value"))?;
+            checks.require(require_sufficient_balance(10, 5))
+        };
+
+        let mut fail_fast = Checks::fail_fast();
+        assert_eq!(
+            run(&mut fail_fast),
+            Err(ZkUsdError::InvalidInput { param: "amount", reason: "Value must be positive" })
+        );
+        assert!(fail_fast.errors().is_empty());
+
+        let mut collect_all = Checks::collect_all();
+        assert_eq!(run(&mut collect_all), Ok(()));
+        assert_eq!(
+            collect_all.into_errors(),
+            [
+                ZkUsdError::InvalidInput { param: "amount", reason: "Value must be positive" },
+                ZkUsdError::BelowMinimum { amount: 5, minimum: 10 },
+            ]
+        );
+    }
+
     #[test]
     fn test_require_positive() {
         assert!(require_positive(100, "amount").is_ok());
         assert!(require_positive(0, "amount").is_err());
     }
 
+    #[test]
+    fn test_require_min_adjustment() {
+        assert!(require_min_adjustment(0, 100).is_ok());
+        assert!(require_min_adjustment(100, 100).is_ok());
+        assert_eq!(
+            require_min_adjustment(99, 100),
+            Err(ZkUsdError::AdjustmentTooSmall { delta: 99, minimum: 100 })
+        );
+        assert!(require_min_adjustment(1, 0).is_ok());
+    }
+
+    #[test]
+    fn test_require_not_dust() {
+        assert!(require_not_dust(0, 546).is_ok());
+        assert!(require_not_dust(546, 546).is_ok());
+        assert_eq!(require_not_dust(545, 546), Err(ZkUsdError::OutputBelowDust { amount: 545, dust_limit: 546 }));
+    }
+
     #[test]
     fn test_require_in_range() {
         assert!(require_in_range(50, 0, 100, "value").is_ok());
@@ -440,6 +881,63 @@ mod tests {
         assert!(require_owner(owner, other).is_err());
     }
 
+    #[test]
+    fn test_require_owner_or_operator() {
+        let owner = [1u8; 32];
+        let operator = [2u8; 32];
+        let other = [3u8; 32];
+
+        assert!(require_owner_or_operator(owner, Some(operator), owner).is_ok());
+        assert!(require_owner_or_operator(owner, Some(operator), operator).is_ok());
+        assert!(require_owner_or_operator(owner, Some(operator), other).is_err());
+        // Without an operator only the owner passes
+        assert!(require_owner_or_operator(owner, None, operator).is_err());
+    }
+
+    #[test]
+    fn test_spell_freshness_helpers() {
+        assert!(require_not_expired(None, 1_000).is_ok());
+        assert!(require_not_expired(Some(100), 100).is_ok());
+        assert_eq!(
+            require_not_expired(Some(100), 101),
+            Err(ZkUsdError::SpellExpired { expires_at: 100, current: 101 })
+        );
+
+        assert!(require_price_at_most(100, None).is_ok());
+        assert!(require_price_at_most(100, Some(100)).is_ok());
+        assert!(require_price_at_most(101, Some(100)).is_err());
+        assert!(require_price_at_least(100, Some(100)).is_ok());
+        assert!(require_price_at_least(99, Some(100)).is_err());
+
+        assert!(require_min_output(0, 0).is_ok());
+        assert!(require_min_output(100, 100).is_ok());
+        assert_eq!(
+            require_min_output(99, 100),
+            Err(ZkUsdError::SlippageExceeded { expected_min: 100, actual: 99 })
+        );
+    }
+
+    #[test]
+    fn test_require_fresh_price() {
+        use crate::types::{PriceData, PriceSource};
+
+        let policy = FreshnessPolicy::default();
+        let oracle = OracleSnapshot::active(PriceData::new(100, 1_000, PriceSource::Mock));
+        assert_eq!(require_fresh_price(&oracle, 1_000 + MAX_PRICE_AGE_BLOCKS, &policy), Ok(100));
+        assert_eq!(
+            require_fresh_price(&oracle, 1_001 + MAX_PRICE_AGE_BLOCKS, &policy),
+            Err(ZkUsdError::OracleStale {
+                last_update_block: 1_000,
+                current_block: 1_001 + MAX_PRICE_AGE_BLOCKS,
+                max_age: MAX_PRICE_AGE_BLOCKS,
+            })
+        );
+        assert!(require_fresh_price(&oracle, 1_002, &FreshnessPolicy { max_age_blocks: 1 }).is_err());
+
+        let inactive = OracleSnapshot { is_active: false, ..oracle };
+        assert_eq!(require_fresh_price(&inactive, 1_000, &policy), Err(ZkUsdError::OracleNotInitialized));
+    }
+
     #[test]
     fn test_require_min_icr() {
         assert!(require_min_icr(150, 110).is_ok());
@@ -478,4 +976,46 @@ mod tests {
         assert!(validate_cross_contract_call(caller, &authorized, "mint").is_ok());
         assert!(validate_cross_contract_call(unauthorized, &authorized, "mint").is_err());
     }
+
+    #[test]
+    fn test_require_companion() {
+        let oracle = [3u8; 32];
+        let role = CompanionRole::PriceOracle;
+
+        assert_eq!(require_companion(role, oracle, [(oracle, 100u64)]), Ok(100));
+        // The same charm seen twice (e.g. in refs and ins) is not ambiguous
+        assert_eq!(require_companion(role, oracle, [(oracle, 100u64), (oracle, 100)]), Ok(100));
+
+        assert_eq!(
+            require_companion::<u64>(role, oracle, []),
+            Err(ZkUsdError::WrongCompanionApp { expected: oracle, found: [0u8; 32], role })
+        );
+        assert_eq!(
+            require_companion(role, oracle, [(oracle, 100u64), ([9u8; 32], 50)]),
+            Err(ZkUsdError::WrongCompanionApp { expected: oracle, found: [9u8; 32], role })
+        );
+        assert_eq!(
+            require_companion(role, oracle, [(oracle, 100u64), (oracle, 50)]),
+            Err(ZkUsdError::DuplicateCompanionApp { role })
+        );
+    }
+
+    #[test]
+    fn test_applied_actions_rejects_duplicate() {
+        let mut applied = AppliedActions::new();
+        let vault = [1u8; 32];
+
+        assert!(applied.record(&(4u8, 500u64), &vault).is_ok());
+        // Same action on another input, or another action on the same input
+        assert!(applied.record(&(4u8, 500u64), &[2u8; 32]).is_ok());
+        assert!(applied.record(&(4u8, 600u64), &vault).is_ok());
+        assert_eq!(applied.len(), 3);
+
+        let key = AppliedActions::key(&(4u8, 500u64), &vault);
+        assert_eq!(
+            applied.record(&(4u8, 500u64), &vault),
+            Err(ZkUsdError::DuplicateAction { key })
+        );
+        assert_eq!(applied.len(), 3);
+    }
 }
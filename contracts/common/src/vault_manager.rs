@@ -12,9 +12,12 @@
 //! - **Health Monitoring**: Continuous vault health tracking
 //! - **UTXO-Native**: All operations designed for UTXO atomicity
 
-use crate::{Vec, ZkUsdError, ZkUsdResult, Vault, calculate_icr};
+use borsh::BorshSerialize;
+
+use crate::{Vec, ZkUsdError, ZkUsdResult, Vault, calculate_icr, Sats, ZkUsd};
 use crate::errors::AmountErrorReason;
-use crate::constants::token;
+use crate::constants::{limits, token};
+use crate::validation::{require_min_adjustment, AppliedActions};
 
 // ============================================================================
 // Constants
@@ -55,7 +58,7 @@ pub enum VmVaultStatus {
 }
 
 /// Operation type for vault modifications
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize)]
 pub enum VaultOperation {
     /// Add collateral to vault
     AddCollateral,
@@ -260,7 +263,7 @@ pub fn create_vault(request: CreateVaultRequest, mcr_bps: u64) -> ZkUsdResult<Cr
     validate_create_params(&request)?;
 
     // Calculate ICR
-    let icr = calculate_icr(request.collateral, request.debt, request.btc_price)?;
+    let icr = calculate_icr(Sats(request.collateral), ZkUsd(request.debt), request.btc_price)?;
     let icr_bps = icr * 100; // Convert ratio to basis points
 
     // Check MCR requirement
@@ -331,6 +334,13 @@ pub fn adjust_vault(
         vault.debt.saturating_sub((-request.debt_change) as u64)
     };
 
+    // Nonzero changes must clear the adjustment minimums; paying the debt
+    // off entirely is exempt
+    require_min_adjustment(request.collateral_change.unsigned_abs(), limits::MIN_COLLATERAL_ADJUSTMENT)?;
+    if new_debt > 0 {
+        require_min_adjustment(request.debt_change.unsigned_abs(), limits::MIN_ADJUSTMENT)?;
+    }
+
     // Validate minimum amounts if not closing
     if new_debt > 0 {
         if new_collateral < VM_MIN_COLLATERAL {
@@ -351,7 +361,7 @@ pub fn adjust_vault(
     let icr_bps = if new_debt == 0 {
         u64::MAX
     } else {
-        let icr = calculate_icr(new_collateral, new_debt, request.btc_price)?;
+        let icr = calculate_icr(Sats(new_collateral), ZkUsd(new_debt), request.btc_price)?;
         icr * 100
     };
 
@@ -372,7 +382,7 @@ pub fn adjust_vault(
 
     // Calculate fees (only for new debt)
     let fees = if request.debt_change > 0 {
-        (request.debt_change as u64) * 50 / 10000
+        crate::math::safe_mul_div(request.debt_change as u64, 50, 10000)?
     } else {
         0
     };
@@ -433,9 +443,10 @@ pub fn calculate_vault_health(
     current_block: u64,
     at_risk_block: Option<u64>,
 ) -> VaultHealth {
-    let icr = calculate_icr(vault.collateral, vault.debt, btc_price).unwrap_or(0);
-    let icr_bps = icr * 100;
-    let buffer_bps = icr_bps as i64 - mcr_bps as i64;
+    let icr = calculate_icr(Sats(vault.collateral), ZkUsd(vault.debt), btc_price).unwrap_or(0);
+    // A vault without debt has an unbounded ratio: saturate rather than wrap
+    let icr_bps = icr.saturating_mul(100);
+    let buffer_bps = i64::try_from(icr_bps as i128 - mcr_bps as i128).unwrap_or(i64::MAX);
 
     // Determine status
     let status = if vault.debt == 0 {
@@ -504,8 +515,13 @@ pub fn execute_batch_operations(
     }
 
     let mut results = Vec::new();
+    let mut applied = AppliedActions::new();
 
     for op in operations {
+        // Every op adjusts the vault as it was before the batch, so a
+        // repeated op would be applied twice against the same input
+        applied.record(&(op.operation, op.amount), &op.vault_id)?;
+
         // Find the vault
         let vault_opt = vaults.iter().find(|(_, p)| p.vault_id == op.vault_id);
         let (vault, _) = vault_opt.ok_or(ZkUsdError::VaultNotFound { vault_id: op.vault_id })?;
@@ -572,7 +588,7 @@ pub fn calculate_registry_stats(vaults: &[(Vault, VaultPosition)], btc_price: u6
         total_collateral = total_collateral.saturating_add(vault.collateral);
         total_debt = total_debt.saturating_add(vault.debt);
 
-        let icr = calculate_icr(vault.collateral, vault.debt, btc_price).unwrap_or(0);
+        let icr = calculate_icr(Sats(vault.collateral), ZkUsd(vault.debt), btc_price).unwrap_or(0);
         let icr_bps = icr * 100;
         icrs.push(icr_bps);
 
@@ -767,6 +783,29 @@ mod tests {
         assert!(matches!(result, Err(ZkUsdError::InsufficientCollateralRatio)));
     }
 
+    #[test]
+    fn test_adjust_vault_below_minimum_adjustment() {
+        let vault_id = generate_vault_id(&test_owner(), 1000, ONE_BTC);
+        let vault = Vault::new(vault_id, test_owner(), ONE_BTC, 30_000 * ONE_ZKUSD, 1000);
+        let request = |collateral_change, debt_change| AdjustVaultRequest {
+            vault_id,
+            collateral_change,
+            debt_change,
+            btc_price: TEST_BTC_PRICE,
+            block_height: 1001,
+        };
+
+        let result = adjust_vault(&vault, &request(-9_999, 0), MCR_BPS);
+        assert_eq!(result.unwrap_err(), ZkUsdError::AdjustmentTooSmall { delta: 9_999, minimum: 10_000 });
+        let result = adjust_vault(&vault, &request(0, 1), MCR_BPS);
+        assert_eq!(result.unwrap_err(), ZkUsdError::AdjustmentTooSmall { delta: 1, minimum: ONE_ZKUSD });
+
+        // Paying the debt off entirely is exempt
+        let mut dusty = vault.clone();
+        dusty.debt = 1;
+        assert!(adjust_vault(&dusty, &request(0, -1), MCR_BPS).is_ok());
+    }
+
     #[test]
     fn test_close_vault() {
         let vault_id = generate_vault_id(&test_owner(), 1000, ONE_BTC);
@@ -809,6 +848,18 @@ mod tests {
         assert!(health.buffer_bps < 0);
     }
 
+    #[test]
+    fn test_vault_health_without_debt() {
+        let vault_id = generate_vault_id(&test_owner(), 1000, ONE_BTC);
+        let vault = Vault::new(vault_id, test_owner(), ONE_BTC, 0, 1000);
+
+        let health = calculate_vault_health(&vault, TEST_BTC_PRICE, MCR_BPS, 1001, None);
+
+        assert_eq!(health.status, VmVaultStatus::Closed);
+        assert_eq!(health.icr_bps, u64::MAX);
+        assert_eq!(health.buffer_bps, i64::MAX);
+    }
+
     #[test]
     fn test_vault_health_liquidatable() {
         let vault_id = generate_vault_id(&test_owner(), 1000, ONE_BTC);
@@ -899,6 +950,33 @@ mod tests {
         assert_eq!(stats.vaults_at_risk, 0);
     }
 
+    #[test]
+    fn test_batch_rejects_duplicate_operation() {
+        let vault_id = generate_vault_id(&test_owner(), 1000, ONE_BTC);
+        let vaults = vec![(
+            Vault::new(vault_id, test_owner(), ONE_BTC, 20_000 * ONE_ZKUSD, 1000),
+            VaultPosition {
+                vault_id,
+                owner: test_owner(),
+                icr_bps: 25000,
+                prev: None,
+                next: None,
+            },
+        )];
+        let mint = BatchVaultOperation {
+            vault_id,
+            operation: VaultOperation::BorrowMore,
+            amount: 1_000 * ONE_ZKUSD,
+        };
+
+        let once = execute_batch_operations(&mut vaults.clone(), &[mint.clone()], TEST_BTC_PRICE, MCR_BPS, 1000);
+        assert_eq!(once.unwrap().len(), 1);
+
+        let key = AppliedActions::key(&(mint.operation, mint.amount), &vault_id);
+        let twice = execute_batch_operations(&mut vaults.clone(), &[mint.clone(), mint], TEST_BTC_PRICE, MCR_BPS, 1000);
+        assert_eq!(twice.unwrap_err(), ZkUsdError::DuplicateAction { key });
+    }
+
     #[test]
     fn test_vault_limit() {
         assert!(check_vault_limit(0).is_ok());
@@ -0,0 +1,527 @@
+//! Vault Registry
+//!
+//! Validators only see the vaults a spell includes, so a redeemer could
+//! skip the cheapest vault and nobody would notice. The registry is an
+//! optional on-chain summary of every active vault, maintained by the
+//! VaultManager app, that makes the ordering checkable.
+//!
+//! ## Layout
+//!
+//! Entries are sorted by `(interest_rate_bps, vault_id)`, lowest rate first,
+//! and split into shard charms of at most `MAX_REGISTRY_ENTRIES` entries.
+//! Shards are packed: shard `i` is full before shard `i + 1` holds anything,
+//! so the registry has exactly `max(1, ceil(len / MAX))` shards and the
+//! lowest-rate vaults are always at the front of shard 0.
+//!
+//! ## Maintenance
+//!
+//! When the registry is enabled every vault-mutating spell spends all
+//! shards and recreates them with the vault's change applied: inserted on
+//! open, updated when an active vault's entry fields change, removed when the vault
+//! stops being active. The expected output shards are recomputed with
+//! `apply_change` and must match exactly.
+//!
+//! ## Redemption Order
+//!
+//! A redemption must hit the lowest-rate active vault. Lower-rate vaults may
+//! only be skipped while they are inside the redemption lockout or carry
+//! less than `MIN_DEBT`; see `verify_redemption_order`. Variable-rate vaults
+//! keep their opening rate as sort key but are compared at the current
+//! variable rate.
+//!
+//! ## Liquidation Order
+//!
+//! Vaults at the same ICR (to the basis point) would otherwise be taken in
+//! whatever order liquidators race them in. Among equal-ICR vaults a
+//! liquidation must take the one with the lowest `liquidation_tie_key`,
+//! derived from the vault ID and the block the price was published at, so
+//! no liquidator can pick which owner goes first; see
+//! `verify_liquidation_order`. Keepers can rank candidates with
+//! `liquidation_order`.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::constants::limits::MAX_REGISTRY_ENTRIES;
+use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::math::calculate_icr_bps;
+use crate::types::{RateMode, Vault, VaultId};
+use crate::units::{Sats, ZkUsd};
+use crate::Vec;
+
+/// Summary of one active vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct RegistryEntry {
+    /// Vault ID
+    pub vault_id: VaultId,
+    /// Vault interest rate (BPS), the primary sort key
+    pub interest_rate_bps: u64,
+    /// Vault debt
+    pub debt: u64,
+    /// Vault collateral
+    pub collateral: u64,
+    /// Vault creation block, to check the redemption lockout
+    pub created_at: u64,
+    /// Whether the vault pays the variable rate
+    #[serde(default)]
+    pub rate_mode: RateMode,
+}
+
+impl RegistryEntry {
+    /// Entry summarizing a vault
+    pub fn from_vault(vault: &Vault) -> Self {
+        Self {
+            vault_id: vault.id,
+            interest_rate_bps: vault.interest_rate_bps,
+            debt: vault.debt,
+            collateral: vault.collateral,
+            created_at: vault.created_at,
+            rate_mode: vault.rate_mode,
+        }
+    }
+
+    /// Rate the vault is charged now, given the current variable rate
+    pub fn effective_rate_bps(&self, variable_rate_bps: u64) -> u64 {
+        match self.rate_mode {
+            RateMode::Fixed => self.interest_rate_bps,
+            RateMode::Variable => variable_rate_bps,
+        }
+    }
+
+    /// Sort key: lowest rate first, vault ID breaks ties
+    pub fn key(&self) -> (u64, VaultId) {
+        (self.interest_rate_bps, self.vault_id)
+    }
+
+    /// ICR of the vault at `btc_price` (BPS)
+    pub fn icr_bps(&self, btc_price: u64) -> ZkUsdResult<u64> {
+        calculate_icr_bps(Sats(self.collateral), ZkUsd(self.debt), btc_price)
+    }
+}
+
+/// One shard of the registry, carried as its own charm
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct VaultRegistry {
+    /// Position of this shard in the registry
+    pub shard: u16,
+    /// Entries of this shard, sorted by `RegistryEntry::key`
+    pub entries: Vec<RegistryEntry>,
+}
+
+/// Change a vault-mutating spell makes to the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryChange {
+    /// A vault became active
+    Insert(RegistryEntry),
+    /// An active vault changed
+    Update(RegistryEntry),
+    /// A vault stopped being active
+    Remove(VaultId),
+}
+
+impl RegistryChange {
+    /// Change implied by a vault's input and output states, if any
+    ///
+    /// An active vault whose entry fields are unchanged needs no update.
+    pub fn between(vault: Option<&Vault>, new_vault: Option<&Vault>) -> Option<Self> {
+        let old_entry = vault.filter(|v| v.is_active()).map(RegistryEntry::from_vault);
+        let new_entry = new_vault.filter(|v| v.is_active()).map(RegistryEntry::from_vault);
+        match (old_entry, new_entry) {
+            (None, Some(entry)) => Some(Self::Insert(entry)),
+            (Some(old), Some(entry)) => (old != entry).then_some(Self::Update(entry)),
+            (Some(old), None) => Some(Self::Remove(old.vault_id)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Registry entries in order, after checking the shards are complete and packed
+///
+/// `shards` must be every shard of the registry, in order.
+pub fn flatten(shards: &[VaultRegistry]) -> ZkUsdResult<Vec<RegistryEntry>> {
+    if shards.is_empty() {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    check_shard_count(shards.len())?;
+
+    let mut entries = Vec::new();
+    for (i, shard) in shards.iter().enumerate() {
+        let is_last = i + 1 == shards.len();
+        let well_formed = usize::from(shard.shard) == i
+            && shard.entries.len() <= MAX_REGISTRY_ENTRIES
+            && (is_last || shard.entries.len() == MAX_REGISTRY_ENTRIES);
+        if !well_formed {
+            return Err(ZkUsdError::InvalidStateTransition);
+        }
+        entries.extend_from_slice(&shard.entries);
+    }
+
+    if entries.windows(2).any(|w| w[0].key() >= w[1].key()) {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    Ok(entries)
+}
+
+/// Pack sorted entries into shards
+pub fn split(entries: &[RegistryEntry]) -> ZkUsdResult<Vec<VaultRegistry>> {
+    let shards: Vec<VaultRegistry> = entries
+        .chunks(MAX_REGISTRY_ENTRIES)
+        .enumerate()
+        .map(|(i, chunk)| VaultRegistry { shard: i as u16, entries: chunk.to_vec() })
+        .collect();
+    check_shard_count(shards.len())?;
+
+    if shards.is_empty() {
+        return Ok(Vec::from([VaultRegistry::default()]));
+    }
+    Ok(shards)
+}
+
+/// Shards expected after applying `change` to the registry in `shards`
+pub fn apply_change(shards: &[VaultRegistry], change: RegistryChange) -> ZkUsdResult<Vec<VaultRegistry>> {
+    let mut entries = flatten(shards)?;
+    let find = |entries: &[RegistryEntry], id: &VaultId| entries.iter().position(|e| &e.vault_id == id);
+
+    match change {
+        RegistryChange::Insert(entry) => {
+            if find(&entries, &entry.vault_id).is_some() {
+                return Err(ZkUsdError::VaultAlreadyExists { vault_id: entry.vault_id });
+            }
+            let at = entries.partition_point(|e| e.key() < entry.key());
+            entries.insert(at, entry);
+        }
+        RegistryChange::Update(entry) => {
+            let at = find(&entries, &entry.vault_id)
+                .ok_or(ZkUsdError::VaultNotFound { vault_id: entry.vault_id })?;
+            // The sort key is fixed for a vault's lifetime
+            if entries[at].interest_rate_bps != entry.interest_rate_bps {
+                return Err(ZkUsdError::InvalidStateTransition);
+            }
+            entries[at] = entry;
+        }
+        RegistryChange::Remove(vault_id) => {
+            let at = find(&entries, &vault_id).ok_or(ZkUsdError::VaultNotFound { vault_id })?;
+            entries.remove(at);
+        }
+    }
+
+    split(&entries)
+}
+
+/// Check that redeeming against `vault_id` respects the redemption order
+///
+/// Every entry charged less than the vault at `variable_rate_bps` (ties
+/// broken by vault ID) must be `exempt` (inside the redemption lockout or
+/// below the minimum debt, as decided by the caller).
+pub fn verify_redemption_order(
+    shards: &[VaultRegistry],
+    vault_id: &VaultId,
+    variable_rate_bps: u64,
+    exempt: impl Fn(&RegistryEntry) -> bool,
+) -> ZkUsdResult<()> {
+    let entries = flatten(shards)?;
+    let key = |entry: &RegistryEntry| (entry.effective_rate_bps(variable_rate_bps), entry.vault_id);
+    let target = entries
+        .iter()
+        .find(|entry| &entry.vault_id == vault_id)
+        .ok_or(ZkUsdError::VaultNotFound { vault_id: *vault_id })?;
+
+    // Entries are in stored-key order, so the first skipped one reported is
+    // the lowest fixed-rate vault skipped
+    match entries.iter().find(|entry| key(entry) < key(target) && !exempt(entry)) {
+        Some(skipped) => Err(ZkUsdError::RedemptionOrderViolated {
+            vault_id: *vault_id,
+            skipped: skipped.vault_id,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Tie-break key of a vault among equal-ICR vaults, for a price published
+/// at `price_block`: `sha256(vault_id || price_block)`, the block as 8
+/// little-endian bytes
+pub fn liquidation_tie_key(vault_id: &VaultId, price_block: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(vault_id);
+    hasher.update(price_block.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Entries in the order liquidations must take them: lowest ICR at
+/// `btc_price` first, equal ICRs by `liquidation_tie_key`
+pub fn liquidation_order(entries: &[RegistryEntry], btc_price: u64, price_block: u64) -> ZkUsdResult<Vec<RegistryEntry>> {
+    let mut keyed = entries
+        .iter()
+        .map(|entry| Ok((entry.icr_bps(btc_price)?, liquidation_tie_key(&entry.vault_id, price_block), *entry)))
+        .collect::<ZkUsdResult<Vec<_>>>()?;
+    keyed.sort_by_key(|&(icr_bps, tie_key, _)| (icr_bps, tie_key));
+    Ok(keyed.into_iter().map(|(_, _, entry)| entry).collect())
+}
+
+/// Check that liquidating `vault_id` respects the liquidation order
+///
+/// No entry at the vault's ICR at `btc_price` may have a lower tie key for
+/// a price published at `price_block`. An equal-ICR vault is as
+/// liquidatable as the target, so none may be skipped; vaults at other
+/// ICRs are not constrained.
+pub fn verify_liquidation_order(
+    shards: &[VaultRegistry],
+    vault_id: &VaultId,
+    btc_price: u64,
+    price_block: u64,
+) -> ZkUsdResult<()> {
+    let entries = flatten(shards)?;
+    let target = entries
+        .iter()
+        .find(|entry| &entry.vault_id == vault_id)
+        .ok_or(ZkUsdError::VaultNotFound { vault_id: *vault_id })?;
+    let icr_bps = target.icr_bps(btc_price)?;
+
+    // The first vault at the target's ICR in liquidation order is the one
+    // to take
+    let first = liquidation_order(&entries, btc_price, price_block)?
+        .into_iter()
+        .find(|entry| entry.icr_bps(btc_price) == Ok(icr_bps))
+        .ok_or(ZkUsdError::VaultNotFound { vault_id: *vault_id })?;
+    if &first.vault_id != vault_id {
+        return Err(ZkUsdError::LiquidationOrderViolated { vault_id: *vault_id, skipped: first.vault_id });
+    }
+    Ok(())
+}
+
+/// No more shards than a shard index can address
+fn check_shard_count(count: usize) -> ZkUsdResult<()> {
+    if count > usize::from(u16::MAX) + 1 {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: count as u64,
+            maximum: u64::from(u16::MAX) + 1,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u8, rate: u64) -> RegistryEntry {
+        RegistryEntry {
+            vault_id: [id; 32],
+            interest_rate_bps: rate,
+            debt: 100,
+            collateral: 200,
+            created_at: 0,
+            rate_mode: RateMode::Fixed,
+        }
+    }
+
+    fn registry(entries: &[RegistryEntry]) -> Vec<VaultRegistry> {
+        split(entries).unwrap()
+    }
+
+    #[test]
+    fn test_insert_keeps_rate_order() {
+        let shards = registry(&[entry(1, 100), entry(2, 300)]);
+
+        let shards = apply_change(&shards, RegistryChange::Insert(entry(3, 200))).unwrap();
+        let ids: Vec<u8> = shards[0].entries.iter().map(|e| e.vault_id[0]).collect();
+        assert_eq!(ids, [1, 3, 2]);
+
+        // Equal rates are ordered by vault ID
+        let shards = apply_change(&shards, RegistryChange::Insert(entry(0, 300))).unwrap();
+        let ids: Vec<u8> = shards[0].entries.iter().map(|e| e.vault_id[0]).collect();
+        assert_eq!(ids, [1, 3, 0, 2]);
+
+        assert_eq!(
+            apply_change(&shards, RegistryChange::Insert(entry(1, 100))),
+            Err(ZkUsdError::VaultAlreadyExists { vault_id: [1; 32] })
+        );
+    }
+
+    #[test]
+    fn test_update_and_remove() {
+        let shards = registry(&[entry(1, 100), entry(2, 300)]);
+
+        let mut updated = entry(2, 300);
+        updated.debt = 50;
+        let after = apply_change(&shards, RegistryChange::Update(updated)).unwrap();
+        assert_eq!(after[0].entries[1], updated);
+
+        // A vault's rate cannot move within the order
+        assert_eq!(
+            apply_change(&shards, RegistryChange::Update(entry(2, 50))),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+
+        let after = apply_change(&shards, RegistryChange::Remove([1; 32])).unwrap();
+        assert_eq!(after[0].entries, [entry(2, 300)]);
+        assert_eq!(
+            apply_change(&after, RegistryChange::Remove([1; 32])),
+            Err(ZkUsdError::VaultNotFound { vault_id: [1; 32] })
+        );
+
+        // Removing the last vault leaves an empty shard 0
+        let after = apply_change(&after, RegistryChange::Remove([2; 32])).unwrap();
+        assert_eq!(after, [VaultRegistry::default()]);
+    }
+
+    #[test]
+    fn test_change_between_vault_states() {
+        let vault = Vault::new([1; 32], [2; 32], 100_000_000, 1_000, 10);
+        let mut closed = vault.clone();
+        closed.status = crate::types::VaultStatus::Closed;
+        let mut topped_up = vault.clone();
+        topped_up.collateral += 1;
+
+        assert_eq!(
+            RegistryChange::between(None, Some(&vault)),
+            Some(RegistryChange::Insert(RegistryEntry::from_vault(&vault)))
+        );
+        assert_eq!(
+            RegistryChange::between(Some(&vault), Some(&topped_up)),
+            Some(RegistryChange::Update(RegistryEntry::from_vault(&topped_up)))
+        );
+        assert_eq!(RegistryChange::between(Some(&vault), Some(&vault)), None);
+        assert_eq!(RegistryChange::between(Some(&vault), Some(&closed)), Some(RegistryChange::Remove([1; 32])));
+        assert_eq!(RegistryChange::between(Some(&closed), Some(&closed)), None);
+        assert_eq!(RegistryChange::between(None, None), None);
+    }
+
+    #[test]
+    fn test_spillover_into_second_shard() {
+        let full: Vec<_> = (0..MAX_REGISTRY_ENTRIES as u64).map(|i| entry(1, 1_000 + i)).map(|mut e| {
+            e.vault_id[..8].copy_from_slice(&e.interest_rate_bps.to_le_bytes());
+            e
+        }).collect();
+        let shards = registry(&full);
+        assert_eq!(shards.len(), 1);
+
+        // The lowest rate goes to the front of shard 0 and pushes the highest into shard 1
+        let shards = apply_change(&shards, RegistryChange::Insert(entry(9, 1))).unwrap();
+        assert_eq!(shards.len(), 2);
+        assert_eq!(shards[0].entries.len(), MAX_REGISTRY_ENTRIES);
+        assert_eq!(shards[0].entries[0], entry(9, 1));
+        assert_eq!(shards[1].shard, 1);
+        assert_eq!(shards[1].entries, [full[MAX_REGISTRY_ENTRIES - 1]]);
+
+        // Removing from shard 0 pulls the spilled entry back
+        let shards = apply_change(&shards, RegistryChange::Remove([9; 32])).unwrap();
+        assert_eq!(shards, registry(&full));
+    }
+
+    #[test]
+    fn test_malformed_shards_rejected() {
+        let shards = registry(&[entry(1, 100), entry(2, 300)]);
+
+        // Missing or out-of-place shard
+        assert_eq!(flatten(&[]), Err(ZkUsdError::InvalidStateTransition));
+        let mut renumbered = shards.clone();
+        renumbered[0].shard = 1;
+        assert_eq!(flatten(&renumbered), Err(ZkUsdError::InvalidStateTransition));
+
+        // Out of order
+        let mut unsorted = shards.clone();
+        unsorted[0].entries.swap(0, 1);
+        assert_eq!(flatten(&unsorted), Err(ZkUsdError::InvalidStateTransition));
+
+        // Not packed: shard 0 has room but shard 1 holds an entry
+        let sparse = [shards[0].clone(), VaultRegistry { shard: 1, entries: vec![entry(3, 400)] }];
+        assert_eq!(flatten(&sparse), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_redemption_order() {
+        let mut locked = entry(1, 100);
+        locked.created_at = 1_000;
+        let mut dust = entry(2, 200);
+        dust.debt = 1;
+        let shards = registry(&[locked, dust, entry(3, 300), entry(4, 400)]);
+        let exempt = |e: &RegistryEntry| e.created_at > 900 || e.debt < 10;
+
+        // Skipping only exempt vaults is fine
+        assert!(verify_redemption_order(&shards, &[3; 32], 0, exempt).is_ok());
+        assert!(verify_redemption_order(&shards, &[1; 32], 0, exempt).is_ok());
+
+        // Skipping vault 3 is not
+        assert_eq!(
+            verify_redemption_order(&shards, &[4; 32], 0, exempt),
+            Err(ZkUsdError::RedemptionOrderViolated { vault_id: [4; 32], skipped: [3; 32] })
+        );
+        assert_eq!(
+            verify_redemption_order(&shards, &[5; 32], 0, |_| true),
+            Err(ZkUsdError::VaultNotFound { vault_id: [5; 32] })
+        );
+    }
+
+    #[test]
+    fn test_redemption_order_mixes_fixed_and_variable() {
+        // Vault 2 opened at 150 bps on the variable rate
+        let mut variable = entry(2, 150);
+        variable.rate_mode = RateMode::Variable;
+        let shards = registry(&[entry(1, 100), variable, entry(3, 300)]);
+        let none = |_: &RegistryEntry| false;
+
+        // At a variable rate of 50 bps, vault 2 is the cheapest
+        assert!(verify_redemption_order(&shards, &[2; 32], 50, none).is_ok());
+        assert_eq!(
+            verify_redemption_order(&shards, &[1; 32], 50, none),
+            Err(ZkUsdError::RedemptionOrderViolated { vault_id: [1; 32], skipped: [2; 32] })
+        );
+
+        // At 1,050 bps it is the most expensive, and fixed vaults go first
+        assert!(verify_redemption_order(&shards, &[1; 32], 1_050, none).is_ok());
+        assert_eq!(
+            verify_redemption_order(&shards, &[2; 32], 1_050, none),
+            Err(ZkUsdError::RedemptionOrderViolated { vault_id: [2; 32], skipped: [1; 32] })
+        );
+        assert_eq!(
+            verify_redemption_order(&shards, &[3; 32], 1_050, none),
+            Err(ZkUsdError::RedemptionOrderViolated { vault_id: [3; 32], skipped: [1; 32] })
+        );
+    }
+
+    #[test]
+    fn test_equal_icr_vaults_liquidated_in_tie_key_order() {
+        const PRICE: u64 = 100_000_00000000;
+        const PRICE_BLOCK: u64 = 500;
+        // Vaults 1-3 share an ICR; vault 4 is below it
+        let mut lower = entry(4, 400);
+        lower.collateral = 150;
+        let shards = registry(&[entry(1, 100), entry(2, 200), entry(3, 300), lower]);
+        let entries = flatten(&shards).unwrap();
+
+        let order: Vec<VaultId> =
+            liquidation_order(&entries, PRICE, PRICE_BLOCK).unwrap().iter().map(|e| e.vault_id).collect();
+        assert_eq!(order[0], [4; 32]);
+        let equal = &order[1..];
+        let mut by_key = equal.to_vec();
+        by_key.sort_by_key(|id| liquidation_tie_key(id, PRICE_BLOCK));
+        assert_eq!(equal, by_key);
+
+        // Only the first equal vault in order may be taken...
+        assert_eq!(verify_liquidation_order(&shards, &equal[0], PRICE, PRICE_BLOCK), Ok(()));
+        for skipping in &equal[1..] {
+            assert_eq!(
+                verify_liquidation_order(&shards, skipping, PRICE, PRICE_BLOCK),
+                Err(ZkUsdError::LiquidationOrderViolated { vault_id: *skipping, skipped: equal[0] })
+            );
+        }
+
+        // ...then the next once it is gone
+        let shards = apply_change(&shards, RegistryChange::Remove(equal[0])).unwrap();
+        assert_eq!(verify_liquidation_order(&shards, &equal[1], PRICE, PRICE_BLOCK), Ok(()));
+        assert_eq!(
+            verify_liquidation_order(&shards, &equal[2], PRICE, PRICE_BLOCK),
+            Err(ZkUsdError::LiquidationOrderViolated { vault_id: equal[2], skipped: equal[1] })
+        );
+
+        // A vault at another ICR is not bound by the rule
+        assert_eq!(verify_liquidation_order(&shards, &[4; 32], PRICE, PRICE_BLOCK), Ok(()));
+
+        // The order is derived from the price's block
+        assert_ne!(liquidation_tie_key(&[1; 32], PRICE_BLOCK), liquidation_tie_key(&[1; 32], PRICE_BLOCK + 1));
+    }
+}
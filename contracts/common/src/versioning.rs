@@ -0,0 +1,197 @@
+//! Charm State Versioning
+//!
+//! Every persisted app state (`ZkUsdTokenState`, `VaultManagerState`,
+//! `StabilityPoolState`, the price oracle's `OracleState`) and every
+//! `Vault` leads with a `version: u8`. Decoders check it before trusting the rest of the
+//! layout, so a field added by a later release can never be misread as
+//! some other field of an older charm.
+//!
+//! ## Migration contract
+//!
+//! - Versions start at [`INITIAL_STATE_VERSION`] and only ever increase.
+//!   Version 0 is never written.
+//! - `version` stays the first field. Other fields are never removed or
+//!   reordered; a new field is appended, with a `#[serde(default)]` that
+//!   reproduces the behavior of states written before it existed.
+//! - Adding a field bumps the type's [`VersionedState::VERSION`]. Borsh
+//!   has no defaults, so the bump must also override
+//!   [`VersionedState::migrate`] with a decoder for the previous layout,
+//!   and [`VersionedState::upgrade`] to fill in the new field. Every past
+//!   version stays decodable for as long as charms of it may be live.
+//! - States are always written at the current version. An output carrying
+//!   any other version is rejected, as is any version newer than this
+//!   build understands.
+//!
+//! Charm data predating the field decodes (through serde) at version 1.
+//!
+//! ## Implicit migration
+//!
+//! There is no migration action. A spell may spend a charm at any
+//! supported version; the validator upgrades it itself, through the
+//! functions in `migrations`, before applying the action, so the output
+//! must equal the migrated input with the action's changes on top. A
+//! field the spell sets to anything but its migrated value fails the
+//! action's own checks exactly as it would on a current charm.
+//!
+//! ## Version-bump checklist
+//!
+//! 1. Freeze the current layout as `<Type>V<n>` in `migrations`, with a
+//!    `From<Type>` that keeps only its fields.
+//! 2. Add the new fields, each with a `#[serde(default)]`.
+//! 3. Write `migrate_<type>_v<n>_v<n+1>`, giving every new field a fixed
+//!    value: zero, `None`, or the value a fresh state starts with.
+//! 4. Bump `VERSION`, and override `upgrade` and `migrate` to route
+//!    version `n` through the frozen layout.
+//! 5. Capture borsh bytes of the old layout as a fixture, from a copy of
+//!    the old struct in a test-only module, and test they migrate.
+//! 6. Bump `COMMITMENT_VERSION` and regenerate the golden hashes.
+
+use borsh::BorshDeserialize;
+
+use crate::errors::{ZkUsdError, ZkUsdResult};
+use crate::migrations::{migrate_stability_pool_v1_v2, migrate_vault_v1_v2, StabilityPoolStateV1, VaultV1, V2};
+use crate::types::{StabilityPoolState, Vault};
+
+/// First version of every persisted state type
+pub const INITIAL_STATE_VERSION: u8 = 1;
+
+/// Serde default for `version`: charm data without one predates the field
+pub fn initial_state_version() -> u8 {
+    INITIAL_STATE_VERSION
+}
+
+/// A persisted state type carrying a leading version byte
+pub trait VersionedState: BorshDeserialize + Sized {
+    /// Version this build writes
+    const VERSION: u8;
+
+    /// Version this value was written at
+    fn version(&self) -> u8;
+
+    /// Bring a state decoded at an older version up to `VERSION`
+    ///
+    /// Only `VERSION` itself is accepted until a type's first bump, which
+    /// overrides this to convert each older version it still supports.
+    fn upgrade(self) -> ZkUsdResult<Self> {
+        match self.version() {
+            version if version == Self::VERSION => Ok(self),
+            found => Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION }),
+        }
+    }
+
+    /// Decode borsh bytes written at any supported version, upgraded to
+    /// `VERSION`
+    ///
+    /// The leading byte selects the layout; trailing bytes are rejected.
+    fn migrate(bytes: &[u8]) -> ZkUsdResult<Self> {
+        let found = *bytes.first().ok_or(ZkUsdError::InvalidSpellFormat)?;
+        if found != Self::VERSION {
+            return Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION });
+        }
+        Self::try_from_slice(bytes)
+            .map_err(|_| ZkUsdError::InvalidSpellFormat)?
+            .upgrade()
+    }
+
+    /// Whether this value is at the version this build writes
+    fn is_current(&self) -> bool {
+        self.version() == Self::VERSION
+    }
+}
+
+impl VersionedState for StabilityPoolState {
+    const VERSION: u8 = V2;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn upgrade(self) -> ZkUsdResult<Self> {
+        match self.version {
+            INITIAL_STATE_VERSION => Ok(migrate_stability_pool_v1_v2(StabilityPoolStateV1::from(self))),
+            V2 => Ok(self),
+            found => Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION }),
+        }
+    }
+
+    fn migrate(bytes: &[u8]) -> ZkUsdResult<Self> {
+        match *bytes.first().ok_or(ZkUsdError::InvalidSpellFormat)? {
+            INITIAL_STATE_VERSION => StabilityPoolStateV1::try_from_slice(bytes)
+                .map(migrate_stability_pool_v1_v2)
+                .map_err(|_| ZkUsdError::InvalidSpellFormat),
+            V2 => Self::try_from_slice(bytes).map_err(|_| ZkUsdError::InvalidSpellFormat),
+            found => Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION }),
+        }
+    }
+}
+
+impl VersionedState for Vault {
+    const VERSION: u8 = V2;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn upgrade(self) -> ZkUsdResult<Self> {
+        match self.version {
+            INITIAL_STATE_VERSION => Ok(migrate_vault_v1_v2(VaultV1::from(self))),
+            V2 => Ok(self),
+            found => Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION }),
+        }
+    }
+
+    fn migrate(bytes: &[u8]) -> ZkUsdResult<Self> {
+        match *bytes.first().ok_or(ZkUsdError::InvalidSpellFormat)? {
+            INITIAL_STATE_VERSION => VaultV1::try_from_slice(bytes)
+                .map(migrate_vault_v1_v2)
+                .map_err(|_| ZkUsdError::InvalidSpellFormat),
+            V2 => Self::try_from_slice(bytes).map_err(|_| ZkUsdError::InvalidSpellFormat),
+            found => Err(ZkUsdError::UnsupportedStateVersion { found, current: Self::VERSION }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_round_trips_through_migrate() {
+        let mut pool = StabilityPoolState::new();
+        pool.total_zkusd = 1_000;
+        pool.depositor_count = 3;
+
+        let bytes = borsh::to_vec(&pool).unwrap();
+        assert_eq!(bytes[0], V2);
+        assert_eq!(StabilityPoolState::migrate(&bytes).unwrap(), pool);
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_versions() {
+        let pool = StabilityPoolState::new();
+        let mut bytes = borsh::to_vec(&pool).unwrap();
+
+        for found in [0, V2 + 1] {
+            bytes[0] = found;
+            assert_eq!(
+                StabilityPoolState::migrate(&bytes),
+                Err(ZkUsdError::UnsupportedStateVersion { found, current: V2 })
+            );
+        }
+        assert_eq!(StabilityPoolState::migrate(&[]), Err(ZkUsdError::InvalidSpellFormat));
+
+        let mut padded = borsh::to_vec(&pool).unwrap();
+        padded.push(0);
+        assert_eq!(StabilityPoolState::migrate(&padded), Err(ZkUsdError::InvalidSpellFormat));
+    }
+
+    #[test]
+    fn test_unversioned_charm_data_reads_as_v1() {
+        let mut json = serde_json::to_value(StabilityPoolState::new()).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+
+        let legacy: StabilityPoolState = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.version, INITIAL_STATE_VERSION);
+        assert_eq!(legacy.upgrade().unwrap(), StabilityPoolState::new());
+    }
+}
@@ -10,6 +10,33 @@
 //! - Not consumed when read (can be referenced by multiple transactions)
 //! - Only operator can spend and update the oracle charm
 //! - Price freshness verified via block height comparison
+//!
+//! ## Signed Attestations
+//!
+//! Besides the operator's `UpdatePrice`, anyone may submit an
+//! `AggregateUpdate`: ed25519-signed observations from a quorum of the
+//! feeds the admin registered, published at their median. Signatures are
+//! checked in the validator with the `crypto` feature (on by default with
+//! `charms`); without it aggregate updates are always rejected.
+//!
+//! ## Guardian Freeze
+//!
+//! The admin may appoint a guardian, who can freeze the oracle as soon as
+//! it suspects manipulation rather than waiting for the circuit breaker to
+//! trip. A freeze trips the breaker, so liquidations price at the last good
+//! price and redemptions are refused, and rejects every price update until
+//! the admin resets the breaker. The guardian can only freeze: it can
+//! neither publish a price nor lift its own freeze.
+//!
+//! ## Secondary Feeds
+//!
+//! Next to its BTC/USD price the oracle carries up to `MAX_FEEDS` secondary
+//! feeds (a stablecoin's USD price, a market rate), each with its own
+//! operator, price bounds and single-update deviation limit, updated
+//! independently with `UpdateFeed` and read with `get_feed_price`. The
+//! BTC/USD price is the feed `BTC_USD_FEED`: `UpdateFeed` on it is an
+//! `UpdatePrice`, and `get_feed_price` on it is `get_price`, so existing
+//! consumers read it as before. A guardian freeze stops every feed.
 
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -19,41 +46,161 @@ pub mod charms;
 use serde::{Deserialize, Serialize};
 
 use zkusd_common::{
-    constants::oracle::MAX_PRICE_DEVIATION_BPS,
+    commitment::{state_commitment, CommittedApp},
+    constants::oracle::{
+        DEFAULT_DEVIATION_SCALING_BPS_PER_BLOCK, DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS,
+        DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS, DEVIATION_WINDOW_UPDATES, MAX_ATTESTATION_SOURCES,
+        MAX_CUMULATIVE_DEVIATION_BPS, MAX_DEVIATION_SCALING_BPS_PER_BLOCK, MAX_PRICE_AGE_BLOCKS,
+        MAX_PRICE_DEVIATION_BPS, MAX_SCALED_PRICE_DEVIATION_BPS, MAX_UPDATE_INTERVAL_BLOCKS,
+        MIN_CUMULATIVE_DEVIATION_BPS, MIN_UPDATE_INTERVAL_BLOCKS, BTC_USD_FEED, MAX_FEEDS,
+    },
     errors::{ZkUsdError, ZkUsdResult},
     events::{EventLog, ZkUsdEvent},
-    types::{Address, OracleAction, PriceData, PriceSource},
+    types::{
+        Address, CircuitBreakerState, Feed, FeedId, OracleAction, OracleSnapshot, PriceAttestation, PriceBounds,
+        PriceData, PriceSource,
+    },
+    validation::{require_fresh_price, require_in_range, verify_field_eq, FreshnessPolicy},
+    versioning::{initial_state_version, VersionedState, INITIAL_STATE_VERSION},
 };
 
 // ============ Oracle State ============
 
 /// Oracle contract state
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct OracleState {
+    /// Layout version (see `zkusd_common::versioning`)
+    #[serde(default = "initial_state_version")]
+    pub version: u8,
     /// Current price data
     pub price: PriceData,
+    /// Price before the latest update, in effect until the latest is
+    #[serde(default)]
+    pub previous_price: PriceData,
     /// Authorized operator (can update price)
     pub operator: Address,
     /// Admin (can change operator)
     pub admin: Address,
+    /// Guardian (can freeze the oracle); all zeros until the admin sets one
+    #[serde(default)]
+    pub guardian: Address,
     /// Whether oracle is active
     pub is_active: bool,
     /// Last valid price (fallback)
     pub last_valid_price: u64,
+    /// Minimum blocks between price updates
+    #[serde(default = "default_min_update_interval")]
+    pub min_update_interval_blocks: u64,
+    /// Limit on the summed deviation of the last `DEVIATION_WINDOW_UPDATES`
+    /// updates (BPS), so small steps cannot walk the price arbitrarily far
+    #[serde(default = "default_max_cumulative_deviation")]
+    pub max_cumulative_deviation_bps: u64,
+    /// Deviations of the most recent updates, oldest first (BPS)
+    #[serde(default)]
+    pub recent_deviations_bps: Vec<u64>,
+    /// Growth of the single-update deviation limit per block since the last
+    /// update (BPS), so a quiet period permits a larger accumulated move
+    #[serde(default)]
+    pub deviation_scaling_bps_per_block: u64,
+    /// Halts liquidations and redemptions after a sudden price move
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerState,
+    /// Ed25519 keys of the feeds whose signed attestations
+    /// `AggregateUpdate` accepts
+    #[serde(default)]
+    pub attestation_sources: Vec<[u8; 32]>,
+    /// Prices accepted for the oracle's asset (BTC's range by default)
+    #[serde(default)]
+    pub price_bounds: PriceBounds,
+    /// Secondary feeds, at most `MAX_FEEDS`
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+}
+
+fn default_min_update_interval() -> u64 {
+    DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS
+}
+
+fn default_max_cumulative_deviation() -> u64 {
+    DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS
+}
+
+impl VersionedState for OracleState {
+    const VERSION: u8 = INITIAL_STATE_VERSION;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
 }
 
 impl OracleState {
     /// Create new oracle state with initial price
     pub fn new(admin: Address, operator: Address, initial_price: u64, block_height: u64) -> Self {
         Self {
+            version: INITIAL_STATE_VERSION,
             price: PriceData::new(initial_price, block_height, PriceSource::Mock),
+            previous_price: PriceData::new(initial_price, block_height, PriceSource::Mock),
             operator,
             admin,
+            guardian: [0u8; 32],
             is_active: true,
             last_valid_price: initial_price,
+            min_update_interval_blocks: DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+            max_cumulative_deviation_bps: DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS,
+            recent_deviations_bps: Vec::new(),
+            deviation_scaling_bps_per_block: DEFAULT_DEVIATION_SCALING_BPS_PER_BLOCK,
+            circuit_breaker: CircuitBreakerState::default(),
+            attestation_sources: Vec::new(),
+            price_bounds: PriceBounds::default(),
+            feeds: Vec::new(),
+        }
+    }
+
+    /// Price and liveness, as read by the other contracts
+    pub fn snapshot(&self) -> OracleSnapshot {
+        OracleSnapshot {
+            price: self.price.clone(),
+            is_active: self.is_active,
+            circuit_breaker: self.circuit_breaker,
+            previous_price: self.previous_price.clone(),
+            operator: self.operator,
         }
     }
 
+    /// Secondary feed `feed_id`, if the oracle carries it
+    pub fn feed(&self, feed_id: &FeedId) -> Option<&Feed> {
+        self.feeds.iter().find(|feed| feed.feed_id == *feed_id)
+    }
+
+    /// Recent deviation window after recording an update of `deviation_bps`
+    pub fn deviations_after(&self, deviation_bps: u64) -> Vec<u64> {
+        let mut window = self.recent_deviations_bps.clone();
+        window.push(deviation_bps);
+        let expired = window.len().saturating_sub(DEVIATION_WINDOW_UPDATES);
+        window.drain(..expired);
+        window
+    }
+
+    /// Largest deviation a single update may make at `block_height` (BPS)
+    ///
+    /// Grows by `deviation_scaling_bps_per_block` for every block since the
+    /// last update, up to `MAX_SCALED_PRICE_DEVIATION_BPS`.
+    pub fn max_deviation_bps(&self, block_height: u64) -> u64 {
+        let elapsed = block_height.saturating_sub(self.price.timestamp_block);
+        self.deviation_scaling_bps_per_block
+            .saturating_mul(elapsed)
+            .saturating_add(MAX_PRICE_DEVIATION_BPS)
+            .min(MAX_SCALED_PRICE_DEVIATION_BPS)
+    }
+
+    /// Canonical commitment to this state (see `zkusd_common::commitment`)
+    ///
+    /// Consensus-relevant: light clients compare against this hash.
+    pub fn commitment(&self) -> [u8; 32] {
+        state_commitment(CommittedApp::PriceOracle, self)
+    }
+
     /// Default price for testing ($100,000)
     pub const DEFAULT_BTC_PRICE: u64 = 100_000_00000000;
 }
@@ -61,11 +208,22 @@ impl OracleState {
 impl Default for OracleState {
     fn default() -> Self {
         Self {
+            version: INITIAL_STATE_VERSION,
             price: PriceData::new(Self::DEFAULT_BTC_PRICE, 0, PriceSource::Mock),
+            previous_price: PriceData::new(Self::DEFAULT_BTC_PRICE, 0, PriceSource::Mock),
             operator: [0u8; 32],
             admin: [0u8; 32],
+            guardian: [0u8; 32],
             is_active: true,
             last_valid_price: Self::DEFAULT_BTC_PRICE,
+            min_update_interval_blocks: DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+            max_cumulative_deviation_bps: DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS,
+            recent_deviations_bps: Vec::new(),
+            deviation_scaling_bps_per_block: DEFAULT_DEVIATION_SCALING_BPS_PER_BLOCK,
+            circuit_breaker: CircuitBreakerState::default(),
+            attestation_sources: Vec::new(),
+            price_bounds: PriceBounds::default(),
+            feeds: Vec::new(),
         }
     }
 }
@@ -73,6 +231,7 @@ impl Default for OracleState {
 // ============ Validation Context ============
 
 /// Context for validating oracle operations
+#[derive(Debug, Clone)]
 pub struct OracleContext {
     /// Current oracle state
     pub state: OracleState,
@@ -86,19 +245,98 @@ pub struct OracleContext {
     pub events: EventLog,
 }
 
+/// Arbitrary spell inputs with an empty event log
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for OracleContext {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            state: u.arbitrary()?,
+            new_state: u.arbitrary()?,
+            signer: u.arbitrary()?,
+            block_height: u.arbitrary()?,
+            events: EventLog::new(),
+        })
+    }
+}
+
 // ============ Validation Functions ============
 
 /// Main validation entry point
 pub fn validate(ctx: &mut OracleContext, action: &OracleAction) -> ZkUsdResult<()> {
     match action {
-        OracleAction::Initialize { .. } => {
-            // Initialize is handled directly in charms.rs validate_oracle_operation
-            // as it doesn't require an input state context
-            Ok(())
+        OracleAction::Initialize { admin, operator, initial_price } => {
+            validate_initialize(ctx, admin, operator, *initial_price)?
+        }
+        OracleAction::UpdatePrice { price } => validate_update_price(ctx, *price)?,
+        OracleAction::SetOperator { operator } => validate_set_operator(ctx, operator)?,
+        OracleAction::SetUpdateLimits {
+            min_update_interval_blocks,
+            max_cumulative_deviation_bps,
+        } => validate_set_update_limits(ctx, *min_update_interval_blocks, *max_cumulative_deviation_bps)?,
+        OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block } => {
+            validate_set_deviation_scaling(ctx, *deviation_scaling_bps_per_block)?
         }
-        OracleAction::UpdatePrice { price } => validate_update_price(ctx, *price),
-        OracleAction::SetOperator { operator } => validate_set_operator(ctx, operator),
+        OracleAction::ResetCircuitBreaker => validate_reset_circuit_breaker(ctx)?,
+        OracleAction::SetAttestationSources { sources } => validate_set_attestation_sources(ctx, sources)?,
+        OracleAction::AggregateUpdate { attestations } => validate_aggregate_update(ctx, attestations)?,
+        OracleAction::SetPriceBounds { bounds } => validate_set_price_bounds(ctx, bounds)?,
+        OracleAction::SetGuardian { guardian } => validate_set_guardian(ctx, guardian)?,
+        OracleAction::FreezePrice => validate_freeze_price(ctx)?,
+        OracleAction::UpdateFeed { feed_id, price } if *feed_id == BTC_USD_FEED => validate_update_price(ctx, *price)?,
+        OracleAction::UpdateFeed { feed_id, price } => validate_update_feed(ctx, feed_id, *price)?,
+        OracleAction::AddFeed { feed } => validate_add_feed(ctx, feed)?,
+        OracleAction::RemoveFeed { feed_id } => validate_remove_feed(ctx, feed_id)?,
     }
+
+    ctx.events.emit(ZkUsdEvent::StateCommitted {
+        app: CommittedApp::PriceOracle,
+        commitment: ctx.new_state.commitment(),
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the oracle's creation
+///
+/// There is no input state: only the created state is checked, against the
+/// initialization parameters. `ctx.state` is ignored.
+fn validate_initialize(
+    ctx: &OracleContext,
+    admin: &Address,
+    operator: &Address,
+    initial_price: u64,
+) -> ZkUsdResult<()> {
+    let created = &ctx.new_state;
+
+    // 1. The created state carries the initialization parameters
+    verify_field_eq(created.admin, *admin)?;
+    verify_field_eq(created.operator, *operator)?;
+    verify_field_eq(created.price.price, initial_price)?;
+    verify_field_eq(created.last_valid_price, initial_price)?;
+    if !created.is_active {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 2. Rate limits within bounds, with an empty deviation window and no
+    //    secondary feeds
+    require_update_limits(created.min_update_interval_blocks, created.max_cumulative_deviation_bps)?;
+    if !created.recent_deviations_bps.is_empty() || !created.feeds.is_empty() {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    require_in_range(
+        created.deviation_scaling_bps_per_block,
+        0,
+        MAX_DEVIATION_SCALING_BPS_PER_BLOCK,
+        "deviation_scaling_bps_per_block",
+    )?;
+
+    // 3. The initial price within the asset's bounds: BTC's range unless the
+    //    oracle is created with bounds of its own
+    if !created.price_bounds.is_valid() || !created.price_bounds.contains(initial_price) {
+        return Err(ZkUsdError::InvalidInput { param: "initial_price", reason: "outside the oracle's price bounds" });
+    }
+    Ok(())
 }
 
 /// Validate price update
@@ -111,60 +349,125 @@ fn validate_update_price(ctx: &mut OracleContext, new_price: u64) -> ZkUsdResult
         });
     }
 
-    // 2. Oracle must be active
-    if !ctx.state.is_active {
-        return Err(ZkUsdError::InvalidOracleSource);
-    }
+    verify_price_update(ctx, new_price)
+}
+
+/// Verify publishing `new_price` at the current block, whoever submits it
+///
+/// Checks the price against the rate limits and the new state against the
+/// update, and emits the update's events.
+fn verify_price_update(ctx: &mut OracleContext, new_price: u64) -> ZkUsdResult<()> {
+    // 2. Oracle must be active, and not frozen by the guardian
+    require_accepting_updates(&ctx.state)?;
 
     // 3. Price must be positive
     if new_price == 0 {
         return Err(ZkUsdError::ZeroAmount);
     }
 
-    // 3b. Price must be within reasonable range ($1,000 - $10,000,000)
-    if !validate_price_format(new_price) {
+    // 3b. Price must be within the asset's reasonable range
+    if !ctx.state.price_bounds.contains(new_price) {
         return Err(ZkUsdError::InvalidInput {
             param: "price",
-            reason: "outside reasonable range ($1k - $10M)",
+            reason: "outside the oracle's price bounds",
+        });
+    }
+
+    // 3c. Updates must be spaced by the minimum interval
+    let last_update_block = ctx.state.price.timestamp_block;
+    if ctx.block_height.saturating_sub(last_update_block) < ctx.state.min_update_interval_blocks {
+        return Err(ZkUsdError::OracleUpdateTooSoon {
+            last_update_block,
+            current_block: ctx.block_height,
+            min_interval: ctx.state.min_update_interval_blocks,
         });
     }
 
-    // 4. Check price deviation (prevent manipulation)
+    // 4. Check price deviation (prevent manipulation); the limit widens
+    //    with the time since the last update
     let old_price = ctx.state.price.price;
     let deviation = calculate_price_deviation(old_price, new_price);
+    let max_deviation_bps = ctx.state.max_deviation_bps(ctx.block_height);
 
-    if deviation > MAX_PRICE_DEVIATION_BPS {
+    if deviation > max_deviation_bps {
         return Err(ZkUsdError::OraclePriceDeviation {
             old_price,
             new_price,
-            max_deviation_bps: MAX_PRICE_DEVIATION_BPS,
+            max_deviation_bps,
+        });
+    }
+
+    // 4b. Deviation summed over the recent window must stay within the limit
+    let window = ctx.state.deviations_after(deviation);
+    let cumulative_bps: u64 = window.iter().sum();
+    if cumulative_bps > ctx.state.max_cumulative_deviation_bps {
+        return Err(ZkUsdError::OracleCumulativeDeviation {
+            cumulative_bps,
+            max_bps: ctx.state.max_cumulative_deviation_bps,
         });
     }
 
-    // 5. Verify new state
+    // 5. Verify new state: the price takes effect next block, and the
+    //    current one stays in effect until then
     if ctx.new_state.price.price != new_price {
         return Err(ZkUsdError::InvalidStateTransition);
     }
     if ctx.new_state.price.timestamp_block != ctx.block_height {
         return Err(ZkUsdError::InvalidStateTransition);
     }
+    verify_field_eq(ctx.new_state.price.effective_from_block, ctx.block_height.saturating_add(1))?;
+    verify_field_eq(&ctx.new_state.previous_price, &ctx.state.price)?;
 
     // 6. Update last valid price
     if ctx.new_state.last_valid_price != new_price {
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // 7. Emit event
+    // 6b. Record the deviation; the limits themselves are unchanged
+    verify_field_eq(&ctx.new_state.recent_deviations_bps, &window)?;
+    verify_field_eq(ctx.new_state.min_update_interval_blocks, ctx.state.min_update_interval_blocks)?;
+    verify_field_eq(ctx.new_state.max_cumulative_deviation_bps, ctx.state.max_cumulative_deviation_bps)?;
+    verify_field_eq(ctx.new_state.deviation_scaling_bps_per_block, ctx.state.deviation_scaling_bps_per_block)?;
+    verify_field_eq(&ctx.new_state.attestation_sources, &ctx.state.attestation_sources)?;
+    verify_field_eq(ctx.new_state.price_bounds, ctx.state.price_bounds)?;
+    verify_field_eq(ctx.new_state.guardian, ctx.state.guardian)?;
+    verify_field_eq(&ctx.new_state.feeds, &ctx.state.feeds)?;
+
+    // 6c. The circuit breaker follows the move from its reference price
+    let breaker = ctx.state.circuit_breaker.after_update(&ctx.state.price, new_price, ctx.block_height);
+    verify_field_eq(ctx.new_state.circuit_breaker, breaker)?;
+
+    // 7. Emit events
     ctx.events.emit(ZkUsdEvent::PriceUpdated {
         old_price,
         new_price,
         source: ctx.state.price.source as u8,
         block_height: ctx.block_height,
     });
+    if breaker.tripped && !ctx.state.circuit_breaker.is_active(ctx.block_height) {
+        ctx.events.emit(ZkUsdEvent::CircuitBreakerTripped {
+            reference_price: breaker.reference_price,
+            new_price,
+            until_block: breaker.until_block(),
+            block_height: ctx.block_height,
+        });
+    }
 
     Ok(())
 }
 
+/// Require an active oracle the guardian has not frozen, so it accepts
+/// price updates on any feed
+fn require_accepting_updates(state: &OracleState) -> ZkUsdResult<()> {
+    if !state.is_active {
+        return Err(ZkUsdError::InvalidOracleSource);
+    }
+    if state.circuit_breaker.guardian_frozen {
+        return Err(ZkUsdError::OracleFrozen { frozen_at: state.circuit_breaker.tripped_at });
+    }
+    Ok(())
+}
+
 /// Validate operator change
 fn validate_set_operator(ctx: &mut OracleContext, new_operator: &Address) -> ZkUsdResult<()> {
     // 1. Only admin can change operator
@@ -195,6 +498,498 @@ fn validate_set_operator(ctx: &mut OracleContext, new_operator: &Address) -> ZkU
     Ok(())
 }
 
+/// Validate tuning the price update rate limits
+fn validate_set_update_limits(
+    ctx: &mut OracleContext,
+    min_update_interval_blocks: u64,
+    max_cumulative_deviation_bps: u64,
+) -> ZkUsdResult<()> {
+    // 1. Only admin can tune the limits
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. Limits must be within their bounds
+    require_update_limits(min_update_interval_blocks, max_cumulative_deviation_bps)?;
+
+    // 3. Verify new state: only the limits change
+    let expected = OracleState {
+        min_update_interval_blocks,
+        max_cumulative_deviation_bps,
+        ..ctx.state.clone()
+    };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleUpdateLimitsChanged {
+        min_update_interval_blocks,
+        max_cumulative_deviation_bps,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate tuning how fast the deviation limit grows between updates
+fn validate_set_deviation_scaling(
+    ctx: &mut OracleContext,
+    deviation_scaling_bps_per_block: u64,
+) -> ZkUsdResult<()> {
+    // 1. Only admin can tune the scaling
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. Scaling must be within its bounds
+    require_in_range(
+        deviation_scaling_bps_per_block,
+        0,
+        MAX_DEVIATION_SCALING_BPS_PER_BLOCK,
+        "deviation_scaling_bps_per_block",
+    )?;
+
+    // 3. Verify new state: only the scaling changes
+    let expected = OracleState { deviation_scaling_bps_per_block, ..ctx.state.clone() };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleDeviationScalingChanged {
+        old_bps_per_block: ctx.state.deviation_scaling_bps_per_block,
+        new_bps_per_block: deviation_scaling_bps_per_block,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin setting the guardian
+fn validate_set_guardian(ctx: &mut OracleContext, new_guardian: &Address) -> ZkUsdResult<()> {
+    // 1. Only admin can set the guardian
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. New guardian must be different
+    if *new_guardian == ctx.state.guardian {
+        return Err(ZkUsdError::InvalidInput {
+            param: "guardian",
+            reason: "same as current",
+        });
+    }
+
+    // 3. Verify new state: only the guardian changes
+    let expected = OracleState { guardian: *new_guardian, ..ctx.state.clone() };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleGuardianChanged {
+        old_guardian: ctx.state.guardian,
+        new_guardian: *new_guardian,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the guardian freezing the oracle
+fn validate_freeze_price(ctx: &mut OracleContext) -> ZkUsdResult<()> {
+    // 1. Only the guardian can freeze, and only once one is set
+    if ctx.signer != ctx.state.guardian || ctx.state.guardian == [0u8; 32] {
+        return Err(ZkUsdError::Unauthorized {
+            expected: ctx.state.guardian,
+            actual: ctx.signer,
+        });
+    }
+
+    // 2. Oracle must not be frozen already
+    if ctx.state.circuit_breaker.guardian_frozen {
+        return Err(ZkUsdError::InvalidInput {
+            param: "circuit_breaker",
+            reason: "already frozen",
+        });
+    }
+
+    // 3. Verify new state: only the breaker changes, frozen at the last
+    //    good price
+    let breaker = ctx.state.circuit_breaker.freeze(&ctx.state.price, ctx.block_height);
+    let expected = OracleState { circuit_breaker: breaker, ..ctx.state.clone() };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleFrozen {
+        guardian: ctx.signer,
+        reference_price: breaker.reference_price,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin clearing a tripped circuit breaker or lifting a
+/// guardian freeze
+fn validate_reset_circuit_breaker(ctx: &mut OracleContext) -> ZkUsdResult<()> {
+    // 1. Only admin can reset the breaker
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. Breaker must have tripped
+    if !ctx.state.circuit_breaker.tripped {
+        return Err(ZkUsdError::InvalidInput {
+            param: "circuit_breaker",
+            reason: "not tripped",
+        });
+    }
+
+    // 3. Verify new state: only the breaker changes, measuring from the
+    //    current price again
+    let expected = OracleState {
+        circuit_breaker: ctx.state.circuit_breaker.reset(&ctx.state.price),
+        ..ctx.state.clone()
+    };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::CircuitBreakerReset {
+        by: ctx.signer,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin replacing the registered attestation sources
+fn validate_set_attestation_sources(ctx: &mut OracleContext, sources: &[[u8; 32]]) -> ZkUsdResult<()> {
+    // 1. Only admin can register feeds
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. Bounded, so verifying a quorum stays cheap, and without repeats,
+    //    so one feed cannot count toward the quorum twice
+    if sources.len() > MAX_ATTESTATION_SOURCES {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: sources.len() as u64,
+            maximum: MAX_ATTESTATION_SOURCES as u64,
+        });
+    }
+    if has_duplicate(sources) {
+        return Err(ZkUsdError::InvalidInput {
+            param: "sources",
+            reason: "duplicate source",
+        });
+    }
+
+    // 3. Verify new state: only the sources change
+    let expected = OracleState { attestation_sources: sources.to_vec(), ..ctx.state.clone() };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleAttestationSourcesChanged {
+        old_count: ctx.state.attestation_sources.len() as u64,
+        new_count: sources.len() as u64,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin setting the range of prices accepted for the asset
+fn validate_set_price_bounds(ctx: &mut OracleContext, bounds: &PriceBounds) -> ZkUsdResult<()> {
+    // 1. Only admin can set the bounds
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. Bounds must be a non-empty range of positive prices
+    if !bounds.is_valid() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "price_bounds",
+            reason: "min must be positive and below max",
+        });
+    }
+
+    // 3. Verify new state: only the bounds change
+    let expected = OracleState { price_bounds: *bounds, ..ctx.state.clone() };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OraclePriceBoundsChanged {
+        old_min_price: ctx.state.price_bounds.min_price,
+        old_max_price: ctx.state.price_bounds.max_price,
+        new_min_price: bounds.min_price,
+        new_max_price: bounds.max_price,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate publishing the median of signed attestations
+///
+/// Anyone may submit: authority comes from the signatures, which must be
+/// from a quorum of distinct registered sources, each observed since the
+/// current price and no older than `MAX_PRICE_AGE_BLOCKS`. The median
+/// then passes the same rate limits as an operator update.
+fn validate_aggregate_update(ctx: &mut OracleContext, attestations: &[PriceAttestation]) -> ZkUsdResult<()> {
+    // 1. A quorum of the registered sources must attest
+    let quorum = attestation_quorum(ctx.state.attestation_sources.len());
+    if attestations.len() < quorum {
+        return Err(ZkUsdError::BelowMinimum {
+            amount: attestations.len() as u64,
+            minimum: quorum as u64,
+        });
+    }
+
+    // 2. Every attestation comes from a distinct registered source
+    for (i, attestation) in attestations.iter().enumerate() {
+        if !ctx.state.attestation_sources.contains(&attestation.source_pubkey) {
+            return Err(ZkUsdError::InvalidOracleSource);
+        }
+        if attestations[..i].iter().any(|a| a.source_pubkey == attestation.source_pubkey) {
+            return Err(ZkUsdError::InvalidInput {
+                param: "attestations",
+                reason: "duplicate source",
+            });
+        }
+    }
+
+    // 3. Every observation is newer than the current price and fresh, so
+    //    attestations cannot be replayed into a later update
+    let last_update_block = ctx.state.price.timestamp_block;
+    for attestation in attestations {
+        let observed = attestation.observed_at_block;
+        if observed <= last_update_block
+            || observed > ctx.block_height
+            || ctx.block_height - observed > MAX_PRICE_AGE_BLOCKS
+        {
+            return Err(ZkUsdError::OracleStale {
+                last_update_block: observed,
+                current_block: ctx.block_height,
+                max_age: MAX_PRICE_AGE_BLOCKS,
+            });
+        }
+    }
+
+    // 4. Every signature verifies
+    for attestation in attestations {
+        verify_attestation(attestation)?;
+    }
+
+    // 5. The median is published as an aggregated price; nothing else
+    //    changes beyond what the update itself records
+    let new_price = median_attested_price(attestations).ok_or(ZkUsdError::ZeroAmount)?;
+    verify_price_update(ctx, new_price)?;
+    let expected = OracleState {
+        price: PriceData::published(new_price, ctx.block_height, PriceSource::Aggregated),
+        previous_price: ctx.state.price.clone(),
+        last_valid_price: new_price,
+        recent_deviations_bps: ctx.new_state.recent_deviations_bps.clone(),
+        circuit_breaker: ctx.new_state.circuit_breaker,
+        ..ctx.state.clone()
+    };
+    verify_field_eq(&ctx.new_state, &expected)
+}
+
+// ============ Secondary Feeds ============
+
+/// Error for a feed the oracle does not carry
+fn unknown_feed() -> ZkUsdError {
+    ZkUsdError::InvalidInput { param: "feed_id", reason: "unknown feed" }
+}
+
+/// Validate a secondary feed's operator publishing a price
+///
+/// The feed has its own bounds and deviation limit, and shares the
+/// oracle's minimum update interval. Only the feed's price changes; the
+/// circuit breaker and deviation window track the BTC/USD price alone.
+fn validate_update_feed(ctx: &mut OracleContext, feed_id: &FeedId, new_price: u64) -> ZkUsdResult<()> {
+    // 1. The feed must exist
+    let index = ctx.state.feeds.iter().position(|feed| feed.feed_id == *feed_id).ok_or_else(unknown_feed)?;
+    let feed = &ctx.state.feeds[index];
+
+    // 2. Only the feed's operator can update it
+    if ctx.signer != feed.operator {
+        return Err(ZkUsdError::Unauthorized {
+            expected: feed.operator,
+            actual: ctx.signer,
+        });
+    }
+
+    // 3. Oracle must be active, and not frozen by the guardian
+    require_accepting_updates(&ctx.state)?;
+
+    // 4. Price must be positive and within the feed's bounds
+    if new_price == 0 {
+        return Err(ZkUsdError::ZeroAmount);
+    }
+    if !feed.bounds.contains(new_price) {
+        return Err(ZkUsdError::InvalidInput {
+            param: "price",
+            reason: "outside the feed's price bounds",
+        });
+    }
+
+    // 5. Updates must be spaced by the minimum interval
+    let last_update_block = feed.price.timestamp_block;
+    if ctx.block_height.saturating_sub(last_update_block) < ctx.state.min_update_interval_blocks {
+        return Err(ZkUsdError::OracleUpdateTooSoon {
+            last_update_block,
+            current_block: ctx.block_height,
+            min_interval: ctx.state.min_update_interval_blocks,
+        });
+    }
+
+    // 6. Deviation within the feed's limit
+    let old_price = feed.price.price;
+    if calculate_price_deviation(old_price, new_price) > feed.max_deviation_bps {
+        return Err(ZkUsdError::OraclePriceDeviation {
+            old_price,
+            new_price,
+            max_deviation_bps: feed.max_deviation_bps,
+        });
+    }
+
+    // 7. Verify new state: only the feed's price changes, taking effect
+    //    next block
+    let mut expected = ctx.state.clone();
+    expected.feeds[index].price = PriceData {
+        price: new_price,
+        timestamp_block: ctx.block_height,
+        effective_from_block: ctx.block_height.saturating_add(1),
+        ..feed.price.clone()
+    };
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 8. Emit event
+    ctx.events.emit(ZkUsdEvent::FeedPriceUpdated {
+        feed_id: *feed_id,
+        old_price,
+        new_price,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin adding a secondary feed
+///
+/// The feed starts at its initial price, published at this block.
+fn validate_add_feed(ctx: &mut OracleContext, feed: &Feed) -> ZkUsdResult<()> {
+    // 1. Only admin can add feeds
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. A new id, never BTC/USD's, within the feed limit
+    if feed.feed_id == BTC_USD_FEED || ctx.state.feed(&feed.feed_id).is_some() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "feed_id",
+            reason: "feed already exists",
+        });
+    }
+    if ctx.state.feeds.len() >= MAX_FEEDS {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: ctx.state.feeds.len() as u64 + 1,
+            maximum: MAX_FEEDS as u64,
+        });
+    }
+
+    // 3. Sane limits, and an initial price within them
+    if !feed.bounds.is_valid() || !feed.bounds.contains(feed.price.price) {
+        return Err(ZkUsdError::InvalidInput {
+            param: "feed",
+            reason: "initial price outside valid bounds",
+        });
+    }
+    require_in_range(feed.max_deviation_bps, 1, MAX_SCALED_PRICE_DEVIATION_BPS, "max_deviation_bps")?;
+    verify_field_eq(feed.price.timestamp_block, ctx.block_height)?;
+
+    // 4. Verify new state: only the feed is added
+    let mut expected = ctx.state.clone();
+    expected.feeds.push(feed.clone());
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 5. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleFeedAdded {
+        feed_id: feed.feed_id,
+        operator: feed.operator,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate the admin removing a secondary feed
+fn validate_remove_feed(ctx: &mut OracleContext, feed_id: &FeedId) -> ZkUsdResult<()> {
+    // 1. Only admin can remove feeds
+    if ctx.signer != ctx.state.admin {
+        return Err(ZkUsdError::AdminOnly);
+    }
+
+    // 2. The feed must exist (BTC/USD is not a secondary feed)
+    ctx.state.feed(feed_id).ok_or_else(unknown_feed)?;
+
+    // 3. Verify new state: only the feed is removed
+    let mut expected = ctx.state.clone();
+    expected.feeds.retain(|feed| feed.feed_id != *feed_id);
+    verify_field_eq(&ctx.new_state, &expected)?;
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::OracleFeedRemoved {
+        feed_id: *feed_id,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+// ============ Attestations ============
+
+/// Verify an attestation's ed25519 signature over its message
+///
+/// Uses strict verification, rejecting non-canonical signatures and
+/// small-order keys, so every accepted attestation has one encoding.
+///
+/// # Errors
+/// - `InvalidAttestationSignature` if the key or signature is malformed or
+///   does not verify
+#[cfg(feature = "crypto")]
+pub fn verify_attestation(attestation: &PriceAttestation) -> ZkUsdResult<()> {
+    use ed25519_dalek::{Signature, VerifyingKey};
+
+    let invalid = |_| ZkUsdError::InvalidAttestationSignature { source_id: attestation.source_pubkey };
+    let key = VerifyingKey::from_bytes(&attestation.source_pubkey).map_err(invalid)?;
+    let signature = Signature::from_bytes(&attestation.signature);
+    key.verify_strict(&attestation.message(), &signature).map_err(invalid)
+}
+
+/// Without the `crypto` feature no signature can be checked, so every
+/// attestation is rejected
+#[cfg(not(feature = "crypto"))]
+pub fn verify_attestation(attestation: &PriceAttestation) -> ZkUsdResult<()> {
+    Err(ZkUsdError::InvalidAttestationSignature { source_id: attestation.source_pubkey })
+}
+
+/// Attestations an aggregate update needs: a majority of the registered
+/// sources (one if none are registered, which no attestation can meet)
+pub fn attestation_quorum(sources: usize) -> usize {
+    sources / 2 + 1
+}
+
+/// Median of the attested prices (the lower middle for an even count, so
+/// the result is always a price some source attested)
+pub fn median_attested_price(attestations: &[PriceAttestation]) -> Option<u64> {
+    let mut prices: Vec<u64> = attestations.iter().map(|a| a.price).collect();
+    prices.sort_unstable();
+    prices.get(prices.len().saturating_sub(1) / 2).copied()
+}
+
+/// Whether any key appears more than once
+fn has_duplicate(keys: &[[u8; 32]]) -> bool {
+    keys.iter().enumerate().any(|(i, key)| keys[..i].contains(key))
+}
+
 // ============ Query Functions ============
 
 /// Get current BTC price
@@ -206,36 +1001,52 @@ fn validate_set_operator(ctx: &mut OracleContext, new_operator: &Address) -> ZkU
 /// - `OracleNotInitialized` if oracle is not active
 /// - `OracleStale` if price exceeds MAX_PRICE_AGE_BLOCKS
 pub fn get_price(state: &OracleState, current_block: u64) -> ZkUsdResult<u64> {
-    // Check if oracle is active
-    if !state.is_active {
-        return Err(ZkUsdError::OracleNotInitialized);
-    }
+    require_fresh_price(&state.snapshot(), current_block, &FreshnessPolicy::default())
+}
 
-    // Check if price is stale - MUST error in production to prevent
-    // using outdated prices for liquidations/redemptions
-    if state.price.is_stale(current_block) {
-        return Err(ZkUsdError::OracleStale {
-            last_update_block: state.price.timestamp_block,
-            current_block,
-            max_age: zkusd_common::constants::oracle::MAX_PRICE_AGE_BLOCKS,
-        });
+/// Get a feed's current price
+///
+/// `BTC_USD_FEED` reads the oracle's own price, exactly as `get_price`.
+/// A secondary feed is held to the same staleness limit, counted from its
+/// own last update.
+///
+/// # Errors
+/// - `InvalidInput` if the oracle carries no such feed
+/// - `OracleNotInitialized` if oracle is not active
+/// - `OracleStale` if the feed's price exceeds MAX_PRICE_AGE_BLOCKS
+pub fn get_feed_price(state: &OracleState, feed_id: &FeedId, current_block: u64) -> ZkUsdResult<u64> {
+    if *feed_id == BTC_USD_FEED {
+        return get_price(state, current_block);
     }
-
-    Ok(state.price.price)
+    let feed = state.feed(feed_id).ok_or_else(unknown_feed)?;
+    let snapshot = OracleSnapshot { price: feed.price.clone(), ..state.snapshot() };
+    require_fresh_price(&snapshot, current_block, &FreshnessPolicy::default())
 }
 
 /// Get price with fallback for read-only queries (NOT for transactions)
 ///
 /// This function can return stale prices and should ONLY be used for
 /// display/informational purposes, never for validation logic.
-pub fn get_price_for_display(state: &OracleState, current_block: u64) -> (u64, bool) {
+/// Returns `(price, is_stale, effective_confidence)`.
+pub fn get_price_for_display(state: &OracleState, current_block: u64) -> (u64, bool, u8) {
     let is_stale = state.price.is_stale(current_block);
     let price = if is_stale {
         state.last_valid_price
     } else {
         state.price.price
     };
-    (price, is_stale)
+    (price, is_stale, effective_confidence(state, current_block))
+}
+
+/// Get the stored confidence decayed by price age
+///
+/// Equals `price.confidence` on the update block and decays linearly to
+/// zero at `MAX_PRICE_AGE_BLOCKS`. An inactive oracle has no confidence.
+pub fn effective_confidence(state: &OracleState, current_block: u64) -> u8 {
+    if !state.is_active {
+        return 0;
+    }
+    state.price.effective_confidence(current_block)
 }
 
 /// Check if price is fresh (not stale)
@@ -243,13 +1054,24 @@ pub fn is_price_fresh(state: &OracleState, current_block: u64) -> bool {
     state.is_active && !state.price.is_stale(current_block)
 }
 
+// ============ Quote Conversions ============
+
+/// Conversions at an oracle price, in both rounding directions. These are
+/// the protocol's own (`zkusd_common::math`), so an integrator quoting
+/// redemptions or deposits rounds exactly as the contracts do: `_floor`
+/// for amounts paid to the user, `_ceil` for amounts charged.
+pub use zkusd_common::math::{
+    btc_to_zkusd_ceil, btc_to_zkusd_floor, price_inverse_ceil, price_inverse_floor, zkusd_to_btc_ceil,
+    zkusd_to_btc_floor,
+};
+
 // ============ Helper Functions ============
 
 /// Calculate price deviation in basis points
 ///
 /// Returns the percentage deviation between old and new price in basis points.
 /// 100 bps = 1%, 10000 bps = 100%
-fn calculate_price_deviation(old_price: u64, new_price: u64) -> u64 {
+pub fn calculate_price_deviation(old_price: u64, new_price: u64) -> u64 {
     if old_price == 0 {
         return 10000; // 100% if no previous price
     }
@@ -267,13 +1089,33 @@ fn calculate_price_deviation(old_price: u64, new_price: u64) -> u64 {
     deviation.min(u64::MAX as u128) as u64
 }
 
-/// Validate price format (8 decimals)
+/// Require price update rate limits to be within their bounds
+fn require_update_limits(min_update_interval_blocks: u64, max_cumulative_deviation_bps: u64) -> ZkUsdResult<()> {
+    require_in_range(
+        min_update_interval_blocks,
+        MIN_UPDATE_INTERVAL_BLOCKS,
+        MAX_UPDATE_INTERVAL_BLOCKS,
+        "min_update_interval_blocks",
+    )?;
+    require_in_range(
+        max_cumulative_deviation_bps,
+        MIN_CUMULATIVE_DEVIATION_BPS,
+        MAX_CUMULATIVE_DEVIATION_BPS,
+        "max_cumulative_deviation_bps",
+    )
+}
+
+/// Check price update rate limits are within their bounds
+pub fn update_limits_in_bounds(min_update_interval_blocks: u64, max_cumulative_deviation_bps: u64) -> bool {
+    require_update_limits(min_update_interval_blocks, max_cumulative_deviation_bps).is_ok()
+}
+
+/// Validate price format (8 decimals) against the default BTC bounds
+///
+/// Updates are checked against the oracle's own `price_bounds`.
 pub fn validate_price_format(price: u64) -> bool {
     // Price should be reasonable ($1,000 - $10,000,000)
-    const MIN_REASONABLE_PRICE: u64 = 1_000_00000000;      // $1,000
-    const MAX_REASONABLE_PRICE: u64 = 10_000_000_00000000; // $10,000,000
-
-    price >= MIN_REASONABLE_PRICE && price <= MAX_REASONABLE_PRICE
+    PriceBounds::default().contains(price)
 }
 
 /// Convert price to different decimal precision
@@ -296,6 +1138,7 @@ pub fn convert_price_decimals(price: u64, from_decimals: u8, to_decimals: u8) ->
 #[cfg(test)]
 mod tests {
     use super::*;
+    use zkusd_common::constants::oracle::CIRCUIT_BREAKER_COOLDOWN_BLOCKS;
 
     const BTC_PRICE_100K: u64 = 100_000_00000000;
 
@@ -307,11 +1150,57 @@ mod tests {
             state: OracleState::new(admin, operator, BTC_PRICE_100K, 100),
             new_state: OracleState::new(admin, operator, BTC_PRICE_100K, 100),
             signer: operator,
-            block_height: 101,
+            block_height: 100 + DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
             events: EventLog::new(),
         }
     }
 
+    /// Apply a price update on top of `ctx.state`, advancing the state on success
+    fn update_on(ctx: &mut OracleContext, price: u64) -> ZkUsdResult<()> {
+        let deviation = calculate_price_deviation(ctx.state.price.price, price);
+        ctx.new_state = ctx.state.clone();
+        ctx.new_state.price.price = price;
+        ctx.new_state.price.timestamp_block = ctx.block_height;
+        ctx.new_state.price.effective_from_block = ctx.block_height + 1;
+        ctx.new_state.previous_price = ctx.state.price.clone();
+        ctx.new_state.last_valid_price = price;
+        ctx.new_state.recent_deviations_bps = ctx.state.deviations_after(deviation);
+        ctx.new_state.circuit_breaker = ctx.state.circuit_breaker.after_update(&ctx.state.price, price, ctx.block_height);
+
+        validate(ctx, &OracleAction::UpdatePrice { price })?;
+        ctx.state = ctx.new_state.clone();
+        Ok(())
+    }
+
+    #[test]
+    fn test_initialize_checks_created_state() {
+        let (admin, operator) = ([9u8; 32], [1u8; 32]);
+        let created = OracleState::new(admin, operator, BTC_PRICE_100K, 100);
+        let initialize = OracleAction::Initialize { admin, operator, initial_price: BTC_PRICE_100K };
+        let mut ctx = OracleContext {
+            state: created.clone(),
+            new_state: created.clone(),
+            signer: admin,
+            block_height: 100,
+            events: EventLog::new(),
+        };
+        assert_eq!(validate(&mut ctx, &initialize), Ok(()));
+
+        // The created state must carry the witness's parameters and sane limits
+        let bad_states = [
+            OracleState { operator: admin, ..created.clone() },
+            OracleState { last_valid_price: BTC_PRICE_100K + 1, ..created.clone() },
+            OracleState { is_active: false, ..created.clone() },
+            OracleState { min_update_interval_blocks: 0, ..created.clone() },
+            OracleState { recent_deviations_bps: vec![100], ..created.clone() },
+            OracleState { price_bounds: PriceBounds { min_price: 1, max_price: BTC_PRICE_100K - 1 }, ..created },
+        ];
+        for new_state in bad_states {
+            let mut ctx = OracleContext { new_state, ..ctx.clone() };
+            assert!(validate(&mut ctx, &initialize).is_err());
+        }
+    }
+
     #[test]
     fn test_update_price_success() {
         let mut ctx = create_test_context();
@@ -319,13 +1208,28 @@ mod tests {
 
         ctx.new_state.price.price = new_price;
         ctx.new_state.price.timestamp_block = ctx.block_height;
+        ctx.new_state.price.effective_from_block = ctx.block_height + 1;
+        ctx.new_state.previous_price = ctx.state.price.clone();
         ctx.new_state.last_valid_price = new_price;
+        ctx.new_state.recent_deviations_bps = vec![100];
+        ctx.new_state.circuit_breaker.reference_price = BTC_PRICE_100K;
+        ctx.new_state.circuit_breaker.reference_block = 100;
 
         let action = OracleAction::UpdatePrice { price: new_price };
+        let spell = ctx.clone();
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Should succeed: {:?}", result);
-        assert_eq!(ctx.events.len(), 1);
+        // PriceUpdated + StateCommitted
+        assert_eq!(ctx.events.len(), 2);
+
+        // The price takes effect next block, with the old one kept until then
+        let mut immediate = spell.clone();
+        immediate.new_state.price.effective_from_block = immediate.block_height;
+        assert_eq!(validate(&mut immediate, &action), Err(ZkUsdError::InvalidStateTransition));
+        let mut forgetful = spell;
+        forgetful.new_state.previous_price = forgetful.new_state.price.clone();
+        assert_eq!(validate(&mut forgetful, &action), Err(ZkUsdError::InvalidStateTransition));
     }
 
     #[test]
@@ -339,6 +1243,329 @@ mod tests {
         assert!(matches!(result, Err(ZkUsdError::OraclePriceDeviation { .. })));
     }
 
+    #[test]
+    fn test_back_to_back_updates_rejected() {
+        let mut ctx = create_test_context();
+        update_on(&mut ctx, BTC_PRICE_100K / 100 * 101).expect("first update should succeed");
+
+        // Same block, then the next block: both inside the interval
+        for _ in 0..DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS {
+            assert_eq!(
+                update_on(&mut ctx, BTC_PRICE_100K / 100 * 102),
+                Err(ZkUsdError::OracleUpdateTooSoon {
+                    last_update_block: 102,
+                    current_block: ctx.block_height,
+                    min_interval: DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS,
+                })
+            );
+            ctx.block_height += 1;
+        }
+
+        assert!(update_on(&mut ctx, BTC_PRICE_100K / 100 * 102).is_ok());
+    }
+
+    #[test]
+    fn test_walking_price_rejected_by_cumulative_limit() {
+        let mut ctx = create_test_context();
+        ctx.state.min_update_interval_blocks = 1;
+        ctx.block_height = 101;
+
+        // Three 5% steps use the whole 15% window
+        let mut price = BTC_PRICE_100K;
+        for _ in 0..3 {
+            price = price / 100 * 105;
+            update_on(&mut ctx, price).expect("step within the window limit");
+            ctx.block_height += 1;
+        }
+
+        // A fourth 5% step would walk the price 20% in five blocks
+        assert_eq!(
+            update_on(&mut ctx, price / 100 * 105),
+            Err(ZkUsdError::OracleCumulativeDeviation {
+                cumulative_bps: 2000,
+                max_bps: DEFAULT_MAX_CUMULATIVE_DEVIATION_BPS,
+            })
+        );
+    }
+
+    /// Context whose limits let five 5% steps land in five blocks
+    fn crash_context() -> OracleContext {
+        let mut ctx = create_test_context();
+        ctx.state.min_update_interval_blocks = 1;
+        ctx.state.max_cumulative_deviation_bps = MAX_CUMULATIVE_DEVIATION_BPS;
+        ctx.block_height = 101;
+        ctx
+    }
+
+    /// Drop the price 5% per block, five times
+    fn crash(ctx: &mut OracleContext) {
+        for _ in 0..5 {
+            let price = ctx.state.price.price / 100 * 95;
+            ctx.events = EventLog::new();
+            update_on(ctx, price).expect("each step is within the update limits");
+            ctx.block_height += 1;
+        }
+    }
+
+    #[test]
+    fn test_crash_trips_circuit_breaker() {
+        let mut ctx = crash_context();
+        crash(&mut ctx);
+
+        // 0.95^5: a 22.6% fall within the window
+        let breaker = ctx.state.circuit_breaker;
+        assert!(breaker.tripped);
+        assert_eq!((breaker.tripped_at, breaker.reference_price), (105, BTC_PRICE_100K));
+        assert!(ctx.events.events().contains(&ZkUsdEvent::CircuitBreakerTripped {
+            reference_price: BTC_PRICE_100K,
+            new_price: ctx.state.price.price,
+            until_block: 105 + CIRCUIT_BREAKER_COOLDOWN_BLOCKS,
+            block_height: 105,
+        }));
+
+        // The first update after the cooldown clears it
+        ctx.block_height = breaker.until_block() + 1;
+        let price = ctx.state.price.price / 100 * 99;
+        let before = ctx.clone();
+        update_on(&mut ctx, price).expect("update after the cooldown should succeed");
+        assert!(!ctx.state.circuit_breaker.tripped);
+
+        // Carrying the tripped breaker over instead is rejected
+        let mut forged = before;
+        forged.new_state = OracleState { circuit_breaker: breaker, ..ctx.state.clone() };
+        assert!(validate(&mut forged, &OracleAction::UpdatePrice { price }).is_err());
+    }
+
+    #[test]
+    fn test_reset_circuit_breaker() {
+        let mut ctx = crash_context();
+        crash(&mut ctx);
+        let admin = ctx.state.admin;
+        let expected = OracleState {
+            circuit_breaker: ctx.state.circuit_breaker.reset(&ctx.state.price),
+            ..ctx.state.clone()
+        };
+        ctx.new_state = expected.clone();
+        ctx.events = EventLog::new();
+
+        // Only the admin may reset
+        assert_eq!(validate(&mut ctx.clone(), &OracleAction::ResetCircuitBreaker), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = admin;
+        validate(&mut ctx, &OracleAction::ResetCircuitBreaker).expect("admin should reset the breaker");
+        assert!(!ctx.new_state.circuit_breaker.is_active(ctx.block_height));
+        assert_eq!(ctx.new_state.circuit_breaker.reference_price, ctx.state.price.price);
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::CircuitBreakerReset { by: admin, block_height: ctx.block_height }
+        );
+
+        // Nothing to reset once cleared
+        ctx.state = expected;
+        assert!(matches!(
+            validate(&mut ctx, &OracleAction::ResetCircuitBreaker),
+            Err(ZkUsdError::InvalidInput { param: "circuit_breaker", .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_guardian() {
+        let mut ctx = create_test_context();
+        let guardian = [9u8; 32];
+        ctx.new_state.guardian = guardian;
+        let action = OracleAction::SetGuardian { guardian };
+
+        // Only the admin may appoint the guardian
+        assert_eq!(validate(&mut ctx.clone(), &action), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = ctx.state.admin;
+        validate(&mut ctx, &action).expect("admin should set the guardian");
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::OracleGuardianChanged { old_guardian: [0u8; 32], new_guardian: guardian, block_height: ctx.block_height }
+        );
+
+        // With no guardian set, nobody can freeze, the admin included
+        let mut unset = create_test_context();
+        unset.signer = unset.state.admin;
+        unset.new_state.circuit_breaker = unset.state.circuit_breaker.freeze(&unset.state.price, unset.block_height);
+        assert!(matches!(validate(&mut unset, &OracleAction::FreezePrice), Err(ZkUsdError::Unauthorized { .. })));
+    }
+
+    #[test]
+    fn test_guardian_freeze_blocks_updates_until_admin_reset() {
+        let mut ctx = create_test_context();
+        let guardian = [9u8; 32];
+        let operator = ctx.state.operator;
+        ctx.state.guardian = guardian;
+        let breaker = ctx.state.circuit_breaker.freeze(&ctx.state.price, ctx.block_height);
+        ctx.new_state = OracleState { circuit_breaker: breaker, ..ctx.state.clone() };
+
+        // Only the guardian may freeze
+        assert_eq!(
+            validate(&mut ctx.clone(), &OracleAction::FreezePrice),
+            Err(ZkUsdError::Unauthorized { expected: guardian, actual: operator })
+        );
+
+        ctx.signer = guardian;
+        validate(&mut ctx, &OracleAction::FreezePrice).expect("guardian should freeze the oracle");
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::OracleFrozen { guardian, reference_price: BTC_PRICE_100K, block_height: ctx.block_height }
+        );
+        let frozen_at = ctx.block_height;
+        ctx.state = ctx.new_state.clone();
+
+        // Updates are rejected, however long the freeze lasts
+        ctx.signer = operator;
+        for _ in 0..2 {
+            assert_eq!(update_on(&mut ctx, 101_000_00000000), Err(ZkUsdError::OracleFrozen { frozen_at }));
+            ctx.block_height += CIRCUIT_BREAKER_COOLDOWN_BLOCKS;
+        }
+        assert!(ctx.state.circuit_breaker.is_active(ctx.block_height));
+
+        // The guardian cannot lift its own freeze; the admin can
+        ctx.new_state = OracleState { circuit_breaker: ctx.state.circuit_breaker.reset(&ctx.state.price), ..ctx.state.clone() };
+        ctx.signer = guardian;
+        assert_eq!(validate(&mut ctx.clone(), &OracleAction::ResetCircuitBreaker), Err(ZkUsdError::AdminOnly));
+        ctx.signer = ctx.state.admin;
+        validate(&mut ctx, &OracleAction::ResetCircuitBreaker).expect("admin should lift the freeze");
+        ctx.state = ctx.new_state.clone();
+
+        ctx.signer = operator;
+        update_on(&mut ctx, 101_000_00000000).expect("updates resume after the reset");
+    }
+
+    #[test]
+    fn test_volatile_day_within_window_accepted() {
+        let mut ctx = create_test_context();
+
+        // +3%, -4%, +2%, -5%: 14% of movement in the window
+        let mut price = BTC_PRICE_100K;
+        for step_bps in [10_300, 9_600, 10_200, 9_500, 10_300] {
+            price = price / 10_000 * step_bps;
+            update_on(&mut ctx, price).expect("volatile update within the window limit");
+            ctx.block_height += DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS;
+        }
+
+        // The oldest deviation slid out of the window on the fifth update
+        assert_eq!(ctx.state.recent_deviations_bps.len(), DEVIATION_WINDOW_UPDATES);
+        assert_eq!(ctx.state.recent_deviations_bps[0], 400);
+    }
+
+    #[test]
+    fn test_deviation_limit_scales_with_elapsed_blocks() {
+        let ten_percent_up = BTC_PRICE_100K / 100 * 110;
+
+        // Fixed policy: a 10% move is too large however long the gap
+        let mut ctx = create_test_context();
+        ctx.block_height = 120;
+        assert!(matches!(update_on(&mut ctx, ten_percent_up), Err(ZkUsdError::OraclePriceDeviation { .. })));
+
+        // Scaled policy: 5% + 0.25% per block since the last update
+        let mut ctx = create_test_context();
+        ctx.state.min_update_interval_blocks = 1;
+        ctx.state.deviation_scaling_bps_per_block = 25;
+
+        ctx.block_height = 101;
+        assert_eq!(
+            update_on(&mut ctx, ten_percent_up),
+            Err(ZkUsdError::OraclePriceDeviation {
+                old_price: BTC_PRICE_100K,
+                new_price: ten_percent_up,
+                max_deviation_bps: 525,
+            })
+        );
+
+        ctx.block_height = 120;
+        assert!(update_on(&mut ctx, ten_percent_up).is_ok());
+    }
+
+    #[test]
+    fn test_scaled_deviation_limit_clamped() {
+        let mut state = OracleState::new([0u8; 32], [1u8; 32], BTC_PRICE_100K, 100);
+        assert_eq!(state.max_deviation_bps(10_000), MAX_PRICE_DEVIATION_BPS);
+
+        state.deviation_scaling_bps_per_block = MAX_DEVIATION_SCALING_BPS_PER_BLOCK;
+        let five_blocks = MAX_PRICE_DEVIATION_BPS + 5 * MAX_DEVIATION_SCALING_BPS_PER_BLOCK;
+        assert_eq!(state.max_deviation_bps(105), five_blocks);
+        assert_eq!(state.max_deviation_bps(10_000), MAX_SCALED_PRICE_DEVIATION_BPS);
+        assert_eq!(state.max_deviation_bps(u64::MAX), MAX_SCALED_PRICE_DEVIATION_BPS);
+    }
+
+    #[test]
+    fn test_set_deviation_scaling() {
+        let mut ctx = create_test_context();
+        let action = OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block: 25 };
+        ctx.new_state.deviation_scaling_bps_per_block = 25;
+
+        // Operator cannot tune the scaling
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = ctx.state.admin;
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        let too_fast = OracleAction::SetDeviationScaling {
+            deviation_scaling_bps_per_block: MAX_DEVIATION_SCALING_BPS_PER_BLOCK + 1,
+        };
+        assert!(matches!(validate(&mut ctx, &too_fast), Err(ZkUsdError::ExceedsMaximum { .. })));
+
+        // Nothing else may change alongside the scaling
+        ctx.new_state.max_cumulative_deviation_bps = 2000;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_price_update_keeps_deviation_scaling() {
+        let mut ctx = create_test_context();
+        let price = BTC_PRICE_100K / 100 * 101;
+        ctx.new_state.price.price = price;
+        ctx.new_state.price.timestamp_block = ctx.block_height;
+        ctx.new_state.price.effective_from_block = ctx.block_height + 1;
+        ctx.new_state.previous_price = ctx.state.price.clone();
+        ctx.new_state.last_valid_price = price;
+        ctx.new_state.recent_deviations_bps = vec![100];
+        ctx.new_state.deviation_scaling_bps_per_block = 25;
+
+        assert_eq!(
+            validate(&mut ctx, &OracleAction::UpdatePrice { price }),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+    }
+
+    #[test]
+    fn test_set_update_limits() {
+        let mut ctx = create_test_context();
+        let action = OracleAction::SetUpdateLimits {
+            min_update_interval_blocks: 3,
+            max_cumulative_deviation_bps: 2000,
+        };
+        ctx.new_state.min_update_interval_blocks = 3;
+        ctx.new_state.max_cumulative_deviation_bps = 2000;
+
+        // Operator cannot tune the limits
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = ctx.state.admin;
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        // Limits are bounded
+        let too_slow = OracleAction::SetUpdateLimits {
+            min_update_interval_blocks: MAX_UPDATE_INTERVAL_BLOCKS + 1,
+            max_cumulative_deviation_bps: 2000,
+        };
+        assert!(matches!(validate(&mut ctx, &too_slow), Err(ZkUsdError::ExceedsMaximum { .. })));
+        let too_tight = OracleAction::SetUpdateLimits {
+            min_update_interval_blocks: 3,
+            max_cumulative_deviation_bps: MAX_PRICE_DEVIATION_BPS - 1,
+        };
+        assert!(matches!(validate(&mut ctx, &too_tight), Err(ZkUsdError::BelowMinimum { .. })));
+
+        // Nothing else may change alongside the limits
+        ctx.new_state.price.price = BTC_PRICE_100K / 100 * 101;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
     #[test]
     fn test_update_price_unauthorized() {
         let mut ctx = create_test_context();
@@ -361,6 +1588,43 @@ mod tests {
         assert!(!is_price_fresh(&state, 110));
     }
 
+    #[test]
+    fn test_effective_confidence_decays_with_age() {
+        let state = OracleState::new([0u8; 32], [1u8; 32], BTC_PRICE_100K, 100);
+
+        // Just updated: full confidence
+        assert_eq!(effective_confidence(&state, 100), 100);
+        // Linear decay over MAX_PRICE_AGE_BLOCKS (6)
+        assert_eq!(effective_confidence(&state, 101), 83);
+        assert_eq!(effective_confidence(&state, 103), 50);
+        assert_eq!(effective_confidence(&state, 105), 16);
+        // Staleness boundary and beyond: no confidence
+        assert_eq!(effective_confidence(&state, 106), 0);
+        assert_eq!(effective_confidence(&state, 200), 0);
+    }
+
+    #[test]
+    fn test_effective_confidence_scales_stored_confidence() {
+        let mut state = OracleState::new([0u8; 32], [1u8; 32], BTC_PRICE_100K, 100);
+        state.price.confidence = 60;
+
+        assert_eq!(effective_confidence(&state, 100), 60);
+        assert_eq!(effective_confidence(&state, 103), 30);
+
+        state.is_active = false;
+        assert_eq!(effective_confidence(&state, 100), 0);
+    }
+
+    #[test]
+    fn test_display_price_reports_decayed_confidence() {
+        let mut state = OracleState::new([0u8; 32], [1u8; 32], BTC_PRICE_100K, 100);
+        state.last_valid_price = BTC_PRICE_100K - 1;
+
+        assert_eq!(get_price_for_display(&state, 100), (BTC_PRICE_100K, false, 100));
+        assert_eq!(get_price_for_display(&state, 103), (BTC_PRICE_100K, false, 50));
+        assert_eq!(get_price_for_display(&state, 110), (BTC_PRICE_100K - 1, true, 0));
+    }
+
     #[test]
     fn test_price_deviation_calculation() {
         // 0% deviation
@@ -401,4 +1665,598 @@ mod tests {
         assert!(!validate_price_format(100_00000000));     // $100 (too low)
         assert!(!validate_price_format(100_000_000_00000000)); // $100M (too high)
     }
+
+    #[test]
+    fn test_set_price_bounds() {
+        let mut ctx = create_test_context();
+        let bounds = PriceBounds { min_price: 1_000_000_000_000, max_price: 100_000_000_000_000 };
+        ctx.new_state = OracleState { price_bounds: bounds, ..ctx.state.clone() };
+        let action = OracleAction::SetPriceBounds { bounds };
+
+        // Operator cannot set the bounds
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = ctx.state.admin;
+        assert!(validate(&mut ctx, &action).is_ok());
+        assert!(ctx.events.events().iter().any(|e| matches!(
+            e,
+            ZkUsdEvent::OraclePriceBoundsChanged { new_min_price: 1_000_000_000_000, .. }
+        )));
+
+        // Inverted, empty and zero-based ranges are rejected
+        for (min_price, max_price) in [(200, 100), (100, 100), (0, 100)] {
+            let bounds = PriceBounds { min_price, max_price };
+            ctx.new_state.price_bounds = bounds;
+            assert!(matches!(
+                validate(&mut ctx, &OracleAction::SetPriceBounds { bounds }),
+                Err(ZkUsdError::InvalidInput { param: "price_bounds", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_update_price_uses_asset_bounds() {
+        // $101,000 is a plausible BTC price
+        let price = BTC_PRICE_100K / 100 * 101;
+        let mut btc = create_test_context();
+        assert!(update_on(&mut btc, price).is_ok());
+
+        // but not for an asset whose oracle tops out at $100,000
+        let mut asset = create_test_context();
+        asset.state.price_bounds = PriceBounds { min_price: 100_000_000_000, max_price: BTC_PRICE_100K };
+        assert!(matches!(
+            update_on(&mut asset, price),
+            Err(ZkUsdError::InvalidInput { param: "price", .. })
+        ));
+
+        // A $2 asset is out of BTC's range, yet fine within its own bounds
+        let mut cheap = create_test_context();
+        cheap.state.price.price = 200_000_000;
+        assert!(update_on(&mut cheap.clone(), 202_000_000).is_err());
+        cheap.state.price_bounds = PriceBounds { min_price: 10_000_000, max_price: 1_000_000_000 };
+        assert!(update_on(&mut cheap, 202_000_000).is_ok());
+
+        // Updates cannot move the bounds
+        btc.block_height += DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS;
+        btc.new_state = btc.state.clone();
+        btc.new_state.price.timestamp_block = btc.block_height;
+        btc.new_state.price.effective_from_block = btc.block_height + 1;
+        btc.new_state.previous_price = btc.state.price.clone();
+        btc.new_state.recent_deviations_bps = btc.state.deviations_after(0);
+        btc.new_state.circuit_breaker = btc.state.circuit_breaker.after_update(&btc.state.price, price, btc.block_height);
+        btc.new_state.price_bounds.max_price = price;
+        assert_eq!(
+            validate(&mut btc, &OracleAction::UpdatePrice { price }),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+    }
+
+    const USDC_USD: FeedId = *b"USDCUSD\0";
+    const EURC_USD: FeedId = *b"EURCUSD\0";
+    const ONE_USD: u64 = 100_000_000;
+    const USDC_OPERATOR: Address = [5u8; 32];
+    const EURC_OPERATOR: Address = [6u8; 32];
+
+    /// Stablecoin feed at $1, published at block 100, allowed 2% per update
+    fn stable_feed(feed_id: FeedId, operator: Address) -> Feed {
+        Feed {
+            feed_id,
+            price: PriceData::published(ONE_USD, 100, PriceSource::Mock),
+            bounds: PriceBounds { min_price: ONE_USD / 2, max_price: ONE_USD * 2 },
+            max_deviation_bps: 200,
+            operator,
+        }
+    }
+
+    /// `create_test_context` with USDC/USD and EURC/USD feeds
+    fn feed_context() -> OracleContext {
+        let mut ctx = create_test_context();
+        ctx.state.feeds = vec![stable_feed(USDC_USD, USDC_OPERATOR), stable_feed(EURC_USD, EURC_OPERATOR)];
+        ctx.new_state = ctx.state.clone();
+        ctx
+    }
+
+    /// Apply an update of feed `feed_id` on top of `ctx.state`, advancing the
+    /// state on success
+    fn feed_update_on(ctx: &mut OracleContext, feed_id: FeedId, price: u64) -> ZkUsdResult<()> {
+        ctx.new_state = ctx.state.clone();
+        if let Some(feed) = ctx.new_state.feeds.iter_mut().find(|feed| feed.feed_id == feed_id) {
+            feed.price = PriceData {
+                price,
+                timestamp_block: ctx.block_height,
+                effective_from_block: ctx.block_height + 1,
+                ..feed.price.clone()
+            };
+        }
+
+        validate(ctx, &OracleAction::UpdateFeed { feed_id, price })?;
+        ctx.state = ctx.new_state.clone();
+        Ok(())
+    }
+
+    #[test]
+    fn test_feeds_update_independently() {
+        let mut ctx = feed_context();
+
+        // Each feed answers to its own operator only
+        ctx.signer = ctx.state.operator;
+        assert!(matches!(feed_update_on(&mut ctx.clone(), USDC_USD, 99_000_000), Err(ZkUsdError::Unauthorized { .. })));
+        ctx.signer = EURC_OPERATOR;
+        assert_eq!(
+            feed_update_on(&mut ctx.clone(), USDC_USD, 99_000_000),
+            Err(ZkUsdError::Unauthorized { expected: USDC_OPERATOR, actual: EURC_OPERATOR })
+        );
+
+        ctx.signer = USDC_OPERATOR;
+        feed_update_on(&mut ctx, USDC_USD, 99_000_000).expect("operator should update its feed");
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::FeedPriceUpdated {
+                feed_id: USDC_USD,
+                old_price: ONE_USD,
+                new_price: 99_000_000,
+                block_height: ctx.block_height,
+            }
+        );
+        // Only that feed moved
+        assert_eq!(ctx.state.feed(&EURC_USD).unwrap().price.price, ONE_USD);
+        assert_eq!(ctx.state.price.price, BTC_PRICE_100K);
+        assert!(ctx.state.recent_deviations_bps.is_empty());
+
+        // The other feed is updated on its own schedule, in the same block
+        ctx.signer = EURC_OPERATOR;
+        feed_update_on(&mut ctx, EURC_USD, 101_000_000).expect("second feed updates independently");
+        ctx.signer = USDC_OPERATOR;
+        assert!(matches!(
+            feed_update_on(&mut ctx.clone(), USDC_USD, ONE_USD),
+            Err(ZkUsdError::OracleUpdateTooSoon { .. })
+        ));
+
+        // A feed update changes nothing but the feed's price
+        ctx.block_height += DEFAULT_MIN_UPDATE_INTERVAL_BLOCKS;
+        feed_update_on(&mut ctx.clone(), USDC_USD, ONE_USD).expect("update after the interval");
+        ctx.new_state.last_valid_price += 1;
+        assert_eq!(
+            validate(&mut ctx, &OracleAction::UpdateFeed { feed_id: USDC_USD, price: ONE_USD }),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+    }
+
+    #[test]
+    fn test_feed_limits_and_staleness() {
+        let mut ctx = feed_context();
+        ctx.signer = USDC_OPERATOR;
+
+        // 3% is within BTC's limit, not within the feed's 2%
+        assert!(matches!(
+            feed_update_on(&mut ctx.clone(), USDC_USD, 103_000_000),
+            Err(ZkUsdError::OraclePriceDeviation { max_deviation_bps: 200, .. })
+        ));
+        assert!(matches!(
+            feed_update_on(&mut ctx.clone(), USDC_USD, ONE_USD * 3),
+            Err(ZkUsdError::InvalidInput { param: "price", .. })
+        ));
+        assert!(matches!(
+            feed_update_on(&mut ctx.clone(), *b"XAUUSD\0\0", ONE_USD),
+            Err(ZkUsdError::InvalidInput { param: "feed_id", .. })
+        ));
+        let mut frozen = ctx.clone();
+        frozen.state.circuit_breaker = frozen.state.circuit_breaker.freeze(&frozen.state.price, 100);
+        assert_eq!(feed_update_on(&mut frozen, USDC_USD, ONE_USD), Err(ZkUsdError::OracleFrozen { frozen_at: 100 }));
+
+        // Each feed goes stale counting from its own last update
+        feed_update_on(&mut ctx, USDC_USD, 101_000_000).expect("update within the feed's limits");
+        let later = ctx.block_height + MAX_PRICE_AGE_BLOCKS;
+        assert_eq!(get_feed_price(&ctx.state, &USDC_USD, later), Ok(101_000_000));
+        assert!(matches!(
+            get_feed_price(&ctx.state, &EURC_USD, later),
+            Err(ZkUsdError::OracleStale { last_update_block: 100, .. })
+        ));
+        assert!(matches!(get_feed_price(&ctx.state, &BTC_USD_FEED, later), Err(ZkUsdError::OracleStale { .. })));
+        assert!(matches!(
+            get_feed_price(&ctx.state, b"XAUUSD\0\0", later),
+            Err(ZkUsdError::InvalidInput { param: "feed_id", .. })
+        ));
+    }
+
+    #[test]
+    fn test_btc_usd_feed_is_the_oracle_price() {
+        let price = 101_000_00000000;
+        let spell = feed_context();
+        let mut legacy = spell.clone();
+        update_on(&mut legacy, price).expect("price update should succeed");
+
+        // Updating the BTC/USD feed is a price update, and leaves the feeds alone
+        let mut feed = OracleContext { new_state: legacy.state.clone(), ..spell.clone() };
+        validate(&mut feed, &OracleAction::UpdateFeed { feed_id: BTC_USD_FEED, price }).expect("BTC/USD feed update");
+        assert_eq!(feed.events.events(), legacy.events.events());
+        let mut tampered = OracleContext { new_state: legacy.state.clone(), ..spell };
+        tampered.new_state.feeds.pop();
+        assert_eq!(validate(&mut tampered, &OracleAction::UpdatePrice { price }), Err(ZkUsdError::InvalidStateTransition));
+
+        // and reading it is reading the price
+        for block in [100, 102, 108, 120] {
+            assert_eq!(get_feed_price(&legacy.state, &BTC_USD_FEED, block), get_price(&legacy.state, block));
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_feed() {
+        let mut ctx = create_test_context();
+        let feed = Feed {
+            price: PriceData::published(ONE_USD, ctx.block_height, PriceSource::Mock),
+            ..stable_feed(USDC_USD, USDC_OPERATOR)
+        };
+        let add = OracleAction::AddFeed { feed: feed.clone() };
+        ctx.new_state = OracleState { feeds: vec![feed.clone()], ..ctx.state.clone() };
+        assert_eq!(validate(&mut ctx.clone(), &add), Err(ZkUsdError::AdminOnly));
+
+        // BTC/USD is the oracle's own price, and every feed needs sane limits
+        ctx.signer = ctx.state.admin;
+        let bad_feeds = [
+            Feed { feed_id: BTC_USD_FEED, ..feed.clone() },
+            Feed { max_deviation_bps: 0, ..feed.clone() },
+            Feed { bounds: PriceBounds { min_price: ONE_USD * 2, max_price: ONE_USD * 3 }, ..feed.clone() },
+            Feed { price: PriceData::published(ONE_USD, 100, PriceSource::Mock), ..feed.clone() },
+        ];
+        for bad in bad_feeds {
+            let new_state = OracleState { feeds: vec![bad.clone()], ..ctx.state.clone() };
+            let mut bad_ctx = OracleContext { new_state, ..ctx.clone() };
+            assert!(validate(&mut bad_ctx, &OracleAction::AddFeed { feed: bad }).is_err());
+        }
+
+        validate(&mut ctx, &add).expect("admin should add the feed");
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::OracleFeedAdded { feed_id: USDC_USD, operator: USDC_OPERATOR, block_height: ctx.block_height }
+        );
+        ctx.state = ctx.new_state.clone();
+        assert!(matches!(validate(&mut ctx.clone(), &add), Err(ZkUsdError::InvalidInput { param: "feed_id", .. })));
+
+        // At most MAX_FEEDS
+        let eurc = Feed { feed_id: EURC_USD, ..feed };
+        let mut full = ctx.clone();
+        full.state.feeds = (0..MAX_FEEDS as u8).map(|i| stable_feed([i; 8], USDC_OPERATOR)).collect();
+        full.new_state = full.state.clone();
+        full.new_state.feeds.push(eurc.clone());
+        assert!(matches!(
+            validate(&mut full, &OracleAction::AddFeed { feed: eurc }),
+            Err(ZkUsdError::ExceedsMaximum { .. })
+        ));
+
+        // Removal is the admin's too, of a feed the oracle carries
+        let remove = OracleAction::RemoveFeed { feed_id: USDC_USD };
+        ctx.new_state = OracleState { feeds: Vec::new(), ..ctx.state.clone() };
+        let mut by_operator = OracleContext { signer: USDC_OPERATOR, ..ctx.clone() };
+        assert_eq!(validate(&mut by_operator, &remove), Err(ZkUsdError::AdminOnly));
+        ctx.events = EventLog::new();
+        validate(&mut ctx, &remove).expect("admin should remove the feed");
+        assert_eq!(
+            ctx.events.events()[0],
+            ZkUsdEvent::OracleFeedRemoved { feed_id: USDC_USD, block_height: ctx.block_height }
+        );
+        ctx.state = ctx.new_state.clone();
+        assert!(matches!(
+            validate(&mut ctx, &OracleAction::RemoveFeed { feed_id: BTC_USD_FEED }),
+            Err(ZkUsdError::InvalidInput { param: "feed_id", .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_attestation_sources() {
+        let mut ctx = create_test_context();
+        let sources = vec![[7u8; 32], [8u8; 32]];
+        ctx.new_state = OracleState { attestation_sources: sources.clone(), ..ctx.state.clone() };
+        let action = OracleAction::SetAttestationSources { sources: sources.clone() };
+
+        // Operator cannot register feeds
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::AdminOnly));
+
+        ctx.signer = ctx.state.admin;
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        // A feed listed twice would count twice toward the quorum
+        let repeated = OracleAction::SetAttestationSources { sources: vec![[7u8; 32], [7u8; 32]] };
+        ctx.new_state.attestation_sources = vec![[7u8; 32], [7u8; 32]];
+        assert!(matches!(validate(&mut ctx, &repeated), Err(ZkUsdError::InvalidInput { .. })));
+
+        let too_many: Vec<[u8; 32]> = (0..=MAX_ATTESTATION_SOURCES as u8).map(|i| [i; 32]).collect();
+        ctx.new_state.attestation_sources = too_many.clone();
+        assert!(matches!(
+            validate(&mut ctx, &OracleAction::SetAttestationSources { sources: too_many }),
+            Err(ZkUsdError::ExceedsMaximum { .. })
+        ));
+
+        // Operator price updates leave the sources alone
+        let mut ctx = create_test_context();
+        ctx.new_state = ctx.state.clone();
+        ctx.new_state.attestation_sources = sources;
+        let price = BTC_PRICE_100K + BTC_PRICE_100K / 100;
+        ctx.new_state.price.price = price;
+        ctx.new_state.price.timestamp_block = ctx.block_height;
+        ctx.new_state.price.effective_from_block = ctx.block_height + 1;
+        ctx.new_state.previous_price = ctx.state.price.clone();
+        ctx.new_state.last_valid_price = price;
+        ctx.new_state.recent_deviations_bps = ctx.state.deviations_after(100);
+        ctx.new_state.circuit_breaker = ctx.state.circuit_breaker.after_update(&ctx.state.price, price, ctx.block_height);
+        assert_eq!(validate(&mut ctx, &OracleAction::UpdatePrice { price }), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_median_attested_price() {
+        let at = |price| PriceAttestation { source_pubkey: [0; 32], price, observed_at_block: 0, signature: [0; 64] };
+        assert_eq!(median_attested_price(&[]), None);
+        assert_eq!(median_attested_price(&[at(5), at(1), at(3)]), Some(3));
+        assert_eq!(median_attested_price(&[at(4), at(1), at(3), at(2)]), Some(2));
+        assert_eq!(attestation_quorum(0), 1);
+        assert_eq!(attestation_quorum(3), 2);
+        assert_eq!(attestation_quorum(4), 3);
+    }
+
+    #[cfg(feature = "crypto")]
+    mod attestations {
+        use super::*;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        fn feed(seed: u8) -> SigningKey {
+            SigningKey::from_bytes(&[seed; 32])
+        }
+
+        fn attest(key: &SigningKey, price: u64, observed_at_block: u64) -> PriceAttestation {
+            let mut attestation = PriceAttestation {
+                source_pubkey: key.verifying_key().to_bytes(),
+                price,
+                observed_at_block,
+                signature: [0; 64],
+            };
+            attestation.signature = key.sign(&attestation.message()).to_bytes();
+            attestation
+        }
+
+        /// Oracle with feeds 1-3 registered, submitted by a third party
+        fn attested_context() -> OracleContext {
+            let mut ctx = create_test_context();
+            ctx.state.attestation_sources = (1..=3).map(|seed| feed(seed).verifying_key().to_bytes()).collect();
+            ctx.signer = [9u8; 32];
+            ctx
+        }
+
+        /// Validate an aggregate update with the state it should produce
+        fn aggregate_on(ctx: &mut OracleContext, attestations: Vec<PriceAttestation>) -> ZkUsdResult<()> {
+            let price = median_attested_price(&attestations).unwrap_or_default();
+            let deviation = calculate_price_deviation(ctx.state.price.price, price);
+            ctx.new_state = OracleState {
+                price: PriceData::published(price, ctx.block_height, PriceSource::Aggregated),
+                previous_price: ctx.state.price.clone(),
+                last_valid_price: price,
+                recent_deviations_bps: ctx.state.deviations_after(deviation),
+                circuit_breaker: ctx.state.circuit_breaker.after_update(&ctx.state.price, price, ctx.block_height),
+                ..ctx.state.clone()
+            };
+            validate(ctx, &OracleAction::AggregateUpdate { attestations })
+        }
+
+        #[test]
+        fn test_aggregate_update_publishes_median() {
+            let mut ctx = attested_context();
+            let block = ctx.block_height;
+            let attestations = vec![
+                attest(&feed(1), BTC_PRICE_100K + BTC_PRICE_100K / 100, block),
+                attest(&feed(2), BTC_PRICE_100K - BTC_PRICE_100K / 100, block - 1),
+                attest(&feed(3), BTC_PRICE_100K + BTC_PRICE_100K / 200, block),
+            ];
+
+            assert!(verify_attestation(&attestations[0]).is_ok());
+            assert!(aggregate_on(&mut ctx, attestations).is_ok());
+            assert_eq!(ctx.new_state.price.price, BTC_PRICE_100K + BTC_PRICE_100K / 200);
+            assert_eq!(ctx.new_state.price.source, PriceSource::Aggregated);
+            assert!(ctx.events.events().iter().any(|e| matches!(e, ZkUsdEvent::PriceUpdated { .. })));
+
+            // Submitter cannot slip in other changes
+            let mut ctx = attested_context();
+            let attestations = vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(2), BTC_PRICE_100K, block)];
+            let price = median_attested_price(&attestations).unwrap();
+            ctx.new_state = OracleState {
+                price: PriceData::new(price, block, PriceSource::Aggregated),
+                recent_deviations_bps: ctx.state.deviations_after(0),
+                operator: ctx.signer,
+                ..ctx.state.clone()
+            };
+            assert_eq!(
+                validate(&mut ctx, &OracleAction::AggregateUpdate { attestations }),
+                Err(ZkUsdError::InvalidStateTransition)
+            );
+        }
+
+        #[test]
+        fn test_aggregate_update_rejects_tampered_signature() {
+            let mut ctx = attested_context();
+            let block = ctx.block_height;
+            let mut flipped = attest(&feed(2), BTC_PRICE_100K, block);
+            flipped.signature[10] ^= 1;
+            let rejected = Err(ZkUsdError::InvalidAttestationSignature { source_id: flipped.source_pubkey });
+            assert_eq!(verify_attestation(&flipped), rejected);
+            assert_eq!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), flipped]),
+                rejected
+            );
+
+            // A price changed after signing no longer matches the signature
+            let mut repriced = attest(&feed(2), BTC_PRICE_100K, block);
+            repriced.price = BTC_PRICE_100K / 100 * 104;
+            assert_eq!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K / 100 * 104, block), repriced]),
+                rejected
+            );
+        }
+
+        #[test]
+        fn test_aggregate_update_rejects_wrong_pubkey() {
+            let mut ctx = attested_context();
+            let block = ctx.block_height;
+
+            // Signed by an unregistered feed
+            assert_eq!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(4), BTC_PRICE_100K, block)]),
+                Err(ZkUsdError::InvalidOracleSource)
+            );
+
+            // Signed by another key while claiming a registered feed
+            let rejected = Err(ZkUsdError::InvalidAttestationSignature { source_id: feed(2).verifying_key().to_bytes() });
+            let mut forged = attest(&feed(4), BTC_PRICE_100K, block);
+            forged.source_pubkey = feed(2).verifying_key().to_bytes();
+            assert_eq!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), forged]),
+                rejected
+            );
+        }
+
+        #[test]
+        fn test_aggregate_update_requires_fresh_quorum() {
+            let mut ctx = attested_context();
+            let block = ctx.block_height;
+
+            // One of three feeds is not a majority
+            assert!(matches!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block)]),
+                Err(ZkUsdError::BelowMinimum { amount: 1, minimum: 2 })
+            ));
+
+            // The same feed twice is still one feed
+            assert!(matches!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(1), BTC_PRICE_100K, block)]),
+                Err(ZkUsdError::InvalidInput { .. })
+            ));
+
+            // Observations from before the current price would be replays
+            let last_update = ctx.state.price.timestamp_block;
+            assert!(matches!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(2), BTC_PRICE_100K, last_update)]),
+                Err(ZkUsdError::OracleStale { .. })
+            ));
+
+            // Observations from the future are rejected too
+            assert!(matches!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(2), BTC_PRICE_100K, block + 1)]),
+                Err(ZkUsdError::OracleStale { .. })
+            ));
+
+            // And ones older than the staleness limit
+            ctx.block_height = last_update + MAX_PRICE_AGE_BLOCKS + 2;
+            let block = ctx.block_height;
+            assert!(matches!(
+                aggregate_on(&mut ctx, vec![attest(&feed(1), BTC_PRICE_100K, block), attest(&feed(2), BTC_PRICE_100K, last_update + 1)]),
+                Err(ZkUsdError::OracleStale { .. })
+            ));
+        }
+    }
+
+    fn hex(bytes: &[u8; 32]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_state_commitment_golden() {
+        // Consensus-relevant: changing this requires a COMMITMENT_VERSION bump
+        let ctx = create_test_context();
+        assert_eq!(
+            hex(&ctx.state.commitment()),
+            "c4e66e765fa4e076d5cbf3dddc65e24f23bb4d3c5991dc6389bf7cab1e013035"
+        );
+    }
+
+    // ============ Conformance Vector Tests ============
+
+    /// Oracle state a conformance vector describes
+    fn vector_state(v: &zkusd_common::conformance::VectorContext) -> OracleState {
+        OracleState {
+            guardian: v.guardian,
+            min_update_interval_blocks: v.min_update_interval_blocks,
+            max_cumulative_deviation_bps: v.max_cumulative_deviation_bps,
+            recent_deviations_bps: v.recent_deviations_bps.clone(),
+            deviation_scaling_bps_per_block: v.deviation_scaling_bps_per_block,
+            circuit_breaker: v.circuit_breaker,
+            attestation_sources: v.attestation_sources.clone(),
+            price_bounds: v.price_bounds,
+            feeds: v.feeds.clone(),
+            ..OracleState::new(v.admin, v.operator, v.btc_price, v.price_block)
+        }
+    }
+
+    /// State a valid spell carries for `action` on top of `state`
+    fn vector_new_state(state: &OracleState, action: &OracleAction, block_height: u64) -> OracleState {
+        let mut new_state = state.clone();
+        match action {
+            OracleAction::UpdatePrice { price } | OracleAction::UpdateFeed { feed_id: BTC_USD_FEED, price } => {
+                let deviation = calculate_price_deviation(state.price.price, *price);
+                new_state.price.price = *price;
+                new_state.price.timestamp_block = block_height;
+                new_state.price.effective_from_block = block_height + 1;
+                new_state.previous_price = state.price.clone();
+                new_state.last_valid_price = *price;
+                new_state.recent_deviations_bps = state.deviations_after(deviation);
+                new_state.circuit_breaker = state.circuit_breaker.after_update(&state.price, *price, block_height);
+            }
+            OracleAction::UpdateFeed { feed_id, price } => {
+                if let Some(feed) = new_state.feeds.iter_mut().find(|feed| feed.feed_id == *feed_id) {
+                    feed.price = PriceData {
+                        price: *price,
+                        timestamp_block: block_height,
+                        effective_from_block: block_height + 1,
+                        ..feed.price.clone()
+                    };
+                }
+            }
+            OracleAction::SetOperator { operator } => new_state.operator = *operator,
+            OracleAction::SetUpdateLimits { min_update_interval_blocks, max_cumulative_deviation_bps } => {
+                new_state.min_update_interval_blocks = *min_update_interval_blocks;
+                new_state.max_cumulative_deviation_bps = *max_cumulative_deviation_bps;
+            }
+            OracleAction::SetDeviationScaling { deviation_scaling_bps_per_block } => {
+                new_state.deviation_scaling_bps_per_block = *deviation_scaling_bps_per_block;
+            }
+            OracleAction::ResetCircuitBreaker => new_state.circuit_breaker = state.circuit_breaker.reset(&state.price),
+            OracleAction::FreezePrice => {
+                new_state.circuit_breaker = state.circuit_breaker.freeze(&state.price, block_height);
+            }
+            OracleAction::SetGuardian { guardian } => new_state.guardian = *guardian,
+            OracleAction::SetAttestationSources { sources } => new_state.attestation_sources = sources.clone(),
+            OracleAction::SetPriceBounds { bounds } => new_state.price_bounds = *bounds,
+            OracleAction::AddFeed { feed } => new_state.feeds.push(feed.clone()),
+            OracleAction::RemoveFeed { feed_id } => new_state.feeds.retain(|feed| feed.feed_id != *feed_id),
+            OracleAction::Initialize { .. } | OracleAction::AggregateUpdate { .. } => {}
+        }
+        new_state
+    }
+
+    #[test]
+    fn test_conformance_vectors() {
+        use zkusd_common::conformance::{decode_vector, vectors_for, ConformanceContract};
+
+        for vector in vectors_for(ConformanceContract::PriceOracle) {
+            let (action, v) = decode_vector::<OracleAction>(&vector).unwrap();
+            let state = match action {
+                // The created state is the spell's only output
+                OracleAction::Initialize { admin, operator, initial_price } => {
+                    OracleState::new(admin, operator, initial_price, v.block_height)
+                }
+                _ => vector_state(&v),
+            };
+            let mut ctx = OracleContext {
+                new_state: vector_new_state(&state, &action, v.block_height),
+                state,
+                signer: v.signer,
+                block_height: v.block_height,
+                events: EventLog::new(),
+            };
+            let result = validate(&mut ctx, &action);
+            assert!(
+                vector.expected.matches(&result),
+                "vector {} expected {:?}, got {:?}",
+                vector.name,
+                vector.expected,
+                result
+            );
+        }
+    }
 }
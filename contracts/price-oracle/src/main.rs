@@ -27,7 +27,7 @@
 //! - Only the operator can update (spend + recreate) the oracle
 //! - Price freshness is checked via block height
 
-use charms_sdk::data::{App, Data, Transaction};
+use zkusd_charms_compat::{App, Data, Transaction};
 
 /// Main validation function for Price Oracle operations.
 ///
@@ -49,4 +49,4 @@ pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
 }
 
 // Use the Charms SDK main macro to generate the entry point
-charms_sdk::main!(app_contract);
+zkusd_charms_compat::main!(app_contract);
@@ -20,11 +20,28 @@
 //!   IN:  [Deposit charm (user), StabilityPool state (ref)]
 //!   OUT: [BTC output (gains), Deposit charm (updated snapshot)]
 //!
+//! ClaimBtcToVault (with the VaultManager's AddCollateral):
+//!   IN:  [Deposit charm (user), Vault charm (user), StabilityPool state (ref)]
+//!   OUT: [Vault charm (collateral + gains), Deposit charm (updated snapshot)]
+//!
+//! SweepDustDeposit (anyone for a deposit worn down to dust, the depositor
+//! if it still has BTC gains):
+//!   IN:  [Deposit charm (dust), StabilityPool state]
+//!   OUT: [BTC output (gains to depositor), StabilityPool state (one depositor fewer, dust to the protocol)]
+//!
+//! RegisterFrontend (admin):
+//!   IN:  [StabilityPool config, StabilityPool state (ref)]
+//!   OUT: [StabilityPool config (frontend appended), StabilityPool state (unchanged)]
+//!
 //! Offset (called by VaultManager during liquidation):
 //!   IN:  [StabilityPool state, BTC from liquidated vault]
 //!   OUT: [StabilityPool state (updated P/S/total)]
 //! ```
 //!
+//! A deposit tagged with a frontend has the frontend's share of its BTC
+//! gains paid to a BTC output locked to the payout script the frontend
+//! registered.
+//!
 //! ## Key Insight: Deposits as Individual Charms
 //!
 //! Unlike smart contracts where deposits are entries in a mapping, here each
@@ -36,11 +53,17 @@
 //! - No indexer needed to track balances
 //! - Deposits are spent and recreated atomically in transactions
 
-use charms_data::{App, Data, Transaction, B32};
-use crate::{StabilityPoolConfig, StabilityPoolContext, validate};
+use zkusd_charms_compat::{coin_outputs_to, extract_coin_flows, App, Charms, Data, Transaction, UtxoId};
+use crate::{RegisteredFrontend, StabilityPoolConfig, StabilityPoolContext, validate};
 use zkusd_common::{
+    address::is_zero,
+    commitment::StateRef,
+    errors::{CompanionRole, ZkUsdError, ZkUsdResult},
     events::EventLog,
-    types::{Address, StabilityDeposit, StabilityPoolAction, StabilityPoolState},
+    types::{Address, EpochSnapshot, OffsetSample, StabilityDeposit, StabilityPoolAction, StabilityPoolState},
+    units::{Sats, ZkUsd},
+    validation::require_companion,
+    versioning::{initial_state_version, VersionedState},
 };
 
 // ============ Operation Codes ============
@@ -57,12 +80,19 @@ pub mod op {
     pub const CLAIM_BTC: u8 = 0x22;
     /// Offset debt during liquidation (VaultManager only)
     pub const OFFSET: u8 = 0x23;
+    /// Claim BTC rewards as collateral of the depositor's vault
+    pub const CLAIM_BTC_TO_VAULT: u8 = 0x24;
+    /// Close a dust deposit, paying out its BTC gain (anyone)
+    pub const SWEEP_DUST_DEPOSIT: u8 = 0x25;
+    /// Register a frontend deposits may be tagged with (admin only)
+    pub const REGISTER_FRONTEND: u8 = 0x26;
 }
 
 // ============ Witness Structures ============
 
 /// Witness data for Initialize operation
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct InitWitness {
     pub op: u8,
     /// zkUSD Token app_id
@@ -75,6 +105,7 @@ pub struct InitWitness {
 
 /// Witness data for stability pool operations
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct StabilityWitness {
     /// Operation type (see `op` module)
     pub op: u8,
@@ -84,6 +115,25 @@ pub struct StabilityWitness {
     pub debt: Option<u64>,
     /// Collateral amount for offset operations
     pub collateral: Option<u64>,
+    /// Vault receiving claimed BTC (ClaimBtcToVault)
+    #[serde(default)]
+    pub vault_id: Option<[u8; 32]>,
+    /// Owner of the deposit being swept (SweepDustDeposit)
+    #[serde(default)]
+    pub depositor: Option<[u8; 32]>,
+    /// Frontend being registered (RegisterFrontend)
+    #[serde(default)]
+    pub frontend: Option<[u8; 32]>,
+    /// Kickback rate of the frontend being registered (RegisterFrontend)
+    #[serde(default)]
+    pub kickback_bps: Option<u64>,
+    /// scriptPubKey the frontend being registered is paid to
+    /// (RegisterFrontend)
+    #[serde(default)]
+    pub payout_script: Option<Vec<u8>>,
+    /// Pool state the spell was built against (see `StateRef`)
+    #[serde(default)]
+    pub based_on: Option<StateRef>,
 }
 
 impl StabilityWitness {
@@ -94,6 +144,12 @@ impl StabilityWitness {
             amount: Some(amount),
             debt: None,
             collateral: None,
+            vault_id: None,
+            depositor: None,
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
         }
     }
 
@@ -104,6 +160,12 @@ impl StabilityWitness {
             amount: Some(amount),
             debt: None,
             collateral: None,
+            vault_id: None,
+            depositor: None,
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
         }
     }
 
@@ -114,6 +176,12 @@ impl StabilityWitness {
             amount: None,
             debt: None,
             collateral: None,
+            vault_id: None,
+            depositor: None,
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
         }
     }
 
@@ -124,6 +192,75 @@ impl StabilityWitness {
             amount: None,
             debt: Some(debt),
             collateral: Some(collateral),
+            vault_id: None,
+            depositor: None,
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
+        }
+    }
+
+    /// Create witness for claiming BTC rewards into a vault
+    pub fn claim_btc_to_vault(vault_id: [u8; 32]) -> Self {
+        Self {
+            op: op::CLAIM_BTC_TO_VAULT,
+            amount: None,
+            debt: None,
+            collateral: None,
+            vault_id: Some(vault_id),
+            depositor: None,
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
+        }
+    }
+
+    /// Create witness for sweeping a dust deposit
+    pub fn sweep_dust_deposit(depositor: [u8; 32]) -> Self {
+        Self {
+            op: op::SWEEP_DUST_DEPOSIT,
+            amount: None,
+            debt: None,
+            collateral: None,
+            vault_id: None,
+            depositor: Some(depositor),
+            frontend: None,
+            kickback_bps: None,
+            payout_script: None,
+            based_on: None,
+        }
+    }
+
+    /// Create witness for registering a frontend
+    pub fn register_frontend(frontend: [u8; 32], kickback_bps: u64, payout_script: Vec<u8>) -> Self {
+        Self {
+            op: op::REGISTER_FRONTEND,
+            amount: None,
+            debt: None,
+            collateral: None,
+            vault_id: None,
+            depositor: None,
+            frontend: Some(frontend),
+            kickback_bps: Some(kickback_bps),
+            payout_script: Some(payout_script),
+            based_on: None,
+        }
+    }
+
+    /// Witness carrying `action`, as the entry point parses it back
+    pub fn for_action(action: &StabilityPoolAction) -> Self {
+        match action {
+            StabilityPoolAction::Deposit { amount } => Self::deposit(amount.into_inner()),
+            StabilityPoolAction::Withdraw { amount } => Self::withdraw(amount.into_inner()),
+            StabilityPoolAction::ClaimBtc => Self::claim_btc(),
+            StabilityPoolAction::Offset { debt, collateral } => Self::offset(debt.into_inner(), collateral.into_inner()),
+            StabilityPoolAction::ClaimBtcToVault { vault_id } => Self::claim_btc_to_vault(*vault_id),
+            StabilityPoolAction::SweepDustDeposit { depositor } => Self::sweep_dust_deposit(*depositor),
+            StabilityPoolAction::RegisterFrontend { frontend, kickback_bps, payout_script } => {
+                Self::register_frontend(*frontend, *kickback_bps, payout_script.clone())
+            }
         }
     }
 }
@@ -139,26 +276,43 @@ impl StabilityWitness {
 /// ## Operations
 ///
 /// - **Initialize**: Creates initial pool state (no input state required)
-/// - **Deposit/Withdraw/ClaimBtc/Offset**: Requires existing pool state
+/// - **Deposit/Withdraw/ClaimBtc/Offset/ClaimBtcToVault/SweepDustDeposit/
+///   RegisterFrontend**: Requires existing pool state
 ///
 /// # Cross-App Interactions
 ///
-/// - **VaultManager**: Can call offset during liquidations
+/// - **VaultManager**: Can call offset during liquidations, and must be
+///   present to receive BTC claimed into a vault
 /// - **zkUSD Token**: Deposits/withdrawals involve token transfers
 ///
 /// # Arguments
 /// * `app` - The StabilityPool app definition
 /// * `tx` - The transaction being validated
-/// * `_x` - Public inputs (unused for stability pool)
+/// * `x` - Public inputs (unused for stability pool)
 /// * `w` - Witness data (operation details)
 ///
 /// # Returns
 /// `true` if the operation is valid, `false` otherwise
 pub fn validate_stability_operation(
+    app: &App,
+    tx: &Transaction,
+    x: &Data,
+    w: &Data,
+) -> bool {
+    validate_stability_operation_at(app, tx, x, w, 0)
+}
+
+/// `validate_stability_operation` at a known block height
+///
+/// The Charms runtime does not expose the block height yet, so the entry
+/// point validates at block 0; hosts that know the height (tests, indexers)
+/// validate at it here.
+pub fn validate_stability_operation_at(
     app: &App,
     tx: &Transaction,
     _x: &Data,
     w: &Data,
+    block_height: u64,
 ) -> bool {
     // Check if this is an Initialize operation
     if let Some(init) = parse_init_witness(w) {
@@ -182,10 +336,20 @@ pub fn validate_stability_operation(
         None => return false,
     };
 
-    // 3. Extract pool config from app identity
-    let config = match extract_config(app, tx) {
-        Some(c) => c,
-        None => return false,
+    // 3. Extract pool config carried by this app: referenced, or spent
+    //    and re-created by the admin actions that change it
+    let changes_config = matches!(action, StabilityPoolAction::RegisterFrontend { .. });
+    let config = match extract_config(app, if changes_config { &tx.ins } else { &tx.refs }) {
+        Ok(c) => c,
+        Err(_) => return false,
+    };
+    let new_config = if changes_config {
+        match extract_output_config(app, tx) {
+            Some(c) => Some(c),
+            None => return false,
+        }
+    } else {
+        None
     };
 
     // 4. Extract pool states from transaction
@@ -200,11 +364,20 @@ pub fn validate_stability_operation(
     // 6. Calculate zkUSD flows
     let (zkusd_inputs, zkusd_outputs) = calculate_zkusd_flows(tx, &config.zkusd_token_id);
 
-    // 7. Calculate BTC flows
-    let (btc_inputs, btc_outputs) = calculate_btc_flows(tx);
-
-    // 8. Check if called by another app (for offset authorization)
-    let caller_app_id = extract_caller_app(tx, app);
+    // 7. Calculate BTC flows, and the share paid to the payout script of
+    //    the deposit's frontend
+    let coins = extract_coin_flows(tx);
+    let frontend_btc_outputs = deposit
+        .as_ref()
+        .and_then(|deposit| deposit.frontend_tag)
+        .and_then(|frontend| config.payout_script_of(&frontend))
+        .map_or(0, |script| coin_outputs_to(tx, script));
+
+    // 8. Resolve the VaultManager caller (for offset authorization)
+    let caller_app_id = match extract_caller_app(tx, &config.vault_manager_id) {
+        Ok(caller) => caller,
+        Err(_) => return false,
+    };
 
     // 9. Get signer from transaction
     let signer = extract_signer(tx);
@@ -214,15 +387,18 @@ pub fn validate_stability_operation(
         state,
         new_state,
         config,
+        new_config,
         deposit,
         new_deposit,
-        zkusd_inputs,
-        zkusd_outputs,
-        btc_inputs,
-        btc_outputs,
+        zkusd_inputs: ZkUsd(zkusd_inputs),
+        zkusd_outputs: ZkUsd(zkusd_outputs),
+        btc_inputs: Sats(coins.btc_in),
+        btc_outputs: Sats(coins.btc_out),
+        frontend_btc_outputs: Sats(frontend_btc_outputs),
         caller_app_id,
         signer,
-        block_height: 0, // Would be extracted from tx metadata
+        block_height,
+        based_on: witness.based_on,
         events: EventLog::new(),
     };
 
@@ -263,9 +439,9 @@ fn validate_initialize(
 ) -> bool {
     // If witness has placeholder values (from string pattern), only validate output state
     // Otherwise verify config matches initialization parameters
-    let is_placeholder_witness = init.zkusd_token_id == [0u8; 32]
-        && init.vault_manager_id == [0u8; 32]
-        && init.admin == [0u8; 32];
+    let is_placeholder_witness = is_zero(&init.zkusd_token_id)
+        && is_zero(&init.vault_manager_id)
+        && is_zero(&init.admin);
 
     if !is_placeholder_witness {
         // Full validation with witness parameters
@@ -281,17 +457,24 @@ fn validate_initialize(
     }
     // For placeholder witness, just validate output config is not all zeros
     else {
-        if output_config.zkusd_token_id == [0u8; 32] {
+        if is_zero(&output_config.zkusd_token_id) {
             return false;
         }
-        if output_config.vault_manager_id == [0u8; 32] {
+        if is_zero(&output_config.vault_manager_id) {
             return false;
         }
-        if output_config.admin == [0u8; 32] {
+        if is_zero(&output_config.admin) {
             return false;
         }
     }
+    // Frontends are only registered through RegisterFrontend
+    if !output_config.frontends.is_empty() {
+        return false;
+    }
     // Verify initial state is valid
+    if !output_state.is_current() {
+        return false;
+    }
     if output_state.total_zkusd != 0 {
         return false;
     }
@@ -314,9 +497,15 @@ fn validate_initialize(
     if output_state.depositor_count != 0 {
         return false;
     }
+    if !output_state.epoch_snapshots.is_empty() {
+        return false;
+    }
+    if !output_state.recent_offsets.is_empty() {
+        return false;
+    }
     // Admin validation: for non-placeholder witnesses, admin cannot be zero
     // For placeholder witnesses, admin is always [0;32] so we skip this check
-    if !is_placeholder_witness && init.admin == [0u8; 32] {
+    if !is_placeholder_witness && is_zero(&init.admin) {
         return false;
     }
     true
@@ -341,8 +530,10 @@ fn extract_output_config_and_state(
                     zkusd_token_id: flat.zkusd_token_id,
                     vault_manager_id: flat.vault_manager_id,
                     admin: flat.admin,
+                    frontends: flat.frontends,
                 };
                 let state = StabilityPoolState {
+                    version: flat.version,
                     total_zkusd: flat.total_zkusd,
                     total_btc: flat.total_btc,
                     product_p: flat.product_p,
@@ -350,6 +541,9 @@ fn extract_output_config_and_state(
                     current_epoch: flat.current_epoch,
                     current_scale: flat.current_scale,
                     depositor_count: flat.depositor_count,
+                    epoch_snapshots: flat.epoch_snapshots,
+                    recent_offsets: flat.recent_offsets,
+                    protocol_dust: flat.protocol_dust,
                 };
                 return Some((config, state));
             }
@@ -372,7 +566,11 @@ pub struct StabilityPoolFlat {
     pub zkusd_token_id: [u8; 32],
     pub vault_manager_id: [u8; 32],
     pub admin: [u8; 32],
+    #[serde(default)]
+    pub frontends: Vec<RegisteredFrontend>,
     // State fields
+    #[serde(default = "initial_state_version")]
+    pub version: u8,
     pub total_zkusd: u64,
     pub total_btc: u64,
     pub product_p: u128,
@@ -380,6 +578,12 @@ pub struct StabilityPoolFlat {
     pub current_epoch: u64,
     pub current_scale: u64,
     pub depositor_count: u64,
+    #[serde(default)]
+    pub epoch_snapshots: Vec<EpochSnapshot>,
+    #[serde(default)]
+    pub recent_offsets: Vec<OffsetSample>,
+    #[serde(default)]
+    pub protocol_dust: u64,
 }
 
 /// Parse witness data into StabilityWitness
@@ -391,15 +595,26 @@ fn parse_witness(w: &Data) -> Option<StabilityWitness> {
 fn witness_to_action(w: &StabilityWitness) -> Option<StabilityPoolAction> {
     match w.op {
         op::DEPOSIT => Some(StabilityPoolAction::Deposit {
-            amount: w.amount?,
+            amount: ZkUsd(w.amount?),
         }),
         op::WITHDRAW => Some(StabilityPoolAction::Withdraw {
-            amount: w.amount?,
+            amount: ZkUsd(w.amount?),
         }),
         op::CLAIM_BTC => Some(StabilityPoolAction::ClaimBtc),
         op::OFFSET => Some(StabilityPoolAction::Offset {
-            debt: w.debt?,
-            collateral: w.collateral?,
+            debt: ZkUsd(w.debt?),
+            collateral: Sats(w.collateral?),
+        }),
+        op::CLAIM_BTC_TO_VAULT => Some(StabilityPoolAction::ClaimBtcToVault {
+            vault_id: w.vault_id?,
+        }),
+        op::SWEEP_DUST_DEPOSIT => Some(StabilityPoolAction::SweepDustDeposit {
+            depositor: w.depositor?,
+        }),
+        op::REGISTER_FRONTEND => Some(StabilityPoolAction::RegisterFrontend {
+            frontend: w.frontend?,
+            kickback_bps: w.kickback_bps?,
+            payout_script: w.payout_script.clone()?,
         }),
         _ => None,
     }
@@ -407,20 +622,28 @@ fn witness_to_action(w: &StabilityWitness) -> Option<StabilityPoolAction> {
 
 // ============ State Extraction ============
 
-/// Extract pool configuration from app or transaction
-fn extract_config(_app: &App, tx: &Transaction) -> Option<StabilityPoolConfig> {
-    // Config could be stored in app identity or in a reference input
-    // For now, look for it in reference inputs
-    for (_, charms) in tx.refs.iter() {
-        for (_, data) in charms.iter() {
-            if let Ok(config) = data.value::<StabilityPoolConfig>() {
-                return Some(config);
-            }
-        }
-    }
+/// Extract pool configuration from `utxos`, the reference inputs or, for
+/// actions that change the config, the spent inputs
+///
+/// Only a config carried by this pool's own app is trusted: a lookalike
+/// config charm from another app (e.g. pointing at a counterfeit token)
+/// fails the spell, as do two differing configs.
+fn extract_config(app: &App, utxos: &[(UtxoId, Charms)]) -> ZkUsdResult<StabilityPoolConfig> {
+    let claimants = utxos.iter()
+        .flat_map(|(_, charms)| charms.iter())
+        .filter_map(|(charm_app, data)| {
+            data.value::<StabilityPoolConfig>()
+                .ok()
+                .map(|config| (charm_app.identity.0, (charm_app.vk.0, config)))
+        });
 
-    // Fallback: derive from app identity (would be encoded in app setup)
-    None
+    let (_, config) = require_companion(CompanionRole::StabilityPool, app.identity.0, claimants)?;
+    Ok(config)
+}
+
+/// Extract the pool configuration re-created by the transaction
+fn extract_output_config(app: &App, tx: &Transaction) -> Option<StabilityPoolConfig> {
+    tx.outs.iter().find_map(|charms| charms.get(app).and_then(|data| data.value::<StabilityPoolConfig>().ok()))
 }
 
 /// Extract pool states from transaction inputs and outputs
@@ -433,7 +656,7 @@ fn extract_pool_states(
         .chain(tx.ins.iter())
         .find_map(|(_, charms)| {
             charms.get(app).and_then(|data| {
-                data.value::<StabilityPoolState>().ok()
+                data.value::<StabilityPoolState>().ok().and_then(|s| s.upgrade().ok())
             })
         })?;
 
@@ -441,7 +664,7 @@ fn extract_pool_states(
     let output_state = tx.outs.iter()
         .find_map(|charms| {
             charms.get(app).and_then(|data| {
-                data.value::<StabilityPoolState>().ok()
+                data.value::<StabilityPoolState>().ok().filter(VersionedState::is_current)
             })
         })?;
 
@@ -473,16 +696,20 @@ fn extract_deposits(
     (input_deposit, output_deposit)
 }
 
-/// Extract caller app ID (for cross-app authorization)
-fn extract_caller_app(tx: &Transaction, _current_app: &App) -> Option<[u8; 32]> {
-    // In Charms, cross-app calls are detected by analyzing which apps
-    // are involved in the same transaction. If VaultManager is also
-    // validating this transaction, it's the caller.
-    for (app, _) in tx.app_public_inputs.iter() {
-        // Return the first other app found (in real impl, would be more specific)
-        return Some(app.identity.0);
+/// Resolve the caller app ID (for cross-app authorization)
+///
+/// Only an app in the spell whose identity equals the configured
+/// `vault_manager_id` counts as the caller; any other app is ignored.
+/// Returns `DuplicateCompanionApp` if two different apps carry that id.
+fn extract_caller_app(tx: &Transaction, vault_manager_id: &[u8; 32]) -> ZkUsdResult<Option<[u8; 32]>> {
+    let mut callers = tx.app_public_inputs.keys()
+        .filter(|caller_app| caller_app.identity.0 == *vault_manager_id);
+
+    match (callers.next(), callers.next()) {
+        (None, _) => Ok(None),
+        (Some(_), None) => Ok(Some(*vault_manager_id)),
+        (Some(_), Some(_)) => Err(ZkUsdError::DuplicateCompanionApp { role: CompanionRole::VaultManager }),
     }
-    None
 }
 
 /// Extract signer from transaction
@@ -502,49 +729,19 @@ fn extract_signer(tx: &Transaction) -> Address {
 // ============ Flow Calculations ============
 
 /// Calculate zkUSD token flows in transaction
+///
+/// Token charms are matched by tag and identity: their VK is the token
+/// app's, which the pool does not know.
 fn calculate_zkusd_flows(tx: &Transaction, token_app_id: &[u8; 32]) -> (u64, u64) {
-    let mut inputs: u64 = 0;
-    let mut outputs: u64 = 0;
-
-    // Create App key for lookup
-    let token_app = App {
-        tag: 't',
-        identity: B32(*token_app_id),
-        vk: B32([0u8; 32]), // VK not needed for lookup
+    let amounts = |charms: &Charms| {
+        charms.iter()
+            .filter(|(app, _)| app.tag == 't' && app.identity.0 == *token_app_id)
+            .filter_map(|(_, data)| data.value::<u64>().ok())
+            .fold(0u64, u64::saturating_add)
     };
 
-    // Sum token amounts from inputs
-    for (_, charms) in tx.ins.iter() {
-        if let Some(data) = charms.get(&token_app) {
-            if let Ok(amount) = data.value::<u64>() {
-                inputs = inputs.saturating_add(amount);
-            }
-        }
-    }
-
-    // Sum token amounts from outputs
-    for charms in tx.outs.iter() {
-        if let Some(data) = charms.get(&token_app) {
-            if let Ok(amount) = data.value::<u64>() {
-                outputs = outputs.saturating_add(amount);
-            }
-        }
-    }
-
-    (inputs, outputs)
-}
-
-/// Calculate total BTC flowing in and out
-fn calculate_btc_flows(tx: &Transaction) -> (u64, u64) {
-    let inputs = tx.coin_ins
-        .as_ref()
-        .map(|ins| ins.iter().map(|o| o.amount).sum())
-        .unwrap_or(0);
-
-    let outputs = tx.coin_outs
-        .as_ref()
-        .map(|outs| outs.iter().map(|o| o.amount).sum())
-        .unwrap_or(0);
+    let inputs = tx.ins.iter().map(|(_, charms)| amounts(charms)).fold(0u64, u64::saturating_add);
+    let outputs = tx.outs.iter().map(amounts).fold(0u64, u64::saturating_add);
 
     (inputs, outputs)
 }
@@ -554,6 +751,8 @@ fn calculate_btc_flows(tx: &Transaction) -> (u64, u64) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
+    use zkusd_charms_compat::{TxId, UtxoId, B32};
 
     fn create_test_witness() -> StabilityWitness {
         StabilityWitness::deposit(1_000_00000000) // 1000 zkUSD
@@ -575,7 +774,7 @@ mod tests {
         let action = witness_to_action(&witness).unwrap();
 
         match action {
-            StabilityPoolAction::Deposit { amount } => {
+            StabilityPoolAction::Deposit { amount: ZkUsd(amount) } => {
                 assert_eq!(amount, 1_000_00000000);
             }
             _ => panic!("Expected Deposit action"),
@@ -591,7 +790,7 @@ mod tests {
         let action = witness_to_action(&witness).unwrap();
 
         match action {
-            StabilityPoolAction::Offset { debt, collateral } => {
+            StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) } => {
                 assert_eq!(debt, 10_000_00000000);
                 assert_eq!(collateral, 100_000_000);
             }
@@ -606,4 +805,143 @@ mod tests {
 
         assert!(matches!(action, StabilityPoolAction::ClaimBtc));
     }
+
+    #[test]
+    fn test_claim_btc_to_vault_witness() {
+        let witness = StabilityWitness::claim_btc_to_vault([7u8; 32]);
+        let action = witness_to_action(&witness).unwrap();
+        assert_eq!(action, StabilityPoolAction::ClaimBtcToVault { vault_id: [7u8; 32] });
+
+        // The receiving vault is required
+        let witness = StabilityWitness { vault_id: None, ..witness };
+        assert_eq!(witness_to_action(&witness), None);
+    }
+
+    #[test]
+    fn test_sweep_dust_deposit_witness() {
+        let witness = StabilityWitness::sweep_dust_deposit([7u8; 32]);
+        let action = witness_to_action(&witness).unwrap();
+        assert_eq!(action, StabilityPoolAction::SweepDustDeposit { depositor: [7u8; 32] });
+
+        // The swept deposit's owner is required
+        let witness = StabilityWitness { depositor: None, ..witness };
+        assert_eq!(witness_to_action(&witness), None);
+    }
+
+    #[test]
+    fn test_for_action_round_trips() {
+        let actions = [
+            StabilityPoolAction::Deposit { amount: ZkUsd(1_000) },
+            StabilityPoolAction::Withdraw { amount: ZkUsd(500) },
+            StabilityPoolAction::ClaimBtc,
+            StabilityPoolAction::Offset { debt: ZkUsd(1_000), collateral: Sats(11) },
+            StabilityPoolAction::ClaimBtcToVault { vault_id: [7u8; 32] },
+            StabilityPoolAction::SweepDustDeposit { depositor: [7u8; 32] },
+            StabilityPoolAction::RegisterFrontend {
+                frontend: [7u8; 32],
+                kickback_bps: 9_000,
+                payout_script: [&[0x00, 0x14][..], &[7u8; 20]].concat(),
+            },
+        ];
+        for action in actions {
+            let data = Data::from(&StabilityWitness::for_action(&action));
+            assert_eq!(witness_to_action(&parse_witness(&data).unwrap()), Some(action));
+        }
+    }
+
+    // ============ Companion App Tests ============
+
+    fn pool_app() -> App {
+        App { tag: 'n', identity: B32([4u8; 32]), vk: B32([4u8; 32]) }
+    }
+
+    fn test_config(zkusd_token_id: [u8; 32]) -> StabilityPoolConfig {
+        StabilityPoolConfig {
+            zkusd_token_id,
+            vault_manager_id: [2u8; 32],
+            admin: [1u8; 32],
+            frontends: Vec::new(),
+        }
+    }
+
+    fn tx_with_config_refs(configs: Vec<(App, StabilityPoolConfig)>) -> Transaction {
+        let refs = configs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (app, config))| {
+                let mut charms = BTreeMap::new();
+                charms.insert(app, Data::from(&config));
+                (UtxoId(TxId([i as u8; 32]), 0), charms)
+            })
+            .collect();
+
+        Transaction {
+            ins: Vec::new(),
+            refs,
+            outs: Vec::new(),
+            coin_ins: None,
+            coin_outs: None,
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_config_from_own_app() {
+        let tx = tx_with_config_refs(vec![(pool_app(), test_config([1u8; 32]))]);
+        assert_eq!(extract_config(&pool_app(), &tx.refs), Ok(test_config([1u8; 32])));
+    }
+
+    #[test]
+    fn test_counterfeit_config_rejected() {
+        // Lookalike config pointing the pool at an attacker's token
+        let counterfeit = App { tag: 'n', identity: B32([9u8; 32]), vk: B32([4u8; 32]) };
+        let tx = tx_with_config_refs(vec![
+            (pool_app(), test_config([1u8; 32])),
+            (counterfeit, test_config([9u8; 32])),
+        ]);
+
+        assert_eq!(
+            extract_config(&pool_app(), &tx.refs),
+            Err(ZkUsdError::WrongCompanionApp {
+                expected: [4u8; 32],
+                found: [9u8; 32],
+                role: CompanionRole::StabilityPool,
+            })
+        );
+    }
+
+    #[test]
+    fn test_zkusd_flows_from_token_charms() {
+        let token_id = [1u8; 32];
+        let token = App { tag: 't', identity: B32(token_id), vk: B32([7u8; 32]) };
+        let counterfeit = App { tag: 't', identity: B32([9u8; 32]), vk: B32([7u8; 32]) };
+        let mut tx = tx_with_config_refs(Vec::new());
+        tx.ins.push((UtxoId(TxId([1u8; 32]), 0), BTreeMap::from([(token.clone(), Data::from(&600u64))])));
+        tx.ins.push((UtxoId(TxId([2u8; 32]), 0), BTreeMap::from([(counterfeit, Data::from(&1_000u64))])));
+        tx.outs.push(BTreeMap::from([(token, Data::from(&400u64))]));
+
+        // Counted under the token's own VK; other tokens are ignored
+        assert_eq!(calculate_zkusd_flows(&tx, &token_id), (600, 400));
+    }
+
+    #[test]
+    fn test_caller_resolved_by_vault_manager_id() {
+        let vault_manager_id = [2u8; 32];
+        let mut tx = tx_with_config_refs(Vec::new());
+
+        // Any other app in the spell is not the caller
+        tx.app_public_inputs.insert(App { tag: 'n', identity: B32([3u8; 32]), vk: B32([3u8; 32]) }, Data::empty());
+        assert_eq!(extract_caller_app(&tx, &vault_manager_id), Ok(None));
+
+        tx.app_public_inputs.insert(App { tag: 'n', identity: B32(vault_manager_id), vk: B32([2u8; 32]) }, Data::empty());
+        assert_eq!(extract_caller_app(&tx, &vault_manager_id), Ok(Some(vault_manager_id)));
+
+        // A second app claiming the VaultManager id
+        tx.app_public_inputs.insert(App { tag: 'n', identity: B32(vault_manager_id), vk: B32([9u8; 32]) }, Data::empty());
+        assert_eq!(
+            extract_caller_app(&tx, &vault_manager_id),
+            Err(ZkUsdError::DuplicateCompanionApp { role: CompanionRole::VaultManager })
+        );
+    }
 }
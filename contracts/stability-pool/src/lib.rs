@@ -16,20 +16,38 @@ use borsh::{BorshDeserialize, BorshSerialize};
 // Charms SDK integration (conditional compilation)
 #[cfg(feature = "charms")]
 pub mod charms;
+#[cfg(test)]
+mod testkit;
 use serde::{Deserialize, Serialize};
 
 use zkusd_common::{
-    constants::stability_pool::{MIN_DEPOSIT, SCALE_FACTOR},
-    errors::{ZkUsdError, ZkUsdResult},
-    events::{EventLog, ZkUsdEvent},
-    math::{calculate_btc_gain, calculate_compounded_deposit},
-    types::{Address, AppId, StabilityDeposit, StabilityPoolAction, StabilityPoolState},
+    commitment::{CommittedApp, StateRef},
+    constants::{
+        fees::BPS_DENOMINATOR,
+        limits::DUST_LIMIT,
+        stability_pool::{DUST_DEPOSIT_THRESHOLD, MAX_FRONTENDS, MAX_PAYOUT_SCRIPT_LEN, MIN_DEPOSIT, SCALE_FACTOR},
+        time::BLOCKS_PER_YEAR,
+    },
+    errors::{AmountErrorReason, ZkUsdError, ZkUsdResult},
+    events::{EventLog, HealthTick, ZkUsdEvent},
+    math::{
+        btc_to_zkusd_floor, calculate_btc_gain_with_scale_factor, calculate_compounded_deposit,
+        calculate_compounded_deposit_with_scale_factor, calculate_epoch_btc_gain_with_scale_factor,
+        calculate_pending_btc, safe_mul_div_u128,
+    },
+    types::{
+        Address, AppId, BtcGainSplit, EpochSnapshot, OffsetSample, StabilityDeposit, StabilityPoolAction,
+        StabilityPoolState, VaultId,
+    },
+    units::{Sats, ZkUsd},
+    validation::{require_not_dust, require_valid_address},
 };
 
 // ============ Stability Pool Config ============
 
 /// Configuration for the Stability Pool
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct StabilityPoolConfig {
     /// zkUSD Token app_id
     pub zkusd_token_id: AppId,
@@ -37,11 +55,48 @@ pub struct StabilityPoolConfig {
     pub vault_manager_id: AppId,
     /// Admin address
     pub admin: Address,
+    /// Registered frontends, at most `MAX_FRONTENDS`
+    #[serde(default)]
+    pub frontends: Vec<RegisteredFrontend>,
+}
+
+impl StabilityPoolConfig {
+    /// Registration of `frontend`, if registered
+    pub fn frontend(&self, frontend: &Address) -> Option<&RegisteredFrontend> {
+        self.frontends.iter().find(|registered| registered.frontend == *frontend)
+    }
+
+    /// Kickback rate `frontend` is registered at, if registered
+    pub fn kickback_bps_of(&self, frontend: &Address) -> Option<u64> {
+        self.frontend(frontend).map(|registered| registered.kickback_bps)
+    }
+
+    /// Script `frontend`'s share of BTC gains is paid to, if registered
+    pub fn payout_script_of(&self, frontend: &Address) -> Option<&[u8]> {
+        self.frontend(frontend).map(|registered| registered.payout_script.as_slice())
+    }
+}
+
+/// A frontend deposits may be tagged with
+///
+/// The tag is an `Address`, which a BTC output cannot be checked against,
+/// so the frontend's share of BTC gains is paid to the scriptPubKey it
+/// registers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct RegisteredFrontend {
+    /// Address deposits are tagged with
+    pub frontend: Address,
+    /// BPS of a tagged deposit's BTC gains kept by the depositor
+    pub kickback_bps: u64,
+    /// scriptPubKey the frontend's share of BTC gains is paid to
+    pub payout_script: Vec<u8>,
 }
 
 // ============ Validation Context ============
 
 /// Context for validating stability pool operations
+#[derive(Debug, Clone)]
 pub struct StabilityPoolContext {
     /// Current pool state
     pub state: StabilityPoolState,
@@ -49,40 +104,99 @@ pub struct StabilityPoolContext {
     pub new_state: StabilityPoolState,
     /// Config
     pub config: StabilityPoolConfig,
+    /// Updated config (admin actions that change it)
+    pub new_config: Option<StabilityPoolConfig>,
     /// User's deposit (if any)
     pub deposit: Option<StabilityDeposit>,
     /// Updated user deposit
     pub new_deposit: Option<StabilityDeposit>,
     /// zkUSD inputs
-    pub zkusd_inputs: u64,
+    pub zkusd_inputs: ZkUsd,
     /// zkUSD outputs
-    pub zkusd_outputs: u64,
+    pub zkusd_outputs: ZkUsd,
     /// BTC inputs (from liquidations)
-    pub btc_inputs: u64,
+    pub btc_inputs: Sats,
     /// BTC outputs (to claimers)
-    pub btc_outputs: u64,
+    pub btc_outputs: Sats,
+    /// Part of `btc_outputs` paid to the payout script of the deposit's
+    /// frontend
+    pub frontend_btc_outputs: Sats,
     /// Caller app_id (for offset authorization)
     pub caller_app_id: Option<AppId>,
     /// Signer address
     pub signer: Address,
     /// Current block height
     pub block_height: u64,
+    /// Pool state the spell was built against, which the spent state must be
+    pub based_on: Option<StateRef>,
     /// Event log
     pub events: EventLog,
 }
 
+/// Arbitrary spell inputs with an empty event log
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for StabilityPoolContext {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            state: u.arbitrary()?,
+            new_state: u.arbitrary()?,
+            config: u.arbitrary()?,
+            new_config: u.arbitrary()?,
+            deposit: u.arbitrary()?,
+            new_deposit: u.arbitrary()?,
+            zkusd_inputs: u.arbitrary()?,
+            zkusd_outputs: u.arbitrary()?,
+            btc_inputs: u.arbitrary()?,
+            btc_outputs: u.arbitrary()?,
+            frontend_btc_outputs: u.arbitrary()?,
+            caller_app_id: u.arbitrary()?,
+            signer: u.arbitrary()?,
+            block_height: u.arbitrary()?,
+            based_on: u.arbitrary()?,
+            events: EventLog::new(),
+        })
+    }
+}
+
 // ============ Validation Functions ============
 
 /// Main validation entry point
 pub fn validate(ctx: &mut StabilityPoolContext, action: &StabilityPoolAction) -> ZkUsdResult<()> {
+    // A claim built on an offset a reorg has since undone must not apply
+    // to the pool that replaced it
+    if let Some(based_on) = &ctx.based_on {
+        based_on.require_matches(&ctx.state.commitment())?;
+    }
+
+    // Only a sweep moves zkUSD to the protocol
+    let sweep = matches!(action, StabilityPoolAction::SweepDustDeposit { .. });
+    if !sweep && ctx.new_state.protocol_dust != ctx.state.protocol_dust {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
     match action {
-        StabilityPoolAction::Deposit { amount } => validate_deposit(ctx, *amount),
-        StabilityPoolAction::Withdraw { amount } => validate_withdraw(ctx, *amount),
+        StabilityPoolAction::Deposit { amount: ZkUsd(amount) } => validate_deposit(ctx, *amount),
+        StabilityPoolAction::Withdraw { amount: ZkUsd(amount) } => validate_withdraw(ctx, *amount),
         StabilityPoolAction::ClaimBtc => validate_claim_btc(ctx),
-        StabilityPoolAction::Offset { debt, collateral } => {
+        StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) } => {
             validate_offset(ctx, *debt, *collateral)
         }
-    }
+        StabilityPoolAction::ClaimBtcToVault { vault_id } => validate_claim_btc_to_vault(ctx, vault_id),
+        StabilityPoolAction::SweepDustDeposit { depositor } => validate_sweep_dust_deposit(ctx, depositor),
+        StabilityPoolAction::RegisterFrontend { frontend, kickback_bps, payout_script } => {
+            validate_register_frontend(ctx, frontend, *kickback_bps, payout_script)
+        }
+    }?;
+
+    ctx.events.emit(ZkUsdEvent::StateCommitted {
+        app: CommittedApp::StabilityPool,
+        commitment: ctx.new_state.commitment(),
+        block_height: ctx.block_height,
+    });
+    // The pool sees no price; the vault manager's tick carries it
+    ctx.events.emit(ZkUsdEvent::HealthTick(HealthTick::from_states(None, Some(&ctx.new_state), 0, ctx.block_height)));
+
+    Ok(())
 }
 
 /// Validate depositing zkUSD into the pool
@@ -106,9 +220,9 @@ fn validate_deposit(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult<
     }
 
     // 3. Verify zkUSD is being deposited
-    if ctx.zkusd_inputs < amount {
+    if ctx.zkusd_inputs < ZkUsd(amount) {
         return Err(ZkUsdError::InsufficientBalance {
-            available: ctx.zkusd_inputs,
+            available: ctx.zkusd_inputs.into_inner(),
             requested: amount,
         });
     }
@@ -138,6 +252,13 @@ fn validate_deposit(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult<
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
+    // 5b. The frontend tag is chosen at the first deposit and kept by
+    //     top-ups
+    match &ctx.deposit {
+        Some(deposit) => require_same_frontend(deposit, new_deposit)?,
+        None => require_registered_frontend(&ctx.config, new_deposit)?,
+    }
+
     // 6. Verify pool state update
     let expected_total = ctx.state.total_zkusd
         .checked_add(amount)
@@ -161,16 +282,18 @@ fn validate_deposit(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult<
 
 /// Validate withdrawing zkUSD from the pool
 fn validate_withdraw(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult<()> {
-    // 1. Amount must be positive
-    if amount == 0 {
-        return Err(ZkUsdError::ZeroAmount);
-    }
-
-    // 2. Get deposit
+    // 1. Get deposit
     let deposit = ctx.deposit.as_ref().ok_or(ZkUsdError::DepositNotFound {
         user: ctx.signer,
     })?;
 
+    // 2. Amount must be positive, unless a closed epoch consumed the deposit
+    //    and only its BTC gains remain
+    let consumed = deposit.snapshot_epoch < ctx.state.current_epoch;
+    if amount == 0 && !consumed {
+        return Err(ZkUsdError::ZeroAmount);
+    }
+
     // 3. Only owner can withdraw
     if deposit.owner != ctx.signer {
         return Err(ZkUsdError::Unauthorized {
@@ -199,21 +322,25 @@ fn validate_withdraw(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult
     }
 
     // 6. Calculate and distribute any BTC gains
-    let btc_gain = calculate_btc_gain(
-        deposit.initial_value,
-        deposit.snapshot_s,
-        ctx.state.sum_s,
-    );
+    let btc_gain = get_pending_btc(deposit, &ctx.state)?;
 
-    // 7. Verify zkUSD output
-    if ctx.zkusd_outputs < amount {
+    // 7. zkUSD output must be exactly the withdrawn amount
+    if ctx.zkusd_outputs != ZkUsd(amount) {
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // 8. Verify BTC output if there are gains
-    if btc_gain > 0 && ctx.btc_outputs < btc_gain {
+    // 8. BTC output must be exactly the gain: no less (silent loss) and
+    //    no more (paying out BTC the deposit did not earn)
+    if ctx.btc_outputs != Sats(btc_gain) {
         return Err(ZkUsdError::InvalidStateTransition);
     }
+    let split = verify_frontend_share(ctx, deposit, btc_gain)?;
+
+    // 8b. A remaining deposit keeps its frontend tag
+    if let Some(new_deposit) = &ctx.new_deposit {
+        require_same_frontend(deposit, new_deposit)?;
+    }
+    let kickback = frontend_kickback_event(deposit, split, ctx.block_height);
 
     // 9. Emit event
     ctx.events.emit(ZkUsdEvent::StabilityWithdrawal {
@@ -223,7 +350,7 @@ fn validate_withdraw(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult
         block_height: ctx.block_height,
     });
 
-    // 10. Emit BTC reward event if applicable
+    // 10. Emit BTC reward events if applicable
     if btc_gain > 0 {
         ctx.events.emit(ZkUsdEvent::BtcRewardClaimed {
             depositor: ctx.signer,
@@ -231,12 +358,75 @@ fn validate_withdraw(ctx: &mut StabilityPoolContext, amount: u64) -> ZkUsdResult
             block_height: ctx.block_height,
         });
     }
+    if let Some(event) = kickback {
+        ctx.events.emit(event);
+    }
 
     Ok(())
 }
 
 /// Validate claiming BTC rewards without withdrawing zkUSD
 fn validate_claim_btc(ctx: &mut StabilityPoolContext) -> ZkUsdResult<()> {
+    // 1. Deposit, ownership, snapshot and frontend checks
+    let (split, kickback) = validate_btc_claim(ctx)?;
+    let btc_gain = split.total();
+
+    // 2. BTC output must be exactly the gain, and large enough to relay
+    require_not_dust(btc_gain, DUST_LIMIT)?;
+    if ctx.btc_outputs != Sats(btc_gain) {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 3. Emit events
+    ctx.events.emit(ZkUsdEvent::BtcRewardClaimed {
+        depositor: ctx.signer,
+        btc_amount: btc_gain,
+        block_height: ctx.block_height,
+    });
+    if let Some(event) = kickback {
+        ctx.events.emit(event);
+    }
+
+    Ok(())
+}
+
+/// Validate claiming BTC rewards straight into the depositor's vault
+///
+/// The claim is validated as `ClaimBtc`, but the BTC is not paid out to
+/// the depositor: the VaultManager must be in the spell, and it credits
+/// the depositor's share of the gain to the vault as added collateral
+/// (checking that the depositor owns the vault and that the collateral
+/// grows by exactly that share). A frontend's share is still paid out.
+fn validate_claim_btc_to_vault(ctx: &mut StabilityPoolContext, vault_id: &VaultId) -> ZkUsdResult<()> {
+    // 1. Deposit, ownership, snapshot and frontend checks
+    let (split, kickback) = validate_btc_claim(ctx)?;
+
+    // 2. The BTC is routed to the VaultManager
+    let vault_manager_id = ctx.config.vault_manager_id;
+    if ctx.caller_app_id != Some(vault_manager_id) {
+        return Err(ZkUsdError::Unauthorized {
+            expected: vault_manager_id,
+            actual: ctx.caller_app_id.unwrap_or([0u8; 32]),
+        });
+    }
+
+    // 3. Emit events
+    ctx.events.emit(ZkUsdEvent::BtcClaimedToVault {
+        depositor: ctx.signer,
+        vault_id: *vault_id,
+        btc_amount: split.depositor,
+        block_height: ctx.block_height,
+    });
+    if let Some(event) = kickback {
+        ctx.events.emit(event);
+    }
+
+    Ok(())
+}
+
+/// Checks shared by BTC claims, returning the split of the gain being
+/// claimed and the kickback event to emit for it
+fn validate_btc_claim(ctx: &StabilityPoolContext) -> ZkUsdResult<(BtcGainSplit, Option<ZkUsdEvent>)> {
     // 1. Get deposit
     let deposit = ctx.deposit.as_ref().ok_or(ZkUsdError::DepositNotFound {
         user: ctx.signer,
@@ -251,34 +441,166 @@ fn validate_claim_btc(ctx: &mut StabilityPoolContext) -> ZkUsdResult<()> {
     }
 
     // 3. Calculate BTC gains
-    let btc_gain = calculate_btc_gain(
-        deposit.initial_value,
-        deposit.snapshot_s,
-        ctx.state.sum_s,
-    );
+    let btc_gain = get_pending_btc(deposit, &ctx.state)?;
 
     // 4. Must have rewards to claim
     if btc_gain == 0 {
         return Err(ZkUsdError::NoRewardsToClaim);
     }
 
-    // 5. Verify BTC output
-    if ctx.btc_outputs < btc_gain {
+    // 5. Verify deposit snapshot is moved to the current epoch and S
+    let new_deposit = ctx.new_deposit.as_ref().ok_or(ZkUsdError::StateNotFound)?;
+    if new_deposit.snapshot_s != ctx.state.sum_s
+        || new_deposit.snapshot_epoch != ctx.state.current_epoch
+    {
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // 6. Verify deposit snapshot is updated
-    let new_deposit = ctx.new_deposit.as_ref().ok_or(ZkUsdError::StateNotFound)?;
-    if new_deposit.snapshot_s != ctx.state.sum_s {
+    // 5b. A deposit consumed by a closed epoch carries no zkUSD forward
+    if deposit.snapshot_epoch < ctx.state.current_epoch && new_deposit.initial_value != 0 {
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // 7. Emit event
-    ctx.events.emit(ZkUsdEvent::BtcRewardClaimed {
-        depositor: ctx.signer,
+    // 6. The deposit keeps its frontend, which is paid its share
+    require_same_frontend(deposit, new_deposit)?;
+    let split = verify_frontend_share(ctx, deposit, btc_gain)?;
+
+    Ok((split, frontend_kickback_event(deposit, split, ctx.block_height)))
+}
+
+/// Validate sweeping a deposit worn down to dust by liquidations
+///
+/// Anyone may sweep a deposit with nothing left to pay out, as the
+/// depositor would pay more to withdraw than the deposit is worth. A
+/// deposit still owed a BTC gain is swept by its depositor only: the BTC
+/// output is checked in total, as with withdrawals, so a keeper could
+/// direct the gain to itself. The zkUSD dust leaves the pool total for
+/// `protocol_dust`.
+fn validate_sweep_dust_deposit(ctx: &mut StabilityPoolContext, depositor: &Address) -> ZkUsdResult<()> {
+    // 1. Get the depositor's deposit
+    let deposit = ctx
+        .deposit
+        .as_ref()
+        .filter(|deposit| deposit.owner == *depositor)
+        .ok_or(ZkUsdError::DepositNotFound { user: *depositor })?;
+
+    // 2. Only dust may be swept
+    let compounded_value = get_compounded_value(deposit, &ctx.state);
+    if compounded_value >= DUST_DEPOSIT_THRESHOLD {
+        return Err(ZkUsdError::InvalidAmount {
+            amount: compounded_value,
+            reason: AmountErrorReason::TooLarge,
+        });
+    }
+
+    // 3. BTC output must be exactly the gain, and no zkUSD is paid out; a
+    // gain is only swept by the depositor it goes to
+    let btc_gain = get_pending_btc(deposit, &ctx.state)?;
+    if btc_gain > 0 && ctx.signer != deposit.owner {
+        return Err(ZkUsdError::Unauthorized {
+            expected: deposit.owner,
+            actual: ctx.signer,
+        });
+    }
+    if ctx.btc_outputs != Sats(btc_gain) || ctx.zkusd_outputs != ZkUsd::ZERO {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    let split = verify_frontend_share(ctx, deposit, btc_gain)?;
+    let kickback = frontend_kickback_event(deposit, split, ctx.block_height);
+
+    // 4. Deposit charm is closed
+    if ctx.new_deposit.is_some() {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 5. One depositor fewer, with the dust moved from the pool total to
+    // the protocol
+    let expected_total = ctx.state.total_zkusd.saturating_sub(compounded_value);
+    let expected_dust = ctx.state.protocol_dust.checked_add(compounded_value).ok_or(ZkUsdError::Overflow)?;
+    if ctx.new_state.depositor_count != ctx.state.depositor_count.saturating_sub(1)
+        || ctx.new_state.total_zkusd != expected_total
+        || ctx.new_state.protocol_dust != expected_dust
+    {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 6. Emit events
+    ctx.events.emit(ZkUsdEvent::DustDepositSwept {
+        depositor: *depositor,
+        swept_by: ctx.signer,
+        zkusd_dust: compounded_value,
         btc_amount: btc_gain,
         block_height: ctx.block_height,
     });
+    if let Some(event) = kickback {
+        ctx.events.emit(event);
+    }
+
+    Ok(())
+}
+
+/// Validate registering a frontend (admin only)
+///
+/// The config is re-created with the frontend appended; the pool state
+/// carries over unchanged. A frontend's rate and payout script are fixed
+/// once registered, and each deposit keeps the rate it was tagged at.
+fn validate_register_frontend(
+    ctx: &mut StabilityPoolContext,
+    frontend: &Address,
+    kickback_bps: u64,
+    payout_script: &[u8],
+) -> ZkUsdResult<()> {
+    // 1. Only the admin can register frontends
+    if ctx.signer != ctx.config.admin {
+        return Err(ZkUsdError::Unauthorized {
+            expected: ctx.config.admin,
+            actual: ctx.signer,
+        });
+    }
+
+    // 2. A new frontend, at a rate of at most 100%, within the bound
+    require_valid_address(*frontend, "frontend")?;
+    if kickback_bps > BPS_DENOMINATOR {
+        return Err(ZkUsdError::ExceedsMaximum { amount: kickback_bps, maximum: BPS_DENOMINATOR });
+    }
+    if ctx.config.kickback_bps_of(frontend).is_some() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "frontend",
+            reason: "frontend already registered",
+        });
+    }
+    if ctx.config.frontends.len() >= MAX_FRONTENDS {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: ctx.config.frontends.len() as u64 + 1,
+            maximum: MAX_FRONTENDS as u64,
+        });
+    }
+
+    // 2b. A payout script an output can be locked to
+    if payout_script.is_empty() || payout_script.len() > MAX_PAYOUT_SCRIPT_LEN {
+        return Err(ZkUsdError::InvalidInput {
+            param: "payout_script",
+            reason: "payout script must be 1 to 34 bytes",
+        });
+    }
+
+    // 3. Verify the config and pool state updates
+    let mut expected = ctx.config.clone();
+    expected.frontends.push(RegisteredFrontend {
+        frontend: *frontend,
+        kickback_bps,
+        payout_script: payout_script.to_vec(),
+    });
+    if ctx.new_config.as_ref() != Some(&expected) || ctx.new_state != ctx.state {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 4. Emit event
+    ctx.events.emit(ZkUsdEvent::FrontendRegistered {
+        frontend: *frontend,
+        kickback_bps,
+        block_height: ctx.block_height,
+    });
 
     Ok(())
 }
@@ -313,9 +635,9 @@ fn validate_offset(
 
     // 3. Verify collateral is being received
     // Charms v0.12+ always populates coin_ins (PR #151 fix)
-    if ctx.btc_inputs < collateral {
+    if ctx.btc_inputs < Sats(collateral) {
         return Err(ZkUsdError::InsufficientBalance {
-            available: ctx.btc_inputs,
+            available: ctx.btc_inputs.into_inner(),
             requested: collateral,
         });
     }
@@ -323,25 +645,14 @@ fn validate_offset(
     // 4. Update P and S values
     // P_new = P * (1 - debt / total_zkusd)
     // S_new = S + (collateral / total_zkusd) * P
-    let debt_ratio = (debt as u128)
-        .checked_mul(SCALE_FACTOR)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(ctx.state.total_zkusd as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
-
-    let expected_p = ctx.state.product_p
-        .checked_mul(SCALE_FACTOR - debt_ratio)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(SCALE_FACTOR)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+    let debt_ratio = safe_mul_div_u128(debt as u128, SCALE_FACTOR, ctx.state.total_zkusd as u128)?;
+
+    let expected_p = safe_mul_div_u128(ctx.state.product_p, SCALE_FACTOR - debt_ratio, SCALE_FACTOR)?;
 
     // Calculate S value update: S_new = S + (collateral * P / total_zkusd)
     // This tracks cumulative BTC gains per unit of zkUSD deposited
-    let collateral_per_unit = (collateral as u128)
-        .checked_mul(ctx.state.product_p)
-        .ok_or(ZkUsdError::Overflow)?
-        .checked_div(ctx.state.total_zkusd as u128)
-        .ok_or(ZkUsdError::DivisionByZero)?;
+    let collateral_per_unit =
+        safe_mul_div_u128(collateral as u128, ctx.state.product_p, ctx.state.total_zkusd as u128)?;
 
     let expected_s = ctx.state.sum_s
         .checked_add(collateral_per_unit)
@@ -356,17 +667,29 @@ fn validate_offset(
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // Verify P value update
-    if ctx.new_state.product_p != expected_p {
-        return Err(ZkUsdError::InvalidStateTransition);
+    if expected_total == 0 {
+        // Pool emptied: close the epoch, keeping its final S for claimers
+        verify_epoch_rollover(ctx, expected_s)?;
+    } else {
+        // Verify P value update
+        if ctx.new_state.product_p != expected_p {
+            return Err(ZkUsdError::InvalidStateTransition);
+        }
+
+        // Verify S value update (BTC distribution tracking)
+        if ctx.new_state.sum_s != expected_s {
+            return Err(ZkUsdError::InvalidStateTransition);
+        }
     }
 
-    // Verify S value update (BTC distribution tracking)
-    if ctx.new_state.sum_s != expected_s {
+    // 6. The offset must be appended to the recent-offset samples
+    let mut expected_offsets = ctx.state.clone();
+    expected_offsets.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
+    if ctx.new_state.recent_offsets != expected_offsets.recent_offsets {
         return Err(ZkUsdError::InvalidStateTransition);
     }
 
-    // 6. Emit event
+    // 7. Emit event
     ctx.events.emit(ZkUsdEvent::LiquidationOffset {
         debt_offset: debt,
         collateral_gained: collateral,
@@ -377,8 +700,82 @@ fn validate_offset(
     Ok(())
 }
 
+/// Verify the pool state after an offset that emptied the pool
+///
+/// The closed epoch's final S is recorded in `epoch_snapshots` and the
+/// next epoch starts from P = 1, S = 0 at scale 0.
+fn verify_epoch_rollover(ctx: &StabilityPoolContext, final_s: u128) -> ZkUsdResult<()> {
+    let mut expected = ctx.state.clone();
+    expected.record_epoch_snapshot(EpochSnapshot {
+        epoch: ctx.state.current_epoch,
+        final_scale: ctx.state.current_scale,
+        final_s_at_scale: final_s,
+        final_s_at_scale_plus_one: 0,
+    });
+    expected.current_epoch = ctx.state.current_epoch
+        .checked_add(1)
+        .ok_or(ZkUsdError::Overflow)?;
+    expected.current_scale = 0;
+    expected.product_p = SCALE_FACTOR;
+    expected.sum_s = 0;
+
+    if ctx.new_state.current_epoch != expected.current_epoch
+        || ctx.new_state.current_scale != expected.current_scale
+        || ctx.new_state.product_p != expected.product_p
+        || ctx.new_state.sum_s != expected.sum_s
+        || ctx.new_state.epoch_snapshots != expected.epoch_snapshots
+    {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    Ok(())
+}
+
 // ============ Helper Functions ============
 
+/// Require a first deposit's frontend tag, if any, to be registered at
+/// the rate the deposit records; an untagged deposit records no rate
+fn require_registered_frontend(config: &StabilityPoolConfig, deposit: &StabilityDeposit) -> ZkUsdResult<()> {
+    match deposit.frontend_tag {
+        Some(frontend) if config.kickback_bps_of(&frontend) != Some(deposit.kickback_bps) => {
+            Err(ZkUsdError::FrontendNotRegistered { frontend })
+        }
+        None if deposit.kickback_bps != 0 => Err(ZkUsdError::InvalidStateTransition),
+        _ => Ok(()),
+    }
+}
+
+/// Require an updated deposit to keep the frontend tag and rate it was
+/// first made with
+fn require_same_frontend(deposit: &StabilityDeposit, new_deposit: &StabilityDeposit) -> ZkUsdResult<()> {
+    if new_deposit.frontend_tag != deposit.frontend_tag || new_deposit.kickback_bps != deposit.kickback_bps {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    Ok(())
+}
+
+/// Require the deposit's frontend to be paid exactly its share of `gain`,
+/// returning the split
+fn verify_frontend_share(ctx: &StabilityPoolContext, deposit: &StabilityDeposit, gain: u64) -> ZkUsdResult<BtcGainSplit> {
+    let split = deposit.split_btc_gain(gain)?;
+    if ctx.frontend_btc_outputs != Sats(split.frontend) {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+    Ok(split)
+}
+
+/// Event recording a tagged deposit's gain split, if there is one to pay
+fn frontend_kickback_event(deposit: &StabilityDeposit, split: BtcGainSplit, block_height: u64) -> Option<ZkUsdEvent> {
+    let frontend = deposit.frontend_tag.filter(|_| split.total() > 0)?;
+    Some(ZkUsdEvent::FrontendKickbackPaid {
+        depositor: deposit.owner,
+        frontend,
+        depositor_btc: split.depositor,
+        frontend_btc: split.frontend,
+        block_height,
+    })
+}
+
 /// Calculate user's current compounded deposit value
 pub fn get_compounded_value(
     deposit: &StabilityDeposit,
@@ -396,15 +793,193 @@ pub fn get_compounded_value(
 }
 
 /// Calculate user's pending BTC rewards
+///
+/// Deposits from a closed epoch are paid from that epoch's snapshot.
+///
+/// # Errors
+/// - `EpochSnapshotEvicted` if the deposit's epoch is no longer retained
 pub fn get_pending_btc(
     deposit: &StabilityDeposit,
     state: &StabilityPoolState,
+) -> ZkUsdResult<u64> {
+    calculate_pending_btc(deposit, state)
+}
+
+// ============ Pool Statistics ============
+
+/// Loss-absorption capacity of the pool against total system debt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapacityReport {
+    /// Share of system debt the pool could absorb (BPS, capped at 100%)
+    pub coverage_bps: u64,
+    /// zkUSD available to offset liquidations
+    pub zkusd_available: u64,
+}
+
+/// Deposit's share of the pool (BPS)
+pub fn pool_share_bps(deposit: &StabilityDeposit, state: &StabilityPoolState) -> u64 {
+    if state.total_zkusd == 0 {
+        return 0;
+    }
+    let share = get_compounded_value(deposit, state) as u128 * BPS_DENOMINATOR as u128
+        / state.total_zkusd as u128;
+    share.min(BPS_DENOMINATOR as u128) as u64
+}
+
+/// How much of the total system debt the pool could absorb
+pub fn absorption_capacity(state: &StabilityPoolState, total_system_debt: u64) -> CapacityReport {
+    let coverage_bps = if total_system_debt == 0 {
+        BPS_DENOMINATOR
+    } else {
+        let coverage = state.total_zkusd as u128 * BPS_DENOMINATOR as u128 / total_system_debt as u128;
+        coverage.min(BPS_DENOMINATOR as u128) as u64
+    };
+    CapacityReport { coverage_bps, zkusd_available: state.total_zkusd }
+}
+
+/// Annualized liquidation yield of the pool (BPS)
+///
+/// Replays the offsets of the last `window_blocks` (counted back from the
+/// newest sample) with the P/S compounding math, rebuilding the pool size
+/// before each offset from the current total. The yield is the BTC gained
+/// per unit deposited, valued at `btc_price`, net of the zkUSD absorbed.
+/// Deposits and withdrawals between offsets are not visible in the
+/// samples, so this is an estimate for display, never for validation.
+pub fn estimate_apy(
+    recent_offsets: &[OffsetSample],
+    state: &StabilityPoolState,
+    btc_price: u64,
+    window_blocks: u64,
 ) -> u64 {
-    calculate_btc_gain(
-        deposit.initial_value,
-        deposit.snapshot_s,
-        state.sum_s,
-    )
+    let latest = match recent_offsets.iter().map(|s| s.block).max() {
+        Some(block) if window_blocks > 0 => block,
+        _ => return 0,
+    };
+    let window_start = latest.saturating_sub(window_blocks);
+    let in_window = || recent_offsets.iter().filter(move |s| s.block > window_start);
+
+    // Pool size before the first offset of the window
+    let mut pool = in_window().fold(state.total_zkusd as u128, |pool, s| pool + s.debt as u128);
+
+    // Per-unit gain and loss, scaled by SCALE_FACTOR as S and P are
+    let mut product_p = SCALE_FACTOR;
+    let mut gain = 0u128;
+    let mut loss = 0u128;
+    for sample in in_window() {
+        if pool == 0 {
+            continue;
+        }
+        let value = btc_to_zkusd_floor(Sats(sample.collateral), btc_price).map_or(u64::MAX, ZkUsd::into_inner);
+        gain = gain.saturating_add(product_p.saturating_mul(value as u128) / pool);
+        loss = loss.saturating_add(product_p.saturating_mul(sample.debt as u128) / pool);
+
+        let remaining = pool.saturating_sub(sample.debt as u128);
+        product_p = product_p * remaining / pool;
+        pool = remaining;
+    }
+
+    let apy = gain
+        .saturating_sub(loss)
+        .saturating_mul(BPS_DENOMINATOR as u128 * BLOCKS_PER_YEAR as u128)
+        / (SCALE_FACTOR * window_blocks as u128);
+    u64::try_from(apy).unwrap_or(u64::MAX)
+}
+
+// ============ Deposit Migration ============
+
+/// Re-base a deposit onto a migrated pool
+///
+/// One-time tool for when pool accounting changes underneath existing
+/// deposits, e.g. a new `SCALE_FACTOR`. The deposit is valued against
+/// `old_state`, whose P and S are expressed in `old_scale_factor`, then
+/// snapshotted against `new_state` (current `SCALE_FACTOR`): it keeps its
+/// initial value, and new P and S snapshots are chosen so that it has the
+/// same compounded value and the same pending BTC there.
+///
+/// # Errors
+/// - `EpochSnapshotEvicted` if the deposit's closed epoch is no longer retained
+/// - `InvalidInput` if the pending BTC cannot be carried: the deposit was
+///   depleted (claim first) or the new `sum_s` is too small
+/// - `InvalidStateTransition` if rounding would change either value
+pub fn migrate_deposit(
+    deposit: &StabilityDeposit,
+    old_state: &StabilityPoolState,
+    new_state: &StabilityPoolState,
+    old_scale_factor: u128,
+) -> ZkUsdResult<StabilityDeposit> {
+    // 1. Value and pending BTC under the old accounting
+    let (value, gain) = if deposit.snapshot_epoch >= old_state.current_epoch {
+        let value = calculate_compounded_deposit_with_scale_factor(
+            old_scale_factor,
+            deposit.initial_value,
+            deposit.snapshot_p,
+            old_state.product_p,
+            deposit.snapshot_scale,
+            old_state.current_scale,
+        );
+        let gain = calculate_btc_gain_with_scale_factor(
+            old_scale_factor,
+            deposit.initial_value,
+            deposit.snapshot_s,
+            old_state.sum_s,
+        );
+        (value, gain)
+    } else {
+        let epoch = old_state.epoch_snapshot(deposit.snapshot_epoch).ok_or(
+            ZkUsdError::EpochSnapshotEvicted { epoch: deposit.snapshot_epoch },
+        )?;
+        let gain = calculate_epoch_btc_gain_with_scale_factor(
+            old_scale_factor,
+            deposit.initial_value,
+            deposit.snapshot_s,
+            deposit.snapshot_scale,
+            epoch,
+        );
+        (0, gain)
+    };
+
+    let fresh = StabilityDeposit {
+        snapshot_p: new_state.product_p,
+        snapshot_s: new_state.sum_s,
+        snapshot_epoch: new_state.current_epoch,
+        snapshot_scale: new_state.current_scale,
+        ..deposit.clone()
+    };
+
+    // 2. A depleted deposit has nothing left to compound
+    if value == 0 {
+        if gain > 0 {
+            return Err(ZkUsdError::InvalidInput {
+                param: "deposit",
+                reason: "depleted deposit has unclaimed BTC",
+            });
+        }
+        return Ok(StabilityDeposit { initial_value: 0, ..fresh });
+    }
+
+    // 3. Snapshot P that compounds the initial value down to `value`, and
+    //    snapshot S that leaves `gain` pending; both rounded in the
+    //    depositor's favor
+    let initial = deposit.initial_value as u128;
+    let p_ratio = (value as u128 * SCALE_FACTOR).div_ceil(initial);
+    let s_offset = (gain as u128 * SCALE_FACTOR).div_ceil(initial);
+    let migrated = StabilityDeposit {
+        snapshot_p: new_state.product_p.saturating_mul(SCALE_FACTOR) / p_ratio,
+        snapshot_s: new_state.sum_s.checked_sub(s_offset).ok_or(ZkUsdError::InvalidInput {
+            param: "new_state",
+            reason: "sum_s too small to carry pending BTC",
+        })?,
+        ..fresh
+    };
+
+    // 4. Economic value must survive the migration exactly
+    if get_compounded_value(&migrated, new_state) != value
+        || get_pending_btc(&migrated, new_state)? != gain
+    {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    Ok(migrated)
 }
 
 // ============ Tests ============
@@ -412,40 +987,17 @@ pub fn get_pending_btc(
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    const ONE_ZKUSD: u64 = 100_000_000;
-    const ONE_BTC: u64 = 100_000_000;
-
-    fn create_test_context() -> StabilityPoolContext {
-        StabilityPoolContext {
-            state: StabilityPoolState::new(),
-            new_state: StabilityPoolState::new(),
-            config: StabilityPoolConfig {
-                zkusd_token_id: [1u8; 32],
-                vault_manager_id: [2u8; 32],
-                admin: [0u8; 32],
-            },
-            deposit: None,
-            new_deposit: None,
-            zkusd_inputs: 0,
-            zkusd_outputs: 0,
-            btc_inputs: 0,
-            btc_outputs: 0,
-            caller_app_id: None,
-            signer: [1u8; 32],
-            block_height: 100,
-            events: EventLog::new(),
-        }
-    }
+    use zkusd_common::events::HEALTH_TICK_VERSION;
+    use crate::testkit::{offset_collateral, pct, state_after_offset, SpCtx, ONE_BTC, ONE_ZKUSD, VAULT_MANAGER};
 
     #[test]
     fn test_deposit_success() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let depositor = [1u8; 32];
         let amount = 10_000 * ONE_ZKUSD;
 
         ctx.signer = depositor;
-        ctx.zkusd_inputs = amount;
+        ctx.zkusd_inputs = ZkUsd(amount);
         ctx.new_state.total_zkusd = amount;
 
         ctx.new_deposit = Some(StabilityDeposit {
@@ -456,25 +1008,51 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 100,
+            frontend_tag: None,
+            kickback_bps: 0,
         });
 
-        let action = StabilityPoolAction::Deposit { amount };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(amount) };
+        let spell = ctx.clone();
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Should succeed: {:?}", result);
-        assert_eq!(ctx.events.len(), 1);
+        // StabilityDeposit + StateCommitted + HealthTick
+        assert_eq!(ctx.events.len(), 3);
+        assert_eq!(
+            ctx.events.events().last(),
+            Some(&ZkUsdEvent::HealthTick(HealthTick {
+                version: HEALTH_TICK_VERSION,
+                tcr: 0,
+                total_collateral: 0,
+                total_debt: 0,
+                active_vault_count: 0,
+                sp_total_zkusd: amount,
+                sp_total_btc: 0,
+                btc_price: 0,
+                base_rate: 0,
+                recovery_mode: false,
+                block_height: 100,
+            }))
+        );
+
+        // A failed spell ticks nothing
+        let mut ctx = spell;
+        ctx.new_state.total_zkusd += 1;
+        assert!(validate(&mut ctx, &action).is_err());
+        assert!(!ctx.events.events().iter().any(|event| matches!(event, ZkUsdEvent::HealthTick(_))));
     }
 
     #[test]
     fn test_deposit_below_minimum() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         // In testnet mode, MIN_DEPOSIT is 1 zkUSD
         // Use 0 to trigger the BelowMinimum error (or ZeroAmount)
         let amount = 0;
 
-        ctx.zkusd_inputs = amount;
+        ctx.zkusd_inputs = ZkUsd(amount);
 
-        let action = StabilityPoolAction::Deposit { amount };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(amount) };
         let result = validate(&mut ctx, &action);
 
         // Should fail with either ZeroAmount or BelowMinimum
@@ -483,14 +1061,14 @@ mod tests {
 
     #[test]
     fn test_offset_authorized() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
         ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
         ctx.state.product_p = SCALE_FACTOR;
         ctx.state.sum_s = 0;
-        ctx.btc_inputs = ONE_BTC;
+        ctx.btc_inputs = Sats(ONE_BTC);
 
         let debt = 10_000 * ONE_ZKUSD;
         let collateral = ONE_BTC;
@@ -510,10 +1088,11 @@ mod tests {
         ctx.new_state.total_zkusd = 90_000 * ONE_ZKUSD;
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = expected_s;
+        ctx.new_state.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
 
         let action = StabilityPoolAction::Offset {
-            debt,
-            collateral,
+            debt: ZkUsd(debt),
+            collateral: Sats(collateral),
         };
 
         let result = validate(&mut ctx, &action);
@@ -525,15 +1104,11 @@ mod tests {
 
     #[test]
     fn test_offset_unauthorized() {
-        let mut ctx = create_test_context();
-        let attacker = [99u8; 32];
-
-        ctx.caller_app_id = Some(attacker);
-        ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
+        let mut ctx = SpCtx::with_deposit(100_000).mutate(|ctx| ctx.caller_app_id = Some([99u8; 32])).build();
 
         let action = StabilityPoolAction::Offset {
-            debt: 10_000 * ONE_ZKUSD,
-            collateral: ONE_BTC,
+            debt: ZkUsd(10_000 * ONE_ZKUSD),
+            collateral: Sats(ONE_BTC),
         };
 
         let result = validate(&mut ctx, &action);
@@ -544,14 +1119,14 @@ mod tests {
 
     #[test]
     fn test_offset_updates_p_value_correctly() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
         ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
         ctx.state.product_p = SCALE_FACTOR;
         ctx.state.sum_s = 0;
-        ctx.btc_inputs = ONE_BTC;
+        ctx.btc_inputs = Sats(ONE_BTC);
 
         let debt = 20_000 * ONE_ZKUSD; // 20% of pool
         let collateral = ONE_BTC;
@@ -566,8 +1141,9 @@ mod tests {
         ctx.new_state.total_zkusd = 80_000 * ONE_ZKUSD;
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = expected_s;
+        ctx.new_state.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Offset with correct P should succeed: {:?}", result);
@@ -575,7 +1151,7 @@ mod tests {
 
     #[test]
     fn test_offset_updates_s_value_correctly() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
@@ -585,7 +1161,7 @@ mod tests {
 
         let debt = 10_000 * ONE_ZKUSD; // 20% of pool
         let collateral = 2 * ONE_BTC;
-        ctx.btc_inputs = collateral;
+        ctx.btc_inputs = Sats(collateral);
 
         // Calculate expected S increase
         let s_increase = (collateral as u128) * SCALE_FACTOR / (50_000 * ONE_ZKUSD) as u128;
@@ -597,8 +1173,9 @@ mod tests {
         ctx.new_state.total_zkusd = 40_000 * ONE_ZKUSD;
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = expected_s;
+        ctx.new_state.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Offset with correct S should succeed: {:?}", result);
@@ -607,7 +1184,7 @@ mod tests {
     #[test]
     fn test_multiple_offsets_compound_p_value() {
         // Test that multiple liquidations properly compound P
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
@@ -618,7 +1195,7 @@ mod tests {
 
         let debt = 10_000 * ONE_ZKUSD; // 10% of remaining pool
         let collateral = ONE_BTC / 2;
-        ctx.btc_inputs = collateral;
+        ctx.btc_inputs = Sats(collateral);
 
         // New P = old_P * (1 - 10%) = 0.8 * 0.9 = 0.72
         let debt_ratio = (debt as u128) * SCALE_FACTOR / (100_000 * ONE_ZKUSD) as u128;
@@ -630,8 +1207,9 @@ mod tests {
         ctx.new_state.total_zkusd = 90_000 * ONE_ZKUSD;
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = expected_s;
+        ctx.new_state.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Compounded P offset should succeed: {:?}", result);
@@ -641,7 +1219,7 @@ mod tests {
 
     #[test]
     fn test_deposit_to_existing_deposit_compounds() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let depositor = [1u8; 32];
 
         // Existing deposit that has been compounded (P reduced)
@@ -653,6 +1231,8 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
         };
 
         // P has been reduced by liquidations (90% remaining)
@@ -666,7 +1246,7 @@ mod tests {
         let expected_total = compounded + new_amount;
 
         ctx.signer = depositor;
-        ctx.zkusd_inputs = new_amount;
+        ctx.zkusd_inputs = ZkUsd(new_amount);
         ctx.new_state.total_zkusd = ctx.state.total_zkusd + new_amount;
 
         ctx.new_deposit = Some(StabilityDeposit {
@@ -677,9 +1257,11 @@ mod tests {
             snapshot_epoch: ctx.state.current_epoch,
             snapshot_scale: ctx.state.current_scale,
             last_updated: 100,
+            frontend_tag: None,
+            kickback_bps: 0,
         });
 
-        let action = StabilityPoolAction::Deposit { amount: new_amount };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(new_amount) };
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Deposit to existing should compound: {:?}", result);
@@ -687,9 +1269,9 @@ mod tests {
 
     #[test]
     fn test_deposit_zero_amount_fails() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
 
-        let action = StabilityPoolAction::Deposit { amount: 0 };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(0) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::ZeroAmount)));
@@ -697,7 +1279,7 @@ mod tests {
 
     #[test]
     fn test_deposit_overflow_protection() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let depositor = [1u8; 32];
 
         // Existing very large deposit
@@ -709,14 +1291,16 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
         };
 
         ctx.state.total_zkusd = u64::MAX - 1000;
         ctx.deposit = Some(existing_deposit);
         ctx.signer = depositor;
-        ctx.zkusd_inputs = 2000; // Would cause overflow
+        ctx.zkusd_inputs = ZkUsd(2000); // Would cause overflow
 
-        let action = StabilityPoolAction::Deposit { amount: 2000 };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(2000) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::Overflow)));
@@ -726,23 +1310,9 @@ mod tests {
 
     #[test]
     fn test_withdraw_zero_amount_fails() {
-        let mut ctx = create_test_context();
-        let depositor = [1u8; 32];
-
-        let deposit = StabilityDeposit {
-            owner: depositor,
-            initial_value: 10_000 * ONE_ZKUSD,
-            snapshot_p: SCALE_FACTOR,
-            snapshot_s: 0,
-            snapshot_epoch: 0,
-            snapshot_scale: 0,
-            last_updated: 50,
-        };
+        let mut ctx = SpCtx::with_deposit(10_000).build();
 
-        ctx.deposit = Some(deposit);
-        ctx.signer = depositor;
-
-        let action = StabilityPoolAction::Withdraw { amount: 0 };
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(0) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::ZeroAmount)));
@@ -750,27 +1320,11 @@ mod tests {
 
     #[test]
     fn test_withdraw_more_than_compounded_value_fails() {
-        let mut ctx = create_test_context();
-        let depositor = [1u8; 32];
-
-        let deposit = StabilityDeposit {
-            owner: depositor,
-            initial_value: 10_000 * ONE_ZKUSD,
-            snapshot_p: SCALE_FACTOR,
-            snapshot_s: 0,
-            snapshot_epoch: 0,
-            snapshot_scale: 0,
-            last_updated: 50,
-        };
-
-        // P reduced to 50%, so compounded value is 5,000
-        ctx.state.product_p = SCALE_FACTOR / 2;
-        ctx.state.total_zkusd = 5_000 * ONE_ZKUSD;
-        ctx.deposit = Some(deposit);
-        ctx.signer = depositor;
+        // P halved, so the compounded value is 5,000
+        let mut ctx = SpCtx::with_deposit(10_000).after_offset(pct(50)).build();
 
         // Try to withdraw more than compounded value
-        let action = StabilityPoolAction::Withdraw { amount: 8_000 * ONE_ZKUSD };
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(8_000 * ONE_ZKUSD) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::InsufficientBalance { .. })));
@@ -778,24 +1332,10 @@ mod tests {
 
     #[test]
     fn test_withdraw_not_owner_fails() {
-        let mut ctx = create_test_context();
-        let owner = [1u8; 32];
         let attacker = [99u8; 32];
+        let mut ctx = SpCtx::with_deposit(10_000).mutate(move |ctx| ctx.signer = attacker).build();
 
-        let deposit = StabilityDeposit {
-            owner,
-            initial_value: 10_000 * ONE_ZKUSD,
-            snapshot_p: SCALE_FACTOR,
-            snapshot_s: 0,
-            snapshot_epoch: 0,
-            snapshot_scale: 0,
-            last_updated: 50,
-        };
-
-        ctx.deposit = Some(deposit);
-        ctx.signer = attacker; // Not the owner
-
-        let action = StabilityPoolAction::Withdraw { amount: 1_000 * ONE_ZKUSD };
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(1_000 * ONE_ZKUSD) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::Unauthorized { .. })));
@@ -805,22 +1345,8 @@ mod tests {
 
     #[test]
     fn test_claim_btc_no_rewards_fails() {
-        let mut ctx = create_test_context();
-        let depositor = [1u8; 32];
-
-        let deposit = StabilityDeposit {
-            owner: depositor,
-            initial_value: 10_000 * ONE_ZKUSD,
-            snapshot_p: SCALE_FACTOR,
-            snapshot_s: 0, // No gains yet
-            snapshot_epoch: 0,
-            snapshot_scale: 0,
-            last_updated: 50,
-        };
-
-        ctx.state.sum_s = 0; // S hasn't increased
-        ctx.deposit = Some(deposit);
-        ctx.signer = depositor;
+        // No offset yet, so S hasn't increased
+        let mut ctx = SpCtx::with_deposit(10_000).build();
 
         let action = StabilityPoolAction::ClaimBtc;
         let result = validate(&mut ctx, &action);
@@ -830,23 +1356,12 @@ mod tests {
 
     #[test]
     fn test_claim_btc_not_owner_fails() {
-        let mut ctx = create_test_context();
-        let owner = [1u8; 32];
+        // The offset leaves the deposit with BTC to claim
         let attacker = [99u8; 32];
-
-        let deposit = StabilityDeposit {
-            owner,
-            initial_value: 10_000 * ONE_ZKUSD,
-            snapshot_p: SCALE_FACTOR,
-            snapshot_s: 0,
-            snapshot_epoch: 0,
-            snapshot_scale: 0,
-            last_updated: 50,
-        };
-
-        ctx.state.sum_s = SCALE_FACTOR; // Has rewards
-        ctx.deposit = Some(deposit);
-        ctx.signer = attacker;
+        let mut ctx = SpCtx::with_deposit(10_000)
+            .after_offset(pct(10))
+            .mutate(move |ctx| ctx.signer = attacker)
+            .build();
 
         let action = StabilityPoolAction::ClaimBtc;
         let result = validate(&mut ctx, &action);
@@ -854,21 +1369,464 @@ mod tests {
         assert!(matches!(result, Err(ZkUsdError::Unauthorized { .. })));
     }
 
+    /// Depositor of 10k zkUSD with 10k units of pending BTC gain
+    fn rewarded_context() -> (StabilityPoolContext, u64) {
+        let mut ctx = SpCtx::new().build();
+        let depositor = [1u8; 32];
+        ctx.state.total_zkusd = 10_000 * ONE_ZKUSD;
+        ctx.state.sum_s = SCALE_FACTOR;
+        let deposit = consumed_deposit(depositor, 10_000 * ONE_ZKUSD, 0);
+        ctx.new_deposit = Some(StabilityDeposit { snapshot_s: SCALE_FACTOR, ..deposit.clone() });
+        ctx.deposit = Some(deposit);
+        ctx.signer = depositor;
+
+        let gain = get_pending_btc(ctx.deposit.as_ref().unwrap(), &ctx.state).unwrap();
+        (ctx, gain)
+    }
+
+    #[test]
+    fn test_withdraw_requires_exact_btc_gain() {
+        let (mut ctx, gain) = rewarded_context();
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(1_000 * ONE_ZKUSD) };
+        ctx.zkusd_outputs = ZkUsd(1_000 * ONE_ZKUSD);
+
+        ctx.btc_outputs = Sats(gain);
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        // Dropping the gain, or paying out more than was earned, is rejected
+        for btc_outputs in [0, gain - 1, gain + 1] {
+            ctx.btc_outputs = Sats(btc_outputs);
+            assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+        }
+    }
+
+    #[test]
+    fn test_withdraw_requires_exact_zkusd_output() {
+        let (mut ctx, gain) = rewarded_context();
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(1_000 * ONE_ZKUSD) };
+        ctx.btc_outputs = Sats(gain);
+
+        for zkusd_outputs in [999 * ONE_ZKUSD, 1_001 * ONE_ZKUSD] {
+            ctx.zkusd_outputs = ZkUsd(zkusd_outputs);
+            assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+        }
+    }
+
+    #[test]
+    fn test_claim_btc_requires_exact_gain() {
+        let (mut ctx, gain) = rewarded_context();
+
+        ctx.btc_outputs = Sats(gain);
+        assert!(validate(&mut ctx, &StabilityPoolAction::ClaimBtc).is_ok());
+
+        for btc_outputs in [gain - 1, gain + 1] {
+            ctx.btc_outputs = Sats(btc_outputs);
+            assert_eq!(
+                validate(&mut ctx, &StabilityPoolAction::ClaimBtc),
+                Err(ZkUsdError::InvalidStateTransition)
+            );
+        }
+    }
+
+    const FRONTEND: Address = [0xF1; 32];
+
+    /// P2TR scriptPubKey a frontend's share is paid to
+    fn p2tr(key: [u8; 32]) -> Vec<u8> {
+        [&[0x51, 0x20][..], &key].concat()
+    }
+
+    /// `frontend` registered at `kickback_bps`, paid to a P2TR output
+    fn registration(frontend: Address, kickback_bps: u64) -> RegisteredFrontend {
+        RegisteredFrontend { frontend, kickback_bps, payout_script: p2tr(frontend) }
+    }
+
+    /// `ctx`'s deposit, before and after, tagged with `FRONTEND` at 90%
+    fn tag_deposit(ctx: &mut StabilityPoolContext) {
+        for deposit in [&mut ctx.deposit, &mut ctx.new_deposit].into_iter().flatten() {
+            deposit.frontend_tag = Some(FRONTEND);
+            deposit.kickback_bps = 9_000;
+        }
+    }
+
+    #[test]
+    fn test_tagged_deposit_splits_gain_with_frontend() {
+        let (mut ctx, gain) = rewarded_context();
+        tag_deposit(&mut ctx);
+        let split = ctx.deposit.as_ref().unwrap().split_btc_gain(gain).unwrap();
+        assert_eq!(split, BtcGainSplit { depositor: gain * 9 / 10, frontend: gain - gain * 9 / 10 });
+
+        // The depositor keeps 90%, the frontend is paid the rest
+        ctx.btc_outputs = Sats(gain);
+        ctx.frontend_btc_outputs = Sats(split.frontend);
+        let mut claim = ctx.clone();
+        assert_eq!(validate(&mut claim, &StabilityPoolAction::ClaimBtc), Ok(()));
+        assert!(claim.events.events().contains(&ZkUsdEvent::FrontendKickbackPaid {
+            depositor: [1u8; 32],
+            frontend: FRONTEND,
+            depositor_btc: split.depositor,
+            frontend_btc: split.frontend,
+            block_height: 100,
+        }));
+
+        let action = StabilityPoolAction::Withdraw { amount: ZkUsd(1_000 * ONE_ZKUSD) };
+        ctx.zkusd_outputs = ZkUsd(1_000 * ONE_ZKUSD);
+        assert_eq!(validate(&mut ctx.clone(), &action), Ok(()));
+
+        // Shorting the frontend, or paying it the depositor's share
+        for frontend_btc in [0, split.frontend - 1, split.frontend + 1, split.depositor] {
+            ctx.frontend_btc_outputs = Sats(frontend_btc);
+            assert_eq!(validate(&mut ctx.clone(), &action), Err(ZkUsdError::InvalidStateTransition));
+            assert_eq!(
+                validate(&mut ctx.clone(), &StabilityPoolAction::ClaimBtc),
+                Err(ZkUsdError::InvalidStateTransition)
+            );
+        }
+    }
+
+    #[test]
+    fn test_untagged_deposit_keeps_whole_gain() {
+        let (mut ctx, gain) = rewarded_context();
+        let deposit = ctx.deposit.clone().unwrap();
+        assert_eq!(deposit.split_btc_gain(gain), Ok(BtcGainSplit { depositor: gain, frontend: 0 }));
+
+        // A rate recorded without a tag pays nothing out either
+        let untagged_rate = StabilityDeposit { kickback_bps: 5_000, ..deposit };
+        assert_eq!(untagged_rate.split_btc_gain(gain), Ok(BtcGainSplit { depositor: gain, frontend: 0 }));
+
+        ctx.btc_outputs = Sats(gain);
+        let mut claim = ctx.clone();
+        assert_eq!(validate(&mut claim, &StabilityPoolAction::ClaimBtc), Ok(()));
+        assert!(!claim.events.events().iter().any(|event| matches!(event, ZkUsdEvent::FrontendKickbackPaid { .. })));
+
+        ctx.frontend_btc_outputs = Sats(1);
+        assert_eq!(validate(&mut ctx, &StabilityPoolAction::ClaimBtc), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    /// Top-up of 1k zkUSD to the signer's 10k deposit
+    fn top_up_context() -> StabilityPoolContext {
+        SpCtx::with_deposit(10_000)
+            .mutate(|ctx| {
+                ctx.zkusd_inputs = ZkUsd(1_000 * ONE_ZKUSD);
+                ctx.new_state.total_zkusd += 1_000 * ONE_ZKUSD;
+                let deposit = ctx.deposit.clone().unwrap();
+                ctx.new_deposit = Some(StabilityDeposit { initial_value: 11_000 * ONE_ZKUSD, ..deposit });
+                ctx.config.frontends = Vec::from([registration(FRONTEND, 9_000), registration([0xF2; 32], 8_000)]);
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_frontend_tag_kept_by_top_ups() {
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(1_000 * ONE_ZKUSD) };
+        let mut ctx = top_up_context();
+        tag_deposit(&mut ctx);
+        assert_eq!(validate(&mut ctx.clone(), &action), Ok(()));
+
+        // Retagging to another registered frontend, dropping the tag, or
+        // changing the rate
+        let retags: [fn(&mut StabilityDeposit); 3] = [
+            |deposit| (deposit.frontend_tag, deposit.kickback_bps) = (Some([0xF2; 32]), 8_000),
+            |deposit| (deposit.frontend_tag, deposit.kickback_bps) = (None, 0),
+            |deposit| deposit.kickback_bps = 10_000,
+        ];
+        for retag in retags {
+            let mut ctx = ctx.clone();
+            retag(ctx.new_deposit.as_mut().unwrap());
+            assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+        }
+
+        // An untagged deposit cannot be tagged later either
+        let mut ctx = top_up_context();
+        ctx.new_deposit.as_mut().unwrap().frontend_tag = Some(FRONTEND);
+        ctx.new_deposit.as_mut().unwrap().kickback_bps = 9_000;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_first_deposit_requires_registered_frontend() {
+        let amount = 1_000 * ONE_ZKUSD;
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(amount) };
+        let first_deposit = |frontend_tag: Option<Address>, kickback_bps: u64| {
+            let mut ctx = top_up_context();
+            ctx.deposit = None;
+            ctx.zkusd_inputs = ZkUsd(amount);
+            ctx.new_state.total_zkusd = ctx.state.total_zkusd + amount;
+            let deposit = ctx.new_deposit.as_mut().unwrap();
+            (deposit.initial_value, deposit.frontend_tag, deposit.kickback_bps) = (amount, frontend_tag, kickback_bps);
+            ctx
+        };
+
+        assert_eq!(validate(&mut first_deposit(Some(FRONTEND), 9_000), &action), Ok(()));
+        assert_eq!(validate(&mut first_deposit(None, 0), &action), Ok(()));
+
+        // Unregistered frontend, or a rate other than the registered one
+        for (frontend, kickback_bps) in [([0xF3; 32], 9_000), (FRONTEND, 10_000)] {
+            assert_eq!(
+                validate(&mut first_deposit(Some(frontend), kickback_bps), &action),
+                Err(ZkUsdError::FrontendNotRegistered { frontend })
+            );
+        }
+        assert_eq!(validate(&mut first_deposit(None, 9_000), &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_register_frontend() {
+        let admin = [0xAD; 32];
+        let action =
+            StabilityPoolAction::RegisterFrontend { frontend: FRONTEND, kickback_bps: 9_000, payout_script: p2tr(FRONTEND) };
+        let ctx = SpCtx::with_deposit(10_000)
+            .mutate(move |ctx| {
+                ctx.config.admin = admin;
+                ctx.signer = admin;
+                let mut new_config = ctx.config.clone();
+                new_config.frontends.push(registration(FRONTEND, 9_000));
+                ctx.new_config = Some(new_config);
+            })
+            .build();
+
+        let mut registered = ctx.clone();
+        assert_eq!(validate(&mut registered, &action), Ok(()));
+        assert!(registered.events.events().contains(&ZkUsdEvent::FrontendRegistered {
+            frontend: FRONTEND,
+            kickback_bps: 9_000,
+            block_height: 100,
+        }));
+
+        // Signed by anyone but the admin
+        let mut other = ctx.clone();
+        other.signer = [1u8; 32];
+        assert_eq!(validate(&mut other, &action), Err(ZkUsdError::Unauthorized { expected: admin, actual: [1u8; 32] }));
+
+        // Config or pool state other than the frontend appended
+        let mut wrong_rate = ctx.clone();
+        wrong_rate.new_config.as_mut().unwrap().frontends[0].kickback_bps = 8_000;
+        let mut wrong_script = ctx.clone();
+        wrong_script.new_config.as_mut().unwrap().frontends[0].payout_script = p2tr([0xF2; 32]);
+        let mut pool_changed = ctx.clone();
+        pool_changed.new_state.total_zkusd += 1;
+        for mut ctx in [wrong_rate, wrong_script, pool_changed] {
+            assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+        }
+
+        // No payout script, or one longer than any standard output's
+        for payout_script in [Vec::new(), Vec::from([0x51; MAX_PAYOUT_SCRIPT_LEN + 1])] {
+            let unpayable = StabilityPoolAction::RegisterFrontend { frontend: FRONTEND, kickback_bps: 9_000, payout_script };
+            assert!(matches!(
+                validate(&mut ctx.clone(), &unpayable),
+                Err(ZkUsdError::InvalidInput { param: "payout_script", .. })
+            ));
+        }
+
+        // Above 100%, already registered, or no room left
+        let above =
+            StabilityPoolAction::RegisterFrontend { frontend: FRONTEND, kickback_bps: 10_001, payout_script: p2tr(FRONTEND) };
+        assert_eq!(
+            validate(&mut ctx.clone(), &above),
+            Err(ZkUsdError::ExceedsMaximum { amount: 10_001, maximum: BPS_DENOMINATOR })
+        );
+        let mut again = registered.clone();
+        again.config = again.new_config.clone().unwrap();
+        assert!(matches!(validate(&mut again, &action), Err(ZkUsdError::InvalidInput { param: "frontend", .. })));
+        let mut full = ctx;
+        full.config.frontends = (0..MAX_FRONTENDS as u8).map(|i| registration([i + 1; 32], 9_000)).collect();
+        assert_eq!(
+            validate(&mut full, &action),
+            Err(ZkUsdError::ExceedsMaximum { amount: MAX_FRONTENDS as u64 + 1, maximum: MAX_FRONTENDS as u64 })
+        );
+    }
+
+    #[test]
+    fn test_claim_btc_below_dust_rejected() {
+        // S grows by 100 sats per 10k zkUSD deposited
+        let (mut ctx, _) = rewarded_context();
+        ctx.state.sum_s = SCALE_FACTOR / 10_000_000_000;
+        ctx.new_deposit.as_mut().unwrap().snapshot_s = ctx.state.sum_s;
+        let gain = get_pending_btc(ctx.deposit.as_ref().unwrap(), &ctx.state).unwrap();
+        assert_eq!(gain, 100);
+
+        ctx.btc_outputs = Sats(gain);
+        assert_eq!(
+            validate(&mut ctx, &StabilityPoolAction::ClaimBtc),
+            Err(ZkUsdError::OutputBelowDust { amount: 100, dust_limit: DUST_LIMIT })
+        );
+    }
+
+    #[test]
+    fn test_claim_built_on_reorged_offset_rejected() {
+        let (mut ctx, gain) = rewarded_context();
+        ctx.btc_outputs = Sats(gain);
+        let built_on = StateRef::new(ctx.state.commitment(), ctx.block_height - 1);
+
+        ctx.based_on = Some(built_on);
+        assert!(validate(&mut ctx.clone(), &StabilityPoolAction::ClaimBtc).is_ok());
+
+        // A reorg replaced the offset the gain came from
+        ctx.state.sum_s -= 1;
+        assert_eq!(
+            validate(&mut ctx, &StabilityPoolAction::ClaimBtc),
+            Err(ZkUsdError::StaleStateReference { block_height: ctx.block_height - 1 })
+        );
+    }
+
+    #[test]
+    fn test_claim_btc_to_vault_requires_vault_manager() {
+        let (mut ctx, gain) = rewarded_context();
+        let action = StabilityPoolAction::ClaimBtcToVault { vault_id: [7u8; 32] };
+
+        // Without the VaultManager there is nowhere to route the BTC
+        assert_eq!(
+            validate(&mut ctx, &action),
+            Err(ZkUsdError::Unauthorized { expected: [2u8; 32], actual: [0u8; 32] })
+        );
+
+        ctx.caller_app_id = Some([2u8; 32]);
+        assert!(validate(&mut ctx, &action).is_ok());
+        assert!(ctx.events.events().contains(&ZkUsdEvent::BtcClaimedToVault {
+            depositor: [1u8; 32],
+            vault_id: [7u8; 32],
+            btc_amount: gain,
+            block_height: ctx.block_height,
+        }));
+    }
+
+    // ============ Dust Sweep Tests ============
+
+    /// Depositor sweeping a 10k zkUSD deposit liquidations wore down to 0.1
+    /// zkUSD, with its BTC gain still unclaimed
+    fn dust_context() -> (StabilityPoolContext, u64) {
+        let mut ctx = SpCtx::new().build();
+        let depositor = [1u8; 32];
+        ctx.state.total_zkusd = 5 * ONE_ZKUSD;
+        ctx.state.depositor_count = 3;
+        ctx.state.product_p = SCALE_FACTOR / 100_000;
+        ctx.state.sum_s = SCALE_FACTOR;
+        ctx.new_state = StabilityPoolState {
+            depositor_count: 2,
+            total_zkusd: 5 * ONE_ZKUSD - ONE_ZKUSD / 10,
+            protocol_dust: ONE_ZKUSD / 10,
+            ..ctx.state.clone()
+        };
+        ctx.deposit = Some(consumed_deposit(depositor, 10_000 * ONE_ZKUSD, 0));
+        ctx.signer = depositor;
+
+        let gain = get_pending_btc(ctx.deposit.as_ref().unwrap(), &ctx.state).unwrap();
+        ctx.btc_outputs = Sats(gain);
+        (ctx, gain)
+    }
+
+    #[test]
+    fn test_sweep_dust_deposit_pays_out_btc_gain() {
+        let (mut ctx, gain) = dust_context();
+        assert!(gain > 0);
+
+        let action = StabilityPoolAction::SweepDustDeposit { depositor: [1u8; 32] };
+        assert!(validate(&mut ctx, &action).is_ok());
+        assert!(ctx.events.events().contains(&ZkUsdEvent::DustDepositSwept {
+            depositor: [1u8; 32],
+            swept_by: [1u8; 32],
+            zkusd_dust: ONE_ZKUSD / 10,
+            btc_amount: gain,
+            block_height: ctx.block_height,
+        }));
+    }
+
+    #[test]
+    fn test_keeper_sweeps_only_without_gain() {
+        let action = StabilityPoolAction::SweepDustDeposit { depositor: [1u8; 32] };
+
+        // A keeper could direct the gain to itself
+        let (mut ctx, _) = dust_context();
+        ctx.signer = [9u8; 32];
+        assert_eq!(
+            validate(&mut ctx, &action),
+            Err(ZkUsdError::Unauthorized { expected: [1u8; 32], actual: [9u8; 32] })
+        );
+
+        // With the gain claimed, anyone may clean up
+        let (mut ctx, _) = dust_context();
+        ctx.signer = [9u8; 32];
+        let deposit = ctx.deposit.as_mut().unwrap();
+        deposit.snapshot_s = ctx.state.sum_s;
+        ctx.btc_outputs = Sats(0);
+        assert!(validate(&mut ctx, &action).is_ok());
+    }
+
+    #[test]
+    fn test_sweep_healthy_deposit_fails() {
+        let (mut ctx, _) = dust_context();
+        ctx.state.product_p = SCALE_FACTOR / 10_000;
+        ctx.new_state.product_p = ctx.state.product_p;
+        ctx.btc_outputs = Sats(get_pending_btc(ctx.deposit.as_ref().unwrap(), &ctx.state).unwrap());
+
+        let action = StabilityPoolAction::SweepDustDeposit { depositor: [1u8; 32] };
+        assert_eq!(
+            validate(&mut ctx, &action),
+            Err(ZkUsdError::InvalidAmount { amount: ONE_ZKUSD, reason: AmountErrorReason::TooLarge })
+        );
+    }
+
+    #[test]
+    fn test_sweep_dust_deposit_mismatches_rejected() {
+        let action = StabilityPoolAction::SweepDustDeposit { depositor: [1u8; 32] };
+
+        // Someone else's deposit
+        let (mut ctx, _) = dust_context();
+        let other = StabilityPoolAction::SweepDustDeposit { depositor: [2u8; 32] };
+        assert_eq!(validate(&mut ctx, &other), Err(ZkUsdError::DepositNotFound { user: [2u8; 32] }));
+
+        // Withholding the BTC gain
+        let (mut ctx, gain) = dust_context();
+        ctx.btc_outputs = Sats(gain - 1);
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // Paying the dust out
+        let (mut ctx, _) = dust_context();
+        ctx.zkusd_outputs = ZkUsd(ONE_ZKUSD / 10);
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // Keeping the deposit charm
+        let (mut ctx, _) = dust_context();
+        ctx.new_deposit = ctx.deposit.clone();
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // Keeping the depositor count
+        let (mut ctx, _) = dust_context();
+        ctx.new_state.depositor_count = 3;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // Leaving the dust to the other depositors
+        let (mut ctx, _) = dust_context();
+        ctx.new_state.total_zkusd = ctx.state.total_zkusd;
+        ctx.new_state.protocol_dust = 0;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // Crediting the protocol more than the dust
+        let (mut ctx, _) = dust_context();
+        ctx.new_state.protocol_dust += 1;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_only_sweep_moves_protocol_dust() {
+        let mut ctx = SpCtx::with_deposit(10_000).build();
+        ctx.new_state.protocol_dust = ONE_ZKUSD;
+        let withdraw = StabilityPoolAction::Withdraw { amount: ZkUsd(1_000 * ONE_ZKUSD) };
+        assert_eq!(validate(&mut ctx, &withdraw), Err(ZkUsdError::InvalidStateTransition));
+    }
+
     // ============ Offset Edge Cases ============
 
     #[test]
     fn test_offset_insufficient_pool_balance_fails() {
-        let mut ctx = create_test_context();
-        let vault_manager = [2u8; 32];
-
-        ctx.caller_app_id = Some(vault_manager);
-        ctx.state.total_zkusd = 5_000 * ONE_ZKUSD;
-        ctx.btc_inputs = ONE_BTC;
+        let mut ctx = SpCtx::with_deposit(5_000)
+            .called_by_vault_manager()
+            .mutate(|ctx| ctx.btc_inputs = Sats(ONE_BTC))
+            .build();
 
         // Try to offset more debt than pool has
         let action = StabilityPoolAction::Offset {
-            debt: 10_000 * ONE_ZKUSD,
-            collateral: ONE_BTC,
+            debt: ZkUsd(10_000 * ONE_ZKUSD),
+            collateral: Sats(ONE_BTC),
         };
         let result = validate(&mut ctx, &action);
 
@@ -877,18 +1835,15 @@ mod tests {
 
     #[test]
     fn test_offset_insufficient_collateral_fails() {
-        let mut ctx = create_test_context();
-        let vault_manager = [2u8; 32];
-
-        ctx.caller_app_id = Some(vault_manager);
-        ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
-        ctx.state.product_p = SCALE_FACTOR;
-        ctx.btc_inputs = ONE_BTC / 2; // Only 0.5 BTC
+        let mut ctx = SpCtx::with_deposit(100_000)
+            .called_by_vault_manager()
+            .mutate(|ctx| ctx.btc_inputs = Sats(ONE_BTC / 2)) // Only 0.5 BTC
+            .build();
 
         // Claim 1 BTC collateral but only have 0.5
         let action = StabilityPoolAction::Offset {
-            debt: 10_000 * ONE_ZKUSD,
-            collateral: ONE_BTC,
+            debt: ZkUsd(10_000 * ONE_ZKUSD),
+            collateral: Sats(ONE_BTC),
         };
         let result = validate(&mut ctx, &action);
 
@@ -897,21 +1852,80 @@ mod tests {
 
     #[test]
     fn test_offset_no_caller_app_id_fails() {
-        let mut ctx = create_test_context();
-
-        ctx.caller_app_id = None; // No caller
-        ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
-        ctx.btc_inputs = ONE_BTC;
+        let mut ctx = SpCtx::with_deposit(100_000).mutate(|ctx| ctx.btc_inputs = Sats(ONE_BTC)).build();
 
         let action = StabilityPoolAction::Offset {
-            debt: 10_000 * ONE_ZKUSD,
-            collateral: ONE_BTC,
+            debt: ZkUsd(10_000 * ONE_ZKUSD),
+            collateral: Sats(ONE_BTC),
         };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::Unauthorized { .. })));
     }
 
+    /// Offset of `debt` from a pool of 100k that has already absorbed 10%,
+    /// with the golden output state
+    fn offset_after_offset(debt: u64) -> (StabilityPoolContext, StabilityPoolAction) {
+        let collateral = offset_collateral(debt);
+        let ctx = SpCtx::with_deposit(100_000)
+            .after_offset(pct(10))
+            .called_by_vault_manager()
+            .mutate(move |ctx| {
+                ctx.btc_inputs = Sats(collateral);
+                ctx.new_state = state_after_offset(&ctx.state, debt, collateral, ctx.block_height);
+            })
+            .build();
+        (ctx, StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) })
+    }
+
+    #[test]
+    fn test_offset_without_caller_rejected_even_when_otherwise_valid() {
+        let (mut ctx, action) = offset_after_offset(20_000 * ONE_ZKUSD);
+        assert_eq!(validate(&mut ctx.clone(), &action), Ok(()));
+
+        // Only the missing caller is wrong, and it is named as the zero app_id
+        ctx.caller_app_id = None;
+        assert_eq!(
+            validate(&mut ctx, &action),
+            Err(ZkUsdError::Unauthorized { expected: VAULT_MANAGER, actual: [0u8; 32] })
+        );
+        assert!(!ctx.events.has_events());
+    }
+
+    #[test]
+    fn test_offset_after_offset_matches_golden_state() {
+        let (mut ctx, action) = offset_after_offset(20_000 * ONE_ZKUSD);
+        assert_eq!(validate(&mut ctx, &action), Ok(()));
+
+        // 90k absorbing 20k leaves 70k, and the deposit that was the whole
+        // pool bears the whole loss
+        assert_eq!(ctx.new_state.total_zkusd, 70_000 * ONE_ZKUSD);
+        assert_eq!(ctx.new_state.recent_offsets.len(), 2);
+        let deposit = ctx.deposit.as_ref().unwrap();
+        let lost = deposit.initial_value - get_compounded_value(deposit, &ctx.new_state);
+        assert!(lost.abs_diff(30_000 * ONE_ZKUSD) <= 1, "lost {}", lost);
+
+        // The pool's tick reports what it holds after both offsets
+        match ctx.events.events().last() {
+            Some(ZkUsdEvent::HealthTick(tick)) => assert_eq!(tick.sp_total_zkusd, 70_000 * ONE_ZKUSD),
+            other => panic!("expected a health tick, got {:?}", other),
+        }
+
+        // Any departure from the golden state is rejected
+        let golden = ctx.new_state.clone();
+        for corrupt in [
+            |s: &mut StabilityPoolState| s.total_zkusd += 1,
+            |s: &mut StabilityPoolState| s.product_p -= 1,
+            |s: &mut StabilityPoolState| s.sum_s += 1,
+            |s: &mut StabilityPoolState| s.recent_offsets.truncate(1),
+        ] {
+            let (mut ctx, action) = offset_after_offset(20_000 * ONE_ZKUSD);
+            ctx.new_state = golden.clone();
+            corrupt(&mut ctx.new_state);
+            assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+        }
+    }
+
     // ============ Helper Function Tests ============
 
     #[test]
@@ -924,6 +1938,8 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
         };
 
         let mut state = StabilityPoolState::new();
@@ -943,13 +1959,15 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
         };
 
         let mut state = StabilityPoolState::new();
         // S increased by SCALE_FACTOR = 1 unit per token
         state.sum_s = SCALE_FACTOR;
 
-        let pending = get_pending_btc(&deposit, &state);
+        let pending = get_pending_btc(&deposit, &state).unwrap();
         // gain = 10,000 * SCALE_FACTOR / SCALE_FACTOR = 10,000
         assert_eq!(pending, 10_000 * ONE_ZKUSD);
     }
@@ -958,7 +1976,7 @@ mod tests {
 
     #[test]
     fn test_offset_full_liquidation_flow() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         // Initial pool: 100k zkUSD, P = 1, S = 0
@@ -970,7 +1988,7 @@ mod tests {
         // Liquidation: 50k debt absorbed, 0.6 BTC distributed
         let debt = 50_000 * ONE_ZKUSD;
         let collateral = 60_000_000; // 0.6 BTC
-        ctx.btc_inputs = collateral;
+        ctx.btc_inputs = Sats(collateral);
 
         // Expected P: P * (1 - 0.5) = 0.5 * SCALE_FACTOR
         let debt_ratio = (debt as u128) * SCALE_FACTOR / (100_000 * ONE_ZKUSD) as u128;
@@ -982,24 +2000,161 @@ mod tests {
         ctx.new_state.total_zkusd = 50_000 * ONE_ZKUSD;
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = expected_s;
+        ctx.new_state.record_offset(OffsetSample { debt, collateral, block: ctx.block_height });
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(result.is_ok(), "Full liquidation flow should succeed: {:?}", result);
         assert!(ctx.events.has_events(), "Should emit LiquidationOffset event");
     }
 
+    // ============ Epoch Rollover Tests ============
+
+    /// Offset that empties a 100k pool, as seen by the validator
+    fn emptying_offset_context(collateral: u64) -> StabilityPoolContext {
+        let mut ctx = SpCtx::new().build();
+        ctx.caller_app_id = Some([2u8; 32]);
+        ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
+        ctx.btc_inputs = Sats(collateral);
+
+        let final_s = (collateral as u128) * SCALE_FACTOR / (100_000 * ONE_ZKUSD) as u128;
+        ctx.new_state.current_epoch = 1;
+        ctx.new_state.epoch_snapshots = vec![EpochSnapshot {
+            epoch: 0,
+            final_scale: 0,
+            final_s_at_scale: final_s,
+            final_s_at_scale_plus_one: 0,
+        }];
+        ctx.new_state.record_offset(OffsetSample {
+            debt: 100_000 * ONE_ZKUSD,
+            collateral,
+            block: ctx.block_height,
+        });
+        ctx
+    }
+
+    fn consumed_deposit(owner: Address, initial_value: u64, epoch: u64) -> StabilityDeposit {
+        StabilityDeposit {
+            owner,
+            initial_value,
+            snapshot_p: SCALE_FACTOR,
+            snapshot_s: 0,
+            snapshot_epoch: epoch,
+            snapshot_scale: 0,
+            last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
+        }
+    }
+
+    #[test]
+    fn test_offset_emptying_pool_rolls_epoch() {
+        let mut ctx = emptying_offset_context(ONE_BTC);
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(100_000 * ONE_ZKUSD), collateral: Sats(ONE_BTC) };
+
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        // Rolling over without recording the closed epoch is rejected
+        ctx.new_state.epoch_snapshots.clear();
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // So is keeping the old epoch open
+        let mut ctx = emptying_offset_context(ONE_BTC);
+        ctx.new_state.current_epoch = 0;
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_consumed_deposit_claims_btc_after_rollover() {
+        let depositor = [1u8; 32];
+        let mut offset_ctx = emptying_offset_context(ONE_BTC);
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(100_000 * ONE_ZKUSD), collateral: Sats(ONE_BTC) };
+        validate(&mut offset_ctx, &action).unwrap();
+
+        // Depositor held a quarter of the emptied pool
+        let mut ctx = SpCtx::new().build();
+        ctx.state = offset_ctx.new_state;
+        ctx.deposit = Some(consumed_deposit(depositor, 25_000 * ONE_ZKUSD, 0));
+        ctx.signer = depositor;
+
+        assert_eq!(get_compounded_value(ctx.deposit.as_ref().unwrap(), &ctx.state), 0);
+        assert_eq!(get_pending_btc(ctx.deposit.as_ref().unwrap(), &ctx.state), Ok(ONE_BTC / 4));
+
+        // Withdrawal returns no zkUSD but the pro-rata BTC
+        ctx.btc_outputs = Sats(ONE_BTC / 4);
+        let result = validate(&mut ctx, &StabilityPoolAction::Withdraw { amount: ZkUsd(0) });
+        assert!(result.is_ok(), "Should pay out closed-epoch BTC: {:?}", result);
+
+        ctx.btc_outputs = Sats(ONE_BTC / 4 - 1);
+        assert_eq!(
+            validate(&mut ctx, &StabilityPoolAction::Withdraw { amount: ZkUsd(0) }),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+
+        // Claiming instead must move the emptied deposit into the current epoch
+        ctx.btc_outputs = Sats(ONE_BTC / 4);
+        ctx.new_deposit = Some(consumed_deposit(depositor, 0, 1));
+        assert!(validate(&mut ctx, &StabilityPoolAction::ClaimBtc).is_ok());
+
+        ctx.new_deposit = Some(consumed_deposit(depositor, 0, 0));
+        assert_eq!(
+            validate(&mut ctx, &StabilityPoolAction::ClaimBtc),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+    }
+
+    #[test]
+    fn test_evicted_epoch_claim_fails() {
+        let depositor = [1u8; 32];
+        let mut ctx = SpCtx::new().build();
+        ctx.state.current_epoch = 2;
+        // Epoch 0 has been evicted, only epoch 1 remains
+        ctx.state.epoch_snapshots = vec![EpochSnapshot {
+            epoch: 1,
+            final_scale: 0,
+            final_s_at_scale: SCALE_FACTOR,
+            final_s_at_scale_plus_one: 0,
+        }];
+        ctx.deposit = Some(consumed_deposit(depositor, 10_000 * ONE_ZKUSD, 0));
+        ctx.signer = depositor;
+        ctx.btc_outputs = Sats(ONE_BTC);
+
+        let expected = Err(ZkUsdError::EpochSnapshotEvicted { epoch: 0 });
+        assert_eq!(validate(&mut ctx, &StabilityPoolAction::ClaimBtc), expected);
+        assert_eq!(validate(&mut ctx, &StabilityPoolAction::Withdraw { amount: ZkUsd(0) }), expected);
+    }
+
+    #[test]
+    fn test_epoch_snapshots_bounded() {
+        use zkusd_common::constants::stability_pool::MAX_EPOCH_SNAPSHOTS;
+
+        let mut state = StabilityPoolState::new();
+        for epoch in 0..(MAX_EPOCH_SNAPSHOTS as u64 + 2) {
+            state.record_epoch_snapshot(EpochSnapshot {
+                epoch,
+                final_scale: 0,
+                final_s_at_scale: epoch as u128,
+                final_s_at_scale_plus_one: 0,
+            });
+        }
+
+        assert_eq!(state.epoch_snapshots.len(), MAX_EPOCH_SNAPSHOTS);
+        assert!(state.epoch_snapshot(0).is_none());
+        assert!(state.epoch_snapshot(1).is_none());
+        assert!(state.epoch_snapshot(2).is_some());
+    }
+
     // ============ State Transition Validation Tests ============
 
     #[test]
     fn test_deposit_wrong_total_zkusd_fails() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let depositor = [1u8; 32];
         let amount = 10_000 * ONE_ZKUSD;
 
         ctx.signer = depositor;
-        ctx.zkusd_inputs = amount;
+        ctx.zkusd_inputs = ZkUsd(amount);
         ctx.new_state.total_zkusd = 5_000 * ONE_ZKUSD; // Wrong! Should be 10k
 
         ctx.new_deposit = Some(StabilityDeposit {
@@ -1010,9 +2165,11 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 100,
+            frontend_tag: None,
+            kickback_bps: 0,
         });
 
-        let action = StabilityPoolAction::Deposit { amount };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(amount) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::InvalidStateTransition)));
@@ -1020,12 +2177,12 @@ mod tests {
 
     #[test]
     fn test_deposit_wrong_initial_value_fails() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let depositor = [1u8; 32];
         let amount = 10_000 * ONE_ZKUSD;
 
         ctx.signer = depositor;
-        ctx.zkusd_inputs = amount;
+        ctx.zkusd_inputs = ZkUsd(amount);
         ctx.new_state.total_zkusd = amount;
 
         ctx.new_deposit = Some(StabilityDeposit {
@@ -1036,9 +2193,11 @@ mod tests {
             snapshot_epoch: 0,
             snapshot_scale: 0,
             last_updated: 100,
+            frontend_tag: None,
+            kickback_bps: 0,
         });
 
-        let action = StabilityPoolAction::Deposit { amount };
+        let action = StabilityPoolAction::Deposit { amount: ZkUsd(amount) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::InvalidStateTransition)));
@@ -1046,14 +2205,14 @@ mod tests {
 
     #[test]
     fn test_offset_wrong_p_value_fails() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
         ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
         ctx.state.product_p = SCALE_FACTOR;
         ctx.state.sum_s = 0;
-        ctx.btc_inputs = ONE_BTC;
+        ctx.btc_inputs = Sats(ONE_BTC);
 
         let debt = 10_000 * ONE_ZKUSD;
         let collateral = ONE_BTC;
@@ -1063,7 +2222,7 @@ mod tests {
         ctx.new_state.product_p = SCALE_FACTOR; // Wrong! Should be 0.9 * SCALE_FACTOR
         ctx.new_state.sum_s = (collateral as u128) * SCALE_FACTOR / (100_000 * ONE_ZKUSD) as u128;
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::InvalidStateTransition)));
@@ -1071,14 +2230,14 @@ mod tests {
 
     #[test]
     fn test_offset_wrong_s_value_fails() {
-        let mut ctx = create_test_context();
+        let mut ctx = SpCtx::new().build();
         let vault_manager = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
         ctx.state.total_zkusd = 100_000 * ONE_ZKUSD;
         ctx.state.product_p = SCALE_FACTOR;
         ctx.state.sum_s = 0;
-        ctx.btc_inputs = ONE_BTC;
+        ctx.btc_inputs = Sats(ONE_BTC);
 
         let debt = 10_000 * ONE_ZKUSD;
         let collateral = ONE_BTC;
@@ -1090,9 +2249,267 @@ mod tests {
         ctx.new_state.product_p = expected_p;
         ctx.new_state.sum_s = 999; // Wrong S value!
 
-        let action = StabilityPoolAction::Offset { debt, collateral };
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) };
         let result = validate(&mut ctx, &action);
 
         assert!(matches!(result, Err(ZkUsdError::InvalidStateTransition)));
     }
+
+    #[test]
+    fn test_offset_must_record_sample() {
+        let mut ctx = emptying_offset_context(ONE_BTC);
+        let action = StabilityPoolAction::Offset { debt: ZkUsd(100_000 * ONE_ZKUSD), collateral: Sats(ONE_BTC) };
+        assert!(validate(&mut ctx, &action).is_ok());
+
+        ctx.new_state.recent_offsets.clear();
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+
+        // A sample misreporting the offset is rejected too
+        ctx.new_state.record_offset(OffsetSample { debt: 1, collateral: ONE_BTC, block: ctx.block_height });
+        assert_eq!(validate(&mut ctx, &action), Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_offset_samples_bounded() {
+        use zkusd_common::constants::stability_pool::MAX_OFFSET_SAMPLES;
+
+        let mut state = StabilityPoolState::new();
+        for block in 0..(MAX_OFFSET_SAMPLES as u64 + 2) {
+            state.record_offset(OffsetSample { debt: ONE_ZKUSD, collateral: 1, block });
+        }
+
+        assert_eq!(state.recent_offsets.len(), MAX_OFFSET_SAMPLES);
+        assert_eq!(state.recent_offsets[0].block, 2);
+    }
+
+    // ============ Pool Statistics Tests ============
+
+    #[test]
+    fn test_pool_share_of_ten_percent_depositor() {
+        let mut state = StabilityPoolState::new();
+        state.total_zkusd = 100_000 * ONE_ZKUSD;
+        let deposit = consumed_deposit([1u8; 32], 10_000 * ONE_ZKUSD, 0);
+
+        assert_eq!(pool_share_bps(&deposit, &state), 1_000);
+
+        // Losses shrink the deposit and the pool alike
+        state.product_p = SCALE_FACTOR / 2;
+        state.total_zkusd = 50_000 * ONE_ZKUSD;
+        assert_eq!(pool_share_bps(&deposit, &state), 1_000);
+
+        assert_eq!(pool_share_bps(&deposit, &StabilityPoolState::new()), 0);
+    }
+
+    #[test]
+    fn test_absorption_capacity() {
+        let mut state = StabilityPoolState::new();
+        state.total_zkusd = 250_000 * ONE_ZKUSD;
+
+        let report = absorption_capacity(&state, 1_000_000 * ONE_ZKUSD);
+        assert_eq!(report, CapacityReport { coverage_bps: 2_500, zkusd_available: 250_000 * ONE_ZKUSD });
+
+        // A pool larger than all system debt covers it fully, not more
+        let report = absorption_capacity(&state, 100_000 * ONE_ZKUSD);
+        assert_eq!(report.coverage_bps, 10_000);
+        assert_eq!(report.zkusd_available, 250_000 * ONE_ZKUSD);
+        assert_eq!(absorption_capacity(&state, 0).coverage_bps, 10_000);
+    }
+
+    #[test]
+    fn test_estimate_apy_over_a_month() {
+        use zkusd_common::constants::time::BLOCKS_PER_DAY;
+
+        let btc_price = 100_000 * ONE_ZKUSD; // $100k
+        let month = 30 * BLOCKS_PER_DAY;
+        let mut state = StabilityPoolState::new();
+        state.total_zkusd = 1_000_000 * ONE_ZKUSD;
+
+        // Outside the window: ignored
+        state.record_offset(OffsetSample { debt: 50_000 * ONE_ZKUSD, collateral: ONE_BTC, block: 100 });
+        // Weekly liquidations of 10k debt backed by $11k of BTC
+        for week in 0..4 {
+            state.record_offset(OffsetSample {
+                debt: 10_000 * ONE_ZKUSD,
+                collateral: 11 * ONE_BTC / 100,
+                block: 5_000 + week * 7 * BLOCKS_PER_DAY,
+            });
+        }
+
+        // The pool held 1.04M before the first offset, so each nets a
+        // depositor 1k / 1.04M: 4k / 1.04M over the month is 38.46 bps,
+        // x 52,560 / 4,320 blocks = 467.9 bps a year
+        let apy = estimate_apy(&state.recent_offsets, &state, btc_price, month);
+        assert_eq!(apy, 467);
+
+        // No samples, or an empty window, estimate nothing
+        assert_eq!(estimate_apy(&[], &state, btc_price, month), 0);
+        assert_eq!(estimate_apy(&state.recent_offsets, &state, btc_price, 0), 0);
+
+        // Liquidations below par lose money: the estimate floors at zero
+        assert_eq!(estimate_apy(&state.recent_offsets, &state, btc_price / 2, month), 0);
+    }
+
+    #[test]
+    fn test_migrate_deposit_across_scale_factor_change() {
+        // Pool accounting used to run at 1e9 precision
+        let old_scale_factor = 1_000_000_000u128;
+        let deposit = StabilityDeposit {
+            owner: [1u8; 32],
+            initial_value: 10_000 * ONE_ZKUSD,
+            snapshot_p: old_scale_factor,
+            snapshot_s: 0,
+            snapshot_epoch: 0,
+            snapshot_scale: 0,
+            last_updated: 50,
+            frontend_tag: None,
+            kickback_bps: 0,
+        };
+
+        // Offsets since the deposit: 10% of it absorbed, 0.1 BTC earned
+        let mut old_state = StabilityPoolState::new();
+        old_state.product_p = old_scale_factor * 9 / 10;
+        old_state.sum_s = 10_000;
+        let old_value = calculate_compounded_deposit_with_scale_factor(
+            old_scale_factor, deposit.initial_value, deposit.snapshot_p, old_state.product_p, 0, 0,
+        );
+        assert_eq!(old_value, 9_000 * ONE_ZKUSD);
+
+        // The same pool re-expressed at the current SCALE_FACTOR
+        let mut new_state = old_state.clone();
+        new_state.product_p = SCALE_FACTOR * 9 / 10;
+        new_state.sum_s = old_state.sum_s * (SCALE_FACTOR / old_scale_factor);
+
+        // Old snapshots read against the new state would mis-value the deposit
+        assert_ne!(get_compounded_value(&deposit, &new_state), old_value);
+
+        let migrated = migrate_deposit(&deposit, &old_state, &new_state, old_scale_factor).unwrap();
+        assert_eq!(get_compounded_value(&migrated, &new_state), old_value);
+        assert_eq!(get_pending_btc(&migrated, &new_state).unwrap(), ONE_BTC / 10);
+        assert_eq!(migrated.initial_value, deposit.initial_value);
+        assert_eq!(migrated.snapshot_p, SCALE_FACTOR);
+
+        // Pending BTC needs room below the new S
+        new_state.sum_s = 0;
+        assert!(matches!(
+            migrate_deposit(&deposit, &old_state, &new_state, old_scale_factor),
+            Err(ZkUsdError::InvalidInput { param: "new_state", .. })
+        ));
+    }
+
+    // ============ Conformance Vector Tests ============
+
+    /// Pool context for a conformance vector, carrying the outputs a valid
+    /// spell for `action` would
+    fn vector_context(v: &zkusd_common::conformance::VectorContext, action: &StabilityPoolAction) -> StabilityPoolContext {
+        let state = v.pool.clone();
+        let mut ctx = StabilityPoolContext {
+            state: state.clone(),
+            new_state: state.clone(),
+            config: StabilityPoolConfig {
+                zkusd_token_id: v.authorized_minter,
+                vault_manager_id: v.vault_manager_id,
+                admin: v.admin,
+                frontends: Vec::new(),
+            },
+            new_config: None,
+            deposit: v.deposit.clone(),
+            new_deposit: None,
+            zkusd_inputs: ZkUsd(v.zkusd_inputs()),
+            zkusd_outputs: ZkUsd::ZERO,
+            btc_inputs: Sats(v.btc_inputs),
+            btc_outputs: Sats::ZERO,
+            frontend_btc_outputs: Sats::ZERO,
+            caller_app_id: v.caller_app_id,
+            signer: v.signer,
+            block_height: v.block_height,
+            based_on: None,
+            events: EventLog::new(),
+        };
+
+        // The deposit carried forward at the pool's current snapshots
+        let carried = |deposit: &StabilityDeposit, initial_value| StabilityDeposit {
+            initial_value,
+            snapshot_p: state.product_p,
+            snapshot_s: state.sum_s,
+            snapshot_epoch: state.current_epoch,
+            snapshot_scale: state.current_scale,
+            last_updated: v.block_height,
+            ..deposit.clone()
+        };
+        if let Some(deposit) = &v.deposit {
+            let compounded = get_compounded_value(deposit, &state);
+            let split = get_pending_btc(deposit, &state).and_then(|gain| deposit.split_btc_gain(gain));
+            let split = split.unwrap_or(BtcGainSplit { depositor: 0, frontend: 0 });
+            ctx.btc_outputs = Sats(split.total());
+            ctx.frontend_btc_outputs = Sats(split.frontend);
+            match action {
+                StabilityPoolAction::Deposit { amount: ZkUsd(amount) } => {
+                    ctx.new_deposit = Some(carried(deposit, compounded + amount));
+                }
+                StabilityPoolAction::Withdraw { amount: ZkUsd(amount) } => {
+                    ctx.zkusd_outputs = ZkUsd(*amount);
+                    ctx.new_deposit = Some(carried(deposit, compounded.saturating_sub(*amount)));
+                    ctx.new_state.total_zkusd = state.total_zkusd.saturating_sub(*amount);
+                }
+                StabilityPoolAction::ClaimBtc => ctx.new_deposit = Some(carried(deposit, compounded)),
+                // The depositor's share goes to the vault, not to an output
+                StabilityPoolAction::ClaimBtcToVault { .. } => {
+                    ctx.btc_outputs = Sats(split.frontend);
+                    ctx.new_deposit = Some(carried(deposit, compounded));
+                }
+                StabilityPoolAction::SweepDustDeposit { .. } => {
+                    ctx.new_state.depositor_count = state.depositor_count.saturating_sub(1);
+                    ctx.new_state.total_zkusd = state.total_zkusd.saturating_sub(compounded);
+                    ctx.new_state.protocol_dust = state.protocol_dust + compounded;
+                }
+                _ => {}
+            }
+        }
+
+        match action {
+            StabilityPoolAction::Deposit { amount: ZkUsd(amount) } => {
+                ctx.new_state.total_zkusd = state.total_zkusd + amount;
+                if v.deposit.is_none() {
+                    ctx.new_state.depositor_count += 1;
+                    ctx.new_deposit = Some(StabilityDeposit {
+                        owner: v.signer,
+                        initial_value: *amount,
+                        snapshot_p: state.product_p,
+                        snapshot_s: state.sum_s,
+                        snapshot_epoch: state.current_epoch,
+                        snapshot_scale: state.current_scale,
+                        last_updated: v.block_height,
+                        frontend_tag: None,
+                        kickback_bps: 0,
+                    });
+                }
+            }
+            // An offset the pool cannot absorb is rejected before its output
+            // is read
+            StabilityPoolAction::Offset { debt: ZkUsd(debt), collateral: Sats(collateral) }
+                if *debt < state.total_zkusd =>
+            {
+                ctx.new_state = state_after_offset(&state, *debt, *collateral, v.block_height);
+            }
+            _ => {}
+        }
+        ctx
+    }
+
+    #[test]
+    fn test_conformance_vectors() {
+        use zkusd_common::conformance::{decode_vector, vectors_for, ConformanceContract};
+
+        for vector in vectors_for(ConformanceContract::StabilityPool) {
+            let (action, v) = decode_vector::<StabilityPoolAction>(&vector).unwrap();
+            let result = validate(&mut vector_context(&v, &action), &action);
+            assert!(
+                vector.expected.matches(&result),
+                "vector {} expected {:?}, got {:?}",
+                vector.name,
+                vector.expected,
+                result
+            );
+        }
+    }
 }
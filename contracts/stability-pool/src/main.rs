@@ -25,7 +25,7 @@
 //! The pool state only tracks aggregate values. Individual balances
 //! are computed from each user's deposit charm + pool snapshots.
 
-use charms_sdk::data::{App, Data, Transaction};
+use zkusd_charms_compat::{App, Data, Transaction};
 
 /// Main validation function for Stability Pool operations.
 ///
@@ -48,4 +48,4 @@ pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
 }
 
 // Use the Charms SDK main macro to generate the entry point
-charms_sdk::main!(app_contract);
+zkusd_charms_compat::main!(app_contract);
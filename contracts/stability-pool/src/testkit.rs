@@ -0,0 +1,159 @@
+//! Test Kit
+//!
+//! Fluent factories for `StabilityPoolContext`:
+//!
+//! ```ignore
+//! let ctx = SpCtx::with_deposit(10_000).after_offset(pct(10)).build();
+//! ```
+//!
+//! `state_after_offset` constructs the golden pool state an offset must
+//! produce, for tests to set as the output or compare against.
+
+use zkusd_common::{
+    constants::stability_pool::SCALE_FACTOR,
+    events::EventLog,
+    types::{Address, AppId, OffsetSample, StabilityDeposit, StabilityPoolState},
+    units::{Sats, ZkUsd},
+};
+
+use crate::{StabilityPoolConfig, StabilityPoolContext};
+
+/// One zkUSD in base units
+pub const ONE_ZKUSD: u64 = 100_000_000;
+/// One BTC in satoshis
+pub const ONE_BTC: u64 = 100_000_000;
+/// Block the context executes at
+pub const BLOCK: u64 = 100;
+/// Signer of the context, and owner of its deposit
+pub const DEPOSITOR: Address = [1u8; 32];
+/// Vault Manager app_id in the context config
+pub const VAULT_MANAGER: AppId = [2u8; 32];
+
+/// Collateral an offset brings per unit of debt absorbed (110%, as at MCR
+/// with BTC at $100,000)
+const OFFSET_COLLATERAL_RATIO: u64 = 110;
+
+/// Share of the pool an offset absorbs
+#[derive(Debug, Clone, Copy)]
+pub struct Pct(u64);
+
+/// `percent`% of the pool
+pub fn pct(percent: u64) -> Pct {
+    assert!(percent < 100, "an offset emptying the pool rolls the epoch over");
+    Pct(percent)
+}
+
+/// Change applied to a built context
+type Mutation = Box<dyn FnOnce(&mut StabilityPoolContext)>;
+
+/// Builder for a `StabilityPoolContext`
+pub struct SpCtx {
+    deposit: Option<u64>,
+    offsets: Vec<Pct>,
+    caller: Option<AppId>,
+    mutations: Vec<Mutation>,
+}
+
+impl SpCtx {
+    /// Empty pool, with no deposit and no calling app
+    pub fn new() -> Self {
+        Self { deposit: None, offsets: Vec::new(), caller: None, mutations: Vec::new() }
+    }
+
+    /// Pool holding only the signer's deposit of `zkusd` whole zkUSD
+    pub fn with_deposit(zkusd: u64) -> Self {
+        Self { deposit: Some(zkusd * ONE_ZKUSD), ..Self::new() }
+    }
+
+    /// Have the pool absorb `share` of its zkUSD in an offset before the
+    /// spell, against collateral worth 110% of the debt
+    pub fn after_offset(mut self, share: Pct) -> Self {
+        self.offsets.push(share);
+        self
+    }
+
+    /// Run the spell as called by the Vault Manager
+    pub fn called_by_vault_manager(mut self) -> Self {
+        self.caller = Some(VAULT_MANAGER);
+        self
+    }
+
+    /// Change the built context; applied in order after everything else
+    pub fn mutate(mut self, mutation: impl FnOnce(&mut StabilityPoolContext) + 'static) -> Self {
+        self.mutations.push(Box::new(mutation));
+        self
+    }
+
+    /// Build the context, with the pool state before the spell also its
+    /// output
+    pub fn build(self) -> StabilityPoolContext {
+        let mut state = StabilityPoolState::new();
+        let deposit = self.deposit.map(|amount| {
+            state.total_zkusd = amount;
+            state.depositor_count = 1;
+            StabilityDeposit {
+                owner: DEPOSITOR,
+                initial_value: amount,
+                snapshot_p: state.product_p,
+                snapshot_s: state.sum_s,
+                snapshot_epoch: state.current_epoch,
+                snapshot_scale: state.current_scale,
+                last_updated: BLOCK - 50,
+                frontend_tag: None,
+                kickback_bps: 0,
+            }
+        });
+        for Pct(percent) in self.offsets {
+            let debt = state.total_zkusd * percent / 100;
+            state = state_after_offset(&state, debt, offset_collateral(debt), BLOCK - 10);
+        }
+
+        let mut ctx = StabilityPoolContext {
+            state: state.clone(),
+            new_state: state,
+            config: StabilityPoolConfig {
+                zkusd_token_id: [1u8; 32],
+                vault_manager_id: VAULT_MANAGER,
+                admin: [0u8; 32],
+                frontends: Vec::new(),
+            },
+            new_config: None,
+            deposit,
+            new_deposit: None,
+            zkusd_inputs: ZkUsd(0),
+            zkusd_outputs: ZkUsd(0),
+            btc_inputs: Sats(0),
+            btc_outputs: Sats(0),
+            frontend_btc_outputs: Sats(0),
+            caller_app_id: self.caller,
+            signer: DEPOSITOR,
+            block_height: BLOCK,
+            based_on: None,
+            events: EventLog::new(),
+        };
+        for mutation in self.mutations {
+            mutation(&mut ctx);
+        }
+        ctx
+    }
+}
+
+/// Collateral (satoshis) an offset of `debt` brings
+pub fn offset_collateral(debt: u64) -> u64 {
+    let collateral = debt as u128 * OFFSET_COLLATERAL_RATIO as u128 * ONE_BTC as u128 / (100 * 100_000 * ONE_ZKUSD) as u128;
+    u64::try_from(collateral).expect("collateral fits u64")
+}
+
+/// Golden pool state after an offset of `debt` against `collateral` at
+/// `block`, for an offset that leaves zkUSD in the pool
+pub fn state_after_offset(state: &StabilityPoolState, debt: u64, collateral: u64, block: u64) -> StabilityPoolState {
+    let total = state.total_zkusd as u128;
+    assert!((debt as u128) < total, "an offset emptying the pool rolls the epoch over");
+
+    let mut next = state.clone();
+    next.total_zkusd -= debt;
+    next.product_p = state.product_p * (SCALE_FACTOR - debt as u128 * SCALE_FACTOR / total) / SCALE_FACTOR;
+    next.sum_s += collateral as u128 * state.product_p / total;
+    next.record_offset(OffsetSample { debt, collateral, block });
+    next
+}
@@ -18,6 +18,14 @@
 //! Liquidate:
 //!   IN: [Vault(underwater), StabilityPool, ProtocolState, PriceOracle(ref)]
 //!   OUT: [Vault(liquidated), StabilityPool(updated), ProtocolState(updated), BTC(to liquidator)]
+//!
+//! BeginLiquidation (vaults too large for Liquidate):
+//!   IN: [Vault(underwater), ProtocolState, StabilityPool(ref), PriceOracle(ref)]
+//!   OUT: [Vault(liquidating), ProtocolState(updated)]
+//!
+//! ContinueLiquidation (one per tranche):
+//!   IN: [Vault(liquidating), StabilityPool, ProtocolState]
+//!   OUT: [Vault(liquidating or liquidated), StabilityPool(updated), ProtocolState, BTC(to liquidator)]
 //! ```
 //!
 //! ## Cross-App Validation
@@ -25,13 +33,30 @@
 //! The VaultManager interacts with other apps in the same transaction:
 //! - **zkusd-token**: Minting/burning tokens (authorized caller)
 //! - **price-oracle**: Reading BTC price (reference input)
-//! - **stability-pool**: Absorbing liquidations
+//! - **stability-pool**: Absorbing liquidations, funding AddCollateral
+//!   with a depositor's BTC gain claimed in the same spell, and backing the
+//!   depositor fee discount with a referenced deposit
 
-use charms_data::{App, Data, Transaction};
-use crate::{VaultManagerState, VaultContext, validate};
+use zkusd_charms_compat::{extract_coin_flows, App, Charms, Data, Transaction, UtxoId};
+use crate::{
+    ExpectedOutputs, LinkedBtcClaim, LinkedDeposit, LinkedOffset, SpellBounds, VaultManagerState, VaultContext, validate,
+};
 use zkusd_common::{
+    address::is_zero,
+    commitment::StateRef,
+    constants::{fees, ratios},
+    errors::{CompanionRole, ZkUsdError, ZkUsdResult},
     events::EventLog,
-    types::{Vault, VaultAction, VaultId, PriceData},
+    math::{calculate_compounded_deposit, calculate_pending_btc},
+    types::{
+        Address, CircuitBreakerState, FeeDistribution, FeeSplit, OracleSnapshot, PriceData, ProtocolControlledValue,
+        StabilityDeposit,
+        StabilityPoolState, Vault, VaultAction, VaultId,
+    },
+    units::{Sats, ZkUsd},
+    validation::{require_companion, AppliedActions},
+    vault_registry::VaultRegistry,
+    versioning::VersionedState,
 };
 
 // ============ Operation Codes ============
@@ -50,6 +75,12 @@ pub mod op {
     pub const REPAY_DEBT: u8 = 0x15;
     pub const LIQUIDATE: u8 = 0x16;
     pub const REDEEM: u8 = 0x17;
+    pub const SET_VAULT_OPERATOR: u8 = 0x18;
+    pub const SET_PROTECTION: u8 = 0x19;
+    pub const SET_BENEFICIARY: u8 = 0x1A;
+    pub const CLAIM_AS_BENEFICIARY: u8 = 0x1B;
+    pub const BEGIN_LIQUIDATION: u8 = 0x1C;
+    pub const CONTINUE_LIQUIDATION: u8 = 0x1D;
 
     // Advanced UTXO-Native Operations (0x20 - 0x2F)
     pub const FLASH_MINT: u8 = 0x20;
@@ -57,12 +88,25 @@ pub mod op {
     pub const PURCHASE_INSURANCE: u8 = 0x22;
     pub const TRIGGER_INSURANCE: u8 = 0x23;
     pub const TRANSFER_INSURANCE: u8 = 0x24;
+    pub const EXPIRE_INSURANCE: u8 = 0x25;
+
+    // Admin Operations (0x30 - 0x3F)
+    pub const SET_FLASH_FEE: u8 = 0x30;
+    pub const SET_FEE_DISTRIBUTION: u8 = 0x31;
+
+    // Fee and Interest Maintenance (0x40 - 0x4F)
+    pub const POKE_BASE_RATE: u8 = 0x40;
+    pub const ACCRUE_INTEREST: u8 = 0x41;
+
+    // Bootstrap (0x50 - 0x5F)
+    pub const GRADUATE_BOOTSTRAP: u8 = 0x50;
 }
 
 // ============ Witness Structures ============
 
 /// Witness data for Initialize operation
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct InitWitness {
     pub op: u8,
     /// Admin address
@@ -81,6 +125,7 @@ pub struct InitWitness {
 
 /// Witness data for vault operations
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct VaultWitness {
     /// Operation type (see `op` module)
     pub op: u8,
@@ -106,6 +151,37 @@ pub struct VaultWitness {
     pub insurance_id: Option<[u8; 32]>,
     /// New owner for transfer
     pub new_owner: Option<[u8; 32]>,
+    /// Vault operator key (`None` clears it)
+    pub operator: Option<[u8; 32]>,
+    /// Flash mint fee in basis points (admin)
+    pub fee_bps: Option<u64>,
+    /// Split of protocol fees between destinations (admin)
+    pub fee_distribution: Option<FeeDistribution>,
+    /// Redemption-protected share of collateral in basis points
+    #[serde(default)]
+    pub protection_bps: Option<u64>,
+    /// Vault beneficiary key (`None` clears it)
+    #[serde(default)]
+    pub beneficiary: Option<[u8; 32]>,
+    /// Blocks of owner inactivity before the beneficiary may claim
+    #[serde(default)]
+    pub inactivity_blocks: Option<u64>,
+    /// Merkle proof of the signer's bootstrap whitelist membership
+    #[serde(default)]
+    pub whitelist_proof: Option<Vec<[u8; 32]>>,
+
+    // Spell bounds
+    /// Last block at which the spell may execute
+    pub expires_at_block: Option<u64>,
+    /// Maximum acceptable BTC price (OpenVault, MintDebt)
+    pub max_price: Option<u64>,
+    /// Minimum acceptable BTC price (Redeem)
+    pub min_price: Option<u64>,
+    /// Minimum BTC to receive in satoshis (Redeem)
+    pub min_btc_out: Option<u64>,
+    /// State the spell was built against (see `StateRef`)
+    #[serde(default)]
+    pub based_on: Option<StateRef>,
 }
 
 impl VaultWitness {
@@ -123,6 +199,18 @@ impl VaultWitness {
             trigger_icr: None,
             insurance_id: None,
             new_owner: None,
+            operator: None,
+            fee_bps: None,
+            fee_distribution: None,
+            protection_bps: None,
+            beneficiary: None,
+            inactivity_blocks: None,
+            whitelist_proof: None,
+            expires_at_block: None,
+            max_price: None,
+            min_price: None,
+            min_btc_out: None,
+            based_on: None,
         }
     }
 
@@ -149,6 +237,21 @@ impl VaultWitness {
         w
     }
 
+    /// Create witness for beginning a two-phase liquidation
+    pub fn begin_liquidation(vault_id: VaultId) -> Self {
+        let mut w = Self::default_with_op(op::BEGIN_LIQUIDATION);
+        w.vault_id = Some(vault_id);
+        w
+    }
+
+    /// Create witness for one tranche of a two-phase liquidation
+    pub fn continue_liquidation(vault_id: VaultId, debt_portion: u64) -> Self {
+        let mut w = Self::default_with_op(op::CONTINUE_LIQUIDATION);
+        w.vault_id = Some(vault_id);
+        w.debt = Some(debt_portion);
+        w
+    }
+
     /// Create witness for flash mint
     pub fn flash_mint(amount: u64, purpose: u8) -> Self {
         let mut w = Self::default_with_op(op::FLASH_MINT);
@@ -194,6 +297,177 @@ impl VaultWitness {
         w.vault_id = Some(vault_id);
         w
     }
+
+    /// Create witness for retiring an expired insurance charm
+    pub fn expire_insurance(insurance_id: [u8; 32], vault_id: VaultId) -> Self {
+        let mut w = Self::default_with_op(op::EXPIRE_INSURANCE);
+        w.insurance_id = Some(insurance_id);
+        w.vault_id = Some(vault_id);
+        w
+    }
+
+    /// Create witness for graduating the protocol from bootstrap mode
+    pub fn graduate_bootstrap() -> Self {
+        Self::default_with_op(op::GRADUATE_BOOTSTRAP)
+    }
+
+    /// Set the Merkle proof of the signer's bootstrap whitelist membership
+    pub fn with_whitelist_proof(mut self, proof: Vec<[u8; 32]>) -> Self {
+        self.whitelist_proof = Some(proof);
+        self
+    }
+
+    /// Set the last block at which the spell may execute
+    pub fn with_expiry(mut self, expires_at_block: u64) -> Self {
+        self.expires_at_block = Some(expires_at_block);
+        self
+    }
+
+    /// Set the maximum acceptable BTC price
+    pub fn with_max_price(mut self, max_price: u64) -> Self {
+        self.max_price = Some(max_price);
+        self
+    }
+
+    /// Set the minimum acceptable BTC price
+    pub fn with_min_price(mut self, min_price: u64) -> Self {
+        self.min_price = Some(min_price);
+        self
+    }
+
+    /// Create witness for setting or clearing a vault's operator key
+    pub fn set_vault_operator(vault_id: VaultId, operator: Option<[u8; 32]>) -> Self {
+        let mut w = Self::default_with_op(op::SET_VAULT_OPERATOR);
+        w.vault_id = Some(vault_id);
+        w.operator = operator;
+        w
+    }
+
+    /// Create witness for raising a vault's redemption protection
+    pub fn set_protection(vault_id: VaultId, bps: u64) -> Self {
+        let mut w = Self::default_with_op(op::SET_PROTECTION);
+        w.vault_id = Some(vault_id);
+        w.protection_bps = Some(bps);
+        w
+    }
+
+    /// Create witness for designating or clearing a vault's beneficiary
+    pub fn set_beneficiary(vault_id: VaultId, beneficiary: Option<([u8; 32], u64)>) -> Self {
+        let mut w = Self::default_with_op(op::SET_BENEFICIARY);
+        w.vault_id = Some(vault_id);
+        w.beneficiary = beneficiary.map(|(beneficiary, _)| beneficiary);
+        w.inactivity_blocks = beneficiary.map(|(_, inactivity_blocks)| inactivity_blocks);
+        w
+    }
+
+    /// Create witness for a beneficiary claiming an inactive vault
+    pub fn claim_as_beneficiary(vault_id: VaultId) -> Self {
+        let mut w = Self::default_with_op(op::CLAIM_AS_BENEFICIARY);
+        w.vault_id = Some(vault_id);
+        w
+    }
+
+    /// Create witness for setting the flash mint fee
+    pub fn set_flash_fee(fee_bps: u64) -> Self {
+        let mut w = Self::default_with_op(op::SET_FLASH_FEE);
+        w.fee_bps = Some(fee_bps);
+        w
+    }
+
+    /// Create witness for setting the protocol fee split
+    pub fn set_fee_distribution(distribution: FeeDistribution) -> Self {
+        let mut w = Self::default_with_op(op::SET_FEE_DISTRIBUTION);
+        w.fee_distribution = Some(distribution);
+        w
+    }
+
+    /// Create witness for applying the base rate decay
+    pub fn poke_base_rate() -> Self {
+        Self::default_with_op(op::POKE_BASE_RATE)
+    }
+
+    /// Create witness for folding a vault's interest into its debt
+    pub fn accrue_interest(vault_id: VaultId) -> Self {
+        let mut w = Self::default_with_op(op::ACCRUE_INTEREST);
+        w.vault_id = Some(vault_id);
+        w
+    }
+
+    /// Witness carrying `action`, as the entry point parses it back
+    ///
+    /// Spell bounds and the whitelist proof are not part of the action; set
+    /// them with the `with_*` methods.
+    pub fn for_action(action: &VaultAction) -> Self {
+        match action {
+            VaultAction::OpenVault { collateral, debt } => Self::open_vault(collateral.into_inner(), debt.into_inner()),
+            VaultAction::CloseVault { vault_id } => {
+                let mut w = Self::default_with_op(op::CLOSE_VAULT);
+                w.vault_id = Some(*vault_id);
+                w
+            }
+            VaultAction::AddCollateral { vault_id, amount } => Self::add_collateral(*vault_id, amount.into_inner()),
+            VaultAction::WithdrawCollateral { vault_id, amount } => {
+                let mut w = Self::default_with_op(op::WITHDRAW_COLLATERAL);
+                w.vault_id = Some(*vault_id);
+                w.collateral = Some(amount.into_inner());
+                w
+            }
+            VaultAction::MintDebt { vault_id, amount } => {
+                let mut w = Self::default_with_op(op::MINT_DEBT);
+                w.vault_id = Some(*vault_id);
+                w.debt = Some(amount.into_inner());
+                w
+            }
+            VaultAction::RepayDebt { vault_id, amount } => {
+                let mut w = Self::default_with_op(op::REPAY_DEBT);
+                w.vault_id = Some(*vault_id);
+                w.debt = Some(amount.into_inner());
+                w
+            }
+            VaultAction::Liquidate { vault_id } => Self::liquidate(*vault_id),
+            VaultAction::Redeem { amount, min_btc_out } => {
+                let mut w = Self::default_with_op(op::REDEEM);
+                w.debt = Some(amount.into_inner());
+                w.min_btc_out = Some(min_btc_out.into_inner());
+                w
+            }
+            VaultAction::FlashMint { amount, purpose } => Self::flash_mint(amount.into_inner(), *purpose),
+            VaultAction::AtomicRescue { vault_id, collateral_to_add, debt_to_repay, rescuer_discount } => {
+                Self::atomic_rescue(
+                    *vault_id,
+                    collateral_to_add.into_inner(),
+                    debt_to_repay.into_inner(),
+                    rescuer_discount.into_inner(),
+                )
+            }
+            VaultAction::PurchaseInsurance { vault_id, coverage_btc, premium, trigger_icr } => {
+                Self::purchase_insurance(*vault_id, coverage_btc.into_inner(), premium.into_inner(), *trigger_icr)
+            }
+            VaultAction::TriggerInsurance { insurance_id, vault_id } => Self::trigger_insurance(*insurance_id, *vault_id),
+            VaultAction::TransferInsurance { insurance_id, new_owner } => {
+                let mut w = Self::default_with_op(op::TRANSFER_INSURANCE);
+                w.insurance_id = Some(*insurance_id);
+                w.new_owner = Some(*new_owner);
+                w
+            }
+            VaultAction::SetFlashFee { fee_bps } => Self::set_flash_fee(*fee_bps),
+            VaultAction::SetFeeDistribution { distribution } => Self::set_fee_distribution(*distribution),
+            VaultAction::SetVaultOperator { vault_id, operator } => Self::set_vault_operator(*vault_id, *operator),
+            VaultAction::PokeBaseRate {} => Self::poke_base_rate(),
+            VaultAction::SetProtection { vault_id, bps } => Self::set_protection(*vault_id, *bps),
+            VaultAction::SetBeneficiary { vault_id, beneficiary, inactivity_blocks } => {
+                Self::set_beneficiary(*vault_id, beneficiary.map(|beneficiary| (beneficiary, *inactivity_blocks)))
+            }
+            VaultAction::ClaimAsBeneficiary { vault_id } => Self::claim_as_beneficiary(*vault_id),
+            VaultAction::BeginLiquidation { vault_id } => Self::begin_liquidation(*vault_id),
+            VaultAction::ContinueLiquidation { vault_id, debt_portion } => {
+                Self::continue_liquidation(*vault_id, debt_portion.into_inner())
+            }
+            VaultAction::ExpireInsurance { insurance_id, vault_id } => Self::expire_insurance(*insurance_id, *vault_id),
+            VaultAction::GraduateBootstrap {} => Self::graduate_bootstrap(),
+            VaultAction::AccrueInterest { vault_id } => Self::accrue_interest(*vault_id),
+        }
+    }
 }
 
 // ============ Main Validation Function ============
@@ -212,7 +486,8 @@ impl VaultWitness {
 /// # Cross-App Interactions
 ///
 /// The VaultManager reads data from other apps in the transaction:
-/// - **Price Oracle**: BTC/USD price from `app_public_inputs`
+/// - **Price Oracle**: BTC/USD price from the oracle charm whose app id
+///   equals `price_oracle_id` in protocol state
 /// - **Stability Pool**: For liquidation offsets
 ///
 /// It also authorizes actions in other apps:
@@ -221,7 +496,7 @@ impl VaultWitness {
 /// # Arguments
 /// * `app` - The VaultManager app definition
 /// * `tx` - The transaction being validated
-/// * `x` - Public inputs (contains oracle price data)
+/// * `x` - Public inputs (unused; prices are only read from the oracle charm)
 /// * `w` - Witness data (operation details)
 ///
 /// # Returns
@@ -231,6 +506,26 @@ pub fn validate_vault_operation(
     tx: &Transaction,
     x: &Data,
     w: &Data,
+) -> bool {
+    match extract_block_height(tx) {
+        Some(block_height) => validate_vault_operation_at(app, tx, x, w, block_height),
+        // Without the height the price's age is unknown, so its confidence
+        // cannot gate a liquidation: those are refused outright
+        None => !needs_block_height(w) && validate_vault_operation_at(app, tx, x, w, 0),
+    }
+}
+
+/// `validate_vault_operation` at a known block height
+///
+/// The Charms runtime does not expose the block height yet, so the entry
+/// point refuses liquidations and validates everything else at block 0;
+/// hosts that know the height (tests, indexers) validate at it here.
+pub fn validate_vault_operation_at(
+    app: &App,
+    tx: &Transaction,
+    _x: &Data,
+    w: &Data,
+    block_height: u64,
 ) -> bool {
     // Check if this is an Initialize operation
     if let Some(init) = parse_init_witness(w) {
@@ -239,7 +534,8 @@ pub fn validate_vault_operation(
             Some(s) => s,
             None => return false,
         };
-        return validate_initialize(&output_state, &init);
+        let (_, registry) = extract_registries(app, tx);
+        return validate_initialize(&output_state, &registry, &init);
     }
 
     // 1. Parse witness to get operation
@@ -262,19 +558,29 @@ pub fn validate_vault_operation(
 
     // 4. Extract vault being operated on (if applicable)
     let (vault, new_vault) = extract_vaults(app, tx, witness.vault_id);
+    let (registry, new_registry) = extract_registries(app, tx);
 
-    // 5. Get BTC price from public inputs or referenced oracle
-    let btc_price = match extract_btc_price(tx, x) {
-        Some(p) => p,
-        None => return false,
+    // 5. Get BTC price from the recorded price oracle
+    let oracle = match extract_oracle(tx, &state.price_oracle_id) {
+        Ok(o) => o,
+        Err(_) => return false,
     };
 
     // 6. Calculate BTC inputs and outputs
-    let (btc_inputs, btc_outputs) = calculate_btc_flows(tx);
+    let coins = extract_coin_flows(tx);
 
     // 7. Calculate zkUSD inputs and outputs
     let (zkusd_inputs, zkusd_outputs) = calculate_zkusd_flows(tx, &state.zkusd_token_id);
 
+    // 7b. Stability pool gain claimed in the same spell
+    let linked_btc_claim = extract_linked_btc_claim(tx, &state.stability_pool_id);
+
+    // 7c. Stability deposit referenced for the depositor fee discount
+    let linked_deposit = extract_linked_deposit(tx, &state.stability_pool_id);
+
+    // 7d. Stability pool read, and any offset made, by a liquidation
+    let linked_offset = extract_linked_offset(tx, &state.stability_pool_id);
+
     // 8. Get signer from transaction
     let signer = extract_signer(tx);
 
@@ -284,13 +590,35 @@ pub fn validate_vault_operation(
         new_state,
         vault,
         new_vault,
-        btc_price,
-        btc_inputs,
-        btc_outputs,
-        zkusd_inputs,
-        zkusd_outputs,
+        // Inactive oracles were rejected above; freshness is checked by
+        // the validator for price-sensitive actions
+        oracle,
+        btc_inputs: Sats(coins.btc_in),
+        btc_outputs: Sats(coins.btc_out),
+        zkusd_inputs: ZkUsd(zkusd_inputs),
+        zkusd_outputs: ZkUsd(zkusd_outputs),
+        // The witness carries a single action, so nothing is minted ahead of it
+        vault_minted: ZkUsd(0),
+        linked_btc_claim,
+        linked_deposit,
+        linked_offset,
+        // Insurance charms are not extracted yet; triggers use the
+        // vault's insurance_balance
+        insurance: None,
+        new_insurance: None,
+        registry,
+        new_registry,
+        bounds: SpellBounds {
+            expires_at_block: witness.expires_at_block,
+            max_price: witness.max_price,
+            min_price: witness.min_price,
+            based_on: witness.based_on,
+        },
+        whitelist_proof: witness.whitelist_proof.unwrap_or_default(),
         signer,
-        block_height: 0, // Would be extracted from tx metadata
+        block_height,
+        applied_actions: AppliedActions::new(),
+        expected: ExpectedOutputs::default(),
         events: EventLog::new(),
     };
 
@@ -300,6 +628,19 @@ pub fn validate_vault_operation(
 
 // ============ Parsing Functions ============
 
+/// Block height the spell executes at, when the transaction carries it
+fn extract_block_height(_tx: &Transaction) -> Option<u64> {
+    // No supported Charms version exposes the block height to apps yet
+    None
+}
+
+/// Whether the witness's action seizes collateral on the price's
+/// age-decayed confidence, which needs the block height
+fn needs_block_height(w: &Data) -> bool {
+    let action = parse_witness(w).and_then(|witness| witness_to_action(&witness));
+    matches!(action, Some(VaultAction::Liquidate { .. } | VaultAction::BeginLiquidation { .. }))
+}
+
 /// Parse witness data to check if it's an Initialize operation
 fn parse_init_witness(w: &Data) -> Option<InitWitness> {
     if let Ok(init) = w.value::<InitWitness>() {
@@ -311,7 +652,7 @@ fn parse_init_witness(w: &Data) -> Option<InitWitness> {
 }
 
 /// Validate initialization of VaultManager
-fn validate_initialize(output: &VaultManagerState, init: &InitWitness) -> bool {
+fn validate_initialize(output: &VaultManagerState, registry: &[VaultRegistry], init: &InitWitness) -> bool {
     // Verify output state matches initialization parameters
     if output.zkusd_token_id != init.zkusd_token_id {
         return false;
@@ -344,15 +685,53 @@ fn validate_initialize(output: &VaultManagerState, init: &InitWitness) -> bool {
     if output.protocol.is_paused {
         return false;
     }
+    if output.protocol.accumulated_fees != 0 {
+        return false;
+    }
+    if output.protocol.flash_fee_bps < fees::MIN_FLASH_FEE_BPS
+        || output.protocol.flash_fee_bps > fees::MAX_FLASH_FEE_BPS
+    {
+        return false;
+    }
+    // Fee split must be complete and nothing collected yet
+    if output.fee_distribution.validate().is_err() {
+        return false;
+    }
+    if output.collected_fees != FeeSplit::default() {
+        return false;
+    }
+    // Opening buffer within bounds
+    if output.open_buffer_bps > ratios::MAX_OPEN_BUFFER_BPS {
+        return false;
+    }
+    // The vault registry starts disabled, or as a single empty shard
+    let expected_registry = match output.registry_shards {
+        0 => Vec::new(),
+        1 => Vec::from([VaultRegistry::default()]),
+        _ => return false,
+    };
+    if registry != expected_registry.as_slice() {
+        return false;
+    }
+    // Any bootstrap starts active, with its loan untouched
+    if let Some(bootstrap) = &output.protocol.bootstrap {
+        let pcv = &bootstrap.pcv;
+        if !bootstrap.is_active() || bootstrap.recovery_mode_seen {
+            return false;
+        }
+        if *pcv != ProtocolControlledValue::new(pcv.bootstrap_debt) {
+            return false;
+        }
+    }
     // Admin cannot be zero address
-    if init.admin == [0u8; 32] {
+    if is_zero(&init.admin) {
         return false;
     }
     // Pool addresses cannot be zero
-    if init.active_pool == [0u8; 32] {
+    if is_zero(&init.active_pool) {
         return false;
     }
-    if init.default_pool == [0u8; 32] {
+    if is_zero(&init.default_pool) {
         return false;
     }
     true
@@ -366,6 +745,7 @@ fn extract_output_state(app: &App, tx: &Transaction) -> Option<VaultManagerState
                 data.value::<VaultManagerState>().ok()
             })
         })
+        .filter(VersionedState::is_current)
 }
 
 /// Parse witness data into VaultWitness
@@ -378,50 +758,78 @@ fn parse_witness(w: &Data) -> Option<VaultWitness> {
 fn witness_to_action(w: &VaultWitness) -> Option<VaultAction> {
     match w.op {
         op::OPEN_VAULT => Some(VaultAction::OpenVault {
-            collateral: w.collateral?,
-            debt: w.debt?,
+            collateral: Sats(w.collateral?),
+            debt: ZkUsd(w.debt?),
         }),
         op::CLOSE_VAULT => Some(VaultAction::CloseVault {
             vault_id: w.vault_id?,
         }),
         op::ADD_COLLATERAL => Some(VaultAction::AddCollateral {
             vault_id: w.vault_id?,
-            amount: w.collateral?,
+            amount: Sats(w.collateral?),
         }),
         op::WITHDRAW_COLLATERAL => Some(VaultAction::WithdrawCollateral {
             vault_id: w.vault_id?,
-            amount: w.collateral?,
+            amount: Sats(w.collateral?),
         }),
         op::MINT_DEBT => Some(VaultAction::MintDebt {
             vault_id: w.vault_id?,
-            amount: w.debt?,
+            amount: ZkUsd(w.debt?),
         }),
         op::REPAY_DEBT => Some(VaultAction::RepayDebt {
             vault_id: w.vault_id?,
-            amount: w.debt?,
+            amount: ZkUsd(w.debt?),
         }),
         op::LIQUIDATE => Some(VaultAction::Liquidate {
             vault_id: w.vault_id?,
         }),
         op::REDEEM => Some(VaultAction::Redeem {
-            amount: w.debt?,
+            amount: ZkUsd(w.debt?),
+            min_btc_out: Sats(w.min_btc_out.unwrap_or(0)),
+        }),
+        op::SET_VAULT_OPERATOR => Some(VaultAction::SetVaultOperator {
+            vault_id: w.vault_id?,
+            operator: w.operator,
+        }),
+        op::SET_PROTECTION => Some(VaultAction::SetProtection {
+            vault_id: w.vault_id?,
+            bps: w.protection_bps?,
+        }),
+        op::SET_BENEFICIARY => Some(VaultAction::SetBeneficiary {
+            vault_id: w.vault_id?,
+            beneficiary: w.beneficiary,
+            // A designation needs its window; clearing one ignores it
+            inactivity_blocks: match w.beneficiary {
+                Some(_) => w.inactivity_blocks?,
+                None => 0,
+            },
+        }),
+        op::CLAIM_AS_BENEFICIARY => Some(VaultAction::ClaimAsBeneficiary {
+            vault_id: w.vault_id?,
+        }),
+        op::BEGIN_LIQUIDATION => Some(VaultAction::BeginLiquidation {
+            vault_id: w.vault_id?,
+        }),
+        op::CONTINUE_LIQUIDATION => Some(VaultAction::ContinueLiquidation {
+            vault_id: w.vault_id?,
+            debt_portion: ZkUsd(w.debt?),
         }),
 
         // Advanced UTXO-Native Operations
         op::FLASH_MINT => Some(VaultAction::FlashMint {
-            amount: w.debt?,
+            amount: ZkUsd(w.debt?),
             purpose: w.flash_purpose.unwrap_or(5), // Default to Custom
         }),
         op::ATOMIC_RESCUE => Some(VaultAction::AtomicRescue {
             vault_id: w.vault_id?,
-            collateral_to_add: w.collateral?,
-            debt_to_repay: w.debt?,
-            rescuer_discount: w.rescuer_discount.unwrap_or(0),
+            collateral_to_add: Sats(w.collateral?),
+            debt_to_repay: ZkUsd(w.debt?),
+            rescuer_discount: Sats(w.rescuer_discount.unwrap_or(0)),
         }),
         op::PURCHASE_INSURANCE => Some(VaultAction::PurchaseInsurance {
             vault_id: w.vault_id?,
-            coverage_btc: w.coverage?,
-            premium: w.premium?,
+            coverage_btc: Sats(w.coverage?),
+            premium: ZkUsd(w.premium?),
             trigger_icr: w.trigger_icr?,
         }),
         op::TRIGGER_INSURANCE => Some(VaultAction::TriggerInsurance {
@@ -432,6 +840,27 @@ fn witness_to_action(w: &VaultWitness) -> Option<VaultAction> {
             insurance_id: w.insurance_id?,
             new_owner: w.new_owner?,
         }),
+        op::EXPIRE_INSURANCE => Some(VaultAction::ExpireInsurance {
+            insurance_id: w.insurance_id?,
+            vault_id: w.vault_id?,
+        }),
+
+        // Admin Operations
+        op::SET_FLASH_FEE => Some(VaultAction::SetFlashFee {
+            fee_bps: w.fee_bps?,
+        }),
+        op::SET_FEE_DISTRIBUTION => Some(VaultAction::SetFeeDistribution {
+            distribution: w.fee_distribution?,
+        }),
+
+        // Fee and Interest Maintenance
+        op::POKE_BASE_RATE => Some(VaultAction::PokeBaseRate {}),
+        op::ACCRUE_INTEREST => Some(VaultAction::AccrueInterest {
+            vault_id: w.vault_id?,
+        }),
+
+        // Bootstrap
+        op::GRADUATE_BOOTSTRAP => Some(VaultAction::GraduateBootstrap {}),
         _ => None,
     }
 }
@@ -467,7 +896,7 @@ fn extract_protocol_states(
                 }
             }
             None
-        })?;
+        })?; // Migrated by validation, which holds the output to the result
 
     // Output state - match by VK+tag
     let output_state = tx.outs.iter()
@@ -480,7 +909,8 @@ fn extract_protocol_states(
                 }
             }
             None
-        })?;
+        })
+        .filter(VersionedState::is_current)?;
 
     Some((input_state, output_state))
 }
@@ -511,7 +941,8 @@ fn extract_vaults(
             None
         });
 
-    // Find output vault matching ID - match by VK+tag
+    // Find output vault matching ID - match by VK+tag, at the current
+    // version
     let output_vault = tx.outs.iter()
         .find_map(|charms| {
             for (charm_app, data) in charms.iter() {
@@ -524,11 +955,32 @@ fn extract_vaults(
                 }
             }
             None
-        });
+        })
+        .filter(VersionedState::is_current);
 
     (input_vault, output_vault)
 }
 
+/// Extract vault registry shards from transaction inputs and outputs
+///
+/// Both lists are sorted by shard index; gaps and duplicates are left for
+/// the registry checks to reject.
+fn extract_registries(app: &App, tx: &Transaction) -> (Vec<VaultRegistry>, Vec<VaultRegistry>) {
+    let decode = |charms: &Charms| {
+        charms.iter()
+            .filter(|(charm_app, _)| matches_app(charm_app, app))
+            .filter_map(|(_, data)| data.value::<VaultRegistry>().ok())
+            .collect::<Vec<_>>()
+    };
+
+    let mut inputs: Vec<VaultRegistry> = tx.ins.iter().flat_map(|(_, charms)| decode(charms)).collect();
+    let mut outputs: Vec<VaultRegistry> = tx.outs.iter().flat_map(decode).collect();
+    inputs.sort_by_key(|registry| registry.shard);
+    outputs.sort_by_key(|registry| registry.shard);
+
+    (inputs, outputs)
+}
+
 /// Minimal OracleState for price extraction
 /// (avoids circular dependency on price-oracle crate)
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -538,72 +990,138 @@ struct OracleStateMinimal {
     is_active: bool,
     #[serde(default)]
     last_valid_price: u64,
+    #[serde(default)]
+    circuit_breaker: CircuitBreakerState,
+    #[serde(default)]
+    previous_price: PriceData,
+    #[serde(default)]
+    operator: Address,
 }
 
-/// Extract BTC price from public inputs or oracle reference
-fn extract_btc_price(tx: &Transaction, x: &Data) -> Option<u64> {
-    // First try public inputs as PriceData
-    if let Ok(price_data) = x.value::<PriceData>() {
-        return Some(price_data.price);
-    }
-
-    // Try public inputs as OracleState
-    if let Ok(oracle) = x.value::<OracleStateMinimal>() {
-        if oracle.is_active {
-            return Some(oracle.price.price);
-        }
+/// Decode a charm's oracle snapshot if its data has the shape of an oracle
+///
+/// Returns `Some(None)` for an inactive oracle so it still counts as a
+/// claimant of the oracle role.
+fn decode_oracle(data: &Data) -> Option<Option<OracleSnapshot>> {
+    if let Ok(price_data) = data.value::<PriceData>() {
+        return Some(Some(OracleSnapshot::active(price_data)));
     }
-
-    // Then try to find oracle app in transaction
-    for (_app, data) in tx.app_public_inputs.iter() {
-        // Try as PriceData first
-        if let Ok(price_data) = data.value::<PriceData>() {
-            return Some(price_data.price);
-        }
-        // Try as OracleState
-        if let Ok(oracle) = data.value::<OracleStateMinimal>() {
-            if oracle.is_active {
-                return Some(oracle.price.price);
-            }
-        }
+    if let Ok(oracle) = data.value::<OracleStateMinimal>() {
+        let snapshot = OracleSnapshot {
+            price: oracle.price,
+            is_active: true,
+            circuit_breaker: oracle.circuit_breaker,
+            previous_price: oracle.previous_price,
+            operator: oracle.operator,
+        };
+        return Some(oracle.is_active.then_some(snapshot));
     }
+    None
+}
 
-    // Also check reference inputs
-    for (_, charms) in tx.refs.iter() {
-        for (_, data) in charms.iter() {
-            // Try as PriceData first
-            if let Ok(price_data) = data.value::<PriceData>() {
-                return Some(price_data.price);
-            }
-            // Try as OracleState (oracle charm contains nested PriceData)
-            if let Ok(oracle) = data.value::<OracleStateMinimal>() {
-                if oracle.is_active {
-                    return Some(oracle.price.price);
-                }
-            }
-        }
-    }
+/// Extract the price and circuit breaker from the price oracle charm in refs or inputs
+///
+/// The oracle is resolved strictly by `oracle_id`: a charm from any other
+/// app whose state decodes as an oracle fails the spell instead of being
+/// skipped, and so do two oracle charms (different VK or price) sharing it.
+fn extract_oracle(tx: &Transaction, oracle_id: &[u8; 32]) -> ZkUsdResult<OracleSnapshot> {
+    let claimants = tx.refs.iter()
+        .chain(tx.ins.iter())
+        .flat_map(|(_, charms)| charms.iter())
+        .filter_map(|(charm_app, data)| {
+            decode_oracle(data)
+                .map(|oracle| (charm_app.identity.0, (charm_app.vk.0, oracle)))
+        });
 
-    None
+    let (_, oracle) = require_companion(CompanionRole::PriceOracle, *oracle_id, claimants)?;
+    oracle.ok_or(ZkUsdError::OracleNotInitialized)
 }
 
-// ============ Flow Calculations ============
+/// Extract the BTC gain of a stability deposit spent in the same spell
+///
+/// The pool state is read from refs or inputs and the deposit from inputs,
+/// both resolved by `stability_pool_id`. A spent deposit with a pending
+/// gain means the pool is paying that gain out, so the depositor's share
+/// of it (see `StabilityDeposit::split_btc_gain`) is what an AddCollateral
+/// in the spell must deposit.
+fn extract_linked_btc_claim(tx: &Transaction, stability_pool_id: &[u8; 32]) -> Option<LinkedBtcClaim> {
+    let inputs = pool_charms(&tx.ins, stability_pool_id);
+
+    let pool = pool_charms(&tx.refs, stability_pool_id)
+        .iter()
+        .chain(inputs.iter())
+        .find_map(|data| data.value::<StabilityPoolState>().ok())?;
+    let deposit = inputs.iter().find_map(|data| data.value::<StabilityDeposit>().ok())?;
+
+    let gain = calculate_pending_btc(&deposit, &pool).ok()?;
+    let amount = Some(deposit.split_btc_gain(gain).ok()?.depositor).filter(|amount| *amount > 0)?;
+    Some(LinkedBtcClaim { depositor: deposit.owner, amount })
+}
 
-/// Calculate total BTC flowing in and out of transaction
-fn calculate_btc_flows(tx: &Transaction) -> (u64, u64) {
-    let inputs = tx.coin_ins
-        .as_ref()
-        .map(|ins| ins.iter().map(|o| o.amount).sum())
-        .unwrap_or(0);
+/// Extract a stability deposit referenced by the spell, valued at the
+/// pool's current P
+///
+/// The deposit is read from refs, so neither the discount nor the rebate
+/// spends the deposit that backs it. The pool state is read from refs or
+/// inputs, as `extract_linked_offset` reads it: a liquidation's offset
+/// spends the pool, and the deposit is valued against that same state.
+fn extract_linked_deposit(tx: &Transaction, stability_pool_id: &[u8; 32]) -> Option<LinkedDeposit> {
+    let refs = pool_charms(&tx.refs, stability_pool_id);
+    let pool = refs.iter()
+        .chain(pool_charms(&tx.ins, stability_pool_id).iter())
+        .find_map(|data| data.value::<StabilityPoolState>().ok())?;
+    let deposit = refs.iter().find_map(|data| data.value::<StabilityDeposit>().ok())?;
+
+    let value = calculate_compounded_deposit(
+        deposit.initial_value,
+        deposit.snapshot_p,
+        pool.product_p,
+        deposit.snapshot_scale,
+        pool.current_scale,
+        deposit.snapshot_epoch,
+        pool.current_epoch,
+    );
+    Some(LinkedDeposit { depositor: deposit.owner, value })
+}
 
-    let outputs = tx.coin_outs
-        .as_ref()
-        .map(|outs| outs.iter().map(|o| o.amount).sum())
-        .unwrap_or(0);
+/// Extract the stability pool state read by the spell and the offset it
+/// makes
+///
+/// The pool before the spell is read from refs or inputs, and after it from
+/// outputs, both resolved by `stability_pool_id`. A pool only referenced
+/// makes no offset; the stability pool app validates the offset itself.
+fn extract_linked_offset(tx: &Transaction, stability_pool_id: &[u8; 32]) -> Option<LinkedOffset> {
+    let decode = |data: &Data| data.value::<StabilityPoolState>().ok();
+    let pool = pool_charms(&tx.refs, stability_pool_id)
+        .iter()
+        .chain(pool_charms(&tx.ins, stability_pool_id).iter())
+        .find_map(decode)?;
+    let new_pool = tx.outs.iter()
+        .flat_map(|charms| charms.iter())
+        .filter(|(charm_app, _)| charm_app.identity.0 == *stability_pool_id)
+        .find_map(|(_, data)| decode(data));
+
+    let (debt, collateral) = match new_pool {
+        Some(new_pool) => (
+            pool.total_zkusd.saturating_sub(new_pool.total_zkusd),
+            new_pool.total_btc.saturating_sub(pool.total_btc),
+        ),
+        None => (0, 0),
+    };
+    Some(LinkedOffset { pool_zkusd: pool.total_zkusd, debt, collateral })
+}
 
-    (inputs, outputs)
+/// Charm data of the stability pool app in `utxos`
+fn pool_charms(utxos: &[(UtxoId, Charms)], stability_pool_id: &[u8; 32]) -> Vec<Data> {
+    utxos.iter()
+        .flat_map(|(_, charms)| charms.iter())
+        .filter(|(charm_app, _)| charm_app.identity.0 == *stability_pool_id)
+        .map(|(_, data)| data.clone())
+        .collect()
 }
 
+// ============ Flow Calculations ============
+
 /// Calculate zkUSD token flows in transaction
 fn calculate_zkusd_flows(tx: &Transaction, token_app_id: &[u8; 32]) -> (u64, u64) {
     let mut inputs: u64 = 0;
@@ -656,6 +1174,8 @@ fn extract_signer(tx: &Transaction) -> [u8; 32] {
 mod tests {
     use super::*;
     use std::collections::BTreeMap;
+    use zkusd_charms_compat::{TxId, UtxoId, B32};
+    use zkusd_common::types::PriceSource;
 
     fn create_test_witness() -> VaultWitness {
         VaultWitness::open_vault(100_000_000, 50_000_00000000)
@@ -678,7 +1198,7 @@ mod tests {
         let action = witness_to_action(&witness).unwrap();
 
         match action {
-            VaultAction::OpenVault { collateral, debt } => {
+            VaultAction::OpenVault { collateral: Sats(collateral), debt: ZkUsd(debt) } => {
                 assert_eq!(collateral, 100_000_000);
                 assert_eq!(debt, 50_000_00000000);
             }
@@ -686,6 +1206,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_for_action_round_trips() {
+        let (vault_id, insurance_id) = ([42u8; 32], [8u8; 32]);
+        let actions = [
+            VaultAction::OpenVault { collateral: Sats(100_000_000), debt: ZkUsd(50_000_00000000) },
+            VaultAction::CloseVault { vault_id },
+            VaultAction::AddCollateral { vault_id, amount: Sats(1_000) },
+            VaultAction::WithdrawCollateral { vault_id, amount: Sats(1_000) },
+            VaultAction::MintDebt { vault_id, amount: ZkUsd(1_000) },
+            VaultAction::RepayDebt { vault_id, amount: ZkUsd(1_000) },
+            VaultAction::Liquidate { vault_id },
+            VaultAction::Redeem { amount: ZkUsd(1_000), min_btc_out: Sats(10) },
+            VaultAction::FlashMint { amount: ZkUsd(1_000), purpose: 1 },
+            VaultAction::AtomicRescue {
+                vault_id,
+                collateral_to_add: Sats(1_000),
+                debt_to_repay: ZkUsd(1_000),
+                rescuer_discount: Sats(10),
+            },
+            VaultAction::PurchaseInsurance { vault_id, coverage_btc: Sats(1_000), premium: ZkUsd(10), trigger_icr: 150 },
+            VaultAction::TriggerInsurance { insurance_id, vault_id },
+            VaultAction::TransferInsurance { insurance_id, new_owner: [6u8; 32] },
+            VaultAction::SetFlashFee { fee_bps: 10 },
+            VaultAction::SetFeeDistribution {
+                distribution: FeeDistribution { treasury_bps: 5_000, stability_pool_bps: 5_000, staking_bps: 0 },
+            },
+            VaultAction::SetVaultOperator { vault_id, operator: Some([6u8; 32]) },
+            VaultAction::PokeBaseRate {},
+            VaultAction::SetProtection { vault_id, bps: 1_000 },
+            VaultAction::SetBeneficiary { vault_id, beneficiary: Some([6u8; 32]), inactivity_blocks: 1_000 },
+            VaultAction::SetBeneficiary { vault_id, beneficiary: None, inactivity_blocks: 0 },
+            VaultAction::ClaimAsBeneficiary { vault_id },
+            VaultAction::BeginLiquidation { vault_id },
+            VaultAction::ContinueLiquidation { vault_id, debt_portion: ZkUsd(1_000) },
+            VaultAction::ExpireInsurance { insurance_id, vault_id },
+            VaultAction::GraduateBootstrap {},
+            VaultAction::AccrueInterest { vault_id },
+        ];
+        for action in actions {
+            let data = Data::from(&VaultWitness::for_action(&action));
+            assert_eq!(witness_to_action(&parse_witness(&data).unwrap()), Some(action));
+        }
+    }
+
     #[test]
     fn test_liquidate_witness() {
         let vault_id = [42u8; 32];
@@ -699,4 +1263,308 @@ mod tests {
             _ => panic!("Expected Liquidate action"),
         }
     }
+
+    #[test]
+    fn test_set_flash_fee_witness() {
+        let witness = VaultWitness::set_flash_fee(25);
+        let action = witness_to_action(&witness).unwrap();
+
+        assert_eq!(action, VaultAction::SetFlashFee { fee_bps: 25 });
+    }
+
+    #[test]
+    fn test_set_vault_operator_witness() {
+        let vault_id = [7u8; 32];
+        let operator = [9u8; 32];
+
+        let action = witness_to_action(&VaultWitness::set_vault_operator(vault_id, Some(operator))).unwrap();
+        assert_eq!(action, VaultAction::SetVaultOperator { vault_id, operator: Some(operator) });
+
+        // No operator in the witness clears it
+        let action = witness_to_action(&VaultWitness::set_vault_operator(vault_id, None)).unwrap();
+        assert_eq!(action, VaultAction::SetVaultOperator { vault_id, operator: None });
+    }
+
+    #[test]
+    fn test_set_protection_witness() {
+        let vault_id = [7u8; 32];
+        let action = witness_to_action(&VaultWitness::set_protection(vault_id, 3_000)).unwrap();
+        assert_eq!(action, VaultAction::SetProtection { vault_id, bps: 3_000 });
+
+        // The protected share is required
+        let witness = VaultWitness { protection_bps: None, ..VaultWitness::set_protection(vault_id, 3_000) };
+        assert_eq!(witness_to_action(&witness), None);
+    }
+
+    #[test]
+    fn test_beneficiary_witnesses() {
+        let vault_id = [7u8; 32];
+        let heir = [9u8; 32];
+
+        let action = witness_to_action(&VaultWitness::set_beneficiary(vault_id, Some((heir, 26_280)))).unwrap();
+        assert_eq!(
+            action,
+            VaultAction::SetBeneficiary { vault_id, beneficiary: Some(heir), inactivity_blocks: 26_280 }
+        );
+
+        // A designation without its window is malformed; a clear needs none
+        let witness = VaultWitness { inactivity_blocks: None, ..VaultWitness::set_beneficiary(vault_id, Some((heir, 1))) };
+        assert_eq!(witness_to_action(&witness), None);
+        let action = witness_to_action(&VaultWitness::set_beneficiary(vault_id, None)).unwrap();
+        assert_eq!(action, VaultAction::SetBeneficiary { vault_id, beneficiary: None, inactivity_blocks: 0 });
+
+        let action = witness_to_action(&VaultWitness::claim_as_beneficiary(vault_id)).unwrap();
+        assert_eq!(action, VaultAction::ClaimAsBeneficiary { vault_id });
+    }
+
+    #[test]
+    fn test_set_fee_distribution_witness() {
+        let distribution = FeeDistribution { treasury_bps: 4_000, stability_pool_bps: 4_000, staking_bps: 2_000 };
+        let witness = VaultWitness::set_fee_distribution(distribution);
+        let action = witness_to_action(&witness).unwrap();
+
+        assert_eq!(action, VaultAction::SetFeeDistribution { distribution });
+    }
+
+    // ============ Companion Oracle Tests ============
+
+    const ORACLE_ID: [u8; 32] = [3u8; 32];
+    const BTC_PRICE_100K: u64 = 100_000_00000000;
+
+    fn oracle_app(identity: [u8; 32], vk: [u8; 32]) -> App {
+        App { tag: 'n', identity: B32(identity), vk: B32(vk) }
+    }
+
+    fn oracle_state(price: u64) -> OracleStateMinimal {
+        OracleStateMinimal {
+            price: PriceData {
+                price,
+                timestamp_block: 100,
+                source: PriceSource::Mock,
+                confidence: 100,
+                effective_from_block: 100,
+            },
+            is_active: true,
+            last_valid_price: price,
+            circuit_breaker: CircuitBreakerState::default(),
+            previous_price: PriceData::default(),
+            operator: [0u8; 32],
+        }
+    }
+
+    /// Spell with each oracle charm in its own reference input
+    fn tx_with_oracle_refs(oracles: Vec<(App, OracleStateMinimal)>) -> Transaction {
+        let refs = oracles
+            .into_iter()
+            .enumerate()
+            .map(|(i, (app, state))| {
+                let mut charms = BTreeMap::new();
+                charms.insert(app, Data::from(&state));
+                (UtxoId(TxId([i as u8; 32]), 0), charms)
+            })
+            .collect();
+
+        Transaction {
+            ins: Vec::new(),
+            refs,
+            outs: Vec::new(),
+            coin_ins: None,
+            coin_outs: None,
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_oracle_price_from_recorded_app() {
+        let tx = tx_with_oracle_refs(vec![(oracle_app(ORACLE_ID, [7u8; 32]), oracle_state(BTC_PRICE_100K))]);
+        assert_eq!(extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price), Ok(BTC_PRICE_100K));
+    }
+
+    #[test]
+    fn test_oracle_circuit_breaker_read_from_charm() {
+        let breaker = CircuitBreakerState {
+            tripped: true,
+            tripped_at: 100,
+            reference_price: BTC_PRICE_100K,
+            reference_block: 96,
+            ..CircuitBreakerState::default()
+        };
+        let state = OracleStateMinimal { circuit_breaker: breaker, ..oracle_state(BTC_PRICE_100K / 100 * 75) };
+        let tx = tx_with_oracle_refs(vec![(oracle_app(ORACLE_ID, [7u8; 32]), state)]);
+        assert_eq!(extract_oracle(&tx, &ORACLE_ID).map(|o| o.circuit_breaker), Ok(breaker));
+    }
+
+    #[test]
+    fn test_counterfeit_oracle_rejected() {
+        // Lookalike app whose state decodes cleanly, with a favorable price
+        let counterfeit = oracle_app([9u8; 32], [7u8; 32]);
+        let tx = tx_with_oracle_refs(vec![(counterfeit.clone(), oracle_state(1_000_000_00000000))]);
+        assert!(Data::from(&oracle_state(1)).value::<OracleStateMinimal>().is_ok());
+
+        let expected = Err(ZkUsdError::WrongCompanionApp {
+            expected: ORACLE_ID,
+            found: [9u8; 32],
+            role: CompanionRole::PriceOracle,
+        });
+        assert_eq!(extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price), expected);
+
+        // Still rejected when the genuine oracle is referenced alongside it
+        let tx = tx_with_oracle_refs(vec![
+            (oracle_app(ORACLE_ID, [7u8; 32]), oracle_state(BTC_PRICE_100K)),
+            (counterfeit, oracle_state(1_000_000_00000000)),
+        ]);
+        assert_eq!(extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price), expected);
+    }
+
+    #[test]
+    fn test_missing_oracle_rejected() {
+        // A price in public inputs carries no app identity and is ignored
+        let mut tx = tx_with_oracle_refs(Vec::new());
+        tx.app_public_inputs.insert(
+            oracle_app(ORACLE_ID, [7u8; 32]),
+            Data::from(&oracle_state(BTC_PRICE_100K).price),
+        );
+
+        assert_eq!(
+            extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price),
+            Err(ZkUsdError::WrongCompanionApp {
+                expected: ORACLE_ID,
+                found: [0u8; 32],
+                role: CompanionRole::PriceOracle,
+            })
+        );
+    }
+
+    // ============ Linked BTC Claim Tests ============
+
+    const POOL_ID: [u8; 32] = [2u8; 32];
+
+    fn pool_app(identity: [u8; 32]) -> App {
+        App { tag: 'n', identity: B32(identity), vk: B32([8u8; 32]) }
+    }
+
+    /// Spell spending a 1,000 zkUSD deposit with `gain` sats pending
+    /// against a referenced pool state
+    fn tx_with_deposit(pool_id: [u8; 32], gain: u64) -> Transaction {
+        let value = 1_000 * 100_000_000;
+        let pool = StabilityPoolState {
+            total_zkusd: value,
+            sum_s: u128::from(gain) * 10_000_000,
+            ..StabilityPoolState::new()
+        };
+        let deposit = StabilityDeposit {
+            owner: [5u8; 32],
+            initial_value: value,
+            snapshot_p: pool.product_p,
+            snapshot_s: 0,
+            snapshot_epoch: 0,
+            snapshot_scale: 0,
+            last_updated: 100,
+            frontend_tag: None,
+            kickback_bps: 0,
+        };
+
+        let utxo = |i: u8, data: Data| {
+            (UtxoId(TxId([i; 32]), 0), BTreeMap::from([(pool_app(pool_id), data)]))
+        };
+        let mut tx = tx_with_oracle_refs(Vec::new());
+        tx.refs.push(utxo(0, Data::from(&pool)));
+        tx.ins.push(utxo(1, Data::from(&deposit)));
+        tx
+    }
+
+    #[test]
+    fn test_linked_btc_claim_from_spent_deposit() {
+        let tx = tx_with_deposit(POOL_ID, 50_000);
+        assert_eq!(
+            extract_linked_btc_claim(&tx, &POOL_ID),
+            Some(LinkedBtcClaim { depositor: [5u8; 32], amount: 50_000 })
+        );
+
+        // No gain, or a deposit of another app, links nothing
+        assert_eq!(extract_linked_btc_claim(&tx_with_deposit(POOL_ID, 0), &POOL_ID), None);
+        assert_eq!(extract_linked_btc_claim(&tx_with_deposit([9u8; 32], 50_000), &POOL_ID), None);
+    }
+
+    #[test]
+    fn test_linked_deposit_from_referenced_deposit() {
+        // A spent deposit backs no discount
+        let mut tx = tx_with_deposit(POOL_ID, 0);
+        assert_eq!(extract_linked_deposit(&tx, &POOL_ID), None);
+
+        // Referenced, it is valued at the pool's current P: half after the
+        // pool lost half its zkUSD to offsets
+        let deposit = tx.ins.pop().unwrap();
+        tx.refs.push(deposit);
+        assert_eq!(
+            extract_linked_deposit(&tx, &POOL_ID),
+            Some(LinkedDeposit { depositor: [5u8; 32], value: 1_000 * 100_000_000 })
+        );
+        let (_, charms) = &mut tx.refs[0];
+        let pool = charms.values_mut().next().unwrap();
+        let mut state = pool.value::<StabilityPoolState>().unwrap();
+        state.product_p /= 2;
+        *pool = Data::from(&state);
+        assert_eq!(extract_linked_deposit(&tx, &POOL_ID).map(|d| d.value), Some(500 * 100_000_000));
+
+        // ...and against a pool the spell spends to offset debt
+        let pool = tx.refs.remove(0);
+        tx.ins.push(pool);
+        assert_eq!(extract_linked_deposit(&tx, &POOL_ID).map(|d| d.value), Some(500 * 100_000_000));
+    }
+
+    #[test]
+    fn test_referenced_pool_pays_no_rebate() {
+        let depositor = [5u8; 32];
+        let mut state = VaultManagerState::new([1u8; 32], [4u8; 32], POOL_ID, ORACLE_ID, [6u8; 32], [7u8; 32]).unwrap();
+        state.sp_liquidator_rebate_bps = 100;
+        state.protocol.accumulated_fees = 1_000 * 100_000_000;
+        let rebate = |tx: &Transaction| {
+            state.sp_liquidator_rebate(
+                extract_linked_deposit(tx, &POOL_ID).as_ref(),
+                &depositor,
+                extract_linked_offset(tx, &POOL_ID).as_ref(),
+            )
+        };
+
+        // The depositor's 1,000 zkUSD deposit is the whole pool, which the
+        // spell only references
+        let mut tx = tx_with_deposit(POOL_ID, 0);
+        let deposit = tx.ins.pop().unwrap();
+        tx.refs.push(deposit);
+        assert_eq!(extract_linked_offset(&tx, &POOL_ID).map(|o| o.debt), Some(0));
+        assert_eq!(rebate(&tx), Ok(0));
+
+        // Spent and offsetting 400 zkUSD, 1% of it is rebated
+        let pool = tx.refs.remove(0);
+        let mut offset_pool = pool.1.values().next().unwrap().value::<StabilityPoolState>().unwrap();
+        offset_pool.total_zkusd -= 400 * 100_000_000;
+        tx.ins.push(pool);
+        tx.outs.push(BTreeMap::from([(pool_app(POOL_ID), Data::from(&offset_pool))]));
+        assert_eq!(rebate(&tx), Ok(4 * 100_000_000));
+    }
+
+    #[test]
+    fn test_duplicate_oracle_claim_rejected() {
+        // Same app id, different VK
+        let tx = tx_with_oracle_refs(vec![
+            (oracle_app(ORACLE_ID, [7u8; 32]), oracle_state(BTC_PRICE_100K)),
+            (oracle_app(ORACLE_ID, [8u8; 32]), oracle_state(BTC_PRICE_100K)),
+        ]);
+        assert_eq!(
+            extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price),
+            Err(ZkUsdError::DuplicateCompanionApp { role: CompanionRole::PriceOracle })
+        );
+
+        // Same app, conflicting prices
+        let tx = tx_with_oracle_refs(vec![
+            (oracle_app(ORACLE_ID, [7u8; 32]), oracle_state(BTC_PRICE_100K)),
+            (oracle_app(ORACLE_ID, [7u8; 32]), oracle_state(BTC_PRICE_100K / 2)),
+        ]);
+        assert_eq!(
+            extract_oracle(&tx, &ORACLE_ID).map(|o| o.price.price),
+            Err(ZkUsdError::DuplicateCompanionApp { role: CompanionRole::PriceOracle })
+        );
+    }
 }
@@ -28,7 +28,7 @@
 //! └─ Protocol state  ────► └─ Protocol state (updated)
 //! ```
 
-use charms_sdk::data::{App, Data, Transaction};
+use zkusd_charms_compat::{App, Data, Transaction};
 
 /// Main validation function for Vault Manager operations.
 ///
@@ -55,4 +55,4 @@ pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
 }
 
 // Use the Charms SDK main macro to generate the entry point
-charms_sdk::main!(app_contract);
+zkusd_charms_compat::main!(app_contract);
@@ -0,0 +1,400 @@
+//! Wallet Display Metadata
+//!
+//! Display metadata for the charms wallets render: vaults, insurance charms
+//! and stability deposits. Each serializes to JSON under a versioned schema,
+//! so wallets need not know our struct layouts.
+//!
+//! Display only, but it must agree with the validators: every computed
+//! value comes from the shared math they use (`calculate_icr_bps`,
+//! `calculate_vault_health`, `liquidation_price`, and the deposit
+//! compounding and gain helpers), and amounts are formatted by the
+//! `Sats`/`ZkUsd` display impls.
+//!
+//! ## Schema (version 1)
+//!
+//! ```text
+//! {
+//!   "schema_version": "1",
+//!   "name": "zkUSD Vault #0a0b0c",        // kind and short id (first 3 bytes)
+//!   "id": "0a0b0c...",                    // full id, hex (vaults, insurance)
+//!   "owner": "tzkusd1...",                // bech32m address
+//!   "attributes": [{ "trait_type": "Debt", "value": "50000.00000000 zkUSD" }, ...],
+//!   "flags": { "insured": false, ... }    // booleans, per charm type
+//! }
+//! ```
+//!
+//! Every numeric value is a string, so JavaScript wallets never lose
+//! precision on amounts past 2^53. Values that cannot be computed (a
+//! liquidation price without collateral, a gain from an evicted epoch)
+//! read `"n/a"`. Adding fields is backward compatible; changing or removing
+//! one bumps `METADATA_SCHEMA_VERSION`.
+
+use serde::Serialize;
+
+use zkusd_common::{
+    address::{to_display, HRP},
+    constants::ratios,
+    errors::ZkUsdResult,
+    math::{calculate_compounded_deposit, calculate_icr_bps, calculate_pending_btc, liquidation_price},
+    types::{InsuranceCharm, StabilityDeposit, StabilityPoolState, Vault, VaultStatus},
+    units::{Sats, ZkUsd},
+    vault_manager::{calculate_vault_health, VmVaultStatus},
+};
+
+/// Version of the metadata JSON schema
+pub const METADATA_SCHEMA_VERSION: u32 = 1;
+
+/// Value shown for a field that cannot be computed
+const NOT_AVAILABLE: &str = "n/a";
+
+/// One displayed attribute of a charm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Attribute {
+    /// Attribute name
+    pub trait_type: &'static str,
+    /// Formatted value
+    pub value: String,
+}
+
+/// Display flags of a vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VaultFlags {
+    /// The vault holds an insurance balance
+    pub insured: bool,
+    /// The vault carries debt below the warning ICR
+    pub at_risk: bool,
+    /// A two-phase liquidation of the vault has begun
+    pub in_soft_liquidation: bool,
+}
+
+/// Display metadata of a vault charm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VaultMetadata {
+    /// `METADATA_SCHEMA_VERSION`
+    pub schema_version: String,
+    /// Display name: charm kind and short id
+    pub name: String,
+    /// Full id, in hex
+    pub id: String,
+    /// Owner address, bech32m
+    pub owner: String,
+    /// Displayed attributes, in order
+    pub attributes: Vec<Attribute>,
+    /// Display flags
+    pub flags: VaultFlags,
+}
+
+/// Display flags of an insurance charm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct InsuranceFlags {
+    /// Untriggered and unexpired
+    pub active: bool,
+    /// Triggered to protect its vault
+    pub triggered: bool,
+    /// Triggered, and its grace period is still running
+    pub in_grace_period: bool,
+}
+
+/// Display metadata of an insurance charm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InsuranceMetadata {
+    /// `METADATA_SCHEMA_VERSION`
+    pub schema_version: String,
+    /// Display name: charm kind and short id
+    pub name: String,
+    /// Full id, in hex
+    pub id: String,
+    /// Owner address, bech32m
+    pub owner: String,
+    /// Displayed attributes, in order
+    pub attributes: Vec<Attribute>,
+    /// Display flags
+    pub flags: InsuranceFlags,
+}
+
+/// Display flags of a stability deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DepositFlags {
+    /// Liquidations have left a BTC gain to claim
+    pub has_btc_gain: bool,
+    /// Liquidations have used up the whole deposit
+    pub depleted: bool,
+}
+
+/// Display metadata of a stability deposit charm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DepositMetadata {
+    /// `METADATA_SCHEMA_VERSION`
+    pub schema_version: String,
+    /// Display name: charm kind and short id
+    pub name: String,
+    /// Owner address, bech32m
+    pub owner: String,
+    /// Displayed attributes, in order
+    pub attributes: Vec<Attribute>,
+    /// Display flags
+    pub flags: DepositFlags,
+}
+
+/// Metadata of `vault` at `price` (8 decimals) and `block`
+pub fn vault_metadata(vault: &Vault, price: u64, block: u64) -> VaultMetadata {
+    let icr_bps = calculate_icr_bps(Sats(vault.collateral), ZkUsd(vault.debt), price);
+    let health = calculate_vault_health(vault, price, ratios::MCR * 100, block, None);
+    let at_risk = vault.debt > 0 && icr_bps.as_ref().map_or(true, |&icr| icr < ratios::WARNING_ICR * 100);
+
+    VaultMetadata {
+        schema_version: METADATA_SCHEMA_VERSION.to_string(),
+        name: format!("zkUSD Vault #{}", short_id(&vault.id)),
+        id: hex(&vault.id),
+        owner: to_display(&vault.owner, HRP),
+        attributes: vec![
+            attribute("Collateral", Sats(vault.collateral)),
+            attribute("Debt", ZkUsd(vault.debt)),
+            attribute("ICR", ratio(icr_bps, vault.debt)),
+            attribute("Health", health_name(health.status)),
+            attribute("Liquidation Price", price_or_na(liquidation_price(Sats(vault.collateral), ZkUsd(vault.debt)))),
+            attribute("Status", status_name(vault.status)),
+            attribute("Interest Rate", percent(vault.interest_rate_bps)),
+            attribute("Insurance", Sats(vault.insurance_balance)),
+        ],
+        flags: VaultFlags {
+            insured: vault.has_insurance(),
+            at_risk,
+            in_soft_liquidation: vault.pending_liquidation.is_some(),
+        },
+    }
+}
+
+/// Metadata of an insurance charm at `block`
+pub fn insurance_metadata(charm: &InsuranceCharm, block: u64) -> InsuranceMetadata {
+    InsuranceMetadata {
+        schema_version: METADATA_SCHEMA_VERSION.to_string(),
+        name: format!("zkUSD Insurance #{}", short_id(&charm.charm_id)),
+        id: hex(&charm.charm_id),
+        owner: to_display(&charm.owner, HRP),
+        attributes: vec![
+            attribute("Vault", short_id(&charm.vault_id)),
+            attribute("Coverage", Sats(charm.coverage_btc)),
+            attribute("Trigger ICR", percent(charm.trigger_icr * 100)),
+            attribute("Premium Paid", Sats(charm.premium_paid)),
+            attribute("Expires At Block", charm.expires_at),
+            attribute("Grace Blocks", charm.grace_blocks),
+        ],
+        flags: InsuranceFlags {
+            active: charm.is_active(block),
+            triggered: charm.is_triggered,
+            in_grace_period: charm.is_in_grace_period(block),
+        },
+    }
+}
+
+/// Metadata of a stability deposit in `pool`
+pub fn deposit_metadata(deposit: &StabilityDeposit, pool: &StabilityPoolState) -> DepositMetadata {
+    let compounded = calculate_compounded_deposit(
+        deposit.initial_value,
+        deposit.snapshot_p,
+        pool.product_p,
+        deposit.snapshot_scale,
+        pool.current_scale,
+        deposit.snapshot_epoch,
+        pool.current_epoch,
+    );
+    let gain = calculate_pending_btc(deposit, pool);
+
+    DepositMetadata {
+        schema_version: METADATA_SCHEMA_VERSION.to_string(),
+        name: format!("zkUSD Stability Deposit #{}", short_id(&deposit.owner)),
+        owner: to_display(&deposit.owner, HRP),
+        attributes: vec![
+            attribute("Deposited", ZkUsd(deposit.initial_value)),
+            attribute("Current Value", ZkUsd(compounded)),
+            attribute("BTC Gain", gain.as_ref().map_or_else(|_| NOT_AVAILABLE.to_string(), |&gain| Sats(gain).to_string())),
+            attribute("Last Updated Block", deposit.last_updated),
+        ],
+        flags: DepositFlags {
+            has_btc_gain: gain.is_ok_and(|gain| gain > 0),
+            depleted: compounded == 0,
+        },
+    }
+}
+
+/// Metadata as JSON
+pub fn to_json<T: Serialize>(metadata: &T) -> String {
+    serde_json::to_string(metadata).expect("metadata serializes to JSON")
+}
+
+fn attribute(trait_type: &'static str, value: impl ToString) -> Attribute {
+    Attribute { trait_type, value: value.to_string() }
+}
+
+/// Basis points as a percentage with two decimals
+fn percent(bps: u64) -> String {
+    format!("{}.{:02}%", bps / 100, bps % 100)
+}
+
+/// ICR in basis points as a percentage; unbounded without debt
+fn ratio(icr_bps: ZkUsdResult<u64>, debt: u64) -> String {
+    match icr_bps {
+        _ if debt == 0 => "unbounded".to_string(),
+        Ok(bps) => percent(bps),
+        Err(_) => NOT_AVAILABLE.to_string(),
+    }
+}
+
+/// BTC price (8 decimals) in zkUSD
+fn price_or_na(price: ZkUsdResult<u64>) -> String {
+    price.map_or_else(|_| NOT_AVAILABLE.to_string(), |price| ZkUsd(price).to_string())
+}
+
+fn health_name(status: VmVaultStatus) -> &'static str {
+    match status {
+        VmVaultStatus::Active => "healthy",
+        VmVaultStatus::AtRisk => "below_mcr",
+        VmVaultStatus::Liquidatable => "liquidatable",
+        VmVaultStatus::Closed => "closed",
+        VmVaultStatus::Liquidated => "liquidated",
+    }
+}
+
+fn status_name(status: VaultStatus) -> &'static str {
+    match status {
+        VaultStatus::Active => "active",
+        VaultStatus::Liquidating => "liquidating",
+        VaultStatus::Closed => "closed",
+        VaultStatus::Liquidated => "liquidated",
+    }
+}
+
+/// First three bytes of an id, in hex
+fn short_id(id: &[u8; 32]) -> String {
+    hex(&id[..3])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{BLOCK, BTC_PRICE_100K, ONE_BTC, ONE_ZKUSD, OWNER};
+    use zkusd_common::types::PendingLiquidation;
+
+    const VAULT_ID: [u8; 32] = [0xab; 32];
+
+    fn vault() -> Vault {
+        Vault::new(VAULT_ID, OWNER, 150_000_000, 50_000 * ONE_ZKUSD, BLOCK)
+    }
+
+    fn value<'a>(attributes: &'a [Attribute], trait_type: &str) -> &'a str {
+        &attributes.iter().find(|a| a.trait_type == trait_type).unwrap().value
+    }
+
+    #[test]
+    fn test_vault_metadata_golden() {
+        let metadata = vault_metadata(&vault(), BTC_PRICE_100K, BLOCK);
+        assert_eq!(
+            to_json(&metadata),
+            format!(
+                concat!(
+                    r#"{{"schema_version":"1","name":"zkUSD Vault #ababab","id":"{id}","owner":"{owner}","attributes":["#,
+                    r#"{{"trait_type":"Collateral","value":"150000000 sats"}},"#,
+                    r#"{{"trait_type":"Debt","value":"50000.00000000 zkUSD"}},"#,
+                    r#"{{"trait_type":"ICR","value":"300.00%"}},"#,
+                    r#"{{"trait_type":"Health","value":"healthy"}},"#,
+                    r#"{{"trait_type":"Liquidation Price","value":"36666.66666667 zkUSD"}},"#,
+                    r#"{{"trait_type":"Status","value":"active"}},"#,
+                    r#"{{"trait_type":"Interest Rate","value":"1.00%"}},"#,
+                    r#"{{"trait_type":"Insurance","value":"0 sats"}}],"#,
+                    r#""flags":{{"insured":false,"at_risk":false,"in_soft_liquidation":false}}}}"#,
+                ),
+                id = hex(&VAULT_ID),
+                owner = to_display(&OWNER, HRP),
+            )
+        );
+    }
+
+    #[test]
+    fn test_vault_metadata_follows_validator_math() {
+        // A vault under the warning ICR, insured, with a liquidation begun
+        let vault = Vault {
+            collateral: ONE_BTC * 6 / 10,
+            insurance_balance: 1_000,
+            pending_liquidation: Some(PendingLiquidation { trigger_price: BTC_PRICE_100K, started_at: BLOCK, bonus_bps: 0 }),
+            ..vault()
+        };
+        let metadata = vault_metadata(&vault, BTC_PRICE_100K, BLOCK);
+
+        let icr = calculate_icr_bps(Sats(vault.collateral), ZkUsd(vault.debt), BTC_PRICE_100K).unwrap();
+        let price = liquidation_price(Sats(vault.collateral), ZkUsd(vault.debt)).unwrap();
+        assert_eq!(value(&metadata.attributes, "ICR"), percent(icr));
+        assert_eq!(value(&metadata.attributes, "Liquidation Price"), ZkUsd(price).to_string());
+        assert_eq!(metadata.flags, VaultFlags { insured: true, at_risk: true, in_soft_liquidation: true });
+
+        // Below MCR at half the price; no debt has no ratio or price to show
+        let crashed = vault_metadata(&vault, BTC_PRICE_100K / 2, BLOCK);
+        assert_eq!(value(&crashed.attributes, "Health"), "below_mcr");
+        let repaid = vault_metadata(&Vault { debt: 0, ..vault }, BTC_PRICE_100K, BLOCK);
+        assert_eq!(value(&repaid.attributes, "ICR"), "unbounded");
+        assert_eq!(value(&repaid.attributes, "Liquidation Price"), "0.00000000 zkUSD");
+        assert!(!repaid.flags.at_risk);
+    }
+
+    #[test]
+    fn test_insurance_metadata_golden() {
+        let charm = InsuranceCharm::new([0xcd; 32], VAULT_ID, OWNER, ONE_BTC / 10, 25_000, 115, 144, BLOCK, 1_000);
+        assert_eq!(
+            to_json(&insurance_metadata(&charm, BLOCK)),
+            format!(
+                concat!(
+                    r#"{{"schema_version":"1","name":"zkUSD Insurance #cdcdcd","id":"{id}","owner":"{owner}","attributes":["#,
+                    r#"{{"trait_type":"Vault","value":"ababab"}},"#,
+                    r#"{{"trait_type":"Coverage","value":"10000000 sats"}},"#,
+                    r#"{{"trait_type":"Trigger ICR","value":"115.00%"}},"#,
+                    r#"{{"trait_type":"Premium Paid","value":"25000 sats"}},"#,
+                    r#"{{"trait_type":"Expires At Block","value":"1100"}},"#,
+                    r#"{{"trait_type":"Grace Blocks","value":"144"}}],"#,
+                    r#""flags":{{"active":true,"triggered":false,"in_grace_period":false}}}}"#,
+                ),
+                id = hex(&[0xcd; 32]),
+                owner = to_display(&OWNER, HRP),
+            )
+        );
+        assert!(!insurance_metadata(&charm, 1_100).flags.active);
+    }
+
+    #[test]
+    fn test_deposit_metadata_golden() {
+        let mut pool = StabilityPoolState::new();
+        let deposit = StabilityDeposit {
+            owner: OWNER,
+            initial_value: 10_000 * ONE_ZKUSD,
+            snapshot_p: pool.product_p,
+            snapshot_s: pool.sum_s,
+            snapshot_epoch: pool.current_epoch,
+            snapshot_scale: pool.current_scale,
+            last_updated: BLOCK,
+            frontend_tag: None,
+            kickback_bps: 0,
+        };
+        // An offset of a fifth of the pool against 0.05 BTC
+        pool.product_p = pool.product_p * 4 / 5;
+        pool.sum_s += 5_000_000u128 * deposit.snapshot_p / deposit.initial_value as u128;
+
+        let metadata = deposit_metadata(&deposit, &pool);
+        assert_eq!(
+            to_json(&metadata),
+            format!(
+                concat!(
+                    r#"{{"schema_version":"1","name":"zkUSD Stability Deposit #010101","owner":"{owner}","attributes":["#,
+                    r#"{{"trait_type":"Deposited","value":"10000.00000000 zkUSD"}},"#,
+                    r#"{{"trait_type":"Current Value","value":"8000.00000000 zkUSD"}},"#,
+                    r#"{{"trait_type":"BTC Gain","value":"5000000 sats"}},"#,
+                    r#"{{"trait_type":"Last Updated Block","value":"100"}}],"#,
+                    r#""flags":{{"has_btc_gain":true,"depleted":false}}}}"#,
+                ),
+                owner = to_display(&OWNER, HRP),
+            )
+        );
+    }
+}
@@ -0,0 +1,699 @@
+//! Vault Manager State Migrations
+//!
+//! The v1 and v2 layouts of `VaultManagerState` and their migrations to
+//! v3, and the rule holding a spell that spends an older charm to the
+//! migrated values.
+//!
+//! ## Vault Manager State, v2 to v3
+//!
+//! v3 adds `sp_liquidator_rebate_bps`, migrated at 0: the rebate stays off
+//! until governance sets it.
+//!
+//! ## Vault Manager State, v3 to v4
+//!
+//! v4 adds `min_liquidation_confidence`, migrated at
+//! `MIN_LIQUIDATION_CONFIDENCE`, the gate liquidations applied before it
+//! became a parameter.
+//!
+//! ## Implicit migration
+//!
+//! No action migrates a charm on its own. Any action may spend a vault or
+//! a state written at an older version: `validate` migrates it first,
+//! through `zkusd_common::migrations`, `migrate_state_v1_v2`,
+//! `migrate_state_v2_v3` and `migrate_state_v3_v4`, and validates the
+//! action against the result. The outputs must then be that migration with
+//! the action's changes on top:
+//!
+//! - every field the v1 vault lacked comes out at its migrated value,
+//!   unless the action itself sets it;
+//! - the state's governance parameters come out at their migrated values,
+//!   but for those the action itself sets.
+//!
+//! Outputs are always written at the current version.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+use zkusd_common::{
+    constants::oracle,
+    errors::ZkUsdResult,
+    migrations::{migrate_protocol_v1_v2, ProtocolStateV1, V2},
+    token_ops::MintTracker,
+    types::{Address, AppId, FeeDistribution, FeeSplit, InsuranceFund, ProtocolState, Vault},
+    versioning::INITIAL_STATE_VERSION,
+};
+
+use crate::VaultManagerState;
+
+/// Version of the Vault Manager state layout that adds
+/// `sp_liquidator_rebate_bps`
+pub const V3: u8 = V2 + 1;
+
+/// Version of the Vault Manager state layout that adds
+/// `min_liquidation_confidence`
+pub const V4: u8 = V3 + 1;
+
+/// Vault Manager state as laid out at version 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct VaultManagerStateV1 {
+    /// Layout version, always 1
+    pub version: u8,
+    /// Protocol-wide state
+    pub protocol: ProtocolStateV1,
+    /// zkUSD Token app_id
+    pub zkusd_token_id: AppId,
+    /// Stability Pool app_id
+    pub stability_pool_id: AppId,
+    /// Price Oracle app_id
+    pub price_oracle_id: AppId,
+    /// Active Pool address (holds active collateral)
+    pub active_pool: Address,
+    /// Default Pool address (holds liquidated collateral)
+    pub default_pool: Address,
+}
+
+impl From<VaultManagerState> for VaultManagerStateV1 {
+    /// The fields a v1 state carries; any later field a value decoded at
+    /// version 1 holds is dropped rather than carried over
+    fn from(state: VaultManagerState) -> Self {
+        Self {
+            version: INITIAL_STATE_VERSION,
+            protocol: ProtocolStateV1::from(state.protocol),
+            zkusd_token_id: state.zkusd_token_id,
+            stability_pool_id: state.stability_pool_id,
+            price_oracle_id: state.price_oracle_id,
+            active_pool: state.active_pool,
+            default_pool: state.default_pool,
+        }
+    }
+}
+
+/// Vault Manager state as laid out at version 2
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct VaultManagerStateV2 {
+    /// Layout version, always 2
+    pub version: u8,
+    /// Protocol-wide state
+    pub protocol: ProtocolState,
+    /// zkUSD Token app_id
+    pub zkusd_token_id: AppId,
+    /// Stability Pool app_id
+    pub stability_pool_id: AppId,
+    /// Price Oracle app_id
+    pub price_oracle_id: AppId,
+    /// Active Pool address (holds active collateral)
+    pub active_pool: Address,
+    /// Default Pool address (holds liquidated collateral)
+    pub default_pool: Address,
+    /// Blocks after creation during which a vault is skipped by redemptions
+    pub redemption_lockout_blocks: u64,
+    /// Share of insurance coverage paid out on the first trigger (BPS)
+    pub insurance_initial_payout_bps: u64,
+    /// Lifetime zkUSD minted per owner, under an optional per-owner cap
+    pub mint_tracker: MintTracker,
+    /// Split of borrowing fees between treasury, Stability Pool and stakers
+    pub fee_distribution: FeeDistribution,
+    /// Borrowing fees collected so far, per destination
+    pub collected_fees: FeeSplit,
+    /// Number of vault registry shards
+    pub registry_shards: u16,
+    /// Buffer above MCR a new vault must open with (BPS)
+    pub open_buffer_bps: u64,
+    /// Whether old vaults get the loyalty discount on borrowing fees
+    pub loyalty_discount_enabled: bool,
+    /// Blocks a vault must wait between owner changes
+    pub operation_cooldown_blocks: u64,
+    /// Most vaults a batch liquidation may liquidate in one block
+    pub max_liquidations_per_block: u64,
+    /// Smallest nonzero debt change
+    pub min_adjustment: u64,
+    /// Smallest nonzero collateral change
+    pub min_collateral_adjustment: u64,
+    /// Borrowing fee discount for borrowers holding a stability deposit
+    pub depositor_discount_bps: u64,
+    /// Smallest stability deposit value earning the depositor discount
+    pub depositor_discount_min_deposit: u64,
+    /// Liquidator bonus paid in Recovery Mode (BPS)
+    pub recovery_liquidator_bonus_bps: u64,
+    /// ICR (percentage) below which an operation warns the vault is at risk
+    pub warning_icr: u64,
+    /// Insurance coverage sold against the premiums backing it
+    pub insurance_fund: InsuranceFund,
+    /// Flash mint purposes allowed, as a mask of `FlashMintPurpose::bit`
+    pub allowed_flash_mint_purposes: u8,
+    /// Most total debt the protocol may carry (zkUSD base units)
+    pub global_debt_ceiling: u64,
+}
+
+impl From<VaultManagerState> for VaultManagerStateV2 {
+    /// The fields a v2 state carries; any later field a value decoded at
+    /// version 2 holds is dropped rather than carried over
+    fn from(state: VaultManagerState) -> Self {
+        Self {
+            version: V2,
+            protocol: state.protocol,
+            zkusd_token_id: state.zkusd_token_id,
+            stability_pool_id: state.stability_pool_id,
+            price_oracle_id: state.price_oracle_id,
+            active_pool: state.active_pool,
+            default_pool: state.default_pool,
+            redemption_lockout_blocks: state.redemption_lockout_blocks,
+            insurance_initial_payout_bps: state.insurance_initial_payout_bps,
+            mint_tracker: state.mint_tracker,
+            fee_distribution: state.fee_distribution,
+            collected_fees: state.collected_fees,
+            registry_shards: state.registry_shards,
+            open_buffer_bps: state.open_buffer_bps,
+            loyalty_discount_enabled: state.loyalty_discount_enabled,
+            operation_cooldown_blocks: state.operation_cooldown_blocks,
+            max_liquidations_per_block: state.max_liquidations_per_block,
+            min_adjustment: state.min_adjustment,
+            min_collateral_adjustment: state.min_collateral_adjustment,
+            depositor_discount_bps: state.depositor_discount_bps,
+            depositor_discount_min_deposit: state.depositor_discount_min_deposit,
+            recovery_liquidator_bonus_bps: state.recovery_liquidator_bonus_bps,
+            warning_icr: state.warning_icr,
+            insurance_fund: state.insurance_fund,
+            allowed_flash_mint_purposes: state.allowed_flash_mint_purposes,
+            global_debt_ceiling: state.global_debt_ceiling,
+        }
+    }
+}
+
+/// Vault Manager state as laid out at version 3
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct VaultManagerStateV3 {
+    /// Layout version, always 3
+    pub version: u8,
+    /// Protocol-wide state
+    pub protocol: ProtocolState,
+    /// zkUSD Token app_id
+    pub zkusd_token_id: AppId,
+    /// Stability Pool app_id
+    pub stability_pool_id: AppId,
+    /// Price Oracle app_id
+    pub price_oracle_id: AppId,
+    /// Active Pool address (holds active collateral)
+    pub active_pool: Address,
+    /// Default Pool address (holds liquidated collateral)
+    pub default_pool: Address,
+    /// Blocks after creation during which a vault is skipped by redemptions
+    pub redemption_lockout_blocks: u64,
+    /// Share of insurance coverage paid out on the first trigger (BPS)
+    pub insurance_initial_payout_bps: u64,
+    /// Lifetime zkUSD minted per owner, under an optional per-owner cap
+    pub mint_tracker: MintTracker,
+    /// Split of borrowing fees between treasury, Stability Pool and stakers
+    pub fee_distribution: FeeDistribution,
+    /// Borrowing fees collected so far, per destination
+    pub collected_fees: FeeSplit,
+    /// Number of vault registry shards
+    pub registry_shards: u16,
+    /// Buffer above MCR a new vault must open with (BPS)
+    pub open_buffer_bps: u64,
+    /// Whether old vaults get the loyalty discount on borrowing fees
+    pub loyalty_discount_enabled: bool,
+    /// Blocks a vault must wait between owner changes
+    pub operation_cooldown_blocks: u64,
+    /// Most vaults a batch liquidation may liquidate in one block
+    pub max_liquidations_per_block: u64,
+    /// Smallest nonzero debt change
+    pub min_adjustment: u64,
+    /// Smallest nonzero collateral change
+    pub min_collateral_adjustment: u64,
+    /// Borrowing fee discount for borrowers holding a stability deposit
+    pub depositor_discount_bps: u64,
+    /// Smallest stability deposit value earning the depositor discount
+    pub depositor_discount_min_deposit: u64,
+    /// Liquidator bonus paid in Recovery Mode (BPS)
+    pub recovery_liquidator_bonus_bps: u64,
+    /// ICR (percentage) below which an operation warns the vault is at risk
+    pub warning_icr: u64,
+    /// Insurance coverage sold against the premiums backing it
+    pub insurance_fund: InsuranceFund,
+    /// Flash mint purposes allowed, as a mask of `FlashMintPurpose::bit`
+    pub allowed_flash_mint_purposes: u8,
+    /// Most total debt the protocol may carry (zkUSD base units)
+    pub global_debt_ceiling: u64,
+    /// Rebate for a liquidator whose own stability deposit absorbs part
+    /// of the offset (BPS)
+    pub sp_liquidator_rebate_bps: u64,
+}
+
+impl From<VaultManagerState> for VaultManagerStateV3 {
+    /// The fields a v3 state carries; any later field a value decoded at
+    /// version 3 holds is dropped rather than carried over
+    fn from(state: VaultManagerState) -> Self {
+        Self {
+            version: V3,
+            protocol: state.protocol,
+            zkusd_token_id: state.zkusd_token_id,
+            stability_pool_id: state.stability_pool_id,
+            price_oracle_id: state.price_oracle_id,
+            active_pool: state.active_pool,
+            default_pool: state.default_pool,
+            redemption_lockout_blocks: state.redemption_lockout_blocks,
+            insurance_initial_payout_bps: state.insurance_initial_payout_bps,
+            mint_tracker: state.mint_tracker,
+            fee_distribution: state.fee_distribution,
+            collected_fees: state.collected_fees,
+            registry_shards: state.registry_shards,
+            open_buffer_bps: state.open_buffer_bps,
+            loyalty_discount_enabled: state.loyalty_discount_enabled,
+            operation_cooldown_blocks: state.operation_cooldown_blocks,
+            max_liquidations_per_block: state.max_liquidations_per_block,
+            min_adjustment: state.min_adjustment,
+            min_collateral_adjustment: state.min_collateral_adjustment,
+            depositor_discount_bps: state.depositor_discount_bps,
+            depositor_discount_min_deposit: state.depositor_discount_min_deposit,
+            recovery_liquidator_bonus_bps: state.recovery_liquidator_bonus_bps,
+            warning_icr: state.warning_icr,
+            insurance_fund: state.insurance_fund,
+            allowed_flash_mint_purposes: state.allowed_flash_mint_purposes,
+            global_debt_ceiling: state.global_debt_ceiling,
+            sp_liquidator_rebate_bps: state.sp_liquidator_rebate_bps,
+        }
+    }
+}
+
+/// State `old` migrated to version 2, every later field at the value
+/// `VaultManagerState::new` starts a state with
+///
+/// # Errors
+/// Returns `ZkUsdError::InvalidAddress` if either pool address is zero,
+/// as no v1 state could have been created with one.
+pub fn migrate_state_v1_v2(old: VaultManagerStateV1) -> ZkUsdResult<VaultManagerStateV2> {
+    let fresh = VaultManagerState::new(
+        old.protocol.admin,
+        old.zkusd_token_id,
+        old.stability_pool_id,
+        old.price_oracle_id,
+        old.active_pool,
+        old.default_pool,
+    )?;
+    Ok(VaultManagerStateV2::from(VaultManagerState { protocol: migrate_protocol_v1_v2(old.protocol), ..fresh }))
+}
+
+/// State `old` migrated to version 3, with the stability pool liquidator
+/// rebate off
+pub fn migrate_state_v2_v3(old: VaultManagerStateV2) -> VaultManagerStateV3 {
+    VaultManagerStateV3 {
+        version: V3,
+        protocol: old.protocol,
+        zkusd_token_id: old.zkusd_token_id,
+        stability_pool_id: old.stability_pool_id,
+        price_oracle_id: old.price_oracle_id,
+        active_pool: old.active_pool,
+        default_pool: old.default_pool,
+        redemption_lockout_blocks: old.redemption_lockout_blocks,
+        insurance_initial_payout_bps: old.insurance_initial_payout_bps,
+        mint_tracker: old.mint_tracker,
+        fee_distribution: old.fee_distribution,
+        collected_fees: old.collected_fees,
+        registry_shards: old.registry_shards,
+        open_buffer_bps: old.open_buffer_bps,
+        loyalty_discount_enabled: old.loyalty_discount_enabled,
+        operation_cooldown_blocks: old.operation_cooldown_blocks,
+        max_liquidations_per_block: old.max_liquidations_per_block,
+        min_adjustment: old.min_adjustment,
+        min_collateral_adjustment: old.min_collateral_adjustment,
+        depositor_discount_bps: old.depositor_discount_bps,
+        depositor_discount_min_deposit: old.depositor_discount_min_deposit,
+        recovery_liquidator_bonus_bps: old.recovery_liquidator_bonus_bps,
+        warning_icr: old.warning_icr,
+        insurance_fund: old.insurance_fund,
+        allowed_flash_mint_purposes: old.allowed_flash_mint_purposes,
+        global_debt_ceiling: old.global_debt_ceiling,
+        sp_liquidator_rebate_bps: 0,
+    }
+}
+
+/// State `old` migrated to version 4, liquidations gated at the confidence
+/// they required before it was a parameter
+pub fn migrate_state_v3_v4(old: VaultManagerStateV3) -> VaultManagerState {
+    VaultManagerState {
+        version: V4,
+        protocol: old.protocol,
+        zkusd_token_id: old.zkusd_token_id,
+        stability_pool_id: old.stability_pool_id,
+        price_oracle_id: old.price_oracle_id,
+        active_pool: old.active_pool,
+        default_pool: old.default_pool,
+        redemption_lockout_blocks: old.redemption_lockout_blocks,
+        insurance_initial_payout_bps: old.insurance_initial_payout_bps,
+        mint_tracker: old.mint_tracker,
+        fee_distribution: old.fee_distribution,
+        collected_fees: old.collected_fees,
+        registry_shards: old.registry_shards,
+        open_buffer_bps: old.open_buffer_bps,
+        loyalty_discount_enabled: old.loyalty_discount_enabled,
+        operation_cooldown_blocks: old.operation_cooldown_blocks,
+        max_liquidations_per_block: old.max_liquidations_per_block,
+        min_adjustment: old.min_adjustment,
+        min_collateral_adjustment: old.min_collateral_adjustment,
+        depositor_discount_bps: old.depositor_discount_bps,
+        depositor_discount_min_deposit: old.depositor_discount_min_deposit,
+        recovery_liquidator_bonus_bps: old.recovery_liquidator_bonus_bps,
+        warning_icr: old.warning_icr,
+        insurance_fund: old.insurance_fund,
+        allowed_flash_mint_purposes: old.allowed_flash_mint_purposes,
+        global_debt_ceiling: old.global_debt_ceiling,
+        sp_liquidator_rebate_bps: old.sp_liquidator_rebate_bps,
+        min_liquidation_confidence: oracle::MIN_LIQUIDATION_CONFIDENCE,
+    }
+}
+
+/// Expected vault output for a spell spending `migrated`, a vault just
+/// migrated from v1: `output` with every field the v1 layout lacked reset
+/// to its migrated value, for the validators to constrain further
+pub fn expected_after_migration(migrated: &Vault, output: &Vault) -> Vault {
+    Vault {
+        operator: migrated.operator,
+        twa_collateral: migrated.twa_collateral,
+        twa_updated_at: migrated.twa_updated_at,
+        stats: migrated.stats,
+        protected_collateral_bps: migrated.protected_collateral_bps,
+        beneficiary: migrated.beneficiary,
+        adjustment_window: migrated.adjustment_window,
+        rate_mode: migrated.rate_mode,
+        pending_liquidation: migrated.pending_liquidation,
+        ..output.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkusd_common::{errors::ZkUsdError, versioning::VersionedState};
+
+    /// Layouts of the state before this change, copied verbatim, to
+    /// capture v1 fixtures independently of `VaultManagerStateV1`
+    mod pre_change {
+        use borsh::BorshSerialize;
+
+        use zkusd_common::types::{Address, AppId};
+
+        #[derive(BorshSerialize)]
+        pub struct ProtocolState {
+            pub total_collateral: u64,
+            pub total_debt: u64,
+            pub active_vault_count: u64,
+            pub base_rate: u64,
+            pub last_fee_update_block: u64,
+            pub admin: Address,
+            pub is_paused: bool,
+        }
+
+        #[derive(BorshSerialize)]
+        pub struct VaultManagerState {
+            pub protocol: ProtocolState,
+            pub zkusd_token_id: AppId,
+            pub stability_pool_id: AppId,
+            pub price_oracle_id: AppId,
+            pub active_pool: Address,
+            pub default_pool: Address,
+        }
+    }
+
+    /// Layout of the state before `sp_liquidator_rebate_bps`, copied
+    /// verbatim, to capture v2 fixtures independently of
+    /// `VaultManagerStateV2`
+    mod before_rebate {
+        use borsh::BorshSerialize;
+
+        use zkusd_common::{
+            token_ops::MintTracker,
+            types::{Address, AppId, FeeDistribution, FeeSplit, InsuranceFund, ProtocolState},
+        };
+
+        #[derive(BorshSerialize)]
+        pub struct VaultManagerState {
+            pub version: u8,
+            pub protocol: ProtocolState,
+            pub zkusd_token_id: AppId,
+            pub stability_pool_id: AppId,
+            pub price_oracle_id: AppId,
+            pub active_pool: Address,
+            pub default_pool: Address,
+            pub redemption_lockout_blocks: u64,
+            pub insurance_initial_payout_bps: u64,
+            pub mint_tracker: MintTracker,
+            pub fee_distribution: FeeDistribution,
+            pub collected_fees: FeeSplit,
+            pub registry_shards: u16,
+            pub open_buffer_bps: u64,
+            pub loyalty_discount_enabled: bool,
+            pub operation_cooldown_blocks: u64,
+            pub max_liquidations_per_block: u64,
+            pub min_adjustment: u64,
+            pub min_collateral_adjustment: u64,
+            pub depositor_discount_bps: u64,
+            pub depositor_discount_min_deposit: u64,
+            pub recovery_liquidator_bonus_bps: u64,
+            pub warning_icr: u64,
+            pub insurance_fund: InsuranceFund,
+            pub allowed_flash_mint_purposes: u8,
+            pub global_debt_ceiling: u64,
+        }
+    }
+
+    /// Layout of the state before `min_liquidation_confidence`, copied
+    /// verbatim, to capture v3 fixtures independently of
+    /// `VaultManagerStateV3`
+    mod before_confidence {
+        use borsh::BorshSerialize;
+
+        use zkusd_common::{
+            token_ops::MintTracker,
+            types::{Address, AppId, FeeDistribution, FeeSplit, InsuranceFund, ProtocolState},
+        };
+
+        #[derive(BorshSerialize)]
+        pub struct VaultManagerState {
+            pub version: u8,
+            pub protocol: ProtocolState,
+            pub zkusd_token_id: AppId,
+            pub stability_pool_id: AppId,
+            pub price_oracle_id: AppId,
+            pub active_pool: Address,
+            pub default_pool: Address,
+            pub redemption_lockout_blocks: u64,
+            pub insurance_initial_payout_bps: u64,
+            pub mint_tracker: MintTracker,
+            pub fee_distribution: FeeDistribution,
+            pub collected_fees: FeeSplit,
+            pub registry_shards: u16,
+            pub open_buffer_bps: u64,
+            pub loyalty_discount_enabled: bool,
+            pub operation_cooldown_blocks: u64,
+            pub max_liquidations_per_block: u64,
+            pub min_adjustment: u64,
+            pub min_collateral_adjustment: u64,
+            pub depositor_discount_bps: u64,
+            pub depositor_discount_min_deposit: u64,
+            pub recovery_liquidator_bonus_bps: u64,
+            pub warning_icr: u64,
+            pub insurance_fund: InsuranceFund,
+            pub allowed_flash_mint_purposes: u8,
+            pub global_debt_ceiling: u64,
+            pub sp_liquidator_rebate_bps: u64,
+        }
+    }
+
+    fn pre_change_state() -> pre_change::VaultManagerState {
+        pre_change::VaultManagerState {
+            protocol: pre_change::ProtocolState {
+                total_collateral: 300_000_000,
+                total_debt: 100_000_00000000,
+                active_vault_count: 2,
+                base_rate: 75,
+                last_fee_update_block: 90,
+                admin: [0x0A; 32],
+                is_paused: false,
+            },
+            zkusd_token_id: [0x01; 32],
+            stability_pool_id: [0x02; 32],
+            price_oracle_id: [0x03; 32],
+            active_pool: [0x04; 32],
+            default_pool: [0x05; 32],
+        }
+    }
+
+    /// Borsh bytes of `pre_change_state` behind a v1 version byte
+    fn v1_fixture() -> Vec<u8> {
+        let mut bytes = Vec::from([INITIAL_STATE_VERSION]);
+        bytes.extend(borsh::to_vec(&pre_change_state()).unwrap());
+        bytes
+    }
+
+    fn migrated_state() -> VaultManagerState {
+        let mut state =
+            VaultManagerState::new([0x0A; 32], [0x01; 32], [0x02; 32], [0x03; 32], [0x04; 32], [0x05; 32]).unwrap();
+        state.protocol = ProtocolState {
+            total_collateral: 300_000_000,
+            total_debt: 100_000_00000000,
+            active_vault_count: 2,
+            base_rate: 75,
+            last_fee_update_block: 90,
+            ..state.protocol
+        };
+        state
+    }
+
+    /// Borsh bytes of `migrated_state` as a v2 state, a few governance
+    /// parameters set
+    fn v2_fixture() -> Vec<u8> {
+        let state = migrated_state();
+        borsh::to_vec(&before_rebate::VaultManagerState {
+            version: V2,
+            protocol: state.protocol,
+            zkusd_token_id: state.zkusd_token_id,
+            stability_pool_id: state.stability_pool_id,
+            price_oracle_id: state.price_oracle_id,
+            active_pool: state.active_pool,
+            default_pool: state.default_pool,
+            redemption_lockout_blocks: state.redemption_lockout_blocks,
+            insurance_initial_payout_bps: state.insurance_initial_payout_bps,
+            mint_tracker: state.mint_tracker,
+            fee_distribution: state.fee_distribution,
+            collected_fees: state.collected_fees,
+            registry_shards: 4,
+            open_buffer_bps: state.open_buffer_bps,
+            loyalty_discount_enabled: state.loyalty_discount_enabled,
+            operation_cooldown_blocks: state.operation_cooldown_blocks,
+            max_liquidations_per_block: state.max_liquidations_per_block,
+            min_adjustment: state.min_adjustment,
+            min_collateral_adjustment: state.min_collateral_adjustment,
+            depositor_discount_bps: 25,
+            depositor_discount_min_deposit: state.depositor_discount_min_deposit,
+            recovery_liquidator_bonus_bps: state.recovery_liquidator_bonus_bps,
+            warning_icr: state.warning_icr,
+            insurance_fund: state.insurance_fund,
+            allowed_flash_mint_purposes: state.allowed_flash_mint_purposes,
+            global_debt_ceiling: 5_000_000 * 100_000_000,
+        })
+        .unwrap()
+    }
+
+    /// Borsh bytes of `migrated_state` as a v3 state, the rebate and a few
+    /// governance parameters set
+    fn v3_fixture() -> Vec<u8> {
+        let state = migrated_state();
+        borsh::to_vec(&before_confidence::VaultManagerState {
+            version: V3,
+            protocol: state.protocol,
+            zkusd_token_id: state.zkusd_token_id,
+            stability_pool_id: state.stability_pool_id,
+            price_oracle_id: state.price_oracle_id,
+            active_pool: state.active_pool,
+            default_pool: state.default_pool,
+            redemption_lockout_blocks: state.redemption_lockout_blocks,
+            insurance_initial_payout_bps: state.insurance_initial_payout_bps,
+            mint_tracker: state.mint_tracker,
+            fee_distribution: state.fee_distribution,
+            collected_fees: state.collected_fees,
+            registry_shards: 4,
+            open_buffer_bps: state.open_buffer_bps,
+            loyalty_discount_enabled: state.loyalty_discount_enabled,
+            operation_cooldown_blocks: state.operation_cooldown_blocks,
+            max_liquidations_per_block: state.max_liquidations_per_block,
+            min_adjustment: state.min_adjustment,
+            min_collateral_adjustment: state.min_collateral_adjustment,
+            depositor_discount_bps: 25,
+            depositor_discount_min_deposit: state.depositor_discount_min_deposit,
+            recovery_liquidator_bonus_bps: state.recovery_liquidator_bonus_bps,
+            warning_icr: state.warning_icr,
+            insurance_fund: state.insurance_fund,
+            allowed_flash_mint_purposes: state.allowed_flash_mint_purposes,
+            global_debt_ceiling: 5_000_000 * 100_000_000,
+            sp_liquidator_rebate_bps: 100,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_v1_state_fixture_migrates() {
+        let state = VaultManagerState::migrate(&v1_fixture()).unwrap();
+        assert_eq!(state, migrated_state());
+        assert_eq!(state.version, V4);
+
+        // ...and a current state round-trips as is
+        assert_eq!(VaultManagerState::migrate(&borsh::to_vec(&state).unwrap()), Ok(state));
+
+        let mut padded = v1_fixture();
+        padded.push(0);
+        assert_eq!(VaultManagerState::migrate(&padded), Err(ZkUsdError::InvalidSpellFormat));
+    }
+
+    #[test]
+    fn test_v1_state_drops_later_fields() {
+        // A state decoded at v1 loses whatever later fields it claims
+        let mut decoded = migrated_state();
+        decoded.version = INITIAL_STATE_VERSION;
+        decoded.recovery_liquidator_bonus_bps = 0;
+        decoded.protocol.flash_fee_bps = 1;
+        decoded.registry_shards = 4;
+        assert_eq!(decoded.upgrade(), Ok(migrated_state()));
+    }
+
+    #[test]
+    fn test_v2_state_fixture_migrates() {
+        let state = VaultManagerState::migrate(&v2_fixture()).unwrap();
+        assert_eq!(
+            state,
+            VaultManagerState {
+                registry_shards: 4,
+                depositor_discount_bps: 25,
+                global_debt_ceiling: 5_000_000 * 100_000_000,
+                ..migrated_state()
+            }
+        );
+        assert_eq!((state.version, state.sp_liquidator_rebate_bps), (V4, 0));
+
+        let mut padded = v2_fixture();
+        padded.push(0);
+        assert_eq!(VaultManagerState::migrate(&padded), Err(ZkUsdError::InvalidSpellFormat));
+
+        // A state decoded at v2 loses any rebate it claims
+        let decoded = VaultManagerState { version: V2, sp_liquidator_rebate_bps: 100, ..state.clone() };
+        assert_eq!(decoded.upgrade(), Ok(state));
+    }
+
+    #[test]
+    fn test_v3_state_fixture_migrates() {
+        let state = VaultManagerState::migrate(&v3_fixture()).unwrap();
+        assert_eq!(
+            state,
+            VaultManagerState {
+                registry_shards: 4,
+                depositor_discount_bps: 25,
+                global_debt_ceiling: 5_000_000 * 100_000_000,
+                sp_liquidator_rebate_bps: 100,
+                ..migrated_state()
+            }
+        );
+        assert_eq!((state.version, state.min_liquidation_confidence), (V4, oracle::MIN_LIQUIDATION_CONFIDENCE));
+
+        let mut padded = v3_fixture();
+        padded.push(0);
+        assert_eq!(VaultManagerState::migrate(&padded), Err(ZkUsdError::InvalidSpellFormat));
+
+        // A state decoded at v3 liquidates at the confidence it always did
+        let decoded = VaultManagerState { version: V3, min_liquidation_confidence: 0, ..state.clone() };
+        assert_eq!(decoded.upgrade(), Ok(state));
+    }
+
+    #[test]
+    fn test_unknown_state_versions_rejected() {
+        let mut bytes = borsh::to_vec(&migrated_state()).unwrap();
+        for found in [0, V4 + 1] {
+            bytes[0] = found;
+            assert_eq!(
+                VaultManagerState::migrate(&bytes),
+                Err(ZkUsdError::UnsupportedStateVersion { found, current: V4 })
+            );
+            let state = VaultManagerState { version: found, ..migrated_state() };
+            assert_eq!(state.upgrade(), Err(ZkUsdError::UnsupportedStateVersion { found, current: V4 }));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Vault Status State Machine
+//!
+//! Central definition of which `VaultStatus` transitions each action may
+//! perform on an existing vault:
+//!
+//! ```text
+//!            mutations
+//!            ┌──────┐
+//!            ▼      │
+//!         Active ───┘
+//!        /   |   \
+//!  Close/    |    \ BeginLiquidation
+//!  Redeem    |     ▼
+//!     ▼      |   Liquidating ──┐
+//!  Closed    |     │    ▲      │ tranches
+//!            |     │    └──────┘
+//!  Liquidate |     │ last tranche
+//!            ▼     ▼
+//!          Liquidated
+//! ```
+//!
+//! A `Liquidating` vault only ever moves on through `ContinueLiquidation`;
+//! its owner and redeemers can no longer touch it.
+//!
+//! `Closed` and `Liquidated` are terminal. Actions that don't operate on an
+//! existing vault (open, flash mint, insurance transfer, admin, bootstrap
+//! graduation) allow no transition at all. The action match is exhaustive
+//! so new actions must be added here explicitly.
+
+use zkusd_common::{
+    errors::{ZkUsdError, ZkUsdResult},
+    types::{Vault, VaultAction, VaultStatus},
+};
+
+/// Returns true if `action` may move a vault from `from` to `to`
+pub fn is_valid_transition(from: VaultStatus, to: VaultStatus, action: &VaultAction) -> bool {
+    use VaultStatus::*;
+
+    match action {
+        // Mutations keep the vault active
+        VaultAction::AddCollateral { .. }
+        | VaultAction::WithdrawCollateral { .. }
+        | VaultAction::MintDebt { .. }
+        | VaultAction::RepayDebt { .. }
+        | VaultAction::AtomicRescue { .. }
+        | VaultAction::PurchaseInsurance { .. }
+        | VaultAction::TriggerInsurance { .. }
+        | VaultAction::ExpireInsurance { .. }
+        | VaultAction::SetVaultOperator { .. }
+        | VaultAction::SetProtection { .. }
+        | VaultAction::SetBeneficiary { .. }
+        | VaultAction::ClaimAsBeneficiary { .. }
+        | VaultAction::AccrueInterest { .. } => matches!((from, to), (Active, Active)),
+
+        VaultAction::CloseVault { .. } => matches!((from, to), (Active, Closed)),
+
+        // Partial redemption keeps the vault active, full redemption closes it
+        VaultAction::Redeem { .. } => matches!((from, to), (Active, Active) | (Active, Closed)),
+
+        // Single-shot liquidation goes straight to Liquidated, two-phase
+        // liquidation passes through Liquidating for as many tranches as
+        // it takes
+        VaultAction::Liquidate { .. } => matches!((from, to), (Active, Liquidated)),
+        VaultAction::BeginLiquidation { .. } => matches!((from, to), (Active, Liquidating)),
+        VaultAction::ContinueLiquidation { .. } => {
+            matches!((from, to), (Liquidating, Liquidating) | (Liquidating, Liquidated))
+        }
+
+        // No existing vault is transitioned
+        VaultAction::OpenVault { .. }
+        | VaultAction::FlashMint { .. }
+        | VaultAction::TransferInsurance { .. }
+        | VaultAction::SetFlashFee { .. }
+        | VaultAction::SetFeeDistribution { .. }
+        | VaultAction::PokeBaseRate {}
+        | VaultAction::GraduateBootstrap {} => false,
+    }
+}
+
+/// Validate the status transition between input and output vault (if both present)
+pub fn validate_status_transition(
+    vault: Option<&Vault>,
+    new_vault: Option<&Vault>,
+    action: &VaultAction,
+) -> ZkUsdResult<()> {
+    if let (Some(vault), Some(new_vault)) = (vault, new_vault) {
+        let (from, to) = (vault.status, new_vault.status);
+        if !is_valid_transition(from, to, action) {
+            return Err(ZkUsdError::InvalidStatusTransition { from, to });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkusd_common::units::{Sats, ZkUsd};
+
+    const STATUSES: [VaultStatus; 4] = [
+        VaultStatus::Active,
+        VaultStatus::Liquidating,
+        VaultStatus::Closed,
+        VaultStatus::Liquidated,
+    ];
+
+    /// One sample of every action; the match forces new variants to be listed
+    fn all_actions() -> Vec<(&'static str, VaultAction)> {
+        let id = [0u8; 32];
+        let actions = vec![
+            VaultAction::OpenVault { collateral: Sats(1), debt: ZkUsd(1) },
+            VaultAction::CloseVault { vault_id: id },
+            VaultAction::AddCollateral { vault_id: id, amount: Sats(1) },
+            VaultAction::WithdrawCollateral { vault_id: id, amount: Sats(1) },
+            VaultAction::MintDebt { vault_id: id, amount: ZkUsd(1) },
+            VaultAction::RepayDebt { vault_id: id, amount: ZkUsd(1) },
+            VaultAction::Liquidate { vault_id: id },
+            VaultAction::Redeem { amount: ZkUsd(1), min_btc_out: Sats(0) },
+            VaultAction::FlashMint { amount: ZkUsd(1), purpose: 0 },
+            VaultAction::AtomicRescue {
+                vault_id: id,
+                collateral_to_add: Sats(1),
+                debt_to_repay: ZkUsd(1),
+                rescuer_discount: Sats(0),
+            },
+            VaultAction::PurchaseInsurance {
+                vault_id: id,
+                coverage_btc: Sats(1),
+                premium: ZkUsd(1),
+                trigger_icr: 1,
+            },
+            VaultAction::TriggerInsurance { insurance_id: id, vault_id: id },
+            VaultAction::TransferInsurance { insurance_id: id, new_owner: id },
+            VaultAction::SetFlashFee { fee_bps: 1 },
+            VaultAction::SetFeeDistribution { distribution: Default::default() },
+            VaultAction::SetVaultOperator { vault_id: id, operator: None },
+            VaultAction::PokeBaseRate {},
+            VaultAction::SetProtection { vault_id: id, bps: 1 },
+            VaultAction::SetBeneficiary { vault_id: id, beneficiary: None, inactivity_blocks: 0 },
+            VaultAction::ClaimAsBeneficiary { vault_id: id },
+            VaultAction::BeginLiquidation { vault_id: id },
+            VaultAction::ContinueLiquidation { vault_id: id, debt_portion: ZkUsd(1) },
+            VaultAction::ExpireInsurance { insurance_id: id, vault_id: id },
+            VaultAction::GraduateBootstrap {},
+            VaultAction::AccrueInterest { vault_id: id },
+        ];
+
+        actions
+            .into_iter()
+            .map(|a| {
+                let name = match a {
+                    VaultAction::OpenVault { .. } => "OpenVault",
+                    VaultAction::CloseVault { .. } => "CloseVault",
+                    VaultAction::AddCollateral { .. } => "AddCollateral",
+                    VaultAction::WithdrawCollateral { .. } => "WithdrawCollateral",
+                    VaultAction::MintDebt { .. } => "MintDebt",
+                    VaultAction::RepayDebt { .. } => "RepayDebt",
+                    VaultAction::Liquidate { .. } => "Liquidate",
+                    VaultAction::Redeem { .. } => "Redeem",
+                    VaultAction::FlashMint { .. } => "FlashMint",
+                    VaultAction::AtomicRescue { .. } => "AtomicRescue",
+                    VaultAction::PurchaseInsurance { .. } => "PurchaseInsurance",
+                    VaultAction::TriggerInsurance { .. } => "TriggerInsurance",
+                    VaultAction::TransferInsurance { .. } => "TransferInsurance",
+                    VaultAction::SetFlashFee { .. } => "SetFlashFee",
+                    VaultAction::SetFeeDistribution { .. } => "SetFeeDistribution",
+                    VaultAction::SetVaultOperator { .. } => "SetVaultOperator",
+                    VaultAction::PokeBaseRate {} => "PokeBaseRate",
+                    VaultAction::SetProtection { .. } => "SetProtection",
+                    VaultAction::SetBeneficiary { .. } => "SetBeneficiary",
+                    VaultAction::ClaimAsBeneficiary { .. } => "ClaimAsBeneficiary",
+                    VaultAction::BeginLiquidation { .. } => "BeginLiquidation",
+                    VaultAction::ContinueLiquidation { .. } => "ContinueLiquidation",
+                    VaultAction::ExpireInsurance { .. } => "ExpireInsurance",
+                    VaultAction::GraduateBootstrap {} => "GraduateBootstrap",
+                    VaultAction::AccrueInterest { .. } => "AccrueInterest",
+                };
+                (name, a)
+            })
+            .collect()
+    }
+
+    /// Hand-written allow-list: (action, from, to)
+    fn allowed() -> Vec<(&'static str, VaultStatus, VaultStatus)> {
+        use VaultStatus::*;
+        vec![
+            ("CloseVault", Active, Closed),
+            ("AddCollateral", Active, Active),
+            ("WithdrawCollateral", Active, Active),
+            ("MintDebt", Active, Active),
+            ("RepayDebt", Active, Active),
+            ("Liquidate", Active, Liquidated),
+            ("BeginLiquidation", Active, Liquidating),
+            ("ContinueLiquidation", Liquidating, Liquidating),
+            ("ContinueLiquidation", Liquidating, Liquidated),
+            ("Redeem", Active, Active),
+            ("Redeem", Active, Closed),
+            ("AtomicRescue", Active, Active),
+            ("PurchaseInsurance", Active, Active),
+            ("TriggerInsurance", Active, Active),
+            ("ExpireInsurance", Active, Active),
+            ("SetVaultOperator", Active, Active),
+            ("SetProtection", Active, Active),
+            ("AccrueInterest", Active, Active),
+            ("SetBeneficiary", Active, Active),
+            ("ClaimAsBeneficiary", Active, Active),
+        ]
+    }
+
+    #[test]
+    fn test_transition_matrix_matches_allow_list() {
+        let allowed = allowed();
+        for (name, action) in all_actions() {
+            for from in STATUSES {
+                for to in STATUSES {
+                    let expected = allowed.contains(&(name, from, to));
+                    assert_eq!(
+                        is_valid_transition(from, to, &action),
+                        expected,
+                        "{} {:?} -> {:?}",
+                        name,
+                        from,
+                        to
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_exits_from_terminal_states() {
+        for (_, action) in all_actions() {
+            for to in STATUSES {
+                assert!(!is_valid_transition(VaultStatus::Closed, to, &action));
+                assert!(!is_valid_transition(VaultStatus::Liquidated, to, &action));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_status_transition_error() {
+        let vault = Vault::new([0u8; 32], [1u8; 32], 1, 1, 0);
+        let closed = Vault { status: VaultStatus::Closed, ..vault.clone() };
+        let action = VaultAction::AddCollateral { vault_id: [0u8; 32], amount: Sats(1) };
+
+        let result = validate_status_transition(Some(&closed), Some(&vault), &action);
+        assert_eq!(
+            result,
+            Err(ZkUsdError::InvalidStatusTransition {
+                from: VaultStatus::Closed,
+                to: VaultStatus::Active,
+            })
+        );
+
+        // Missing input or output vault is left to the action validator
+        assert!(validate_status_transition(None, Some(&vault), &action).is_ok());
+        assert!(validate_status_transition(Some(&vault), None, &action).is_ok());
+    }
+}
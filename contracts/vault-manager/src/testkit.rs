@@ -0,0 +1,185 @@
+//! Test Kit
+//!
+//! Fluent factories for `VaultContext`, so a test states only what it is
+//! about (the vault's ICR, the system's TCR, the price) and the rest of the
+//! context stays consistent with it:
+//!
+//! ```ignore
+//! let ctx = VaultCtx::healthy_vault().with_icr(150).in_recovery_mode().build();
+//! ```
+//!
+//! The built context spends the vault and carries an unchanged copy of the
+//! state as output, but no vault output; a test sets the outputs its action
+//! expects, with `mutate` or directly. `assert_protocol_eq` and `assert_vault_eq` compare
+//! constructed states field by field.
+
+use zkusd_common::{
+    constants::{fees, ratios},
+    diagnostics::{diff_protocol, diff_vault},
+    events::EventLog,
+    interest::rate_weight,
+    types::{OracleSnapshot, PriceData, PriceSource, ProtocolState, Vault},
+    units::{Sats, ZkUsd},
+    validation::AppliedActions,
+};
+
+use crate::{ExpectedOutputs, SpellBounds, VaultContext, VaultManagerState};
+
+/// BTC price of $100,000 (8 decimals)
+pub const BTC_PRICE_100K: u64 = 100_000_00000000;
+/// One BTC in satoshis
+pub const ONE_BTC: u64 = 100_000_000;
+/// One zkUSD in base units
+pub const ONE_ZKUSD: u64 = 100_000_000;
+/// Block the context executes at
+pub const BLOCK: u64 = 100;
+/// Signer of the context, and owner of its vault
+pub const OWNER: [u8; 32] = [1u8; 32];
+/// Debt of vaults other than the context vault when a TCR is set
+pub const OTHER_DEBT: u64 = 100_000 * ONE_ZKUSD;
+
+/// TCR `in_recovery_mode` puts the system at (%)
+const RECOVERY_MODE_TCR: u64 = ratios::CCR - 10;
+
+/// Change applied to a built context
+type Mutation = Box<dyn FnOnce(&mut VaultContext)>;
+
+/// Builder for a `VaultContext`
+pub struct VaultCtx {
+    vault: Option<Vault>,
+    icr: Option<u64>,
+    tcr: Option<u64>,
+    price: u64,
+    mutations: Vec<Mutation>,
+}
+
+impl VaultCtx {
+    /// Empty protocol at $100k, with interest accrued to `BLOCK`
+    pub fn new() -> Self {
+        Self { vault: None, icr: None, tcr: None, price: BTC_PRICE_100K, mutations: Vec::new() }
+    }
+
+    /// `new` with an active vault of 1.5 BTC against 50,000 zkUSD (300% ICR)
+    /// owned by the signer, and the protocol holding just that vault
+    pub fn healthy_vault() -> Self {
+        Self::new().with_vault(Vault::new([0u8; 32], OWNER, 150_000_000, 50_000 * ONE_ZKUSD, BLOCK))
+    }
+
+    /// Spend `vault` in the context
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    /// Set the vault's collateral so its ICR is `icr` (%) at the price
+    pub fn with_icr(mut self, icr: u64) -> Self {
+        self.icr = Some(icr);
+        self
+    }
+
+    /// Add other vaults carrying `OTHER_DEBT`, collateralized so the TCR
+    /// is `tcr` (%) at the price
+    pub fn with_tcr(mut self, tcr: u64) -> Self {
+        self.tcr = Some(tcr);
+        self
+    }
+
+    /// Put the system in Recovery Mode (TCR 10 points below CCR)
+    pub fn in_recovery_mode(self) -> Self {
+        self.with_tcr(RECOVERY_MODE_TCR)
+    }
+
+    /// Price the oracle publishes at `BLOCK`
+    pub fn with_price(mut self, price: u64) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Change the built context; applied in order after everything else
+    pub fn mutate(mut self, mutation: impl FnOnce(&mut VaultContext) + 'static) -> Self {
+        self.mutations.push(Box::new(mutation));
+        self
+    }
+
+    /// Build the context
+    pub fn build(self) -> VaultContext {
+        let mut state = VaultManagerState::new([0u8; 32], [1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32], [5u8; 32])
+            .expect("test state creation should succeed");
+        state.protocol.last_interest_accrual_block = BLOCK;
+
+        let vault = self.vault.map(|vault| match self.icr {
+            Some(icr) => Vault { collateral: collateral_for(icr, vault.debt, self.price), ..vault },
+            None => vault,
+        });
+        if let Some(vault) = &vault {
+            add_vaults(&mut state.protocol, 1, vault.collateral, vault.debt);
+        }
+        if let Some(tcr) = self.tcr {
+            let total_collateral = collateral_for(tcr, state.protocol.total_debt + OTHER_DEBT, self.price);
+            let other_collateral = total_collateral
+                .checked_sub(state.protocol.total_collateral)
+                .expect("TCR too low to cover the context vault");
+            add_vaults(&mut state.protocol, 1, other_collateral, OTHER_DEBT);
+        }
+
+        let mut ctx = VaultContext {
+            state: state.clone(),
+            new_state: state,
+            vault,
+            new_vault: None,
+            oracle: OracleSnapshot::active(PriceData::new(self.price, BLOCK, PriceSource::Mock)),
+            btc_inputs: Sats(0),
+            btc_outputs: Sats(0),
+            zkusd_inputs: ZkUsd(0),
+            zkusd_outputs: ZkUsd(0),
+            vault_minted: ZkUsd(0),
+            linked_btc_claim: None,
+            linked_deposit: None,
+            linked_offset: None,
+            insurance: None,
+            new_insurance: None,
+            registry: Vec::new(),
+            new_registry: Vec::new(),
+            bounds: SpellBounds::default(),
+            whitelist_proof: Vec::new(),
+            signer: OWNER,
+            block_height: BLOCK,
+            applied_actions: AppliedActions::new(),
+            expected: ExpectedOutputs::default(),
+            events: EventLog::new(),
+        };
+        for mutation in self.mutations {
+            mutation(&mut ctx);
+        }
+        ctx
+    }
+}
+
+/// Collateral (satoshis, rounded up) backing `debt` at `ratio` (%)
+pub fn collateral_for(ratio: u64, debt: u64, price: u64) -> u64 {
+    let collateral = (ratio as u128 * debt as u128 * ONE_BTC as u128).div_ceil(100 * price as u128);
+    u64::try_from(collateral).expect("collateral fits u64")
+}
+
+/// Add `count` vaults at the default rate to the protocol totals
+fn add_vaults(protocol: &mut ProtocolState, count: u64, collateral: u64, debt: u64) {
+    protocol.total_collateral += collateral;
+    protocol.total_debt += debt;
+    protocol.active_vault_count += count;
+    protocol.rate_weighted_debt += rate_weight(debt, fees::DEFAULT_INTEREST_RATE_BPS);
+}
+
+/// Assert a constructed protocol state matches, naming every field that
+/// does not
+#[track_caller]
+pub fn assert_protocol_eq(actual: &ProtocolState, expected: &ProtocolState) {
+    let diffs = diff_protocol(expected, actual);
+    assert!(diffs.is_empty(), "protocol state differs: {:?}", diffs);
+}
+
+/// Assert a constructed vault matches, naming every field that does not
+#[track_caller]
+pub fn assert_vault_eq(actual: &Vault, expected: &Vault) {
+    let diffs = diff_vault(expected, actual);
+    assert!(diffs.is_empty(), "vault differs: {:?}", diffs);
+}
@@ -17,12 +17,21 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "charms")]
 pub mod charms;
+#[cfg(test)]
+mod testkit;
 
 use zkusd_common::{
+    address::is_zero,
+    commitment::{state_commitment, CommittedApp},
     constants::token,
-    errors::{ZkUsdError, ZkUsdResult},
+    diagnostics::FieldDiff,
+    errors::{AmountErrorReason, ZkUsdError, ZkUsdResult},
     events::{EventLog, ZkUsdEvent},
-    types::{Address, AppId, TokenAction},
+    math::safe_sum,
+    types::{Address, AppId, Memo, TokenAction},
+    units::ZkUsd,
+    validation::require_valid_address,
+    versioning::{initial_state_version, VersionedState, INITIAL_STATE_VERSION},
 };
 
 // ============ Token State ============
@@ -30,13 +39,23 @@ use zkusd_common::{
 /// zkUSD Token state stored in charm data
 /// Note: TokenMetadata is static and accessed via constants, not stored in state
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct ZkUsdTokenState {
+    /// Layout version (see `zkusd_common::versioning`)
+    #[serde(default = "initial_state_version")]
+    pub version: u8,
     /// Admin address (can set minter once during bootstrap)
     pub admin: Address,
     /// Authorized minter (VaultManager app_id) - zero means pending setup
     pub authorized_minter: AppId,
     /// Total supply tracking
     pub total_supply: u64,
+    /// Owners are implicit in UTXO ownership (simple fungible charms):
+    /// outputs may be owned by the zero address, and mints into such
+    /// outputs have no recipient to check. Off unless enabled at
+    /// initialization.
+    #[serde(default)]
+    pub implicit_ownership: bool,
 }
 
 // NOTE: Default trait intentionally NOT implemented to force explicit initialization
@@ -47,24 +66,35 @@ impl ZkUsdTokenState {
     /// Create new token state with admin (minter set to zero - pending configuration)
     pub fn new(admin: Address) -> Self {
         Self {
+            version: INITIAL_STATE_VERSION,
             admin,
             authorized_minter: [0u8; 32], // Pending - must call set_minter
             total_supply: 0,
+            implicit_ownership: false,
         }
     }
 
     /// Create token state with both admin and minter (for direct initialization)
     pub fn with_minter(admin: Address, authorized_minter: AppId) -> Self {
         Self {
+            version: INITIAL_STATE_VERSION,
             admin,
             authorized_minter,
             total_supply: 0,
+            implicit_ownership: false,
         }
     }
 
     /// Check if minter has been configured (non-zero)
     pub fn is_minter_configured(&self) -> bool {
-        self.authorized_minter != [0u8; 32]
+        !is_zero(&self.authorized_minter)
+    }
+
+    /// Canonical commitment to this state (see `zkusd_common::commitment`)
+    ///
+    /// Consensus-relevant: light clients compare against this hash.
+    pub fn commitment(&self) -> [u8; 32] {
+        state_commitment(CommittedApp::Token, self)
     }
 
     /// Get token name
@@ -83,10 +113,31 @@ impl ZkUsdTokenState {
     }
 }
 
+impl VersionedState for ZkUsdTokenState {
+    const VERSION: u8 = INITIAL_STATE_VERSION;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+/// Fields of a token state that differ from the expected state
+/// (see `zkusd_common::diagnostics`)
+pub fn diff_token_state(expected: &ZkUsdTokenState, actual: &ZkUsdTokenState) -> Vec<FieldDiff> {
+    zkusd_common::diff_fields!(ZkUsdTokenState, expected, actual, [
+        version,
+        admin,
+        authorized_minter,
+        total_supply,
+        implicit_ownership,
+    ])
+}
+
 // ============ Token Balance (per UTXO) ============
 
 /// Token balance held in a UTXO
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 pub struct TokenBalance {
     /// Owner address
     pub owner: Address,
@@ -104,6 +155,7 @@ impl TokenBalance {
 
 /// Context for validating token operations
 /// This simulates what Charms SpellContext would provide
+#[derive(Debug, Clone)]
 pub struct TokenContext {
     /// Input token balances being spent
     pub inputs: Vec<TokenBalance>,
@@ -115,6 +167,9 @@ pub struct TokenContext {
     pub new_token_state: ZkUsdTokenState,
     /// Caller app_id (for mint/burn authorization)
     pub caller_app_id: Option<AppId>,
+    /// zkUSD the authorized minter's own transition mints in this spell
+    /// (if known); a mint must match it exactly
+    pub minter_amount: Option<u64>,
     /// Signer address
     pub signer: Address,
     /// Current block height
@@ -123,21 +178,70 @@ pub struct TokenContext {
     pub events: EventLog,
 }
 
+/// Arbitrary spell inputs with an empty event log
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for TokenContext {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            inputs: u.arbitrary()?,
+            outputs: u.arbitrary()?,
+            token_state: u.arbitrary()?,
+            new_token_state: u.arbitrary()?,
+            caller_app_id: u.arbitrary()?,
+            minter_amount: u.arbitrary()?,
+            signer: u.arbitrary()?,
+            block_height: u.arbitrary()?,
+            events: EventLog::new(),
+        })
+    }
+}
+
 // ============ Validation Functions ============
 
 /// Main validation entry point for token operations
 pub fn validate(ctx: &mut TokenContext, action: &TokenAction) -> ZkUsdResult<()> {
+    // Unless ownership is implicit, every output names its owner: tokens
+    // sent to the zero address could never be spent, so only a burn may
+    // destroy them
+    if !ctx.token_state.implicit_ownership {
+        require_owned_outputs(&ctx.outputs)?;
+    }
+
     match action {
-        TokenAction::Transfer { from, to, amount } => {
-            validate_transfer(ctx, from, to, *amount)
+        TokenAction::Transfer { from, to, amount: ZkUsd(amount), memo } => {
+            // Transfers leave the controller state untouched
+            return validate_transfer(ctx, from, to, *amount, *memo);
+        }
+        TokenAction::Consolidate { owner } => {
+            // So do consolidations
+            return validate_consolidate(ctx, owner);
         }
-        TokenAction::Mint { to, amount } => {
-            validate_mint(ctx, to, *amount)
+        TokenAction::Mint { to, amount: ZkUsd(amount) } => {
+            validate_mint(ctx, to, *amount)?
         }
-        TokenAction::Burn { from, amount } => {
-            validate_burn(ctx, from, *amount)
+        TokenAction::Burn { from, amount: ZkUsd(amount) } => {
+            validate_burn(ctx, from, *amount)?
+        }
+        TokenAction::MintMulti { recipients } => {
+            validate_mint_multi(ctx, recipients)?
         }
     }
+
+    // Whatever the action, the controller's supply moves with the tokens
+    reconcile_supply(ctx)?;
+
+    // Implicit ownership is fixed at initialization
+    if ctx.new_token_state.implicit_ownership != ctx.token_state.implicit_ownership {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    ctx.events.emit(ZkUsdEvent::StateCommitted {
+        app: CommittedApp::Token,
+        commitment: ctx.new_token_state.commitment(),
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
 }
 
 /// Validate a transfer operation
@@ -146,6 +250,7 @@ fn validate_transfer(
     from: &Address,
     to: &Address,
     amount: u64,
+    memo: Option<Memo>,
 ) -> ZkUsdResult<()> {
     // 1. Amount must be positive
     if amount == 0 {
@@ -153,11 +258,9 @@ fn validate_transfer(
     }
 
     // 2. Calculate total inputs from sender
-    let sender_input_total: u64 = ctx.inputs
-        .iter()
-        .filter(|i| &i.owner == from)
-        .map(|i| i.amount)
-        .sum();
+    let sender_input_total = safe_sum(
+        ctx.inputs.iter().filter(|i| &i.owner == from).map(|i| i.amount),
+    )?;
 
     // 3. Sender must have enough balance
     if sender_input_total < amount {
@@ -168,8 +271,8 @@ fn validate_transfer(
     }
 
     // 4. Calculate total outputs
-    let total_inputs: u64 = ctx.inputs.iter().map(|i| i.amount).sum();
-    let total_outputs: u64 = ctx.outputs.iter().map(|o| o.amount).sum();
+    let total_inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let total_outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
 
     // 5. Conservation: inputs must equal outputs (no creation/destruction)
     if total_inputs != total_outputs {
@@ -180,16 +283,14 @@ fn validate_transfer(
     }
 
     // 6. Verify recipient receives the amount
-    let recipient_output: u64 = ctx.outputs
-        .iter()
-        .filter(|o| &o.owner == to)
-        .map(|o| o.amount)
-        .sum();
+    let recipient_output = safe_sum(
+        ctx.outputs.iter().filter(|o| &o.owner == to).map(|o| o.amount),
+    )?;
 
     if recipient_output < amount {
         return Err(ZkUsdError::InvalidAmount {
             amount: recipient_output,
-            reason: zkusd_common::errors::AmountErrorReason::TooSmall,
+            reason: AmountErrorReason::TooSmall,
         });
     }
 
@@ -201,17 +302,116 @@ fn validate_transfer(
         });
     }
 
-    // 8. Emit transfer event
+    // 8. Emit transfer event, carrying the memo through unchanged
     ctx.events.emit(ZkUsdEvent::TokenTransfer {
         from: *from,
         to: *to,
         amount,
+        memo,
+        block_height: ctx.block_height,
+    });
+
+    Ok(())
+}
+
+/// Validate a consolidation of an owner's balances into fewer UTXOs
+///
+/// Nothing changes hands, so there is no recipient to credit: every input
+/// and output belongs to the owner, amounts are conserved exactly and the
+/// spell must leave fewer balances than it spent.
+fn validate_consolidate(ctx: &mut TokenContext, owner: &Address) -> ZkUsdResult<()> {
+    // 1. Only the owner may consolidate
+    if ctx.signer != *owner {
+        return Err(ZkUsdError::Unauthorized {
+            expected: *owner,
+            actual: ctx.signer,
+        });
+    }
+
+    // 2. Input count must be bounded
+    if ctx.inputs.len() > token::MAX_CONSOLIDATION_INPUTS {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: ctx.inputs.len() as u64,
+            maximum: token::MAX_CONSOLIDATION_INPUTS as u64,
+        });
+    }
+
+    // 3. Every input and output belongs to the owner
+    if let Some(input) = ctx.inputs.iter().find(|i| &i.owner != owner) {
+        return Err(ZkUsdError::Unauthorized {
+            expected: *owner,
+            actual: input.owner,
+        });
+    }
+    if ctx.outputs.iter().any(|o| &o.owner != owner) {
+        return Err(ZkUsdError::InvalidInput {
+            param: "outputs",
+            reason: "Consolidation output not owned by the owner",
+        });
+    }
+
+    // 4. At least one output, and fewer than the inputs
+    if ctx.outputs.is_empty() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "outputs",
+            reason: "No outputs",
+        });
+    }
+    if ctx.outputs.len() >= ctx.inputs.len() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "outputs",
+            reason: "Consolidation must reduce the UTXO count",
+        });
+    }
+
+    // 5. Conservation: inputs must equal outputs
+    let total_inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let total_outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
+
+    if total_inputs != total_outputs {
+        return Err(ZkUsdError::ConservationViolated {
+            inputs: total_inputs,
+            outputs: total_outputs,
+        });
+    }
+
+    // 6. Emit only the lightweight consolidation event; counts fit u16 as
+    // inputs are bounded above
+    ctx.events.emit(ZkUsdEvent::UtxosConsolidated {
+        owner: *owner,
+        inputs: ctx.inputs.len() as u16,
+        outputs: ctx.outputs.len() as u16,
         block_height: ctx.block_height,
     });
 
     Ok(())
 }
 
+/// Group `utxos` into at most `target_outputs` consolidations, each
+/// spending at most `MAX_CONSOLIDATION_INPUTS` balances
+///
+/// Returns the indices of the balances each consolidation spends, smallest
+/// balances first; the largest balances are left out when there are more
+/// than the consolidations can spend. Groups of a single balance are
+/// dropped, since spending one UTXO into one output reduces nothing. A
+/// wallet consolidates each group into one output.
+pub fn suggest_consolidation(utxos: &[TokenBalance], target_outputs: usize) -> Vec<Vec<usize>> {
+    if target_outputs == 0 {
+        return Vec::new();
+    }
+
+    let mut order: Vec<usize> = (0..utxos.len()).collect();
+    order.sort_by_key(|&i| (utxos[i].amount, i));
+    let order = &order[..order.len().min(target_outputs * token::MAX_CONSOLIDATION_INPUTS)];
+
+    let group_size = order.len().div_ceil(target_outputs).max(1);
+    order
+        .chunks(group_size)
+        .filter(|group| group.len() > 1)
+        .map(|group| group.to_vec())
+        .collect()
+}
+
 /// Validate a mint operation (only from authorized minter)
 fn validate_mint(
     ctx: &mut TokenContext,
@@ -223,21 +423,15 @@ fn validate_mint(
         return Err(ZkUsdError::ZeroAmount);
     }
 
-    // 2. Caller must be authorized minter (VaultManager)
-    let caller = ctx.caller_app_id.ok_or(ZkUsdError::MintUnauthorized {
-        caller: [0u8; 32],
-    })?;
-
-    if caller != ctx.token_state.authorized_minter {
-        return Err(ZkUsdError::MintUnauthorized { caller });
-    }
+    // 2. Caller must be the authorized minter, minting what it intended
+    verify_minter(ctx, amount)?;
 
     // 3. Calculate input/output totals
-    let total_inputs: u64 = ctx.inputs.iter().map(|i| i.amount).sum();
-    let total_outputs: u64 = ctx.outputs.iter().map(|o| o.amount).sum();
+    let total_inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let total_outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
 
     // 4. Outputs must be exactly inputs + minted amount
-    if total_outputs != total_inputs + amount {
+    if Some(total_outputs) != total_inputs.checked_add(amount) {
         return Err(ZkUsdError::ConservationViolated {
             inputs: total_inputs,
             outputs: total_outputs,
@@ -245,29 +439,13 @@ fn validate_mint(
     }
 
     // 5. Verify recipient receives the minted amount
-    // For simple fungible tokens (owner=[0;32]), we skip the recipient check
-    // since the owner is implicit in UTXO ownership
-    let is_simple_fungible = ctx.outputs.iter().all(|o| o.owner == [0u8; 32]);
-
-    if !is_simple_fungible {
-        let recipient_output: u64 = ctx.outputs
-            .iter()
-            .filter(|o| &o.owner == to)
-            .map(|o| o.amount)
-            .sum();
-
-        let recipient_input: u64 = ctx.inputs
-            .iter()
-            .filter(|i| &i.owner == to)
-            .map(|i| i.amount)
-            .sum();
-
-        if recipient_output < recipient_input + amount {
-            return Err(ZkUsdError::InvalidAmount {
-                amount: recipient_output,
-                reason: zkusd_common::errors::AmountErrorReason::TooSmall,
-            });
-        }
+    // Ownerless (simple fungible) outputs carry no recipient to check, so
+    // they are only accepted under implicit ownership
+    let is_simple_fungible = ctx.outputs.iter().all(|o| is_zero(&o.owner));
+
+    if !(ctx.token_state.implicit_ownership && is_simple_fungible) {
+        require_valid_address(*to, "to")?;
+        verify_recipient_credited(ctx, to, amount)?;
     }
 
     // 6. Update total supply in new state
@@ -290,6 +468,134 @@ fn validate_mint(
     Ok(())
 }
 
+/// Validate a mint split across several recipients (only from authorized minter)
+fn validate_mint_multi(
+    ctx: &mut TokenContext,
+    recipients: &[(Address, ZkUsd)],
+) -> ZkUsdResult<()> {
+    // 1. Recipient count must be bounded
+    if recipients.is_empty() {
+        return Err(ZkUsdError::InvalidInput {
+            param: "recipients",
+            reason: "No recipients",
+        });
+    }
+    if recipients.len() > token::MAX_MINT_RECIPIENTS {
+        return Err(ZkUsdError::ExceedsMaximum {
+            amount: recipients.len() as u64,
+            maximum: token::MAX_MINT_RECIPIENTS as u64,
+        });
+    }
+
+    // 2. Every entry needs a real, distinct recipient and a positive amount
+    let mut total: u64 = 0;
+    for (i, (to, ZkUsd(amount))) in recipients.iter().enumerate() {
+        require_valid_address(*to, "recipients")?;
+        if *amount == 0 {
+            return Err(ZkUsdError::ZeroAmount);
+        }
+        if recipients[..i].iter().any(|(seen, _)| seen == to) {
+            return Err(ZkUsdError::InvalidInput {
+                param: "recipients",
+                reason: "Duplicate recipient",
+            });
+        }
+        total = total.checked_add(*amount).ok_or(ZkUsdError::Overflow)?;
+    }
+
+    // 3. Caller must be the authorized minter, minting what it intended
+    verify_minter(ctx, total)?;
+
+    // 4. Outputs must be exactly inputs + minted total
+    let total_inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let total_outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
+
+    if Some(total_outputs) != total_inputs.checked_add(total) {
+        return Err(ZkUsdError::ConservationViolated {
+            inputs: total_inputs,
+            outputs: total_outputs,
+        });
+    }
+
+    // 5. Each recipient receives at least its amount; the ownerless
+    // bypass never applies since every recipient is named
+    for (to, ZkUsd(amount)) in recipients {
+        verify_recipient_credited(ctx, to, *amount)?;
+    }
+
+    // 6. Total supply grows by exactly the minted total
+    let new_supply = ctx.token_state.total_supply
+        .checked_add(total)
+        .ok_or(ZkUsdError::Overflow)?;
+
+    if ctx.new_token_state.total_supply != new_supply {
+        return Err(ZkUsdError::InvalidStateTransition);
+    }
+
+    // 7. Emit one mint event per recipient
+    let mut running_supply = ctx.token_state.total_supply;
+    for (to, ZkUsd(amount)) in recipients {
+        running_supply += amount;
+        ctx.events.emit(ZkUsdEvent::TokenMint {
+            to: *to,
+            amount: *amount,
+            new_total_supply: running_supply,
+            block_height: ctx.block_height,
+        });
+    }
+
+    Ok(())
+}
+
+/// Require every output to be owned by a nonzero address
+fn require_owned_outputs(outputs: &[TokenBalance]) -> ZkUsdResult<()> {
+    if outputs.iter().any(|output| is_zero(&output.owner)) {
+        return Err(ZkUsdError::InvalidAddress { reason: "zero-address recipient" });
+    }
+    Ok(())
+}
+
+/// Check the caller is the authorized minter and, when the minter's own
+/// mint amount is known, that `amount` matches it
+fn verify_minter(ctx: &TokenContext, amount: u64) -> ZkUsdResult<()> {
+    let caller = ctx.caller_app_id.ok_or(ZkUsdError::MintUnauthorized {
+        caller: [0u8; 32],
+    })?;
+
+    if caller != ctx.token_state.authorized_minter {
+        return Err(ZkUsdError::MintUnauthorized { caller });
+    }
+
+    if ctx.minter_amount.is_some_and(|intended| intended != amount) {
+        return Err(ZkUsdError::InvalidInput {
+            param: "amount",
+            reason: "Differs from the minter's mint amount",
+        });
+    }
+
+    Ok(())
+}
+
+/// Check `to` ends the spell holding at least `amount` more than it put in
+fn verify_recipient_credited(ctx: &TokenContext, to: &Address, amount: u64) -> ZkUsdResult<()> {
+    let recipient_output = safe_sum(
+        ctx.outputs.iter().filter(|o| &o.owner == to).map(|o| o.amount),
+    )?;
+
+    let recipient_input = safe_sum(
+        ctx.inputs.iter().filter(|i| &i.owner == to).map(|i| i.amount),
+    )?;
+
+    if recipient_output < recipient_input.saturating_add(amount) {
+        return Err(ZkUsdError::InvalidAmount {
+            amount: recipient_output,
+            reason: AmountErrorReason::TooSmall,
+        });
+    }
+
+    Ok(())
+}
+
 /// Validate a burn operation (repaying debt)
 fn validate_burn(
     ctx: &mut TokenContext,
@@ -311,11 +617,11 @@ fn validate_burn(
     }
 
     // 3. Calculate totals
-    let total_inputs: u64 = ctx.inputs.iter().map(|i| i.amount).sum();
-    let total_outputs: u64 = ctx.outputs.iter().map(|o| o.amount).sum();
+    let total_inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let total_outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
 
     // 4. Inputs must exceed outputs by burn amount
-    if total_inputs != total_outputs + amount {
+    if Some(total_inputs) != total_outputs.checked_add(amount) {
         return Err(ZkUsdError::ConservationViolated {
             inputs: total_inputs,
             outputs: total_outputs,
@@ -323,11 +629,9 @@ fn validate_burn(
     }
 
     // 5. Burner must have had the tokens
-    let burner_input: u64 = ctx.inputs
-        .iter()
-        .filter(|i| &i.owner == from)
-        .map(|i| i.amount)
-        .sum();
+    let burner_input = safe_sum(
+        ctx.inputs.iter().filter(|i| &i.owner == from).map(|i| i.amount),
+    )?;
 
     if burner_input < amount {
         return Err(ZkUsdError::InsufficientBalance {
@@ -356,6 +660,24 @@ fn validate_burn(
     Ok(())
 }
 
+/// Check the controller's supply moved by exactly the spell's net token
+/// flow: up by outputs minus inputs on a mint, down by inputs minus outputs
+/// on a burn
+fn reconcile_supply(ctx: &TokenContext) -> ZkUsdResult<()> {
+    let inputs = safe_sum(ctx.inputs.iter().map(|i| i.amount))?;
+    let outputs = safe_sum(ctx.outputs.iter().map(|o| o.amount))?;
+    let old_supply = ctx.token_state.total_supply;
+    let new_supply = ctx.new_token_state.total_supply;
+
+    let supply_delta = i128::from(new_supply) - i128::from(old_supply);
+    let flow_delta = i128::from(outputs) - i128::from(inputs);
+    if supply_delta != flow_delta {
+        return Err(ZkUsdError::SupplyFlowMismatch { old_supply, new_supply, inputs, outputs });
+    }
+
+    Ok(())
+}
+
 // ============ Helper Functions ============
 
 /// Get token name
@@ -385,58 +707,77 @@ pub fn format_amount(amount: u64) -> (u64, u64) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testkit::TokenCtx;
 
-    fn create_test_context() -> TokenContext {
-        let admin = [0u8; 32];
-        let vault_manager = [1u8; 32];
-        TokenContext {
-            inputs: Vec::new(),
-            outputs: Vec::new(),
-            token_state: ZkUsdTokenState::with_minter(admin, vault_manager),
-            new_token_state: ZkUsdTokenState::with_minter(admin, vault_manager),
-            caller_app_id: None,
-            signer: [0u8; 32],
-            block_height: 100,
-            events: EventLog::new(),
-        }
+    #[test]
+    fn test_diff_token_state() {
+        let expected = ZkUsdTokenState::with_minter([0u8; 32], [1u8; 32]);
+        assert!(diff_token_state(&expected, &expected).is_empty());
+
+        let actual = ZkUsdTokenState { total_supply: 5, ..expected.clone() };
+        assert_eq!(
+            diff_token_state(&expected, &actual),
+            [FieldDiff { field: "total_supply", expected: "0".into(), actual: "5".into() }]
+        );
     }
 
     #[test]
     fn test_transfer_success() {
-        let mut ctx = create_test_context();
         let alice = [1u8; 32];
         let bob = [2u8; 32];
-
-        ctx.signer = alice;
-        ctx.inputs.push(TokenBalance::new(alice, 1000));
-        ctx.outputs.push(TokenBalance::new(bob, 600));
-        ctx.outputs.push(TokenBalance::new(alice, 400)); // change
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000)])
+            .with_outputs(&[(bob, 600), (alice, 400)]) // change
+            .build();
 
         let action = TokenAction::Transfer {
             from: alice,
             to: bob,
-            amount: 600,
+            amount: ZkUsd(600),
+            memo: None,
         };
 
         let result = validate(&mut ctx, &action);
         assert!(result.is_ok());
         assert_eq!(ctx.events.len(), 1);
+        assert!(matches!(ctx.events.events()[0], ZkUsdEvent::TokenTransfer { memo: None, .. }));
     }
 
     #[test]
-    fn test_transfer_insufficient_balance() {
-        let mut ctx = create_test_context();
+    fn test_transfer_memo_in_event() {
+        let mut ctx = TokenCtx::new().build();
         let alice = [1u8; 32];
         let bob = [2u8; 32];
+        let memo = zkusd_common::token_ops::memo_hash(b"invoice-1042");
 
         ctx.signer = alice;
-        ctx.inputs.push(TokenBalance::new(alice, 500));
-        ctx.outputs.push(TokenBalance::new(bob, 1000));
+        ctx.inputs.push(TokenBalance::new(alice, 600));
+        ctx.outputs.push(TokenBalance::new(bob, 600));
 
         let action = TokenAction::Transfer {
             from: alice,
             to: bob,
-            amount: 1000,
+            amount: ZkUsd(600),
+            memo: Some(memo),
+        };
+
+        validate(&mut ctx, &action).unwrap();
+        assert_eq!(
+            ctx.events.events(),
+            [ZkUsdEvent::TokenTransfer { from: alice, to: bob, amount: 600, memo: Some(memo), block_height: ctx.block_height }]
+        );
+    }
+
+    #[test]
+    fn test_transfer_insufficient_balance() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 500)]).with_outputs(&[(bob, 1000)]).build();
+
+        let action = TokenAction::Transfer {
+            from: alice,
+            to: bob,
+            amount: ZkUsd(1000),
+            memo: None,
         };
 
         let result = validate(&mut ctx, &action);
@@ -444,21 +785,123 @@ mod tests {
     }
 
     #[test]
-    fn test_mint_authorized() {
-        let mut ctx = create_test_context();
-        let vault_manager = [1u8; 32];
-        let user = [2u8; 32];
+    fn test_transfer_spends_only_the_signers_balances() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let carol = [3u8; 32];
+
+        // Bob's 500 sits alongside Alice's 1000 in the inputs, but only
+        // Alice's balance counts towards her transfer
+        let transfer = TokenAction::Transfer { from: alice, to: carol, amount: ZkUsd(1500), memo: None };
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000), (bob, 500)]).with_outputs(&[(carol, 1500)]).build();
+        assert_eq!(validate(&mut ctx, &transfer), Err(ZkUsdError::InsufficientBalance { available: 1000, requested: 1500 }));
+
+        let transfer = TokenAction::Transfer { from: alice, to: carol, amount: ZkUsd(1000), memo: None };
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000), (bob, 500)])
+            .with_outputs(&[(carol, 1000), (bob, 500)])
+            .build();
+        assert_eq!(validate(&mut ctx, &transfer), Ok(()));
+
+        // Nor can Bob move Alice's balance
+        let transfer = TokenAction::Transfer { from: alice, to: bob, amount: ZkUsd(1000), memo: None };
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000), (bob, 500)])
+            .signed_by(bob)
+            .with_outputs(&[(bob, 1500)])
+            .build();
+        assert!(validate(&mut ctx, &transfer).is_err());
+    }
 
-        ctx.caller_app_id = Some(vault_manager);
-        ctx.token_state.authorized_minter = vault_manager;
-        ctx.token_state.total_supply = 0;
-        ctx.new_token_state.total_supply = 1000;
+    #[test]
+    fn test_consolidate_ten_into_two() {
+        let alice = [1u8; 32];
+        let dust: Vec<(Address, u64)> = (1..=10).map(|i| (alice, i * 100)).collect();
+        let mut ctx = TokenCtx::with_utxos(&dust).with_outputs(&[(alice, 2_500), (alice, 3_000)]).build();
+
+        validate(&mut ctx, &TokenAction::Consolidate { owner: alice }).unwrap();
+        assert_eq!(
+            ctx.events.events(),
+            [ZkUsdEvent::UtxosConsolidated { owner: alice, inputs: 10, outputs: 2, block_height: ctx.block_height }]
+        );
+    }
 
-        ctx.outputs.push(TokenBalance::new(user, 1000));
+    #[test]
+    fn test_consolidate_rejections() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let consolidate = TokenAction::Consolidate { owner: alice };
+
+        // Someone else's balance among the inputs
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (bob, 200)]).with_outputs(&[(alice, 300)]).build();
+        assert_eq!(validate(&mut ctx, &consolidate), Err(ZkUsdError::Unauthorized { expected: alice, actual: bob }));
+
+        // Signed by someone other than the owner
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (alice, 200)]).with_outputs(&[(alice, 300)]).signed_by(bob).build();
+        assert_eq!(validate(&mut ctx, &consolidate), Err(ZkUsdError::Unauthorized { expected: alice, actual: bob }));
+
+        // As many outputs as inputs, or none
+        let not_fewer = ZkUsdError::InvalidInput { param: "outputs", reason: "Consolidation must reduce the UTXO count" };
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (alice, 200)]).with_outputs(&[(alice, 150), (alice, 150)]).build();
+        assert_eq!(validate(&mut ctx, &consolidate), Err(not_fewer));
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (alice, 200)]).build();
+        assert_eq!(validate(&mut ctx, &consolidate), Err(ZkUsdError::InvalidInput { param: "outputs", reason: "No outputs" }));
+
+        // An output to anyone else is a transfer
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (alice, 200)]).with_outputs(&[(bob, 300)]).build();
+        assert!(matches!(validate(&mut ctx, &consolidate), Err(ZkUsdError::InvalidInput { param: "outputs", .. })));
+
+        // Too many inputs for one spell
+        let many = vec![(alice, 1); token::MAX_CONSOLIDATION_INPUTS + 1];
+        let mut ctx = TokenCtx::with_utxos(&many).with_outputs(&[(alice, many.len() as u64)]).build();
+        assert!(matches!(validate(&mut ctx, &consolidate), Err(ZkUsdError::ExceedsMaximum { .. })));
+    }
+
+    #[test]
+    fn test_consolidate_conserves_amounts() {
+        let alice = [1u8; 32];
+        let consolidate = TokenAction::Consolidate { owner: alice };
+
+        for output in [299, 301] {
+            let mut ctx = TokenCtx::with_utxos(&[(alice, 100), (alice, 200)]).with_outputs(&[(alice, output)]).build();
+            assert_eq!(
+                validate(&mut ctx, &consolidate),
+                Err(ZkUsdError::ConservationViolated { inputs: 300, outputs: output })
+            );
+        }
+    }
+
+    #[test]
+    fn test_suggest_consolidation() {
+        let alice = [1u8; 32];
+        let utxos: Vec<TokenBalance> = [500, 100, 900, 300, 200].iter().map(|&amount| TokenBalance::new(alice, amount)).collect();
+
+        // Smallest balances first, split evenly
+        assert_eq!(suggest_consolidation(&utxos, 1), [vec![1, 4, 3, 0, 2]]);
+        assert_eq!(suggest_consolidation(&utxos, 2), [vec![1, 4, 3], vec![0, 2]]);
+
+        // Groups of one reduce nothing
+        assert_eq!(suggest_consolidation(&utxos, 5), Vec::<Vec<usize>>::new());
+        assert_eq!(suggest_consolidation(&utxos, 0), Vec::<Vec<usize>>::new());
+
+        // Each group fits one spell, leaving the largest balances out
+        let many: Vec<TokenBalance> = (0..100).map(|i| TokenBalance::new(alice, 1_000 - i)).collect();
+        let groups = suggest_consolidation(&many, 1);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), token::MAX_CONSOLIDATION_INPUTS);
+        assert_eq!(groups[0][0], 99);
+    }
+
+    #[test]
+    fn test_mint_authorized() {
+        let user = [2u8; 32];
+        let mut ctx = TokenCtx::new()
+            .with_outputs(&[(user, 1000)])
+            .called_by_minter()
+            .mutate(|ctx| ctx.new_token_state.total_supply = 1000)
+            .build();
 
         let action = TokenAction::Mint {
             to: user,
-            amount: 1000,
+            amount: ZkUsd(1000),
         };
 
         let result = validate(&mut ctx, &action);
@@ -467,7 +910,7 @@ mod tests {
 
     #[test]
     fn test_mint_unauthorized() {
-        let mut ctx = create_test_context();
+        let mut ctx = TokenCtx::new().build();
         let vault_manager = [1u8; 32];
         let attacker = [99u8; 32];
         let user = [2u8; 32];
@@ -477,7 +920,7 @@ mod tests {
 
         let action = TokenAction::Mint {
             to: user,
-            amount: 1000,
+            amount: ZkUsd(1000),
         };
 
         let result = validate(&mut ctx, &action);
@@ -485,46 +928,345 @@ mod tests {
     }
 
     #[test]
-    fn test_burn_success() {
-        let mut ctx = create_test_context();
+    fn test_mint_ownerless_outputs_rejected_by_default() {
+        let mut ctx = TokenCtx::new().build();
         let vault_manager = [1u8; 32];
         let user = [2u8; 32];
 
         ctx.caller_app_id = Some(vault_manager);
-        ctx.token_state.authorized_minter = vault_manager;
-        ctx.token_state.total_supply = 10000;
-        ctx.new_token_state.total_supply = 9000;
+        ctx.new_token_state.total_supply = 1000;
+        // Simple fungible output: the minted amount could go to anyone
+        ctx.outputs.push(TokenBalance::new([0u8; 32], 1000));
+
+        let zero_recipient = Err(ZkUsdError::InvalidAddress { reason: "zero-address recipient" });
+        let result = validate(&mut ctx, &TokenAction::Mint { to: user, amount: ZkUsd(1000) });
+        assert_eq!(result, zero_recipient);
+
+        // Naming the zero address as recipient does not reopen the bypass
+        let result = validate(&mut ctx, &TokenAction::Mint { to: [0u8; 32], amount: ZkUsd(1000) });
+        assert_eq!(result, zero_recipient);
+
+        // With an owned output the recipient is checked
+        ctx.outputs = vec![TokenBalance::new([9u8; 32], 1000)];
+        let result = validate(&mut ctx, &TokenAction::Mint { to: user, amount: ZkUsd(1000) });
+        assert!(matches!(result, Err(ZkUsdError::InvalidAmount { .. })));
+        let result = validate(&mut ctx, &TokenAction::Mint { to: [0u8; 32], amount: ZkUsd(1000) });
+        assert_eq!(result, Err(ZkUsdError::InvalidAddress { reason: "to" }));
+    }
+
+    #[test]
+    fn test_implicit_ownership_preserves_ownerless_outputs() {
+        let user = [2u8; 32];
+        let implicit = |ctx: &mut TokenContext| {
+            ctx.token_state.implicit_ownership = true;
+            ctx.new_token_state.implicit_ownership = true;
+        };
+
+        // Ownerless mint outputs skip the recipient check...
+        let mut ctx = TokenCtx::new()
+            .with_outputs(&[([0u8; 32], 1000)])
+            .called_by_minter()
+            .mutate(implicit)
+            .mutate(|ctx| ctx.new_token_state.total_supply = 1000)
+            .build();
+        let result = validate(&mut ctx, &TokenAction::Mint { to: user, amount: ZkUsd(1000) });
+        assert!(result.is_ok(), "Implicit ownership should accept: {:?}", result);
+
+        // ...but still count towards the supply, which must match them
+        ctx.new_token_state.total_supply = 0;
+        assert_eq!(
+            validate(&mut ctx, &TokenAction::Mint { to: user, amount: ZkUsd(1000) }),
+            Err(ZkUsdError::InvalidStateTransition)
+        );
+
+        // Transfers between implicitly owned outputs go through as before
+        let mut ctx = TokenCtx::with_utxos(&[([0u8; 32], 1000)])
+            .with_outputs(&[([0u8; 32], 1000)])
+            .mutate(implicit)
+            .build();
+        let transfer = TokenAction::Transfer { from: [0u8; 32], to: [0u8; 32], amount: ZkUsd(1000), memo: None };
+        assert_eq!(validate(&mut ctx, &transfer), Ok(()));
+    }
+
+    #[test]
+    fn test_zero_address_outputs_rejected_in_every_action() {
+        let alice = [1u8; 32];
+        let zero_recipient = Err(ZkUsdError::InvalidAddress { reason: "zero-address recipient" });
+
+        // A transfer to the zero address is rejected even when conserved
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000)]).with_outputs(&[([0u8; 32], 600), (alice, 400)]).build();
+        let transfer = TokenAction::Transfer { from: alice, to: [0u8; 32], amount: ZkUsd(600), memo: None };
+        assert_eq!(validate(&mut ctx, &transfer), zero_recipient);
+
+        // So is a change output to it
+        let bob = [2u8; 32];
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000)]).with_outputs(&[(bob, 600), ([0u8; 32], 400)]).build();
+        let transfer = TokenAction::Transfer { from: alice, to: bob, amount: ZkUsd(600), memo: None };
+        assert_eq!(validate(&mut ctx, &transfer), zero_recipient);
+
+        // A multi-mint cannot route a share there
+        let mut ctx = mint_multi_context(&[(bob, ZkUsd(1000))]);
+        ctx.outputs = vec![TokenBalance::new(bob, 600), TokenBalance::new([0u8; 32], 400)];
+        assert_eq!(validate(&mut ctx, &TokenAction::MintMulti { recipients: vec![(bob, ZkUsd(1000))] }), zero_recipient);
+
+        // Destroying tokens takes an explicit burn, which lowers the supply
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000)])
+            .with_outputs(&[(alice, 400)])
+            .called_by_minter()
+            .mutate(|ctx| ctx.new_token_state.total_supply = 400)
+            .build();
+        assert_eq!(validate(&mut ctx, &TokenAction::Burn { from: alice, amount: ZkUsd(600) }), Ok(()));
+    }
+
+    #[test]
+    fn test_mint_cannot_enable_ownerless_bypass() {
+        let mut ctx = TokenCtx::new().build();
+        let user = [2u8; 32];
+
+        ctx.caller_app_id = Some([1u8; 32]);
+        ctx.new_token_state.total_supply = 1000;
+        ctx.new_token_state.implicit_ownership = true;
+        ctx.outputs.push(TokenBalance::new(user, 1000));
+
+        let result = validate(&mut ctx, &TokenAction::Mint { to: user, amount: ZkUsd(1000) });
+        assert_eq!(result, Err(ZkUsdError::InvalidStateTransition));
+    }
 
-        ctx.inputs.push(TokenBalance::new(user, 5000));
-        ctx.outputs.push(TokenBalance::new(user, 4000));
+    fn mint_multi_context(recipients: &[(Address, ZkUsd)]) -> TokenContext {
+        let mut ctx = TokenCtx::new().build();
+        let total: u64 = recipients.iter().map(|(_, amount)| amount.into_inner()).sum();
+
+        ctx.caller_app_id = Some([1u8; 32]);
+        ctx.token_state.total_supply = 5000;
+        ctx.new_token_state.total_supply = 5000 + total;
+        for (to, amount) in recipients {
+            ctx.outputs.push(TokenBalance::new(*to, amount.into_inner()));
+        }
+        ctx
+    }
+
+    #[test]
+    fn test_mint_multi_success() {
+        let recipients = vec![([2u8; 32], ZkUsd(600)), ([3u8; 32], ZkUsd(300)), ([4u8; 32], ZkUsd(100))];
+        let mut ctx = mint_multi_context(&recipients);
+        ctx.minter_amount = Some(1000);
+
+        let result = validate(&mut ctx, &TokenAction::MintMulti { recipients });
+        assert!(result.is_ok(), "Multi-mint should succeed: {:?}", result);
+        // One mint per recipient plus the state commitment
+        assert_eq!(ctx.events.len(), 4);
+    }
+
+    #[test]
+    fn test_mint_multi_total_must_match_minter() {
+        let recipients = vec![([2u8; 32], ZkUsd(600)), ([3u8; 32], ZkUsd(400))];
+        let mut ctx = mint_multi_context(&recipients);
+        ctx.minter_amount = Some(900);
+
+        let result = validate(&mut ctx, &TokenAction::MintMulti { recipients });
+        assert!(matches!(result, Err(ZkUsdError::InvalidInput { param: "amount", .. })));
+    }
+
+    #[test]
+    fn test_mint_multi_recipient_shortchanged() {
+        let recipients = vec![([2u8; 32], ZkUsd(600)), ([3u8; 32], ZkUsd(400))];
+        let mut ctx = mint_multi_context(&recipients);
+        // Conserved overall, but the second recipient's share went to the first
+        ctx.outputs = vec![TokenBalance::new([2u8; 32], 900), TokenBalance::new([3u8; 32], 100)];
+
+        let result = validate(&mut ctx, &TokenAction::MintMulti { recipients });
+        assert!(matches!(result, Err(ZkUsdError::InvalidAmount { .. })));
+    }
+
+    #[test]
+    fn test_mint_multi_conservation_and_supply() {
+        let recipients = vec![([2u8; 32], ZkUsd(600)), ([3u8; 32], ZkUsd(400))];
+
+        let mut ctx = mint_multi_context(&recipients);
+        ctx.outputs.push(TokenBalance::new([9u8; 32], 50));
+        let result = validate(&mut ctx, &TokenAction::MintMulti { recipients: recipients.clone() });
+        assert!(matches!(result, Err(ZkUsdError::ConservationViolated { .. })));
+
+        let mut ctx = mint_multi_context(&recipients);
+        ctx.new_token_state.total_supply += 1;
+        let result = validate(&mut ctx, &TokenAction::MintMulti { recipients });
+        assert_eq!(result, Err(ZkUsdError::InvalidStateTransition));
+    }
+
+    #[test]
+    fn test_mint_multi_rejects_bad_recipients() {
+        let cases = [
+            (vec![], "empty"),
+            (vec![([2u8; 32], ZkUsd(600)), ([0u8; 32], ZkUsd(400))], "zero address"),
+            (vec![([2u8; 32], ZkUsd(600)), ([3u8; 32], ZkUsd(0))], "zero amount"),
+            (vec![([2u8; 32], ZkUsd(600)), ([2u8; 32], ZkUsd(400))], "duplicate"),
+            ((2..=18u8).map(|i| ([i; 32], ZkUsd(10))).collect(), "too many"),
+        ];
+
+        for (recipients, case) in cases {
+            let mut ctx = mint_multi_context(&recipients);
+            let result = validate(&mut ctx, &TokenAction::MintMulti { recipients });
+            assert!(result.is_err(), "{} should be rejected", case);
+        }
+
+        // Exactly the maximum is accepted
+        let recipients: Vec<_> = (2..18u8).map(|i| ([i; 32], ZkUsd(10))).collect();
+        assert_eq!(recipients.len(), token::MAX_MINT_RECIPIENTS);
+        let mut ctx = mint_multi_context(&recipients);
+        assert!(validate(&mut ctx, &TokenAction::MintMulti { recipients }).is_ok());
+    }
+
+    #[test]
+    fn test_burn_success() {
+        let user = [2u8; 32];
+        let mut ctx = TokenCtx::with_utxos(&[(user, 5000)])
+            .with_outputs(&[(user, 4000)])
+            .called_by_minter()
+            .mutate(|ctx| {
+                ctx.token_state.total_supply = 10000;
+                ctx.new_token_state.total_supply = 9000;
+            })
+            .build();
 
         let action = TokenAction::Burn {
             from: user,
-            amount: 1000,
+            amount: ZkUsd(1000),
         };
 
         let result = validate(&mut ctx, &action);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_supply_reconciled_with_token_flow() {
+        let user = [2u8; 32];
+        let mut ctx = TokenCtx::new().build();
+        ctx.caller_app_id = Some([1u8; 32]);
+        ctx.token_state.total_supply = 5000;
+
+        // Supply claims 1000 minted but only 900 tokens are created
+        ctx.new_token_state.total_supply = 6000;
+        ctx.outputs.push(TokenBalance::new(user, 900));
+        assert_eq!(
+            reconcile_supply(&ctx),
+            Err(ZkUsdError::SupplyFlowMismatch { old_supply: 5000, new_supply: 6000, inputs: 0, outputs: 900 })
+        );
+        for amount in [900, 1000] {
+            let result = validate(&mut ctx.clone(), &TokenAction::Mint { to: user, amount: ZkUsd(amount) });
+            assert!(result.is_err(), "mint of {} should be rejected", amount);
+        }
+
+        // The symmetric burn: supply drops by 1000 while only 900 leave
+        ctx.new_token_state.total_supply = 4000;
+        ctx.inputs.push(TokenBalance::new(user, 1900));
+        ctx.outputs = vec![TokenBalance::new(user, 1000)];
+        assert!(matches!(reconcile_supply(&ctx), Err(ZkUsdError::SupplyFlowMismatch { .. })));
+
+        // Matching deltas reconcile either way
+        ctx.new_token_state.total_supply = 4100;
+        assert_eq!(reconcile_supply(&ctx), Ok(()));
+        assert!(validate(&mut ctx, &TokenAction::Burn { from: user, amount: ZkUsd(900) }).is_ok());
+    }
+
     #[test]
     fn test_conservation_violation() {
-        let mut ctx = create_test_context();
         let alice = [1u8; 32];
         let bob = [2u8; 32];
-
-        ctx.signer = alice;
-        ctx.inputs.push(TokenBalance::new(alice, 1000));
-        ctx.outputs.push(TokenBalance::new(bob, 1500)); // More than input!
+        let mut ctx = TokenCtx::with_utxos(&[(alice, 1000)])
+            .with_outputs(&[(bob, 1500)]) // More than input!
+            .build();
 
         let action = TokenAction::Transfer {
             from: alice,
             to: bob,
-            amount: 1500,
+            amount: ZkUsd(1500),
+            memo: None,
         };
 
         let result = validate(&mut ctx, &action);
         // Should fail due to insufficient balance or conservation
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_balance_sums_overflow_rejected() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+
+        // Input balances summing past u64::MAX
+        let mut ctx = TokenCtx::new().build();
+        ctx.signer = alice;
+        ctx.inputs.push(TokenBalance::new(alice, u64::MAX));
+        ctx.inputs.push(TokenBalance::new(alice, 1));
+        ctx.outputs.push(TokenBalance::new(bob, 1));
+        let action = TokenAction::Transfer { from: alice, to: bob, amount: ZkUsd(1), memo: None };
+        assert!(matches!(validate(&mut ctx, &action), Err(ZkUsdError::Overflow)));
+
+        // Minting on top of a maxed-out input balance
+        let vault_manager = [1u8; 32];
+        let mut ctx = TokenCtx::new().build();
+        ctx.caller_app_id = Some(vault_manager);
+        ctx.new_token_state.total_supply = 1;
+        ctx.inputs.push(TokenBalance::new(bob, u64::MAX));
+        ctx.outputs.push(TokenBalance::new(bob, u64::MAX));
+        let action = TokenAction::Mint { to: bob, amount: ZkUsd(1) };
+        assert!(validate(&mut ctx, &action).is_err());
+    }
+
+    fn hex(bytes: &[u8; 32]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_state_commitment_golden() {
+        // Consensus-relevant: changing this requires a COMMITMENT_VERSION bump
+        let ctx = TokenCtx::new().build();
+        assert_eq!(
+            hex(&ctx.token_state.commitment()),
+            "20ffb8988bc2f0b79a08ea8f9a5293eb824308ea07fb3e3c7a7eddadbdd2c705"
+        );
+    }
+
+    // ============ Conformance Vector Tests ============
+
+    /// Token context for a conformance vector: the controller's supply
+    /// covers the inputs and follows the spell's token flow
+    fn vector_context(v: &zkusd_common::conformance::VectorContext) -> TokenContext {
+        let balances = |entries: &[zkusd_common::conformance::VectorBalance]| -> Vec<TokenBalance> {
+            entries.iter().map(|b| TokenBalance::new(b.owner, b.amount)).collect()
+        };
+        let mut token_state = ZkUsdTokenState::with_minter(v.admin, v.authorized_minter);
+        token_state.total_supply = v.zkusd_inputs();
+        let mut new_token_state = token_state.clone();
+        new_token_state.total_supply = v.zkusd_outputs();
+
+        TokenContext {
+            inputs: balances(&v.token_inputs),
+            outputs: balances(&v.token_outputs),
+            token_state,
+            new_token_state,
+            caller_app_id: v.caller_app_id,
+            minter_amount: None,
+            signer: v.signer,
+            block_height: v.block_height,
+            events: EventLog::new(),
+        }
+    }
+
+    #[test]
+    fn test_conformance_vectors() {
+        use zkusd_common::conformance::{decode_vector, vectors_for, ConformanceContract};
+
+        for vector in vectors_for(ConformanceContract::ZkusdToken) {
+            let (action, v) = decode_vector::<TokenAction>(&vector).unwrap();
+            let result = validate(&mut vector_context(&v), &action);
+            assert!(
+                vector.expected.matches(&result),
+                "vector {} expected {:?}, got {:?}",
+                vector.name,
+                vector.expected,
+                result
+            );
+        }
+    }
 }
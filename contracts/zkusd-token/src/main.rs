@@ -3,7 +3,7 @@
 //! This binary is the entry point for the zkUSD Token when running
 //! as a Charms application on Bitcoin.
 
-use charms_sdk::data::{App, Data, Transaction};
+use zkusd_charms_compat::{App, Data, Transaction};
 
 /// Main validation function for zkUSD Token operations.
 ///
@@ -24,4 +24,4 @@ pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
 }
 
 // Use the Charms SDK main macro to generate the entry point
-charms_sdk::main!(app_contract);
+zkusd_charms_compat::main!(app_contract);
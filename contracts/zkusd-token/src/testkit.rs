@@ -0,0 +1,99 @@
+//! Test Kit
+//!
+//! Fluent factories for `TokenContext`:
+//!
+//! ```ignore
+//! let ctx = TokenCtx::with_utxos(&[(alice, 1000), (bob, 500)]).with_outputs(&[(carol, 1500)]).build();
+//! ```
+
+use std::vec::Vec;
+
+use zkusd_common::{
+    events::EventLog,
+    types::{Address, AppId},
+};
+
+use crate::{TokenBalance, TokenContext, ZkUsdTokenState};
+
+/// Admin of the token state
+pub const ADMIN: Address = [0u8; 32];
+/// Authorized minter (the Vault Manager app_id)
+pub const MINTER: AppId = [1u8; 32];
+/// Block the context executes at
+pub const BLOCK: u64 = 100;
+
+/// Change applied to a built context
+type Mutation = Box<dyn FnOnce(&mut TokenContext)>;
+
+/// Builder for a `TokenContext`
+pub struct TokenCtx {
+    inputs: Vec<TokenBalance>,
+    outputs: Vec<TokenBalance>,
+    signer: Option<Address>,
+    caller: Option<AppId>,
+    mutations: Vec<Mutation>,
+}
+
+impl TokenCtx {
+    /// No balances, no calling app and a zero signer
+    pub fn new() -> Self {
+        Self { inputs: Vec::new(), outputs: Vec::new(), signer: None, caller: None, mutations: Vec::new() }
+    }
+
+    /// Spend a balance per `(owner, amount)`, signed by the first owner;
+    /// the supply covers exactly these balances
+    pub fn with_utxos(utxos: &[(Address, u64)]) -> Self {
+        Self { inputs: balances(utxos), signer: utxos.first().map(|&(owner, _)| owner), ..Self::new() }
+    }
+
+    /// Create a balance per `(owner, amount)`
+    pub fn with_outputs(mut self, outputs: &[(Address, u64)]) -> Self {
+        self.outputs.extend(balances(outputs));
+        self
+    }
+
+    /// Sign the spell as `signer`
+    pub fn signed_by(mut self, signer: Address) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Run the spell as called by the authorized minter
+    pub fn called_by_minter(mut self) -> Self {
+        self.caller = Some(MINTER);
+        self
+    }
+
+    /// Change the built context; applied in order after everything else
+    pub fn mutate(mut self, mutation: impl FnOnce(&mut TokenContext) + 'static) -> Self {
+        self.mutations.push(Box::new(mutation));
+        self
+    }
+
+    /// Build the context, with the token state before the spell also its
+    /// output
+    pub fn build(self) -> TokenContext {
+        let mut token_state = ZkUsdTokenState::with_minter(ADMIN, MINTER);
+        token_state.total_supply = self.inputs.iter().map(|balance| balance.amount).sum();
+
+        let mut ctx = TokenContext {
+            inputs: self.inputs,
+            outputs: self.outputs,
+            token_state: token_state.clone(),
+            new_token_state: token_state,
+            caller_app_id: self.caller,
+            minter_amount: None,
+            signer: self.signer.unwrap_or([0u8; 32]),
+            block_height: BLOCK,
+            events: EventLog::new(),
+        };
+        for mutation in self.mutations {
+            mutation(&mut ctx);
+        }
+        ctx
+    }
+}
+
+fn balances(entries: &[(Address, u64)]) -> Vec<TokenBalance> {
+    entries.iter().map(|&(owner, amount)| TokenBalance::new(owner, amount)).collect()
+}
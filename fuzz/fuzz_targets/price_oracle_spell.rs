@@ -0,0 +1,12 @@
+//! Price Oracle Charms entry point: witness decoding through validation
+
+#![no_main]
+
+use charms_data::Data;
+use libfuzzer_sys::fuzz_target;
+use zkusd_fuzz::{FuzzSpell, PRICE_ORACLE};
+use zkusd_price_oracle::charms::{validate_oracle_operation, OracleWitness};
+
+fuzz_target!(|spell: FuzzSpell<OracleWitness>| {
+    validate_oracle_operation(&PRICE_ORACLE, &spell.transaction(), &Data::empty(), &spell.witness());
+});
@@ -0,0 +1,12 @@
+//! Price Oracle validation from an arbitrary context and action
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zkusd_common::types::OracleAction;
+use zkusd_price_oracle::{validate, OracleContext};
+
+fuzz_target!(|input: (OracleContext, OracleAction)| {
+    let (mut ctx, action) = input;
+    let _ = validate(&mut ctx, &action);
+});
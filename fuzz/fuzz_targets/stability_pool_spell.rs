@@ -0,0 +1,12 @@
+//! Stability Pool Charms entry point: witness decoding through validation
+
+#![no_main]
+
+use charms_data::Data;
+use libfuzzer_sys::fuzz_target;
+use zkusd_fuzz::{FuzzSpell, STABILITY_POOL};
+use zkusd_stability_pool::charms::{validate_stability_operation, StabilityWitness};
+
+fuzz_target!(|spell: FuzzSpell<StabilityWitness>| {
+    validate_stability_operation(&STABILITY_POOL, &spell.transaction(), &Data::empty(), &spell.witness());
+});
@@ -0,0 +1,12 @@
+//! Stability Pool validation from an arbitrary context and action
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zkusd_common::types::StabilityPoolAction;
+use zkusd_stability_pool::{validate, StabilityPoolContext};
+
+fuzz_target!(|input: (StabilityPoolContext, StabilityPoolAction)| {
+    let (mut ctx, action) = input;
+    let _ = validate(&mut ctx, &action);
+});
@@ -0,0 +1,12 @@
+//! VaultManager Charms entry point: witness decoding through validation
+
+#![no_main]
+
+use charms_data::Data;
+use libfuzzer_sys::fuzz_target;
+use zkusd_fuzz::{FuzzSpell, VAULT_MANAGER};
+use zkusd_vault_manager::charms::{validate_vault_operation, VaultWitness};
+
+fuzz_target!(|spell: FuzzSpell<VaultWitness>| {
+    validate_vault_operation(&VAULT_MANAGER, &spell.transaction(), &Data::empty(), &spell.witness());
+});
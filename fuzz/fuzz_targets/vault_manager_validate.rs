@@ -0,0 +1,12 @@
+//! VaultManager validation from an arbitrary context and action
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zkusd_common::types::VaultAction;
+use zkusd_vault_manager::{validate, VaultContext};
+
+fuzz_target!(|input: (VaultContext, VaultAction)| {
+    let (mut ctx, action) = input;
+    let _ = validate(&mut ctx, &action);
+});
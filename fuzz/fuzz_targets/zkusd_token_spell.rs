@@ -0,0 +1,15 @@
+//! zkUSD token Charms entry point: witness decoding through validation,
+//! for both the controller state NFT and the fungible token
+
+#![no_main]
+
+use charms_data::Data;
+use libfuzzer_sys::fuzz_target;
+use zkusd_fuzz::{FuzzSpell, TOKEN, TOKEN_STATE};
+use zkusd_token::charms::{validate_token_operation, TokenWitness};
+
+fuzz_target!(|input: (bool, FuzzSpell<TokenWitness>)| {
+    let (fungible, spell) = input;
+    let app = if fungible { TOKEN } else { TOKEN_STATE };
+    validate_token_operation(&app, &spell.transaction(), &Data::empty(), &spell.witness());
+});
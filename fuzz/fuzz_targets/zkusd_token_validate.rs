@@ -0,0 +1,12 @@
+//! zkUSD token validation from an arbitrary context and action
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zkusd_common::types::TokenAction;
+use zkusd_token::{validate, TokenContext};
+
+fuzz_target!(|input: (TokenContext, TokenAction)| {
+    let (mut ctx, action) = input;
+    let _ = validate(&mut ctx, &action);
+});
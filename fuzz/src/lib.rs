@@ -0,0 +1,182 @@
+//! zkUSD Fuzzing Support
+//!
+//! Shared pieces of the cargo-fuzz targets in `fuzz_targets/`:
+//!
+//! - **Allocation cap**: a global allocator that aborts (a crash to
+//!   libFuzzer) on any single allocation above `MAX_ALLOCATION_BYTES` or
+//!   once live memory exceeds `MAX_LIVE_BYTES`. The ZK guest has little
+//!   memory, so an input that makes a contract allocate without bound is
+//!   a prover DoS even if it never panics.
+//! - **Spells**: `FuzzSpell` builds a Charms `Transaction` from arbitrary
+//!   charms placed on a fixed set of apps, so the `validate_*_operation`
+//!   entry points see both well-typed states and raw CBOR.
+//!
+//! Every target asserts the same property: the code under test returns
+//! (accept or reject) without panicking.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use arbitrary::Arbitrary;
+use charms_data::{App, Charms, Data, Transaction, TxId, UtxoId, B32};
+use zkusd_common::types::{StabilityDeposit, StabilityPoolState, Vault};
+use zkusd_common::vault_registry::VaultRegistry;
+use zkusd_price_oracle::OracleState;
+use zkusd_stability_pool::StabilityPoolConfig;
+use zkusd_token::ZkUsdTokenState;
+use zkusd_vault_manager::VaultManagerState;
+
+// ============ Allocation Cap ============
+
+/// Largest single allocation a contract may make (1 MiB)
+pub const MAX_ALLOCATION_BYTES: usize = 1 << 20;
+
+/// Most memory a contract may hold at once (16 MiB)
+pub const MAX_LIVE_BYTES: usize = 16 << 20;
+
+/// System allocator enforcing the caps above
+pub struct CappedAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CappedAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        if layout.size() > MAX_ALLOCATION_BYTES || live > MAX_LIVE_BYTES {
+            // Panicking would allocate; abort is reported as a crash
+            std::process::abort();
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CappedAllocator = CappedAllocator;
+
+// ============ Apps ============
+
+/// VaultManager protocol state and vault charms
+pub const VAULT_MANAGER: App = app('n', 1);
+/// Stability Pool state and deposit charms
+pub const STABILITY_POOL: App = app('n', 2);
+/// Price Oracle state charm
+pub const PRICE_ORACLE: App = app('n', 3);
+/// zkUSD token controller state charm
+pub const TOKEN_STATE: App = app('n', 4);
+/// zkUSD fungible token
+pub const TOKEN: App = app('t', 4);
+
+const APPS: [App; 5] = [VAULT_MANAGER, STABILITY_POOL, PRICE_ORACLE, TOKEN_STATE, TOKEN];
+
+/// App with identity and VK both `[id; 32]`, so states referencing apps
+/// by id can name them with a single repeated byte
+const fn app(tag: char, id: u8) -> App {
+    App { tag, identity: B32([id; 32]), vk: B32([id; 32]) }
+}
+
+// ============ Spells ============
+
+/// CBOR data, either typed or raw
+#[derive(Debug, Arbitrary)]
+pub enum FuzzData<T> {
+    /// Serialized from a well-typed value
+    Typed(T),
+    /// Arbitrary bytes, kept only if they decode as CBOR
+    Raw(Vec<u8>),
+}
+
+impl<T: serde::Serialize> FuzzData<T> {
+    /// Charms `Data` carrying this value (`None` for non-CBOR bytes)
+    pub fn to_data(&self) -> Option<Data> {
+        match self {
+            Self::Typed(value) => Some(Data::from(value)),
+            Self::Raw(bytes) => {
+                let value: ciborium::Value = ciborium::de::from_reader(bytes.as_slice()).ok()?;
+                Some(Data::from(&value))
+            }
+        }
+    }
+}
+
+/// Data of one charm, typed as any of the protocol's charm payloads
+#[derive(Debug, Arbitrary, serde::Serialize)]
+#[serde(untagged)]
+pub enum CharmValue {
+    Amount(u64),
+    VaultManager(Box<VaultManagerState>),
+    Vault(Vault),
+    Registry(VaultRegistry),
+    StabilityPool(StabilityPoolState),
+    StabilityPoolConfig(StabilityPoolConfig),
+    Deposit(StabilityDeposit),
+    Oracle(OracleState),
+    Token(ZkUsdTokenState),
+}
+
+/// One charm: data on one of the fixed apps
+#[derive(Debug, Arbitrary)]
+pub struct FuzzCharm {
+    app: u8,
+    data: FuzzData<CharmValue>,
+}
+
+/// A UTXO's charms
+#[derive(Debug, Arbitrary)]
+pub struct FuzzUtxo(Vec<FuzzCharm>);
+
+impl FuzzUtxo {
+    fn charms(&self) -> Charms {
+        self.0
+            .iter()
+            .filter_map(|charm| {
+                let app = APPS[usize::from(charm.app) % APPS.len()].clone();
+                Some((app, charm.data.to_data()?))
+            })
+            .collect()
+    }
+}
+
+/// A spell: a transaction plus the witness of the app under test
+///
+/// Native coin flows are left empty, as Charms v0.11.1 does.
+#[derive(Debug, Arbitrary)]
+pub struct FuzzSpell<W> {
+    ins: Vec<FuzzUtxo>,
+    refs: Vec<FuzzUtxo>,
+    outs: Vec<FuzzUtxo>,
+    witness: FuzzData<W>,
+}
+
+impl<W: serde::Serialize> FuzzSpell<W> {
+    /// The spell's transaction
+    pub fn transaction(&self) -> Transaction {
+        let spent = |utxos: &[FuzzUtxo]| {
+            utxos
+                .iter()
+                .enumerate()
+                .map(|(i, utxo)| (UtxoId(TxId([i as u8; 32]), 0), utxo.charms()))
+                .collect()
+        };
+
+        Transaction {
+            ins: spent(&self.ins),
+            refs: spent(&self.refs),
+            outs: self.outs.iter().map(FuzzUtxo::charms).collect(),
+            coin_ins: None,
+            coin_outs: None,
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        }
+    }
+
+    /// The witness (empty for non-CBOR bytes)
+    pub fn witness(&self) -> Data {
+        self.witness.to_data().unwrap_or_else(Data::empty)
+    }
+}